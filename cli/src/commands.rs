@@ -1,93 +1,189 @@
 //! CLI Commands
 
-use crate::wallet::{Wallet, WalletManager};
-use rainsonet_core::{Address, Amount, Nonce};
-use rainsonet_relyo::VerifiedTransaction;
-use reqwest::Client;
+use crate::wallet::{Wallet, WalletInfo};
+use futures::stream::{self, StreamExt};
+use rainsonet_core::{amount_as_string, Address, Amount, Nonce};
+use reqwest::{Client, RequestBuilder, Response, StatusCode};
+use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
+use std::future::Future;
 use std::time::Duration;
 
+/// Default per-request timeout
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Default number of retries for idempotent requests
+pub const DEFAULT_RETRIES: u32 = 3;
+
+/// Delay before the first retry, doubled on each further attempt
+const INITIAL_BACKOFF: Duration = Duration::from_millis(250);
+
 /// API Client for interacting with RAINSONET node
+///
+/// Idempotent GET requests are retried with exponential backoff when the
+/// node cannot be reached or reports itself unavailable. Transaction
+/// submission is attempted exactly once, so a request that reached the node
+/// is never submitted twice.
 pub struct ApiClient {
     base_url: String,
     client: Client,
+    retries: u32,
+    backoff: Duration,
+    admin_token: Option<String>,
 }
 
 impl ApiClient {
     pub fn new(base_url: &str) -> Self {
         Self {
             base_url: base_url.trim_end_matches('/').to_string(),
-            client: Client::builder()
-                .timeout(Duration::from_secs(30))
-                .build()
-                .expect("Failed to create HTTP client"),
+            client: build_client(DEFAULT_TIMEOUT),
+            retries: DEFAULT_RETRIES,
+            backoff: INITIAL_BACKOFF,
+            admin_token: None,
         }
     }
     
+    /// Set the per-request timeout
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.client = build_client(timeout);
+        self
+    }
+    
+    /// Set how many times idempotent requests are retried
+    pub fn with_retries(mut self, retries: u32) -> Self {
+        self.retries = retries;
+        self
+    }
+    
+    /// Set the delay before the first retry
+    pub fn with_backoff(mut self, backoff: Duration) -> Self {
+        self.backoff = backoff;
+        self
+    }
+    
+    /// Authenticate admin requests with the node's `api.admin_token`
+    pub fn with_admin_token(mut self, token: impl Into<String>) -> Self {
+        self.admin_token = Some(token.into());
+        self
+    }
+    
     /// Get node status
     pub async fn status(&self) -> Result<NodeStatus, ApiError> {
-        let url = format!("{}/status", self.base_url);
-        let resp: ApiResponse<NodeStatus> = self.client.get(&url).send().await?.json().await?;
-        
-        if resp.success {
-            resp.data.ok_or(ApiError::EmptyResponse)
-        } else {
-            Err(ApiError::Server(resp.error.unwrap_or_default()))
-        }
+        self.get(&format!("{}/status", self.base_url)).await
     }
     
     /// Get account info
     pub async fn get_account(&self, address: &str) -> Result<AccountInfo, ApiError> {
-        let url = format!("{}/account/{}", self.base_url, address);
-        let resp: ApiResponse<AccountInfo> = self.client.get(&url).send().await?.json().await?;
-        
-        if resp.success {
-            resp.data.ok_or(ApiError::EmptyResponse)
-        } else {
-            Err(ApiError::Server(resp.error.unwrap_or_default()))
-        }
+        self.get(&format!("{}/account/{}", self.base_url, address)).await
+    }
+    
+    /// Get account info including changes that are not finalized yet, as
+    /// needed to pick the next nonce
+    pub async fn get_optimistic_account(&self, address: &str) -> Result<AccountInfo, ApiError> {
+        self.get(&format!("{}/account/{}?view=optimistic", self.base_url, address)).await
     }
     
     /// Get balance
     pub async fn get_balance(&self, address: &str) -> Result<BalanceInfo, ApiError> {
-        let url = format!("{}/balance/{}", self.base_url, address);
-        let resp: ApiResponse<BalanceInfo> = self.client.get(&url).send().await?.json().await?;
-        
-        if resp.success {
-            resp.data.ok_or(ApiError::EmptyResponse)
-        } else {
-            Err(ApiError::Server(resp.error.unwrap_or_default()))
-        }
+        self.get(&format!("{}/balance/{}", self.base_url, address)).await
     }
     
     /// Submit transaction
+    ///
+    /// Never retried: a failed attempt may still have reached the node.
     pub async fn submit_transaction(&self, tx: &TransactionRequest) -> Result<TransactionResponse, ApiError> {
         let url = format!("{}/transaction", self.base_url);
-        let resp: ApiResponse<TransactionResponse> = self.client
-            .post(&url)
-            .json(tx)
-            .send()
-            .await?
-            .json()
-            .await?;
-        
-        if resp.success {
-            resp.data.ok_or(ApiError::EmptyResponse)
-        } else {
-            Err(ApiError::Server(resp.error.unwrap_or_default()))
-        }
+        let resp = send(self.client.post(&url).json(tx)).await?;
+        parse_response(resp).await
+    }
+    
+    /// Submit a transaction signed offline, as hex of its serialized bytes
+    pub async fn submit_raw_transaction(&self, tx_hex: &str) -> Result<TransactionResponse, ApiError> {
+        let url = format!("{}/transaction/raw", self.base_url);
+        let body = serde_json::json!({ "tx": tx_hex });
+        let resp = send(self.client.post(&url).json(&body)).await?;
+        parse_response(resp).await
+    }
+    
+    /// Ask the node to dial a peer
+    pub async fn dial_peer(&self, multiaddr: &str) -> Result<PeerCommandResponse, ApiError> {
+        let url = format!("{}/admin/peers/dial", self.base_url);
+        let body = serde_json::json!({ "multiaddr": multiaddr });
+        let resp = send(self.admin(self.client.post(&url)).json(&body)).await?;
+        parse_response(resp).await
+    }
+    
+    /// Ask the node to disconnect a peer
+    pub async fn disconnect_peer(&self, node_id: &str) -> Result<PeerCommandResponse, ApiError> {
+        let url = format!("{}/admin/peers/disconnect", self.base_url);
+        let body = serde_json::json!({ "node_id": node_id });
+        let resp = send(self.admin(self.client.post(&url)).json(&body)).await?;
+        parse_response(resp).await
+    }
+    
+    /// Get the fee and amount limits the node validates transactions against
+    pub async fn policy(&self) -> Result<TransactionPolicy, ApiError> {
+        self.get(&format!("{}/policy", self.base_url)).await
     }
     
     /// Get transaction status
     pub async fn get_transaction(&self, tx_id: &str) -> Result<TransactionResponse, ApiError> {
-        let url = format!("{}/transaction/{}", self.base_url, tx_id);
-        let resp: ApiResponse<TransactionResponse> = self.client.get(&url).send().await?.json().await?;
+        self.get(&format!("{}/transaction/{}", self.base_url, tx_id)).await
+    }
+    
+    /// Attach the admin token, if set, to `request`
+    fn admin(&self, request: RequestBuilder) -> RequestBuilder {
+        match &self.admin_token {
+            Some(token) => request.bearer_auth(token),
+            None => request,
+        }
+    }
+    
+    /// GET `url`, retrying transient failures with exponential backoff
+    async fn get<T: DeserializeOwned>(&self, url: &str) -> Result<T, ApiError> {
+        let mut backoff = self.backoff;
+        let mut attempt = 0;
         
-        if resp.success {
-            resp.data.ok_or(ApiError::EmptyResponse)
-        } else {
-            Err(ApiError::Server(resp.error.unwrap_or_default()))
+        loop {
+            match send(self.client.get(url)).await {
+                Ok(resp) => return parse_response(resp).await,
+                Err(e) if e.is_transient() && attempt < self.retries => {
+                    attempt += 1;
+                    tokio::time::sleep(backoff).await;
+                    backoff *= 2;
+                }
+                Err(e) => return Err(e),
+            }
+        }
+    }
+}
+
+fn build_client(timeout: Duration) -> Client {
+    Client::builder()
+        .timeout(timeout)
+        .build()
+        .expect("Failed to create HTTP client")
+}
+
+/// Send a request, treating gateway and availability statuses as errors
+async fn send(request: RequestBuilder) -> Result<Response, ApiError> {
+    let resp = request.send().await?;
+    match resp.status() {
+        StatusCode::BAD_GATEWAY | StatusCode::SERVICE_UNAVAILABLE | StatusCode::GATEWAY_TIMEOUT => {
+            Err(ApiError::Unavailable(resp.status()))
         }
+        _ => Ok(resp),
+    }
+}
+
+/// Decode an API response envelope
+async fn parse_response<T: DeserializeOwned>(resp: Response) -> Result<T, ApiError> {
+    let resp: ApiResponse<T> = resp.json().await?;
+    
+    if resp.success {
+        resp.data.ok_or(ApiError::EmptyResponse)
+    } else {
+        Err(ApiError::Server(resp.error.unwrap_or_default()))
     }
 }
 
@@ -103,7 +199,8 @@ struct ApiResponse<T> {
 #[derive(Debug, Deserialize)]
 pub struct NodeStatus {
     pub node_id: String,
-    pub state_version: u64,
+    pub optimistic_version: u64,
+    pub finalized_version: u64,
     pub state_root: String,
     pub peer_count: usize,
     pub is_validator: bool,
@@ -114,7 +211,8 @@ pub struct NodeStatus {
 #[derive(Debug, Deserialize)]
 pub struct AccountInfo {
     pub address: String,
-    pub balance: String,
+    #[serde(with = "amount_as_string")]
+    pub balance: Amount,
     pub nonce: u64,
 }
 
@@ -122,20 +220,50 @@ pub struct AccountInfo {
 #[derive(Debug, Deserialize)]
 pub struct BalanceInfo {
     pub address: String,
-    pub balance: String,
+    #[serde(with = "amount_as_string")]
+    pub balance: Amount,
     pub balance_relyo: String,
 }
 
+/// Transaction policy, pinned by genesis
+#[derive(Debug, Deserialize)]
+pub struct TransactionPolicy {
+    #[serde(with = "amount_as_string")]
+    pub min_fee: Amount,
+    #[serde(with = "amount_as_string")]
+    pub max_tx_amount: Amount,
+}
+
+impl TransactionPolicy {
+    /// Reject a transfer the node would refuse, before it is signed
+    pub fn check(&self, amount: Amount, fee: Amount) -> Result<(), String> {
+        if amount > self.max_tx_amount {
+            return Err(format!(
+                "Amount {} exceeds the per-transaction limit {}",
+                amount, self.max_tx_amount
+            ));
+        }
+        if fee < self.min_fee {
+            return Err(format!("Fee {} is below the minimum {}", fee, self.min_fee));
+        }
+        Ok(())
+    }
+}
+
 /// Transaction request
 #[derive(Serialize)]
 pub struct TransactionRequest {
     pub from: String,
     pub to: String,
-    pub amount: String,
-    pub fee: String,
+    #[serde(with = "amount_as_string")]
+    pub amount: Amount,
+    #[serde(with = "amount_as_string")]
+    pub fee: Amount,
     pub nonce: u64,
     pub public_key: String,
     pub signature: String,
+    /// Signed timestamp, so the node verifies the exact bytes we signed
+    pub timestamp: u64,
 }
 
 /// Transaction response
@@ -143,16 +271,51 @@ pub struct TransactionRequest {
 pub struct TransactionResponse {
     pub tx_id: String,
     pub status: String,
+    #[serde(default)]
+    pub version: Option<u64>,
+    #[serde(default)]
+    pub confirmations: Option<u64>,
+    /// Whether the fee is likely to be selected soon, only on submission
+    #[serde(default)]
+    pub fee_adequate: Option<bool>,
+    #[serde(default)]
+    pub suggested_fee: Option<String>,
+    /// Why an accepted transaction may never finalize
+    #[serde(default)]
+    pub warning: Option<String>,
+}
+
+/// Peer command response
+#[derive(Debug, Deserialize)]
+pub struct PeerCommandResponse {
+    pub command: String,
+    pub target: String,
 }
 
 /// API Error
 #[derive(Debug)]
 pub enum ApiError {
     Http(reqwest::Error),
+    /// The node (or a proxy in front of it) is temporarily unavailable
+    Unavailable(StatusCode),
     Server(String),
     EmptyResponse,
 }
 
+impl ApiError {
+    /// Whether retrying the same request may succeed.
+    ///
+    /// Connection failures, timeouts and unavailability are transient;
+    /// errors reported by the node itself are not.
+    pub fn is_transient(&self) -> bool {
+        match self {
+            ApiError::Http(e) => e.is_connect() || e.is_timeout(),
+            ApiError::Unavailable(_) => true,
+            ApiError::Server(_) | ApiError::EmptyResponse => false,
+        }
+    }
+}
+
 impl From<reqwest::Error> for ApiError {
     fn from(err: reqwest::Error) -> Self {
         ApiError::Http(err)
@@ -163,6 +326,7 @@ impl std::fmt::Display for ApiError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             ApiError::Http(e) => write!(f, "HTTP error: {}", e),
+            ApiError::Unavailable(status) => write!(f, "Node unavailable: {}", status),
             ApiError::Server(e) => write!(f, "Server error: {}", e),
             ApiError::EmptyResponse => write!(f, "Empty response"),
         }
@@ -180,7 +344,7 @@ pub fn build_transaction_request(
     nonce: u64,
 ) -> Result<TransactionRequest, String> {
     let to_addr = Address::from_hex(to)
-        .map_err(|_| "Invalid recipient address")?;
+        .map_err(|e| format!("Invalid recipient address: {}", e))?;
     
     let tx = wallet
         .create_transaction(to_addr, amount, fee, Nonce::new(nonce))
@@ -189,10 +353,338 @@ pub fn build_transaction_request(
     Ok(TransactionRequest {
         from: tx.from.to_hex(),
         to: tx.to.to_hex(),
-        amount: tx.amount.0.to_string(),
-        fee: tx.fee.0.to_string(),
+        amount: tx.amount,
+        fee: tx.fee,
         nonce: tx.nonce.0,
         public_key: tx.public_key.to_hex(),
         signature: tx.signature.to_hex(),
+        timestamp: tx.timestamp.as_millis(),
     })
 }
+
+/// Amount that empties `account` once `fee` is paid, for `send --max`
+pub fn max_send_amount(account: &AccountInfo, fee: Amount) -> Result<Amount, String> {
+    let amount = Amount::send_max(account.balance, fee);
+    if amount == Amount::ZERO {
+        return Err(format!(
+            "Balance {} does not cover the fee {}",
+            account.balance, fee
+        ));
+    }
+    Ok(amount)
+}
+
+/// Most balance lookups in flight at once for `wallet list --with-balances`
+pub const MAX_CONCURRENT_BALANCE_QUERIES: usize = 8;
+
+/// Wallet row for `wallet list --with-balances`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WalletBalance {
+    pub name: String,
+    pub address: String,
+    /// `None` when the node could not be asked
+    pub balance: Option<Amount>,
+}
+
+/// Look up every wallet's balance with `fetch`, at most
+/// [`MAX_CONCURRENT_BALANCE_QUERIES`] at a time, keeping the wallet order.
+/// A failed lookup leaves that wallet's balance unknown rather than failing
+/// the whole list.
+pub async fn wallet_balances<F, Fut>(wallets: &[WalletInfo], fetch: F) -> Vec<WalletBalance>
+where
+    F: Fn(String) -> Fut,
+    Fut: Future<Output = Result<BalanceInfo, ApiError>>,
+{
+    stream::iter(wallets)
+        .map(|wallet| {
+            let lookup = fetch(wallet.address.clone());
+            async move {
+                WalletBalance {
+                    name: wallet.name.clone(),
+                    address: wallet.address.clone(),
+                    balance: lookup.await.ok().map(|info| info.balance),
+                }
+            }
+        })
+        .buffered(MAX_CONCURRENT_BALANCE_QUERIES)
+        .collect()
+        .await
+}
+
+/// Sign a transaction without contacting the node, returning the hex of
+/// its serialized bytes for `broadcast`
+pub fn build_raw_transaction(
+    wallet: &Wallet,
+    to: &str,
+    amount: Amount,
+    fee: Amount,
+    nonce: u64,
+) -> Result<String, String> {
+    let to_addr = Address::from_hex(to)
+        .map_err(|e| format!("Invalid recipient address: {}", e))?;
+    
+    let tx = wallet
+        .create_transaction(to_addr, amount, fee, Nonce::new(nonce))
+        .map_err(|e| e.to_string())?;
+    
+    Ok(hex::encode(tx.to_bytes()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+    
+    #[tokio::test]
+    async fn test_wallet_balances_rows() {
+        let address = |i: usize| format!("{:064x}", i);
+        let wallets: Vec<WalletInfo> = (0..20)
+            .map(|i| WalletInfo {
+                name: format!("wallet{}", i),
+                address: address(i),
+                path: std::path::PathBuf::new(),
+                encrypted: false,
+            })
+            .collect();
+        let in_flight = AtomicUsize::new(0);
+        let peak = AtomicUsize::new(0);
+        
+        let rows = wallet_balances(&wallets, |addr| {
+            let (in_flight, peak) = (&in_flight, &peak);
+            async move {
+                peak.fetch_max(in_flight.fetch_add(1, Ordering::SeqCst) + 1, Ordering::SeqCst);
+                tokio::time::sleep(Duration::from_millis(5)).await;
+                in_flight.fetch_sub(1, Ordering::SeqCst);
+                
+                // The node cannot be reached for one wallet
+                if addr == address(3) {
+                    return Err(ApiError::Unavailable(StatusCode::SERVICE_UNAVAILABLE));
+                }
+                let index = u128::from_str_radix(&addr, 16).unwrap();
+                Ok(BalanceInfo {
+                    address: addr,
+                    balance: Amount::new(index * 10),
+                    balance_relyo: String::new(),
+                })
+            }
+        })
+        .await;
+        
+        assert_eq!(rows.len(), 20);
+        assert_eq!(
+            rows[1],
+            WalletBalance {
+                name: "wallet1".into(),
+                address: address(1),
+                balance: Some(Amount::new(10)),
+            }
+        );
+        assert_eq!(rows[3].name, "wallet3");
+        assert_eq!(rows[3].balance, None);
+        assert_eq!(rows[19].balance, Some(Amount::new(190)));
+        
+        let peak = peak.load(Ordering::SeqCst);
+        assert!(peak > 1 && peak <= MAX_CONCURRENT_BALANCE_QUERIES, "peak {}", peak);
+    }
+    
+    /// HTTP server that answers 503 to the first `failures` requests and
+    /// with `body` afterwards, counting every request it sees
+    async fn flaky_server(failures: usize, body: &'static str) -> (String, Arc<AtomicUsize>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let requests = Arc::new(AtomicUsize::new(0));
+        let counter = requests.clone();
+        
+        tokio::spawn(async move {
+            loop {
+                let (mut socket, _) = listener.accept().await.unwrap();
+                read_request(&mut socket).await;
+                
+                let seen = counter.fetch_add(1, Ordering::SeqCst);
+                let (status, body) = if seen < failures {
+                    ("503 Service Unavailable", "")
+                } else {
+                    ("200 OK", body)
+                };
+                let response = format!(
+                    "HTTP/1.1 {}\r\ncontent-type: application/json\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{}",
+                    status,
+                    body.len(),
+                    body
+                );
+                let _ = socket.write_all(response.as_bytes()).await;
+            }
+        });
+        
+        (url, requests)
+    }
+    
+    /// Consume a whole request so closing the socket doesn't reset it
+    async fn read_request(socket: &mut tokio::net::TcpStream) {
+        let mut request = Vec::new();
+        let mut buf = [0u8; 4096];
+        loop {
+            let n = socket.read(&mut buf).await.unwrap_or(0);
+            if n == 0 {
+                return;
+            }
+            request.extend_from_slice(&buf[..n]);
+            
+            let text = String::from_utf8_lossy(&request);
+            if let Some(header_end) = text.find("\r\n\r\n") {
+                let content_length = text[..header_end]
+                    .lines()
+                    .find_map(|line| {
+                        let (name, value) = line.split_once(':')?;
+                        name.eq_ignore_ascii_case("content-length")
+                            .then(|| value.trim().parse::<usize>().ok())
+                            .flatten()
+                    })
+                    .unwrap_or(0);
+                if request.len() >= header_end + 4 + content_length {
+                    return;
+                }
+            }
+        }
+    }
+    
+    const STATUS_BODY: &str = r#"{"success":true,"data":{"node_id":"00","optimistic_version":1,"finalized_version":1,"state_root":"00","peer_count":0,"is_validator":true,"mempool_size":0}}"#;
+    const TX_BODY: &str = r#"{"success":true,"data":{"tx_id":"00","status":"pending"}}"#;
+    const POLICY_BODY: &str = r#"{"success":true,"data":{"min_fee":"10","max_tx_amount":"1000","fee_burn_percent":50,"tx_expiry_seconds":3600}}"#;
+    
+    fn client(url: &str, retries: u32) -> ApiClient {
+        ApiClient::new(url)
+            .with_retries(retries)
+            .with_backoff(Duration::from_millis(1))
+    }
+    
+    #[tokio::test]
+    async fn test_max_send_drains_balance() {
+        use rainsonet_core::RelyoConfig;
+        use rainsonet_relyo::RelyoTransactionValidator;
+        use rainsonet_state::{AccountState, MemoryStateStore};
+        
+        let wallet = Wallet::new("max");
+        let balance = Amount::from_relyo(10);
+        let account = AccountInfo {
+            address: wallet.address().to_hex(),
+            balance,
+            nonce: 0,
+        };
+        let config = RelyoConfig::default();
+        let fee = Amount::new(config.min_fee);
+        
+        let amount = max_send_amount(&account, fee).unwrap();
+        let tx = wallet
+            .create_transaction(Address::from_bytes([9u8; 32]), amount, fee, Nonce::new(0))
+            .unwrap();
+        assert_eq!(tx.total_cost(), balance);
+        
+        let state = MemoryStateStore::new();
+        state
+            .set_account(wallet.address().as_bytes(), &AccountState::new(balance.0, 0))
+            .unwrap();
+        RelyoTransactionValidator::new(config).validate(&tx, &state).await.unwrap();
+        
+        // Nothing left to send once the fee takes the whole balance
+        let drained = AccountInfo { balance: fee, ..account };
+        assert!(max_send_amount(&drained, fee).is_err());
+    }
+    
+    #[test]
+    fn test_transaction_request_carries_signed_timestamp() {
+        use rainsonet_core::{PublicKey, Signature, Timestamp};
+        use rainsonet_relyo::RelyoTransaction;
+        
+        let wallet = Wallet::new("signed");
+        let to = Address::from_bytes([9u8; 32]);
+        let request =
+            build_transaction_request(&wallet, &to.to_hex(), Amount::new(5), Amount::new(1), 0)
+                .unwrap();
+        
+        // The node rebuilds the transaction from the request as sent
+        let tx = RelyoTransaction {
+            from: Address::from_hex(&request.from).unwrap(),
+            to,
+            amount: request.amount,
+            fee: request.fee,
+            nonce: Nonce::new(request.nonce),
+            public_key: PublicKey::from_hex(&request.public_key).unwrap(),
+            signature: Signature::from_hex(&request.signature).unwrap(),
+            timestamp: Timestamp::from_millis(request.timestamp),
+        };
+        tx.verify_signature().unwrap();
+    }
+    
+    #[tokio::test]
+    async fn test_policy_rejects_over_limit_send() {
+        let (url, requests) = flaky_server(0, POLICY_BODY).await;
+        let policy = client(&url, 0).policy().await.unwrap();
+        assert_eq!(policy.max_tx_amount, Amount::new(1000));
+        
+        assert!(policy.check(Amount::new(1000), Amount::new(10)).is_ok());
+        let err = policy.check(Amount::new(1001), Amount::new(10)).unwrap_err();
+        assert!(err.contains("per-transaction limit"), "{}", err);
+        assert!(policy.check(Amount::new(1), Amount::new(9)).is_err());
+        
+        // Only the policy was fetched; nothing was submitted
+        assert_eq!(requests.load(Ordering::SeqCst), 1);
+    }
+    
+    fn transaction_request() -> TransactionRequest {
+        TransactionRequest {
+            from: "00".into(),
+            to: "00".into(),
+            amount: Amount::new(1),
+            fee: Amount::ZERO,
+            nonce: 0,
+            public_key: "00".into(),
+            signature: "00".into(),
+            timestamp: 0,
+        }
+    }
+    
+    #[tokio::test]
+    async fn test_get_retries_until_available() {
+        let (url, requests) = flaky_server(2, STATUS_BODY).await;
+        
+        let status = client(&url, 3).status().await.unwrap();
+        assert_eq!(status.finalized_version, 1);
+        assert_eq!(requests.load(Ordering::SeqCst), 3);
+    }
+    
+    #[tokio::test]
+    async fn test_get_gives_up_after_retries() {
+        let (url, requests) = flaky_server(5, STATUS_BODY).await;
+        
+        let result = client(&url, 2).status().await;
+        assert!(matches!(result, Err(ApiError::Unavailable(StatusCode::SERVICE_UNAVAILABLE))));
+        assert_eq!(requests.load(Ordering::SeqCst), 3);
+    }
+    
+    #[tokio::test]
+    async fn test_server_error_not_retried() {
+        let (url, requests) = flaky_server(0, r#"{"success":false,"error":"Account not found"}"#).await;
+        
+        let result = client(&url, 3).get_account("00").await;
+        assert!(matches!(result, Err(ApiError::Server(_))));
+        assert_eq!(requests.load(Ordering::SeqCst), 1);
+    }
+    
+    #[tokio::test]
+    async fn test_submit_transaction_not_retried() {
+        let (url, requests) = flaky_server(1, TX_BODY).await;
+        let api = client(&url, 3);
+        
+        let result = api.submit_transaction(&transaction_request()).await;
+        assert!(matches!(result, Err(ApiError::Unavailable(_))));
+        assert_eq!(requests.load(Ordering::SeqCst), 1);
+        
+        // The node is back, so the next explicit attempt goes through
+        let response = api.submit_transaction(&transaction_request()).await.unwrap();
+        assert_eq!(response.status, "pending");
+    }
+}
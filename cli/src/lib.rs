@@ -1,7 +1,9 @@
 //! RAINSONET CLI - Command Line Interface
 
 mod commands;
+mod verify;
 mod wallet;
 
 pub use commands::*;
+pub use verify::*;
 pub use wallet::*;
@@ -2,10 +2,14 @@
 
 use clap::{Parser, Subcommand};
 use rainsonet_cli::{
-    build_transaction_request, ApiClient, Wallet, WalletManager,
+    build_raw_transaction, build_transaction_request, load_json, load_snapshot, max_send_amount,
+    snapshot_account, verify_account, wallet_balances, ApiClient, TransactionResponse, Wallet,
+    WalletCheck, WalletManager, DEFAULT_RETRIES,
 };
-use rainsonet_core::Amount;
+use rainsonet_core::{AddressConfig, AddressFormat, Amount, Hash, NodeId, DEFAULT_ADDRESS_HRP};
+use rainsonet_crypto::hashing::{set_default_hash_algorithm, HashAlgorithm};
 use std::path::PathBuf;
+use std::time::Duration;
 
 #[derive(Parser)]
 #[command(name = "relyo")]
@@ -20,6 +24,27 @@ struct Cli {
     #[arg(short, long, default_value = "./wallets")]
     wallets_dir: PathBuf,
     
+    /// Retries for read-only requests (transactions are never retried)
+    #[arg(long, global = true, default_value_t = DEFAULT_RETRIES)]
+    retries: u32,
+    
+    /// Request timeout in seconds
+    #[arg(long, global = true, default_value_t = 30)]
+    timeout: u64,
+    
+    /// Address display format, hex or bech32; either is accepted as input
+    #[arg(long, global = true, default_value = "hex")]
+    address_format: AddressFormat,
+    
+    /// Human-readable prefix of bech32 addresses
+    #[arg(long, global = true, default_value = DEFAULT_ADDRESS_HRP)]
+    address_hrp: String,
+    
+    /// Hash algorithm of the chain's state roots, as set in its genesis;
+    /// proofs and snapshots are checked with it
+    #[arg(long, global = true, default_value = "blake3")]
+    hash_algorithm: HashAlgorithm,
+    
     #[command(subcommand)]
     command: Commands,
 }
@@ -53,8 +78,12 @@ enum Commands {
         to: String,
         
         /// Amount to send (in RELYO units)
-        #[arg(short, long)]
-        amount: f64,
+        #[arg(short, long, required_unless_present = "max")]
+        amount: Option<f64>,
+        
+        /// Send the whole balance minus the fee
+        #[arg(long, conflicts_with_all = ["amount", "sign_only"])]
+        max: bool,
         
         /// Transaction fee (in RELYO units)
         #[arg(long, default_value = "0.001")]
@@ -63,16 +92,93 @@ enum Commands {
         /// Nonce (optional, auto-fetch if not provided)
         #[arg(long)]
         nonce: Option<u64>,
+        
+        /// Print the signed transaction for `broadcast` instead of sending it
+        #[arg(long, requires = "nonce")]
+        sign_only: bool,
+    },
+    
+    /// Submit a transaction signed with `send --sign-only`
+    Broadcast {
+        /// Signed transaction (hex)
+        tx: String,
     },
     
     /// Get transaction status
     Transaction {
         /// Transaction ID
         tx_id: String,
+        
+        /// Poll until the transaction has at least this many confirmations
+        #[arg(long)]
+        min_confirmations: Option<u64>,
+        
+        /// Seconds between polls with --min-confirmations
+        #[arg(long, default_value_t = 2)]
+        poll_interval: u64,
     },
     
     /// Node status
     Status,
+    
+    /// Peer connection management on the node
+    Peer {
+        /// Admin token the node's `api.admin_token` is set to
+        #[arg(long)]
+        admin_token: Option<String>,
+        
+        #[command(subcommand)]
+        action: PeerAction,
+    },
+    
+    /// Read an account balance from a state snapshot file, without a node
+    BalanceOffline {
+        /// State snapshot file
+        #[arg(long)]
+        snapshot: PathBuf,
+        
+        /// Account address
+        #[arg(long)]
+        address: String,
+    },
+    
+    /// Verify an account bundle from /account/:address/verified offline
+    VerifyAccount {
+        /// Finality certificate (JSON)
+        #[arg(long)]
+        cert: PathBuf,
+        
+        /// Merkle proof of the account (JSON)
+        #[arg(long)]
+        proof: PathBuf,
+        
+        /// Account address
+        #[arg(long)]
+        address: String,
+        
+        /// Claimed account state (JSON)
+        #[arg(long)]
+        account: PathBuf,
+        
+        /// Trusted validator set (JSON)
+        #[arg(long)]
+        validators: PathBuf,
+    },
+}
+
+#[derive(Subcommand)]
+enum PeerAction {
+    /// Dial a peer multiaddr
+    Dial {
+        /// Peer address, e.g. /ip4/10.0.0.2/tcp/30333
+        multiaddr: String,
+    },
+    
+    /// Disconnect a peer
+    Disconnect {
+        /// Peer node ID (hex)
+        node_id: String,
+    },
 }
 
 #[derive(Subcommand)]
@@ -84,7 +190,11 @@ enum WalletAction {
     },
     
     /// List all wallets
-    List,
+    List {
+        /// Also show each wallet's balance, queried from the node
+        #[arg(long)]
+        with_balances: bool,
+    },
     
     /// Show wallet info
     Info {
@@ -106,26 +216,51 @@ enum WalletAction {
         /// Wallet name
         name: String,
     },
+    
+    /// Encrypt a plaintext wallet with a passphrase
+    Encrypt {
+        /// Wallet name
+        name: String,
+    },
+    
+    /// Decrypt an encrypted wallet back to plaintext
+    Decrypt {
+        /// Wallet name
+        name: String,
+    },
+    
+    /// Diagnose a wallet file that fails to load, restoring a missing address
+    Check {
+        /// Wallet name
+        name: String,
+    },
 }
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
     let cli = Cli::parse();
+    set_default_hash_algorithm(cli.hash_algorithm);
     
     let wallet_manager = WalletManager::new(cli.wallets_dir);
-    let api_client = ApiClient::new(&cli.node);
+    let api_client = ApiClient::new(&cli.node)
+        .with_timeout(Duration::from_secs(cli.timeout))
+        .with_retries(cli.retries);
+    let addresses = AddressConfig {
+        format: cli.address_format,
+        hrp: cli.address_hrp,
+    };
     
     match cli.command {
         Commands::Wallet { action } => {
-            handle_wallet_command(action, &wallet_manager)?;
+            handle_wallet_command(action, &wallet_manager, &api_client, &addresses).await?;
         }
         
         Commands::Balance { address, wallet } => {
             let addr = if let Some(wallet_name) = wallet {
-                let w = wallet_manager.get(&wallet_name)?;
+                let w = open_wallet(&wallet_manager, &wallet_name)?;
                 w.address().to_hex()
             } else if let Some(a) = address {
-                a
+                addresses.parse(&a)?.to_hex()
             } else {
                 eprintln!("Error: Provide either an address or --wallet");
                 std::process::exit(1);
@@ -133,9 +268,9 @@ async fn main() -> anyhow::Result<()> {
             
             match api_client.get_balance(&addr).await {
                 Ok(info) => {
-                    println!("Address:  {}", info.address);
+                    println!("Address:  {}", render_address(&addresses, &info.address));
                     println!("Balance:  {} RELYO", info.balance_relyo);
-                    println!("(Raw:     {} wei)", info.balance);
+                    println!("(Raw:     {} wei)", info.balance.0);
                 }
                 Err(e) => {
                     eprintln!("Error: {}", e);
@@ -144,32 +279,60 @@ async fn main() -> anyhow::Result<()> {
             }
         }
         
-        Commands::Send { from, to, amount, fee, nonce } => {
-            let wallet = wallet_manager.get(&from)?;
+        Commands::Send { from, to, amount, max: _, fee, nonce, sign_only } => {
+            let wallet = open_wallet(&wallet_manager, &from)?;
+            let to = addresses.parse(&to)?;
+            let to_hex = to.to_hex();
             
             // Get nonce if not provided
             let tx_nonce = match nonce {
                 Some(n) => n,
                 None => {
-                    let account = api_client.get_account(&wallet.address().to_hex()).await?;
+                    let address = wallet.address().to_hex();
+                    let account = api_client.get_optimistic_account(&address).await?;
                     account.nonce
                 }
             };
             
-            // Convert amounts
-            let amount_wei = Amount::from_relyo_f64(amount);
+            // Convert amounts; without an amount, `--max` drains the account
             let fee_wei = Amount::from_relyo_f64(fee);
+            let amount_wei = match amount {
+                Some(amount) => Amount::from_relyo_f64(amount),
+                None => {
+                    let address = wallet.address().to_hex();
+                    let account = api_client.get_optimistic_account(&address).await?;
+                    max_send_amount(&account, fee_wei).map_err(anyhow::Error::msg)?
+                }
+            };
+            
+            // Signed offline, submitted later with `broadcast`
+            if sign_only {
+                let raw = build_raw_transaction(&wallet, &to_hex, amount_wei, fee_wei, tx_nonce)
+                    .map_err(anyhow::Error::msg)?;
+                println!("{}", raw);
+                return Ok(());
+            }
+            
+            // Refuse up front what the node would reject
+            let policy = api_client.policy().await?;
+            if let Err(e) = policy.check(amount_wei, fee_wei) {
+                eprintln!("❌ Error: {}", e);
+                std::process::exit(1);
+            }
             
             // Build and send transaction
-            let tx_req = build_transaction_request(&wallet, &to, amount_wei, fee_wei, tx_nonce)?;
+            let tx_req =
+                build_transaction_request(&wallet, &to_hex, amount_wei, fee_wei, tx_nonce)
+                    .map_err(anyhow::Error::msg)?;
             
-            println!("Sending {} RELYO to {}...", amount, to);
+            println!("Sending {} to {}...", amount_wei, addresses.render(&to));
             
             match api_client.submit_transaction(&tx_req).await {
                 Ok(resp) => {
                     println!("✅ Transaction submitted!");
                     println!("TX ID:  {}", resp.tx_id);
                     println!("Status: {}", resp.status);
+                    print_fee_advisory(&resp);
                 }
                 Err(e) => {
                     eprintln!("❌ Error: {}", e);
@@ -178,27 +341,66 @@ async fn main() -> anyhow::Result<()> {
             }
         }
         
-        Commands::Transaction { tx_id } => {
-            match api_client.get_transaction(&tx_id).await {
+        Commands::Broadcast { tx } => {
+            match api_client.submit_raw_transaction(tx.trim()).await {
                 Ok(resp) => {
+                    println!("✅ Transaction submitted!");
                     println!("TX ID:  {}", resp.tx_id);
                     println!("Status: {}", resp.status);
+                    print_fee_advisory(&resp);
                 }
                 Err(e) => {
-                    eprintln!("Error: {}", e);
+                    eprintln!("❌ Error: {}", e);
                     std::process::exit(1);
                 }
             }
         }
         
+        Commands::Transaction { tx_id, min_confirmations, poll_interval } => {
+            loop {
+                let resp = match api_client.get_transaction(&tx_id).await {
+                    Ok(resp) => resp,
+                    Err(e) => {
+                        eprintln!("Error: {}", e);
+                        std::process::exit(1);
+                    }
+                };
+                
+                let confirmed = match (min_confirmations, resp.confirmations) {
+                    (None, _) => true,
+                    (Some(min), Some(n)) => n >= min,
+                    (Some(_), None) => resp.status == "unknown",
+                };
+                if !confirmed {
+                    tokio::time::sleep(Duration::from_secs(poll_interval)).await;
+                    continue;
+                }
+                
+                println!("TX ID:         {}", resp.tx_id);
+                println!("Status:        {}", resp.status);
+                if let Some(version) = resp.version {
+                    println!("Version:       {}", version);
+                }
+                if let Some(confirmations) = resp.confirmations {
+                    println!("Confirmations: {}", confirmations);
+                }
+                if min_confirmations.is_some() && resp.status == "unknown" {
+                    eprintln!("Transaction is not known to the node");
+                    std::process::exit(1);
+                }
+                break;
+            }
+        }
+        
         Commands::Status => {
             match api_client.status().await {
                 Ok(status) => {
                     println!("🌧️ RAINSONET Node Status");
                     println!("========================");
-                    println!("Node ID:       {}", truncate(&status.node_id, 16));
-                    println!("State Version: {}", status.state_version);
-                    println!("State Root:    {}", truncate(&status.state_root, 16));
+                    println!("Node ID:       {}", short_node_id(&status.node_id));
+                    println!("Finalized:     {}", status.finalized_version);
+                    println!("Optimistic:    {}", status.optimistic_version);
+                    println!("State Root:    {}", short_hash(&status.state_root));
                     println!("Peer Count:    {}", status.peer_count);
                     println!("Is Validator:  {}", if status.is_validator { "Yes" } else { "No" });
                     println!("Mempool Size:  {}", status.mempool_size);
@@ -209,61 +411,213 @@ async fn main() -> anyhow::Result<()> {
                 }
             }
         }
+        
+        Commands::Peer { admin_token, action } => {
+            let api_client = match admin_token {
+                Some(token) => api_client.with_admin_token(token),
+                None => api_client,
+            };
+            let result = match &action {
+                PeerAction::Dial { multiaddr } => api_client.dial_peer(multiaddr).await,
+                PeerAction::Disconnect { node_id } => api_client.disconnect_peer(node_id).await,
+            };
+            match result {
+                Ok(resp) => println!("✅ {} {} queued", resp.command, resp.target),
+                Err(e) => {
+                    eprintln!("Error: {}", e);
+                    std::process::exit(1);
+                }
+            }
+        }
+        
+        Commands::BalanceOffline { snapshot, address } => {
+            let address = addresses.parse(&address)?;
+            let snapshot = match load_snapshot(&snapshot) {
+                Ok(snapshot) => snapshot,
+                Err(e) => {
+                    eprintln!("❌ Invalid snapshot: {}", e);
+                    std::process::exit(1);
+                }
+            };
+            let account = snapshot_account(&snapshot, &address)?;
+            
+            println!("Address:    {}", addresses.render(&address));
+            println!("Balance:    {}", Amount::new(account.balance));
+            println!("(Raw:       {} wei)", account.balance);
+            println!("Nonce:      {}", account.nonce);
+            println!("Version:    {}", snapshot.version.0);
+            println!("State Root: {}", snapshot.root);
+        }
+        
+        Commands::VerifyAccount { cert, proof, address, account, validators } => {
+            let address = addresses.parse(&address)?;
+            let certificate = load_json(&cert)?;
+            let proof = load_json(&proof)?;
+            let account = load_json(&account)?;
+            let validators = load_json(&validators)?;
+            
+            match verify_account(&certificate, &proof, &address, &account, validators) {
+                Ok(report) => {
+                    println!("✅ PASS");
+                    println!("Address:    {}", addresses.render(&address));
+                    println!("Balance:    {}", account.balance);
+                    println!("Nonce:      {}", account.nonce);
+                    println!("Version:    {}", report.state_version.0);
+                    println!("State Root: {}", report.state_root);
+                    println!("Approvals:  {} of {} required", report.approvals, report.required_votes);
+                }
+                Err(e) => {
+                    println!("❌ FAIL: {}", e);
+                    std::process::exit(1);
+                }
+            }
+        }
     }
     
     Ok(())
 }
 
-fn handle_wallet_command(action: WalletAction, manager: &WalletManager) -> anyhow::Result<()> {
+async fn handle_wallet_command(
+    action: WalletAction,
+    manager: &WalletManager,
+    api_client: &ApiClient,
+    addresses: &AddressConfig,
+) -> anyhow::Result<()> {
     match action {
         WalletAction::Create { name } => {
             let wallet = manager.create(&name)?;
             println!("✅ Wallet '{}' created!", name);
-            println!("Address: {}", wallet.address().to_hex());
+            println!("Address: {}", addresses.render(&wallet.address()));
         }
         
-        WalletAction::List => {
+        WalletAction::List { with_balances } => {
             let wallets = manager.list()?;
             
             if wallets.is_empty() {
                 println!("No wallets found.");
+            } else if with_balances {
+                let rows = wallet_balances(&wallets, |address| async move {
+                    api_client.get_balance(&address).await
+                })
+                .await;
+                println!("Wallets:");
+                println!("{:<20} {:<66} Balance", "Name", "Address");
+                println!("{:-<20} {:-<66} {:-<9}", "", "", "");
+                for row in rows {
+                    let balance = row
+                        .balance
+                        .map(|b| b.to_string())
+                        .unwrap_or_else(|| "unavailable".to_string());
+                    let address = render_address(addresses, &row.address);
+                    println!("{:<20} {:<66} {}", row.name, address, balance);
+                }
             } else {
                 println!("Wallets:");
-                println!("{:<20} {}", "Name", "Address");
-                println!("{:-<20} {:-<66}", "", "");
+                println!("{:<20} {:<66} Encrypted", "Name", "Address");
+                println!("{:-<20} {:-<66} {:-<9}", "", "", "");
                 for w in wallets {
-                    println!("{:<20} {}", w.name, w.address);
+                    let encrypted = if w.encrypted { "yes" } else { "no" };
+                    let address = render_address(addresses, &w.address);
+                    println!("{:<20} {:<66} {}", w.name, address, encrypted);
                 }
             }
         }
         
         WalletAction::Info { name } => {
-            let wallet = manager.get(&name)?;
+            let wallet = open_wallet(manager, &name)?;
             println!("Wallet: {}", wallet.name());
-            println!("Address: {}", wallet.address().to_hex());
+            println!("Address: {}", addresses.render(&wallet.address()));
             println!("Public Key: {}", wallet.keypair().public_key().to_hex());
         }
         
         WalletAction::Import { name, secret } => {
             let wallet = manager.import(&name, &secret)?;
             println!("✅ Wallet '{}' imported!", name);
-            println!("Address: {}", wallet.address().to_hex());
+            println!("Address: {}", addresses.render(&wallet.address()));
         }
         
         WalletAction::Export { name } => {
-            let wallet = manager.get(&name)?;
+            let wallet = open_wallet(manager, &name)?;
             println!("⚠️  Keep this secret key safe!");
             println!("Secret Key: {}", hex::encode(wallet.keypair().secret_bytes()));
         }
+        
+        WalletAction::Encrypt { name } => {
+            let passphrase = dialoguer::Password::new()
+                .with_prompt(format!("New passphrase for '{}'", name))
+                .with_confirmation("Confirm passphrase", "Passphrases do not match")
+                .interact()?;
+            manager.encrypt(&name, &passphrase)?;
+            println!("🔒 Wallet '{}' encrypted!", name);
+        }
+        
+        WalletAction::Decrypt { name } => {
+            let passphrase = dialoguer::Password::new()
+                .with_prompt(format!("Passphrase for '{}'", name))
+                .interact()?;
+            manager.decrypt(&name, &passphrase)?;
+            println!("⚠️  Wallet '{}' decrypted, its secret key is now stored in plaintext", name);
+        }
+        
+        WalletAction::Check { name } => match manager.repair_or_report(&name) {
+            Ok(WalletCheck::Healthy) => println!("✅ Wallet '{}' is healthy", name),
+            Ok(WalletCheck::Repaired) => {
+                println!("🔧 Wallet '{}' repaired: address and public key restored", name);
+            }
+            Err(e) => {
+                eprintln!("❌ {}", e);
+                std::process::exit(1);
+            }
+        },
     }
     
     Ok(())
 }
 
-fn truncate(s: &str, max_len: usize) -> String {
-    if s.len() <= max_len {
-        s.to_string()
-    } else {
-        format!("{}...", &s[..max_len])
+/// Open a wallet, prompting for its passphrase if it is encrypted
+fn open_wallet(manager: &WalletManager, name: &str) -> anyhow::Result<Wallet> {
+    if !manager.is_encrypted(name)? {
+        return Ok(manager.get(name)?);
+    }
+    
+    let passphrase = dialoguer::Password::new()
+        .with_prompt(format!("Passphrase for '{}'", name))
+        .interact()?;
+    Ok(manager.get_with_passphrase(name, Some(&passphrase))?)
+}
+
+/// Warn when an accepted transaction's fee is below what the node suggests,
+/// or it cannot reach a validator
+fn print_fee_advisory(resp: &TransactionResponse) {
+    if let Some(warning) = &resp.warning {
+        println!("⚠️  Accepted, but {}; it will stay pending until one connects", warning);
     }
+    if resp.fee_adequate != Some(false) {
+        return;
+    }
+    let suggested = resp
+        .suggested_fee
+        .as_deref()
+        .and_then(|fee| fee.parse::<Amount>().ok())
+        .map(|fee| fee.to_string())
+        .unwrap_or_else(|| "a higher fee".to_string());
+    println!("⚠️  Fee is below the current suggestion of {}; confirmation may be slow", suggested);
+}
+
+/// Show a hex address in the configured format, printing anything unparseable as is
+fn render_address(addresses: &AddressConfig, s: &str) -> String {
+    addresses
+        .parse(s)
+        .map(|address| addresses.render(&address))
+        .unwrap_or_else(|_| s.to_string())
+}
+
+/// Abbreviate a hex hash from the API, printing anything unparseable as is
+fn short_hash(s: &str) -> String {
+    Hash::from_hex(s).map(|h| h.short()).unwrap_or_else(|_| s.to_string())
+}
+
+/// Abbreviate a hex node ID from the API, printing anything unparseable as is
+fn short_node_id(s: &str) -> String {
+    NodeId::from_hex(s).map(|id| id.short()).unwrap_or_else(|_| s.to_string())
 }
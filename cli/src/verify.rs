@@ -0,0 +1,234 @@
+//! Offline verification of finalized account state
+
+use rainsonet_consensus::{FinalityCertificate, ValidatorInfo, ValidatorSet};
+use rainsonet_core::{Address, RainsonetError, RainsonetResult, StateRoot, StateVersion};
+use rainsonet_crypto::hashing::MerkleProof;
+use rainsonet_state::{account_key, AccountState, StateEntry, StateSnapshot};
+use serde::de::DeserializeOwned;
+use std::path::Path;
+
+/// Summary of a successful account verification
+#[derive(Debug, Clone)]
+pub struct AccountVerification {
+    pub state_version: StateVersion,
+    pub state_root: StateRoot,
+    pub approvals: usize,
+    pub required_votes: usize,
+}
+
+/// Verify an account bundle from `/account/:address/verified` without
+/// trusting the node that served it: the certificate must carry a quorum of
+/// valid signatures from `validators`, and `proof` must place `account`
+/// under `address` in the certified state root.
+///
+/// The result is only as good as `validators`. They must come from a
+/// trusted source, not from the bundle the node served, since a node that
+/// lies about the account can sign a certificate with a set of its own.
+pub fn verify_account(
+    certificate: &FinalityCertificate,
+    proof: &MerkleProof,
+    address: &Address,
+    account: &AccountState,
+    validators: Vec<ValidatorInfo>,
+) -> RainsonetResult<AccountVerification> {
+    let validator_set = ValidatorSet::with_validators(validators);
+    certificate.verify_full(&validator_set)?;
+    
+    let entry = StateEntry {
+        key: account_key(address.as_bytes()),
+        value: account.to_bytes(),
+    };
+    if !proof.verify(entry.hash(), &certificate.state_root) {
+        return Err(RainsonetError::StateCorruption(format!(
+            "Account proof does not match certified root {}",
+            certificate.state_root
+        )));
+    }
+    
+    Ok(AccountVerification {
+        state_version: certificate.state_version,
+        state_root: certificate.state_root,
+        approvals: certificate.votes.iter().filter(|v| v.approve).count(),
+        required_votes: validator_set.required_votes(),
+    })
+}
+
+/// Load a snapshot written with `StateSnapshot::to_bytes` or
+/// `to_compressed_bytes`, rejecting it unless its entries hash to the root
+/// it claims
+pub fn load_snapshot(path: &Path) -> RainsonetResult<StateSnapshot> {
+    let bytes = std::fs::read(path)
+        .map_err(|e| RainsonetError::StorageError(format!("{}: {}", path.display(), e)))?;
+    let snapshot = StateSnapshot::from_bytes(&bytes)?;
+    if !snapshot.verify() {
+        return Err(RainsonetError::StateCorruption(format!(
+            "Snapshot entries do not match root {}",
+            snapshot.root
+        )));
+    }
+    Ok(snapshot)
+}
+
+/// Account state of `address` in `snapshot`; absent accounts are empty
+pub fn snapshot_account(
+    snapshot: &StateSnapshot,
+    address: &Address,
+) -> RainsonetResult<AccountState> {
+    let key = account_key(address.as_bytes());
+    match snapshot.entries.iter().find(|entry| entry.key == key) {
+        Some(entry) => AccountState::from_bytes(&entry.value),
+        None => Ok(AccountState::new(0, 0)),
+    }
+}
+
+/// Load a JSON input file
+pub fn load_json<T: DeserializeOwned>(path: &Path) -> RainsonetResult<T> {
+    let content = std::fs::read_to_string(path)
+        .map_err(|e| RainsonetError::StorageError(format!("{}: {}", path.display(), e)))?;
+    serde_json::from_str(&content)
+        .map_err(|e| RainsonetError::DeserializationError(format!("{}: {}", path.display(), e)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rainsonet_consensus::{LocalValidator, RainsonetConsensus};
+    use rainsonet_core::{ConsensusConfig, Hash};
+    use rainsonet_crypto::keys::KeyPair;
+    use rainsonet_state::MemoryStateStore;
+    use std::sync::Arc;
+    
+    struct Bundle {
+        certificate: FinalityCertificate,
+        proof: MerkleProof,
+        address: Address,
+        account: AccountState,
+        validators: Vec<ValidatorInfo>,
+    }
+    
+    /// Certify a small state on a single validator and prove one account in it
+    async fn genuine_bundle() -> Bundle {
+        let keypair = KeyPair::generate();
+        let validators = vec![ValidatorInfo::new(keypair.node_id(), keypair.public_key(), 1000)];
+        let config = ConsensusConfig {
+            is_validator: true,
+            ..ConsensusConfig::devnet()
+        };
+        let consensus = RainsonetConsensus::new(
+            config,
+            Arc::new(ValidatorSet::with_validators(validators.clone())),
+            Some(keypair),
+        )
+        .unwrap();
+        
+        let store = MemoryStateStore::new();
+        let address = Address::from_bytes([7u8; 32]);
+        let account = AccountState::new(5_000, 3);
+        store.set_account(address.as_bytes(), &account).unwrap();
+        store.set_account(&[8u8; 32], &AccountState::new(1, 0)).unwrap();
+        store.set_account(&[9u8; 32], &AccountState::new(2, 0)).unwrap();
+        
+        let proposal = consensus
+            .create_proposal(Hash::ZERO, store.compute_root().unwrap(), vec![], vec![])
+            .await
+            .unwrap();
+        consensus.vote_on_proposal(&proposal.id, true).await.unwrap();
+        
+        Bundle {
+            certificate: consensus.get_certificate(proposal.state_version).unwrap(),
+            proof: store.prove(&account_key(address.as_bytes())).unwrap(),
+            address,
+            account,
+            validators,
+        }
+    }
+    
+    fn verify_bundle(bundle: &Bundle) -> RainsonetResult<AccountVerification> {
+        verify_account(
+            &bundle.certificate,
+            &bundle.proof,
+            &bundle.address,
+            &bundle.account,
+            bundle.validators.clone(),
+        )
+    }
+    
+    #[tokio::test]
+    async fn test_genuine_bundle_passes() {
+        let bundle = genuine_bundle().await;
+        
+        let report = verify_bundle(&bundle).unwrap();
+        assert_eq!(report.state_version, bundle.certificate.state_version);
+        assert_eq!(report.approvals, 1);
+        assert_eq!(report.required_votes, 1);
+    }
+    
+    #[tokio::test]
+    async fn test_tampered_balance_fails() {
+        let mut bundle = genuine_bundle().await;
+        bundle.account.balance += 1;
+        
+        assert!(matches!(
+            verify_bundle(&bundle),
+            Err(RainsonetError::StateCorruption(_))
+        ));
+    }
+    
+    /// Write a snapshot holding one known account to a temp file
+    fn snapshot_file(name: &str, address: &Address, account: &AccountState) -> std::path::PathBuf {
+        let store = MemoryStateStore::new();
+        store.set_account(address.as_bytes(), account).unwrap();
+        store.set_account(&[8u8; 32], &AccountState::new(1, 0)).unwrap();
+        let snapshot = StateSnapshot::from_memory_store(&store).unwrap();
+        
+        let path = std::env::temp_dir()
+            .join(format!("rainsonet-snapshot-{}-{}", name, std::process::id()));
+        std::fs::write(&path, snapshot.to_bytes().unwrap()).unwrap();
+        path
+    }
+    
+    #[test]
+    fn test_snapshot_account_balance() {
+        let address = Address::from_bytes([7u8; 32]);
+        let path = snapshot_file("balance", &address, &AccountState::new(5_000, 3));
+        
+        let snapshot = load_snapshot(&path).unwrap();
+        let account = snapshot_account(&snapshot, &address).unwrap();
+        assert_eq!(account.balance, 5_000);
+        assert_eq!(account.nonce, 3);
+        
+        let missing = snapshot_account(&snapshot, &Address::from_bytes([9u8; 32])).unwrap();
+        assert_eq!(missing.balance, 0);
+        
+        let _ = std::fs::remove_file(path);
+    }
+    
+    #[test]
+    fn test_tampered_snapshot_fails() {
+        let address = Address::from_bytes([7u8; 32]);
+        let path = snapshot_file("tampered", &address, &AccountState::new(5_000, 3));
+        
+        let mut snapshot = StateSnapshot::from_bytes(&std::fs::read(&path).unwrap()).unwrap();
+        let key = account_key(address.as_bytes());
+        let entry = snapshot.entries.iter_mut().find(|e| e.key == key).unwrap();
+        entry.value = AccountState::new(1_000_000, 3).to_bytes();
+        std::fs::write(&path, snapshot.to_bytes().unwrap()).unwrap();
+        
+        assert!(matches!(
+            load_snapshot(&path),
+            Err(RainsonetError::StateCorruption(_))
+        ));
+        
+        let _ = std::fs::remove_file(path);
+    }
+    
+    #[tokio::test]
+    async fn test_tampered_signature_fails() {
+        let mut bundle = genuine_bundle().await;
+        let forger = LocalValidator::new(KeyPair::generate());
+        let vote = &mut bundle.certificate.votes[0];
+        vote.signature = forger.sign(&vote.get_signing_message());
+        
+        assert!(verify_bundle(&bundle).is_err());
+    }
+}
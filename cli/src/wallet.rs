@@ -1,23 +1,254 @@
 //! Wallet management
 
 use rainsonet_core::{Address, Amount, Nonce, RainsonetError, RainsonetResult, Timestamp};
+use rainsonet_crypto::encryption::EncryptedSecret;
 use rainsonet_crypto::keys::KeyPair;
 use rainsonet_relyo::RelyoTransaction;
 use serde::{Deserialize, Serialize};
-use std::path::PathBuf;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use zeroize::{Zeroize, Zeroizing};
+
+/// Length of a wallet's secret key in bytes
+const SECRET_KEY_BYTES: usize = 32;
+
+/// Hint appended to errors about a wallet file that cannot be used as is
+const RESTORE_HINT: &str = "restore it from a backup or re-import the secret key";
+
+/// Problem with a wallet file
+#[derive(Debug, thiserror::Error)]
+pub enum WalletError {
+    #[error("wallet file {} not found; check --wallets-dir and the wallet name", path.display())]
+    NotFound { path: PathBuf },
+    
+    #[error("failed to read wallet file {}: {reason}", path.display())]
+    Unreadable { path: PathBuf, reason: String },
+    
+    #[error(
+        "wallet file {} is not valid JSON at line {line}, column {column}: {reason}; {}",
+        path.display(),
+        RESTORE_HINT
+    )]
+    MalformedJson {
+        path: PathBuf,
+        line: usize,
+        column: usize,
+        reason: String,
+    },
+    
+    #[error("wallet '{name}' has no secret key; {}", RESTORE_HINT)]
+    MissingSecret { name: String },
+    
+    #[error("wallet '{name}' secret key is not valid hex ({reason}); {}", RESTORE_HINT)]
+    InvalidHex { name: String, reason: String },
+    
+    #[error(
+        "wallet '{name}' secret key is {got} bytes, expected {expected}; {}",
+        RESTORE_HINT
+    )]
+    InvalidKeyLength { name: String, expected: usize, got: usize },
+    
+    #[error("wallet '{name}' encrypted secret is damaged ({reason}); {}", RESTORE_HINT)]
+    InvalidEncryptedSecret { name: String, reason: String },
+    
+    #[error("wallet '{name}' is encrypted, a passphrase is required")]
+    PassphraseRequired { name: String },
+    
+    #[error("wallet '{name}' could not be decrypted: {reason}")]
+    DecryptionFailed { name: String, reason: String },
+    
+    #[error(
+        "wallet '{name}' stores address {stored} but its secret key is for {derived}; \
+         the file may have been tampered with, do not send funds to either until it is checked"
+    )]
+    AddressMismatch {
+        name: String,
+        stored: String,
+        derived: String,
+    },
+    
+    #[error("failed to write wallet file {}: {reason}", path.display())]
+    WriteFailed { path: PathBuf, reason: String },
+}
+
+impl From<WalletError> for RainsonetError {
+    fn from(err: WalletError) -> Self {
+        RainsonetError::ConfigError(err.to_string())
+    }
+}
+
+/// Outcome of [`WalletManager::repair_or_report`] on a usable wallet
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WalletCheck {
+    /// Nothing is wrong with the wallet
+    Healthy,
+    /// The address or public key was missing and has been restored from
+    /// the secret key
+    Repaired,
+}
 
 /// Wallet file format
 #[derive(Serialize, Deserialize)]
 pub struct WalletFile {
     pub version: u32,
     pub name: String,
+    /// Hex address, derivable from the secret key, so that listing does not
+    /// need the passphrase of encrypted wallets
+    #[serde(default)]
     pub address: String,
+    #[serde(default)]
     pub public_key: String,
     pub encrypted_secret: Option<Vec<u8>>,
     pub plaintext_secret: Option<String>,
     pub created_at: u64,
 }
 
+impl WalletFile {
+    /// Read a wallet file
+    pub fn read(path: &Path) -> Result<Self, WalletError> {
+        let content = std::fs::read_to_string(path).map_err(|e| {
+            if e.kind() == std::io::ErrorKind::NotFound {
+                WalletError::NotFound { path: path.to_path_buf() }
+            } else {
+                WalletError::Unreadable {
+                    path: path.to_path_buf(),
+                    reason: e.to_string(),
+                }
+            }
+        })?;
+        
+        serde_json::from_str(&content).map_err(|e| WalletError::MalformedJson {
+            path: path.to_path_buf(),
+            line: e.line(),
+            column: e.column(),
+            reason: e.to_string(),
+        })
+    }
+    
+    /// Write the wallet file, replacing any existing one.
+    ///
+    /// The file is written beside `path`, readable by its owner only, and
+    /// renamed into place once on disk, so a crash never leaves a truncated
+    /// wallet and the secret is never readable by others.
+    pub fn write(&self, path: &Path) -> Result<(), WalletError> {
+        let write_failed = |reason: String| WalletError::WriteFailed {
+            path: path.to_path_buf(),
+            reason,
+        };
+        let content = serde_json::to_string_pretty(self)
+            .map(Zeroizing::new)
+            .map_err(|e| write_failed(e.to_string()))?;
+        
+        let partial = path.with_extension("partial");
+        write_private(&partial, content.as_bytes())
+            .and_then(|()| std::fs::rename(&partial, path))
+            .map_err(|e| {
+                let _ = std::fs::remove_file(&partial);
+                write_failed(e.to_string())
+            })
+    }
+    
+    /// Check if the secret key is passphrase-encrypted
+    pub fn is_encrypted(&self) -> bool {
+        self.encrypted_secret.is_some()
+    }
+    
+    /// Recover the keypair, decrypting with `passphrase` if the secret is
+    /// encrypted, and check it against the stored address
+    pub fn keypair(&self, passphrase: Option<&str>) -> Result<KeyPair, WalletError> {
+        let keypair = match (&self.encrypted_secret, &self.plaintext_secret) {
+            (Some(_), _) => {
+                let passphrase = passphrase.ok_or_else(|| WalletError::PassphraseRequired {
+                    name: self.name.clone(),
+                })?;
+                let secret = self.encrypted()?.decrypt(passphrase).map_err(|e| {
+                    WalletError::DecryptionFailed {
+                        name: self.name.clone(),
+                        reason: e.to_string(),
+                    }
+                })?;
+                self.keypair_from_secret(&secret)?
+            }
+            (None, Some(secret_hex)) => {
+                let secret = hex::decode(secret_hex.trim()).map(Zeroizing::new).map_err(|e| {
+                    WalletError::InvalidHex {
+                        name: self.name.clone(),
+                        reason: e.to_string(),
+                    }
+                })?;
+                self.keypair_from_secret(&secret)?
+            }
+            (None, None) => {
+                return Err(WalletError::MissingSecret { name: self.name.clone() });
+            }
+        };
+        
+        self.check_address(&keypair)?;
+        Ok(keypair)
+    }
+    
+    /// Parse the encrypted secret, without decrypting it
+    fn encrypted(&self) -> Result<EncryptedSecret, WalletError> {
+        let bytes = self.encrypted_secret.as_deref().unwrap_or_default();
+        EncryptedSecret::from_bytes(bytes).map_err(|e| WalletError::InvalidEncryptedSecret {
+            name: self.name.clone(),
+            reason: e.to_string(),
+        })
+    }
+    
+    fn keypair_from_secret(&self, secret: &[u8]) -> Result<KeyPair, WalletError> {
+        KeyPair::from_secret_bytes(secret).map_err(|_| WalletError::InvalidKeyLength {
+            name: self.name.clone(),
+            expected: SECRET_KEY_BYTES,
+            got: secret.len(),
+        })
+    }
+    
+    /// Check that the stored address, if any, is the one `keypair` controls
+    fn check_address(&self, keypair: &KeyPair) -> Result<(), WalletError> {
+        if self.address.is_empty() {
+            return Ok(());
+        }
+        let derived = keypair.address();
+        if Address::from_hex(&self.address).ok() != Some(derived) {
+            return Err(WalletError::AddressMismatch {
+                name: self.name.clone(),
+                stored: self.address.clone(),
+                derived: derived.to_hex(),
+            });
+        }
+        Ok(())
+    }
+}
+
+/// Clears the plaintext secret key from memory once the file is dropped
+impl Drop for WalletFile {
+    fn drop(&mut self) {
+        if let Some(secret) = self.plaintext_secret.as_mut() {
+            secret.zeroize();
+        }
+    }
+}
+
+/// Create `path` with owner-only permissions, write `content` and sync it
+fn write_private(path: &Path, content: &[u8]) -> std::io::Result<()> {
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+    let mut file = options.open(path)?;
+    
+    // The mode only applies on creation, not to a leftover partial file
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        file.set_permissions(std::fs::Permissions::from_mode(0o600))?;
+    }
+    
+    file.write_all(content)?;
+    file.sync_all()
+}
+
 /// Local wallet
 pub struct Wallet {
     name: String,
@@ -44,32 +275,29 @@ impl Wallet {
         }
     }
     
-    /// Load wallet from file
-    pub fn load(path: &PathBuf) -> RainsonetResult<Self> {
-        let content = std::fs::read_to_string(path)
-            .map_err(|e| RainsonetError::Io(e.to_string()))?;
-        
-        let wallet_file: WalletFile = serde_json::from_str(&content)
-            .map_err(|e| RainsonetError::Serialization(e.to_string()))?;
-        
-        // For now, only support plaintext (in production, implement encryption)
-        let secret_hex = wallet_file.plaintext_secret
-            .ok_or_else(|| RainsonetError::Config("No secret key in wallet".into()))?;
-        
-        let secret_bytes = hex::decode(&secret_hex)
-            .map_err(|e| RainsonetError::Serialization(e.to_string()))?;
-        
-        let keypair = KeyPair::from_secret_bytes(&secret_bytes)?;
+    /// Load a plaintext wallet from file
+    pub fn load(path: &Path) -> Result<Self, WalletError> {
+        Self::load_with_passphrase(path, None)
+    }
+    
+    /// Load a wallet from file in either format, using `passphrase` if it is
+    /// encrypted. Fails if the stored address is not the secret key's.
+    pub fn load_with_passphrase(
+        path: &Path,
+        passphrase: Option<&str>,
+    ) -> Result<Self, WalletError> {
+        let wallet_file = WalletFile::read(path)?;
+        let keypair = wallet_file.keypair(passphrase)?;
         
         Ok(Self {
-            name: wallet_file.name,
+            name: wallet_file.name.clone(),
             keypair,
-            path: Some(path.clone()),
+            path: Some(path.to_path_buf()),
         })
     }
     
     /// Save wallet to file
-    pub fn save(&self, path: &PathBuf) -> RainsonetResult<()> {
+    pub fn save(&self, path: &Path) -> RainsonetResult<()> {
         let wallet_file = WalletFile {
             version: 1,
             name: self.name.clone(),
@@ -80,13 +308,7 @@ impl Wallet {
             created_at: Timestamp::now().0,
         };
         
-        let content = serde_json::to_string_pretty(&wallet_file)
-            .map_err(|e| RainsonetError::Serialization(e.to_string()))?;
-        
-        std::fs::write(path, content)
-            .map_err(|e| RainsonetError::Io(e.to_string()))?;
-        
-        Ok(())
+        Ok(wallet_file.write(path)?)
     }
     
     /// Get name
@@ -94,6 +316,11 @@ impl Wallet {
         &self.name
     }
     
+    /// File the wallet was loaded from, if any
+    pub fn path(&self) -> Option<&Path> {
+        self.path.as_deref()
+    }
+    
     /// Get address
     pub fn address(&self) -> Address {
         self.keypair.address()
@@ -136,7 +363,7 @@ impl WalletManager {
     /// Create wallets directory if it doesn't exist
     pub fn init(&self) -> RainsonetResult<()> {
         std::fs::create_dir_all(&self.wallets_dir)
-            .map_err(|e| RainsonetError::Io(e.to_string()))?;
+            .map_err(|e| RainsonetError::StorageError(e.to_string()))?;
         Ok(())
     }
     
@@ -147,16 +374,18 @@ impl WalletManager {
         let mut wallets = Vec::new();
         
         for entry in std::fs::read_dir(&self.wallets_dir)
-            .map_err(|e| RainsonetError::Io(e.to_string()))?
+            .map_err(|e| RainsonetError::StorageError(e.to_string()))?
         {
-            let entry = entry.map_err(|e| RainsonetError::Io(e.to_string()))?;
+            let entry = entry.map_err(|e| RainsonetError::StorageError(e.to_string()))?;
             let path = entry.path();
             
+            // Read the stored address so encrypted wallets list without a passphrase
             if path.extension().map(|e| e == "json").unwrap_or(false) {
-                if let Ok(wallet) = Wallet::load(&path) {
+                if let Ok(wallet_file) = WalletFile::read(&path) {
                     wallets.push(WalletInfo {
-                        name: wallet.name().to_string(),
-                        address: wallet.address().to_hex(),
+                        encrypted: wallet_file.is_encrypted(),
+                        name: wallet_file.name.clone(),
+                        address: wallet_file.address.clone(),
                         path,
                     });
                 }
@@ -174,7 +403,7 @@ impl WalletManager {
         let path = self.wallets_dir.join(format!("{}.json", name));
         
         if path.exists() {
-            return Err(RainsonetError::Config(format!(
+            return Err(RainsonetError::ConfigError(format!(
                 "Wallet '{}' already exists",
                 name
             )));
@@ -185,17 +414,106 @@ impl WalletManager {
     }
     
     /// Get wallet by name
-    pub fn get(&self, name: &str) -> RainsonetResult<Wallet> {
-        let path = self.wallets_dir.join(format!("{}.json", name));
+    pub fn get(&self, name: &str) -> Result<Wallet, WalletError> {
+        self.get_with_passphrase(name, None)
+    }
+    
+    /// Get wallet by name, using `passphrase` if it is encrypted
+    pub fn get_with_passphrase(
+        &self,
+        name: &str,
+        passphrase: Option<&str>,
+    ) -> Result<Wallet, WalletError> {
+        Wallet::load_with_passphrase(&self.wallet_path(name), passphrase)
+    }
+    
+    /// Diagnose a wallet that fails to load, naming what is wrong with it.
+    ///
+    /// A missing address or public key is restored from a plaintext secret
+    /// key; anything else is reported, including an address that does not
+    /// match the secret key, which is never overwritten. Encrypted secrets
+    /// are only checked for damage, as decrypting them needs the passphrase.
+    pub fn repair_or_report(&self, name: &str) -> Result<WalletCheck, WalletError> {
+        let path = self.wallet_path(name);
+        let mut wallet_file = WalletFile::read(&path)?;
+        
+        if wallet_file.is_encrypted() {
+            wallet_file.encrypted()?;
+            return Ok(WalletCheck::Healthy);
+        }
+        
+        let keypair = wallet_file.keypair(None)?;
+        if !wallet_file.address.is_empty() && !wallet_file.public_key.is_empty() {
+            return Ok(WalletCheck::Healthy);
+        }
+        
+        wallet_file.address = keypair.address().to_hex();
+        wallet_file.public_key = keypair.public_key().to_hex();
+        wallet_file.write(&path)?;
+        Ok(WalletCheck::Repaired)
+    }
+    
+    /// Check if a wallet's secret key is passphrase-encrypted
+    pub fn is_encrypted(&self, name: &str) -> RainsonetResult<bool> {
+        Ok(WalletFile::read(&self.existing_path(name)?)?.is_encrypted())
+    }
+    
+    /// Encrypt a plaintext wallet in place, keeping its keypair
+    pub fn encrypt(&self, name: &str, passphrase: &str) -> RainsonetResult<()> {
+        let path = self.existing_path(name)?;
+        let mut wallet_file = WalletFile::read(&path)?;
+        
+        if wallet_file.is_encrypted() {
+            return Err(RainsonetError::ConfigError(format!(
+                "Wallet '{}' is already encrypted",
+                name
+            )));
+        }
+        
+        let keypair = wallet_file.keypair(None)?;
+        let encrypted = EncryptedSecret::encrypt(keypair.secret_bytes().as_slice(), passphrase)?;
+        wallet_file.encrypted_secret = Some(encrypted.to_bytes());
+        wallet_file.plaintext_secret = None;
+        
+        Ok(wallet_file.write(&path)?)
+    }
+    
+    /// Decrypt an encrypted wallet in place, storing its secret key in plaintext
+    pub fn decrypt(&self, name: &str, passphrase: &str) -> RainsonetResult<()> {
+        let path = self.existing_path(name)?;
+        let mut wallet_file = WalletFile::read(&path)?;
+        
+        if !wallet_file.is_encrypted() {
+            return Err(RainsonetError::ConfigError(format!(
+                "Wallet '{}' is not encrypted",
+                name
+            )));
+        }
+        
+        let keypair = wallet_file.keypair(Some(passphrase))?;
+        wallet_file.plaintext_secret = Some(hex::encode(keypair.secret_bytes()));
+        wallet_file.encrypted_secret = None;
+        
+        Ok(wallet_file.write(&path)?)
+    }
+    
+    /// Path of a wallet, whether or not it exists
+    fn wallet_path(&self, name: &str) -> PathBuf {
+        self.wallets_dir.join(format!("{}.json", name))
+    }
+    
+    /// Path of an existing wallet
+    fn existing_path(&self, name: &str) -> RainsonetResult<PathBuf> {
+        let path = self.wallet_path(name);
         
         if !path.exists() {
-            return Err(RainsonetError::Config(format!(
+            return Err(RainsonetError::ConfigError(format!(
                 "Wallet '{}' not found",
                 name
             )));
         }
         
-        Wallet::load(&path)
+        Ok(path)
     }
     
     /// Import wallet from secret key
@@ -203,7 +521,8 @@ impl WalletManager {
         self.init()?;
         
         let secret_bytes = hex::decode(secret_hex)
-            .map_err(|e| RainsonetError::Serialization(e.to_string()))?;
+            .map(Zeroizing::new)
+            .map_err(|e| RainsonetError::DeserializationError(e.to_string()))?;
         
         let keypair = KeyPair::from_secret_bytes(&secret_bytes)?;
         let wallet = Wallet::from_keypair(name, keypair);
@@ -221,4 +540,199 @@ pub struct WalletInfo {
     pub name: String,
     pub address: String,
     pub path: PathBuf,
+    pub encrypted: bool,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    fn temp_manager(test: &str) -> WalletManager {
+        let dir = std::env::temp_dir()
+            .join(format!("rainsonet-wallets-{}-{}", test, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        WalletManager::new(dir)
+    }
+    
+    #[test]
+    fn test_encrypt_preserves_keypair() {
+        let manager = temp_manager("encrypt");
+        let wallet = manager.create("alice").unwrap();
+        let secret_hex = hex::encode(wallet.keypair().secret_bytes());
+        
+        manager.encrypt("alice", "hunter2").unwrap();
+        assert!(manager.is_encrypted("alice").unwrap());
+        
+        let on_disk = std::fs::read_to_string(manager.wallets_dir.join("alice.json")).unwrap();
+        assert!(!on_disk.contains(&secret_hex));
+        
+        assert!(manager.get("alice").is_err());
+        assert!(manager.get_with_passphrase("alice", Some("wrong")).is_err());
+        let loaded = manager.get_with_passphrase("alice", Some("hunter2")).unwrap();
+        assert_eq!(loaded.keypair().secret_bytes(), wallet.keypair().secret_bytes());
+        
+        let listed = manager.list().unwrap();
+        assert_eq!(listed.len(), 1);
+        assert!(listed[0].encrypted);
+        assert_eq!(listed[0].address, wallet.address().to_hex());
+        
+        let _ = std::fs::remove_dir_all(&manager.wallets_dir);
+    }
+    
+    #[test]
+    fn test_decrypt_restores_plaintext() {
+        let manager = temp_manager("decrypt");
+        let wallet = manager.create("bob").unwrap();
+        
+        assert!(manager.decrypt("bob", "hunter2").is_err());
+        manager.encrypt("bob", "hunter2").unwrap();
+        assert!(manager.encrypt("bob", "hunter2").is_err());
+        assert!(manager.decrypt("bob", "wrong").is_err());
+        
+        manager.decrypt("bob", "hunter2").unwrap();
+        assert!(!manager.is_encrypted("bob").unwrap());
+        let loaded = manager.get("bob").unwrap();
+        assert_eq!(loaded.keypair().secret_bytes(), wallet.keypair().secret_bytes());
+        
+        let _ = std::fs::remove_dir_all(&manager.wallets_dir);
+    }
+    
+    #[cfg(unix)]
+    #[test]
+    fn test_wallet_written_owner_only() {
+        use std::os::unix::fs::PermissionsExt;
+        
+        let manager = temp_manager("mode");
+        manager.create("carol").unwrap();
+        let path = manager.wallet_path("carol");
+        
+        // A stale partial file from an interrupted write does not widen the mode
+        let partial = path.with_extension("partial");
+        std::fs::write(&partial, "{}").unwrap();
+        std::fs::set_permissions(&partial, std::fs::Permissions::from_mode(0o644)).unwrap();
+        manager.encrypt("carol", "hunter2").unwrap();
+        
+        let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+        assert!(!partial.exists());
+        assert!(manager.get_with_passphrase("carol", Some("hunter2")).is_ok());
+        
+        let _ = std::fs::remove_dir_all(&manager.wallets_dir);
+    }
+    
+    /// Rewrite a wallet's JSON file with `edit` applied
+    fn edit_wallet(manager: &WalletManager, name: &str, edit: impl FnOnce(&mut serde_json::Value)) {
+        let path = manager.wallet_path(name);
+        let mut json: serde_json::Value =
+            serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+        edit(&mut json);
+        std::fs::write(&path, json.to_string()).unwrap();
+    }
+    
+    #[test]
+    fn test_corrupted_wallets_report_specific_errors() {
+        let manager = temp_manager("corrupt");
+        
+        assert!(matches!(manager.get("ghost"), Err(WalletError::NotFound { .. })));
+        assert!(matches!(manager.repair_or_report("ghost"), Err(WalletError::NotFound { .. })));
+        
+        manager.create("truncated").unwrap();
+        let path = manager.wallet_path("truncated");
+        let content = std::fs::read_to_string(&path).unwrap();
+        std::fs::write(&path, &content[..content.len() / 2]).unwrap();
+        assert!(matches!(
+            manager.get("truncated"),
+            Err(WalletError::MalformedJson { line, .. }) if line > 1
+        ));
+        
+        manager.create("no-secret").unwrap();
+        edit_wallet(&manager, "no-secret", |json| {
+            json["plaintext_secret"] = serde_json::Value::Null;
+        });
+        assert!(matches!(manager.get("no-secret"), Err(WalletError::MissingSecret { .. })));
+        
+        manager.create("bad-hex").unwrap();
+        edit_wallet(&manager, "bad-hex", |json| json["plaintext_secret"] = "zz".repeat(32).into());
+        assert!(matches!(manager.get("bad-hex"), Err(WalletError::InvalidHex { .. })));
+        
+        manager.create("short").unwrap();
+        edit_wallet(&manager, "short", |json| json["plaintext_secret"] = "ab".repeat(16).into());
+        assert!(matches!(
+            manager.get("short"),
+            Err(WalletError::InvalidKeyLength { expected: 32, got: 16, .. })
+        ));
+        
+        manager.create("encrypted").unwrap();
+        manager.encrypt("encrypted", "hunter2").unwrap();
+        edit_wallet(&manager, "encrypted", |json| json["encrypted_secret"] = vec![1u8; 4].into());
+        assert!(matches!(
+            manager.repair_or_report("encrypted"),
+            Err(WalletError::InvalidEncryptedSecret { .. })
+        ));
+        
+        // Every problem is diagnosed the same way it fails to load
+        for name in ["truncated", "no-secret", "bad-hex", "short"] {
+            let loaded = manager.get(name).map(|_| ()).unwrap_err().to_string();
+            let reported = manager.repair_or_report(name).unwrap_err().to_string();
+            assert_eq!(loaded, reported);
+            assert!(reported.contains("re-import the secret key"), "{}", reported);
+        }
+        
+        let _ = std::fs::remove_dir_all(&manager.wallets_dir);
+    }
+    
+    #[test]
+    fn test_tampered_address_detected() {
+        let manager = temp_manager("tampered");
+        let wallet = manager.create("carol").unwrap();
+        let attacker = KeyPair::generate().address().to_hex();
+        edit_wallet(&manager, "carol", |json| json["address"] = attacker.clone().into());
+        
+        match manager.get("carol") {
+            Err(WalletError::AddressMismatch { stored, derived, .. }) => {
+                assert_eq!(stored, attacker);
+                assert_eq!(derived, wallet.address().to_hex());
+            }
+            other => panic!("expected an address mismatch, got {:?}", other.map(|w| w.address())),
+        }
+        
+        // A mismatch is reported, never repaired
+        assert!(matches!(
+            manager.repair_or_report("carol"),
+            Err(WalletError::AddressMismatch { .. })
+        ));
+        
+        // An encrypted wallet is checked once decrypted
+        let dave = manager.create("dave").unwrap();
+        manager.encrypt("dave", "hunter2").unwrap();
+        edit_wallet(&manager, "dave", |json| json["address"] = attacker.clone().into());
+        assert!(matches!(
+            manager.get_with_passphrase("dave", Some("hunter2")),
+            Err(WalletError::AddressMismatch { .. })
+        ));
+        assert_ne!(dave.address().to_hex(), attacker);
+        
+        let _ = std::fs::remove_dir_all(&manager.wallets_dir);
+    }
+    
+    #[test]
+    fn test_missing_address_repaired() {
+        let manager = temp_manager("repair");
+        let wallet = manager.create("erin").unwrap();
+        assert_eq!(manager.repair_or_report("erin").unwrap(), WalletCheck::Healthy);
+        
+        edit_wallet(&manager, "erin", |json| {
+            let fields = json.as_object_mut().unwrap();
+            fields.remove("address");
+            fields.remove("public_key");
+        });
+        assert_eq!(manager.repair_or_report("erin").unwrap(), WalletCheck::Repaired);
+        assert_eq!(manager.repair_or_report("erin").unwrap(), WalletCheck::Healthy);
+        
+        let listed = manager.list().unwrap();
+        assert_eq!(listed[0].address, wallet.address().to_hex());
+        assert_eq!(manager.get("erin").unwrap().address(), wallet.address());
+        
+        let _ = std::fs::remove_dir_all(&manager.wallets_dir);
+    }
 }
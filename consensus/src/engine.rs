@@ -1,20 +1,22 @@
 //! Main consensus engine implementation
 
 use crate::proposal::{Proposal, ProposalStatus, ProposalStore};
-use crate::validator::{LocalValidator, SharedValidatorSet, ValidatorSet};
+use crate::validator::{LocalValidator, SharedValidatorSet, VoteRequirement};
 use crate::vote::{FinalityCertificate, Vote, VoteCollection};
 use async_trait::async_trait;
-use parking_lot::RwLock;
+use parking_lot::{Mutex, RwLock};
 use rainsonet_core::{
-    ConsensusConfig, ConsensusEngine as ConsensusEngineTrait, Hash, NodeId,
-    RainsonetError, RainsonetResult, StateChange, StateRoot, StateVersion, Timestamp,
+    system_time, ConsensusConfig, ConsensusEngine as ConsensusEngineTrait, Hash, NodeId,
+    RainsonetError, RainsonetResult, SharedTimeSource, StateChange, StateRoot, StateVersion,
 };
 use rainsonet_crypto::keys::KeyPair;
-use rainsonet_crypto::signing::sign;
-use std::collections::HashMap;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
-use tokio::sync::mpsc;
-use tracing::{debug, error, info, warn};
+use tokio::sync::{mpsc, oneshot};
+use tracing::{debug, info, warn};
 
 /// Events emitted by the consensus engine
 #[derive(Debug, Clone)]
@@ -31,9 +33,63 @@ pub enum ConsensusEvent {
     ProposalRejected(Hash),
     /// Proposal expired
     ProposalExpired(Hash),
+    /// This node was removed from or deactivated in the validator set
+    LocalValidatorDeactivated(NodeId),
+    /// This node is an active validator again
+    LocalValidatorReactivated(NodeId),
+}
+
+/// Votes collected for a proposal that has not finalized, saved so that a
+/// restart does not lose them
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PendingVoteCollection {
+    pub proposal: Proposal,
+    pub changes: Vec<StateChange>,
+    pub votes: Vec<Vote>,
+}
+
+/// Write pending vote collections to `path` as JSON, replacing it only once
+/// the write is complete so a crash mid-save keeps the previous file
+pub fn save_vote_collections(
+    path: &Path,
+    collections: &[PendingVoteCollection],
+) -> RainsonetResult<()> {
+    let json = serde_json::to_vec(collections)
+        .map_err(|e| RainsonetError::SerializationError(e.to_string()))?;
+    let partial = path.with_extension("partial");
+    std::fs::write(&partial, json)
+        .and_then(|()| std::fs::rename(&partial, path))
+        .map_err(|e| RainsonetError::StorageError(e.to_string()))
+}
+
+/// Read pending vote collections saved by [`save_vote_collections`], or
+/// none if `path` does not exist
+pub fn load_vote_collections(path: &Path) -> RainsonetResult<Vec<PendingVoteCollection>> {
+    match std::fs::read(path) {
+        Ok(json) => serde_json::from_slice(&json)
+            .map_err(|e| RainsonetError::DeserializationError(e.to_string())),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Vec::new()),
+        Err(e) => Err(RainsonetError::StorageError(e.to_string())),
+    }
+}
+
+/// Delivers the outcome of a proposal to `propose`
+type OutcomeSender = oneshot::Sender<RainsonetResult<StateVersion>>;
+
+/// Outcome of a proposal created by this engine, kept until it is taken
+enum ProposalOutcome {
+    /// Not yet decided, with the waiter once one subscribes
+    Pending(Option<OutcomeSender>),
+    /// Decided before anyone subscribed
+    Decided(RainsonetResult<StateVersion>),
 }
 
 /// Consensus engine for RAINSONET
+///
+/// Proposal and vote handling is async so it can await validation and
+/// execution. The engine's `parking_lot` locks are only taken in
+/// synchronous sections and never held across an `.await`; their guards are
+/// not `Send`, so the compiler rejects any engine future that would.
 pub struct RainsonetConsensus {
     config: ConsensusConfig,
     validator_set: SharedValidatorSet,
@@ -44,22 +100,36 @@ pub struct RainsonetConsensus {
     finalized_root: RwLock<StateRoot>,
     certificates: RwLock<Vec<FinalityCertificate>>,
     event_tx: Option<mpsc::Sender<ConsensusEvent>>,
+    clock: SharedTimeSource,
+    outcomes: Mutex<HashMap<Hash, ProposalOutcome>>,
+    /// Proposal approved by the local validator for each unfinalized
+    /// version; a validator never approves two proposals for one version
+    approved: RwLock<HashMap<StateVersion, Hash>>,
+    /// Whether the local validator was active when last checked
+    locally_active: AtomicBool,
 }
 
 impl RainsonetConsensus {
-    /// Create a new consensus engine
+    /// Create a new consensus engine.
+    ///
+    /// A validator engine (`config.is_validator`) requires a keypair to sign
+    /// proposals and votes; the keypair is ignored otherwise.
     pub fn new(
         config: ConsensusConfig,
         validator_set: SharedValidatorSet,
         local_keypair: Option<KeyPair>,
-    ) -> Self {
-        let local_validator = if config.is_validator {
-            local_keypair.map(LocalValidator::new)
-        } else {
-            None
+    ) -> RainsonetResult<Self> {
+        let local_validator = match (config.is_validator, local_keypair) {
+            (true, Some(keypair)) => Some(LocalValidator::new(keypair)),
+            (true, None) => {
+                return Err(RainsonetError::ConfigError(
+                    "Validator consensus engine requires a keypair".into(),
+                ));
+            }
+            (false, _) => None,
         };
         
-        Self {
+        Ok(Self {
             config,
             validator_set,
             local_validator,
@@ -69,7 +139,11 @@ impl RainsonetConsensus {
             finalized_root: RwLock::new(Hash::ZERO),
             certificates: RwLock::new(Vec::new()),
             event_tx: None,
-        }
+            clock: system_time(),
+            outcomes: Mutex::new(HashMap::new()),
+            approved: RwLock::new(HashMap::new()),
+            locally_active: AtomicBool::new(true),
+        })
     }
     
     /// Set event channel
@@ -77,6 +151,11 @@ impl RainsonetConsensus {
         self.event_tx = Some(tx);
     }
     
+    /// Set the time source used for timestamps and timeouts
+    pub fn set_time_source(&mut self, clock: SharedTimeSource) {
+        self.clock = clock;
+    }
+    
     /// Check if this node is a validator
     pub fn is_validator(&self) -> bool {
         self.local_validator.is_some()
@@ -87,40 +166,113 @@ impl RainsonetConsensus {
         self.local_validator.as_ref().map(|v| v.node_id())
     }
     
-    /// Create a proposal for state changes
-    pub fn create_proposal(
+    /// Check if this node is currently an active member of the validator set.
+    ///
+    /// Emits `LocalValidatorDeactivated` / `LocalValidatorReactivated` when
+    /// the answer differs from the previous check.
+    pub fn is_active_validator(&self) -> bool {
+        self.active_local_validator().is_ok()
+    }
+    
+    /// Local validator, if it may currently propose and vote
+    fn active_local_validator(&self) -> RainsonetResult<&LocalValidator> {
+        let local = self
+            .local_validator
+            .as_ref()
+            .ok_or(RainsonetError::NotAValidator)?;
+        
+        let node_id = local.node_id();
+        let active = self.validator_set.is_validator(&node_id);
+        if self.locally_active.swap(active, Ordering::SeqCst) != active {
+            if active {
+                info!("Local validator {} reactivated", node_id.short());
+                self.emit_event(ConsensusEvent::LocalValidatorReactivated(node_id));
+            } else {
+                warn!(
+                    "Local validator {} is no longer active, not proposing or voting",
+                    node_id.short()
+                );
+                self.emit_event(ConsensusEvent::LocalValidatorDeactivated(node_id));
+            }
+        }
+        
+        if active {
+            Ok(local)
+        } else {
+            Err(RainsonetError::NotAValidator)
+        }
+    }
+    
+    /// Create a proposal for state changes. The proposer's approval is not
+    /// implied: it casts it with [`Self::vote_on_proposal`] like any other.
+    pub async fn create_proposal(
         &self,
         previous_root: StateRoot,
         new_root: StateRoot,
         tx_ids: Vec<Hash>,
         changes: Vec<StateChange>,
     ) -> RainsonetResult<Proposal> {
-        let local = self
-            .local_validator
-            .as_ref()
-            .ok_or(RainsonetError::NotAValidator)?;
+        self.create_proposal_updating_validators(
+            previous_root,
+            new_root,
+            tx_ids,
+            Vec::new(),
+            Vec::new(),
+            changes,
+        )
+        .await
+    }
+    
+    /// Create a proposal that also deactivates `inactive_validators` and
+    /// applies `stake_updates`.
+    ///
+    /// `changes` must end with their records from
+    /// [`crate::validator::ValidatorSet::deactivation_changes`] and then
+    /// [`crate::validator::ValidatorSet::stake_changes`], which receivers
+    /// rebuild from the lists and check against the changes hash.
+    pub async fn create_proposal_updating_validators(
+        &self,
+        previous_root: StateRoot,
+        new_root: StateRoot,
+        tx_ids: Vec<Hash>,
+        inactive_validators: Vec<NodeId>,
+        stake_updates: Vec<(NodeId, u128)>,
+        changes: Vec<StateChange>,
+    ) -> RainsonetResult<Proposal> {
+        if let VoteRequirement::Insufficient { active, minimum } =
+            self.validator_set.vote_requirement(&self.config)
+        {
+            return Err(RainsonetError::InsufficientValidators { active, minimum });
+        }
+        let local = self.active_local_validator()?;
         
         let next_version = self.finalized_version.read().next();
         
-        let proposal = Proposal::new(
+        let proposal = Proposal::new_at(
             local.node_id(),
             next_version,
             previous_root,
+            self.latest_certificate_hash(),
             new_root,
             tx_ids,
+            inactive_validators,
+            stake_updates,
             &changes,
+            self.clock.now(),
             |msg| local.sign(msg),
         );
         
-        // Store the proposal
+        // Store the proposal, and record its outcome from here on since the
+        // own vote below can already decide it
         self.proposal_store.add(proposal.clone(), changes);
         self.vote_collections
             .write()
             .insert(proposal.id, VoteCollection::new());
+        self.outcomes.lock().insert(proposal.id, ProposalOutcome::Pending(None));
         
         info!(
             "Created proposal {} for version {}",
-            proposal.id, next_version
+            proposal.id.short(), next_version
         );
         
         self.emit_event(ConsensusEvent::ProposalCreated(proposal.id));
@@ -129,19 +281,16 @@ impl RainsonetConsensus {
     }
     
     /// Receive and validate a proposal
-    pub fn receive_proposal(&self, proposal: Proposal, changes: Vec<StateChange>) -> RainsonetResult<()> {
+    pub async fn receive_proposal(&self, proposal: Proposal, changes: Vec<StateChange>) -> RainsonetResult<()> {
         // Validate proposer is a validator
         if !self.validator_set.is_validator(&proposal.proposer) {
             return Err(RainsonetError::NotAValidator);
         }
         
-        // Validate signature
-        let sign_msg = proposal.get_signing_message();
-        self.validator_set
-            .verify_signature(&proposal.proposer, &sign_msg, &proposal.signature)?;
-        
-        // Validate version
-        let expected_version = self.finalized_version.read().next();
+        // Validate version before any expensive checks, so proposals for
+        // versions that can never finalize cost nothing to reject
+        let finalized = *self.finalized_version.read();
+        let expected_version = finalized.next();
         if proposal.state_version != expected_version {
             return Err(RainsonetError::StateVersionMismatch {
                 expected: expected_version.0,
@@ -149,53 +298,137 @@ impl RainsonetConsensus {
             });
         }
         
-        // Store proposal
+        let previous_certificate = self.latest_certificate_hash();
+        if proposal.previous_certificate_hash != previous_certificate {
+            return Err(RainsonetError::ProposalRejected(format!(
+                "Proposal for version {} does not follow certificate {}",
+                proposal.state_version,
+                previous_certificate.short()
+            )));
+        }
+        
+        // A replay must not reset the votes already collected, and an ID
+        // must never stand for two different proposals
+        if let Some(known) = self.proposal_store.get(&proposal.id) {
+            if known == proposal {
+                debug!("Ignoring replayed proposal {}", proposal.id.short());
+                return Ok(());
+            }
+            return Err(RainsonetError::ProposalRejected(format!(
+                "Proposal {} conflicts with a known proposal with the same ID",
+                proposal.id.short()
+            )));
+        }
+        
+        self.check_validator_updates(&proposal)?;
+        
+        if self.proposal_store.count_above(finalized) >= self.config.max_pending_proposals {
+            return Err(RainsonetError::ProposalRejected(
+                "Too many pending proposals".into(),
+            ));
+        }
+        
+        // Validate signature
+        let sign_msg = proposal.get_signing_message();
+        self.validator_set
+            .verify_signature(&proposal.proposer, &sign_msg, &proposal.signature)?;
+        
+        if !proposal.matches_changes(&changes) {
+            return Err(RainsonetError::ProposalRejected(
+                "State changes do not match proposal".into(),
+            ));
+        }
+        
+        // Store proposal, keeping votes collected by a concurrent duplicate
         self.proposal_store.add(proposal.clone(), changes);
         self.vote_collections
             .write()
-            .insert(proposal.id, VoteCollection::new());
+            .entry(proposal.id)
+            .or_default();
         
-        info!("Received proposal {} from {}", proposal.id, proposal.proposer);
+        info!(
+            "Received proposal {} from {}",
+            proposal.id.short(),
+            proposal.proposer.short()
+        );
         
         self.emit_event(ConsensusEvent::ProposalReceived(proposal.id));
         
-        // Auto-vote if we're a validator
-        if self.is_validator() {
-            self.vote_on_proposal(&proposal.id, true)?;
+        // Auto-vote if we're an active validator and have not approved a
+        // competing proposal for this version yet
+        let approved_other = self.approved.read().contains_key(&proposal.state_version);
+        if self.is_active_validator() && !approved_other {
+            self.vote_on_proposal(&proposal.id, true).await?;
+        }
+        
+        Ok(())
+    }
+    
+    /// Check the validator set changes a proposal carries. Deactivations must
+    /// be the ones this node computes from the same finalized history, and
+    /// the proposer may only change its own stake, which its signature on the
+    /// proposal authorizes.
+    pub fn check_validator_updates(&self, proposal: &Proposal) -> RainsonetResult<()> {
+        if proposal.inactive_validators != self.inactive_validators() {
+            return Err(RainsonetError::ProposalRejected(format!(
+                "Proposal {} deactivates validators this node does not consider inactive",
+                proposal.id.short()
+            )));
+        }
+        
+        if proposal.stake_updates.iter().any(|(node_id, _)| *node_id != proposal.proposer) {
+            return Err(RainsonetError::ProposalRejected(format!(
+                "Proposal {} changes the stake of a validator other than its proposer",
+                proposal.id.short()
+            )));
         }
         
         Ok(())
     }
     
     /// Cast a vote on a proposal
-    pub fn vote_on_proposal(&self, proposal_id: &Hash, approve: bool) -> RainsonetResult<Vote> {
-        let local = self
-            .local_validator
-            .as_ref()
-            .ok_or(RainsonetError::NotAValidator)?;
+    pub async fn vote_on_proposal(&self, proposal_id: &Hash, approve: bool) -> RainsonetResult<Vote> {
+        let local = self.active_local_validator()?;
         
         let proposal = self
             .proposal_store
             .get(proposal_id)
             .ok_or(RainsonetError::ProposalRejected("Proposal not found".into()))?;
         
-        let vote = Vote::new(
+        if approve {
+            let mut approved = self.approved.write();
+            match approved.get(&proposal.state_version) {
+                Some(id) if id != proposal_id => {
+                    return Err(RainsonetError::ProposalRejected(format!(
+                        "Already approved proposal {} for version {}",
+                        id.short(),
+                        proposal.state_version
+                    )));
+                }
+                _ => {
+                    approved.insert(proposal.state_version, *proposal_id);
+                }
+            }
+        }
+        
+        let vote = Vote::new_at(
             *proposal_id,
             local.node_id(),
             approve,
             *self.finalized_version.read(),
             *self.finalized_root.read(),
+            self.clock.now(),
             |msg| local.sign(msg),
         );
         
         // Add our own vote
-        self.receive_vote(vote.clone())?;
+        self.receive_vote(vote.clone()).await?;
         
         Ok(vote)
     }
     
     /// Receive and process a vote
-    pub fn receive_vote(&self, vote: Vote) -> RainsonetResult<()> {
+    pub async fn receive_vote(&self, vote: Vote) -> RainsonetResult<()> {
         // Validate voter is a validator
         if !self.validator_set.is_validator(&vote.voter) {
             return Err(RainsonetError::NotAValidator);
@@ -206,21 +439,29 @@ impl RainsonetConsensus {
         self.validator_set
             .verify_signature(&vote.voter, &sign_msg, &vote.signature)?;
         
+        if vote.is_expired_at(self.clock.now(), self.config.vote_timeout_ms) {
+            return Err(RainsonetError::InvalidVote("Vote expired".into()));
+        }
+        
         // Add to collection
         let mut collections = self.vote_collections.write();
         if let Some(collection) = collections.get_mut(&vote.proposal_id) {
             if !collection.add(vote.clone()) {
-                debug!("Duplicate vote from {}", vote.voter);
+                debug!("Duplicate vote from {}", vote.voter.short());
                 return Ok(());
             }
             
             info!(
                 "Vote received for proposal {}: {} from {}",
-                vote.proposal_id,
+                vote.proposal_id.short(),
                 if vote.approve { "approve" } else { "reject" },
-                vote.voter
+                vote.voter.short()
             );
             
+            if let Some(proposal) = self.proposal_store.get(&vote.proposal_id) {
+                self.validator_set.record_vote(&vote.voter, proposal.state_version);
+            }
+            
             self.emit_event(ConsensusEvent::VoteCast(
                 vote.proposal_id,
                 vote.voter,
@@ -228,8 +469,19 @@ impl RainsonetConsensus {
             ));
             
             // Check for consensus
-            let required = self.validator_set.required_votes();
-            let total = self.validator_set.active_count();
+            let (required, total) = match self.validator_set.vote_requirement(&self.config) {
+                VoteRequirement::SingleNode => (1, 1),
+                VoteRequirement::Bft { required, total } => (required, total),
+                VoteRequirement::Insufficient { active, minimum } => {
+                    warn!(
+                        "Not finalizing proposal {}: {} of at least {} validators active",
+                        vote.proposal_id.short(),
+                        active,
+                        minimum
+                    );
+                    return Ok(());
+                }
+            };
             
             if collection.has_consensus(required) {
                 drop(collections);
@@ -238,6 +490,10 @@ impl RainsonetConsensus {
                 self.proposal_store
                     .add_vote(&vote.proposal_id, vote.voter, vote.approve);
                 self.emit_event(ConsensusEvent::ProposalRejected(vote.proposal_id));
+                self.resolve(
+                    &vote.proposal_id,
+                    Err(RainsonetError::ProposalRejected("Rejected by validators".into())),
+                );
             }
         }
         
@@ -251,6 +507,12 @@ impl RainsonetConsensus {
             .get(proposal_id)
             .ok_or(RainsonetError::ProposalRejected("Proposal not found".into()))?;
         
+        // Votes arriving after the quorum, or for a competing proposal at a
+        // finalized version, must not finalize again
+        if proposal.state_version <= *self.finalized_version.read() {
+            return Ok(());
+        }
+        
         let votes: Vec<Vote> = self
             .vote_collections
             .read()
@@ -259,17 +521,27 @@ impl RainsonetConsensus {
             .unwrap_or_default();
         
         // Create finality certificate
-        let certificate = FinalityCertificate::new(
+        let certificate = FinalityCertificate::new_at(
             *proposal_id,
             proposal.state_version,
             proposal.new_root,
             votes,
+            self.clock.now(),
         );
         
-        // Update finalized state
-        *self.finalized_version.write() = proposal.state_version;
-        *self.finalized_root.write() = proposal.new_root;
-        self.certificates.write().push(certificate.clone());
+        // Update finalized state, re-checking under the lock: concurrent
+        // votes can each see the quorum and race to get here
+        {
+            let mut finalized_version = self.finalized_version.write();
+            if proposal.state_version <= *finalized_version {
+                return Ok(());
+            }
+            *finalized_version = proposal.state_version;
+            *self.finalized_root.write() = proposal.new_root;
+            self.certificates.write().push(certificate.clone());
+            self.approved.write().retain(|version, _| *version > proposal.state_version);
+        }
+        self.close_round(&certificate.votes);
         
         info!(
             "State finalized: version {} root {}",
@@ -281,10 +553,222 @@ impl RainsonetConsensus {
             proposal.new_root,
             certificate,
         ));
+        self.resolve(proposal_id, Ok(proposal.state_version));
         
         Ok(())
     }
     
+    /// Count a finalized certificate as a round for inactivity tracking,
+    /// see [`crate::validator::ValidatorSet::record_round`]. Expired proposals are not
+    /// counted, as nothing was finalized for anyone to agree on.
+    fn close_round(&self, votes: &[Vote]) {
+        let voters: HashSet<NodeId> = votes.iter().filter(|v| v.approve).map(|v| v.voter).collect();
+        self.validator_set.record_round(&voters);
+    }
+    
+    /// Validators the next proposal should deactivate for missing
+    /// `inactivity_threshold_rounds` rounds, never taking the active set
+    /// below `min_validators`
+    pub fn inactive_validators(&self) -> Vec<NodeId> {
+        self.validator_set.inactive_validators(
+            self.config.inactivity_threshold_rounds,
+            self.config.min_validators,
+        )
+    }
+    
+    /// Register interest in the outcome of a proposal, which is delivered
+    /// at once if it was already decided
+    fn await_outcome(&self, proposal_id: Hash) -> oneshot::Receiver<RainsonetResult<StateVersion>> {
+        let (tx, rx) = oneshot::channel();
+        let mut outcomes = self.outcomes.lock();
+        match outcomes.remove(&proposal_id) {
+            Some(ProposalOutcome::Decided(outcome)) => {
+                let _ = tx.send(outcome);
+            }
+            _ => {
+                outcomes.insert(proposal_id, ProposalOutcome::Pending(Some(tx)));
+            }
+        }
+        rx
+    }
+    
+    /// Deliver the outcome of a proposal created here to its waiter, or
+    /// keep it for `await_outcome` if nobody waits yet
+    fn resolve(&self, proposal_id: &Hash, outcome: RainsonetResult<StateVersion>) {
+        let mut outcomes = self.outcomes.lock();
+        match outcomes.remove(proposal_id) {
+            Some(ProposalOutcome::Pending(Some(tx))) => {
+                let _ = tx.send(outcome);
+            }
+            Some(ProposalOutcome::Pending(None)) => {
+                outcomes.insert(*proposal_id, ProposalOutcome::Decided(outcome));
+            }
+            // Decided once already, or not created here
+            Some(decided) => {
+                outcomes.insert(*proposal_id, decided);
+            }
+            None => {}
+        }
+    }
+    
+    /// Import a finality certificate received during sync.
+    ///
+    /// The certificate must carry a quorum of valid approving votes for
+    /// `proposal`, and must extend the local chain: its version is exactly
+    /// one past the latest finalized version, the proposal's previous root
+    /// is the latest finalized root, and `finalized_at` does not go back in
+    /// time. Replayed or out-of-order certificates are rejected.
+    pub fn import_certificate(
+        &self,
+        proposal: &Proposal,
+        certificate: FinalityCertificate,
+    ) -> RainsonetResult<()> {
+        if certificate.proposal_id != proposal.id
+            || certificate.state_version != proposal.state_version
+            || certificate.state_root != proposal.new_root
+        {
+            return Err(RainsonetError::ProposalRejected(
+                "Certificate does not match proposal".into(),
+            ));
+        }
+        
+        // Ordering against the local chain
+        let expected_version = self.finalized_version.read().next();
+        if certificate.state_version != expected_version {
+            return Err(RainsonetError::StateVersionMismatch {
+                expected: expected_version.0,
+                got: certificate.state_version.0,
+            });
+        }
+        
+        let finalized_root = *self.finalized_root.read();
+        if proposal.previous_root != finalized_root {
+            return Err(RainsonetError::ProposalRejected(format!(
+                "Certificate for version {} does not extend root {}",
+                certificate.state_version, finalized_root
+            )));
+        }
+        
+        let previous_certificate = self.latest_certificate_hash();
+        if proposal.previous_certificate_hash != previous_certificate {
+            return Err(RainsonetError::ProposalRejected(format!(
+                "Certificate for version {} does not follow certificate {}",
+                certificate.state_version,
+                previous_certificate.short()
+            )));
+        }
+        
+        if let Some(last) = self.certificates.read().last() {
+            if certificate.finalized_at < last.finalized_at {
+                return Err(RainsonetError::ProposalRejected(format!(
+                    "Certificate for version {} finalized before version {}",
+                    certificate.state_version, last.state_version
+                )));
+            }
+        }
+        
+        // Signatures and quorum
+        self.validator_set.verify_signature(
+            &proposal.proposer,
+            &proposal.get_signing_message(),
+            &proposal.signature,
+        )?;
+        certificate.verify_full(&self.validator_set)?;
+        
+        *self.finalized_version.write() = certificate.state_version;
+        *self.finalized_root.write() = certificate.state_root;
+        self.certificates.write().push(certificate.clone());
+        self.close_round(&certificate.votes);
+        
+        debug!("Imported certificate for version {}", certificate.state_version);
+        
+        self.emit_event(ConsensusEvent::StateFinalized(
+            certificate.state_version,
+            certificate.state_root,
+            certificate,
+        ));
+        
+        Ok(())
+    }
+    
+    /// Adopt a gossiped certificate for the next version, finalizing it
+    /// without having seen its votes individually.
+    ///
+    /// Returns `Ok(false)` for versions that are already finalized. Callers
+    /// holding state must apply the certified changes before calling this,
+    /// checking the certificate first with [`Self::verify_certificate`], so
+    /// finality never runs ahead of the state.
+    pub fn receive_certificate(&self, certificate: FinalityCertificate) -> RainsonetResult<bool> {
+        if !self.verify_certificate(&certificate)? {
+            return Ok(false);
+        }
+        
+        let proposal_id = certificate.proposal_id;
+        *self.finalized_version.write() = certificate.state_version;
+        *self.finalized_root.write() = certificate.state_root;
+        self.certificates.write().push(certificate.clone());
+        self.close_round(&certificate.votes);
+        
+        info!("Adopted certificate for version {}", certificate.state_version);
+        
+        self.emit_event(ConsensusEvent::StateFinalized(
+            certificate.state_version,
+            certificate.state_root,
+            certificate.clone(),
+        ));
+        self.resolve(&proposal_id, Ok(certificate.state_version));
+        
+        Ok(true)
+    }
+    
+    /// Check a gossiped certificate without adopting it.
+    ///
+    /// Returns `Ok(false)` for versions that are already finalized. Approving
+    /// voters sign the state they voted from, which must be the local
+    /// finalized state, so a certificate that does not extend the local
+    /// chain is rejected.
+    pub fn verify_certificate(&self, certificate: &FinalityCertificate) -> RainsonetResult<bool> {
+        let finalized_version = *self.finalized_version.read();
+        if certificate.state_version <= finalized_version {
+            return Ok(false);
+        }
+        
+        let expected_version = finalized_version.next();
+        if certificate.state_version != expected_version {
+            return Err(RainsonetError::StateVersionMismatch {
+                expected: expected_version.0,
+                got: certificate.state_version.0,
+            });
+        }
+        
+        certificate.verify_full(&self.validator_set)?;
+        
+        let finalized_root = *self.finalized_root.read();
+        let extends_local = certificate
+            .votes
+            .iter()
+            .filter(|v| v.approve)
+            .all(|v| v.state_version == finalized_version && v.state_root == finalized_root);
+        if !extends_local {
+            return Err(RainsonetError::ProposalRejected(format!(
+                "Certificate for version {} does not extend root {}",
+                certificate.state_version, finalized_root
+            )));
+        }
+        
+        if let Some(proposal) = self.proposal_store.get(&certificate.proposal_id) {
+            if proposal.state_version != certificate.state_version
+                || proposal.new_root != certificate.state_root
+            {
+                return Err(RainsonetError::ProposalRejected(
+                    "Certificate does not match proposal".into(),
+                ));
+            }
+        }
+        
+        Ok(true)
+    }
+    
     /// Get the latest finalized version
     pub fn latest_finalized_version(&self) -> StateVersion {
         *self.finalized_version.read()
@@ -295,6 +779,24 @@ impl RainsonetConsensus {
         *self.finalized_root.read()
     }
     
+    /// Seed the finalized root with the genesis state root, which the first
+    /// proposal extends and its approving votes are cast from. Ignored once
+    /// a version is finalized.
+    pub fn set_genesis_root(&self, root: StateRoot) {
+        if self.finalized_version.read().0 == 0 {
+            *self.finalized_root.write() = root;
+        }
+    }
+    
+    /// Hash of the certificate for the latest finalized version, which the
+    /// next proposal must reference; zero before anything is finalized
+    pub fn latest_certificate_hash(&self) -> Hash {
+        self.certificates
+            .read()
+            .last()
+            .map_or(Hash::ZERO, FinalityCertificate::hash)
+    }
+    
     /// Get a finality certificate
     pub fn get_certificate(&self, version: StateVersion) -> Option<FinalityCertificate> {
         self.certificates
@@ -304,6 +806,121 @@ impl RainsonetConsensus {
             .cloned()
     }
     
+    /// Votes collected so far for a proposal
+    pub fn collected_votes(&self, proposal_id: &Hash) -> Vec<Vote> {
+        self.vote_collections
+            .read()
+            .get(proposal_id)
+            .map(|c| c.votes.clone())
+            .unwrap_or_default()
+    }
+    
+    /// Vote collections for proposals at the next version that have not
+    /// finalized yet, to save across a restart
+    pub fn pending_vote_collections(&self) -> Vec<PendingVoteCollection> {
+        let next = self.finalized_version.read().next();
+        self.vote_collections
+            .read()
+            .iter()
+            .filter(|(id, collection)| {
+                collection.total() > 0
+                    && self.proposal_store.status(id) == Some(ProposalStatus::Pending)
+            })
+            .filter_map(|(id, collection)| {
+                let proposal = self.proposal_store.get(id)?;
+                let changes = self.proposal_store.get_changes(id)?;
+                (proposal.state_version == next).then(|| PendingVoteCollection {
+                    proposal,
+                    changes,
+                    votes: collection.votes.clone(),
+                })
+            })
+            .collect()
+    }
+    
+    /// Rehydrate vote collections saved by [`Self::pending_vote_collections`]
+    /// before a restart, returning how many were restored.
+    ///
+    /// Collections are dropped whose proposal no longer follows the finalized
+    /// state, fails verification or is older than
+    /// `vote_collection_deadline_ms`. Each vote's signature is checked again,
+    /// but not its age: it was current when it was collected. A restored
+    /// collection that now has a quorum finalizes at once.
+    pub fn restore_vote_collections(&self, saved: Vec<PendingVoteCollection>) -> usize {
+        let now = self.clock.now();
+        let mut restored = 0;
+        
+        for PendingVoteCollection { proposal, changes, votes } in saved {
+            let id = proposal.id;
+            if proposal.is_expired_at(now, self.config.vote_collection_deadline_ms) {
+                debug!("Discarding votes for proposal {} past the deadline", id.short());
+                continue;
+            }
+            if proposal.state_version != self.finalized_version.read().next()
+                || proposal.previous_certificate_hash != self.latest_certificate_hash()
+                || self.proposal_store.get(&id).is_some()
+            {
+                debug!("Discarding votes for stale proposal {}", id.short());
+                continue;
+            }
+            let verified = self.validator_set.is_validator(&proposal.proposer)
+                && self
+                    .validator_set
+                    .verify_signature(
+                        &proposal.proposer,
+                        &proposal.get_signing_message(),
+                        &proposal.signature,
+                    )
+                    .is_ok()
+                && proposal.matches_changes(&changes);
+            if !verified {
+                warn!("Discarding votes for unverifiable proposal {}", id.short());
+                continue;
+            }
+            
+            let mut collection = VoteCollection::new();
+            for vote in votes {
+                let valid = vote.proposal_id == id
+                    && self.validator_set.is_validator(&vote.voter)
+                    && self
+                        .validator_set
+                        .verify_signature(&vote.voter, &vote.get_signing_message(), &vote.signature)
+                        .is_ok();
+                if valid {
+                    collection.add(vote);
+                }
+            }
+            
+            info!(
+                "Restored {} votes for proposal {} at version {}",
+                collection.total(),
+                id.short(),
+                proposal.state_version
+            );
+            let has_quorum = match self.validator_set.vote_requirement(&self.config) {
+                VoteRequirement::SingleNode => collection.has_consensus(1),
+                VoteRequirement::Bft { required, .. } => collection.has_consensus(required),
+                VoteRequirement::Insufficient { .. } => false,
+            };
+            self.proposal_store.add(proposal, changes);
+            self.vote_collections.write().insert(id, collection);
+            restored += 1;
+            
+            if has_quorum {
+                if let Err(e) = self.finalize_proposal(&id) {
+                    warn!("Failed to finalize restored proposal {}: {}", id.short(), e);
+                }
+            }
+        }
+        
+        restored
+    }
+    
+    /// State changes of a known proposal, whatever its status
+    pub fn proposal_changes(&self, proposal_id: &Hash) -> Option<Vec<StateChange>> {
+        self.proposal_store.get_changes(proposal_id)
+    }
+    
     /// Get state changes for an approved proposal
     pub fn get_finalized_changes(&self, proposal_id: &Hash) -> Option<Vec<StateChange>> {
         self.proposal_store.get_approved_changes(proposal_id)
@@ -315,12 +932,38 @@ impl RainsonetConsensus {
         }
     }
     
+    /// Expire pending proposals that outlived the proposal timeout
+    pub fn expire_proposals(&self) -> Vec<Hash> {
+        let expired = self.proposal_store.expire_pending(
+            self.clock.now(),
+            self.config.proposal_timeout_ms,
+            *self.finalized_version.read(),
+        );
+        
+        for id in &expired {
+            self.vote_collections.write().remove(id);
+            warn!("Proposal {} expired", id.short());
+            self.emit_event(ConsensusEvent::ProposalExpired(*id));
+            self.resolve(id, Err(RainsonetError::Timeout(format!("Proposal {} expired", id))));
+        }
+        
+        expired
+    }
+    
     /// Cleanup old proposals
     pub fn cleanup(&self) {
         let finalized = *self.finalized_version.read();
         if finalized.0 > 10 {
-            self.proposal_store
+            let removed = self
+                .proposal_store
                 .cleanup(StateVersion::new(finalized.0 - 10));
+            
+            let mut collections = self.vote_collections.write();
+            let mut outcomes = self.outcomes.lock();
+            for id in &removed {
+                collections.remove(id);
+                outcomes.remove(id);
+            }
         }
     }
 }
@@ -332,10 +975,22 @@ impl ConsensusEngineTrait for RainsonetConsensus {
         // Note: In production, we'd compute the new root from changes
         let new_root = rainsonet_crypto::hashing::hash(&bincode::serialize(&changes)?);
         
-        let proposal = self.create_proposal(current_root, new_root, vec![], changes)?;
+        let proposal = self.create_proposal(current_root, new_root, vec![], changes).await?;
+        let outcome = self.await_outcome(proposal.id);
+        self.vote_on_proposal(&proposal.id, true).await?;
         
-        // Wait for finalization (simplified)
-        Ok(proposal.state_version)
+        let timeout = std::time::Duration::from_millis(self.config.proposal_timeout_ms);
+        match tokio::time::timeout(timeout, outcome).await {
+            Ok(Ok(result)) => result,
+            Ok(Err(_)) => Err(RainsonetError::Internal("Proposal outcome dropped".into())),
+            Err(_) => {
+                self.outcomes.lock().remove(&proposal.id);
+                Err(RainsonetError::Timeout(format!(
+                    "Proposal {} not finalized within {}ms",
+                    proposal.id, self.config.proposal_timeout_ms
+                )))
+            }
+        }
     }
     
     async fn vote(&self, vote: rainsonet_core::Vote) -> RainsonetResult<()> {
@@ -347,7 +1002,7 @@ impl ConsensusEngineTrait for RainsonetConsensus {
             vote.state_root,
             |_| vote.signature,
         );
-        self.receive_vote(v)
+        self.receive_vote(v).await
     }
     
     async fn is_finalized(&self, version: StateVersion) -> bool {
@@ -370,7 +1025,10 @@ pub fn create_consensus_channel() -> (mpsc::Sender<ConsensusEvent>, mpsc::Receiv
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::validator::ValidatorInfo;
+    use crate::validator::{ValidatorInfo, ValidatorSet};
+    use rainsonet_core::{TimeSource, Timestamp};
+    use rainsonet_crypto::signing::sign;
+    use rainsonet_p2p::ProposalMessage;
     
     fn setup_validators(count: usize) -> (Vec<KeyPair>, SharedValidatorSet) {
         let keypairs: Vec<KeyPair> = (0..count).map(|_| KeyPair::generate()).collect();
@@ -385,16 +1043,17 @@ mod tests {
         (keypairs, set)
     }
     
-    #[test]
-    fn test_proposal_creation() {
+    #[tokio::test]
+    async fn test_proposal_creation() {
         let (keypairs, validator_set) = setup_validators(3);
         
         let config = ConsensusConfig {
             is_validator: true,
-            ..Default::default()
+            ..ConsensusConfig::devnet()
         };
         
-        let consensus = RainsonetConsensus::new(config, validator_set, Some(keypairs[0].clone()));
+        let consensus =
+            RainsonetConsensus::new(config, validator_set, Some(keypairs[0].clone())).unwrap();
         
         let changes = vec![StateChange::Set {
             key: b"key".to_vec(),
@@ -403,13 +1062,14 @@ mod tests {
         
         let proposal = consensus
             .create_proposal(Hash::ZERO, Hash::from_bytes([1u8; 32]), vec![], changes)
+            .await
             .unwrap();
         
         assert_eq!(proposal.state_version.0, 1);
     }
     
-    #[test]
-    fn test_consensus_flow() {
+    #[tokio::test]
+    async fn test_consensus_flow() {
         let (keypairs, validator_set) = setup_validators(3);
         
         // Create consensus engines for each validator
@@ -418,9 +1078,9 @@ mod tests {
             .map(|kp| {
                 let config = ConsensusConfig {
                     is_validator: true,
-                    ..Default::default()
+                    ..ConsensusConfig::devnet()
                 };
-                RainsonetConsensus::new(config, validator_set.clone(), Some(kp.clone()))
+                RainsonetConsensus::new(config, validator_set.clone(), Some(kp.clone())).unwrap()
             })
             .collect();
         
@@ -432,21 +1092,924 @@ mod tests {
         
         let proposal = engines[0]
             .create_proposal(Hash::ZERO, Hash::from_bytes([1u8; 32]), vec![], changes.clone())
+            .await
             .unwrap();
+        engines[0].vote_on_proposal(&proposal.id, true).await.unwrap();
         
         // Other validators receive and vote
         for engine in &engines[1..] {
-            engine.receive_proposal(proposal.clone(), changes.clone()).unwrap();
+            engine.receive_proposal(proposal.clone(), changes.clone()).await.unwrap();
         }
         
         // Manually process votes (simulating network)
-        let vote1 = engines[1].vote_on_proposal(&proposal.id, true).unwrap();
-        engines[0].receive_vote(vote1).unwrap();
+        let vote1 = engines[1].vote_on_proposal(&proposal.id, true).await.unwrap();
+        engines[0].receive_vote(vote1).await.unwrap();
         
-        let vote2 = engines[2].vote_on_proposal(&proposal.id, true).unwrap();
-        engines[0].receive_vote(vote2).unwrap();
+        let vote2 = engines[2].vote_on_proposal(&proposal.id, true).await.unwrap();
+        engines[0].receive_vote(vote2).await.unwrap();
         
-        // Check finalization
+        // Proposer's own vote plus two others meets the 3-of-3 quorum
         assert_eq!(engines[0].latest_finalized_version().0, 1);
     }
+    
+    #[tokio::test]
+    async fn test_replayed_proposal_keeps_votes() {
+        let (keypairs, validator_set) = setup_validators(3);
+        let engines: Vec<RainsonetConsensus> = keypairs
+            .iter()
+            .map(|kp| {
+                let config = ConsensusConfig {
+                    is_validator: true,
+                    ..ConsensusConfig::devnet()
+                };
+                RainsonetConsensus::new(config, validator_set.clone(), Some(kp.clone())).unwrap()
+            })
+            .collect();
+        
+        let proposal = engines[0]
+            .create_proposal(Hash::ZERO, Hash::from_bytes([1u8; 32]), vec![], vec![])
+            .await
+            .unwrap();
+        engines[1].receive_proposal(proposal.clone(), vec![]).await.unwrap();
+        engines[1]
+            .receive_vote(external_vote(&keypairs[2], proposal.id, true))
+            .await
+            .unwrap();
+        assert_eq!(engines[1].collected_votes(&proposal.id).len(), 2);
+        
+        // An identical replay is a no-op
+        engines[1].receive_proposal(proposal.clone(), vec![]).await.unwrap();
+        assert_eq!(engines[1].collected_votes(&proposal.id).len(), 2);
+        
+        // Transaction IDs are bound into the ID and signature, so content
+        // swapped under a known ID is refused both on the wire and here
+        let mut conflicting = proposal.clone();
+        conflicting.tx_ids = vec![Hash::from_bytes([7u8; 32])];
+        assert!(Proposal::try_from(ProposalMessage::from(&conflicting)).is_err());
+        let result = engines[1].receive_proposal(conflicting, vec![]).await;
+        assert!(matches!(result, Err(RainsonetError::ProposalRejected(_))));
+        assert_eq!(engines[1].proposal_store.get(&proposal.id), Some(proposal.clone()));
+        assert_eq!(engines[1].collected_votes(&proposal.id).len(), 2);
+    }
+    
+    #[tokio::test]
+    async fn test_competing_proposals_approve_only_first() {
+        let (keypairs, validator_set) = setup_validators(3);
+        let engines: Vec<RainsonetConsensus> = keypairs
+            .iter()
+            .map(|kp| {
+                let config = ConsensusConfig {
+                    is_validator: true,
+                    ..ConsensusConfig::devnet()
+                };
+                RainsonetConsensus::new(config, validator_set.clone(), Some(kp.clone())).unwrap()
+            })
+            .collect();
+        
+        let mut competing = Vec::new();
+        for (engine, root) in engines[..2].iter().zip([[1u8; 32], [2u8; 32]]) {
+            let proposal = engine
+                .create_proposal(Hash::ZERO, Hash::from_bytes(root), vec![], vec![])
+                .await
+                .unwrap();
+            competing.push(proposal);
+        }
+        let (first, second) = (&competing[0], &competing[1]);
+        
+        // Once one is approved the other gets no approval, not even explicitly
+        engines[2].receive_proposal(first.clone(), vec![]).await.unwrap();
+        engines[2].receive_proposal(second.clone(), vec![]).await.unwrap();
+        assert_eq!(engines[2].collected_votes(&first.id).len(), 1);
+        assert!(engines[2].collected_votes(&second.id).is_empty());
+        assert!(matches!(
+            engines[2].vote_on_proposal(&second.id, true).await,
+            Err(RainsonetError::ProposalRejected(_))
+        ));
+        engines[2].vote_on_proposal(&second.id, false).await.unwrap();
+    }
+    
+    #[tokio::test]
+    async fn test_removed_validator_stops_proposing_until_readded() {
+        let (keypairs, validator_set) = setup_validators(2);
+        let config = ConsensusConfig {
+            is_validator: true,
+            ..ConsensusConfig::devnet()
+        };
+        let mut consensus =
+            RainsonetConsensus::new(config, validator_set.clone(), Some(keypairs[0].clone())).unwrap();
+        let (tx, mut events) = create_consensus_channel();
+        consensus.set_event_channel(tx);
+        let node_id = keypairs[0].node_id();
+        
+        validator_set.remove_validator(&node_id);
+        let result = consensus.create_proposal(Hash::ZERO, Hash::from_bytes([1u8; 32]), vec![], vec![]).await;
+        assert!(matches!(result, Err(RainsonetError::NotAValidator)));
+        assert!(matches!(
+            events.try_recv(),
+            Ok(ConsensusEvent::LocalValidatorDeactivated(id)) if id == node_id
+        ));
+        assert!(consensus.proposal_store.is_empty());
+        
+        // Repeated attempts short-circuit without emitting again
+        assert!(!consensus.is_active_validator());
+        assert!(events.try_recv().is_err());
+        
+        validator_set.add_validator(ValidatorInfo::new(node_id, keypairs[0].public_key(), 1000));
+        let proposal = consensus
+            .create_proposal(Hash::ZERO, Hash::from_bytes([1u8; 32]), vec![], vec![])
+            .await
+            .unwrap();
+        assert!(matches!(
+            events.try_recv(),
+            Ok(ConsensusEvent::LocalValidatorReactivated(id)) if id == node_id
+        ));
+        assert!(consensus.proposal_store.get(&proposal.id).is_some());
+    }
+    
+    #[test]
+    fn test_validator_engine_requires_keypair() {
+        let (_, validator_set) = setup_validators(1);
+        let config = ConsensusConfig {
+            is_validator: true,
+            ..ConsensusConfig::devnet()
+        };
+        
+        let result = RainsonetConsensus::new(config, validator_set.clone(), None);
+        assert!(matches!(result, Err(RainsonetError::ConfigError(_))));
+        
+        // Observers need no keypair
+        let observer = RainsonetConsensus::new(ConsensusConfig::devnet(), validator_set, None).unwrap();
+        assert!(!observer.is_validator());
+    }
+    
+    #[tokio::test]
+    async fn test_propose_with_empty_validator_set_fails() {
+        let config = ConsensusConfig {
+            is_validator: true,
+            ..ConsensusConfig::devnet()
+        };
+        let consensus =
+            RainsonetConsensus::new(config, Arc::new(ValidatorSet::new()), Some(KeyPair::generate()))
+                .unwrap();
+        
+        let result = consensus.create_proposal(Hash::ZERO, Hash::from_bytes([1u8; 32]), vec![], vec![]).await;
+        assert!(matches!(
+            result,
+            Err(RainsonetError::InsufficientValidators { active: 0, .. })
+        ));
+        assert_eq!(consensus.latest_finalized_version().0, 0);
+    }
+    
+    #[tokio::test]
+    async fn test_single_validator_finalizes_own_proposal() {
+        let (keypairs, validator_set) = setup_validators(1);
+        let config = ConsensusConfig {
+            is_validator: true,
+            ..ConsensusConfig::devnet()
+        };
+        let consensus =
+            RainsonetConsensus::new(config, validator_set, Some(keypairs[0].clone())).unwrap();
+        
+        let proposal = consensus
+            .create_proposal(Hash::ZERO, Hash::from_bytes([1u8; 32]), vec![], vec![])
+            .await
+            .unwrap();
+        assert_eq!(consensus.latest_finalized_version().0, 0);
+        
+        consensus.vote_on_proposal(&proposal.id, true).await.unwrap();
+        assert_eq!(consensus.latest_finalized_version().0, 1);
+    }
+    
+    /// Finalize a chain of proposals on a single validator, returning each
+    /// proposal with its certificate. `roots[i]` is the root after version `i + 1`.
+    async fn certified_chain(
+        consensus: &RainsonetConsensus,
+        previous_roots: &[StateRoot],
+        roots: &[StateRoot],
+    ) -> Vec<(Proposal, FinalityCertificate)> {
+        let mut chain = Vec::new();
+        for (previous, root) in previous_roots.iter().zip(roots) {
+            let proposal = consensus
+                .create_proposal(*previous, *root, vec![], vec![])
+                .await
+                .unwrap();
+            consensus.vote_on_proposal(&proposal.id, true).await.unwrap();
+            let certificate = consensus.get_certificate(proposal.state_version).unwrap();
+            chain.push((proposal, certificate));
+        }
+        chain
+    }
+    
+    fn setup_sync_pair() -> (RainsonetConsensus, RainsonetConsensus) {
+        let (keypairs, validator_set) = setup_validators(1);
+        let producer = RainsonetConsensus::new(
+            ConsensusConfig {
+                is_validator: true,
+                ..ConsensusConfig::devnet()
+            },
+            validator_set.clone(),
+            Some(keypairs[0].clone()),
+        ).unwrap();
+        let follower =
+            RainsonetConsensus::new(ConsensusConfig::devnet(), validator_set, None).unwrap();
+        (producer, follower)
+    }
+    
+    #[tokio::test]
+    async fn test_import_contiguous_certificates() {
+        let (producer, follower) = setup_sync_pair();
+        let r1 = Hash::from_bytes([1u8; 32]);
+        let r2 = Hash::from_bytes([2u8; 32]);
+        
+        let chain = certified_chain(&producer, &[Hash::ZERO, r1], &[r1, r2]).await;
+        for (proposal, certificate) in chain {
+            follower.import_certificate(&proposal, certificate).unwrap();
+        }
+        
+        assert_eq!(follower.latest_finalized_version().0, 2);
+        assert_eq!(follower.latest_finalized_root(), r2);
+    }
+    
+    #[tokio::test]
+    async fn test_import_rejects_skipped_version() {
+        let (producer, follower) = setup_sync_pair();
+        let r1 = Hash::from_bytes([1u8; 32]);
+        let r2 = Hash::from_bytes([2u8; 32]);
+        let chain = certified_chain(&producer, &[Hash::ZERO, r1], &[r1, r2]).await;
+        
+        let (proposal, certificate) = chain[1].clone();
+        let result = follower.import_certificate(&proposal, certificate);
+        assert!(matches!(
+            result,
+            Err(RainsonetError::StateVersionMismatch { expected: 1, got: 2 })
+        ));
+        
+        // Replaying an already imported certificate is rejected the same way
+        let (proposal, certificate) = chain[0].clone();
+        follower.import_certificate(&proposal, certificate.clone()).unwrap();
+        assert!(follower.import_certificate(&proposal, certificate).is_err());
+        assert_eq!(follower.latest_finalized_version().0, 1);
+    }
+    
+    #[tokio::test]
+    async fn test_import_rejects_non_contiguous_root() {
+        let (producer, follower) = setup_sync_pair();
+        let r1 = Hash::from_bytes([1u8; 32]);
+        let r2 = Hash::from_bytes([2u8; 32]);
+        
+        // Version 2 is validly signed but claims to build on the genesis root
+        let chain = certified_chain(&producer, &[Hash::ZERO, Hash::ZERO], &[r1, r2]).await;
+        
+        let (proposal, certificate) = chain[0].clone();
+        follower.import_certificate(&proposal, certificate).unwrap();
+        let (proposal, certificate) = chain[1].clone();
+        let result = follower.import_certificate(&proposal, certificate);
+        assert!(matches!(result, Err(RainsonetError::ProposalRejected(_))));
+        assert_eq!(follower.latest_finalized_root(), r1);
+    }
+    
+    #[tokio::test]
+    async fn test_import_rejects_regressing_finalized_at() {
+        let (producer, follower) = setup_sync_pair();
+        let r1 = Hash::from_bytes([1u8; 32]);
+        let r2 = Hash::from_bytes([2u8; 32]);
+        let chain = certified_chain(&producer, &[Hash::ZERO, r1], &[r1, r2]).await;
+        
+        let (proposal, certificate) = chain[0].clone();
+        let first_finalized_at = certificate.finalized_at;
+        follower.import_certificate(&proposal, certificate).unwrap();
+        
+        let (proposal, mut certificate) = chain[1].clone();
+        certificate.finalized_at = Timestamp::from_millis(first_finalized_at.0 - 1);
+        let result = follower.import_certificate(&proposal, certificate);
+        assert!(matches!(result, Err(RainsonetError::ProposalRejected(_))));
+        assert_eq!(follower.latest_finalized_version().0, 1);
+    }
+    
+    #[tokio::test]
+    async fn test_certificate_hashes_link_finalized_versions() {
+        let (producer, follower) = setup_sync_pair();
+        let roots: Vec<StateRoot> = (1..=3).map(|i| Hash::from_bytes([i; 32])).collect();
+        let chain = certified_chain(&producer, &[Hash::ZERO, roots[0], roots[1]], &roots).await;
+        
+        assert_eq!(chain[0].0.previous_certificate_hash, Hash::ZERO);
+        for pair in chain.windows(2) {
+            assert_eq!(pair[1].0.previous_certificate_hash, pair[0].1.hash());
+        }
+        
+        // A follower importing the chain ends up on the same certificate
+        for (proposal, certificate) in chain.iter().cloned() {
+            follower.import_certificate(&proposal, certificate).unwrap();
+        }
+        assert_eq!(follower.latest_certificate_hash(), chain[2].1.hash());
+        assert_eq!(producer.latest_certificate_hash(), chain[2].1.hash());
+    }
+    
+    #[tokio::test]
+    async fn test_proposal_with_wrong_previous_certificate_rejected() {
+        let (keypairs, validator_set) = setup_validators(1);
+        let config = ConsensusConfig {
+            is_validator: true,
+            ..ConsensusConfig::devnet()
+        };
+        let producer =
+            RainsonetConsensus::new(config, validator_set.clone(), Some(keypairs[0].clone()))
+                .unwrap();
+        let follower =
+            RainsonetConsensus::new(ConsensusConfig::devnet(), validator_set, None).unwrap();
+        let r1 = Hash::from_bytes([1u8; 32]);
+        let r2 = Hash::from_bytes([2u8; 32]);
+        
+        let (proposal, certificate) =
+            certified_chain(&producer, &[Hash::ZERO], &[r1]).await.remove(0);
+        follower.import_certificate(&proposal, certificate).unwrap();
+        
+        let proposal_after = |previous_certificate_hash: Hash| {
+            Proposal::new(
+                keypairs[0].node_id(),
+                StateVersion::new(2),
+                r1,
+                previous_certificate_hash,
+                r2,
+                vec![],
+                &[],
+                |msg| sign(&keypairs[0], msg),
+            )
+        };
+        
+        let result = follower.receive_proposal(proposal_after(Hash::from_bytes([9u8; 32])), vec![]).await;
+        assert!(matches!(result, Err(RainsonetError::ProposalRejected(_))));
+        assert!(follower.proposal_store.is_empty());
+        
+        follower
+            .receive_proposal(proposal_after(follower.latest_certificate_hash()), vec![])
+            .await
+            .unwrap();
+    }
+    
+    #[tokio::test]
+    async fn test_receive_gossiped_certificate() {
+        let (producer, follower) = setup_sync_pair();
+        let r1 = Hash::from_bytes([1u8; 32]);
+        let (_, certificate) = certified_chain(&producer, &[Hash::ZERO], &[r1]).await.remove(0);
+        
+        // Round-trip through the wire format
+        let bytes = rainsonet_p2p::Message::Certificate((&certificate).into()).to_bytes();
+        let received = match rainsonet_p2p::Message::from_bytes(&bytes) {
+            Some(rainsonet_p2p::Message::Certificate(msg)) => FinalityCertificate::from(msg),
+            other => panic!("unexpected message: {:?}", other),
+        };
+        
+        assert!(follower.receive_certificate(received.clone()).unwrap());
+        assert_eq!(follower.latest_finalized_version().0, 1);
+        assert_eq!(follower.latest_finalized_root(), r1);
+        assert!(follower.get_certificate(StateVersion(1)).is_some());
+        
+        // Duplicates are ignored
+        assert!(!follower.receive_certificate(received).unwrap());
+    }
+    
+    #[tokio::test]
+    async fn test_receive_certificate_rejects_skipped_version() {
+        let (producer, follower) = setup_sync_pair();
+        let r1 = Hash::from_bytes([1u8; 32]);
+        let r2 = Hash::from_bytes([2u8; 32]);
+        let chain = certified_chain(&producer, &[Hash::ZERO, r1], &[r1, r2]).await;
+        
+        let result = follower.receive_certificate(chain[1].1.clone());
+        assert!(matches!(
+            result,
+            Err(RainsonetError::StateVersionMismatch { expected: 1, got: 2 })
+        ));
+        assert_eq!(follower.latest_finalized_version().0, 0);
+    }
+    
+    #[tokio::test]
+    async fn test_receive_certificate_rejects_forged_vote() {
+        let (producer, follower) = setup_sync_pair();
+        let r1 = Hash::from_bytes([1u8; 32]);
+        let (_, mut certificate) =
+            certified_chain(&producer, &[Hash::ZERO], &[r1]).await.remove(0);
+        
+        certificate.votes[0].state_root = Hash::from_bytes([9u8; 32]);
+        assert!(follower.receive_certificate(certificate).is_err());
+        assert_eq!(follower.latest_finalized_version().0, 0);
+    }
+    
+    fn mock_clock() -> Arc<rainsonet_core::MockTimeSource> {
+        Arc::new(rainsonet_core::MockTimeSource::new(Timestamp::from_millis(1_700_000_000_000)))
+    }
+    
+    #[tokio::test]
+    async fn test_proposal_expires_after_timeout() {
+        let (keypairs, validator_set) = setup_validators(3);
+        let config = ConsensusConfig {
+            is_validator: true,
+            ..ConsensusConfig::devnet()
+        };
+        let timeout = config.proposal_timeout_ms;
+        let clock = mock_clock();
+        let mut consensus =
+            RainsonetConsensus::new(config, validator_set, Some(keypairs[0].clone())).unwrap();
+        consensus.set_time_source(clock.clone());
+        
+        let proposal = consensus
+            .create_proposal(Hash::ZERO, Hash::from_bytes([1u8; 32]), vec![], vec![])
+            .await
+            .unwrap();
+        assert_eq!(proposal.timestamp, clock.now());
+        
+        clock.advance(std::time::Duration::from_millis(timeout));
+        assert!(consensus.expire_proposals().is_empty());
+        
+        clock.advance(std::time::Duration::from_millis(1));
+        assert_eq!(consensus.expire_proposals(), vec![proposal.id]);
+        assert_eq!(
+            consensus.proposal_store.status(&proposal.id),
+            Some(ProposalStatus::Expired)
+        );
+        
+        // Already expired proposals are not reported again
+        assert!(consensus.expire_proposals().is_empty());
+    }
+    
+    #[tokio::test]
+    async fn test_silent_validator_deactivated_lowers_quorum() {
+        let (keypairs, validator_set) = setup_validators(4);
+        let config = ConsensusConfig {
+            is_validator: true,
+            inactivity_threshold_rounds: 2,
+            ..ConsensusConfig::devnet()
+        };
+        let timeout = std::time::Duration::from_millis(config.proposal_timeout_ms + 1);
+        let clock = mock_clock();
+        let mut consensus =
+            RainsonetConsensus::new(config, validator_set.clone(), Some(keypairs[0].clone())).unwrap();
+        consensus.set_time_source(clock.clone());
+        let silent = keypairs[3].node_id();
+        let vote_from = |voter: &KeyPair, proposal: &Proposal, root: StateRoot| {
+            Vote::new_at(
+                proposal.id,
+                voter.node_id(),
+                true,
+                StateVersion::new(proposal.state_version.0 - 1),
+                root,
+                clock.now(),
+                |msg| sign(voter, msg),
+            )
+        };
+        
+        // An expired proposal finalizes nothing and counts no round
+        let proposal = consensus
+            .create_proposal(Hash::ZERO, Hash::from_bytes([9u8; 32]), vec![], vec![])
+            .await
+            .unwrap();
+        clock.advance(timeout);
+        assert_eq!(consensus.expire_proposals(), vec![proposal.id]);
+        assert_eq!(validator_set.missed_rounds(&silent), 0);
+        
+        // Three of four finalize without the silent validator
+        let mut root = Hash::ZERO;
+        for round in 1..=2u8 {
+            let next = Hash::from_bytes([round; 32]);
+            let proposal = consensus.create_proposal(root, next, vec![], vec![]).await.unwrap();
+            consensus.vote_on_proposal(&proposal.id, true).await.unwrap();
+            for voter in &keypairs[1..3] {
+                consensus.receive_vote(vote_from(voter, &proposal, root)).await.unwrap();
+            }
+            assert_eq!(consensus.latest_finalized_version(), StateVersion::new(round as u64));
+            assert_eq!(validator_set.missed_rounds(&silent), round as u64);
+            root = next;
+        }
+        
+        // Counting only picks the candidate; the finalized records deactivate it
+        assert!(validator_set.is_validator(&silent));
+        assert_eq!(consensus.inactive_validators(), vec![silent]);
+        let changes = validator_set.deactivation_changes(&[silent], 1).unwrap();
+        let next = Hash::from_bytes([3u8; 32]);
+        let proposal = consensus
+            .create_proposal_updating_validators(
+                root,
+                next,
+                vec![],
+                vec![silent],
+                vec![],
+                changes.clone(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(proposal.inactive_validators, vec![silent]);
+        assert!(proposal.matches_changes(&changes));
+        validator_set.apply_records(&changes).unwrap();
+        assert!(!validator_set.is_validator(&silent));
+        assert_eq!(validator_set.required_votes(), 3);
+        
+        // Re-staking brings the silent validator back
+        validator_set.update_stake(&silent, 1000).unwrap();
+        assert!(validator_set.is_validator(&silent));
+        assert_eq!(validator_set.required_votes(), 3);
+    }
+    
+    #[tokio::test]
+    async fn test_proposal_deactivating_active_validator_rejected() {
+        let (keypairs, validator_set) = setup_validators(4);
+        let engine = |kp: &KeyPair| {
+            let config = ConsensusConfig {
+                is_validator: true,
+                ..ConsensusConfig::devnet()
+            };
+            RainsonetConsensus::new(config, validator_set.clone(), Some(kp.clone())).unwrap()
+        };
+        let (proposer, receiver) = (engine(&keypairs[0]), engine(&keypairs[1]));
+        
+        // No rounds were missed, so the receiver deactivates nobody
+        let target = keypairs[3].node_id();
+        let changes = validator_set.deactivation_changes(&[target], 1).unwrap();
+        let proposal = proposer
+            .create_proposal_updating_validators(
+                Hash::ZERO,
+                Hash::from_bytes([1u8; 32]),
+                vec![],
+                vec![target],
+                vec![],
+                changes.clone(),
+            )
+            .await
+            .unwrap();
+        assert!(matches!(
+            receiver.receive_proposal(proposal.clone(), changes).await,
+            Err(RainsonetError::ProposalRejected(_))
+        ));
+        assert!(receiver.collected_votes(&proposal.id).is_empty());
+    }
+    
+    #[tokio::test]
+    async fn test_proposal_changing_other_validators_stake_rejected() {
+        let (keypairs, validator_set) = setup_validators(3);
+        let engine = |kp: &KeyPair| {
+            let config = ConsensusConfig {
+                is_validator: true,
+                ..ConsensusConfig::devnet()
+            };
+            RainsonetConsensus::new(config, validator_set.clone(), Some(kp.clone())).unwrap()
+        };
+        let (proposer, receiver) = (engine(&keypairs[0]), engine(&keypairs[1]));
+        
+        // A proposer may raise its own stake but not a peer's
+        let cases = [(keypairs[2].node_id(), false), (keypairs[0].node_id(), true)];
+        for (round, (node_id, accepted)) in (1u8..).zip(cases) {
+            let updates = vec![(node_id, 5_000)];
+            let changes = validator_set.stake_changes(&updates).unwrap();
+            let proposal = proposer
+                .create_proposal_updating_validators(
+                    Hash::ZERO,
+                    Hash::from_bytes([round; 32]),
+                    vec![],
+                    vec![],
+                    updates,
+                    changes.clone(),
+                )
+                .await
+                .unwrap();
+            let result = receiver.receive_proposal(proposal.clone(), changes).await;
+            if accepted {
+                result.unwrap();
+                assert_eq!(receiver.collected_votes(&proposal.id).len(), 1);
+            } else {
+                assert!(matches!(result, Err(RainsonetError::ProposalRejected(_))));
+            }
+        }
+    }
+    
+    #[tokio::test]
+    async fn test_expired_vote_rejected() {
+        let (keypairs, validator_set) = setup_validators(3);
+        let config = ConsensusConfig {
+            is_validator: true,
+            ..ConsensusConfig::devnet()
+        };
+        let vote_timeout = config.vote_timeout_ms;
+        let clock = mock_clock();
+        
+        let mut proposer =
+            RainsonetConsensus::new(config.clone(), validator_set.clone(), Some(keypairs[0].clone())).unwrap();
+        proposer.set_time_source(clock.clone());
+        let mut voter =
+            RainsonetConsensus::new(config, validator_set, Some(keypairs[1].clone())).unwrap();
+        voter.set_time_source(clock.clone());
+        
+        let proposal = proposer
+            .create_proposal(Hash::ZERO, Hash::from_bytes([1u8; 32]), vec![], vec![])
+            .await
+            .unwrap();
+        voter.receive_proposal(proposal.clone(), vec![]).await.unwrap();
+        let vote = voter.vote_on_proposal(&proposal.id, true).await.unwrap();
+        
+        clock.advance(std::time::Duration::from_millis(vote_timeout + 1));
+        let result = proposer.receive_vote(vote).await;
+        assert!(matches!(result, Err(RainsonetError::InvalidVote(_))));
+        assert_eq!(proposer.latest_finalized_version().0, 0);
+    }
+    
+    #[tokio::test]
+    async fn test_pending_proposals_bounded() {
+        let (keypairs, validator_set) = setup_validators(4);
+        let config = ConsensusConfig {
+            max_pending_proposals: 8,
+            ..ConsensusConfig::devnet()
+        };
+        let follower = RainsonetConsensus::new(config, validator_set, None).unwrap();
+        let proposer = &keypairs[0];
+        
+        let proposal_at = |version: u64, millis: u64| {
+            Proposal::new_at(
+                proposer.node_id(),
+                StateVersion::new(version),
+                Hash::ZERO,
+                Hash::ZERO,
+                Hash::from_bytes([1u8; 32]),
+                vec![],
+                vec![],
+                vec![],
+                &[],
+                Timestamp::from_millis(millis),
+                |msg| sign(proposer, msg),
+            )
+        };
+        
+        // Far-future versions are refused outright
+        for version in 2..100 {
+            let result = follower.receive_proposal(proposal_at(version, 0), vec![]).await;
+            assert!(matches!(result, Err(RainsonetError::StateVersionMismatch { .. })));
+        }
+        assert!(follower.proposal_store.is_empty());
+        
+        // Competing proposals for the next version stop at the cap
+        for millis in 0..20 {
+            let result = follower.receive_proposal(proposal_at(1, millis), vec![]).await;
+            if millis < 8 {
+                assert!(result.is_ok());
+            } else {
+                assert!(matches!(result, Err(RainsonetError::ProposalRejected(_))));
+            }
+        }
+        assert_eq!(follower.proposal_store.len(), 8);
+    }
+    
+    /// Three-validator proposer whose events reveal the proposal ID
+    fn setup_proposer(
+        timeout_ms: u64,
+    ) -> (Vec<KeyPair>, Arc<RainsonetConsensus>, mpsc::Receiver<ConsensusEvent>) {
+        let (keypairs, validator_set) = setup_validators(3);
+        let config = ConsensusConfig {
+            is_validator: true,
+            proposal_timeout_ms: timeout_ms,
+            ..ConsensusConfig::devnet()
+        };
+        let mut consensus =
+            RainsonetConsensus::new(config, validator_set, Some(keypairs[0].clone())).unwrap();
+        let (tx, rx) = create_consensus_channel();
+        consensus.set_event_channel(tx);
+        (keypairs, Arc::new(consensus), rx)
+    }
+    
+    async fn next_proposal_id(events: &mut mpsc::Receiver<ConsensusEvent>) -> Hash {
+        loop {
+            if let Some(ConsensusEvent::ProposalCreated(id)) = events.recv().await {
+                return id;
+            }
+        }
+    }
+    
+    fn external_vote(kp: &KeyPair, proposal_id: Hash, approve: bool) -> Vote {
+        Vote::new(proposal_id, kp.node_id(), approve, StateVersion::new(0), Hash::ZERO, |msg| {
+            sign(kp, msg)
+        })
+    }
+    
+    #[tokio::test]
+    async fn test_propose_single_validator_returns_immediately() {
+        let (keypairs, validator_set) = setup_validators(1);
+        let config = ConsensusConfig {
+            is_validator: true,
+            ..ConsensusConfig::devnet()
+        };
+        let consensus =
+            RainsonetConsensus::new(config, validator_set, Some(keypairs[0].clone())).unwrap();
+        
+        let version = consensus.propose(vec![]).await.unwrap();
+        assert_eq!(version, StateVersion::new(1));
+    }
+    
+    #[tokio::test]
+    async fn test_propose_waits_for_finalization() {
+        let (keypairs, consensus, mut events) = setup_proposer(5_000);
+        
+        let proposer = consensus.clone();
+        let pending = tokio::spawn(async move { proposer.propose(vec![]).await });
+        
+        let proposal_id = next_proposal_id(&mut events).await;
+        assert!(!pending.is_finished());
+        
+        consensus.receive_vote(external_vote(&keypairs[1], proposal_id, true)).await.unwrap();
+        consensus.receive_vote(external_vote(&keypairs[2], proposal_id, true)).await.unwrap();
+        
+        assert_eq!(pending.await.unwrap().unwrap(), StateVersion::new(1));
+    }
+    
+    #[tokio::test]
+    async fn test_propose_rejected() {
+        let (keypairs, consensus, mut events) = setup_proposer(5_000);
+        
+        let proposer = consensus.clone();
+        let pending = tokio::spawn(async move { proposer.propose(vec![]).await });
+        
+        let proposal_id = next_proposal_id(&mut events).await;
+        consensus.receive_vote(external_vote(&keypairs[1], proposal_id, false)).await.unwrap();
+        
+        let result = pending.await.unwrap();
+        assert!(matches!(result, Err(RainsonetError::ProposalRejected(_))));
+    }
+    
+    #[tokio::test]
+    async fn test_propose_times_out() {
+        let (_keypairs, consensus, _events) = setup_proposer(50);
+        
+        let result = consensus.propose(vec![]).await;
+        assert!(matches!(result, Err(RainsonetError::Timeout(_))));
+        assert!(consensus.outcomes.lock().is_empty());
+    }
+    
+    #[tokio::test]
+    async fn test_outcome_decided_before_waiting_is_kept() {
+        let (keypairs, consensus, _events) = setup_proposer(5_000);
+        
+        let proposal = consensus
+            .create_proposal(Hash::ZERO, Hash::from_bytes([1u8; 32]), vec![], vec![])
+            .await
+            .unwrap();
+        consensus.receive_vote(external_vote(&keypairs[1], proposal.id, false)).await.unwrap();
+        
+        let outcome = consensus.await_outcome(proposal.id).await.unwrap();
+        assert!(matches!(outcome, Err(RainsonetError::ProposalRejected(_))));
+        assert!(consensus.outcomes.lock().is_empty());
+    }
+    
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn test_concurrent_votes_finalize_once() {
+        let (keypairs, validator_set) = setup_validators(7);
+        let config = ConsensusConfig {
+            is_validator: true,
+            ..ConsensusConfig::devnet()
+        };
+        let mut consensus =
+            RainsonetConsensus::new(config, validator_set, Some(keypairs[0].clone())).unwrap();
+        let (tx, mut events) = create_consensus_channel();
+        consensus.set_event_channel(tx);
+        let consensus = Arc::new(consensus);
+        
+        let proposal = consensus
+            .create_proposal(Hash::ZERO, Hash::from_bytes([1u8; 32]), vec![], vec![])
+            .await
+            .unwrap();
+        consensus.vote_on_proposal(&proposal.id, true).await.unwrap();
+        
+        let tasks: Vec<_> = keypairs[1..]
+            .iter()
+            .map(|kp| {
+                let consensus = consensus.clone();
+                let vote = external_vote(kp, proposal.id, true);
+                tokio::spawn(async move { consensus.receive_vote(vote).await })
+            })
+            .collect();
+        
+        let all_votes = async {
+            for task in tasks {
+                task.await.unwrap().unwrap();
+            }
+        };
+        tokio::time::timeout(std::time::Duration::from_secs(5), all_votes)
+            .await
+            .expect("concurrent votes blocked the runtime");
+        
+        assert_eq!(consensus.latest_finalized_version().0, 1);
+        assert_eq!(consensus.collected_votes(&proposal.id).len(), 7);
+        
+        let mut finalized = 0;
+        while let Ok(event) = events.try_recv() {
+            if let ConsensusEvent::StateFinalized(version, _, _) = event {
+                assert_eq!(version.0, 1);
+                finalized += 1;
+            }
+        }
+        assert_eq!(finalized, 1);
+    }
+    
+    #[tokio::test]
+    async fn test_quorum_at_finalized_version_ignored() {
+        let (keypairs, validator_set) = setup_validators(4);
+        let engine = |kp: &KeyPair| {
+            let config = ConsensusConfig {
+                is_validator: true,
+                ..ConsensusConfig::devnet()
+            };
+            RainsonetConsensus::new(config, validator_set.clone(), Some(kp.clone())).unwrap()
+        };
+        let mut consensus = engine(&keypairs[0]);
+        let (tx, mut events) = create_consensus_channel();
+        consensus.set_event_channel(tx);
+        
+        let proposal = consensus
+            .create_proposal(Hash::ZERO, Hash::from_bytes([1u8; 32]), vec![], vec![])
+            .await
+            .unwrap();
+        let competing = engine(&keypairs[1])
+            .create_proposal(Hash::ZERO, Hash::from_bytes([2u8; 32]), vec![], vec![])
+            .await
+            .unwrap();
+        consensus.vote_on_proposal(&proposal.id, true).await.unwrap();
+        consensus.receive_proposal(competing.clone(), vec![]).await.unwrap();
+        for voter in &keypairs[1..3] {
+            consensus.receive_vote(external_vote(voter, proposal.id, true)).await.unwrap();
+        }
+        assert_eq!(consensus.latest_finalized_version().0, 1);
+        
+        // A late vote and a quorum for the competing proposal change nothing
+        consensus.receive_vote(external_vote(&keypairs[3], proposal.id, true)).await.unwrap();
+        for voter in &keypairs[1..] {
+            consensus.receive_vote(external_vote(voter, competing.id, true)).await.unwrap();
+        }
+        let certificate = consensus.get_certificate(StateVersion::new(1)).unwrap();
+        assert_eq!(certificate.proposal_id, proposal.id);
+        assert_eq!(consensus.latest_finalized_root(), proposal.new_root);
+        
+        let mut finalized = 0;
+        while let Ok(event) = events.try_recv() {
+            if matches!(event, ConsensusEvent::StateFinalized(..)) {
+                finalized += 1;
+            }
+        }
+        assert_eq!(finalized, 1);
+    }
+    
+    #[tokio::test]
+    async fn test_partial_votes_survive_restart() {
+        let (keypairs, validator_set) = setup_validators(3);
+        let config = ConsensusConfig {
+            is_validator: true,
+            proposal_timeout_ms: 60_000,
+            vote_collection_deadline_ms: 30_000,
+            ..ConsensusConfig::devnet()
+        };
+        let engine = |clock: Option<SharedTimeSource>| {
+            let mut consensus = RainsonetConsensus::new(
+                config.clone(),
+                validator_set.clone(),
+                Some(keypairs[0].clone()),
+            )
+            .unwrap();
+            if let Some(clock) = clock {
+                consensus.set_time_source(clock);
+            }
+            consensus
+        };
+        
+        // The proposer's vote and one other: short of the 3-of-3 quorum
+        let before = engine(None);
+        let proposal = before
+            .create_proposal(Hash::ZERO, Hash::from_bytes([1u8; 32]), vec![], vec![])
+            .await
+            .unwrap();
+        before.vote_on_proposal(&proposal.id, true).await.unwrap();
+        let early_vote = external_vote(&keypairs[1], proposal.id, true);
+        before.receive_vote(early_vote.clone()).await.unwrap();
+        assert_eq!(before.latest_finalized_version().0, 0);
+        
+        let path = std::env::temp_dir()
+            .join(format!("rainsonet-votes-{}.json", std::process::id()));
+        save_vote_collections(&path, &before.pending_vote_collections()).unwrap();
+        assert!(!path.with_extension("partial").exists());
+        drop(before);
+        
+        // Restarted from disk, only the missing vote is needed
+        let after = engine(None);
+        let saved = load_vote_collections(&path).unwrap();
+        assert_eq!(after.restore_vote_collections(saved.clone()), 1);
+        assert_eq!(after.collected_votes(&proposal.id).len(), 2);
+        
+        after.receive_vote(external_vote(&keypairs[2], proposal.id, true)).await.unwrap();
+        assert_eq!(after.latest_finalized_version().0, 1);
+        let certificate = after.get_certificate(StateVersion::new(1)).unwrap();
+        assert!(certificate.voters().contains(&early_vote.voter));
+        assert_eq!(certificate.votes.len(), 3);
+        
+        // Past the deadline the partial collection is discarded
+        let late = Timestamp::from_millis(proposal.timestamp.as_millis() + 30_001);
+        let expired = engine(Some(Arc::new(rainsonet_core::MockTimeSource::new(late))));
+        assert_eq!(expired.restore_vote_collections(saved), 0);
+        assert!(expired.collected_votes(&proposal.id).is_empty());
+        
+        let _ = std::fs::remove_file(&path);
+        assert!(load_vote_collections(&path).unwrap().is_empty());
+    }
 }
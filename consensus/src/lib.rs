@@ -7,6 +7,8 @@
 
 pub mod engine;
 pub mod proposal;
+#[cfg(any(test, feature = "test-utils"))]
+pub mod simulator;
 pub mod validator;
 pub mod vote;
 
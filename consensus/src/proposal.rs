@@ -1,13 +1,19 @@
 //! Proposal management for consensus
 
-use rainsonet_core::{Hash, NodeId, Signature, StateChange, StateRoot, StateVersion, Timestamp};
-use rainsonet_crypto::hashing::hash_multiple;
+use rainsonet_core::{
+    Hash, NodeId, RainsonetError, Signature, StateChange, StateRoot, StateVersion, Timestamp,
+};
+use rainsonet_crypto::hashing::{default_hash_algorithm, hash_multiple_with, hash_with, HashFormat};
+use rainsonet_p2p::ProposalMessage;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use parking_lot::RwLock;
 
+/// Hash format of proposal IDs
+pub const PROPOSAL_ID_FORMAT: HashFormat = HashFormat::LengthPrefixed;
+
 /// State update proposal from a validator
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct Proposal {
     /// Unique proposal ID
     pub id: Hash,
@@ -17,10 +23,18 @@ pub struct Proposal {
     pub state_version: StateVersion,
     /// Previous state root
     pub previous_root: StateRoot,
+    /// Hash of the certificate that finalized the previous version
+    pub previous_certificate_hash: Hash,
     /// New state root after changes
     pub new_root: StateRoot,
     /// Transaction IDs included
     pub tx_ids: Vec<Hash>,
+    /// Validators deactivated for inactivity, in node ID order. The changes
+    /// include their deactivated records after the transactions' changes.
+    pub inactive_validators: Vec<NodeId>,
+    /// Validator stakes the proposal sets, as node ID and new stake. The
+    /// changes include the updated records after the deactivated ones.
+    pub stake_updates: Vec<(NodeId, u128)>,
     /// Hash of state changes
     pub changes_hash: Hash,
     /// Proposer's signature
@@ -31,27 +45,61 @@ pub struct Proposal {
 
 impl Proposal {
     /// Create a new proposal
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         proposer: NodeId,
         state_version: StateVersion,
         previous_root: StateRoot,
+        previous_certificate_hash: Hash,
         new_root: StateRoot,
         tx_ids: Vec<Hash>,
         changes: &[StateChange],
         sign_fn: impl FnOnce(&[u8]) -> Signature,
+    ) -> Self {
+        Self::new_at(
+            proposer,
+            state_version,
+            previous_root,
+            previous_certificate_hash,
+            new_root,
+            tx_ids,
+            Vec::new(),
+            Vec::new(),
+            changes,
+            Timestamp::now(),
+            sign_fn,
+        )
+    }
+    
+    /// Create a new proposal timestamped at `timestamp`, deactivating
+    /// `inactive_validators` and applying `stake_updates`
+    #[allow(clippy::too_many_arguments)]
+    pub fn new_at(
+        proposer: NodeId,
+        state_version: StateVersion,
+        previous_root: StateRoot,
+        previous_certificate_hash: Hash,
+        new_root: StateRoot,
+        tx_ids: Vec<Hash>,
+        inactive_validators: Vec<NodeId>,
+        stake_updates: Vec<(NodeId, u128)>,
+        changes: &[StateChange],
+        timestamp: Timestamp,
+        sign_fn: impl FnOnce(&[u8]) -> Signature,
     ) -> Self {
         let changes_hash = Self::compute_changes_hash(changes);
-        let timestamp = Timestamp::now();
         
-        // Compute proposal ID
-        let id_data = [
-            proposer.as_bytes().as_slice(),
-            &state_version.0.to_le_bytes(),
-            previous_root.as_bytes(),
-            new_root.as_bytes(),
-            &timestamp.0.to_le_bytes(),
-        ];
-        let id = hash_multiple(&id_data);
+        let id = Self::compute_id(
+            &proposer,
+            state_version,
+            &previous_root,
+            &previous_certificate_hash,
+            &new_root,
+            &tx_ids,
+            &inactive_validators,
+            &stake_updates,
+            timestamp,
+        );
         
         // Create signing message
         let sign_msg = Self::signing_message(
@@ -59,7 +107,9 @@ impl Proposal {
             &proposer,
             state_version,
             &previous_root,
+            &previous_certificate_hash,
             &new_root,
+            &tx_ids,
             &changes_hash,
         );
         
@@ -70,27 +120,80 @@ impl Proposal {
             proposer,
             state_version,
             previous_root,
+            previous_certificate_hash,
             new_root,
             tx_ids,
+            inactive_validators,
+            stake_updates,
             changes_hash,
             signature,
             timestamp,
         }
     }
     
-    /// Compute hash of state changes
-    fn compute_changes_hash(changes: &[StateChange]) -> Hash {
+    /// Compute proposal ID. Validators must agree on
+    /// [`PROPOSAL_ID_FORMAT`], as received IDs are recomputed and checked.
+    #[allow(clippy::too_many_arguments)]
+    fn compute_id(
+        proposer: &NodeId,
+        state_version: StateVersion,
+        previous_root: &StateRoot,
+        previous_certificate_hash: &Hash,
+        new_root: &StateRoot,
+        tx_ids: &[Hash],
+        inactive_validators: &[NodeId],
+        stake_updates: &[(NodeId, u128)],
+        timestamp: Timestamp,
+    ) -> Hash {
+        let transactions: Vec<u8> = tx_ids
+            .iter()
+            .flat_map(|tx_id| tx_id.as_bytes().iter().copied())
+            .collect();
+        let inactive: Vec<u8> = inactive_validators
+            .iter()
+            .flat_map(|node_id| node_id.as_bytes().iter().copied())
+            .collect();
+        let stakes: Vec<u8> = stake_updates
+            .iter()
+            .flat_map(|(node_id, stake)| {
+                node_id.as_bytes().iter().copied().chain(stake.to_le_bytes())
+            })
+            .collect();
+        let id_data = [
+            proposer.as_bytes().as_slice(),
+            &state_version.0.to_le_bytes(),
+            previous_root.as_bytes(),
+            previous_certificate_hash.as_bytes(),
+            new_root.as_bytes(),
+            &transactions,
+            &inactive,
+            &stakes,
+            &timestamp.0.to_le_bytes(),
+        ];
+        hash_multiple_with(PROPOSAL_ID_FORMAT, &id_data)
+    }
+    
+    /// Compute hash of state changes, with the default hash algorithm
+    pub fn compute_changes_hash(changes: &[StateChange]) -> Hash {
         let serialized = bincode::serialize(changes).unwrap_or_default();
-        rainsonet_crypto::hashing::hash(&serialized)
+        hash_with(default_hash_algorithm(), &serialized)
+    }
+    
+    /// Check that `changes` are the ones this proposal commits to
+    pub fn matches_changes(&self, changes: &[StateChange]) -> bool {
+        Self::compute_changes_hash(changes) == self.changes_hash
     }
     
     /// Create signing message
+    #[allow(clippy::too_many_arguments)]
     fn signing_message(
         id: &Hash,
         proposer: &NodeId,
         state_version: StateVersion,
         previous_root: &StateRoot,
+        previous_certificate_hash: &Hash,
         new_root: &StateRoot,
+        tx_ids: &[Hash],
         changes_hash: &Hash,
     ) -> Vec<u8> {
         let mut msg = Vec::new();
@@ -99,7 +202,12 @@ impl Proposal {
         msg.extend_from_slice(proposer.as_bytes());
         msg.extend_from_slice(&state_version.0.to_le_bytes());
         msg.extend_from_slice(previous_root.as_bytes());
+        msg.extend_from_slice(previous_certificate_hash.as_bytes());
         msg.extend_from_slice(new_root.as_bytes());
+        msg.extend_from_slice(&(tx_ids.len() as u64).to_le_bytes());
+        for tx_id in tx_ids {
+            msg.extend_from_slice(tx_id.as_bytes());
+        }
         msg.extend_from_slice(changes_hash.as_bytes());
         msg
     }
@@ -111,15 +219,81 @@ impl Proposal {
             &self.proposer,
             self.state_version,
             &self.previous_root,
+            &self.previous_certificate_hash,
             &self.new_root,
+            &self.tx_ids,
             &self.changes_hash,
         )
     }
     
     /// Check if proposal is expired
     pub fn is_expired(&self, timeout_ms: u64) -> bool {
-        let now = Timestamp::now();
-        now.as_millis() - self.timestamp.as_millis() > timeout_ms
+        self.is_expired_at(Timestamp::now(), timeout_ms)
+    }
+    
+    /// Check if proposal is expired as of `now`
+    pub fn is_expired_at(&self, now: Timestamp, timeout_ms: u64) -> bool {
+        now.as_millis().saturating_sub(self.timestamp.as_millis()) > timeout_ms
+    }
+}
+
+impl From<&Proposal> for ProposalMessage {
+    fn from(proposal: &Proposal) -> Self {
+        Self {
+            proposal_id: proposal.id,
+            proposer: proposal.proposer,
+            state_version: proposal.state_version,
+            previous_root: proposal.previous_root,
+            previous_certificate_hash: proposal.previous_certificate_hash,
+            new_root: proposal.new_root,
+            tx_ids: proposal.tx_ids.clone(),
+            inactive_validators: proposal.inactive_validators.clone(),
+            stake_updates: proposal.stake_updates.clone(),
+            changes_hash: proposal.changes_hash,
+            signature: proposal.signature,
+            timestamp: proposal.timestamp,
+        }
+    }
+}
+
+/// Network messages carry only the changes hash; the receiver rebuilds the
+/// changes from the referenced transactions and checks them with
+/// [`Proposal::matches_changes`].
+impl TryFrom<ProposalMessage> for Proposal {
+    type Error = RainsonetError;
+    
+    fn try_from(msg: ProposalMessage) -> Result<Self, Self::Error> {
+        let id = Self::compute_id(
+            &msg.proposer,
+            msg.state_version,
+            &msg.previous_root,
+            &msg.previous_certificate_hash,
+            &msg.new_root,
+            &msg.tx_ids,
+            &msg.inactive_validators,
+            &msg.stake_updates,
+            msg.timestamp,
+        );
+        if id != msg.proposal_id {
+            return Err(RainsonetError::ProposalRejected(
+                "Proposal ID does not match its contents".into(),
+            ));
+        }
+        
+        Ok(Self {
+            id,
+            proposer: msg.proposer,
+            state_version: msg.state_version,
+            previous_root: msg.previous_root,
+            previous_certificate_hash: msg.previous_certificate_hash,
+            new_root: msg.new_root,
+            tx_ids: msg.tx_ids,
+            inactive_validators: msg.inactive_validators,
+            stake_updates: msg.stake_updates,
+            changes_hash: msg.changes_hash,
+            signature: msg.signature,
+            timestamp: msg.timestamp,
+        })
     }
 }
 
@@ -206,14 +380,16 @@ impl ProposalStore {
         }
     }
     
-    /// Add a proposal
+    /// Add a proposal. A proposal already tracked under the same ID is kept
+    /// along with its votes.
     pub fn add(&self, proposal: Proposal, changes: Vec<StateChange>) {
         let id = proposal.id;
         let version = proposal.state_version;
         
         self.proposals
             .write()
-            .insert(id, TrackedProposal::new(proposal, changes));
+            .entry(id)
+            .or_insert_with(|| TrackedProposal::new(proposal, changes));
         self.by_version.write().insert(version, id);
     }
     
@@ -243,6 +419,11 @@ impl ProposalStore {
         }
     }
     
+    /// Get state changes for a proposal, whatever its status
+    pub fn get_changes(&self, proposal_id: &Hash) -> Option<Vec<StateChange>> {
+        self.proposals.read().get(proposal_id).map(|tp| tp.state_changes.clone())
+    }
+    
     /// Get state changes for approved proposal
     pub fn get_approved_changes(&self, proposal_id: &Hash) -> Option<Vec<StateChange>> {
         let proposals = self.proposals.read();
@@ -255,22 +436,67 @@ impl ProposalStore {
         })
     }
     
-    /// Remove old proposals
-    pub fn cleanup(&self, before_version: StateVersion) {
+    /// Expire pending proposals above `finalized` that are older than
+    /// `timeout_ms`, returning their IDs
+    pub fn expire_pending(
+        &self,
+        now: Timestamp,
+        timeout_ms: u64,
+        finalized: StateVersion,
+    ) -> Vec<Hash> {
+        let mut expired = Vec::new();
+        
+        for (id, tp) in self.proposals.write().iter_mut() {
+            if tp.status == ProposalStatus::Pending
+                && tp.proposal.state_version > finalized
+                && tp.proposal.is_expired_at(now, timeout_ms)
+            {
+                tp.expire();
+                expired.push(*id);
+            }
+        }
+        
+        expired
+    }
+    
+    /// Number of tracked proposals
+    pub fn len(&self) -> usize {
+        self.proposals.read().len()
+    }
+    
+    /// Whether no proposals are tracked
+    pub fn is_empty(&self) -> bool {
+        self.proposals.read().is_empty()
+    }
+    
+    /// Number of tracked proposals for versions above `finalized`
+    pub fn count_above(&self, finalized: StateVersion) -> usize {
+        self.proposals
+            .read()
+            .values()
+            .filter(|tp| tp.proposal.state_version > finalized)
+            .count()
+    }
+    
+    /// Remove old proposals, returning their IDs
+    pub fn cleanup(&self, before_version: StateVersion) -> Vec<Hash> {
         let mut by_version = self.by_version.write();
         let mut proposals = self.proposals.write();
         
-        let old_versions: Vec<StateVersion> = by_version
-            .keys()
-            .filter(|v| v.0 < before_version.0)
-            .copied()
+        // Several proposals can compete for one version, so match on the
+        // tracked proposal rather than the by-version index
+        let removed: Vec<Hash> = proposals
+            .iter()
+            .filter(|(_, tp)| tp.proposal.state_version < before_version)
+            .map(|(id, _)| *id)
             .collect();
         
-        for version in old_versions {
-            if let Some(id) = by_version.remove(&version) {
-                proposals.remove(&id);
-            }
+        for id in &removed {
+            proposals.remove(id);
         }
+        by_version.retain(|v, _| *v >= before_version);
+        
+        removed
     }
 }
 
@@ -295,6 +521,7 @@ mod tests {
             node_id,
             StateVersion::new(1),
             Hash::ZERO,
+            Hash::ZERO,
             Hash::from_bytes([1u8; 32]),
             vec![],
             &[],
@@ -305,6 +532,68 @@ mod tests {
         assert_eq!(proposal.state_version.0, 1);
     }
     
+    #[test]
+    fn test_proposal_message_round_trip() {
+        use rainsonet_p2p::Message;
+        
+        let kp = KeyPair::generate();
+        let proposal = Proposal::new(
+            kp.node_id(),
+            StateVersion::new(3),
+            Hash::from_bytes([2u8; 32]),
+            Hash::from_bytes([5u8; 32]),
+            Hash::from_bytes([3u8; 32]),
+            vec![Hash::from_bytes([4u8; 32])],
+            &[],
+            |msg| sign(&kp, msg),
+        );
+        
+        let bytes = Message::Proposal(ProposalMessage::from(&proposal)).to_bytes();
+        let received = match Message::from_bytes(&bytes) {
+            Some(Message::Proposal(msg)) => Proposal::try_from(msg).unwrap(),
+            other => panic!("unexpected message {:?}", other),
+        };
+        
+        assert_eq!(
+            bincode::serialize(&received).unwrap(),
+            bincode::serialize(&proposal).unwrap()
+        );
+        assert!(rainsonet_crypto::signing::verify(
+            &kp.public_key(),
+            &received.get_signing_message(),
+            &received.signature
+        )
+        .is_ok());
+    }
+    
+    #[test]
+    fn test_proposal_message_with_forged_id_rejected() {
+        let kp = KeyPair::generate();
+        let proposal = Proposal::new(
+            kp.node_id(),
+            StateVersion::new(1),
+            Hash::ZERO,
+            Hash::ZERO,
+            Hash::from_bytes([1u8; 32]),
+            vec![],
+            &[],
+            |msg| sign(&kp, msg),
+        );
+        
+        let mut msg = ProposalMessage::from(&proposal);
+        msg.new_root = Hash::from_bytes([9u8; 32]);
+        assert!(Proposal::try_from(msg).is_err());
+        
+        // Transaction IDs are covered by both the ID and the signature
+        let mut msg = ProposalMessage::from(&proposal);
+        msg.tx_ids = vec![Hash::from_bytes([7u8; 32])];
+        assert!(Proposal::try_from(msg).is_err());
+        
+        let mut swapped = proposal.clone();
+        swapped.tx_ids = vec![Hash::from_bytes([7u8; 32])];
+        assert_ne!(swapped.get_signing_message(), proposal.get_signing_message());
+    }
+    
     #[test]
     fn test_tracked_proposal_voting() {
         let kp = KeyPair::generate();
@@ -314,6 +603,7 @@ mod tests {
             node_id,
             StateVersion::new(1),
             Hash::ZERO,
+            Hash::ZERO,
             Hash::from_bytes([1u8; 32]),
             vec![],
             &[],
@@ -0,0 +1,374 @@
+//! In-process consensus simulator for testing validator-set scenarios
+//!
+//! [`ConsensusSimulator`] runs one [`RainsonetConsensus`] per validator and
+//! routes proposals and votes between them in discrete steps, optionally
+//! dropping or delaying messages or cutting the network into partitions.
+//! Validators can be made Byzantine with a [`Behavior`].
+//!
+//! Available in tests and with the `test-utils` feature.
+
+use crate::engine::RainsonetConsensus;
+use crate::proposal::Proposal;
+use crate::validator::{ValidatorInfo, ValidatorSet};
+use crate::vote::Vote;
+use rainsonet_core::{
+    ConsensusConfig, Hash, NodeId, RainsonetResult, StateChange, StateRoot, StateVersion,
+};
+use rainsonet_crypto::keys::KeyPair;
+use rainsonet_crypto::signing::sign;
+use std::collections::VecDeque;
+use std::sync::Arc;
+
+/// Steps after which [`ConsensusSimulator::run`] gives up
+pub const MAX_SIMULATION_STEPS: u64 = 1_000;
+
+/// How a simulated validator treats the messages it receives
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Behavior {
+    /// Follows the protocol
+    Honest,
+    /// Receives messages but never sends any
+    Silent,
+    /// Votes to reject every proposal it receives
+    RejectAll,
+}
+
+/// Message in flight between two validators
+#[derive(Debug, Clone)]
+pub enum SimMessage {
+    Proposal(Proposal, Vec<StateChange>),
+    Vote(Vote),
+}
+
+#[derive(Debug)]
+struct Envelope {
+    to: usize,
+    deliver_at: u64,
+    message: SimMessage,
+}
+
+/// Simulated validator
+pub struct SimNode {
+    pub engine: RainsonetConsensus,
+    pub keypair: KeyPair,
+    pub behavior: Behavior,
+}
+
+impl SimNode {
+    pub fn node_id(&self) -> NodeId {
+        self.keypair.node_id()
+    }
+    
+    pub fn is_honest(&self) -> bool {
+        self.behavior == Behavior::Honest
+    }
+}
+
+/// Runs a set of validators in one process.
+///
+/// Each validator gets its own engine and its own copy of the validator
+/// set, as separate nodes would. Messages sent during a step are delivered
+/// `delay + 1` steps later unless dropped.
+pub struct ConsensusSimulator {
+    nodes: Vec<SimNode>,
+    in_flight: VecDeque<Envelope>,
+    step: u64,
+    delay: u64,
+    loss_rate: f64,
+    rng_state: u64,
+    /// Partition index of each node; messages only cross within a partition
+    partitions: Vec<usize>,
+    delivered: u64,
+    dropped: u64,
+}
+
+impl ConsensusSimulator {
+    /// Simulate `count` honest validators
+    pub fn new(count: usize) -> Self {
+        let keypairs: Vec<KeyPair> = (0..count).map(|_| KeyPair::generate()).collect();
+        let validators: Vec<ValidatorInfo> = keypairs
+            .iter()
+            .map(|kp| ValidatorInfo::new(kp.node_id(), kp.public_key(), 1000))
+            .collect();
+        
+        let nodes = keypairs
+            .into_iter()
+            .map(|keypair| {
+                let config = ConsensusConfig {
+                    is_validator: true,
+                    ..Default::default()
+                };
+                let validator_set = Arc::new(ValidatorSet::with_validators(validators.clone()));
+                let engine = RainsonetConsensus::new(config, validator_set, Some(keypair.clone()))
+                    .expect("validator engine with keypair");
+                SimNode {
+                    engine,
+                    keypair,
+                    behavior: Behavior::Honest,
+                }
+            })
+            .collect();
+        
+        Self {
+            nodes,
+            in_flight: VecDeque::new(),
+            step: 0,
+            delay: 0,
+            loss_rate: 0.0,
+            rng_state: 0x9E37_79B9_7F4A_7C15,
+            partitions: vec![0; count],
+            delivered: 0,
+            dropped: 0,
+        }
+    }
+    
+    /// Make validator `index` behave as `behavior`
+    pub fn with_behavior(mut self, index: usize, behavior: Behavior) -> Self {
+        self.nodes[index].behavior = behavior;
+        self
+    }
+    
+    /// Deliver messages `steps` steps later than the next step
+    pub fn with_delay(mut self, steps: u64) -> Self {
+        self.delay = steps;
+        self
+    }
+    
+    /// Drop each message with probability `rate`, reproducibly for `seed`
+    pub fn with_loss(mut self, rate: f64, seed: u64) -> Self {
+        self.loss_rate = rate;
+        self.rng_state = seed.max(1);
+        self
+    }
+    
+    /// Split the network: validators only reach others in the same group.
+    /// Validators left out of every group form a group of their own.
+    pub fn partition(&mut self, groups: &[&[usize]]) {
+        self.partitions = vec![groups.len(); self.nodes.len()];
+        for (group, members) in groups.iter().enumerate() {
+            for &index in *members {
+                self.partitions[index] = group;
+            }
+        }
+    }
+    
+    /// Reconnect all validators
+    pub fn heal(&mut self) {
+        self.partitions = vec![0; self.nodes.len()];
+    }
+    
+    pub fn node(&self, index: usize) -> &SimNode {
+        &self.nodes[index]
+    }
+    
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+    
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+    
+    /// Messages delivered and dropped so far
+    pub fn message_counts(&self) -> (u64, u64) {
+        (self.delivered, self.dropped)
+    }
+    
+    /// Have validator `index` propose `new_root` on top of its finalized
+    /// root, approve it and broadcast the proposal with its vote
+    pub async fn propose(
+        &mut self,
+        index: usize,
+        new_root: StateRoot,
+        changes: Vec<StateChange>,
+    ) -> RainsonetResult<Proposal> {
+        let engine = &self.nodes[index].engine;
+        let previous_root = engine.latest_finalized_root();
+        let proposal = engine
+            .create_proposal(previous_root, new_root, vec![], changes.clone())
+            .await?;
+        let vote = engine.vote_on_proposal(&proposal.id, true).await?;
+        
+        self.broadcast(index, SimMessage::Proposal(proposal.clone(), changes));
+        self.broadcast(index, SimMessage::Vote(vote));
+        Ok(proposal)
+    }
+    
+    /// Deliver the messages due this step, returning how many were delivered
+    pub async fn step(&mut self) -> usize {
+        self.step += 1;
+        let (due, pending): (Vec<Envelope>, Vec<Envelope>) = self
+            .in_flight
+            .drain(..)
+            .partition(|envelope| envelope.deliver_at <= self.step);
+        self.in_flight = pending.into();
+        
+        let count = due.len();
+        for envelope in due {
+            self.deliver(envelope).await;
+        }
+        count
+    }
+    
+    /// Step until no messages are in flight, returning the steps taken
+    pub async fn run(&mut self) -> u64 {
+        let start = self.step;
+        while !self.in_flight.is_empty() && self.step - start < MAX_SIMULATION_STEPS {
+            self.step().await;
+        }
+        self.step - start
+    }
+    
+    /// Finalized version of every validator, in index order
+    pub fn finalized_versions(&self) -> Vec<StateVersion> {
+        self.nodes
+            .iter()
+            .map(|node| node.engine.latest_finalized_version())
+            .collect()
+    }
+    
+    /// Whether every honest validator finalized `version` with the same root
+    pub fn finalized_by_all(&self, version: StateVersion) -> bool {
+        let mut roots = self
+            .nodes
+            .iter()
+            .filter(|node| node.is_honest())
+            .map(|node| node.engine.get_certificate(version).map(|c| c.state_root));
+        match roots.next() {
+            Some(Some(root)) => roots.all(|other| other == Some(root)),
+            _ => false,
+        }
+    }
+    
+    /// Panic unless every honest validator finalized `version` alike
+    pub fn assert_finalized_by_all(&self, version: StateVersion) {
+        assert!(
+            self.finalized_by_all(version),
+            "version {} not finalized by all honest validators: {:?}",
+            version,
+            self.finalized_versions()
+        );
+    }
+    
+    async fn deliver(&mut self, envelope: Envelope) {
+        let to = envelope.to;
+        let node = &self.nodes[to];
+        match envelope.message {
+            SimMessage::Proposal(proposal, changes) => {
+                let id = proposal.id;
+                if node.engine.receive_proposal(proposal, changes).await.is_err() {
+                    return;
+                }
+                let vote = match node.behavior {
+                    Behavior::Honest => self.own_vote(to, &id),
+                    Behavior::Silent => None,
+                    Behavior::RejectAll => Some(self.reject_vote(to, id)),
+                };
+                if let Some(vote) = vote {
+                    self.broadcast(to, SimMessage::Vote(vote));
+                }
+            }
+            SimMessage::Vote(vote) => {
+                // Votes for unknown proposals and replays are expected noise
+                let _ = node.engine.receive_vote(vote).await;
+            }
+        }
+        self.delivered += 1;
+    }
+    
+    /// Vote validator `index` cast on `proposal_id`, if any
+    fn own_vote(&self, index: usize, proposal_id: &Hash) -> Option<Vote> {
+        let node = &self.nodes[index];
+        let node_id = node.node_id();
+        node.engine
+            .collected_votes(proposal_id)
+            .into_iter()
+            .find(|vote| vote.voter == node_id)
+    }
+    
+    /// Validly signed rejection from validator `index`
+    fn reject_vote(&self, index: usize, proposal_id: Hash) -> Vote {
+        let node = &self.nodes[index];
+        Vote::new(
+            proposal_id,
+            node.node_id(),
+            false,
+            node.engine.latest_finalized_version(),
+            node.engine.latest_finalized_root(),
+            |msg| sign(&node.keypair, msg),
+        )
+    }
+    
+    fn broadcast(&mut self, from: usize, message: SimMessage) {
+        if self.nodes[from].behavior == Behavior::Silent {
+            return;
+        }
+        for to in 0..self.nodes.len() {
+            if to == from {
+                continue;
+            }
+            if self.partitions[to] != self.partitions[from] || self.lose() {
+                self.dropped += 1;
+                continue;
+            }
+            self.in_flight.push_back(Envelope {
+                to,
+                deliver_at: self.step + 1 + self.delay,
+                message: message.clone(),
+            });
+        }
+    }
+    
+    /// Decide whether to drop a message, with a xorshift generator so runs
+    /// are reproducible
+    fn lose(&mut self) -> bool {
+        if self.loss_rate <= 0.0 {
+            return false;
+        }
+        self.rng_state ^= self.rng_state << 13;
+        self.rng_state ^= self.rng_state >> 7;
+        self.rng_state ^= self.rng_state << 17;
+        (self.rng_state as f64 / u64::MAX as f64) < self.loss_rate
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    fn root(byte: u8) -> StateRoot {
+        Hash::from_bytes([byte; 32])
+    }
+    
+    #[tokio::test]
+    async fn test_clean_finalization() {
+        let mut sim = ConsensusSimulator::new(4).with_delay(1);
+        
+        for version in 1..=3u8 {
+            sim.propose(version as usize % 4, root(version), vec![]).await.unwrap();
+            sim.run().await;
+            sim.assert_finalized_by_all(StateVersion::new(version as u64));
+        }
+        
+        assert_eq!(sim.finalized_versions(), vec![StateVersion::new(3); 4]);
+        assert_eq!(sim.message_counts().1, 0);
+    }
+    
+    #[tokio::test]
+    async fn test_one_byzantine_validator() {
+        // Four validators tolerate one fault: quorum is three
+        let mut sim = ConsensusSimulator::new(4).with_behavior(3, Behavior::RejectAll);
+        
+        sim.propose(0, root(1), vec![]).await.unwrap();
+        sim.run().await;
+        sim.assert_finalized_by_all(StateVersion::new(1));
+        
+        // With an honest validator cut off, the Byzantine one blocks the quorum
+        let mut sim = ConsensusSimulator::new(4).with_behavior(3, Behavior::RejectAll);
+        sim.partition(&[&[0, 1, 3], &[2]]);
+        sim.propose(0, root(1), vec![]).await.unwrap();
+        sim.run().await;
+        assert!(!sim.finalized_by_all(StateVersion::new(1)));
+        assert_eq!(sim.node(0).engine.latest_finalized_version().0, 0);
+    }
+}
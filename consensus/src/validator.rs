@@ -2,14 +2,20 @@
 
 use dashmap::DashMap;
 use parking_lot::RwLock;
-use rainsonet_core::{NodeId, PublicKey, RainsonetError, RainsonetResult, Signature};
+use rainsonet_core::{
+    ConsensusConfig, Hash, NodeId, PublicKey, RainsonetError, RainsonetResult, Signature,
+    StateChange, StateVersion,
+};
+use rainsonet_crypto::hashing::hash;
 use rainsonet_crypto::signing::{sign, verify};
 use rainsonet_crypto::keys::KeyPair;
+use rainsonet_state::VALIDATOR_PREFIX;
+use serde::{Deserialize, Serialize};
 use std::collections::HashSet;
 use std::sync::Arc;
 
 /// Validator information
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct ValidatorInfo {
     pub node_id: NodeId,
     pub public_key: PublicKey,
@@ -26,12 +32,124 @@ impl ValidatorInfo {
             active: true,
         }
     }
+    
+    /// State change persisting this record under its validator key
+    pub fn to_state_change(&self) -> RainsonetResult<StateChange> {
+        Ok(StateChange::Set {
+            key: rainsonet_state::validator_key(self.node_id.as_bytes()),
+            value: bincode::serialize(self)?,
+        })
+    }
+    
+    /// Decode a record persisted with [`ValidatorInfo::to_state_change`]
+    pub fn from_bytes(bytes: &[u8]) -> RainsonetResult<Self> {
+        bincode::deserialize(bytes)
+            .map_err(|e| RainsonetError::DeserializationError(e.to_string()))
+    }
+}
+
+/// Number of approvals needed to finalize with `n` active validators.
+///
+/// Uses the Byzantine quorum `floor(2n / 3) + 1`: strictly more than two
+/// thirds of the set, so any two quorums overlap in at least one honest
+/// validator while up to `floor((n - 1) / 3)` validators are faulty.
+///
+/// | n | required | tolerated faults |
+/// |---|----------|------------------|
+/// | 1 | 1        | 0                |
+/// | 2 | 2        | 0                |
+/// | 3 | 3        | 0                |
+/// | 4 | 3        | 1                |
+/// | 5 | 4        | 1                |
+/// | 6 | 5        | 1                |
+/// | 7 | 5        | 2                |
+pub fn quorum_size(n: usize) -> usize {
+    (n * 2 / 3) + 1
+}
+
+/// Votes needed to finalize, see [`ValidatorSet::vote_requirement`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VoteRequirement {
+    /// A lone validator finalizes on its own vote. Only a devnet that
+    /// explicitly sets `min_validators` to 1 runs like this.
+    SingleNode,
+    /// Byzantine quorum of `required` votes out of `total` active validators
+    Bft { required: usize, total: usize },
+    /// Fewer active validators than `minimum`, so nothing may finalize
+    Insufficient { active: usize, minimum: usize },
+}
+
+/// Membership and stake changes applied to a validator set at an epoch
+/// boundary.
+///
+/// Every node must apply the same diff or validator sets diverge. Proposals
+/// do not carry a diff: receivers rebuild a proposal's changes from its
+/// transactions and deactivation list only, so a diff has to be agreed and
+/// applied out of band, e.g. from genesis or an operator-driven epoch, with
+/// [`ValidatorSet::apply_diff`] on every node. Each list is kept sorted by
+/// node ID so equal diffs serialize and hash equally, and nodes can compare
+/// [`ValidatorSetDiff::hash`] before applying.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ValidatorSetDiff {
+    pub added: Vec<ValidatorInfo>,
+    pub removed: Vec<NodeId>,
+    pub stake_updates: Vec<(NodeId, u128)>,
+}
+
+impl ValidatorSetDiff {
+    /// Build a diff from its parts, sorting each list into canonical order
+    pub fn new(
+        mut added: Vec<ValidatorInfo>,
+        mut removed: Vec<NodeId>,
+        mut stake_updates: Vec<(NodeId, u128)>,
+    ) -> Self {
+        added.sort_by(|a, b| a.node_id.as_bytes().cmp(b.node_id.as_bytes()));
+        removed.sort_by(|a, b| a.as_bytes().cmp(b.as_bytes()));
+        stake_updates.sort_by(|a, b| a.0.as_bytes().cmp(b.0.as_bytes()));
+        Self {
+            added,
+            removed,
+            stake_updates,
+        }
+    }
+    
+    /// Check if the diff changes nothing
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.stake_updates.is_empty()
+    }
+    
+    /// Hash of the diff, identical on every node that computed the same diff
+    pub fn hash(&self) -> RainsonetResult<Hash> {
+        Ok(hash(&bincode::serialize(self)?))
+    }
+}
+
+/// Voting participation of an active validator
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ValidatorLiveness {
+    pub node_id: NodeId,
+    pub stake: u128,
+    /// Highest proposal version the validator voted on, if any
+    pub last_voted_version: Option<StateVersion>,
+    /// No vote within the liveness window
+    pub inactive: bool,
 }
 
 /// Validator set management
 pub struct ValidatorSet {
     validators: DashMap<NodeId, ValidatorInfo>,
     active_count: RwLock<usize>,
+    min_stake: u128,
+    /// Highest proposal version each validator voted on. Kept out of
+    /// `ValidatorInfo`, which is persisted and must be identical on all
+    /// nodes, while votes arrive at each node differently.
+    last_voted: DashMap<NodeId, StateVersion>,
+    /// Consecutive finalized rounds each active validator did not vote in,
+    /// as seen by this node
+    missed_rounds: DashMap<NodeId, u64>,
+    /// Validators a finalized block deactivated for missing rounds,
+    /// reactivated by a stake update that meets the minimum stake
+    inactivity_deactivated: DashMap<NodeId, ()>,
 }
 
 impl ValidatorSet {
@@ -39,9 +157,19 @@ impl ValidatorSet {
         Self {
             validators: DashMap::new(),
             active_count: RwLock::new(0),
+            min_stake: 0,
+            last_voted: DashMap::new(),
+            missed_rounds: DashMap::new(),
+            inactivity_deactivated: DashMap::new(),
         }
     }
     
+    /// Deactivate validators whose stake is updated below `min_stake`
+    pub fn with_min_stake(mut self, min_stake: u128) -> Self {
+        self.min_stake = min_stake;
+        self
+    }
+    
     /// Create with initial validators
     pub fn with_validators(validators: Vec<ValidatorInfo>) -> Self {
         let set = Self::new();
@@ -73,6 +201,336 @@ impl ValidatorSet {
         }
     }
     
+    /// State change setting a validator's stake, for a proposal to include.
+    /// The set itself changes once it is finalized, see
+    /// [`ValidatorSet::apply_records`].
+    ///
+    /// An active validator whose stake falls below the set's minimum is
+    /// deactivated; raising the stake again does not reactivate it. A
+    /// validator deactivated for inactivity is reactivated by re-staking at
+    /// least the minimum.
+    pub fn stake_change(&self, node_id: &NodeId, new_stake: u128) -> RainsonetResult<StateChange> {
+        let mut record = self.get_validator(node_id).ok_or(RainsonetError::NotAValidator)?;
+        
+        record.stake = new_stake;
+        if record.active && new_stake < self.min_stake {
+            record.active = false;
+        } else if !record.active
+            && new_stake >= self.min_stake
+            && self.inactivity_deactivated.contains_key(node_id)
+        {
+            record.active = true;
+        }
+        
+        record.to_state_change()
+    }
+    
+    /// State changes for a proposal's `stake_updates`, in order. Fails
+    /// unless each names a validator, once.
+    pub fn stake_changes(
+        &self,
+        stake_updates: &[(NodeId, u128)],
+    ) -> RainsonetResult<Vec<StateChange>> {
+        let mut listed = HashSet::new();
+        stake_updates
+            .iter()
+            .map(|(node_id, stake)| {
+                if !listed.insert(*node_id) {
+                    return Err(RainsonetError::ValidatorSetError(format!(
+                        "Stake of validator {} updated twice",
+                        node_id
+                    )));
+                }
+                self.stake_change(node_id, *stake)
+            })
+            .collect()
+    }
+    
+    /// Set a validator's stake right away, as [`ValidatorSet::stake_change`]
+    /// describes, returning the state change that persists the record.
+    ///
+    /// Only for sets outside consensus; nodes that have to agree on the set
+    /// finalize the change in a block instead.
+    pub fn update_stake(&self, node_id: &NodeId, new_stake: u128) -> RainsonetResult<StateChange> {
+        let change = self.stake_change(node_id, new_stake)?;
+        self.apply_records(std::slice::from_ref(&change))?;
+        Ok(change)
+    }
+    
+    /// Clear the inactivity record of `node_id`, returning whether it was
+    /// deactivated for inactivity
+    fn reactivate(&self, node_id: &NodeId) -> bool {
+        self.missed_rounds.remove(node_id);
+        self.inactivity_deactivated.remove(node_id).is_some()
+    }
+    
+    /// Close a finalized consensus round in which `voters` voted.
+    ///
+    /// Every other active validator has missed one more consecutive round.
+    /// The counts are local: nodes assemble certificates from different
+    /// quorums, so they only decide which validators this node proposes to
+    /// deactivate, see [`ValidatorSet::inactive_validators`].
+    pub fn record_round(&self, voters: &HashSet<NodeId>) {
+        for validator in self.active_validators() {
+            if voters.contains(&validator.node_id) {
+                self.missed_rounds.remove(&validator.node_id);
+            } else {
+                *self.missed_rounds.entry(validator.node_id).or_insert(0) += 1;
+            }
+        }
+    }
+    
+    /// Consecutive rounds `node_id` has missed
+    pub fn missed_rounds(&self, node_id: &NodeId) -> u64 {
+        self.missed_rounds.get(node_id).map(|m| *m).unwrap_or(0)
+    }
+    
+    /// Active validators that missed at least `threshold` consecutive
+    /// rounds, in node ID order, for a proposal to deactivate.
+    ///
+    /// Stops short of taking the active set below `min_validators`, and a
+    /// `threshold` of zero disables deactivation.
+    pub fn inactive_validators(&self, threshold: u64, min_validators: usize) -> Vec<NodeId> {
+        if threshold == 0 {
+            return Vec::new();
+        }
+        
+        let mut inactive: Vec<NodeId> = self
+            .active_validators()
+            .iter()
+            .map(|v| v.node_id)
+            .filter(|node_id| self.missed_rounds(node_id) >= threshold)
+            .collect();
+        inactive.sort_by(|a, b| a.as_bytes().cmp(b.as_bytes()));
+        inactive.truncate(self.active_count().saturating_sub(min_validators.max(1)));
+        inactive
+    }
+    
+    /// State changes deactivating `node_ids` for inactivity, as included
+    /// by a proposal that lists them.
+    ///
+    /// Fails unless each listed validator is active and listed once, and
+    /// at least `min_validators` stay active. The set itself is updated
+    /// once the changes are finalized, by [`ValidatorSet::apply_records`].
+    pub fn deactivation_changes(
+        &self,
+        node_ids: &[NodeId],
+        min_validators: usize,
+    ) -> RainsonetResult<Vec<StateChange>> {
+        let minimum = min_validators.max(1);
+        if self.active_count().saturating_sub(node_ids.len()) < minimum {
+            return Err(RainsonetError::ValidatorSetError(format!(
+                "Deactivating {} validators leaves fewer than {} active",
+                node_ids.len(),
+                minimum
+            )));
+        }
+        
+        let mut listed = HashSet::new();
+        let mut changes = Vec::with_capacity(node_ids.len());
+        for node_id in node_ids {
+            let mut record = self
+                .get_validator(node_id)
+                .filter(|v| v.active && listed.insert(*node_id))
+                .ok_or_else(|| {
+                    RainsonetError::ValidatorSetError(format!(
+                        "Cannot deactivate validator {}",
+                        node_id
+                    ))
+                })?;
+            record.active = false;
+            changes.push(record.to_state_change()?);
+        }
+        
+        Ok(changes)
+    }
+    
+    /// Apply the validator records among finalized `changes`, returning
+    /// the validators they deactivated for inactivity.
+    ///
+    /// Records are chain state, so every node applying the same blocks ends
+    /// up with the same set. A record deactivating a validator whose stake
+    /// still meets the minimum can only come from
+    /// [`ValidatorSet::deactivation_changes`], so it is remembered as an
+    /// inactivity deactivation that re-staking undoes.
+    pub fn apply_records(&self, changes: &[StateChange]) -> RainsonetResult<Vec<NodeId>> {
+        let mut deactivated = Vec::new();
+        let mut active_count = self.active_count.write();
+        for change in changes {
+            match change {
+                StateChange::Set { key, value } if key.starts_with(VALIDATOR_PREFIX) => {
+                    let record = ValidatorInfo::from_bytes(value)?;
+                    let was_active = self.is_validator(&record.node_id);
+                    if was_active && !record.active && record.stake >= self.min_stake {
+                        self.missed_rounds.remove(&record.node_id);
+                        self.inactivity_deactivated.insert(record.node_id, ());
+                        deactivated.push(record.node_id);
+                    } else if record.active
+                        && self.inactivity_deactivated.contains_key(&record.node_id)
+                    {
+                        self.reactivate(&record.node_id);
+                    }
+                    self.validators.insert(record.node_id, record);
+                }
+                StateChange::Delete { key } if key.starts_with(VALIDATOR_PREFIX) => {
+                    let node_id = <[u8; 32]>::try_from(&key[VALIDATOR_PREFIX.len()..])
+                        .map(NodeId::from_bytes)
+                        .map_err(|_| {
+                            RainsonetError::StateCorruption("Malformed validator key".into())
+                        })?;
+                    self.validators.remove(&node_id);
+                    self.reactivate(&node_id);
+                }
+                _ => {}
+            }
+        }
+        *active_count = self.validators.iter().filter(|v| v.active).count();
+        
+        Ok(deactivated)
+    }
+    
+    /// Compute the diff that turns this set into `next`.
+    ///
+    /// Validators missing from `next` are removed, new ones are added, and
+    /// those present in both with a different stake get a stake update.
+    pub fn diff_to(&self, next: &[ValidatorInfo]) -> ValidatorSetDiff {
+        let next_ids: HashSet<NodeId> = next.iter().map(|v| v.node_id).collect();
+        
+        let removed = self
+            .validators
+            .iter()
+            .map(|v| *v.key())
+            .filter(|id| !next_ids.contains(id))
+            .collect();
+        
+        let mut added = Vec::new();
+        let mut stake_updates = Vec::new();
+        for info in next {
+            match self.validators.get(&info.node_id) {
+                Some(current) if current.stake != info.stake => {
+                    stake_updates.push((info.node_id, info.stake));
+                }
+                Some(_) => {}
+                None => added.push(info.clone()),
+            }
+        }
+        
+        ValidatorSetDiff::new(added, removed, stake_updates)
+    }
+    
+    /// Validate `diff` against the current set and return the records it
+    /// writes: added validators, then validators with updated stakes
+    fn resolve_diff(&self, diff: &ValidatorSetDiff) -> RainsonetResult<Vec<ValidatorInfo>> {
+        let mut members: HashSet<NodeId> = self.validators.iter().map(|v| *v.key()).collect();
+        
+        for node_id in &diff.removed {
+            if !members.remove(node_id) {
+                return Err(RainsonetError::ValidatorSetError(format!(
+                    "Cannot remove unknown validator {}",
+                    node_id
+                )));
+            }
+        }
+        
+        let mut records: Vec<ValidatorInfo> = Vec::new();
+        for info in &diff.added {
+            if !members.insert(info.node_id) {
+                return Err(RainsonetError::ValidatorSetError(format!(
+                    "Validator {} is already in the set",
+                    info.node_id
+                )));
+            }
+            records.push(info.clone());
+        }
+        
+        let mut updated = HashSet::new();
+        for (node_id, stake) in &diff.stake_updates {
+            if !members.contains(node_id) || !updated.insert(*node_id) {
+                return Err(RainsonetError::ValidatorSetError(format!(
+                    "Invalid stake update for validator {}",
+                    node_id
+                )));
+            }
+            
+            let index = match records.iter().position(|r| r.node_id == *node_id) {
+                Some(index) => index,
+                None => {
+                    // Present in `members` and not added, so it is in the set
+                    let current = self
+                        .get_validator(node_id)
+                        .ok_or(RainsonetError::NotAValidator)?;
+                    records.push(current);
+                    records.len() - 1
+                }
+            };
+            
+            let record = &mut records[index];
+            record.stake = *stake;
+            if record.active && *stake < self.min_stake {
+                record.active = false;
+            } else if !record.active
+                && *stake >= self.min_stake
+                && self.inactivity_deactivated.contains_key(node_id)
+            {
+                record.active = true;
+            }
+        }
+        
+        Ok(records)
+    }
+    
+    /// State changes that [`ValidatorSet::apply_diff`] would return, without
+    /// modifying the set
+    pub fn preview_diff(&self, diff: &ValidatorSetDiff) -> RainsonetResult<Vec<StateChange>> {
+        let records = self.resolve_diff(diff)?;
+        Self::diff_changes(diff, &records)
+    }
+    
+    /// Deletions for removed validators followed by the resolved records
+    fn diff_changes(
+        diff: &ValidatorSetDiff,
+        records: &[ValidatorInfo],
+    ) -> RainsonetResult<Vec<StateChange>> {
+        let mut changes: Vec<StateChange> = diff
+            .removed
+            .iter()
+            .map(|node_id| StateChange::Delete {
+                key: rainsonet_state::validator_key(node_id.as_bytes()),
+            })
+            .collect();
+        for record in records {
+            changes.push(record.to_state_change()?);
+        }
+        
+        Ok(changes)
+    }
+    
+    /// Apply an epoch diff, returning the state changes that persist it.
+    ///
+    /// The whole diff is validated before anything is modified, so an invalid
+    /// diff leaves the set untouched. Removals are applied first, then
+    /// additions, then stake updates, which deactivate validators that fall
+    /// below the minimum stake and reactivate those deactivated for
+    /// inactivity exactly as [`ValidatorSet::update_stake`] does.
+    pub fn apply_diff(&self, diff: &ValidatorSetDiff) -> RainsonetResult<Vec<StateChange>> {
+        let records = self.resolve_diff(diff)?;
+        let changes = Self::diff_changes(diff, &records)?;
+        
+        let mut active_count = self.active_count.write();
+        for node_id in &diff.removed {
+            self.validators.remove(node_id);
+        }
+        for record in records {
+            if record.active && self.inactivity_deactivated.contains_key(&record.node_id) {
+                self.reactivate(&record.node_id);
+            }
+            self.validators.insert(record.node_id, record);
+        }
+        *active_count = self.validators.iter().filter(|v| v.active).count();
+        
+        Ok(changes)
+    }
+    
     /// Check if a node is a validator
     pub fn is_validator(&self, node_id: &NodeId) -> bool {
         self.validators
@@ -105,10 +563,62 @@ impl ValidatorSet {
         *self.active_count.read()
     }
     
-    /// Calculate required votes for consensus (2/3 majority)
+    /// Record a vote by `node_id` on a proposal for `version`
+    pub fn record_vote(&self, node_id: &NodeId, version: StateVersion) {
+        let mut last = self.last_voted.entry(*node_id).or_insert(version);
+        if version > *last {
+            *last = version;
+        }
+    }
+    
+    /// Highest proposal version `node_id` voted on
+    pub fn last_voted_version(&self, node_id: &NodeId) -> Option<StateVersion> {
+        self.last_voted.get(node_id).map(|v| *v)
+    }
+    
+    /// Voting participation of every active validator at version `current`.
+    ///
+    /// A validator is inactive when it voted on none of the last `window`
+    /// versions; one that never voted counts from version 0.
+    pub fn liveness(&self, current: StateVersion, window: u64) -> Vec<ValidatorLiveness> {
+        let mut liveness: Vec<ValidatorLiveness> = self
+            .active_validators()
+            .into_iter()
+            .map(|v| {
+                let last_voted_version = self.last_voted_version(&v.node_id);
+                let last = last_voted_version.map(|version| version.0).unwrap_or(0);
+                ValidatorLiveness {
+                    node_id: v.node_id,
+                    stake: v.stake,
+                    last_voted_version,
+                    inactive: current.0.saturating_sub(last) >= window,
+                }
+            })
+            .collect();
+        liveness.sort_by(|a, b| a.node_id.as_bytes().cmp(b.node_id.as_bytes()));
+        liveness
+    }
+    
+    /// Calculate required votes for consensus, see [`quorum_size`]
     pub fn required_votes(&self) -> usize {
-        let count = self.active_count();
-        (count * 2 / 3) + 1
+        quorum_size(self.active_count())
+    }
+    
+    /// How proposals finalize with the current active validators under
+    /// `config`. An empty set is always insufficient.
+    pub fn vote_requirement(&self, config: &ConsensusConfig) -> VoteRequirement {
+        let active = self.active_count();
+        let minimum = config.min_validators.max(1);
+        if active < minimum {
+            VoteRequirement::Insufficient { active, minimum }
+        } else if active == 1 {
+            VoteRequirement::SingleNode
+        } else {
+            VoteRequirement::Bft {
+                required: quorum_size(active),
+                total: active,
+            }
+        }
     }
     
     /// Total stake of active validators
@@ -120,6 +630,14 @@ impl ValidatorSet {
             .sum()
     }
     
+    /// Stake needed for a stake-weighted quorum: strictly more than two
+    /// thirds of the active stake, mirroring [`quorum_size`]
+    pub fn required_stake(&self) -> u128 {
+        let total = self.total_stake();
+        // floor(2 * total / 3) without overflowing u128
+        total / 3 * 2 + (total % 3) * 2 / 3 + 1
+    }
+    
     /// Verify a signature from a validator
     pub fn verify_signature(
         &self,
@@ -170,6 +688,13 @@ impl LocalValidator {
     }
 }
 
+/// Lets the network confirm the role peers claim in their handshakes
+impl rainsonet_p2p::ValidatorRegistry for ValidatorSet {
+    fn is_validator(&self, node_id: &NodeId) -> bool {
+        ValidatorSet::is_validator(self, node_id)
+    }
+}
+
 /// Shared validator set
 pub type SharedValidatorSet = Arc<ValidatorSet>;
 
@@ -196,10 +721,139 @@ mod tests {
         let set = ValidatorSet::with_validators(vec![v1.clone(), v2, v3]);
         
         assert_eq!(set.active_count(), 3);
-        assert_eq!(set.required_votes(), 3); // 2/3 of 3 + 1 = 3
+        assert_eq!(set.required_votes(), 3); // floor(2*3/3) + 1 = 3, no faults tolerated
         assert!(set.is_validator(&v1.node_id));
     }
     
+    #[test]
+    fn test_silent_validator_flagged_inactive() {
+        let kp1 = KeyPair::generate();
+        let kp2 = KeyPair::generate();
+        let set = ValidatorSet::with_validators(vec![
+            ValidatorInfo::new(kp1.node_id(), kp1.public_key(), 1000),
+            ValidatorInfo::new(kp2.node_id(), kp2.public_key(), 1000),
+        ]);
+        let inactive = |current: u64| -> Vec<NodeId> {
+            set.liveness(StateVersion::new(current), 5)
+                .into_iter()
+                .filter(|l| l.inactive)
+                .map(|l| l.node_id)
+                .collect()
+        };
+        
+        // Both vote up to version 3, then only the first keeps voting
+        for version in 1..=10 {
+            set.record_vote(&kp1.node_id(), StateVersion::new(version));
+            if version <= 3 {
+                set.record_vote(&kp2.node_id(), StateVersion::new(version));
+            }
+        }
+        
+        assert!(inactive(7).is_empty());
+        assert_eq!(inactive(8), vec![kp2.node_id()]);
+        assert_eq!(inactive(10), vec![kp2.node_id()]);
+        assert_eq!(set.last_voted_version(&kp2.node_id()), Some(StateVersion::new(3)));
+        
+        // A late vote on an older version does not move the mark back
+        set.record_vote(&kp1.node_id(), StateVersion::new(2));
+        assert_eq!(set.last_voted_version(&kp1.node_id()), Some(StateVersion::new(10)));
+    }
+    
+    #[test]
+    fn test_inactivity_deactivation_applies_from_records() {
+        let validators: Vec<ValidatorInfo> = (0..4).map(|_| validator_info(1000)).collect();
+        let silent = validators[3].node_id;
+        let voters: HashSet<NodeId> = validators[..3].iter().map(|v| v.node_id).collect();
+        let proposer = ValidatorSet::with_validators(validators.clone()).with_min_stake(500);
+        let follower = ValidatorSet::with_validators(validators).with_min_stake(500);
+        
+        // Missed rounds only pick candidates on the node that counted them
+        for _ in 0..2 {
+            proposer.record_round(&voters);
+        }
+        assert_eq!(proposer.missed_rounds(&silent), 2);
+        assert!(proposer.inactive_validators(3, 1).is_empty());
+        assert!(proposer.inactive_validators(0, 1).is_empty());
+        assert_eq!(proposer.inactive_validators(2, 1), vec![silent]);
+        assert!(proposer.inactive_validators(2, 4).is_empty());
+        
+        // Never below the configured minimum, nor twice the same validator
+        assert!(proposer.deactivation_changes(&[silent], 4).is_err());
+        assert!(proposer.deactivation_changes(&[silent, silent], 1).is_err());
+        let changes = proposer.deactivation_changes(&[silent], 3).unwrap();
+        assert!(proposer.is_validator(&silent));
+        
+        // Both sets follow the finalized records alike
+        for set in [&proposer, &follower] {
+            assert_eq!(set.apply_records(&changes).unwrap(), vec![silent]);
+            assert!(!set.is_validator(&silent));
+            assert_eq!(set.active_count(), 3);
+            assert_eq!(set.missed_rounds(&silent), 0);
+            
+            set.update_stake(&silent, 1000).unwrap();
+            assert!(set.is_validator(&silent));
+        }
+    }
+    
+    #[test]
+    fn test_quorum_size_table() {
+        let expected = [(1, 1), (2, 2), (3, 3), (4, 3), (7, 5)];
+        
+        for (n, required) in expected {
+            let validators: Vec<ValidatorInfo> = (0..n)
+                .map(|_| {
+                    let kp = KeyPair::generate();
+                    ValidatorInfo::new(kp.node_id(), kp.public_key(), 1000)
+                })
+                .collect();
+            let set = ValidatorSet::with_validators(validators);
+            
+            assert_eq!(quorum_size(n), required, "n = {}", n);
+            assert_eq!(set.required_votes(), required, "n = {}", n);
+            // Two quorums always share at least one validator
+            assert!(2 * required > n, "n = {}", n);
+        }
+    }
+    
+    #[test]
+    fn test_vote_requirement_variants() {
+        let set_of = |n: usize| {
+            ValidatorSet::with_validators((0..n).map(|_| validator_info(1000)).collect())
+        };
+        let config = |min_validators| ConsensusConfig {
+            min_validators,
+            ..ConsensusConfig::devnet()
+        };
+        
+        // An empty set never finalizes, whatever the configured minimum
+        assert_eq!(
+            set_of(0).vote_requirement(&config(0)),
+            VoteRequirement::Insufficient { active: 0, minimum: 1 }
+        );
+        assert_eq!(
+            set_of(0).vote_requirement(&config(3)),
+            VoteRequirement::Insufficient { active: 0, minimum: 3 }
+        );
+        
+        // A lone validator runs only where single-node mode is configured
+        assert_eq!(set_of(1).vote_requirement(&config(1)), VoteRequirement::SingleNode);
+        assert_eq!(
+            set_of(1).vote_requirement(&config(3)),
+            VoteRequirement::Insufficient { active: 1, minimum: 3 }
+        );
+        
+        for min_validators in [1, 4] {
+            assert_eq!(
+                set_of(4).vote_requirement(&config(min_validators)),
+                VoteRequirement::Bft { required: 3, total: 4 }
+            );
+        }
+        assert_eq!(
+            set_of(4).vote_requirement(&config(5)),
+            VoteRequirement::Insufficient { active: 4, minimum: 5 }
+        );
+    }
+    
     #[test]
     fn test_local_validator_signing() {
         let kp = KeyPair::generate();
@@ -213,4 +867,138 @@ mod tests {
         
         assert!(set.verify_signature(&local.node_id(), message, &signature).is_ok());
     }
+    
+    #[test]
+    fn test_update_stake() {
+        let kp1 = KeyPair::generate();
+        let kp2 = KeyPair::generate();
+        let v1 = ValidatorInfo::new(kp1.node_id(), kp1.public_key(), 1000);
+        let v2 = ValidatorInfo::new(kp2.node_id(), kp2.public_key(), 2000);
+        let set = ValidatorSet::with_validators(vec![v1, v2]).with_min_stake(500);
+        
+        assert_eq!(set.total_stake(), 3000);
+        assert_eq!(set.required_stake(), 2001);
+        
+        let change = set.update_stake(&kp1.node_id(), 4000).unwrap();
+        assert_eq!(set.total_stake(), 6000);
+        assert_eq!(set.required_stake(), 4001);
+        
+        // The returned change persists the updated record
+        match change {
+            StateChange::Set { key, value } => {
+                assert_eq!(key, rainsonet_state::validator_key(kp1.node_id().as_bytes()));
+                assert_eq!(ValidatorInfo::from_bytes(&value).unwrap().stake, 4000);
+            }
+            other => panic!("unexpected change {:?}", other),
+        }
+        
+        assert!(set.update_stake(&KeyPair::generate().node_id(), 1).is_err());
+    }
+    
+    #[test]
+    fn test_stake_changes_apply_once_finalized() {
+        let v1 = validator_info(1000);
+        let v2 = validator_info(1000);
+        let (id1, id2) = (v1.node_id, v2.node_id);
+        let set = ValidatorSet::with_validators(vec![v1, v2]).with_min_stake(500);
+        
+        let changes = set.stake_changes(&[(id1, 4000), (id2, 499)]).unwrap();
+        assert_eq!(set.total_stake(), 2000);
+        assert!(set.is_validator(&id2));
+        
+        set.apply_records(&changes).unwrap();
+        assert_eq!(set.total_stake(), 4000);
+        assert!(!set.is_validator(&id2));
+        
+        assert!(set.stake_changes(&[(id1, 1), (id1, 2)]).is_err());
+        assert!(set.stake_changes(&[(validator_info(1).node_id, 1)]).is_err());
+    }
+    
+    #[test]
+    fn test_stake_below_minimum_deactivates() {
+        let kp1 = KeyPair::generate();
+        let kp2 = KeyPair::generate();
+        let v1 = ValidatorInfo::new(kp1.node_id(), kp1.public_key(), 1000);
+        let v2 = ValidatorInfo::new(kp2.node_id(), kp2.public_key(), 1000);
+        let set = ValidatorSet::with_validators(vec![v1, v2]).with_min_stake(500);
+        
+        set.update_stake(&kp1.node_id(), 499).unwrap();
+        
+        assert!(!set.is_validator(&kp1.node_id()));
+        assert_eq!(set.active_count(), 1);
+        assert_eq!(set.total_stake(), 1000);
+        assert_eq!(set.required_votes(), 1);
+        
+        // Deactivation is not undone by topping the stake back up
+        set.update_stake(&kp1.node_id(), 1000).unwrap();
+        assert!(!set.is_validator(&kp1.node_id()));
+        assert_eq!(set.active_count(), 1);
+    }
+    
+    fn validator_info(stake: u128) -> ValidatorInfo {
+        let kp = KeyPair::generate();
+        ValidatorInfo::new(kp.node_id(), kp.public_key(), stake)
+    }
+    
+    fn sorted_validators(set: &ValidatorSet) -> Vec<ValidatorInfo> {
+        let mut validators: Vec<ValidatorInfo> = set.validators.iter().map(|v| v.clone()).collect();
+        validators.sort_by(|a, b| a.node_id.as_bytes().cmp(b.node_id.as_bytes()));
+        validators
+    }
+    
+    #[test]
+    fn test_apply_diff_is_deterministic() {
+        let initial = vec![validator_info(1000), validator_info(2000), validator_info(3000)];
+        let joining = validator_info(4000);
+        
+        let mut next = vec![initial[1].clone(), joining.clone()];
+        next[0].stake = 100;
+        next.push(ValidatorInfo { stake: 5000, ..initial[2].clone() });
+        
+        let a = ValidatorSet::with_validators(initial.clone()).with_min_stake(500);
+        let b = ValidatorSet::with_validators(initial.clone()).with_min_stake(500);
+        let diff = a.diff_to(&next);
+        assert_eq!(diff.removed, vec![initial[0].node_id]);
+        assert_eq!(diff.added, vec![joining.clone()]);
+        assert_eq!(diff.stake_updates.len(), 2);
+        
+        // Both nodes computed the same diff independently
+        assert_eq!(diff.hash().unwrap(), b.diff_to(&next).hash().unwrap());
+        
+        let changes_a = a.apply_diff(&diff).unwrap();
+        let changes_b = b.apply_diff(&diff).unwrap();
+        assert_eq!(format!("{:?}", changes_a), format!("{:?}", changes_b));
+        
+        assert_eq!(sorted_validators(&a), sorted_validators(&b));
+        assert_eq!(a.active_count(), b.active_count());
+        assert_eq!(a.total_stake(), b.total_stake());
+        assert_eq!(a.required_stake(), b.required_stake());
+        
+        // The validator dropped below the minimum stake and was deactivated
+        assert!(!a.is_validator(&initial[1].node_id));
+        assert!(a.get_validator(&initial[0].node_id).is_none());
+        assert_eq!(a.active_count(), 2);
+        assert_eq!(a.total_stake(), 9000);
+    }
+    
+    #[test]
+    fn test_invalid_diff_leaves_set_untouched() {
+        let existing = validator_info(1000);
+        let set = ValidatorSet::with_validators(vec![existing.clone()]);
+        
+        let diff = ValidatorSetDiff::new(
+            vec![validator_info(2000)],
+            vec![KeyPair::generate().node_id()],
+            vec![],
+        );
+        assert!(matches!(
+            set.apply_diff(&diff),
+            Err(RainsonetError::ValidatorSetError(_))
+        ));
+        assert_eq!(sorted_validators(&set), vec![existing.clone()]);
+        
+        let diff = ValidatorSetDiff::new(vec![existing.clone()], vec![], vec![]);
+        assert!(set.apply_diff(&diff).is_err());
+        assert_eq!(set.active_count(), 1);
+    }
 }
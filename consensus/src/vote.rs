@@ -1,8 +1,14 @@
 //! Vote handling for consensus
 
-use rainsonet_core::{Hash, NodeId, Signature, StateRoot, StateVersion, Timestamp};
+use crate::validator::ValidatorSet;
+use rainsonet_core::{
+    Hash, NodeId, RainsonetError, RainsonetResult, Signature, StateRoot, StateVersion, Timestamp,
+};
 use rainsonet_crypto::hashing::hash_multiple;
+use rainsonet_crypto::signing::verify_batch;
+use rainsonet_p2p::{CertificateMessage, VoteMessage};
 use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
 
 /// Vote on a proposal
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -33,8 +39,27 @@ impl Vote {
         state_root: StateRoot,
         sign_fn: impl FnOnce(&[u8]) -> Signature,
     ) -> Self {
-        let timestamp = Timestamp::now();
-        
+        Self::new_at(
+            proposal_id,
+            voter,
+            approve,
+            state_version,
+            state_root,
+            Timestamp::now(),
+            sign_fn,
+        )
+    }
+    
+    /// Create a new vote timestamped at `timestamp`
+    pub fn new_at(
+        proposal_id: Hash,
+        voter: NodeId,
+        approve: bool,
+        state_version: StateVersion,
+        state_root: StateRoot,
+        timestamp: Timestamp,
+        sign_fn: impl FnOnce(&[u8]) -> Signature,
+    ) -> Self {
         let sign_msg = Self::signing_message(
             &proposal_id,
             &voter,
@@ -91,8 +116,40 @@ impl Vote {
     
     /// Check if vote is expired
     pub fn is_expired(&self, timeout_ms: u64) -> bool {
-        let now = Timestamp::now();
-        now.as_millis() - self.timestamp.as_millis() > timeout_ms
+        self.is_expired_at(Timestamp::now(), timeout_ms)
+    }
+    
+    /// Check if vote is expired as of `now`
+    pub fn is_expired_at(&self, now: Timestamp, timeout_ms: u64) -> bool {
+        now.as_millis().saturating_sub(self.timestamp.as_millis()) > timeout_ms
+    }
+}
+
+impl From<&Vote> for VoteMessage {
+    fn from(vote: &Vote) -> Self {
+        Self {
+            proposal_id: vote.proposal_id,
+            voter: vote.voter,
+            approve: vote.approve,
+            state_version: vote.state_version,
+            state_root: vote.state_root,
+            signature: vote.signature,
+            timestamp: vote.timestamp,
+        }
+    }
+}
+
+impl From<VoteMessage> for Vote {
+    fn from(msg: VoteMessage) -> Self {
+        Self {
+            proposal_id: msg.proposal_id,
+            voter: msg.voter,
+            approve: msg.approve,
+            state_version: msg.state_version,
+            state_root: msg.state_root,
+            signature: msg.signature,
+            timestamp: msg.timestamp,
+        }
     }
 }
 
@@ -158,13 +215,24 @@ impl FinalityCertificate {
         state_version: StateVersion,
         state_root: StateRoot,
         votes: Vec<Vote>,
+    ) -> Self {
+        Self::new_at(proposal_id, state_version, state_root, votes, Timestamp::now())
+    }
+    
+    /// Create a certificate finalized at `finalized_at`
+    pub fn new_at(
+        proposal_id: Hash,
+        state_version: StateVersion,
+        state_root: StateRoot,
+        votes: Vec<Vote>,
+        finalized_at: Timestamp,
     ) -> Self {
         Self {
             proposal_id,
             state_version,
             state_root,
             votes,
-            finalized_at: Timestamp::now(),
+            finalized_at,
         }
     }
     
@@ -174,10 +242,79 @@ impl FinalityCertificate {
         approvals >= required_votes
     }
     
+    /// Verify the certificate against a validator set: every vote must be
+    /// for this proposal and signed by a validator, and distinct approvals
+    /// must reach the set's quorum.
+    ///
+    /// The signatures are checked as one batch; a bad one fails with
+    /// `InvalidBatchSignature` naming the index of its vote.
+    pub fn verify_full(&self, validator_set: &ValidatorSet) -> RainsonetResult<()> {
+        let mut approvers = HashSet::new();
+        let mut signed = Vec::with_capacity(self.votes.len());
+        for vote in &self.votes {
+            if vote.proposal_id != self.proposal_id {
+                return Err(RainsonetError::InvalidVote(
+                    "Vote is for a different proposal".into(),
+                ));
+            }
+            let public_key = validator_set
+                .get_public_key(&vote.voter)
+                .ok_or(RainsonetError::NotAValidator)?;
+            signed.push((public_key, vote.get_signing_message(), vote.signature));
+            if vote.approve {
+                approvers.insert(vote.voter);
+            }
+        }
+        verify_batch(&signed)?;
+        
+        if approvers.len() < validator_set.required_votes() {
+            return Err(RainsonetError::ConsensusNotReached);
+        }
+        
+        Ok(())
+    }
+    
     /// Get voter node IDs
     pub fn voters(&self) -> Vec<NodeId> {
         self.votes.iter().map(|v| v.voter).collect()
     }
+    
+    /// Hash that the next version's proposal references.
+    ///
+    /// Covers what was finalized but not the votes or `finalized_at`, which
+    /// differ between nodes that assembled a certificate from different
+    /// quorums.
+    pub fn hash(&self) -> Hash {
+        hash_multiple(&[
+            self.proposal_id.as_bytes(),
+            &self.state_version.0.to_le_bytes(),
+            self.state_root.as_bytes(),
+        ])
+    }
+}
+
+impl From<&FinalityCertificate> for CertificateMessage {
+    fn from(certificate: &FinalityCertificate) -> Self {
+        Self {
+            proposal_id: certificate.proposal_id,
+            state_version: certificate.state_version,
+            state_root: certificate.state_root,
+            votes: certificate.votes.iter().map(VoteMessage::from).collect(),
+            finalized_at: certificate.finalized_at,
+        }
+    }
+}
+
+impl From<CertificateMessage> for FinalityCertificate {
+    fn from(msg: CertificateMessage) -> Self {
+        Self {
+            proposal_id: msg.proposal_id,
+            state_version: msg.state_version,
+            state_root: msg.state_root,
+            votes: msg.votes.into_iter().map(Vote::from).collect(),
+            finalized_at: msg.finalized_at,
+        }
+    }
 }
 
 #[cfg(test)]
@@ -205,6 +342,38 @@ mod tests {
         assert_eq!(vote.voter, node_id);
     }
     
+    #[test]
+    fn test_vote_message_round_trip() {
+        use rainsonet_p2p::Message;
+        
+        let kp = KeyPair::generate();
+        let vote = Vote::new(
+            Hash::from_bytes([1u8; 32]),
+            kp.node_id(),
+            false,
+            StateVersion::new(2),
+            Hash::from_bytes([5u8; 32]),
+            |msg| sign(&kp, msg),
+        );
+        
+        let bytes = Message::Vote(VoteMessage::from(&vote)).to_bytes();
+        let received = match Message::from_bytes(&bytes) {
+            Some(Message::Vote(msg)) => Vote::from(msg),
+            other => panic!("unexpected message {:?}", other),
+        };
+        
+        assert_eq!(
+            bincode::serialize(&received).unwrap(),
+            bincode::serialize(&vote).unwrap()
+        );
+        assert!(rainsonet_crypto::signing::verify(
+            &kp.public_key(),
+            &received.get_signing_message(),
+            &received.signature
+        )
+        .is_ok());
+    }
+    
     #[test]
     fn test_vote_collection() {
         let mut collection = VoteCollection::new();
@@ -226,4 +395,40 @@ mod tests {
         assert_eq!(collection.votes_against, 1);
         assert!(collection.has_consensus(2)); // 2/3 majority
     }
+    
+    #[test]
+    fn test_certificate_names_bad_vote() {
+        use crate::validator::ValidatorInfo;
+        
+        let proposal_id = Hash::from_bytes([1u8; 32]);
+        let validator_set = ValidatorSet::new();
+        let mut votes = Vec::new();
+        for _ in 0..30 {
+            let kp = KeyPair::generate();
+            validator_set.add_validator(ValidatorInfo::new(kp.node_id(), kp.public_key(), 1000));
+            votes.push(Vote::new(
+                proposal_id,
+                kp.node_id(),
+                true,
+                StateVersion::new(1),
+                Hash::ZERO,
+                |msg| sign(&kp, msg),
+            ));
+        }
+        
+        let certificate = FinalityCertificate::new(
+            proposal_id,
+            StateVersion::new(1),
+            Hash::ZERO,
+            votes.clone(),
+        );
+        assert!(certificate.verify_full(&validator_set).is_ok());
+        
+        votes[12].signature = votes[13].signature;
+        let forged = FinalityCertificate::new(proposal_id, StateVersion::new(1), Hash::ZERO, votes);
+        assert!(matches!(
+            forged.verify_full(&validator_set),
+            Err(RainsonetError::InvalidBatchSignature { index: 12 })
+        ));
+    }
 }
@@ -0,0 +1,195 @@
+//! Bech32 encoding (BIP 173)
+//!
+//! A human-readable prefix, a `1` separator, base32 data and a six-character
+//! checksum that catches any single-character typo.
+
+/// Base32 alphabet, indexed by 5-bit value
+const CHARSET: &[u8; 32] = b"qpzry9x8gf2tvdw0s3jn54khce6mua7l";
+
+/// Separates the human-readable prefix from the data
+const SEPARATOR: char = '1';
+
+/// Checksum length in characters
+const CHECKSUM_LEN: usize = 6;
+
+/// Longest valid bech32 string; the checksum is only guaranteed to catch
+/// errors up to this length
+const MAX_LEN: usize = 90;
+
+/// BCH code generator
+const GENERATOR: [u32; 5] = [0x3b6a57b2, 0x26508e6d, 0x1ea119fa, 0x3d4233dd, 0x2a1462b3];
+
+/// Error decoding a bech32 string
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum Bech32Error {
+    #[error("bech32 string mixes upper and lower case")]
+    MixedCase,
+    
+    #[error("bech32 string has no '1' separator")]
+    MissingSeparator,
+    
+    #[error("bech32 string is {0} characters, the limit is 90")]
+    TooLong(usize),
+    
+    #[error("invalid bech32 character {c:?} at position {index}")]
+    InvalidCharacter { c: char, index: usize },
+    
+    #[error("bech32 checksum mismatch")]
+    InvalidChecksum,
+    
+    #[error("bech32 data has non-zero padding")]
+    InvalidPadding,
+    
+    #[error("expected prefix {expected:?}, got {got:?}")]
+    WrongPrefix { expected: String, got: String },
+    
+    #[error("expected {expected} bytes, got {got}")]
+    InvalidLength { expected: usize, got: usize },
+}
+
+fn polymod(values: impl IntoIterator<Item = u8>) -> u32 {
+    let mut chk = 1u32;
+    for value in values {
+        let top = chk >> 25;
+        chk = ((chk & 0x1ff_ffff) << 5) ^ u32::from(value);
+        for (i, g) in GENERATOR.iter().enumerate() {
+            if (top >> i) & 1 == 1 {
+                chk ^= g;
+            }
+        }
+    }
+    chk
+}
+
+fn hrp_expand(hrp: &str) -> impl Iterator<Item = u8> + '_ {
+    hrp.bytes()
+        .map(|b| b >> 5)
+        .chain(std::iter::once(0))
+        .chain(hrp.bytes().map(|b| b & 31))
+}
+
+/// Regroup `data` from `from`-bit to `to`-bit values
+fn convert_bits(data: &[u8], from: u32, to: u32, pad: bool) -> Result<Vec<u8>, Bech32Error> {
+    let max_value = (1u32 << to) - 1;
+    let max_acc = (1u32 << (from + to - 1)) - 1;
+    let mut acc = 0u32;
+    let mut bits = 0u32;
+    let mut out = Vec::with_capacity(data.len() * from as usize / to as usize + 1);
+    
+    for &value in data {
+        acc = ((acc << from) | u32::from(value)) & max_acc;
+        bits += from;
+        while bits >= to {
+            bits -= to;
+            out.push(((acc >> bits) & max_value) as u8);
+        }
+    }
+    
+    if pad {
+        if bits > 0 {
+            out.push(((acc << (to - bits)) & max_value) as u8);
+        }
+    } else if bits >= from || (acc << (to - bits)) & max_value != 0 {
+        return Err(Bech32Error::InvalidPadding);
+    }
+    Ok(out)
+}
+
+/// Encode `data` under the lowercase prefix `hrp`
+pub fn encode(hrp: &str, data: &[u8]) -> String {
+    let values = convert_bits(data, 8, 5, true).expect("padded conversion cannot fail");
+    let checksum = polymod(
+        hrp_expand(hrp)
+            .chain(values.iter().copied())
+            .chain([0; CHECKSUM_LEN]),
+    ) ^ 1;
+    
+    let mut out = String::with_capacity(hrp.len() + 1 + values.len() + CHECKSUM_LEN);
+    out.push_str(hrp);
+    out.push(SEPARATOR);
+    out.extend(values.iter().map(|&v| CHARSET[v as usize] as char));
+    out.extend((0..CHECKSUM_LEN).map(|i| {
+        CHARSET[((checksum >> (5 * (CHECKSUM_LEN - 1 - i))) & 31) as usize] as char
+    }));
+    out
+}
+
+/// Decode a bech32 string into its lowercase prefix and data bytes
+pub fn decode(s: &str) -> Result<(String, Vec<u8>), Bech32Error> {
+    if s.len() > MAX_LEN {
+        return Err(Bech32Error::TooLong(s.len()));
+    }
+    let has_lower = s.chars().any(|c| c.is_ascii_lowercase());
+    let has_upper = s.chars().any(|c| c.is_ascii_uppercase());
+    if has_lower && has_upper {
+        return Err(Bech32Error::MixedCase);
+    }
+    let s = s.to_ascii_lowercase();
+    
+    let split = match s.rfind(SEPARATOR) {
+        Some(split) if split > 0 => split,
+        _ => return Err(Bech32Error::MissingSeparator),
+    };
+    let (hrp, data) = (&s[..split], &s[split + 1..]);
+    
+    if let Some((index, c)) = hrp.char_indices().find(|(_, c)| !matches!(c, '!'..='~')) {
+        return Err(Bech32Error::InvalidCharacter { c, index });
+    }
+    let values = data
+        .char_indices()
+        .map(|(i, c)| {
+            CHARSET
+                .iter()
+                .position(|&b| b as char == c)
+                .map(|v| v as u8)
+                .ok_or(Bech32Error::InvalidCharacter { c, index: split + 1 + i })
+        })
+        .collect::<Result<Vec<u8>, _>>()?;
+    
+    if values.len() < CHECKSUM_LEN
+        || polymod(hrp_expand(hrp).chain(values.iter().copied())) != 1
+    {
+        return Err(Bech32Error::InvalidChecksum);
+    }
+    
+    let bytes = convert_bits(&values[..values.len() - CHECKSUM_LEN], 5, 8, false)?;
+    Ok((hrp.to_string(), bytes))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_bip173_vector() {
+        let (hrp, data) = decode("abcdef1qpzry9x8gf2tvdw0s3jn54khce6mua7lmqqqxw").unwrap();
+        assert_eq!(hrp, "abcdef");
+        assert_eq!(hex::encode(&data), "00443214c74254b635cf84653a56d7c675be77df");
+        assert_eq!(encode(&hrp, &data), "abcdef1qpzry9x8gf2tvdw0s3jn54khce6mua7lmqqqxw");
+        
+        // Upper case is valid, mixed case is not
+        assert!(decode("ABCDEF1QPZRY9X8GF2TVDW0S3JN54KHCE6MUA7LMQQQXW").is_ok());
+        assert_eq!(
+            decode("abcdef1Qpzry9x8gf2tvdw0s3jn54khce6mua7lmqqqxw"),
+            Err(Bech32Error::MixedCase)
+        );
+    }
+    
+    #[test]
+    fn test_malformed() {
+        assert_eq!(decode("qpzry9x8gf"), Err(Bech32Error::MissingSeparator));
+        assert_eq!(decode("1qpzry9x8gf"), Err(Bech32Error::MissingSeparator));
+        assert_eq!(
+            decode("abc1qpzbry"),
+            Err(Bech32Error::InvalidCharacter { c: 'b', index: 7 })
+        );
+        assert_eq!(decode("abc1qpz"), Err(Bech32Error::InvalidChecksum));
+        
+        // Valid checksums past 90 characters are still rejected
+        let long = encode("abc", &[0u8; 51]);
+        assert_eq!(decode(&long), Err(Bech32Error::TooLong(long.len())));
+        let limit = encode("abc", &[0u8; 50]);
+        assert_eq!(limit.len(), 90);
+        assert!(decode(&limit).is_ok());
+    }
+}
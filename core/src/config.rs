@@ -3,6 +3,8 @@
 use serde::{Deserialize, Serialize};
 use std::path::PathBuf;
 
+use crate::types::{Address, AddressError, Amount, DEFAULT_ADDRESS_HRP};
+
 /// Main node configuration
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct NodeConfig {
@@ -21,8 +23,33 @@ pub struct NodeConfig {
     /// API configuration
     pub api: ApiConfig,
     
-    /// Logging level
+    /// Async runtime configuration
+    #[serde(default)]
+    pub runtime: RuntimeConfig,
+    
+    /// Logging level, or a filter directive like `info,rainsonet_p2p=debug`
     pub log_level: String,
+    
+    /// Log output format
+    #[serde(default)]
+    pub log_format: LogFormat,
+    
+    /// Write logs to this file instead of stdout
+    #[serde(default)]
+    pub log_file: Option<PathBuf>,
+    
+    /// Key-value database for persistent state
+    #[serde(default)]
+    pub storage_backend: StorageBackend,
+    
+    /// Versions of state history and transaction receipts kept behind the
+    /// finalized version, zero to keep all of them
+    #[serde(default = "default_history_retention_versions")]
+    pub history_retention_versions: u64,
+    
+    /// How addresses are shown and which bech32 prefix is accepted
+    #[serde(default)]
+    pub address: AddressConfig,
 }
 
 impl Default for NodeConfig {
@@ -33,11 +60,143 @@ impl Default for NodeConfig {
             network: NetworkConfig::default(),
             consensus: ConsensusConfig::default(),
             api: ApiConfig::default(),
+            runtime: RuntimeConfig::default(),
             log_level: "info".to_string(),
+            log_format: LogFormat::default(),
+            log_file: None,
+            storage_backend: StorageBackend::default(),
+            history_retention_versions: default_history_retention_versions(),
+            address: AddressConfig::default(),
+        }
+    }
+}
+
+fn default_history_retention_versions() -> u64 {
+    10_000
+}
+
+impl NodeConfig {
+    /// Settings for a devnet, where a single validator may finalize alone
+    pub fn devnet() -> Self {
+        Self {
+            consensus: ConsensusConfig::devnet(),
+            ..Self::default()
         }
     }
 }
 
+/// Log output format
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogFormat {
+    /// Multi-line, human-readable
+    #[default]
+    Pretty,
+    /// One JSON object per line, for log aggregation
+    Json,
+    /// Single-line, human-readable
+    Compact,
+}
+
+/// Key-value database behind the persistent state store
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum StorageBackend {
+    /// Embedded sled database
+    #[default]
+    Sled,
+    /// RocksDB, for large state; needs the `rocksdb` feature
+    RocksDb,
+}
+
+/// How addresses are displayed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AddressFormat {
+    /// 64 hex characters
+    #[default]
+    Hex,
+    /// Prefixed and checksummed, e.g. `rlyo1...`
+    Bech32,
+}
+
+impl std::str::FromStr for AddressFormat {
+    type Err = String;
+    
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "hex" => Ok(AddressFormat::Hex),
+            "bech32" => Ok(AddressFormat::Bech32),
+            other => Err(format!("unknown address format {:?}, expected hex or bech32", other)),
+        }
+    }
+}
+
+/// Address display format and bech32 prefix
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AddressConfig {
+    /// Format addresses are shown in
+    pub format: AddressFormat,
+    
+    /// Human-readable bech32 prefix; addresses in either format are accepted
+    pub hrp: String,
+}
+
+impl Default for AddressConfig {
+    fn default() -> Self {
+        Self {
+            format: AddressFormat::default(),
+            hrp: DEFAULT_ADDRESS_HRP.to_string(),
+        }
+    }
+}
+
+impl AddressConfig {
+    /// Show `address` in the configured format
+    pub fn render(&self, address: &Address) -> String {
+        match self.format {
+            AddressFormat::Hex => address.to_hex(),
+            AddressFormat::Bech32 => address.to_bech32(&self.hrp),
+        }
+    }
+    
+    /// Parse an address in either format, whatever the display format
+    pub fn parse(&self, s: &str) -> Result<Address, AddressError> {
+        Address::parse(s, &self.hrp)
+    }
+}
+
+/// When a validator applies the blocks it proposes
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FinalizationMode {
+    /// Apply on proposal, for single-validator devnets
+    Instant,
+    /// Apply once the proposal is finalized by a quorum of votes
+    Voted,
+}
+
+/// How gossip message IDs are derived.
+///
+/// Gossipsub drops any message whose ID it has seen recently, including one
+/// the local node publishes again, so the ID decides what counts as a
+/// duplicate.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MessageIdStrategy {
+    /// Hash of the message data on every topic. A byte-identical resend is
+    /// rejected as a duplicate.
+    ContentHash,
+    /// Content hash for transactions and other messages; votes and proposals
+    /// are identified by type, sender and proposal together with the
+    /// publisher's sequence number. Relayed copies of one publication share
+    /// an ID, while a validator re-sending the same vote, e.g. for peers that
+    /// connected since, publishes a new message.
+    #[default]
+    ConsensusIdentity,
+}
+
 /// Network configuration
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct NetworkConfig {
@@ -55,6 +214,10 @@ pub struct NetworkConfig {
     
     /// Connection timeout in seconds
     pub connection_timeout: u64,
+    
+    /// How gossip message IDs are derived
+    #[serde(default)]
+    pub message_ids: MessageIdStrategy,
 }
 
 impl Default for NetworkConfig {
@@ -65,6 +228,7 @@ impl Default for NetworkConfig {
             max_peers: 50,
             enable_mdns: true,
             connection_timeout: 30,
+            message_ids: MessageIdStrategy::default(),
         }
     }
 }
@@ -75,7 +239,8 @@ pub struct ConsensusConfig {
     /// This node is a validator
     pub is_validator: bool,
     
-    /// Minimum validators for consensus
+    /// Minimum active validators for proposals to finalize. 1 allows a
+    /// single validator to finalize alone, see [`ConsensusConfig::devnet`].
     pub min_validators: usize,
     
     /// Required vote percentage (0-100)
@@ -86,6 +251,91 @@ pub struct ConsensusConfig {
     
     /// Vote timeout in milliseconds
     pub vote_timeout_ms: u64,
+    
+    /// Partial vote collections saved before a restart are discarded instead
+    /// of restored once their proposal is older than this many milliseconds.
+    /// Keep it above `proposal_timeout_ms` and the node's save interval, or
+    /// collections are discarded before they could be used. Restored
+    /// proposals still expire after `proposal_timeout_ms` unless the restored
+    /// votes already form a quorum.
+    #[serde(default = "default_vote_collection_deadline_ms")]
+    pub vote_collection_deadline_ms: u64,
+    
+    /// Maximum proposals tracked for versions that are not finalized yet
+    #[serde(default = "default_max_pending_proposals")]
+    pub max_pending_proposals: usize,
+    
+    /// Validators whose stake drops below this are deactivated
+    #[serde(default)]
+    pub min_validator_stake: u128,
+    
+    /// Connected validator peers required before this node proposes.
+    ///
+    /// Zero lets a lone validator finalize by itself, which is only
+    /// appropriate for single-node devnets; on a real network an isolated
+    /// validator would otherwise diverge before it finds its peers.
+    #[serde(default)]
+    pub min_peers_to_propose: usize,
+    
+    /// Most transactions from one sender in a proposed block, independent
+    /// of how many the mempool holds per sender
+    #[serde(default = "default_max_block_transactions_per_sender")]
+    pub max_block_transactions_per_sender: usize,
+    
+    /// Let a pending transaction be replaced by one from the same sender,
+    /// with the same nonce and a strictly higher fee
+    #[serde(default)]
+    pub mempool_replace_by_fee: bool,
+    
+    /// Milliseconds without a newly finalized version, while transactions
+    /// are pending, before the node reports consensus as stalled
+    #[serde(default = "default_stall_threshold_ms")]
+    pub stall_threshold_ms: u64,
+    
+    /// Versions a validator may go without voting before it is reported
+    /// as inactive
+    #[serde(default = "default_liveness_window_versions")]
+    pub liveness_window_versions: u64,
+    
+    /// Consecutive finalized rounds a validator may miss before this node
+    /// proposes deactivating it, zero to never deactivate for inactivity
+    #[serde(default = "default_inactivity_threshold_rounds")]
+    pub inactivity_threshold_rounds: u64,
+    
+    /// When proposed blocks are applied. Unset picks `Instant` with a
+    /// single active validator and `Voted` otherwise.
+    #[serde(default)]
+    pub finalization_mode: Option<FinalizationMode>,
+}
+
+fn default_max_pending_proposals() -> usize {
+    // Honest validators each propose at most a handful per version
+    256
+}
+
+fn default_vote_collection_deadline_ms() -> u64 {
+    // Several proposal timeouts, and well past the 5s save interval
+    30_000
+}
+
+/// Default cap on one sender's transactions per block, shared by the
+/// consensus config and the mempool
+pub const DEFAULT_MAX_BLOCK_TRANSACTIONS_PER_SENDER: usize = 16;
+
+fn default_max_block_transactions_per_sender() -> usize {
+    DEFAULT_MAX_BLOCK_TRANSACTIONS_PER_SENDER
+}
+
+fn default_stall_threshold_ms() -> u64 {
+    60_000
+}
+
+fn default_liveness_window_versions() -> u64 {
+    100
+}
+
+fn default_inactivity_threshold_rounds() -> u64 {
+    64
 }
 
 impl Default for ConsensusConfig {
@@ -96,6 +346,27 @@ impl Default for ConsensusConfig {
             vote_threshold: 67, // 2/3 majority
             proposal_timeout_ms: 5000,
             vote_timeout_ms: 3000,
+            vote_collection_deadline_ms: default_vote_collection_deadline_ms(),
+            max_pending_proposals: default_max_pending_proposals(),
+            min_validator_stake: 0,
+            min_peers_to_propose: 0,
+            max_block_transactions_per_sender: default_max_block_transactions_per_sender(),
+            mempool_replace_by_fee: false,
+            stall_threshold_ms: default_stall_threshold_ms(),
+            liveness_window_versions: default_liveness_window_versions(),
+            inactivity_threshold_rounds: default_inactivity_threshold_rounds(),
+            finalization_mode: None,
+        }
+    }
+}
+
+impl ConsensusConfig {
+    /// Defaults with `min_validators` lowered to 1, so a lone validator
+    /// finalizes on its own vote. Not for networks with real value.
+    pub fn devnet() -> Self {
+        Self {
+            min_validators: 1,
+            ..Self::default()
         }
     }
 }
@@ -114,6 +385,28 @@ pub struct ApiConfig {
     
     /// CORS allowed origins
     pub cors_origins: Vec<String>,
+    
+    /// Maximum request body size in bytes
+    #[serde(default = "default_max_body_bytes")]
+    pub max_body_bytes: usize,
+    
+    /// Requests not answered within this many milliseconds get a 503
+    #[serde(default = "default_request_timeout_ms")]
+    pub request_timeout_ms: u64,
+    
+    /// Bearer token required by token-gated admin endpoints, which are
+    /// refused while it is unset
+    #[serde(default)]
+    pub admin_token: Option<String>,
+}
+
+fn default_max_body_bytes() -> usize {
+    // A signed transfer is a few hundred bytes of JSON; leave room for larger requests
+    16 * 1024
+}
+
+fn default_request_timeout_ms() -> u64 {
+    10_000
 }
 
 impl Default for ApiConfig {
@@ -123,6 +416,34 @@ impl Default for ApiConfig {
             listen_addr: "127.0.0.1:8080".to_string(),
             enable_cors: true,
             cors_origins: vec!["*".to_string()],
+            max_body_bytes: default_max_body_bytes(),
+            request_timeout_ms: default_request_timeout_ms(),
+            admin_token: None,
+        }
+    }
+}
+
+/// Async runtime configuration
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RuntimeConfig {
+    /// Number of async worker threads (0 = one per CPU core)
+    pub worker_threads: usize,
+    
+    /// Upper bound on threads in the blocking pool used for storage and
+    /// other blocking work
+    pub max_blocking_threads: usize,
+    
+    /// Prefix for runtime thread names
+    pub thread_name: String,
+}
+
+impl Default for RuntimeConfig {
+    fn default() -> Self {
+        Self {
+            worker_threads: 0,
+            max_blocking_threads: 512,
+            thread_name: "rainsonet-worker".to_string(),
         }
     }
 }
@@ -139,6 +460,11 @@ pub struct RelyoConfig {
     /// Maximum transaction amount per tx
     pub max_tx_amount: u128,
     
+    /// Accept transfers to the zero address, which destroy the amount for
+    /// good. Off by default so a missing recipient cannot burn funds.
+    #[serde(default)]
+    pub allow_zero_recipient: bool,
+    
     /// Transaction expiry time in seconds
     pub tx_expiry_seconds: u64,
     
@@ -146,14 +472,22 @@ pub struct RelyoConfig {
     pub initial_supply: u128,
 }
 
+/// Whole RELYO in smallest units, for config defaults
+fn relyo(whole: u128) -> u128 {
+    Amount::from_relyo_u128(whole)
+        .expect("default amounts fit in u128")
+        .0
+}
+
 impl Default for RelyoConfig {
     fn default() -> Self {
         Self {
             min_fee: 1_000_000_000_000_000, // 0.001 RELYO
             fee_burn_percent: 50,
-            max_tx_amount: 1_000_000_000_000_000_000_000_000, // 1M RELYO
+            max_tx_amount: relyo(1_000_000),
+            allow_zero_recipient: false,
             tx_expiry_seconds: 3600, // 1 hour
-            initial_supply: 100_000_000_000_000_000_000_000_000, // 100M RELYO
+            initial_supply: relyo(100_000_000),
         }
     }
 }
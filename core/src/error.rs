@@ -3,12 +3,29 @@
 use thiserror::Error;
 
 /// Main error type for RAINSONET
+///
+/// Variants that wrap a message are named `<Kind>Error`:
+///
+/// ```
+/// use rainsonet_core::RainsonetError;
+///
+/// let errors = [
+///     RainsonetError::StorageError("wallet file unreadable".into()),
+///     RainsonetError::SerializationError("wallet encode failed".into()),
+///     RainsonetError::DeserializationError("wallet decode failed".into()),
+///     RainsonetError::ConfigError("wallet not found".into()),
+/// ];
+/// assert_eq!(errors[3].to_string(), "Configuration error: wallet not found");
+/// ```
 #[derive(Error, Debug)]
 pub enum RainsonetError {
     // ============ Cryptography Errors ============
     #[error("Invalid signature")]
     InvalidSignature,
     
+    #[error("Invalid signature at batch index {index}")]
+    InvalidBatchSignature { index: usize },
+    
     #[error("Invalid public key")]
     InvalidPublicKey,
     
@@ -69,6 +86,9 @@ pub enum RainsonetError {
     #[error("Validator set error: {0}")]
     ValidatorSetError(String),
     
+    #[error("Insufficient validators: {active} active, at least {minimum} required")]
+    InsufficientValidators { active: usize, minimum: usize },
+    
     // ============ Network Errors ============
     #[error("Network error: {0}")]
     NetworkError(String),
@@ -4,11 +4,17 @@
 //! This crate provides the foundation for all other RAINSONET components.
 
 pub mod types;
+pub mod bech32;
 pub mod traits;
 pub mod error;
 pub mod config;
+pub mod time;
+pub mod log_limit;
 
 pub use types::*;
+pub use bech32::Bech32Error;
 pub use traits::*;
 pub use error::*;
 pub use config::*;
+pub use time::*;
+pub use log_limit::*;
@@ -0,0 +1,173 @@
+//! Rate limiting for repetitive warnings
+//!
+//! A peer flooding invalid messages would otherwise produce one warning per
+//! message. [`LogLimiter`] lets a few identical warnings through per window
+//! and reports how many it dropped with the next one that gets through, or
+//! when it is dropped itself.
+
+use crate::time::{system_time, SharedTimeSource};
+use crate::types::Timestamp;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::Duration;
+use tracing::warn;
+
+/// Warnings logged per key and window by default
+pub const DEFAULT_LOG_BURST: u32 = 10;
+
+/// Default rate limiting window
+pub const DEFAULT_LOG_WINDOW: Duration = Duration::from_secs(60);
+
+/// Outcome of [`LogLimiter::check`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LogDecision {
+    /// Log the warning, after a summary if the last window dropped some
+    Log { suppressed: u64 },
+    /// Drop the warning
+    Suppress,
+}
+
+/// Warnings seen under one key in the current window
+struct Window {
+    started: Timestamp,
+    logged: u32,
+    suppressed: u64,
+}
+
+/// Limits identical warnings to a burst per window
+pub struct LogLimiter {
+    burst: u32,
+    window: Duration,
+    clock: SharedTimeSource,
+    windows: Mutex<HashMap<&'static str, Window>>,
+}
+
+impl LogLimiter {
+    /// Let `burst` warnings per key through every `window`
+    pub fn new(burst: u32, window: Duration) -> Self {
+        Self {
+            burst,
+            window,
+            clock: system_time(),
+            windows: Mutex::new(HashMap::new()),
+        }
+    }
+    
+    /// Use the given time source instead of the system clock
+    pub fn with_time_source(mut self, clock: SharedTimeSource) -> Self {
+        self.clock = clock;
+        self
+    }
+    
+    /// Decide whether a warning identified by `key` is logged
+    pub fn check(&self, key: &'static str) -> LogDecision {
+        let now = self.clock.now();
+        let mut windows = self.windows.lock().unwrap_or_else(|e| e.into_inner());
+        let window = windows.entry(key).or_insert(Window {
+            started: now,
+            logged: 0,
+            suppressed: 0,
+        });
+        
+        let mut suppressed = 0;
+        let elapsed = now.as_millis().saturating_sub(window.started.as_millis());
+        if elapsed >= self.window.as_millis() as u64 {
+            suppressed = window.suppressed;
+            *window = Window {
+                started: now,
+                logged: 0,
+                suppressed: 0,
+            };
+        }
+        
+        if window.logged < self.burst {
+            window.logged += 1;
+            LogDecision::Log { suppressed }
+        } else {
+            window.suppressed += 1;
+            LogDecision::Suppress
+        }
+    }
+    
+    /// Log `message` as a warning unless `key` exceeded its burst
+    pub fn warn(&self, key: &'static str, message: impl FnOnce() -> String) {
+        if let LogDecision::Log { suppressed } = self.check(key) {
+            if suppressed > 0 {
+                warn!("Suppressed {} occurrences of {}", suppressed, key);
+            }
+            warn!("{}", message());
+        }
+    }
+    
+    /// Take the count of warnings dropped so far in each key's current
+    /// window, which is then no longer reported with its next warning
+    pub fn take_suppressed(&self) -> Vec<(&'static str, u64)> {
+        let mut windows = self.windows.lock().unwrap_or_else(|e| e.into_inner());
+        windows
+            .iter_mut()
+            .filter(|(_, window)| window.suppressed > 0)
+            .map(|(key, window)| (*key, std::mem::take(&mut window.suppressed)))
+            .collect()
+    }
+    
+    /// Log the summaries still pending, e.g. before shutting down
+    pub fn flush(&self) {
+        for (key, suppressed) in self.take_suppressed() {
+            warn!("Suppressed {} occurrences of {}", suppressed, key);
+        }
+    }
+}
+
+impl Default for LogLimiter {
+    fn default() -> Self {
+        Self::new(DEFAULT_LOG_BURST, DEFAULT_LOG_WINDOW)
+    }
+}
+
+impl Drop for LogLimiter {
+    fn drop(&mut self) {
+        self.flush();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::time::MockTimeSource;
+    use std::sync::Arc;
+    
+    #[test]
+    fn test_burst_then_summary() {
+        let clock = Arc::new(MockTimeSource::new(Timestamp::from_millis(1_000)));
+        let limiter = LogLimiter::new(3, Duration::from_secs(10)).with_time_source(clock.clone());
+        
+        let decisions: Vec<_> = (0..10).map(|_| limiter.check("invalid_message")).collect();
+        assert_eq!(decisions[..3], [LogDecision::Log { suppressed: 0 }; 3]);
+        assert!(decisions[3..].iter().all(|d| *d == LogDecision::Suppress));
+        
+        // Other keys have their own budget
+        assert_eq!(limiter.check("mempool_full"), LogDecision::Log { suppressed: 0 });
+        
+        // The next window reports what the last one dropped
+        clock.advance(Duration::from_secs(10));
+        assert_eq!(limiter.check("invalid_message"), LogDecision::Log { suppressed: 7 });
+        assert_eq!(limiter.check("invalid_message"), LogDecision::Log { suppressed: 0 });
+    }
+    
+    #[test]
+    fn test_pending_summary_taken_once() {
+        let clock = Arc::new(MockTimeSource::new(Timestamp::from_millis(1_000)));
+        let limiter = LogLimiter::new(1, Duration::from_secs(10)).with_time_source(clock.clone());
+        
+        for _ in 0..4 {
+            limiter.check("invalid_message");
+        }
+        limiter.check("mempool_full");
+        assert_eq!(limiter.take_suppressed(), vec![("invalid_message", 3)]);
+        assert!(limiter.take_suppressed().is_empty());
+        
+        // Already reported, so the next window has nothing to add
+        clock.advance(Duration::from_secs(10));
+        assert_eq!(limiter.check("invalid_message"), LogDecision::Log { suppressed: 0 });
+    }
+}
@@ -4,6 +4,7 @@
 
 use crate::types::*;
 use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
 use std::fmt::Debug;
 
 /// Result type for RAINSONET operations
@@ -57,7 +58,7 @@ pub trait StateProvider: Send + Sync {
     async fn version(&self) -> StateVersion;
     
     /// Get the state root hash
-    async fn root(&self) -> StateRoot;
+    async fn root(&self) -> RainsonetResult<StateRoot>;
     
     /// Get a value by key
     async fn get(&self, key: &[u8]) -> RainsonetResult<Option<Vec<u8>>>;
@@ -80,7 +81,7 @@ pub trait StateMutator: StateProvider {
 }
 
 /// State change operation
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub enum StateChange {
     Set { key: Vec<u8>, value: Vec<u8> },
     Delete { key: Vec<u8> },
@@ -4,9 +4,66 @@
 
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use std::fmt;
+use std::str::FromStr;
+
+use crate::bech32::{self, Bech32Error};
+
+/// Error parsing a fixed-length hex string
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum HexError {
+    #[error("expected {expected} hex characters, got {got}")]
+    InvalidLength { expected: usize, got: usize },
+    
+    #[error("invalid hex character {c:?} at position {index}")]
+    InvalidCharacter { c: char, index: usize },
+}
+
+/// Decode hex into a fixed-size array, accepting an optional `0x`/`0X` prefix
+fn decode_hex_array<const N: usize>(s: &str) -> Result<[u8; N], HexError> {
+    let digits = s
+        .strip_prefix("0x")
+        .or_else(|| s.strip_prefix("0X"))
+        .unwrap_or(s);
+    
+    if let Some((index, c)) = digits.char_indices().find(|(_, c)| !c.is_ascii_hexdigit()) {
+        return Err(HexError::InvalidCharacter { c, index });
+    }
+    if digits.len() != N * 2 {
+        return Err(HexError::InvalidLength {
+            expected: N * 2,
+            got: digits.len(),
+        });
+    }
+    
+    let mut arr = [0u8; N];
+    hex::decode_to_slice(digits, &mut arr).map_err(|_| HexError::InvalidLength {
+        expected: N * 2,
+        got: digits.len(),
+    })?;
+    Ok(arr)
+}
+
+/// Error parsing an address given as either hex or bech32
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum AddressError {
+    #[error(transparent)]
+    Hex(#[from] HexError),
+    
+    #[error(transparent)]
+    Bech32(#[from] Bech32Error),
+}
+
+/// Default human-readable prefix for bech32 addresses
+pub const DEFAULT_ADDRESS_HRP: &str = "rlyo";
+
+/// Bytes shown by `short()` on 32-byte identifiers (16 hex characters)
+const SHORT_BYTES: usize = 8;
+
+/// Bytes shown by `NodeId::short()` (12 hex characters)
+const SHORT_NODE_BYTES: usize = 6;
 
 /// 32-byte address derived from public key hash
-#[derive(Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[derive(Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub struct Address(pub [u8; 32]);
 
 impl Address {
@@ -24,20 +81,52 @@ impl Address {
         hex::encode(self.0)
     }
     
-    pub fn from_hex(s: &str) -> Result<Self, hex::FromHexError> {
-        let bytes = hex::decode(s)?;
-        if bytes.len() != 32 {
-            return Err(hex::FromHexError::InvalidStringLength);
+    pub fn from_hex(s: &str) -> Result<Self, HexError> {
+        decode_hex_array(s).map(Address)
+    }
+    
+    /// Bech32 form under the prefix `hrp`, e.g. `rlyo1...`
+    pub fn to_bech32(&self, hrp: &str) -> String {
+        bech32::encode(hrp, &self.0)
+    }
+    
+    /// Parse a bech32 address, which must carry the prefix `hrp`
+    pub fn from_bech32(s: &str, hrp: &str) -> Result<Self, Bech32Error> {
+        let (prefix, data) = bech32::decode(s)?;
+        if prefix != hrp {
+            return Err(Bech32Error::WrongPrefix {
+                expected: hrp.to_string(),
+                got: prefix,
+            });
         }
-        let mut arr = [0u8; 32];
-        arr.copy_from_slice(&bytes);
-        Ok(Address(arr))
+        let bytes: [u8; 32] = data.try_into().map_err(|data: Vec<u8>| {
+            Bech32Error::InvalidLength { expected: 32, got: data.len() }
+        })?;
+        Ok(Address(bytes))
+    }
+    
+    /// Parse either form: bech32 when `s` starts with `hrp` and the `1`
+    /// separator, hex otherwise
+    pub fn parse(s: &str, hrp: &str) -> Result<Self, AddressError> {
+        let is_bech32 = s
+            .get(..=hrp.len())
+            .is_some_and(|prefix| prefix.eq_ignore_ascii_case(&format!("{}1", hrp)));
+        if is_bech32 {
+            Ok(Self::from_bech32(s, hrp)?)
+        } else {
+            Ok(Self::from_hex(s)?)
+        }
+    }
+    
+    /// Abbreviated form for logs and tables, e.g. `0x0101010101010101`
+    pub fn short(&self) -> String {
+        format!("0x{}", hex::encode(&self.0[..SHORT_BYTES]))
     }
 }
 
 impl fmt::Display for Address {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "0x{}", &self.to_hex()[..16])
+        write!(f, "0x{}", self.to_hex())
     }
 }
 
@@ -48,7 +137,7 @@ impl fmt::Debug for Address {
 }
 
 /// 32-byte hash type
-#[derive(Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
 pub struct Hash(pub [u8; 32]);
 
 impl Hash {
@@ -66,20 +155,19 @@ impl Hash {
         hex::encode(self.0)
     }
     
-    pub fn from_hex(s: &str) -> Result<Self, hex::FromHexError> {
-        let bytes = hex::decode(s)?;
-        if bytes.len() != 32 {
-            return Err(hex::FromHexError::InvalidStringLength);
-        }
-        let mut arr = [0u8; 32];
-        arr.copy_from_slice(&bytes);
-        Ok(Hash(arr))
+    pub fn from_hex(s: &str) -> Result<Self, HexError> {
+        decode_hex_array(s).map(Hash)
+    }
+    
+    /// Abbreviated form for logs and tables, e.g. `0xabababababababab`
+    pub fn short(&self) -> String {
+        format!("0x{}", hex::encode(&self.0[..SHORT_BYTES]))
     }
 }
 
 impl fmt::Display for Hash {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "0x{}", &self.to_hex()[..16])
+        write!(f, "0x{}", self.to_hex())
     }
 }
 
@@ -105,6 +193,10 @@ impl Signature {
     pub fn to_hex(&self) -> String {
         hex::encode(self.0)
     }
+    
+    pub fn from_hex(s: &str) -> Result<Self, HexError> {
+        decode_hex_array(s).map(Signature)
+    }
 }
 
 impl Serialize for Signature {
@@ -127,13 +219,7 @@ impl<'de> Deserialize<'de> for Signature {
     {
         if deserializer.is_human_readable() {
             let s = String::deserialize(deserializer)?;
-            let bytes = hex::decode(&s).map_err(serde::de::Error::custom)?;
-            if bytes.len() != 64 {
-                return Err(serde::de::Error::custom("signature must be 64 bytes"));
-            }
-            let mut arr = [0u8; 64];
-            arr.copy_from_slice(&bytes);
-            Ok(Signature(arr))
+            Signature::from_hex(&s).map_err(serde::de::Error::custom)
         } else {
             let bytes = <Vec<u8>>::deserialize(deserializer)?;
             if bytes.len() != 64 {
@@ -168,6 +254,10 @@ impl PublicKey {
     pub fn to_hex(&self) -> String {
         hex::encode(self.0)
     }
+    
+    pub fn from_hex(s: &str) -> Result<Self, HexError> {
+        decode_hex_array(s).map(PublicKey)
+    }
 }
 
 impl fmt::Debug for PublicKey {
@@ -193,8 +283,22 @@ impl Amount {
         Amount(value)
     }
     
+    /// Whole RELYO as an amount. Every `u64` fits, see
+    /// [`Amount::from_relyo_u128`] for larger inputs.
     pub fn from_relyo(relyo: u64) -> Self {
-        Amount(relyo as u128 * Self::ONE_RELYO)
+        Self::from_relyo_u128(relyo as u128).expect("u64::MAX RELYO fits in u128 smallest units")
+    }
+    
+    /// Whole RELYO as an amount, or `None` if it overflows the smallest-unit
+    /// representation (above roughly 3.4e20 RELYO)
+    pub fn from_relyo_u128(relyo: u128) -> Option<Self> {
+        relyo.checked_mul(Self::ONE_RELYO).map(Amount)
+    }
+    
+    /// Fractional RELYO as an amount, as typed on the command line. Rounds
+    /// toward zero; negative and NaN inputs give zero, too large ones `MAX`.
+    pub fn from_relyo_f64(relyo: f64) -> Self {
+        Amount((relyo * Self::ONE_RELYO as f64) as u128)
     }
     
     pub fn checked_add(self, other: Amount) -> Option<Amount> {
@@ -212,6 +316,12 @@ impl Amount {
     pub fn saturating_sub(self, other: Amount) -> Amount {
         Amount(self.0.saturating_sub(other.0))
     }
+    
+    /// Largest transfer from `balance` that still pays `fee`, so amount
+    /// plus fee is exactly the balance. Zero if the fee alone exceeds it.
+    pub fn send_max(balance: Amount, fee: Amount) -> Amount {
+        balance.saturating_sub(fee)
+    }
 }
 
 impl fmt::Display for Amount {
@@ -232,6 +342,41 @@ impl fmt::Debug for Amount {
     }
 }
 
+/// Parses a decimal count of smallest units, e.g. `"1000000000000000000"` for 1 RELYO
+impl FromStr for Amount {
+    type Err = std::num::ParseIntError;
+    
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        s.trim().parse::<u128>().map(Amount)
+    }
+}
+
+impl TryFrom<&str> for Amount {
+    type Error = std::num::ParseIntError;
+    
+    fn try_from(s: &str) -> Result<Self, Self::Error> {
+        s.parse()
+    }
+}
+
+/// Serde helper that encodes an [`Amount`] as a decimal string.
+///
+/// JSON numbers lose precision above 2^53 in JavaScript clients, so API
+/// types use this via `#[serde(with = "amount_as_string")]`.
+pub mod amount_as_string {
+    use super::Amount;
+    use serde::{de, Deserialize, Deserializer, Serializer};
+    
+    pub fn serialize<S: Serializer>(amount: &Amount, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(&amount.0)
+    }
+    
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Amount, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        s.parse().map_err(de::Error::custom)
+    }
+}
+
 /// Transaction nonce (sequential per account)
 #[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize, Default)]
 pub struct Nonce(pub u64);
@@ -274,11 +419,84 @@ impl Timestamp {
     pub fn as_millis(&self) -> u64 {
         self.0
     }
+    
+    /// UTC date and time to the millisecond, e.g. `2024-04-05T19:21:18.901Z`.
+    ///
+    /// Falls back to the raw milliseconds past the year 262143, which
+    /// RFC 3339 cannot express.
+    pub fn to_rfc3339(&self) -> String {
+        i64::try_from(self.0)
+            .ok()
+            .and_then(chrono::DateTime::from_timestamp_millis)
+            .map(|time| time.to_rfc3339_opts(chrono::SecondsFormat::Millis, true))
+            .unwrap_or_else(|| self.0.to_string())
+    }
+    
+    /// Parse an RFC 3339 date and time in any offset. Precision below a
+    /// millisecond is truncated.
+    pub fn from_rfc3339(s: &str) -> Result<Self, TimestampError> {
+        let millis = chrono::DateTime::parse_from_rfc3339(s.trim())?.timestamp_millis();
+        u64::try_from(millis)
+            .map(Timestamp)
+            .map_err(|_| TimestampError::BeforeEpoch(s.to_string()))
+    }
 }
 
+/// Error parsing an RFC 3339 timestamp
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum TimestampError {
+    #[error("invalid RFC 3339 timestamp: {0}")]
+    Invalid(#[from] chrono::ParseError),
+    
+    #[error("{0} is before the Unix epoch")]
+    BeforeEpoch(String),
+}
+
+/// Shown as RFC 3339 for logs. Signing and the wire format keep the raw
+/// milliseconds.
 impl fmt::Display for Timestamp {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "{}", self.0)
+        f.write_str(&self.to_rfc3339())
+    }
+}
+
+/// Serde helper that encodes a [`Timestamp`] as an RFC 3339 string, for API
+/// types shown to people. Use the `option` submodule for `Option<Timestamp>`.
+pub mod timestamp_as_rfc3339 {
+    use super::Timestamp;
+    use serde::{de, Deserialize, Deserializer, Serializer};
+    
+    pub fn serialize<S: Serializer>(time: &Timestamp, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&time.to_rfc3339())
+    }
+    
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Timestamp, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        Timestamp::from_rfc3339(&s).map_err(de::Error::custom)
+    }
+    
+    pub mod option {
+        use super::Timestamp;
+        use serde::{Deserialize, Deserializer, Serializer};
+        
+        pub fn serialize<S: Serializer>(
+            time: &Option<Timestamp>,
+            serializer: S,
+        ) -> Result<S::Ok, S::Error> {
+            match time {
+                Some(time) => super::serialize(time, serializer),
+                None => serializer.serialize_none(),
+            }
+        }
+        
+        pub fn deserialize<'de, D: Deserializer<'de>>(
+            deserializer: D,
+        ) -> Result<Option<Timestamp>, D::Error> {
+            #[derive(Deserialize)]
+            struct Rfc3339(#[serde(with = "super")] Timestamp);
+            
+            Ok(Option::<Rfc3339>::deserialize(deserializer)?.map(|Rfc3339(time)| time))
+        }
     }
 }
 
@@ -324,11 +542,20 @@ impl NodeId {
     pub fn to_hex(&self) -> String {
         hex::encode(self.0)
     }
+    
+    pub fn from_hex(s: &str) -> Result<Self, HexError> {
+        decode_hex_array(s).map(NodeId)
+    }
+    
+    /// Abbreviated form for logs and tables, e.g. `node:090909090909`
+    pub fn short(&self) -> String {
+        format!("node:{}", hex::encode(&self.0[..SHORT_NODE_BYTES]))
+    }
 }
 
 impl fmt::Display for NodeId {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "node:{}", &self.to_hex()[..12])
+        write!(f, "0x{}", self.to_hex())
     }
 }
 
@@ -338,6 +565,23 @@ impl fmt::Debug for NodeId {
     }
 }
 
+/// Parse the fixed-length hex types with `str::parse`, as `from_hex`
+macro_rules! impl_from_str_hex {
+    ($($ty:ty),*) => {
+        $(
+            impl FromStr for $ty {
+                type Err = HexError;
+                
+                fn from_str(s: &str) -> Result<Self, Self::Err> {
+                    Self::from_hex(s)
+                }
+            }
+        )*
+    };
+}
+
+impl_from_str_hex!(Address, Hash, Signature, PublicKey, NodeId);
+
 /// Transaction ID (hash of transaction content)
 pub type TxId = Hash;
 
@@ -348,6 +592,88 @@ pub type StateRoot = Hash;
 mod tests {
     use super::*;
     
+    #[test]
+    fn test_timestamp_rfc3339_round_trip() {
+        let time = Timestamp::from_millis(1_712_345_678_901);
+        assert_eq!(time.to_rfc3339(), "2024-04-05T19:34:38.901Z");
+        assert_eq!(time.to_string(), time.to_rfc3339());
+        assert_eq!(Timestamp::from_rfc3339(&time.to_rfc3339()).unwrap(), time);
+        
+        // Offsets are normalized; the millis are unchanged
+        let offset = Timestamp::from_rfc3339("2024-04-05T21:34:38.901+02:00").unwrap();
+        assert_eq!(offset.as_millis(), 1_712_345_678_901);
+        
+        assert!(matches!(Timestamp::from_rfc3339("yesterday"), Err(TimestampError::Invalid(_))));
+        assert!(matches!(
+            Timestamp::from_rfc3339("1969-12-31T23:59:59Z"),
+            Err(TimestampError::BeforeEpoch(_))
+        ));
+        
+        // The derived encoding stays raw millis for the wire
+        assert_eq!(serde_json::to_string(&time).unwrap(), "1712345678901");
+        #[derive(Serialize, Deserialize)]
+        struct Readable {
+            #[serde(with = "timestamp_as_rfc3339")]
+            at: Timestamp,
+            #[serde(with = "timestamp_as_rfc3339::option")]
+            seen: Option<Timestamp>,
+        }
+        let json = serde_json::to_string(&Readable { at: time, seen: None }).unwrap();
+        assert_eq!(json, r#"{"at":"2024-04-05T19:34:38.901Z","seen":null}"#);
+        let back: Readable = serde_json::from_str(&json).unwrap();
+        assert_eq!(back.at, time);
+        assert_eq!(back.seen, None);
+    }
+    
+    #[test]
+    fn test_address_bech32_round_trip() {
+        let addr = Address([0xab; 32]);
+        let encoded = addr.to_bech32(DEFAULT_ADDRESS_HRP);
+        assert!(encoded.starts_with("rlyo1"));
+        
+        let decoded = Address::from_bech32(&encoded, DEFAULT_ADDRESS_HRP).unwrap();
+        assert_eq!(decoded.to_hex(), addr.to_hex());
+        assert_eq!(Address::parse(&encoded, DEFAULT_ADDRESS_HRP).unwrap(), addr);
+        assert_eq!(Address::parse(&encoded.to_uppercase(), DEFAULT_ADDRESS_HRP).unwrap(), addr);
+        assert_eq!(Address::parse(&addr.to_hex(), DEFAULT_ADDRESS_HRP).unwrap(), addr);
+        
+        assert_eq!(
+            Address::from_bech32(&encoded, "other"),
+            Err(Bech32Error::WrongPrefix {
+                expected: "other".to_string(),
+                got: "rlyo".to_string()
+            })
+        );
+        assert_eq!(
+            Address::from_bech32(&bech32::encode("rlyo", &[1; 20]), DEFAULT_ADDRESS_HRP),
+            Err(Bech32Error::InvalidLength { expected: 32, got: 20 })
+        );
+    }
+    
+    #[test]
+    fn test_address_bech32_detects_typos() {
+        let encoded = Address([7; 32]).to_bech32(DEFAULT_ADDRESS_HRP);
+        let data_start = DEFAULT_ADDRESS_HRP.len() + 1;
+        
+        // Every single-character substitution in the data part is caught
+        for index in data_start..encoded.len() {
+            for replacement in "qpzry9x8gf2tvdw0s3jn54khce6mua7l".chars() {
+                let mut corrupted: Vec<char> = encoded.chars().collect();
+                if corrupted[index] == replacement {
+                    continue;
+                }
+                corrupted[index] = replacement;
+                let corrupted: String = corrupted.into_iter().collect();
+                assert_eq!(
+                    Address::from_bech32(&corrupted, DEFAULT_ADDRESS_HRP),
+                    Err(Bech32Error::InvalidChecksum),
+                    "{} was accepted",
+                    corrupted
+                );
+            }
+        }
+    }
+    
     #[test]
     fn test_address_hex() {
         let addr = Address([1u8; 32]);
@@ -356,6 +682,84 @@ mod tests {
         assert_eq!(addr, parsed);
     }
     
+    #[test]
+    fn test_display_is_full_hex() {
+        let addr = Address([1u8; 32]);
+        assert_eq!(addr.to_string(), format!("0x{}", addr.to_hex()));
+        let hash = Hash([0xab; 32]);
+        assert_eq!(hash.to_string(), format!("0x{}", hash.to_hex()));
+        let node = NodeId([9u8; 32]);
+        assert_eq!(node.to_string(), format!("0x{}", node.to_hex()));
+        
+        // Display output parses back
+        assert_eq!(Hash::from_hex(&hash.to_string()).unwrap(), hash);
+    }
+    
+    #[test]
+    fn test_short_is_truncated() {
+        assert_eq!(Address([1u8; 32]).short(), "0x0101010101010101");
+        assert_eq!(Hash([0xab; 32]).short(), "0xabababababababab");
+        assert_eq!(NodeId([9u8; 32]).short(), "node:090909090909");
+    }
+    
+    #[test]
+    fn test_from_hex_accepts_optional_prefix() {
+        let hash = Hash([0xab; 32]);
+        let bare = hash.to_hex();
+        assert_eq!(Hash::from_hex(&bare).unwrap(), hash);
+        assert_eq!(Hash::from_hex(&format!("0x{}", bare)).unwrap(), hash);
+        assert_eq!(Hash::from_hex(&format!("0X{}", bare.to_uppercase())).unwrap(), hash);
+        
+        let sig = Signature([7u8; 64]);
+        assert_eq!(Signature::from_hex(&format!("0x{}", sig.to_hex())).unwrap(), sig);
+        let pk = PublicKey([3u8; 32]);
+        assert_eq!(PublicKey::from_hex(&pk.to_hex()).unwrap(), pk);
+        let node = NodeId([9u8; 32]);
+        assert_eq!(NodeId::from_hex(&format!("0x{}", node.to_hex())).unwrap(), node);
+    }
+    
+    #[test]
+    fn test_from_hex_rejects_malformed_input() {
+        // Odd length and truncated input report the expected length
+        assert_eq!(
+            Address::from_hex(&"a".repeat(63)),
+            Err(HexError::InvalidLength { expected: 64, got: 63 })
+        );
+        assert_eq!(
+            Address::from_hex("0xabcd"),
+            Err(HexError::InvalidLength { expected: 64, got: 4 })
+        );
+        // A 32-byte value is not a valid signature
+        assert_eq!(
+            Signature::from_hex(&"a".repeat(64)),
+            Err(HexError::InvalidLength { expected: 128, got: 64 })
+        );
+        
+        let mut bad = "0".repeat(64);
+        bad.replace_range(10..11, "g");
+        assert_eq!(
+            Address::from_hex(&bad),
+            Err(HexError::InvalidCharacter { c: 'g', index: 10 })
+        );
+        // Only a single leading prefix is stripped
+        assert!(Address::from_hex(&format!("0x0x{}", "0".repeat(60))).is_err());
+    }
+    
+    #[test]
+    fn test_hex_types_parse_from_str() {
+        let pk = PublicKey([3u8; 32]);
+        assert_eq!(format!("0x{}", pk.to_hex()).parse::<PublicKey>(), Ok(pk));
+        let sig = Signature([7u8; 64]);
+        assert_eq!(sig.to_hex().parse::<Signature>(), Ok(sig));
+        assert_eq!(Hash([1u8; 32]).to_string().parse::<Hash>(), Ok(Hash([1u8; 32])));
+        assert_eq!(Address([2u8; 32]).to_string().parse::<Address>(), Ok(Address([2u8; 32])));
+        
+        assert_eq!(
+            "abcd".parse::<Signature>(),
+            Err(HexError::InvalidLength { expected: 128, got: 4 })
+        );
+    }
+    
     #[test]
     fn test_amount_operations() {
         let a = Amount::from_relyo(10);
@@ -364,6 +768,69 @@ mod tests {
         assert_eq!(b.checked_sub(a), None);
     }
     
+    #[test]
+    fn test_send_max() {
+        let fee = Amount::new(1_000);
+        assert_eq!(Amount::send_max(Amount::new(10_000), fee), Amount::new(9_000));
+        assert_eq!(Amount::send_max(Amount::new(1_000), fee), Amount::ZERO);
+        assert_eq!(Amount::send_max(Amount::new(10), fee), Amount::ZERO);
+    }
+    
+    #[test]
+    fn test_amount_from_relyo_u128() {
+        assert_eq!(Amount::from_relyo_u128(0), Some(Amount::ZERO));
+        assert_eq!(Amount::from_relyo_u128(10), Some(Amount::from_relyo(10)));
+        assert_eq!(
+            Amount::from_relyo_u128(u64::MAX as u128),
+            Some(Amount::from_relyo(u64::MAX))
+        );
+        
+        let largest = u128::MAX / Amount::ONE_RELYO;
+        assert_eq!(
+            Amount::from_relyo_u128(largest),
+            Some(Amount::new(largest * Amount::ONE_RELYO))
+        );
+        assert_eq!(Amount::from_relyo_u128(largest + 1), None);
+        assert_eq!(Amount::from_relyo_u128(u128::MAX), None);
+    }
+    
+    #[test]
+    fn test_amount_from_relyo_f64() {
+        assert_eq!(Amount::from_relyo_f64(2.0), Amount::from_relyo(2));
+        assert_eq!(Amount::from_relyo_f64(0.5), Amount::new(Amount::ONE_RELYO / 2));
+        assert_eq!(Amount::from_relyo_f64(-1.0), Amount::ZERO);
+        assert_eq!(Amount::from_relyo_f64(f64::NAN), Amount::ZERO);
+        assert_eq!(Amount::from_relyo_f64(f64::MAX), Amount::MAX);
+    }
+    
+    #[test]
+    fn test_amount_from_str() {
+        assert_eq!("1000".parse::<Amount>().unwrap(), Amount::new(1000));
+        assert_eq!(Amount::try_from(" 42 ").unwrap(), Amount::new(42));
+        assert!("1.5".parse::<Amount>().is_err());
+        assert!("-1".parse::<Amount>().is_err());
+        assert!(Amount::try_from("").is_err());
+    }
+    
+    #[test]
+    fn test_amount_as_string_json() {
+        #[derive(Serialize, Deserialize)]
+        struct Wrapper {
+            #[serde(with = "amount_as_string")]
+            amount: Amount,
+        }
+        
+        let amount = Amount::new(u128::MAX - 1);
+        let json = serde_json::to_string(&Wrapper { amount }).unwrap();
+        assert_eq!(json, format!("{{\"amount\":\"{}\"}}", u128::MAX - 1));
+        
+        let parsed: Wrapper = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed.amount, amount);
+        
+        // Bare JSON numbers are rejected rather than silently truncated
+        assert!(serde_json::from_str::<Wrapper>("{\"amount\":1}").is_err());
+    }
+    
     #[test]
     fn test_nonce_sequence() {
         let n = Nonce::new(0);
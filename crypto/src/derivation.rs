@@ -3,6 +3,7 @@
 use hkdf::Hkdf;
 use rainsonet_core::{RainsonetError, RainsonetResult};
 use sha2::Sha256;
+use zeroize::Zeroizing;
 
 use crate::keys::{KeyPair, SecretKey};
 
@@ -53,6 +54,19 @@ pub fn derive_secret_key(
     Ok(SecretKey::new(derived))
 }
 
+/// Word counts a BIP39 mnemonic may have
+pub const MNEMONIC_WORD_COUNTS: [usize; 5] = [12, 15, 18, 21, 24];
+
+/// BIP39 seed for an English mnemonic: the checksum is verified, then the
+/// phrase is stretched with PBKDF2-HMAC-SHA512 (2048 iterations) salted
+/// with `"mnemonic" + passphrase`
+fn mnemonic_seed(phrase: &str, passphrase: &str) -> RainsonetResult<Zeroizing<[u8; 64]>> {
+    let normalized = phrase.split_whitespace().collect::<Vec<_>>().join(" ").to_lowercase();
+    let mnemonic = bip39::Mnemonic::parse_in(bip39::Language::English, normalized)
+        .map_err(|e| RainsonetError::KeyDerivationFailed(format!("Invalid mnemonic: {}", e)))?;
+    Ok(Zeroizing::new(mnemonic.to_seed(passphrase)))
+}
+
 /// Master key for hierarchical derivation
 pub struct MasterKey {
     seed: [u8; 32],
@@ -72,7 +86,39 @@ impl MasterKey {
         Self { seed }
     }
     
-    /// Create from a mnemonic phrase (simple implementation)
+    /// Generate a random English BIP39 mnemonic with `word_count` words,
+    /// one of [`MNEMONIC_WORD_COUNTS`]; any other count fails with
+    /// `KeyDerivationFailed`.
+    pub fn generate_mnemonic(word_count: usize) -> RainsonetResult<String> {
+        use rand::RngCore;
+        if !MNEMONIC_WORD_COUNTS.contains(&word_count) {
+            return Err(RainsonetError::KeyDerivationFailed(format!(
+                "Mnemonic word count must be one of {:?}",
+                MNEMONIC_WORD_COUNTS
+            )));
+        }
+        
+        // Each word carries 11 bits, one in 33 of them checksum
+        let mut entropy = Zeroizing::new(vec![0u8; word_count * 4 / 3]);
+        rand::rngs::OsRng.fill_bytes(&mut entropy);
+        bip39::Mnemonic::from_entropy(&entropy)
+            .map(|mnemonic| mnemonic.to_string())
+            .map_err(|e| RainsonetError::KeyDerivationFailed(e.to_string()))
+    }
+    
+    /// Restore from a BIP39 mnemonic and optional passphrase (empty for
+    /// none). The mnemonic and passphrase give the standard BIP39 seed, but
+    /// the master key is derived from it with HKDF under a Rainsonet salt,
+    /// not SLIP-10, so other BIP39 wallets derive different keys from the
+    /// same phrase. A bad word or checksum fails with `KeyDerivationFailed`.
+    pub fn from_mnemonic(phrase: &str, passphrase: &str) -> RainsonetResult<Self> {
+        let seed = mnemonic_seed(phrase, passphrase)?;
+        let seed = derive_key_32(seed.as_slice(), Some(b"rainsonet-seed"), b"master")?;
+        Ok(Self { seed })
+    }
+    
+    /// Create from an arbitrary phrase. Not a BIP39 mnemonic; prefer
+    /// [`Self::from_mnemonic`] for wallet backups.
     pub fn from_phrase(phrase: &str) -> RainsonetResult<Self> {
         let seed = derive_key_32(phrase.as_bytes(), Some(b"rainsonet-seed"), b"master")?;
         Ok(Self { seed })
@@ -146,4 +192,56 @@ mod tests {
         
         assert_eq!(kp0.public_key(), kp0_again.public_key());
     }
+    
+    const TEST_MNEMONIC: &str =
+        "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about";
+    
+    #[test]
+    fn test_mnemonic_seed_vector() {
+        // From the BIP39 reference test vectors
+        let seed = mnemonic_seed(TEST_MNEMONIC, "TREZOR").unwrap();
+        assert_eq!(
+            hex::encode(seed.as_slice()),
+            "c55257c360c07c72029aebc1b53c05ed0362ada38ead3e3e9efa3708e53495531f09a6987599d18264c1e1c92f2cf141630c7a3c4ab7c81b2f001698e7463b04"
+        );
+        
+        // Case and spacing are not part of the phrase
+        let messy = format!("  {}  ", TEST_MNEMONIC.to_uppercase().replace(' ', "   "));
+        assert_eq!(mnemonic_seed(&messy, "TREZOR").unwrap(), seed);
+    }
+    
+    #[test]
+    fn test_generated_mnemonic_round_trips() {
+        for word_count in MNEMONIC_WORD_COUNTS {
+            let phrase = MasterKey::generate_mnemonic(word_count).unwrap();
+            assert_eq!(phrase.split(' ').count(), word_count);
+            
+            let master = MasterKey::from_mnemonic(&phrase, "").unwrap();
+            let again = MasterKey::from_mnemonic(&phrase, "").unwrap();
+            assert_eq!(master.seed(), again.seed());
+            
+            // The passphrase selects a different wallet
+            let other = MasterKey::from_mnemonic(&phrase, "extra").unwrap();
+            assert_ne!(master.seed(), other.seed());
+        }
+        
+        assert!(matches!(
+            MasterKey::generate_mnemonic(13),
+            Err(RainsonetError::KeyDerivationFailed(_))
+        ));
+    }
+    
+    #[test]
+    fn test_invalid_mnemonic_rejected() {
+        let bad_checksum = ["abandon"; 12].join(" ");
+        let unknown_word = TEST_MNEMONIC.replace("about", "aboot");
+        let too_short = ["abandon"; 11].join(" ");
+        
+        for phrase in [bad_checksum, unknown_word, too_short] {
+            assert!(matches!(
+                MasterKey::from_mnemonic(&phrase, ""),
+                Err(RainsonetError::KeyDerivationFailed(_))
+            ));
+        }
+    }
 }
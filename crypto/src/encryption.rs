@@ -0,0 +1,210 @@
+//! Passphrase encryption of secret keys
+//!
+//! Secrets are sealed with XChaCha20-Poly1305 under a key derived from the
+//! passphrase with Argon2id, so a wrong passphrase or a modified ciphertext
+//! fails to decrypt rather than yielding a different key. The Argon2
+//! parameters are stored with the ciphertext, so raising the defaults later
+//! leaves existing secrets decryptable.
+
+use argon2::{Algorithm, Argon2, Params, Version};
+use chacha20poly1305::aead::{Aead, KeyInit};
+use chacha20poly1305::{Key, XChaCha20Poly1305, XNonce};
+use rainsonet_core::{RainsonetError, RainsonetResult};
+use rand::RngCore;
+use zeroize::Zeroizing;
+
+/// Salt length for passphrase key derivation
+pub const SALT_LEN: usize = 16;
+
+/// XChaCha20-Poly1305 nonce length
+pub const NONCE_LEN: usize = 24;
+
+/// First byte of an encoded [`EncryptedSecret`]
+const FORMAT_VERSION: u8 = 1;
+
+/// Encoded length of [`KdfParams`]
+const PARAMS_LEN: usize = 12;
+
+/// Largest Argon2 memory cost accepted when decoding, in KiB, so a damaged
+/// or hostile file cannot make decryption allocate without bound
+pub const MAX_KDF_MEMORY_KIB: u32 = 1024 * 1024;
+
+/// Argon2id cost parameters a key was derived with
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KdfParams {
+    /// Memory in KiB
+    pub m_cost: u32,
+    /// Iterations
+    pub t_cost: u32,
+    /// Lanes
+    pub p_cost: u32,
+}
+
+impl Default for KdfParams {
+    fn default() -> Self {
+        Self {
+            m_cost: Params::DEFAULT_M_COST,
+            t_cost: Params::DEFAULT_T_COST,
+            p_cost: Params::DEFAULT_P_COST,
+        }
+    }
+}
+
+/// Secret encrypted with a passphrase
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EncryptedSecret {
+    pub params: KdfParams,
+    pub salt: [u8; SALT_LEN],
+    pub nonce: [u8; NONCE_LEN],
+    /// Ciphertext followed by the authentication tag
+    pub ciphertext: Vec<u8>,
+}
+
+impl EncryptedSecret {
+    /// Encrypt `secret` under `passphrase` with a fresh salt and nonce and
+    /// the default [`KdfParams`]
+    pub fn encrypt(secret: &[u8], passphrase: &str) -> RainsonetResult<Self> {
+        Self::encrypt_with(secret, passphrase, KdfParams::default())
+    }
+    
+    /// Encrypt `secret` under `passphrase` with the given Argon2 parameters
+    pub fn encrypt_with(
+        secret: &[u8],
+        passphrase: &str,
+        params: KdfParams,
+    ) -> RainsonetResult<Self> {
+        let mut salt = [0u8; SALT_LEN];
+        let mut nonce = [0u8; NONCE_LEN];
+        rand::rngs::OsRng.fill_bytes(&mut salt);
+        rand::rngs::OsRng.fill_bytes(&mut nonce);
+        
+        let ciphertext = cipher(passphrase, &salt, params)?
+            .encrypt(XNonce::from_slice(&nonce), secret)
+            .map_err(|e| RainsonetError::KeyDerivationFailed(e.to_string()))?;
+        
+        Ok(Self {
+            params,
+            salt,
+            nonce,
+            ciphertext,
+        })
+    }
+    
+    /// Decrypt with `passphrase`
+    pub fn decrypt(&self, passphrase: &str) -> RainsonetResult<Zeroizing<Vec<u8>>> {
+        cipher(passphrase, &self.salt, self.params)?
+            .decrypt(XNonce::from_slice(&self.nonce), self.ciphertext.as_slice())
+            .map(Zeroizing::new)
+            .map_err(|_| {
+                RainsonetError::KeyDerivationFailed(
+                    "Wrong passphrase or corrupted secret".into(),
+                )
+            })
+    }
+    
+    /// Encode as format version, Argon2 parameters, salt, nonce and
+    /// ciphertext
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes =
+            Vec::with_capacity(1 + PARAMS_LEN + SALT_LEN + NONCE_LEN + self.ciphertext.len());
+        bytes.push(FORMAT_VERSION);
+        bytes.extend_from_slice(&self.params.m_cost.to_le_bytes());
+        bytes.extend_from_slice(&self.params.t_cost.to_le_bytes());
+        bytes.extend_from_slice(&self.params.p_cost.to_le_bytes());
+        bytes.extend_from_slice(&self.salt);
+        bytes.extend_from_slice(&self.nonce);
+        bytes.extend_from_slice(&self.ciphertext);
+        bytes
+    }
+    
+    /// Decode from [`Self::to_bytes`]
+    pub fn from_bytes(bytes: &[u8]) -> RainsonetResult<Self> {
+        if bytes.len() <= 1 + PARAMS_LEN + SALT_LEN + NONCE_LEN {
+            return Err(RainsonetError::DeserializationError(
+                "Encrypted secret too short".into(),
+            ));
+        }
+        if bytes[0] != FORMAT_VERSION {
+            return Err(RainsonetError::DeserializationError(format!(
+                "Unknown encrypted secret format {}",
+                bytes[0]
+            )));
+        }
+        
+        let (params, rest) = bytes[1..].split_at(PARAMS_LEN);
+        let cost = |i: usize| u32::from_le_bytes(params[i..i + 4].try_into().expect("4 bytes"));
+        let params = KdfParams {
+            m_cost: cost(0),
+            t_cost: cost(4),
+            p_cost: cost(8),
+        };
+        if params.m_cost > MAX_KDF_MEMORY_KIB {
+            return Err(RainsonetError::DeserializationError(format!(
+                "Argon2 memory cost {} KiB exceeds {} KiB",
+                params.m_cost, MAX_KDF_MEMORY_KIB
+            )));
+        }
+        
+        let (salt, rest) = rest.split_at(SALT_LEN);
+        let (nonce, ciphertext) = rest.split_at(NONCE_LEN);
+        Ok(Self {
+            params,
+            salt: salt.try_into().expect("salt length checked"),
+            nonce: nonce.try_into().expect("nonce length checked"),
+            ciphertext: ciphertext.to_vec(),
+        })
+    }
+}
+
+/// Cipher keyed by Argon2id with `params` over `passphrase` and `salt`
+fn cipher(passphrase: &str, salt: &[u8], params: KdfParams) -> RainsonetResult<XChaCha20Poly1305> {
+    let mut key = Zeroizing::new([0u8; 32]);
+    let params = Params::new(params.m_cost, params.t_cost, params.p_cost, Some(key.len()))
+        .map_err(|e| RainsonetError::KeyDerivationFailed(e.to_string()))?;
+    Argon2::new(Algorithm::Argon2id, Version::V0x13, params)
+        .hash_password_into(passphrase.as_bytes(), salt, key.as_mut())
+        .map_err(|e| RainsonetError::KeyDerivationFailed(e.to_string()))?;
+    Ok(XChaCha20Poly1305::new(Key::from_slice(key.as_ref())))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_encrypt_decrypt_roundtrip() {
+        let secret = [7u8; 32];
+        let encrypted = EncryptedSecret::encrypt(&secret, "correct horse").unwrap();
+        
+        let decoded = EncryptedSecret::from_bytes(&encrypted.to_bytes()).unwrap();
+        assert_eq!(decoded, encrypted);
+        assert_eq!(decoded.decrypt("correct horse").unwrap().as_slice(), &secret);
+        assert!(decoded.decrypt("wrong horse").is_err());
+        
+        let mut tampered = encrypted.clone();
+        tampered.ciphertext[0] ^= 1;
+        assert!(tampered.decrypt("correct horse").is_err());
+    }
+    
+    #[test]
+    fn test_params_stored_with_ciphertext() {
+        let params = KdfParams {
+            m_cost: 4096,
+            t_cost: 3,
+            p_cost: 1,
+        };
+        let encrypted = EncryptedSecret::encrypt_with(&[9u8; 32], "pass", params).unwrap();
+        let decoded = EncryptedSecret::from_bytes(&encrypted.to_bytes()).unwrap();
+        assert_eq!(decoded.params, params);
+        assert_eq!(decoded.decrypt("pass").unwrap().as_slice(), &[9u8; 32]);
+        
+        // Decrypting under other parameters derives another key
+        let mut other = decoded.clone();
+        other.params = KdfParams::default();
+        assert!(other.decrypt("pass").is_err());
+        
+        let mut oversized = encrypted.to_bytes();
+        oversized[1..5].copy_from_slice(&(MAX_KDF_MEMORY_KIB + 1).to_le_bytes());
+        assert!(EncryptedSecret::from_bytes(&oversized).is_err());
+    }
+}
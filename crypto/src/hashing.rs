@@ -1,7 +1,15 @@
 //! Hashing functions using BLAKE3 (with SHA-256 fallback)
+//!
+//! State roots, Merkle trees and proposal change hashes use the process-wide
+//! [`default_hash_algorithm`], BLAKE3 unless a chain is configured for SHA-256
+//! with [`set_default_hash_algorithm`]. Other hashes, like addresses and
+//! transaction IDs, are always BLAKE3.
 
 use rainsonet_core::Hash;
+use serde::{Deserialize, Serialize};
 use sha2::{Digest, Sha256};
+use std::str::FromStr;
+use std::sync::atomic::{AtomicU8, Ordering};
 
 /// Compute BLAKE3 hash of data
 pub fn blake3_hash(data: &[u8]) -> Hash {
@@ -24,45 +32,402 @@ pub fn hash(data: &[u8]) -> Hash {
     blake3_hash(data)
 }
 
-/// Hash multiple pieces of data
+/// Hash function behind state roots and the hashes proposals commit to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+#[repr(u8)]
+pub enum HashAlgorithm {
+    #[default]
+    Blake3 = 0,
+    /// For interoperating with systems that only verify SHA-256
+    Sha256 = 1,
+}
+
+impl HashAlgorithm {
+    /// Whether this is the default, BLAKE3
+    pub fn is_default(&self) -> bool {
+        *self == HashAlgorithm::default()
+    }
+    
+    fn from_u8(value: u8) -> Self {
+        match value {
+            1 => HashAlgorithm::Sha256,
+            _ => HashAlgorithm::Blake3,
+        }
+    }
+}
+
+impl FromStr for HashAlgorithm {
+    type Err = String;
+    
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "blake3" => Ok(HashAlgorithm::Blake3),
+            "sha256" | "sha-256" => Ok(HashAlgorithm::Sha256),
+            other => Err(format!("unknown hash algorithm {:?}, expected blake3 or sha256", other)),
+        }
+    }
+}
+
+/// Hash `data` with the given algorithm
+pub fn hash_with(algorithm: HashAlgorithm, data: &[u8]) -> Hash {
+    match algorithm {
+        HashAlgorithm::Blake3 => blake3_hash(data),
+        HashAlgorithm::Sha256 => sha256_hash(data),
+    }
+}
+
+static DEFAULT_HASH_ALGORITHM: AtomicU8 = AtomicU8::new(HashAlgorithm::Blake3 as u8);
+
+/// Algorithm for state roots, Merkle trees and proposal change hashes
+pub fn default_hash_algorithm() -> HashAlgorithm {
+    HashAlgorithm::from_u8(DEFAULT_HASH_ALGORITHM.load(Ordering::Relaxed))
+}
+
+/// Set the algorithm for state roots, Merkle trees and proposal change
+/// hashes in this process, before any state is hashed.
+///
+/// It is part of a chain's definition: changing it on a running chain
+/// changes every state root, so the node no longer agrees with its peers or
+/// with certificates and proofs already issued.
+pub fn set_default_hash_algorithm(algorithm: HashAlgorithm) {
+    DEFAULT_HASH_ALGORITHM.store(algorithm as u8, Ordering::Relaxed);
+}
+
+/// How [`hash_multiple_with`] feeds parts into the hasher
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum HashFormat {
+    /// Parts are concatenated, so `["ab", "c"]` and `["a", "bc"]` collide.
+    /// Kept for hashes that must not change, like existing addresses.
+    Concatenated,
+    /// Each part is preceded by its length as a little-endian u64
+    LengthPrefixed,
+}
+
+/// Hash multiple pieces of data, concatenated. State roots, votes and
+/// certificates are hashed this way, so it must not change.
 pub fn hash_multiple(parts: &[&[u8]]) -> Hash {
-    let mut hasher = blake3::Hasher::new();
-    for part in parts {
-        hasher.update(part);
+    hash_multiple_with(HashFormat::Concatenated, parts)
+}
+
+/// Hash multiple pieces of data in the given format
+pub fn hash_multiple_with(format: HashFormat, parts: &[&[u8]]) -> Hash {
+    hash_multiple_in(HashAlgorithm::Blake3, format, parts)
+}
+
+/// Hash multiple pieces of data with the given algorithm and format
+pub fn hash_multiple_in(algorithm: HashAlgorithm, format: HashFormat, parts: &[&[u8]]) -> Hash {
+    fn feed(format: HashFormat, parts: &[&[u8]], mut update: impl FnMut(&[u8])) {
+        for part in parts {
+            if format == HashFormat::LengthPrefixed {
+                update(&(part.len() as u64).to_le_bytes());
+            }
+            update(part);
+        }
+    }
+    
+    match algorithm {
+        HashAlgorithm::Blake3 => {
+            let mut hasher = blake3::Hasher::new();
+            feed(format, parts, |bytes| {
+                hasher.update(bytes);
+            });
+            Hash::from_bytes(*hasher.finalize().as_bytes())
+        }
+        HashAlgorithm::Sha256 => {
+            let mut hasher = Sha256::new();
+            feed(format, parts, |bytes| hasher.update(bytes));
+            let mut bytes = [0u8; 32];
+            bytes.copy_from_slice(&hasher.finalize());
+            Hash::from_bytes(bytes)
+        }
+    }
+}
+
+/// Prefix of leaf nodes in [`MerkleFormat::DomainSeparated`] trees
+const MERKLE_LEAF_PREFIX: &[u8] = &[0];
+
+/// Prefix of internal nodes in [`MerkleFormat::DomainSeparated`] trees
+const MERKLE_NODE_PREFIX: &[u8] = &[1];
+
+/// Hash a Merkle tree node with the default algorithm
+fn hash_tree_node(parts: &[&[u8]]) -> Hash {
+    hash_multiple_in(default_hash_algorithm(), HashFormat::LengthPrefixed, parts)
+}
+
+/// How Merkle trees are built from their leaves
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum MerkleFormat {
+    /// Leaves are used as nodes directly and a trailing node without a
+    /// sibling is paired with itself, so `[a, b, c]` and `[a, b, c, c]` share
+    /// a root. Nodes are concatenated BLAKE3 whatever the default algorithm.
+    /// Kept for checking roots computed before the format changed.
+    DuplicateLast,
+    /// Leaves and internal nodes are hashed under distinct prefixes, and a
+    /// trailing node without a sibling moves up a level unchanged, as in
+    /// RFC 6962, so distinct leaf sequences never share a root
+    DomainSeparated,
+}
+
+impl MerkleFormat {
+    /// Format used by [`merkle_root`], [`merkle_proof`] and [`MerkleBuilder::new`]
+    pub const CURRENT: MerkleFormat = MerkleFormat::DomainSeparated;
+    
+    /// Tree node for a leaf
+    fn leaf(self, leaf: Hash) -> Hash {
+        match self {
+            MerkleFormat::DuplicateLast => leaf,
+            MerkleFormat::DomainSeparated => {
+                hash_tree_node(&[MERKLE_LEAF_PREFIX, leaf.as_bytes()])
+            }
+        }
+    }
+    
+    /// Parent of two sibling nodes
+    fn node(self, left: Hash, right: Hash) -> Hash {
+        match self {
+            MerkleFormat::DuplicateLast => hash_multiple_in(
+                HashAlgorithm::Blake3,
+                HashFormat::Concatenated,
+                &[left.as_bytes(), right.as_bytes()],
+            ),
+            MerkleFormat::DomainSeparated => {
+                hash_tree_node(&[MERKLE_NODE_PREFIX, left.as_bytes(), right.as_bytes()])
+            }
+        }
+    }
+    
+    /// Parent of a trailing node without a sibling
+    fn lone(self, node: Hash) -> Hash {
+        match self {
+            MerkleFormat::DuplicateLast => self.node(node, node),
+            MerkleFormat::DomainSeparated => node,
+        }
+    }
+    
+    /// Format of proofs serialized before proofs recorded their format
+    fn legacy() -> Self {
+        MerkleFormat::DuplicateLast
+    }
+}
+
+impl Default for MerkleFormat {
+    fn default() -> Self {
+        Self::CURRENT
     }
-    let hash = hasher.finalize();
-    Hash::from_bytes(*hash.as_bytes())
 }
 
 /// Merkle tree root computation
 pub fn merkle_root(leaves: &[Hash]) -> Hash {
+    merkle_root_with(MerkleFormat::CURRENT, leaves)
+}
+
+/// Merkle tree root computation in the given format
+pub fn merkle_root_with(format: MerkleFormat, leaves: &[Hash]) -> Hash {
     if leaves.is_empty() {
         return Hash::ZERO;
     }
     
-    if leaves.len() == 1 {
-        return leaves[0];
+    let mut current_level: Vec<Hash> = leaves.iter().map(|leaf| format.leaf(*leaf)).collect();
+    
+    while current_level.len() > 1 {
+        current_level = current_level
+            .chunks(2)
+            .map(|chunk| match chunk.get(1) {
+                Some(right) => format.node(chunk[0], *right),
+                None => format.lone(chunk[0]),
+            })
+            .collect();
+    }
+    
+    current_level[0]
+}
+
+/// Streaming Merkle root computation.
+///
+/// Produces the same root as [`merkle_root_with`] over the pushed leaves
+/// while holding only one pending node per tree level, so leaves never need
+/// to be collected up front.
+#[derive(Debug, Default)]
+pub struct MerkleBuilder {
+    format: MerkleFormat,
+    /// Left node waiting for its right sibling, per level
+    levels: Vec<Option<Hash>>,
+    leaf_count: usize,
+}
+
+impl MerkleBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    
+    /// Build a tree in the given format
+    pub fn with_format(format: MerkleFormat) -> Self {
+        Self {
+            format,
+            ..Self::default()
+        }
     }
     
-    let mut current_level: Vec<Hash> = leaves.to_vec();
+    /// Append the next leaf
+    pub fn push(&mut self, leaf: Hash) {
+        self.leaf_count += 1;
+        let mut node = self.format.leaf(leaf);
+        let mut level = 0;
+        
+        loop {
+            if level == self.levels.len() {
+                self.levels.push(None);
+            }
+            match self.levels[level].take() {
+                Some(left) => {
+                    node = self.format.node(left, node);
+                    level += 1;
+                }
+                None => {
+                    self.levels[level] = Some(node);
+                    return;
+                }
+            }
+        }
+    }
     
-    while current_level.len() > 1 {
-        let mut next_level = Vec::new();
-        
-        for chunk in current_level.chunks(2) {
-            let hash = if chunk.len() == 2 {
-                hash_multiple(&[chunk[0].as_bytes(), chunk[1].as_bytes()])
-            } else {
-                // Odd number: hash with itself
-                hash_multiple(&[chunk[0].as_bytes(), chunk[0].as_bytes()])
+    /// Compute the root of all leaves pushed so far
+    pub fn finish(self) -> Hash {
+        if self.leaf_count == 0 {
+            return Hash::ZERO;
+        }
+        
+        // Close each level's trailing node, which has no sibling when the
+        // level has an odd number of nodes, and carry the result upwards
+        let mut size = self.leaf_count;
+        let mut level = 0;
+        let mut carry: Option<Hash> = None;
+        
+        while size > 1 {
+            carry = match (self.levels[level], carry) {
+                (Some(left), Some(right)) => Some(self.format.node(left, right)),
+                (Some(node), None) | (None, Some(node)) => Some(self.format.lone(node)),
+                (None, None) => None,
             };
-            next_level.push(hash);
+            size = size.div_ceil(2);
+            level += 1;
+        }
+        
+        carry
+            .or_else(|| self.levels.get(level).copied().flatten())
+            .expect("top level holds the root")
+    }
+}
+
+/// Inclusion proof for one leaf of a [`merkle_root_with`] tree
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MerkleProof {
+    /// Position of the leaf among all leaves
+    pub leaf_index: u64,
+    /// Number of leaves in the tree
+    #[serde(default)]
+    pub leaf_count: u64,
+    /// Format of the tree
+    #[serde(default = "MerkleFormat::legacy")]
+    pub format: MerkleFormat,
+    /// Sibling of the running node at each level that has one, from the
+    /// leaves upwards
+    pub siblings: Vec<Hash>,
+}
+
+impl MerkleProof {
+    /// Root of the tree the proof was built from, given its leaf, or `None`
+    /// if the siblings don't fit the tree's shape
+    pub fn compute_root(&self, leaf: Hash) -> Option<Hash> {
+        let mut node = self.format.leaf(leaf);
+        let mut index = self.leaf_index;
+        let mut siblings = self.siblings.iter();
+        
+        match self.format {
+            // Every level has a sibling, possibly the node itself
+            MerkleFormat::DuplicateLast => {
+                for sibling in siblings.by_ref() {
+                    node = if index.is_multiple_of(2) {
+                        self.format.node(node, *sibling)
+                    } else {
+                        self.format.node(*sibling, node)
+                    };
+                    index /= 2;
+                }
+            }
+            // Trailing nodes move up without one
+            MerkleFormat::DomainSeparated => {
+                let mut size = self.leaf_count;
+                if index >= size {
+                    return None;
+                }
+                while size > 1 {
+                    if index ^ 1 < size {
+                        let sibling = *siblings.next()?;
+                        node = if index.is_multiple_of(2) {
+                            self.format.node(node, sibling)
+                        } else {
+                            self.format.node(sibling, node)
+                        };
+                    }
+                    index /= 2;
+                    size = size.div_ceil(2);
+                }
+            }
         }
         
-        current_level = next_level;
+        siblings.next().is_none().then_some(node)
     }
     
-    current_level[0]
+    /// Check that `leaf` is included in the tree with `root`
+    pub fn verify(&self, leaf: Hash, root: &Hash) -> bool {
+        self.compute_root(leaf) == Some(*root)
+    }
+}
+
+/// Build the inclusion proof for `leaves[index]`, matching [`merkle_root`]
+pub fn merkle_proof(leaves: &[Hash], index: usize) -> Option<MerkleProof> {
+    merkle_proof_with(MerkleFormat::CURRENT, leaves, index)
+}
+
+/// Build the inclusion proof for `leaves[index]` in a tree of the given format
+pub fn merkle_proof_with(
+    format: MerkleFormat,
+    leaves: &[Hash],
+    index: usize,
+) -> Option<MerkleProof> {
+    if index >= leaves.len() {
+        return None;
+    }
+    
+    let mut siblings = Vec::new();
+    let mut level: Vec<Hash> = leaves.iter().map(|leaf| format.leaf(*leaf)).collect();
+    let mut position = index;
+    
+    while level.len() > 1 {
+        match level.get(position ^ 1) {
+            Some(sibling) => siblings.push(*sibling),
+            None if format == MerkleFormat::DuplicateLast => siblings.push(level[position]),
+            None => {}
+        }
+        
+        level = level
+            .chunks(2)
+            .map(|chunk| match chunk.get(1) {
+                Some(right) => format.node(chunk[0], *right),
+                None => format.lone(chunk[0]),
+            })
+            .collect();
+        position /= 2;
+    }
+    
+    Some(MerkleProof {
+        leaf_index: index as u64,
+        leaf_count: leaves.len() as u64,
+        format,
+        siblings,
+    })
 }
 
 /// Incremental hasher for large data
@@ -127,6 +492,91 @@ mod tests {
         assert_ne!(blake3, sha256);
     }
     
+    #[test]
+    fn test_hash_with() {
+        let data = b"Hello, RAINSONET!";
+        assert_eq!(hash_with(HashAlgorithm::Blake3, data), blake3_hash(data));
+        assert_eq!(hash_with(HashAlgorithm::Sha256, data), sha256_hash(data));
+        
+        // Multi-part hashing frames parts the same way under either algorithm
+        let parts: [&[u8]; 2] = [b"ab", b"c"];
+        assert_eq!(
+            hash_multiple_in(HashAlgorithm::Sha256, HashFormat::Concatenated, &parts),
+            sha256_hash(b"abc")
+        );
+        
+        assert_eq!("sha256".parse(), Ok(HashAlgorithm::Sha256));
+        assert_eq!("BLAKE3".parse(), Ok(HashAlgorithm::Blake3));
+        assert!("md5".parse::<HashAlgorithm>().is_err());
+    }
+    
+    #[test]
+    fn test_hash_multiple_groupings_differ() {
+        let prefixed = |parts: &[&[u8]]| hash_multiple_with(HashFormat::LengthPrefixed, parts);
+        assert_ne!(prefixed(&[b"ab", b"c"]), prefixed(&[b"a", b"bc"]));
+        assert_ne!(prefixed(&[b"abc"]), prefixed(&[b"abc", b""]));
+        
+        // hash_multiple keeps the concatenated format, matching the joined bytes
+        let concatenated = hash_multiple(&[b"a", b"bc"]);
+        assert_eq!(concatenated, hash_multiple(&[b"ab", b"c"]));
+        assert_eq!(concatenated, hash(b"abc"));
+    }
+    
+    const FORMATS: [MerkleFormat; 2] =
+        [MerkleFormat::DuplicateLast, MerkleFormat::DomainSeparated];
+    
+    #[test]
+    fn test_merkle_builder_matches_merkle_root() {
+        for format in FORMATS {
+            for n in 0..20u32 {
+                let leaves: Vec<Hash> = (0..n).map(|i| hash(&i.to_le_bytes())).collect();
+                let mut builder = MerkleBuilder::with_format(format);
+                for leaf in &leaves {
+                    builder.push(*leaf);
+                }
+                assert_eq!(
+                    builder.finish(),
+                    merkle_root_with(format, &leaves),
+                    "{} leaves in {:?}",
+                    n,
+                    format
+                );
+            }
+        }
+    }
+    
+    #[test]
+    fn test_legacy_format_matches_baseline_roots() {
+        // Roots computed by merkle_root before the format changed
+        let leaves: Vec<Hash> = (1..=5).map(|i| hash(format!("leaf{}", i).as_bytes())).collect();
+        let legacy = MerkleFormat::DuplicateLast;
+        assert_eq!(
+            merkle_root_with(legacy, &leaves[..3]).to_hex(),
+            "7b0297d7b7a76e640bd71f10ba9473321b26a6c5994c7a5e80985b5f51d413e8"
+        );
+        assert_eq!(
+            merkle_root_with(legacy, &leaves).to_hex(),
+            "d93618bfd4686bb8904d493c611b7729bdb0395592f542f295c577863e949dbf"
+        );
+        assert_eq!(merkle_root_with(legacy, &leaves[..1]), leaves[0]);
+    }
+    
+    #[test]
+    fn test_duplicated_leaf_no_longer_collides() {
+        let leaves = vec![hash(b"leaf1"), hash(b"leaf2"), hash(b"leaf3")];
+        let mut duplicated = leaves.clone();
+        duplicated.push(hash(b"leaf3"));
+        
+        let legacy = MerkleFormat::DuplicateLast;
+        assert_eq!(merkle_root_with(legacy, &leaves), merkle_root_with(legacy, &duplicated));
+        assert_ne!(merkle_root(&leaves), merkle_root(&duplicated));
+        
+        // An internal node can no longer pass as a leaf either
+        let pair = merkle_root(&leaves[..2]);
+        assert_ne!(merkle_root(&[pair]), pair);
+        assert_eq!(merkle_root_with(legacy, &[pair]), pair);
+    }
+    
     #[test]
     fn test_merkle_root() {
         let leaves = vec![
@@ -144,6 +594,28 @@ mod tests {
         assert_eq!(root, root2);
     }
     
+    #[test]
+    fn test_merkle_proof() {
+        for format in FORMATS {
+            for n in 1..12u32 {
+                let leaves: Vec<Hash> = (0..n).map(|i| hash(&i.to_le_bytes())).collect();
+                let root = merkle_root_with(format, &leaves);
+                for (i, leaf) in leaves.iter().enumerate() {
+                    let proof = merkle_proof_with(format, &leaves, i).unwrap();
+                    assert!(proof.verify(*leaf, &root), "leaf {} of {} in {:?}", i, n, format);
+                    assert!(!proof.verify(hash(b"other"), &root));
+                }
+            }
+        }
+        assert!(merkle_proof(&[hash(b"leaf")], 1).is_none());
+        
+        // Extra siblings don't fit the tree
+        let leaves = vec![hash(b"leaf1"), hash(b"leaf2"), hash(b"leaf3")];
+        let mut proof = merkle_proof(&leaves, 2).unwrap();
+        proof.siblings.push(hash(b"extra"));
+        assert!(proof.compute_root(leaves[2]).is_none());
+    }
+    
     #[test]
     fn test_merkle_root_empty() {
         let root = merkle_root(&[]);
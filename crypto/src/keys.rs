@@ -10,11 +10,16 @@ use ed25519_dalek::{
 use rainsonet_core::{Address, NodeId, PublicKey, RainsonetError, RainsonetResult};
 use rand::rngs::OsRng;
 use serde::{Deserialize, Serialize};
-use zeroize::{Zeroize, ZeroizeOnDrop};
+use zeroize::{Zeroize, ZeroizeOnDrop, Zeroizing};
 
-use crate::hashing::blake3_hash;
+use crate::hashing::{blake3_hash, hash_multiple_with, HashFormat};
 
-/// A keypair for signing and verification
+/// Domain separator for multisig address derivation
+const MULTISIG_DOMAIN: &[u8] = b"rainsonet-multisig";
+
+/// A keypair for signing and verification.
+///
+/// The secret key is wiped when the keypair, or any clone of it, is dropped.
 #[derive(Clone)]
 pub struct KeyPair {
     signing_key: Ed25519SigningKey,
@@ -60,9 +65,10 @@ impl KeyPair {
         NodeId::from_bytes(*addr.as_bytes())
     }
     
-    /// Get the secret key bytes (BE CAREFUL with this!)
-    pub fn secret_bytes(&self) -> [u8; 32] {
-        self.signing_key.to_bytes()
+    /// Get the secret key bytes (BE CAREFUL with this!), wiped when the
+    /// returned copy is dropped
+    pub fn secret_bytes(&self) -> Zeroizing<[u8; 32]> {
+        Zeroizing::new(self.signing_key.to_bytes())
     }
     
     /// Get the internal signing key for signing operations
@@ -71,11 +77,14 @@ impl KeyPair {
     }
 }
 
-impl Drop for KeyPair {
-    fn drop(&mut self) {
-        // Zeroize is handled by ed25519-dalek internally
-    }
-}
+// `SigningKey` wipes its secret on drop only with ed25519-dalek's `zeroize`
+// feature; fail to compile rather than silently keep secrets around
+const _: fn() = || {
+    fn wipes_on_drop<T: ZeroizeOnDrop>() {}
+    wipes_on_drop::<Ed25519SigningKey>();
+};
+
+impl ZeroizeOnDrop for KeyPair {}
 
 /// Derive address from public key using BLAKE3 hash
 pub fn address_from_public_key(public_key: &PublicKey) -> Address {
@@ -83,6 +92,36 @@ pub fn address_from_public_key(public_key: &PublicKey) -> Address {
     Address::from_bytes(*hash.as_bytes())
 }
 
+/// Derive the canonical address of a `threshold`-of-n multisig key set.
+///
+/// Keys are sorted by their bytes and deduplicated first, so every
+/// participant derives the same address whatever order they list the keys
+/// in. The threshold must be between 1 and the number of distinct keys.
+pub fn multisig_address(pubkeys: &[PublicKey], threshold: u8) -> RainsonetResult<Address> {
+    let mut keys: Vec<&[u8; 32]> = pubkeys.iter().map(|k| k.as_bytes()).collect();
+    keys.sort_unstable();
+    keys.dedup();
+    
+    if threshold == 0 || threshold as usize > keys.len() {
+        return Err(RainsonetError::InvalidAddress(format!(
+            "Multisig threshold {} out of range for {} distinct keys",
+            threshold,
+            keys.len()
+        )));
+    }
+    
+    let mut parts: Vec<&[u8]> = Vec::with_capacity(keys.len() + 2);
+    let threshold = [threshold];
+    parts.push(MULTISIG_DOMAIN);
+    parts.push(&threshold);
+    parts.extend(keys.into_iter().map(|k| k.as_slice()));
+    
+    // All parts but the domain have fixed lengths, so the concatenated
+    // format is unambiguous and keeps existing multisig addresses valid
+    let address = hash_multiple_with(HashFormat::Concatenated, &parts);
+    Ok(Address::from_bytes(*address.as_bytes()))
+}
+
 /// Verify that an address matches a public key
 pub fn verify_address(address: &Address, public_key: &PublicKey) -> bool {
     let derived = address_from_public_key(public_key);
@@ -160,6 +199,52 @@ mod tests {
         assert_eq!(kp1.address(), kp2.address());
     }
     
+    #[test]
+    fn test_multisig_address_is_order_independent() {
+        let keys: Vec<PublicKey> = (0..3).map(|_| KeyPair::generate().public_key()).collect();
+        let address = multisig_address(&keys, 2).unwrap();
+        
+        let permuted = [keys[2], keys[0], keys[1]];
+        assert_eq!(multisig_address(&permuted, 2).unwrap(), address);
+        
+        // Duplicates do not change the key set
+        let duplicated = [keys[1], keys[0], keys[2], keys[0]];
+        assert_eq!(multisig_address(&duplicated, 2).unwrap(), address);
+        
+        assert_ne!(multisig_address(&keys, 3).unwrap(), address);
+        assert_ne!(multisig_address(&keys[..1], 1).unwrap(), address_from_public_key(&keys[0]));
+    }
+    
+    #[test]
+    fn test_multisig_address_rejects_invalid_threshold() {
+        let key = KeyPair::generate().public_key();
+        let keys = [key, KeyPair::generate().public_key()];
+        
+        assert!(multisig_address(&keys, 0).is_err());
+        assert!(multisig_address(&keys, 3).is_err());
+        // Thresholds count distinct keys
+        assert!(multisig_address(&[key, key], 2).is_err());
+        assert!(multisig_address(&[], 1).is_err());
+    }
+    
+    #[test]
+    fn test_keypair_wiped_on_drop() {
+        use std::mem::{size_of, ManuallyDrop};
+        
+        let seed = [0xa5u8; 32];
+        let mut kp = ManuallyDrop::new(KeyPair::from_seed(&seed));
+        let ptr = &*kp as *const KeyPair as *const u8;
+        // SAFETY: reads the keypair's own, still allocated bytes; a
+        // signing key is plain byte arrays and field elements, no padding
+        let memory = || unsafe { std::slice::from_raw_parts(ptr, size_of::<KeyPair>()) }.to_vec();
+        let holds_seed = |bytes: &[u8]| bytes.windows(32).any(|w| w == seed);
+        
+        assert!(holds_seed(&memory()));
+        // SAFETY: dropped once, and never used as a keypair afterwards
+        unsafe { ManuallyDrop::drop(&mut kp) };
+        assert!(!holds_seed(&memory()));
+    }
+    
     #[test]
     fn test_secret_key_zeroize() {
         let secret = SecretKey::new([42u8; 32]);
@@ -4,14 +4,17 @@
 //! - Ed25519 for signatures
 //! - BLAKE3 for hashing (SHA-256 fallback)
 //! - HKDF for key derivation
+//! - Argon2id and XChaCha20-Poly1305 for passphrase-encrypted secrets
 //! - Noise Protocol for network encryption
 
 pub mod keys;
 pub mod signing;
 pub mod hashing;
 pub mod derivation;
+pub mod encryption;
 
 pub use keys::*;
 pub use signing::*;
 pub use hashing::*;
 pub use derivation::*;
+pub use encryption::*;
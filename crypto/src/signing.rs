@@ -21,6 +21,35 @@ pub fn verify(public_key: &PublicKey, message: &[u8], signature: &Signature) ->
         .map_err(|_| RainsonetError::InvalidSignature)
 }
 
+/// Verify many signatures at once, much faster than one by one.
+///
+/// On failure the batch is rechecked one by one, and the error names the
+/// index of the first invalid item.
+pub fn verify_batch(items: &[(PublicKey, Vec<u8>, Signature)]) -> RainsonetResult<()> {
+    let mut keys = Vec::with_capacity(items.len());
+    for (index, (public_key, _, _)) in items.iter().enumerate() {
+        let key = public_key_to_ed25519(public_key)
+            .map_err(|_| RainsonetError::InvalidBatchSignature { index })?;
+        keys.push(key);
+    }
+    let messages: Vec<&[u8]> = items.iter().map(|(_, message, _)| message.as_slice()).collect();
+    let signatures: Vec<ed25519_dalek::Signature> = items
+        .iter()
+        .map(|(_, _, signature)| ed25519_dalek::Signature::from_bytes(signature.as_bytes()))
+        .collect();
+    
+    if ed25519_dalek::verify_batch(&messages, &signatures, &keys).is_ok() {
+        return Ok(());
+    }
+    
+    let index = items
+        .iter()
+        .position(|(key, message, signature)| !is_valid_signature(key, message, signature))
+        // Batch and single verification disagree only on crafted edge cases
+        .unwrap_or(0);
+    Err(RainsonetError::InvalidBatchSignature { index })
+}
+
 /// Check if a signature is valid (returns bool instead of Result)
 pub fn is_valid_signature(public_key: &PublicKey, message: &[u8], signature: &Signature) -> bool {
     verify(public_key, message, signature).is_ok()
@@ -84,6 +113,49 @@ mod tests {
         assert!(verify(&keypair1.public_key(), b"Different message", &signature).is_err());
     }
     
+    fn signed_batch(count: usize) -> Vec<(PublicKey, Vec<u8>, Signature)> {
+        (0..count)
+            .map(|i| {
+                let keypair = KeyPair::generate();
+                let message = format!("vote {}", i).into_bytes();
+                let signature = sign(&keypair, &message);
+                (keypair.public_key(), message, signature)
+            })
+            .collect()
+    }
+    
+    #[test]
+    fn test_verify_batch_names_first_invalid() {
+        let mut items = signed_batch(10);
+        assert!(verify_batch(&items).is_ok());
+        assert!(verify_batch(&[]).is_ok());
+        
+        items[7].1 = b"tampered".to_vec();
+        items[3].2 = items[4].2;
+        assert!(matches!(
+            verify_batch(&items),
+            Err(RainsonetError::InvalidBatchSignature { index: 3 })
+        ));
+    }
+    
+    #[test]
+    #[ignore = "timing benchmark, run with `cargo test --release -- --ignored`"]
+    fn test_verify_batch_faster_than_sequential() {
+        let items = signed_batch(100);
+        
+        let start = std::time::Instant::now();
+        for (public_key, message, signature) in &items {
+            verify(public_key, message, signature).unwrap();
+        }
+        let sequential = start.elapsed();
+        
+        let start = std::time::Instant::now();
+        verify_batch(&items).unwrap();
+        let batch = start.elapsed();
+        
+        assert!(batch < sequential);
+    }
+    
     #[test]
     fn test_signed_message() {
         let keypair = KeyPair::generate();
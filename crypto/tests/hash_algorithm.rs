@@ -0,0 +1,38 @@
+//! The default hash algorithm is process-wide, so it is switched here, in a
+//! test binary of its own, rather than beside tests that hash with it
+
+use rainsonet_core::Hash;
+use rainsonet_crypto::hashing::{
+    default_hash_algorithm, hash, hash_multiple_in, merkle_proof, merkle_root,
+    set_default_hash_algorithm, HashAlgorithm, HashFormat,
+};
+
+/// Leaf and internal node prefixes of domain-separated trees
+const LEAF: &[u8] = &[0];
+const NODE: &[u8] = &[1];
+
+#[test]
+fn test_default_hash_algorithm_drives_merkle_roots() {
+    let leaves = [hash(b"a"), hash(b"b"), hash(b"c")];
+    assert_eq!(default_hash_algorithm(), HashAlgorithm::Blake3);
+    let blake3_root = merkle_root(&leaves);
+    
+    set_default_hash_algorithm(HashAlgorithm::Sha256);
+    assert_eq!(default_hash_algorithm(), HashAlgorithm::Sha256);
+    
+    let sha256 = |parts: &[&[u8]]| {
+        hash_multiple_in(HashAlgorithm::Sha256, HashFormat::LengthPrefixed, parts)
+    };
+    let leaf = |h: &Hash| sha256(&[LEAF, h.as_bytes()]);
+    let node = |l: Hash, r: Hash| sha256(&[NODE, l.as_bytes(), r.as_bytes()]);
+    let expected = node(node(leaf(&leaves[0]), leaf(&leaves[1])), leaf(&leaves[2]));
+    
+    let sha256_root = merkle_root(&leaves);
+    assert_eq!(sha256_root, expected);
+    assert_ne!(sha256_root, blake3_root);
+    assert!(merkle_proof(&leaves, 2).unwrap().verify(leaves[2], &sha256_root));
+    
+    // Switching back restores the original roots
+    set_default_hash_algorithm(HashAlgorithm::Blake3);
+    assert_eq!(merkle_root(&leaves), blake3_root);
+}
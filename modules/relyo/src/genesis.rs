@@ -1,9 +1,15 @@
 //! Genesis configuration for RELYO
 
-use rainsonet_core::{Address, Amount, RainsonetError, RainsonetResult, RelyoConfig};
+use rainsonet_consensus::ValidatorInfo;
+use rainsonet_core::{
+    Address, Amount, NodeId, PublicKey, RainsonetError, RainsonetResult, RelyoConfig,
+};
+use rainsonet_crypto::hashing::HashAlgorithm;
+use rainsonet_crypto::keys::{address_from_public_key, public_key_to_ed25519};
 use rainsonet_state::StateStore;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
 use tracing::info;
 
 use crate::ledger::RelyoLedger;
@@ -15,6 +21,18 @@ pub struct GenesisAllocation {
     pub balance: String,
 }
 
+/// Stake of a genesis validator listed without one
+pub const DEFAULT_VALIDATOR_STAKE: u128 = 1000;
+
+/// Genesis validator entry
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GenesisValidator {
+    pub public_key: String,
+    /// Derived from `public_key`
+    pub address: String,
+    pub stake: String,
+}
+
 /// Genesis configuration
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct GenesisConfig {
@@ -26,11 +44,19 @@ pub struct GenesisConfig {
     pub timestamp: u64,
     /// Initial validator addresses
     pub validators: Vec<String>,
+    /// Initial validators' public keys and stakes, which every node loads
+    /// into its validator set
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub validator_keys: Vec<GenesisValidator>,
     /// Initial token allocations
     pub allocations: Vec<GenesisAllocation>,
-    /// RELYO config
+    /// RELYO config, including the fee policy every node must agree on
     #[serde(default)]
     pub relyo_config: RelyoConfig,
+    /// Algorithm for state roots and proposal change hashes, fixed for the
+    /// life of the chain. Omitted when BLAKE3, so existing genesis hashes hold.
+    #[serde(default, skip_serializing_if = "HashAlgorithm::is_default")]
+    pub hash_algorithm: HashAlgorithm,
 }
 
 impl Default for GenesisConfig {
@@ -40,8 +66,10 @@ impl Default for GenesisConfig {
             chain_id: 1,
             timestamp: 0,
             validators: vec![],
+            validator_keys: vec![],
             allocations: vec![],
             relyo_config: RelyoConfig::default(),
+            hash_algorithm: HashAlgorithm::default(),
         }
     }
 }
@@ -75,13 +103,151 @@ impl GenesisConfig {
         self
     }
     
-    /// Add an allocation
-    pub fn add_allocation(mut self, address: &str, balance_relyo: u64) -> Self {
+    /// Add a validator by its hex public key, listing the address derived
+    /// from it. Fails on a malformed or duplicate key or a zero stake.
+    pub fn try_add_validator_key(mut self, public_key: &str, stake: u128) -> RainsonetResult<Self> {
+        let key = parse_validator_key(public_key)?;
+        if stake == 0 {
+            return Err(RainsonetError::ConfigError(format!(
+                "Validator {} has no stake",
+                public_key
+            )));
+        }
+        if self.validator_keys.iter().any(|v| v.public_key == key.to_hex()) {
+            return Err(RainsonetError::ConfigError(format!(
+                "Validator {} listed twice",
+                public_key
+            )));
+        }
+        
+        let address = address_from_public_key(&key).to_hex();
+        if !self.validators.contains(&address) {
+            self.validators.push(address.clone());
+        }
+        self.validator_keys.push(GenesisValidator {
+            public_key: key.to_hex(),
+            address,
+            stake: stake.to_string(),
+        });
+        Ok(self)
+    }
+    
+    /// Genesis validators with their stakes
+    pub fn parse_validators(&self) -> RainsonetResult<Vec<ValidatorInfo>> {
+        self.validator_keys
+            .iter()
+            .map(|v| {
+                let key = parse_validator_key(&v.public_key)?;
+                let address = address_from_public_key(&key);
+                if Address::from_hex(&v.address).ok() != Some(address) {
+                    return Err(RainsonetError::ConfigError(format!(
+                        "Validator address {} does not match its public key",
+                        v.address
+                    )));
+                }
+                let stake: u128 = v.stake.parse().map_err(|e| {
+                    RainsonetError::ConfigError(format!("Invalid validator stake: {}", e))
+                })?;
+                Ok(ValidatorInfo::new(NodeId::from_bytes(*address.as_bytes()), key, stake))
+            })
+            .collect()
+    }
+    
+    /// Add an allocation of whole RELYO
+    ///
+    /// # Panics
+    ///
+    /// If the balance overflows, see [`GenesisConfig::try_add_allocation`].
+    pub fn add_allocation(self, address: &str, balance_relyo: u128) -> Self {
+        self.try_add_allocation(address, balance_relyo)
+            .expect("genesis allocation overflows")
+    }
+    
+    /// Add an allocation of whole RELYO, failing if it overflows the
+    /// smallest-unit representation
+    pub fn try_add_allocation(
+        mut self,
+        address: &str,
+        balance_relyo: u128,
+    ) -> RainsonetResult<Self> {
+        let balance = Amount::from_relyo_u128(balance_relyo).ok_or_else(|| {
+            RainsonetError::ConfigError(format!(
+                "Allocation of {} RELYO to {} overflows",
+                balance_relyo, address
+            ))
+        })?;
         self.allocations.push(GenesisAllocation {
             address: address.to_string(),
-            balance: format!("{}", Amount::from_relyo(balance_relyo).0),
+            balance: format!("{}", balance.0),
         });
-        self
+        Ok(self)
+    }
+    
+    /// Append allocations from a CSV file of `address,balance_relyo` rows,
+    /// see [`GenesisConfig::add_allocations_from_csv_str`]
+    pub fn add_allocations_from_csv(self, path: impl AsRef<Path>) -> RainsonetResult<Self> {
+        let path = path.as_ref();
+        let csv = std::fs::read_to_string(path).map_err(|e| {
+            RainsonetError::ConfigError(format!("Cannot read {}: {}", path.display(), e))
+        })?;
+        self.add_allocations_from_csv_str(&csv)
+    }
+    
+    /// Append allocations from CSV rows of `address,balance_relyo`, with
+    /// balances in whole RELYO. An `address,...` header and blank lines are
+    /// skipped.
+    ///
+    /// Nothing is added unless every row is valid; errors name the first bad
+    /// row. Addresses already allocated, in the config or earlier in the
+    /// CSV, are rejected, as is a total supply that overflows.
+    pub fn add_allocations_from_csv_str(mut self, csv: &str) -> RainsonetResult<Self> {
+        let existing = self.parse_allocations()?;
+        let mut seen: HashSet<Address> = existing.keys().copied().collect();
+        let mut total = self.total_supply()?.0;
+        let mut added = Vec::new();
+        
+        for (index, line) in csv.lines().enumerate() {
+            let row = index + 1;
+            let row_error =
+                |reason: String| RainsonetError::ConfigError(format!("Row {}: {}", row, reason));
+            let line = line.trim();
+            if line.is_empty() {
+                continue;
+            }
+            
+            let (address, balance) = line
+                .split_once(',')
+                .ok_or_else(|| row_error("expected `address,balance_relyo`".into()))?;
+            let (address, balance) = (address.trim(), balance.trim());
+            if row == 1 && address.eq_ignore_ascii_case("address") {
+                continue;
+            }
+            
+            let parsed = Address::from_hex(address)
+                .map_err(|e| row_error(format!("invalid address {}: {}", address, e)))?;
+            let relyo: u128 = balance
+                .parse()
+                .map_err(|e| row_error(format!("invalid balance {}: {}", balance, e)))?;
+            if relyo == 0 {
+                return Err(row_error("balance must be positive".into()));
+            }
+            let amount = Amount::from_relyo_u128(relyo)
+                .ok_or_else(|| row_error(format!("balance of {} RELYO overflows", relyo)))?;
+            if !seen.insert(parsed) {
+                return Err(row_error(format!("duplicate address {}", address)));
+            }
+            total = total
+                .checked_add(amount.0)
+                .ok_or_else(|| row_error("total supply overflows".into()))?;
+            
+            added.push(GenesisAllocation {
+                address: parsed.to_hex(),
+                balance: amount.0.to_string(),
+            });
+        }
+        
+        self.allocations.extend(added);
+        Ok(self)
     }
     
     /// Parse allocations into address -> amount map
@@ -103,10 +269,13 @@ impl GenesisConfig {
         Ok(result)
     }
     
-    /// Calculate total supply from allocations
+    /// Calculate total supply from allocations, failing if it overflows
     pub fn total_supply(&self) -> RainsonetResult<Amount> {
         let allocations = self.parse_allocations()?;
-        let total: u128 = allocations.values().map(|a| a.0).sum();
+        let total = allocations
+            .values()
+            .try_fold(0u128, |total, a| total.checked_add(a.0))
+            .ok_or_else(|| RainsonetError::ConfigError("Total supply overflows".into()))?;
         Ok(Amount::new(total))
     }
     
@@ -116,26 +285,96 @@ impl GenesisConfig {
             .map_err(|e| RainsonetError::SerializationError(e.to_string()))
     }
     
-    /// Load from JSON
+    /// Load from JSON, rejecting an invalid fee policy
     pub fn from_json(json: &str) -> RainsonetResult<Self> {
-        serde_json::from_str(json)
-            .map_err(|e| RainsonetError::DeserializationError(e.to_string()))
+        let config: Self = serde_json::from_str(json)
+            .map_err(|e| RainsonetError::DeserializationError(e.to_string()))?;
+        config.validate()?;
+        Ok(config)
     }
+    
+    /// Check that the RELYO fee policy is usable and the validator keys
+    /// match their addresses
+    pub fn validate(&self) -> RainsonetResult<()> {
+        self.parse_validators()?;
+        
+        let relyo = &self.relyo_config;
+        if relyo.fee_burn_percent > 100 {
+            return Err(RainsonetError::ConfigError(format!(
+                "Fee burn of {}% exceeds 100%",
+                relyo.fee_burn_percent
+            )));
+        }
+        if relyo.max_tx_amount == 0 {
+            return Err(RainsonetError::ConfigError(
+                "Maximum transaction amount must be positive".into(),
+            ));
+        }
+        if relyo.min_fee > relyo.max_tx_amount {
+            return Err(RainsonetError::ConfigError(format!(
+                "Minimum fee {} exceeds maximum transaction amount {}",
+                relyo.min_fee, relyo.max_tx_amount
+            )));
+        }
+        if relyo.tx_expiry_seconds == 0 {
+            return Err(RainsonetError::ConfigError(
+                "Transaction expiry must be positive".into(),
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Parse a hex Ed25519 public key
+fn parse_validator_key(public_key: &str) -> RainsonetResult<PublicKey> {
+    let key = PublicKey::from_hex(public_key).map_err(|e| {
+        RainsonetError::ConfigError(format!("Invalid validator key {}: {}", public_key, e))
+    })?;
+    public_key_to_ed25519(&key).map_err(|_| {
+        RainsonetError::ConfigError(format!("{} is not an Ed25519 public key", public_key))
+    })?;
+    Ok(key)
 }
 
 /// Initialize genesis state
 pub struct GenesisInitializer<S: StateStore> {
     ledger: std::sync::Arc<RelyoLedger<S>>,
     config: GenesisConfig,
+    force: bool,
 }
 
 impl<S: StateStore + 'static> GenesisInitializer<S> {
     pub fn new(ledger: std::sync::Arc<RelyoLedger<S>>, config: GenesisConfig) -> Self {
-        Self { ledger, config }
+        Self {
+            ledger,
+            config,
+            force: false,
+        }
     }
     
-    /// Initialize the genesis state
+    /// Allow initializing a store that already holds accounts, overwriting
+    /// the balances of allocated addresses
+    pub fn force(mut self, force: bool) -> Self {
+        self.force = force;
+        self
+    }
+    
+    /// Allocations `initialize` would write, without writing them
+    pub fn dry_run(&self) -> RainsonetResult<HashMap<Address, Amount>> {
+        self.config.parse_allocations()
+    }
+    
+    /// Initialize the genesis state.
+    ///
+    /// Refuses to run against a store that already holds accounts unless
+    /// [`force`](Self::force) is set.
     pub async fn initialize(&self) -> RainsonetResult<()> {
+        if !self.force && self.ledger.has_accounts().await? {
+            return Err(RainsonetError::ConfigError(
+                "Genesis refused: state already contains accounts".into(),
+            ));
+        }
+        
         info!("Initializing genesis for chain: {}", self.config.chain_name);
         info!("Chain ID: {}", self.config.chain_id);
         
@@ -153,7 +392,10 @@ impl<S: StateStore + 'static> GenesisInitializer<S> {
     }
 }
 
-/// Genesis state hash computation
+/// Genesis state hash computation.
+///
+/// Covers the whole config including `relyo_config`, so nodes whose
+/// genesis differs only in fee policy get different hashes and do not peer.
 pub fn compute_genesis_hash(config: &GenesisConfig) -> RainsonetResult<rainsonet_core::Hash> {
     let json = config.to_json()?;
     Ok(rainsonet_crypto::hashing::hash(json.as_bytes()))
@@ -179,6 +421,118 @@ mod tests {
         assert_eq!(config.allocations.len(), 1);
     }
     
+    #[test]
+    fn test_large_allocations() {
+        let address = KeyPair::generate().address().to_hex();
+        
+        // Beyond u64 whole RELYO, still representable
+        let config = GenesisConfig::devnet()
+            .try_add_allocation(&address, u64::MAX as u128 + 1)
+            .unwrap();
+        assert_eq!(
+            config.total_supply().unwrap(),
+            Amount::from_relyo_u128(u64::MAX as u128 + 1).unwrap()
+        );
+        
+        let overflow = GenesisConfig::devnet().try_add_allocation(&address, u128::MAX / 1000);
+        assert!(matches!(overflow, Err(RainsonetError::ConfigError(_))));
+    }
+    
+    #[test]
+    fn test_allocations_from_csv() {
+        let (a, b) = (KeyPair::generate().address(), KeyPair::generate().address());
+        let name = format!("rainsonet-allocations-{}.csv", std::process::id());
+        let path = std::env::temp_dir().join(name);
+        std::fs::write(&path, format!("address,balance_relyo\n{},100\n\n{},250\n", a, b)).unwrap();
+        
+        let config = GenesisConfig::devnet().add_allocations_from_csv(&path).unwrap();
+        let _ = std::fs::remove_file(&path);
+        
+        let allocations = config.parse_allocations().unwrap();
+        assert_eq!(allocations.len(), 2);
+        assert_eq!(allocations[&a], Amount::from_relyo(100));
+        assert_eq!(allocations[&b], Amount::from_relyo(250));
+        assert_eq!(config.total_supply().unwrap(), Amount::from_relyo(350));
+    }
+    
+    #[test]
+    fn test_csv_errors_name_the_row() {
+        let a = KeyPair::generate().address();
+        
+        let csv = format!("{},100\nnot-an-address,5\n", a);
+        let err = GenesisConfig::devnet().add_allocations_from_csv_str(&csv).unwrap_err();
+        assert!(err.to_string().contains("Row 2: invalid address"), "{}", err);
+        
+        // Duplicates within the CSV and against existing allocations
+        let csv = format!("{},100\n{},5\n", a, a);
+        let err = GenesisConfig::devnet().add_allocations_from_csv_str(&csv).unwrap_err();
+        assert!(err.to_string().contains("Row 2: duplicate address"), "{}", err);
+        
+        let config = GenesisConfig::devnet().add_allocation(&a.to_hex(), 1);
+        let err = config.add_allocations_from_csv_str(&format!("{},5", a)).unwrap_err();
+        assert!(err.to_string().contains("Row 1: duplicate address"), "{}", err);
+        
+        let csv = format!("{},{}\n", a, u128::MAX);
+        assert!(GenesisConfig::devnet().add_allocations_from_csv_str(&csv).is_err());
+    }
+    
+    #[test]
+    fn test_validator_keys_with_stakes() {
+        let (v1, v2) = (KeyPair::generate(), KeyPair::generate());
+        let holder = KeyPair::generate().address();
+        let build = || {
+            GenesisConfig::devnet()
+                .try_add_validator_key(&v1.public_key().to_hex(), 5_000)
+                .unwrap()
+                .try_add_validator_key(&v2.public_key().to_hex(), DEFAULT_VALIDATOR_STAKE)
+                .unwrap()
+                .try_add_allocation(&holder.to_hex(), 250)
+                .unwrap()
+        };
+        
+        let config = build();
+        assert_eq!(config.validators, vec![v1.address().to_hex(), v2.address().to_hex()]);
+        let validators = config.parse_validators().unwrap();
+        assert_eq!(validators[0].node_id, v1.node_id());
+        assert_eq!(validators[0].public_key, v1.public_key());
+        assert_eq!(validators[0].stake, 5_000);
+        assert_eq!(validators[1].node_id, v2.node_id());
+        assert_eq!(validators[1].stake, DEFAULT_VALIDATOR_STAKE);
+        assert_eq!(config.parse_allocations().unwrap()[&holder], Amount::from_relyo(250));
+        
+        // Survives a round trip, and the same inputs give the same hash
+        let restored = GenesisConfig::from_json(&config.to_json().unwrap()).unwrap();
+        assert_eq!(restored.validator_keys, config.validator_keys);
+        assert_eq!(
+            compute_genesis_hash(&config).unwrap(),
+            compute_genesis_hash(&build()).unwrap()
+        );
+        let restaked = GenesisConfig::devnet()
+            .try_add_validator_key(&v1.public_key().to_hex(), 5_001)
+            .unwrap();
+        assert_ne!(
+            compute_genesis_hash(&restaked).unwrap(),
+            compute_genesis_hash(&config).unwrap()
+        );
+    }
+    
+    #[test]
+    fn test_invalid_validator_keys_rejected() {
+        let key = KeyPair::generate().public_key().to_hex();
+        let genesis = GenesisConfig::devnet();
+        
+        assert!(genesis.clone().try_add_validator_key("abcd", 1).is_err());
+        assert!(genesis.clone().try_add_validator_key(&key, 0).is_err());
+        let twice = genesis.clone().try_add_validator_key(&key, 1).unwrap();
+        assert!(twice.try_add_validator_key(&key, 1).is_err());
+        
+        // A key whose listed address was edited no longer loads
+        let mut tampered = genesis.try_add_validator_key(&key, 1).unwrap();
+        tampered.validator_keys[0].address = KeyPair::generate().address().to_hex();
+        let json = tampered.to_json().unwrap();
+        assert!(matches!(GenesisConfig::from_json(&json), Err(RainsonetError::ConfigError(_))));
+    }
+    
     #[test]
     fn test_genesis_json() {
         let config = GenesisConfig::testnet();
@@ -189,6 +543,50 @@ mod tests {
         assert_eq!(config.chain_id, restored.chain_id);
     }
     
+    #[test]
+    fn test_fee_policy_in_genesis_hash() {
+        let devnet = GenesisConfig::devnet();
+        let json = devnet.to_json().unwrap();
+        assert!(json.contains("\"min_fee\""));
+        assert!(json.contains("\"fee_burn_percent\""));
+        
+        let devnet_hash = compute_genesis_hash(&devnet).unwrap();
+        let mut pricier = GenesisConfig::from_json(&json).unwrap();
+        assert_eq!(compute_genesis_hash(&pricier).unwrap(), devnet_hash);
+        
+        pricier.relyo_config.min_fee = 1_000;
+        assert_ne!(compute_genesis_hash(&pricier).unwrap(), devnet_hash);
+    }
+    
+    #[test]
+    fn test_hash_algorithm_in_genesis_hash() {
+        let devnet = GenesisConfig::devnet();
+        let json = devnet.to_json().unwrap();
+        assert!(!json.contains("hash_algorithm"));
+        
+        let mut sha256 = devnet.clone();
+        sha256.hash_algorithm = HashAlgorithm::Sha256;
+        let json = sha256.to_json().unwrap();
+        assert!(json.contains("\"hash_algorithm\": \"sha256\""));
+        assert_eq!(GenesisConfig::from_json(&json).unwrap().hash_algorithm, HashAlgorithm::Sha256);
+        assert_ne!(
+            compute_genesis_hash(&sha256).unwrap(),
+            compute_genesis_hash(&devnet).unwrap()
+        );
+    }
+    
+    #[test]
+    fn test_invalid_fee_policy_rejected_at_load() {
+        let mut config = GenesisConfig::devnet();
+        config.relyo_config.fee_burn_percent = 101;
+        let json = config.to_json().unwrap();
+        
+        assert!(matches!(
+            GenesisConfig::from_json(&json),
+            Err(RainsonetError::ConfigError(_))
+        ));
+    }
+    
     #[tokio::test]
     async fn test_genesis_initialization() {
         let kp = KeyPair::generate();
@@ -206,4 +604,48 @@ mod tests {
         let balance = ledger.get_balance(&kp.address()).await.unwrap();
         assert_eq!(balance.0, Amount::from_relyo(1000).0);
     }
+    
+    #[tokio::test]
+    async fn test_genesis_refuses_non_empty_store() {
+        let kp = KeyPair::generate();
+        let config = GenesisConfig::devnet().add_allocation(&kp.address().to_hex(), 1000);
+        
+        let state = Arc::new(MemoryStateStore::new());
+        let ledger = Arc::new(RelyoLedger::new(state, config.relyo_config.clone()));
+        GenesisInitializer::new(ledger.clone(), config.clone())
+            .initialize()
+            .await
+            .unwrap();
+        
+        // Spend some of the allocation so a re-run would be visible
+        ledger.set_balance(&kp.address(), Amount::new(5)).await.unwrap();
+        
+        let result = GenesisInitializer::new(ledger.clone(), config.clone()).initialize().await;
+        assert!(matches!(result, Err(RainsonetError::ConfigError(_))));
+        assert_eq!(ledger.get_balance(&kp.address()).await.unwrap(), Amount::new(5));
+        
+        GenesisInitializer::new(ledger.clone(), config)
+            .force(true)
+            .initialize()
+            .await
+            .unwrap();
+        assert_eq!(ledger.get_balance(&kp.address()).await.unwrap(), Amount::from_relyo(1000));
+    }
+    
+    #[tokio::test]
+    async fn test_genesis_dry_run_does_not_write() {
+        let kp = KeyPair::generate();
+        let config = GenesisConfig::devnet().add_allocation(&kp.address().to_hex(), 1000);
+        
+        let state = Arc::new(MemoryStateStore::new());
+        let ledger = Arc::new(RelyoLedger::new(state, config.relyo_config.clone()));
+        let initializer = GenesisInitializer::new(ledger.clone(), config);
+        
+        let allocations = initializer.dry_run().unwrap();
+        assert_eq!(allocations.len(), 1);
+        assert_eq!(allocations[&kp.address()], Amount::from_relyo(1000));
+        
+        assert!(!ledger.has_accounts().await.unwrap());
+        assert_eq!(ledger.total_supply(), Amount::ZERO);
+    }
 }
@@ -1,16 +1,15 @@
 //! RELYO Ledger - Account balance management
 
-use async_trait::async_trait;
-use parking_lot::RwLock;
+use parking_lot::{Mutex, RwLock};
 use rainsonet_core::{
     Address, Amount, Nonce, RainsonetError, RainsonetResult, RelyoConfig, StateChange,
 };
-use rainsonet_state::{AccountState, StateStore};
+use rainsonet_state::{AccountState, StateStore, ACCOUNT_PREFIX};
 use std::collections::HashMap;
-use std::sync::Arc;
-use tracing::{debug, info};
+use std::sync::{mpsc, Arc};
+use tracing::{debug, info, warn};
 
-use crate::transaction::{RelyoTransaction, VerifiedTransaction};
+use crate::transaction::VerifiedTransaction;
 
 /// Account information
 #[derive(Debug, Clone, Default)]
@@ -42,13 +41,85 @@ impl Account {
     }
 }
 
+/// Breakdown of where the total supply currently sits
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SupplyAudit {
+    /// Sum of all account balances
+    pub balances: Amount,
+    /// Fees burned so far
+    pub burned: Amount,
+    /// Validator share of fees, collected but not credited to any account
+    pub collected_fees: Amount,
+    /// Supply tracked by the ledger
+    pub total_supply: Amount,
+}
+
+impl SupplyAudit {
+    /// Whether balances, burned and collected fees add up to the total supply
+    pub fn reconciles(&self) -> bool {
+        self.accounted() == self.total_supply.0
+    }
+    
+    /// Signed difference between accounted-for tokens and the total supply
+    pub fn discrepancy(&self) -> i128 {
+        self.accounted() as i128 - self.total_supply.0 as i128
+    }
+    
+    fn accounted(&self) -> u128 {
+        self.balances
+            .0
+            .saturating_add(self.burned.0)
+            .saturating_add(self.collected_fees.0)
+    }
+}
+
+/// Observer of committed accounts, called with the address and the account
+/// before and after the commit
+pub type AccountHook = Box<dyn Fn(&Address, &Account, &Account) + Send + Sync>;
+
+/// Committed account change queued for the hooks
+struct AccountChange {
+    address: Address,
+    old: Account,
+    new: Account,
+}
+
+/// Changes made by executing one proposal's transactions, kept apart from
+/// committed state until [`RelyoLedger::commit`] applies them.
+///
+/// Every proposal executes in a context of its own, so ledger reads made
+/// while a proposal is in flight only ever see committed state.
+#[derive(Debug, Default)]
+pub struct ProposalContext {
+    accounts: HashMap<Address, Account>,
+    /// Committed state of each account in `accounts`, as it was before the
+    /// first change to it
+    originals: HashMap<Address, Account>,
+    burned: Amount,
+    collected_fees: Amount,
+}
+
+impl ProposalContext {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    
+    /// Whether no account was changed in this context
+    pub fn is_empty(&self) -> bool {
+        self.accounts.is_empty()
+    }
+}
+
 /// RELYO Ledger for managing accounts
 pub struct RelyoLedger<S: StateStore> {
     state: Arc<S>,
     config: RelyoConfig,
-    pending_changes: RwLock<HashMap<Address, Account>>,
+    account_hooks: Arc<RwLock<Vec<AccountHook>>>,
+    /// Queue to the thread running `account_hooks`, started with the first hook
+    account_changes: Mutex<Option<mpsc::Sender<AccountChange>>>,
     total_supply: RwLock<Amount>,
     burned: RwLock<Amount>,
+    collected_fees: RwLock<Amount>,
 }
 
 impl<S: StateStore + 'static> RelyoLedger<S> {
@@ -56,26 +127,61 @@ impl<S: StateStore + 'static> RelyoLedger<S> {
         Self {
             state,
             config,
-            pending_changes: RwLock::new(HashMap::new()),
+            account_hooks: Arc::new(RwLock::new(Vec::new())),
+            account_changes: Mutex::new(None),
             total_supply: RwLock::new(Amount::ZERO),
             burned: RwLock::new(Amount::ZERO),
+            collected_fees: RwLock::new(Amount::ZERO),
         }
     }
     
-    /// Get account, checking pending changes first
-    pub async fn get_account(&self, address: &Address) -> RainsonetResult<Account> {
-        // Check pending changes first
-        if let Some(account) = self.pending_changes.read().get(address) {
-            return Ok(account.clone());
+    /// Register a hook called for every account written by [`Self::commit`].
+    ///
+    /// Hooks run in order on a dedicated thread, so a slow hook delays
+    /// later notifications but never the commit itself.
+    pub fn on_account_change(&self, hook: AccountHook) {
+        self.account_hooks.write().push(hook);
+        
+        let mut account_changes = self.account_changes.lock();
+        if account_changes.is_none() {
+            let (tx, rx) = mpsc::channel::<AccountChange>();
+            let hooks = self.account_hooks.clone();
+            let spawned = std::thread::Builder::new()
+                .name("relyo-account-hooks".into())
+                .spawn(move || {
+                    for change in rx {
+                        for hook in hooks.read().iter() {
+                            hook(&change.address, &change.old, &change.new);
+                        }
+                    }
+                });
+            match spawned {
+                Ok(_) => *account_changes = Some(tx),
+                Err(e) => warn!("Failed to start account hook thread: {}", e),
+            }
         }
-        
-        // Check state store
+    }
+    
+    /// Get committed account state
+    pub async fn get_account(&self, address: &Address) -> RainsonetResult<Account> {
         match self.state.get_account(address.as_bytes()).await? {
             Some(state) => Ok(Account::from_state(*address, state)),
             None => Ok(Account::new(*address, Amount::ZERO)),
         }
     }
     
+    /// Get account as seen by transactions executing in `context`
+    pub async fn get_account_in(
+        &self,
+        context: &ProposalContext,
+        address: &Address,
+    ) -> RainsonetResult<Account> {
+        match context.accounts.get(address) {
+            Some(account) => Ok(account.clone()),
+            None => self.get_account(address).await,
+        }
+    }
+    
     /// Get balance
     pub async fn get_balance(&self, address: &Address) -> RainsonetResult<Amount> {
         Ok(self.get_account(address).await?.balance)
@@ -86,26 +192,21 @@ impl<S: StateStore + 'static> RelyoLedger<S> {
         Ok(self.get_account(address).await?.nonce)
     }
     
-    /// Execute a verified transaction and return state changes
+    /// Execute a verified transaction in `context` and return state changes
     pub async fn execute_transaction(
         &self,
+        context: &mut ProposalContext,
         tx: &VerifiedTransaction,
     ) -> RainsonetResult<Vec<StateChange>> {
         let tx = &tx.tx;
         
         // Get current accounts
-        let mut sender = self.get_account(&tx.from).await?;
-        let mut recipient = self.get_account(&tx.to).await?;
+        let mut sender = self.get_account_in(context, &tx.from).await?;
+        let mut recipient = self.get_account_in(context, &tx.to).await?;
+        let (original_sender, original_recipient) = (sender.clone(), recipient.clone());
         
-        // Validate nonce
-        if tx.nonce != sender.nonce {
-            return Err(RainsonetError::InvalidNonce {
-                expected: sender.nonce.0,
-                got: tx.nonce.0,
-            });
-        }
-        
-        // Validate balance
+        // Validate balance before nonce, so an unaffordable transaction is
+        // dropped rather than parked for a nonce gap
         let total_cost = tx.total_cost();
         if sender.balance.0 < total_cost.0 {
             return Err(RainsonetError::InsufficientBalance {
@@ -114,6 +215,14 @@ impl<S: StateStore + 'static> RelyoLedger<S> {
             });
         }
         
+        // Validate nonce
+        if tx.nonce != sender.nonce {
+            return Err(RainsonetError::InvalidNonce {
+                expected: sender.nonce.0,
+                got: tx.nonce.0,
+            });
+        }
+        
         // Calculate fee distribution
         let burn_amount = Amount::new(
             tx.fee.0 * self.config.fee_burn_percent as u128 / 100,
@@ -127,55 +236,89 @@ impl<S: StateStore + 'static> RelyoLedger<S> {
         // Update recipient
         recipient.balance = recipient.balance.saturating_add(tx.amount);
         
-        // Track burned amount
-        if burn_amount.0 > 0 {
-            *self.burned.write() = self.burned.read().saturating_add(burn_amount);
-        }
+        // Track burned amount, counted by the ledger once committed
+        context.burned = context.burned.saturating_add(burn_amount);
+        context.collected_fees = context.collected_fees.saturating_add(validator_fee);
         
         // Create state changes
-        let mut changes = Vec::new();
-        
-        changes.push(StateChange::Set {
-            key: rainsonet_state::account_key(tx.from.as_bytes()),
-            value: sender.to_state().to_bytes(),
-        });
-        
-        changes.push(StateChange::Set {
-            key: rainsonet_state::account_key(tx.to.as_bytes()),
-            value: recipient.to_state().to_bytes(),
-        });
-        
-        // Update pending changes
-        {
-            let mut pending = self.pending_changes.write();
-            pending.insert(tx.from, sender);
-            pending.insert(tx.to, recipient);
-        }
+        let changes = vec![
+            StateChange::Set {
+                key: rainsonet_state::account_key(tx.from.as_bytes()),
+                value: sender.to_state().to_bytes(),
+            },
+            StateChange::Set {
+                key: rainsonet_state::account_key(tx.to.as_bytes()),
+                value: recipient.to_state().to_bytes(),
+            },
+        ];
+        
+        // Update the context
+        context.accounts.insert(tx.from, sender);
+        context.accounts.insert(tx.to, recipient);
+        context.originals.entry(tx.from).or_insert(original_sender);
+        context.originals.entry(tx.to).or_insert(original_recipient);
         
         debug!(
             "Executed tx: {} -> {} amount={} fee={}",
-            tx.from, tx.to, tx.amount, tx.fee
+            tx.from.short(), tx.to.short(), tx.amount, tx.fee
         );
         
         Ok(changes)
     }
     
-    /// Commit pending changes to state
-    pub async fn commit(&self) -> RainsonetResult<()> {
-        let pending = std::mem::take(&mut *self.pending_changes.write());
-        
-        for (address, account) in pending {
+    /// Compute the state changes a sequence of transactions produces on top
+    /// of committed state, in a context that is discarded afterwards
+    pub async fn preview_changes(
+        &self,
+        transactions: &[VerifiedTransaction],
+    ) -> RainsonetResult<Vec<StateChange>> {
+        let mut context = ProposalContext::new();
+        let mut changes = Vec::new();
+        for tx in transactions {
+            changes.extend(self.execute_transaction(&mut context, tx).await?);
+        }
+        Ok(changes)
+    }
+    
+    /// Commit the changes made in `context` to state, then notify account hooks
+    pub async fn commit(&self, context: ProposalContext) -> RainsonetResult<()> {
+        let ProposalContext {
+            accounts,
+            mut originals,
+            burned,
+            collected_fees,
+        } = context;
+        let account_changes = self.account_changes.lock().clone();
+        
+        for (address, account) in accounts {
             self.state
                 .set_account(address.as_bytes(), &account.to_state())
                 .await?;
+            
+            if let Some(account_changes) = &account_changes {
+                let old = originals
+                    .remove(&address)
+                    .unwrap_or_else(|| Account::new(address, Amount::ZERO));
+                // The hook thread only stops with the ledger
+                let _ = account_changes.send(AccountChange {
+                    address,
+                    old,
+                    new: account,
+                });
+            }
         }
         
+        let mut total_burned = self.burned.write();
+        *total_burned = total_burned.saturating_add(burned);
+        let mut fees = self.collected_fees.write();
+        *fees = fees.saturating_add(collected_fees);
+        
         Ok(())
     }
     
-    /// Rollback pending changes
-    pub fn rollback(&self) {
-        self.pending_changes.write().clear();
+    /// Discard the changes made in `context`
+    pub fn rollback(&self, context: ProposalContext) {
+        debug!("Rolled back changes to {} accounts", context.accounts.len());
     }
     
     /// Get total supply
@@ -188,6 +331,34 @@ impl<S: StateStore + 'static> RelyoLedger<S> {
         *self.burned.read()
     }
     
+    /// Get validator fees collected so far
+    pub fn collected_fees(&self) -> Amount {
+        *self.collected_fees.read()
+    }
+    
+    /// Whether any account has been written to the state store
+    pub async fn has_accounts(&self) -> RainsonetResult<bool> {
+        Ok(!self.state.scan_prefix(ACCOUNT_PREFIX).await?.is_empty())
+    }
+    
+    /// Check that every token of the total supply is accounted for.
+    ///
+    /// Scans all committed accounts. Fees count only once committed too, so
+    /// the audit holds while proposals are still executing.
+    pub async fn audit_supply(&self) -> RainsonetResult<SupplyAudit> {
+        let mut balances: u128 = 0;
+        for entry in self.state.scan_prefix(ACCOUNT_PREFIX).await? {
+            balances = balances.saturating_add(AccountState::from_bytes(&entry.value)?.balance);
+        }
+        
+        Ok(SupplyAudit {
+            balances: Amount::new(balances),
+            burned: self.total_burned(),
+            collected_fees: self.collected_fees(),
+            total_supply: self.total_supply(),
+        })
+    }
+    
     /// Set initial balance (for genesis)
     pub async fn set_balance(
         &self,
@@ -200,7 +371,8 @@ impl<S: StateStore + 'static> RelyoLedger<S> {
             .await?;
         
         // Update total supply
-        *self.total_supply.write() = self.total_supply.read().saturating_add(balance);
+        let mut total_supply = self.total_supply.write();
+        *total_supply = total_supply.saturating_add(balance);
         
         info!("Set balance for {}: {}", address, balance);
         Ok(())
@@ -261,16 +433,52 @@ mod tests {
         .unwrap();
         
         let verified = VerifiedTransaction::new(tx).unwrap();
-        let changes = ledger.execute_transaction(&verified).await.unwrap();
+        let mut context = ProposalContext::new();
+        let changes = ledger.execute_transaction(&mut context, &verified).await.unwrap();
         
         assert!(!changes.is_empty());
         
-        // Check balances (from pending changes)
-        let sender_balance = ledger.get_balance(&sender.address()).await.unwrap();
-        let recipient_balance = ledger.get_balance(&recipient.address()).await.unwrap();
+        // Check balances (from the proposal context)
+        let sender_account = ledger.get_account_in(&context, &sender.address()).await;
+        let recipient_account = ledger.get_account_in(&context, &recipient.address()).await;
         
-        assert!(sender_balance.0 < Amount::from_relyo(1000).0);
-        assert_eq!(recipient_balance.0, Amount::from_relyo(100).0);
+        assert!(sender_account.unwrap().balance.0 < Amount::from_relyo(1000).0);
+        assert_eq!(recipient_account.unwrap().balance.0, Amount::from_relyo(100).0);
+    }
+    
+    #[tokio::test]
+    async fn test_reads_during_proposal_see_committed_state() {
+        let (ledger, sender, recipient) = setup_ledger().await;
+        let tx = crate::transaction::RelyoTransaction::new(
+            sender.address(),
+            recipient.address(),
+            Amount::from_relyo(100),
+            Amount::new(1_000_000_000_000_000),
+            Nonce::new(0),
+            &sender,
+        )
+        .unwrap();
+        let mut context = ProposalContext::new();
+        ledger
+            .execute_transaction(&mut context, &VerifiedTransaction::new(tx).unwrap())
+            .await
+            .unwrap();
+        
+        // A validation read racing the uncommitted proposal
+        let reader = ledger.clone();
+        let (from, to) = (sender.address(), recipient.address());
+        let (sender_balance, recipient_balance) = tokio::spawn(async move {
+            (reader.get_balance(&from).await.unwrap(), reader.get_balance(&to).await.unwrap())
+        })
+        .await
+        .unwrap();
+        assert_eq!(sender_balance, Amount::from_relyo(1000));
+        assert_eq!(recipient_balance, Amount::ZERO);
+        assert_eq!(ledger.get_nonce(&sender.address()).await.unwrap(), Nonce::new(0));
+        
+        ledger.commit(context).await.unwrap();
+        assert_eq!(ledger.get_balance(&to).await.unwrap(), Amount::from_relyo(100));
+        assert_eq!(ledger.get_nonce(&sender.address()).await.unwrap(), Nonce::new(1));
     }
     
     #[tokio::test]
@@ -288,7 +496,7 @@ mod tests {
         .unwrap();
         
         let verified = VerifiedTransaction::new(tx).unwrap();
-        let result = ledger.execute_transaction(&verified).await;
+        let result = ledger.execute_transaction(&mut ProposalContext::new(), &verified).await;
         
         assert!(matches!(result, Err(RainsonetError::InsufficientBalance { .. })));
     }
@@ -308,8 +516,134 @@ mod tests {
         .unwrap();
         
         let verified = VerifiedTransaction::new(tx).unwrap();
-        let result = ledger.execute_transaction(&verified).await;
+        let result = ledger.execute_transaction(&mut ProposalContext::new(), &verified).await;
         
         assert!(matches!(result, Err(RainsonetError::InvalidNonce { .. })));
     }
+    
+    #[tokio::test]
+    async fn test_audit_supply_reconciles() {
+        let (ledger, sender, recipient) = setup_ledger().await;
+        
+        let tx = crate::transaction::RelyoTransaction::new(
+            sender.address(),
+            recipient.address(),
+            Amount::from_relyo(100),
+            Amount::new(1_000_000_000_000_000),
+            Nonce::new(0),
+            &sender,
+        )
+        .unwrap();
+        let mut context = ProposalContext::new();
+        ledger
+            .execute_transaction(&mut context, &VerifiedTransaction::new(tx).unwrap())
+            .await
+            .unwrap();
+        
+        // Uncommitted changes, fees included, stay out of the audit
+        let audit = ledger.audit_supply().await.unwrap();
+        assert!(audit.reconciles(), "{:?}", audit);
+        assert_eq!(audit.burned, Amount::ZERO);
+        
+        ledger.commit(context).await.unwrap();
+        let audit = ledger.audit_supply().await.unwrap();
+        assert!(audit.reconciles(), "{:?}", audit);
+        assert!(audit.burned.0 > 0);
+        assert!(audit.collected_fees.0 > 0);
+        assert_eq!(audit.total_supply, Amount::from_relyo(1000));
+    }
+    
+    #[tokio::test]
+    async fn test_account_hook_sees_transfer() {
+        let (ledger, sender, recipient) = setup_ledger().await;
+        let (tx_changes, rx_changes) = mpsc::channel();
+        let tx_changes = Mutex::new(tx_changes);
+        ledger.on_account_change(Box::new(move |address, old, new| {
+            let _ = tx_changes.lock().send((*address, old.balance, new.balance));
+        }));
+        
+        let fee = Amount::new(1_000_000_000_000_000);
+        let tx = crate::transaction::RelyoTransaction::new(
+            sender.address(),
+            recipient.address(),
+            Amount::from_relyo(100),
+            fee,
+            Nonce::new(0),
+            &sender,
+        )
+        .unwrap();
+        let mut context = ProposalContext::new();
+        ledger
+            .execute_transaction(&mut context, &VerifiedTransaction::new(tx).unwrap())
+            .await
+            .unwrap();
+        
+        // Nothing is reported before the commit
+        assert!(rx_changes.try_recv().is_err());
+        ledger.commit(context).await.unwrap();
+        
+        let mut changes: HashMap<Address, (Amount, Amount)> = HashMap::new();
+        for _ in 0..2 {
+            let (address, old, new) = rx_changes
+                .recv_timeout(std::time::Duration::from_secs(5))
+                .unwrap();
+            changes.insert(address, (old, new));
+        }
+        
+        let spent = Amount::from_relyo(100).saturating_add(fee);
+        assert_eq!(
+            changes[&sender.address()],
+            (Amount::from_relyo(1000), Amount::from_relyo(1000).saturating_sub(spent))
+        );
+        assert_eq!(changes[&recipient.address()], (Amount::ZERO, Amount::from_relyo(100)));
+    }
+    
+    #[tokio::test]
+    async fn test_audit_supply_detects_discrepancy() {
+        let state = Arc::new(MemoryStateStore::new());
+        let ledger = RelyoLedger::new(state.clone(), RelyoConfig::default());
+        let holder = KeyPair::generate();
+        ledger.set_balance(&holder.address(), Amount::new(1_000)).await.unwrap();
+        
+        // Tokens minted behind the ledger's back
+        state
+            .set_account(KeyPair::generate().address().as_bytes(), &AccountState::new(7, 0))
+            .unwrap();
+        
+        let audit = ledger.audit_supply().await.unwrap();
+        assert!(!audit.reconciles());
+        assert_eq!(audit.discrepancy(), 7);
+    }
+    
+    /// Run a ledger on `state` through the `StateStore` bound alone
+    async fn exercise_store<S: StateStore + 'static>(state: Arc<S>) {
+        let ledger = RelyoLedger::new(state.clone(), RelyoConfig::default());
+        let holder = KeyPair::generate().address();
+        let empty_root = state.root().await.unwrap();
+        
+        ledger.set_balance(&holder, Amount::from_relyo(5)).await.unwrap();
+        assert_eq!(ledger.get_balance(&holder).await.unwrap(), Amount::from_relyo(5));
+        assert!(ledger.has_accounts().await.unwrap());
+        assert_ne!(state.root().await.unwrap(), empty_root);
+        assert_eq!(state.root().await.unwrap(), state.compute_root().await.unwrap());
+        
+        let version = state.version().await;
+        let change = StateChange::Set {
+            key: b"key".to_vec(),
+            value: b"value".to_vec(),
+        };
+        assert_eq!(state.apply_batch(vec![change]).await.unwrap(), version.next());
+        assert_eq!(state.version().await, version.next());
+        assert_eq!(state.get(b"key").await.unwrap(), Some(b"value".to_vec()));
+    }
+    
+    #[tokio::test]
+    async fn test_ledger_on_each_store() {
+        exercise_store(Arc::new(MemoryStateStore::new())).await;
+        
+        let path = std::env::temp_dir().join(format!("rainsonet-ledger-{}", std::process::id()));
+        let persistent = rainsonet_state::PersistentStateStore::open(&path).unwrap();
+        exercise_store(Arc::new(persistent)).await;
+        let _ = std::fs::remove_dir_all(path);
+    }
 }
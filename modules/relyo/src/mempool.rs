@@ -1,12 +1,16 @@
 //! Transaction mempool for pending transactions
 
 use parking_lot::RwLock;
-use rainsonet_core::{Address, Amount, Hash, Hashable, Nonce, RainsonetResult, Timestamp};
-use std::collections::{BTreeMap, HashMap, HashSet};
+use rainsonet_core::{
+    system_time, Address, Amount, Hash, LogLimiter, Nonce, RainsonetError,
+    RainsonetResult, SharedTimeSource, Timestamp, DEFAULT_MAX_BLOCK_TRANSACTIONS_PER_SENDER,
+};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
 use std::sync::Arc;
-use tracing::{debug, warn};
+use std::time::Duration;
+use tracing::debug;
 
-use crate::transaction::{RelyoTransaction, VerifiedTransaction};
+use crate::transaction::VerifiedTransaction;
 
 /// Mempool entry with metadata
 #[derive(Debug, Clone)]
@@ -18,17 +22,50 @@ pub struct MempoolEntry {
 
 impl MempoolEntry {
     pub fn new(tx: VerifiedTransaction) -> Self {
+        Self::new_at(tx, Timestamp::now())
+    }
+    
+    /// Create an entry received at the given time
+    pub fn new_at(tx: VerifiedTransaction, received_at: Timestamp) -> Self {
         // Priority based on fee (higher fee = higher priority)
         let priority = tx.tx.fee.0 as u64;
         
         Self {
             tx,
-            received_at: Timestamp::now(),
+            received_at,
             priority,
         }
     }
 }
 
+/// Fee tiers for fee suggestions
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FeeTier {
+    /// Minimum accepted fee
+    Low,
+    /// Median fee of pending transactions
+    Medium,
+    /// 90th percentile fee of pending transactions
+    High,
+}
+
+/// Aggregate mempool statistics
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct MempoolStats {
+    /// Number of pending transactions
+    pub size: usize,
+    /// Number of distinct senders
+    pub senders: usize,
+    pub min_fee: Amount,
+    /// Lower median for an even number of transactions
+    pub median_fee: Amount,
+    pub max_fee: Amount,
+    /// Time the oldest transaction has been pending, in milliseconds
+    pub oldest_age_ms: u64,
+    /// Total serialized size of pending transactions
+    pub total_bytes: u64,
+}
+
 /// Transaction mempool
 /// 
 /// Manages pending transactions before inclusion in state updates
@@ -37,12 +74,28 @@ pub struct Mempool {
     transactions: RwLock<HashMap<Hash, MempoolEntry>>,
     /// Transactions by sender address
     by_sender: RwLock<HashMap<Address, HashSet<Hash>>>,
+    /// Nonces held by each sender's pending transactions
+    reserved: RwLock<HashMap<Address, BTreeSet<u64>>>,
     /// Transactions ordered by priority
     by_priority: RwLock<BTreeMap<(u64, Hash), Hash>>,
     /// Maximum pool size
     max_size: usize,
     /// Maximum transactions per sender
     max_per_sender: usize,
+    /// Maximum transactions per sender in one block
+    max_per_sender_per_block: usize,
+    /// Nonce-gapped transactions held out of blocks, and since when
+    future: RwLock<HashMap<Hash, Timestamp>>,
+    /// How long a transaction may wait for its missing nonces
+    future_expiry: Duration,
+    /// How far ahead of its account a held transaction's nonce may be
+    max_nonce_gap: u64,
+    /// Let a higher-fee transaction take over its sender's pending nonce
+    replace_by_fee: bool,
+    /// Clock for receive times and expiry
+    clock: SharedTimeSource,
+    /// Keeps rejections under a flood from drowning the logs
+    log_limiter: LogLimiter,
 }
 
 impl Mempool {
@@ -50,16 +103,97 @@ impl Mempool {
         Self {
             transactions: RwLock::new(HashMap::new()),
             by_sender: RwLock::new(HashMap::new()),
+            reserved: RwLock::new(HashMap::new()),
             by_priority: RwLock::new(BTreeMap::new()),
             max_size,
             max_per_sender,
+            max_per_sender_per_block: DEFAULT_MAX_BLOCK_TRANSACTIONS_PER_SENDER,
+            future: RwLock::new(HashMap::new()),
+            future_expiry: DEFAULT_FUTURE_EXPIRY,
+            max_nonce_gap: DEFAULT_MAX_NONCE_GAP,
+            replace_by_fee: false,
+            clock: system_time(),
+            log_limiter: LogLimiter::default(),
         }
     }
     
+    /// Limit how many transactions of one sender [`Self::get_executable`]
+    /// returns, so a single sender cannot fill a block
+    pub fn with_max_per_sender_per_block(mut self, limit: usize) -> Self {
+        self.max_per_sender_per_block = limit.max(1);
+        self
+    }
+    
+    /// How long a nonce-gapped transaction waits before
+    /// [`Self::expire_future`] drops it
+    pub fn with_future_expiry(mut self, expiry: Duration) -> Self {
+        self.future_expiry = expiry;
+        self
+    }
+    
+    /// Refuse held transactions more than `gap` nonces ahead of their
+    /// sender's account
+    pub fn with_max_nonce_gap(mut self, gap: u64) -> Self {
+        self.max_nonce_gap = gap;
+        self
+    }
+    
+    /// Let a transaction replace a pending one of the same sender and nonce
+    /// when it pays a strictly higher fee. Off by default, in which case
+    /// both stay pending until one is included.
+    pub fn with_replace_by_fee(mut self, enabled: bool) -> Self {
+        self.replace_by_fee = enabled;
+        self
+    }
+    
+    /// Use the given time source instead of the system clock
+    pub fn with_time_source(mut self, clock: SharedTimeSource) -> Self {
+        self.clock = clock;
+        self
+    }
+    
     /// Add a transaction to the mempool
     pub fn add(&self, tx: VerifiedTransaction) -> RainsonetResult<bool> {
+        let sender = tx.tx.from;
+        let added = self.insert(tx)?;
+        if added {
+            self.promote(&sender, None);
+        }
+        Ok(added)
+    }
+    
+    /// Add a transaction whose nonce is ahead of its sender's account,
+    /// which is at `account_nonce`. It is held out of blocks until the
+    /// missing nonces arrive, and refused if more than the maximum nonce
+    /// gap is missing.
+    pub fn add_future(
+        &self,
+        tx: VerifiedTransaction,
+        account_nonce: Nonce,
+    ) -> RainsonetResult<bool> {
+        let gap = tx.tx.nonce.0.saturating_sub(account_nonce.0);
+        if gap > self.max_nonce_gap {
+            return Err(RainsonetError::InvalidTransaction(format!(
+                "Nonce {} is more than {} ahead of account nonce {}",
+                tx.tx.nonce.0, self.max_nonce_gap, account_nonce.0
+            )));
+        }
+        
         let tx_id = tx.tx_id;
         let sender = tx.tx.from;
+        let added = self.insert(tx)?;
+        if added {
+            self.park(&tx_id);
+            self.promote(&sender, None);
+        }
+        Ok(added)
+    }
+    
+    /// Insert a transaction into all indexes
+    fn insert(&self, tx: VerifiedTransaction) -> RainsonetResult<bool> {
+        let tx_id = tx.tx_id;
+        let sender = tx.tx.from;
+        let nonce = tx.tx.nonce;
         
         let mut transactions = self.transactions.write();
         
@@ -68,11 +202,18 @@ impl Mempool {
             return Ok(false);
         }
         
+        // Replacing under the same lock as the insert, so no other insert
+        // can take the nonce in between
+        if self.replace_by_fee && !self.replace_pending(&mut transactions, &tx) {
+            return Ok(false);
+        }
+        
         // Check pool size
         if transactions.len() >= self.max_size {
             // Try to evict lowest priority
-            if !self.evict_lowest_priority() {
-                warn!("Mempool full, transaction rejected");
+            if !self.evict_lowest_priority(&mut transactions) {
+                self.log_limiter
+                    .warn("mempool_full", || "Mempool full, transaction rejected".to_string());
                 return Ok(false);
             }
         }
@@ -82,13 +223,15 @@ impl Mempool {
             let by_sender = self.by_sender.read();
             if let Some(sender_txs) = by_sender.get(&sender) {
                 if sender_txs.len() >= self.max_per_sender {
-                    warn!("Too many transactions from sender {}", sender);
+                    self.log_limiter.warn("mempool_sender_limit", || {
+                        format!("Too many transactions from sender {}", sender.short())
+                    });
                     return Ok(false);
                 }
             }
         }
         
-        let entry = MempoolEntry::new(tx);
+        let entry = MempoolEntry::new_at(tx, self.clock.now());
         let priority = entry.priority;
         
         // Add to all indexes
@@ -97,22 +240,72 @@ impl Mempool {
         self.by_sender
             .write()
             .entry(sender)
-            .or_insert_with(HashSet::new)
+            .or_default()
             .insert(tx_id);
         
+        self.reserved
+            .write()
+            .entry(sender)
+            .or_default()
+            .insert(nonce.0);
+        
         self.by_priority
             .write()
             .insert((priority, tx_id), tx_id);
         
-        debug!("Added transaction {} to mempool (priority: {})", tx_id, priority);
+        debug!("Added transaction {} to mempool (priority: {})", tx_id.short(), priority);
         
         Ok(true)
     }
     
+    /// Evict `tx`'s sender's pending transactions at the same nonce if
+    /// `tx` outbids all of them. Returns false if one pays at least as much.
+    ///
+    /// Only the sender's own transactions are looked at: `tx` is verified,
+    /// so its signature is from `sender`, and another sender's transaction
+    /// at the same nonce is never evicted.
+    fn replace_pending(
+        &self,
+        transactions: &mut HashMap<Hash, MempoolEntry>,
+        tx: &VerifiedTransaction,
+    ) -> bool {
+        let sender = tx.tx.from;
+        let nonce = tx.tx.nonce;
+        let pending: Vec<Hash> = self
+            .by_sender
+            .read()
+            .get(&sender)
+            .map(|ids| {
+                ids.iter()
+                    .filter(|id| transactions.get(*id).is_some_and(|e| e.tx.tx.nonce == nonce))
+                    .copied()
+                    .collect()
+            })
+            .unwrap_or_default();
+        
+        if let Some(id) = pending.iter().find(|id| transactions[*id].tx.tx.fee >= tx.tx.fee) {
+            debug!("Replacement {} does not outbid {}", tx.tx_id.short(), id.short());
+            return false;
+        }
+        
+        for id in pending {
+            self.remove_locked(transactions, &id);
+            debug!("Transaction {} replaced by {}", id.short(), tx.tx_id.short());
+        }
+        true
+    }
+    
     /// Remove a transaction
     pub fn remove(&self, tx_id: &Hash) -> Option<MempoolEntry> {
-        let mut transactions = self.transactions.write();
-        
+        self.remove_locked(&mut self.transactions.write(), tx_id)
+    }
+    
+    /// [`Self::remove`] with the transactions lock already held
+    fn remove_locked(
+        &self,
+        transactions: &mut HashMap<Hash, MempoolEntry>,
+        tx_id: &Hash,
+    ) -> Option<MempoolEntry> {
         if let Some(entry) = transactions.remove(tx_id) {
             let sender = entry.tx.tx.from;
             
@@ -125,12 +318,32 @@ impl Mempool {
                 }
             }
             
+            // Release the nonce, unless another pending transaction of the
+            // sender still holds it
+            let nonce = entry.tx.tx.nonce.0;
+            let still_held = by_sender.get(&sender).is_some_and(|ids| {
+                ids.iter()
+                    .filter_map(|id| transactions.get(id))
+                    .any(|e| e.tx.tx.nonce.0 == nonce)
+            });
+            if !still_held {
+                let mut reserved = self.reserved.write();
+                if let Some(nonces) = reserved.get_mut(&sender) {
+                    nonces.remove(&nonce);
+                    if nonces.is_empty() {
+                        reserved.remove(&sender);
+                    }
+                }
+            }
+            
             // Remove from priority index
             self.by_priority
                 .write()
                 .remove(&(entry.priority, *tx_id));
             
-            debug!("Removed transaction {} from mempool", tx_id);
+            self.future.write().remove(tx_id);
+            
+            debug!("Removed transaction {} from mempool", tx_id.short());
             
             return Some(entry);
         }
@@ -153,8 +366,17 @@ impl Mempool {
     
     /// Get transactions for a sender
     pub fn get_by_sender(&self, sender: &Address) -> Vec<VerifiedTransaction> {
-        let by_sender = self.by_sender.read();
+        self.get_entries_by_sender(sender)
+            .into_iter()
+            .map(|e| e.tx)
+            .collect()
+    }
+    
+    /// Get mempool entries (with receive metadata) for a sender
+    pub fn get_entries_by_sender(&self, sender: &Address) -> Vec<MempoolEntry> {
+        // Same lock order as insert and remove: transactions, then indexes
         let transactions = self.transactions.read();
+        let by_sender = self.by_sender.read();
         
         by_sender
             .get(sender)
@@ -162,27 +384,44 @@ impl Mempool {
                 tx_ids
                     .iter()
                     .filter_map(|id| transactions.get(id))
-                    .map(|e| e.tx.clone())
+                    .cloned()
                     .collect()
             })
             .unwrap_or_default()
     }
     
-    /// Get next nonce for sender (current nonce + pending tx count)
-    pub fn get_pending_nonce(&self, sender: &Address, current_nonce: Nonce) -> Nonce {
-        let by_sender = self.by_sender.read();
-        let pending_count = by_sender
+    /// Highest nonce held by a pending transaction of `sender`
+    pub fn reserved_nonce(&self, sender: &Address) -> Option<Nonce> {
+        self.reserved
+            .read()
             .get(sender)
-            .map(|txs| txs.len() as u64)
-            .unwrap_or(0);
+            .and_then(|nonces| nonces.last())
+            .map(|nonce| Nonce::new(*nonce))
+    }
+    
+    /// Get the next nonce for sender: the first nonce from `current_nonce`
+    /// on that no pending transaction holds. Evicting a transaction frees
+    /// its nonce again, so the next send fills the gap instead of
+    /// reusing a nonce still pending behind it.
+    pub fn get_pending_nonce(&self, sender: &Address, current_nonce: Nonce) -> Nonce {
+        let reserved = self.reserved.read();
+        let mut next = current_nonce.0;
+        if let Some(nonces) = reserved.get(sender) {
+            for nonce in nonces.range(current_nonce.0..) {
+                if *nonce != next {
+                    break;
+                }
+                next += 1;
+            }
+        }
         
-        Nonce::new(current_nonce.0 + pending_count)
+        Nonce::new(next)
     }
     
     /// Get highest priority transactions for block
     pub fn get_highest_priority(&self, limit: usize) -> Vec<VerifiedTransaction> {
-        let by_priority = self.by_priority.read();
         let transactions = self.transactions.read();
+        let by_priority = self.by_priority.read();
         
         by_priority
             .iter()
@@ -193,29 +432,127 @@ impl Mempool {
             .collect()
     }
     
-    /// Get transactions ordered for execution (by sender nonce)
+    /// Hold a pending transaction out of blocks because nonces before it
+    /// are missing. Returns false if the transaction is not pending.
+    pub fn park(&self, tx_id: &Hash) -> bool {
+        if !self.contains(tx_id) {
+            return false;
+        }
+        self.future.write().entry(*tx_id).or_insert_with(|| self.clock.now());
+        true
+    }
+    
+    /// Whether a transaction is held back waiting for missing nonces
+    pub fn is_future(&self, tx_id: &Hash) -> bool {
+        self.future.read().contains_key(tx_id)
+    }
+    
+    /// Number of transactions waiting for missing nonces
+    pub fn future_count(&self) -> usize {
+        self.future.read().len()
+    }
+    
+    /// Senders with transactions waiting for missing nonces
+    pub fn future_senders(&self) -> Vec<Address> {
+        let ids: Vec<Hash> = self.future.read().keys().copied().collect();
+        let transactions = self.transactions.read();
+        let senders: HashSet<Address> = ids
+            .iter()
+            .filter_map(|id| transactions.get(id))
+            .map(|e| e.tx.tx.from)
+            .collect();
+        senders.into_iter().collect()
+    }
+    
+    /// Release `sender`'s held transactions once nothing is missing before
+    /// them: each must follow an executable pending nonce, or be the
+    /// account's `next_nonce` when known. Returns the released IDs.
+    pub fn promote(&self, sender: &Address, next_nonce: Option<Nonce>) -> Vec<Hash> {
+        let mut entries = self.get_entries_by_sender(sender);
+        entries.sort_by_key(|e| e.tx.tx.nonce);
+        
+        let mut future = self.future.write();
+        let mut ready: HashSet<u64> = entries
+            .iter()
+            .filter(|e| !future.contains_key(&e.tx.tx_id))
+            .map(|e| e.tx.tx.nonce.0)
+            .collect();
+        
+        let mut promoted = Vec::new();
+        for entry in &entries {
+            let nonce = entry.tx.tx.nonce;
+            let follows = nonce.0.checked_sub(1).is_some_and(|prev| ready.contains(&prev));
+            if future.contains_key(&entry.tx.tx_id) && (follows || Some(nonce) == next_nonce) {
+                future.remove(&entry.tx.tx_id);
+                ready.insert(nonce.0);
+                promoted.push(entry.tx.tx_id);
+            }
+        }
+        
+        if !promoted.is_empty() {
+            debug!("Promoted {} transactions from {}", promoted.len(), sender.short());
+        }
+        promoted
+    }
+    
+    /// Drop transactions that waited longer than the future expiry for
+    /// their missing nonces
+    pub fn expire_future(&self) -> Vec<Hash> {
+        let now = self.clock.now();
+        let expiry_ms = self.future_expiry.as_millis() as u64;
+        
+        let expired: Vec<Hash> = self
+            .future
+            .read()
+            .iter()
+            .filter(|(_, since)| now.as_millis().saturating_sub(since.as_millis()) > expiry_ms)
+            .map(|(id, _)| *id)
+            .collect();
+        
+        for id in &expired {
+            self.remove(id);
+        }
+        
+        expired
+    }
+    
+    /// Get transactions for a block, in [`canonical_order`].
+    ///
+    /// Each sender contributes a gapless nonce run starting at its lowest
+    /// pending nonce, capped at the per-block sender limit, and senders are
+    /// picked round-robin so high fees alone cannot crowd others out.
+    /// Transactions waiting for missing nonces are left out.
     pub fn get_executable(&self, limit: usize) -> Vec<VerifiedTransaction> {
         let transactions = self.transactions.read();
-        let by_sender = self.by_sender.read();
+        let future = self.future.read();
         
         let mut result = Vec::new();
         let mut collected_by_sender: HashMap<Address, Vec<&MempoolEntry>> = HashMap::new();
         
         // Group by sender
-        for entry in transactions.values() {
+        for entry in transactions.values().filter(|e| !future.contains_key(&e.tx.tx_id)) {
             collected_by_sender
                 .entry(entry.tx.tx.from)
-                .or_insert_with(Vec::new)
+                .or_default()
                 .push(entry);
         }
         
-        // Sort each sender's transactions by nonce
+        // Sort each sender's transactions by nonce, keeping a capped
+        // contiguous run: later nonces cannot execute before a missing one
         for txs in collected_by_sender.values_mut() {
             txs.sort_by_key(|e| e.tx.tx.nonce.0);
+            let run = txs
+                .windows(2)
+                .position(|pair| pair[1].tx.tx.nonce.0 != pair[0].tx.tx.nonce.0 + 1)
+                .map_or(txs.len(), |gap| gap + 1);
+            txs.truncate(run.min(self.max_per_sender_per_block));
         }
         
-        // Interleave transactions fairly, respecting nonce order
-        let mut round_robin: Vec<_> = collected_by_sender.values_mut().collect();
+        // Pick transactions fairly, visiting senders in address order so
+        // that a truncated block does not depend on hash map iteration
+        let mut round_robin: Vec<_> = collected_by_sender.into_iter().collect();
+        round_robin.sort_by(|a, b| a.0.as_bytes().cmp(b.0.as_bytes()));
+        let mut round_robin: Vec<_> = round_robin.into_iter().map(|(_, txs)| txs).collect();
         let mut i = 0;
         
         while result.len() < limit && !round_robin.is_empty() {
@@ -234,25 +571,23 @@ impl Mempool {
             }
         }
         
+        canonical_order(&mut result);
         result
     }
     
     /// Evict lowest priority transaction
-    fn evict_lowest_priority(&self) -> bool {
-        let mut by_priority = self.by_priority.write();
+    fn evict_lowest_priority(&self, transactions: &mut HashMap<Hash, MempoolEntry>) -> bool {
+        let lowest = self.by_priority.read().values().next().copied();
         
-        if let Some(((_, tx_id), _)) = by_priority.iter().next().map(|(k, v)| (*k, *v)) {
-            drop(by_priority);
-            self.remove(&tx_id);
-            return true;
+        match lowest {
+            Some(tx_id) => self.remove_locked(transactions, &tx_id).is_some(),
+            None => false,
         }
-        
-        false
     }
     
     /// Remove expired transactions
     pub fn remove_expired(&self, expiry_seconds: u64) -> Vec<Hash> {
-        let now = Timestamp::now();
+        let now = self.clock.now();
         let expiry_ms = expiry_seconds * 1000;
         
         let expired: Vec<Hash> = self
@@ -260,7 +595,7 @@ impl Mempool {
             .read()
             .iter()
             .filter(|(_, entry)| {
-                now.as_millis() - entry.received_at.as_millis() > expiry_ms
+                now.as_millis().saturating_sub(entry.received_at.as_millis()) > expiry_ms
             })
             .map(|(id, _)| *id)
             .collect();
@@ -276,7 +611,9 @@ impl Mempool {
     pub fn clear(&self) {
         self.transactions.write().clear();
         self.by_sender.write().clear();
+        self.reserved.write().clear();
         self.by_priority.write().clear();
+        self.future.write().clear();
     }
     
     /// Get pool size
@@ -288,14 +625,112 @@ impl Mempool {
     pub fn all_tx_ids(&self) -> Vec<Hash> {
         self.transactions.read().keys().copied().collect()
     }
+    
+    /// Suggest a fee for the given tier based on pending transaction fees.
+    ///
+    /// Never returns less than `min_fee`; with an empty pool every tier
+    /// suggests `min_fee`.
+    pub fn suggested_fee(&self, tier: FeeTier, min_fee: Amount) -> Amount {
+        let mut fees: Vec<u128> = self
+            .transactions
+            .read()
+            .values()
+            .map(|e| e.tx.tx.fee.0)
+            .collect();
+        
+        if fees.is_empty() || tier == FeeTier::Low {
+            return min_fee;
+        }
+        
+        fees.sort_unstable();
+        let percentile = match tier {
+            FeeTier::Low => 0,
+            FeeTier::Medium => 50,
+            FeeTier::High => 90,
+        };
+        let index = (fees.len() - 1) * percentile / 100;
+        
+        Amount::new(fees[index].max(min_fee.0))
+    }
+}
+
+impl Mempool {
+    /// Snapshot aggregate statistics in a single pass over the pool
+    pub fn stats(&self) -> MempoolStats {
+        let now = self.clock.now().as_millis();
+        // Same lock order as `add`
+        let transactions = self.transactions.read();
+        let senders = self.by_sender.read().len();
+        
+        let mut fees = Vec::with_capacity(transactions.len());
+        let mut min_fee = u128::MAX;
+        let mut max_fee = 0;
+        let mut oldest = now;
+        let mut total_bytes = 0;
+        
+        for entry in transactions.values() {
+            let fee = entry.tx.tx.fee.0;
+            fees.push(fee);
+            min_fee = min_fee.min(fee);
+            max_fee = max_fee.max(fee);
+            oldest = oldest.min(entry.received_at.as_millis());
+            total_bytes += bincode::serialized_size(&entry.tx.tx).unwrap_or(0);
+        }
+        drop(transactions);
+        
+        if fees.is_empty() {
+            return MempoolStats::default();
+        }
+        
+        // Linear-time selection rather than a full sort
+        let median_index = (fees.len() - 1) / 2;
+        let median = *fees.select_nth_unstable(median_index).1;
+        
+        MempoolStats {
+            size: fees.len(),
+            senders,
+            min_fee: Amount::new(min_fee),
+            median_fee: Amount::new(median),
+            max_fee: Amount::new(max_fee),
+            oldest_age_ms: now.saturating_sub(oldest),
+            total_bytes,
+        }
+    }
 }
 
+/// Default time a nonce-gapped transaction waits for its missing nonces
+pub const DEFAULT_FUTURE_EXPIRY: Duration = Duration::from_secs(600);
+
+/// Default limit on how far ahead of its account a held nonce may be
+pub const DEFAULT_MAX_NONCE_GAP: u64 = 64;
+
 impl Default for Mempool {
     fn default() -> Self {
         Self::new(10000, 100)
     }
 }
 
+/// Sort block transactions by sender address, then nonce.
+///
+/// Every validator executes a block in this order, so the same set of
+/// transactions always yields the same changes and changes hash.
+pub fn canonical_order(transactions: &mut [VerifiedTransaction]) {
+    transactions.sort_by(|a, b| {
+        a.tx.from
+            .as_bytes()
+            .cmp(b.tx.from.as_bytes())
+            .then(a.tx.nonce.cmp(&b.tx.nonce))
+    });
+}
+
+/// Whether `transactions` are in [`canonical_order`]
+pub fn is_canonical_order(transactions: &[VerifiedTransaction]) -> bool {
+    transactions.windows(2).all(|pair| {
+        let key = |tx: &VerifiedTransaction| (*tx.tx.from.as_bytes(), tx.tx.nonce);
+        key(&pair[0]) < key(&pair[1])
+    })
+}
+
 /// Shared mempool
 pub type SharedMempool = Arc<Mempool>;
 
@@ -307,6 +742,7 @@ pub fn create_mempool(max_size: usize, max_per_sender: usize) -> SharedMempool {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::transaction::RelyoTransaction;
     use rainsonet_crypto::keys::KeyPair;
     
     fn create_test_tx(sender: &KeyPair, recipient: &KeyPair, nonce: u64, fee: u128) -> VerifiedTransaction {
@@ -367,4 +803,279 @@ mod tests {
         assert!(mempool.add(create_test_tx(&sender, &recipient, 1, 1_000_000_000_000_000)).unwrap());
         assert!(!mempool.add(create_test_tx(&sender, &recipient, 2, 1_000_000_000_000_000)).unwrap());
     }
+    
+    #[test]
+    fn test_executable_caps_each_sender() {
+        let mempool = Mempool::new(1000, 200).with_max_per_sender_per_block(10);
+        let whale = KeyPair::generate();
+        let other = KeyPair::generate();
+        let recipient = KeyPair::generate();
+        
+        for nonce in 0..100 {
+            mempool
+                .add(create_test_tx(&whale, &recipient, nonce, 10_000_000_000_000_000))
+                .unwrap();
+        }
+        // Nonce 3 is missing, so only 0..=2 can execute
+        for nonce in [0, 1, 2, 4] {
+            mempool
+                .add(create_test_tx(&other, &recipient, nonce, 1_000_000_000_000_000))
+                .unwrap();
+        }
+        
+        let block = mempool.get_executable(50);
+        let from_whale: Vec<u64> = block
+            .iter()
+            .filter(|tx| tx.tx.from == whale.address())
+            .map(|tx| tx.tx.nonce.0)
+            .collect();
+        let from_other: Vec<u64> = block
+            .iter()
+            .filter(|tx| tx.tx.from == other.address())
+            .map(|tx| tx.tx.nonce.0)
+            .collect();
+        
+        assert_eq!(from_whale, (0..10).collect::<Vec<_>>());
+        assert_eq!(from_other, vec![0, 1, 2]);
+        assert_eq!(block.len(), 13);
+    }
+    
+    #[test]
+    fn test_gapped_transaction_waits_for_predecessor() {
+        let clock = Arc::new(rainsonet_core::MockTimeSource::new(Timestamp::from_millis(1_000_000)));
+        let mempool = Mempool::new(100, 10)
+            .with_future_expiry(Duration::from_secs(60))
+            .with_max_nonce_gap(8)
+            .with_time_source(clock.clone());
+        let sender = KeyPair::generate();
+        let recipient = KeyPair::generate();
+        
+        let gapped = create_test_tx(&sender, &recipient, 1, 1_000);
+        assert!(mempool.add_future(gapped.clone(), Nonce::new(0)).unwrap());
+        assert!(mempool.is_future(&gapped.tx_id));
+        assert!(mempool.get_executable(10).is_empty());
+        
+        // Retained past blocks until its predecessor arrives
+        clock.advance(Duration::from_secs(30));
+        assert!(mempool.expire_future().is_empty());
+        
+        mempool.add(create_test_tx(&sender, &recipient, 0, 1_000)).unwrap();
+        assert!(!mempool.is_future(&gapped.tx_id));
+        let nonces: Vec<u64> = mempool.get_executable(10).iter().map(|tx| tx.tx.nonce.0).collect();
+        assert_eq!(nonces, vec![0, 1]);
+        
+        // Also released once the account itself reaches the nonce
+        let later = create_test_tx(&sender, &recipient, 5, 1_000);
+        mempool.add_future(later.clone(), Nonce::new(2)).unwrap();
+        assert!(mempool.promote(&sender.address(), Some(Nonce::new(4))).is_empty());
+        assert_eq!(mempool.promote(&sender.address(), Some(Nonce::new(5))), vec![later.tx_id]);
+        
+        // Gaps that never fill expire
+        mempool.park(&later.tx_id);
+        clock.advance(Duration::from_secs(61));
+        assert_eq!(mempool.expire_future(), vec![later.tx_id]);
+        assert!(!mempool.contains(&later.tx_id));
+        assert_eq!(mempool.future_count(), 0);
+        
+        // Nonces too far ahead are not held at all
+        let distant = create_test_tx(&sender, &recipient, 11, 1_000);
+        assert!(mempool.add_future(distant.clone(), Nonce::new(2)).is_err());
+        assert!(!mempool.contains(&distant.tx_id));
+    }
+    
+    #[test]
+    fn test_sequential_sends_get_consecutive_nonces() {
+        let mempool = Mempool::new(100, 10);
+        let sender = KeyPair::generate();
+        let recipient = KeyPair::generate();
+        let current = Nonce::new(3);
+        
+        let mut nonces = Vec::new();
+        for _ in 0..4 {
+            let nonce = mempool.get_pending_nonce(&sender.address(), current);
+            assert!(mempool.add(create_test_tx(&sender, &recipient, nonce.0, 1_000)).unwrap());
+            nonces.push(nonce.0);
+        }
+        
+        assert_eq!(nonces, vec![3, 4, 5, 6]);
+        assert_eq!(mempool.reserved_nonce(&sender.address()), Some(Nonce::new(6)));
+    }
+    
+    #[test]
+    fn test_replace_by_fee_only_for_original_sender() {
+        let mempool = Mempool::new(100, 10).with_replace_by_fee(true);
+        let victim = KeyPair::generate();
+        let attacker = KeyPair::generate();
+        let recipient = KeyPair::generate();
+        
+        let original = create_test_tx(&victim, &recipient, 0, 1_000);
+        assert!(mempool.add(original.clone()).unwrap());
+        
+        // Another sender's higher-fee transaction at the same nonce is its own
+        let other = create_test_tx(&attacker, &recipient, 0, 5_000);
+        assert!(mempool.add(other.clone()).unwrap());
+        assert!(mempool.contains(&original.tx_id));
+        assert!(mempool.contains(&other.tx_id));
+        
+        // Claiming the victim's address under the attacker's key never verifies
+        let mut forged = create_test_tx(&attacker, &recipient, 0, 9_000).tx;
+        forged.from = victim.address();
+        assert!(VerifiedTransaction::new(forged).is_err());
+        assert!(mempool.contains(&original.tx_id));
+        
+        // Not outbidding leaves the original in place
+        assert!(!mempool.add(create_test_tx(&victim, &recipient, 0, 1_000)).unwrap());
+        assert!(mempool.contains(&original.tx_id));
+        
+        let replacement = create_test_tx(&victim, &recipient, 0, 2_000);
+        assert!(mempool.add(replacement.clone()).unwrap());
+        assert!(!mempool.contains(&original.tx_id));
+        assert_eq!(mempool.get_by_sender(&victim.address()).len(), 1);
+        assert_eq!(mempool.reserved_nonce(&victim.address()), Some(Nonce::new(0)));
+        assert_eq!(mempool.size(), 2);
+    }
+    
+    #[test]
+    fn test_replace_by_fee_in_full_pool() {
+        let mempool = Mempool::new(2, 10).with_replace_by_fee(true);
+        let sender = KeyPair::generate();
+        let other = KeyPair::generate();
+        let recipient = KeyPair::generate();
+        
+        let original = create_test_tx(&sender, &recipient, 0, 1_000);
+        let cheap = create_test_tx(&other, &recipient, 0, 500);
+        assert!(mempool.add(original.clone()).unwrap());
+        assert!(mempool.add(cheap.clone()).unwrap());
+        
+        // Replacing frees its own slot, so nothing else is evicted
+        let replacement = create_test_tx(&sender, &recipient, 0, 2_000);
+        assert!(mempool.add(replacement.clone()).unwrap());
+        assert!(!mempool.contains(&original.tx_id));
+        assert!(mempool.contains(&cheap.tx_id));
+        assert_eq!(mempool.size(), 2);
+        
+        // A new transaction in the full pool evicts the lowest priority one
+        let newcomer = create_test_tx(&other, &recipient, 1, 3_000);
+        assert!(mempool.add(newcomer.clone()).unwrap());
+        assert!(!mempool.contains(&cheap.tx_id));
+        assert!(mempool.contains(&replacement.tx_id));
+        assert_eq!(mempool.size(), 2);
+        
+        // Every index agrees with the pool
+        assert_eq!(mempool.get_entries_by_sender(&sender.address()).len(), 1);
+        assert_eq!(mempool.get_entries_by_sender(&other.address()).len(), 1);
+        assert_eq!(mempool.reserved_nonce(&sender.address()), Some(Nonce::new(0)));
+        assert_eq!(mempool.reserved_nonce(&other.address()), Some(Nonce::new(1)));
+        assert_eq!(mempool.get_highest_priority(10).len(), 2);
+    }
+    
+    #[test]
+    fn test_same_nonce_kept_without_replace_by_fee() {
+        let mempool = Mempool::new(100, 10);
+        let sender = KeyPair::generate();
+        let recipient = KeyPair::generate();
+        
+        let original = create_test_tx(&sender, &recipient, 0, 1_000);
+        mempool.add(original.clone()).unwrap();
+        mempool.add(create_test_tx(&sender, &recipient, 0, 2_000)).unwrap();
+        assert!(mempool.contains(&original.tx_id));
+        assert_eq!(mempool.size(), 2);
+    }
+    
+    #[test]
+    fn test_evicting_middle_transaction_frees_its_nonce() {
+        let mempool = Mempool::new(100, 10);
+        let sender = KeyPair::generate();
+        let recipient = KeyPair::generate();
+        let address = sender.address();
+        
+        let txs: Vec<_> = (0..3).map(|n| create_test_tx(&sender, &recipient, n, 1_000)).collect();
+        for tx in &txs {
+            mempool.add(tx.clone()).unwrap();
+        }
+        assert_eq!(mempool.get_pending_nonce(&address, Nonce::new(0)), Nonce::new(3));
+        
+        // Counting pending transactions would hand out 2 again
+        mempool.remove(&txs[1].tx_id);
+        assert_eq!(mempool.reserved_nonce(&address), Some(Nonce::new(2)));
+        assert_eq!(mempool.get_pending_nonce(&address, Nonce::new(0)), Nonce::new(1));
+        
+        // Refilling the gap continues after the highest reservation
+        mempool.add(create_test_tx(&sender, &recipient, 1, 2_000)).unwrap();
+        assert_eq!(mempool.get_pending_nonce(&address, Nonce::new(0)), Nonce::new(3));
+        
+        // Finalized transactions leave the pool and the account nonce moves on
+        for tx in mempool.get_by_sender(&address) {
+            mempool.remove(&tx.tx_id);
+        }
+        assert_eq!(mempool.reserved_nonce(&address), None);
+        assert_eq!(mempool.get_pending_nonce(&address, Nonce::new(3)), Nonce::new(3));
+    }
+    
+    #[test]
+    fn test_suggested_fee() {
+        let mempool = Mempool::new(100, 10);
+        let recipient = KeyPair::generate();
+        let min_fee = Amount::new(1_000);
+        
+        assert_eq!(mempool.suggested_fee(FeeTier::Medium, min_fee), min_fee);
+        
+        for (i, fee) in [500u128, 2_000, 3_000, 4_000, 10_000].iter().enumerate() {
+            let sender = KeyPair::generate();
+            mempool.add(create_test_tx(&sender, &recipient, i as u64, *fee)).unwrap();
+        }
+        
+        assert_eq!(mempool.suggested_fee(FeeTier::Low, min_fee), min_fee);
+        assert_eq!(mempool.suggested_fee(FeeTier::Medium, min_fee), Amount::new(3_000));
+        assert_eq!(mempool.suggested_fee(FeeTier::High, min_fee), Amount::new(4_000));
+        assert_eq!(mempool.suggested_fee(FeeTier::High, Amount::new(50_000)), Amount::new(50_000));
+    }
+    
+    #[test]
+    fn test_stats() {
+        let clock = Arc::new(rainsonet_core::MockTimeSource::new(Timestamp::from_millis(1_000_000)));
+        let mempool = Mempool::new(100, 10).with_time_source(clock.clone());
+        assert_eq!(mempool.stats(), MempoolStats::default());
+        
+        let recipient = KeyPair::generate();
+        let alice = KeyPair::generate();
+        let bob = KeyPair::generate();
+        let mut total_bytes = 0;
+        for (sender, nonce, fee) in [(&alice, 0, 4_000u128), (&alice, 1, 500), (&bob, 0, 10_000), (&bob, 1, 2_000)] {
+            let tx = create_test_tx(sender, &recipient, nonce, fee);
+            total_bytes += tx.tx.to_bytes().len() as u64;
+            mempool.add(tx).unwrap();
+            clock.advance(std::time::Duration::from_secs(1));
+        }
+        
+        let stats = mempool.stats();
+        assert_eq!(stats.size, 4);
+        assert_eq!(stats.senders, 2);
+        assert_eq!(stats.min_fee, Amount::new(500));
+        assert_eq!(stats.median_fee, Amount::new(2_000));
+        assert_eq!(stats.max_fee, Amount::new(10_000));
+        assert_eq!(stats.oldest_age_ms, 4_000);
+        assert_eq!(stats.total_bytes, total_bytes);
+    }
+    
+    #[test]
+    fn test_remove_expired_with_mock_clock() {
+        let clock = Arc::new(rainsonet_core::MockTimeSource::new(Timestamp::from_millis(1_000_000)));
+        let mempool = Mempool::new(100, 10).with_time_source(clock.clone());
+        let sender = KeyPair::generate();
+        let recipient = KeyPair::generate();
+        
+        let old = create_test_tx(&sender, &recipient, 0, 1_000);
+        mempool.add(old.clone()).unwrap();
+        clock.advance(std::time::Duration::from_secs(30));
+        let fresh = create_test_tx(&sender, &recipient, 1, 1_000);
+        mempool.add(fresh.clone()).unwrap();
+        
+        // Nothing has been pending for a full minute yet
+        assert!(mempool.remove_expired(60).is_empty());
+        
+        clock.advance(std::time::Duration::from_secs(31));
+        assert_eq!(mempool.remove_expired(60), vec![old.tx_id]);
+        assert!(mempool.contains(&fresh.tx_id));
+    }
 }
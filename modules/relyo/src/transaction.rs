@@ -18,6 +18,10 @@ use serde::{Deserialize, Serialize};
 /// - fee: transaction fee
 /// - nonce: sequential per account
 /// - signature: Ed25519 signature
+///
+/// The transaction ID is the BLAKE3 hash of the canonical encoding, see
+/// [`RelyoTransaction::to_canonical_bytes`]. `to_bytes` is bincode and only
+/// meant for storage and transport between nodes of the same version.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct RelyoTransaction {
     /// Sender address
@@ -38,6 +42,12 @@ pub struct RelyoTransaction {
     pub signature: Signature,
 }
 
+/// Domain prefix of the signing bytes and the canonical encoding
+const TX_PREFIX: &[u8] = b"RELYO_TX:";
+
+/// Length of [`RelyoTransaction::to_canonical_bytes`]
+pub const CANONICAL_TX_LEN: usize = TX_PREFIX.len() + 32 + 32 + 16 + 16 + 8 + 8 + 32 + 64;
+
 impl RelyoTransaction {
     /// Create and sign a new transaction
     pub fn new(
@@ -78,8 +88,8 @@ impl RelyoTransaction {
         })
     }
     
-    /// Compute bytes to sign
-    fn compute_signing_bytes(
+    /// Compute the canonical bytes a sender signs
+    pub fn compute_signing_bytes(
         from: &Address,
         to: &Address,
         amount: Amount,
@@ -87,8 +97,8 @@ impl RelyoTransaction {
         nonce: Nonce,
         timestamp: Timestamp,
     ) -> Vec<u8> {
-        let mut bytes = Vec::with_capacity(200);
-        bytes.extend_from_slice(b"RELYO_TX:");
+        let mut bytes = Vec::with_capacity(CANONICAL_TX_LEN);
+        bytes.extend_from_slice(TX_PREFIX);
         bytes.extend_from_slice(from.as_bytes());
         bytes.extend_from_slice(to.as_bytes());
         bytes.extend_from_slice(&amount.0.to_le_bytes());
@@ -127,9 +137,72 @@ impl RelyoTransaction {
     
     /// Check if transaction is expired
     pub fn is_expired(&self, expiry_seconds: u64) -> bool {
-        let now = Timestamp::now();
+        self.is_expired_at(Timestamp::now(), expiry_seconds)
+    }
+    
+    /// Check if transaction is expired as of `now`
+    pub fn is_expired_at(&self, now: Timestamp, expiry_seconds: u64) -> bool {
         let expiry_ms = expiry_seconds * 1000;
-        now.as_millis() - self.timestamp.as_millis() > expiry_ms
+        now.as_millis().saturating_sub(self.timestamp.as_millis()) > expiry_ms
+    }
+    
+    /// Canonical encoding the transaction ID is computed from.
+    ///
+    /// Fixed width, in this order, integers little-endian:
+    ///
+    /// | Field | Bytes |
+    /// |-------|-------|
+    /// | `"RELYO_TX:"` | 9 |
+    /// | from | 32 |
+    /// | to | 32 |
+    /// | amount (u128) | 16 |
+    /// | fee (u128) | 16 |
+    /// | nonce (u64) | 8 |
+    /// | timestamp, ms (u64) | 8 |
+    /// | public key | 32 |
+    /// | signature | 64 |
+    ///
+    /// Everything before the public key is exactly the signed message.
+    /// Changing this layout changes every transaction ID.
+    pub fn to_canonical_bytes(&self) -> Vec<u8> {
+        let mut bytes = self.signing_bytes();
+        bytes.extend_from_slice(self.public_key.as_bytes());
+        bytes.extend_from_slice(self.signature.as_bytes());
+        bytes
+    }
+    
+    /// Decode [`to_canonical_bytes`](Self::to_canonical_bytes) output
+    pub fn from_canonical_bytes(bytes: &[u8]) -> RainsonetResult<Self> {
+        if bytes.len() != CANONICAL_TX_LEN {
+            return Err(RainsonetError::DeserializationError(format!(
+                "Canonical transaction is {} bytes, expected {}",
+                bytes.len(),
+                CANONICAL_TX_LEN
+            )));
+        }
+        let body = bytes.strip_prefix(TX_PREFIX).ok_or_else(|| {
+            RainsonetError::DeserializationError("Missing transaction prefix".into())
+        })?;
+        
+        // Lengths are checked above, so the fixed-size conversions cannot fail
+        let (from, rest) = body.split_at(32);
+        let (to, rest) = rest.split_at(32);
+        let (amount, rest) = rest.split_at(16);
+        let (fee, rest) = rest.split_at(16);
+        let (nonce, rest) = rest.split_at(8);
+        let (timestamp, rest) = rest.split_at(8);
+        let (public_key, signature) = rest.split_at(32);
+        
+        Ok(Self {
+            from: Address::from_bytes(from.try_into().unwrap()),
+            to: Address::from_bytes(to.try_into().unwrap()),
+            amount: Amount::new(u128::from_le_bytes(amount.try_into().unwrap())),
+            fee: Amount::new(u128::from_le_bytes(fee.try_into().unwrap())),
+            nonce: Nonce::new(u64::from_le_bytes(nonce.try_into().unwrap())),
+            timestamp: Timestamp::from_millis(u64::from_le_bytes(timestamp.try_into().unwrap())),
+            public_key: PublicKey::from_bytes(public_key.try_into().unwrap()),
+            signature: Signature::from_bytes(signature.try_into().unwrap()),
+        })
     }
     
     /// Serialize to bytes
@@ -146,7 +219,7 @@ impl RelyoTransaction {
 
 impl Hashable for RelyoTransaction {
     fn hash(&self) -> Hash {
-        hash(&self.to_bytes())
+        hash(&self.to_canonical_bytes())
     }
 }
 
@@ -333,6 +406,50 @@ mod tests {
         assert!(tx.verify_signature().is_err());
     }
     
+    /// Fixed transaction for pinning the canonical encoding
+    fn golden_transaction() -> RelyoTransaction {
+        RelyoTransaction {
+            from: Address::from_bytes([1u8; 32]),
+            to: Address::from_bytes([2u8; 32]),
+            amount: Amount::new(1_000),
+            fee: Amount::new(10),
+            nonce: Nonce::new(7),
+            timestamp: Timestamp::from_millis(1_700_000_000_000),
+            public_key: PublicKey::from_bytes([3u8; 32]),
+            signature: Signature::from_bytes([4u8; 64]),
+        }
+    }
+    
+    #[test]
+    fn test_canonical_bytes_golden() {
+        let tx = golden_transaction();
+        
+        let mut expected = b"RELYO_TX:".to_vec();
+        expected.extend_from_slice(&[1u8; 32]);
+        expected.extend_from_slice(&[2u8; 32]);
+        expected.extend_from_slice(&[0xe8, 0x03]);
+        expected.extend_from_slice(&[0u8; 14]);
+        expected.extend_from_slice(&[0x0a]);
+        expected.extend_from_slice(&[0u8; 15]);
+        expected.extend_from_slice(&[0x07, 0, 0, 0, 0, 0, 0, 0]);
+        expected.extend_from_slice(&[0x00, 0x68, 0xe5, 0xcf, 0x8b, 0x01, 0, 0]);
+        expected.extend_from_slice(&[3u8; 32]);
+        expected.extend_from_slice(&[4u8; 64]);
+        
+        let bytes = tx.to_canonical_bytes();
+        assert_eq!(bytes, expected);
+        assert_eq!(bytes.len(), CANONICAL_TX_LEN);
+        assert!(bytes.starts_with(&tx.signing_bytes()));
+        assert_eq!(
+            tx.hash().to_hex(),
+            "f1dcaa2bb78db8d0339557a70dea5902d31b7ab357df5f7bc986381d472472cc"
+        );
+        
+        let decoded = RelyoTransaction::from_canonical_bytes(&bytes).unwrap();
+        assert_eq!(decoded.to_canonical_bytes(), bytes);
+        assert!(RelyoTransaction::from_canonical_bytes(&bytes[1..]).is_err());
+    }
+    
     #[test]
     fn test_total_cost() {
         let tx_amount = Amount::from_relyo(10);
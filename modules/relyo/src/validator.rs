@@ -1,28 +1,53 @@
 //! Transaction validation for RELYO
 
 use async_trait::async_trait;
+use futures::stream::{self, StreamExt};
 use rainsonet_core::{
-    Amount, RainsonetError, RainsonetResult, RelyoConfig, StateProvider, TransactionValidator,
+    system_time, Address, RainsonetError, RainsonetResult, RelyoConfig, SharedTimeSource,
+    StateProvider, TransactionValidator,
 };
+use rainsonet_crypto::keys::verify_address;
+use rainsonet_crypto::signing::verify_batch;
 use rainsonet_state::{AccountState, StateStore};
-use std::sync::Arc;
 
 use crate::transaction::RelyoTransaction;
 
 /// RELYO Transaction Validator
 pub struct RelyoTransactionValidator {
     config: RelyoConfig,
+    clock: SharedTimeSource,
 }
 
 impl RelyoTransactionValidator {
     pub fn new(config: RelyoConfig) -> Self {
-        Self { config }
+        Self {
+            config,
+            clock: system_time(),
+        }
+    }
+    
+    /// Use the given time source for expiry checks
+    pub fn with_time_source(mut self, clock: SharedTimeSource) -> Self {
+        self.clock = clock;
+        self
     }
     
     /// Validate transaction structure
     pub fn validate_structure(&self, tx: &RelyoTransaction) -> RainsonetResult<()> {
         // Self-transfer is allowed (for nonce advancement)
         
+        // No key derives the zero address, so nothing sent there is spendable
+        if tx.from == Address::ZERO {
+            return Err(RainsonetError::InvalidTransaction(
+                "Sender is the zero address".into(),
+            ));
+        }
+        if tx.to == Address::ZERO && !self.config.allow_zero_recipient {
+            return Err(RainsonetError::InvalidTransaction(
+                "Recipient is the zero address".into(),
+            ));
+        }
+        
         // Amount must not exceed max
         if tx.amount.0 > self.config.max_tx_amount {
             return Err(RainsonetError::InvalidTransaction(format!(
@@ -40,7 +65,7 @@ impl RelyoTransactionValidator {
         }
         
         // Check expiry
-        if tx.is_expired(self.config.tx_expiry_seconds) {
+        if tx.is_expired_at(self.clock.now(), self.config.tx_expiry_seconds) {
             return Err(RainsonetError::TransactionExpired);
         }
         
@@ -64,15 +89,8 @@ impl RelyoTransactionValidator {
             .await?
             .unwrap_or_default();
         
-        // Validate nonce
-        if tx.nonce.0 != sender_state.nonce {
-            return Err(RainsonetError::InvalidNonce {
-                expected: sender_state.nonce,
-                got: tx.nonce.0,
-            });
-        }
-        
-        // Validate balance
+        // Validate balance first, so an unaffordable transaction is
+        // rejected rather than held for a nonce gap
         let total_cost = tx.total_cost();
         if sender_state.balance < total_cost.0 {
             return Err(RainsonetError::InsufficientBalance {
@@ -81,6 +99,14 @@ impl RelyoTransactionValidator {
             });
         }
         
+        // Validate nonce
+        if tx.nonce.0 != sender_state.nonce {
+            return Err(RainsonetError::InvalidNonce {
+                expected: sender_state.nonce,
+                got: tx.nonce.0,
+            });
+        }
+        
         Ok(())
     }
     
@@ -114,14 +140,6 @@ impl TransactionValidator<RelyoTransaction> for RelyoTransactionValidator {
             None => AccountState::default(),
         };
         
-        // Validate nonce
-        if tx.nonce.0 != sender_state.nonce {
-            return Err(RainsonetError::InvalidNonce {
-                expected: sender_state.nonce,
-                got: tx.nonce.0,
-            });
-        }
-        
         // Validate balance
         let total_cost = tx.total_cost();
         if sender_state.balance < total_cost.0 {
@@ -131,58 +149,103 @@ impl TransactionValidator<RelyoTransaction> for RelyoTransactionValidator {
             });
         }
         
+        // Validate nonce
+        if tx.nonce.0 != sender_state.nonce {
+            return Err(RainsonetError::InvalidNonce {
+                expected: sender_state.nonce,
+                got: tx.nonce.0,
+            });
+        }
+        
         Ok(())
     }
 }
 
-/// Batch validator for multiple transactions
+/// Default number of transactions a [`BatchValidator`] validates at once
+pub const DEFAULT_BATCH_CONCURRENCY: usize = 16;
+
+/// Batch validator for multiple transactions.
+///
+/// Transactions are validated independently against the same state, up to
+/// a configurable number at once. Validation only reads state, so the
+/// caller must not mutate it while a batch is in flight; each transaction
+/// is checked against the state as it was, not as earlier ones would leave it.
 pub struct BatchValidator {
     validator: RelyoTransactionValidator,
+    concurrency: usize,
 }
 
 impl BatchValidator {
     pub fn new(config: RelyoConfig) -> Self {
         Self {
             validator: RelyoTransactionValidator::new(config),
+            concurrency: DEFAULT_BATCH_CONCURRENCY,
         }
     }
     
-    /// Validate a batch of transactions
+    /// Validate up to `concurrency` transactions at once; 1 validates
+    /// them one after another
+    pub fn with_concurrency(mut self, concurrency: usize) -> Self {
+        self.concurrency = concurrency.max(1);
+        self
+    }
+    
+    /// Check the signatures of a batch of transactions together, much
+    /// faster than one by one. A bad signature fails with
+    /// `InvalidBatchSignature` naming its transaction's index.
+    pub fn verify_signatures(&self, transactions: &[RelyoTransaction]) -> RainsonetResult<()> {
+        let mut signed = Vec::with_capacity(transactions.len());
+        for (index, tx) in transactions.iter().enumerate() {
+            if !verify_address(&tx.from, &tx.public_key) {
+                return Err(RainsonetError::InvalidBatchSignature { index });
+            }
+            let signing_bytes = RelyoTransaction::compute_signing_bytes(
+                &tx.from,
+                &tx.to,
+                tx.amount,
+                tx.fee,
+                tx.nonce,
+                tx.timestamp,
+            );
+            signed.push((tx.public_key, signing_bytes, tx.signature));
+        }
+        verify_batch(&signed)
+    }
+    
+    /// Validate a batch of transactions, returning results in input order
     pub async fn validate_batch<S: StateStore>(
         &self,
         transactions: &[RelyoTransaction],
         state: &S,
     ) -> Vec<RainsonetResult<()>> {
-        let mut results = Vec::with_capacity(transactions.len());
-        
-        for tx in transactions {
-            results.push(self.validator.validate(tx, state).await);
-        }
-        
-        results
+        stream::iter(transactions)
+            .map(|tx| self.validator.validate(tx, state))
+            .buffered(self.concurrency)
+            .collect()
+            .await
     }
     
-    /// Filter valid transactions from a batch
+    /// Filter valid transactions from a batch, keeping their order
     pub async fn filter_valid<S: StateStore>(
         &self,
         transactions: Vec<RelyoTransaction>,
         state: &S,
     ) -> Vec<RelyoTransaction> {
-        let mut valid = Vec::new();
-        
-        for tx in transactions {
-            if self.validator.validate(&tx, state).await.is_ok() {
-                valid.push(tx);
-            }
-        }
+        let results = self.validate_batch(&transactions, state).await;
         
-        valid
+        transactions
+            .into_iter()
+            .zip(results)
+            .filter(|(_, result)| result.is_ok())
+            .map(|(tx, _)| tx)
+            .collect()
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use rainsonet_core::Amount;
     use rainsonet_crypto::keys::KeyPair;
     use rainsonet_state::MemoryStateStore;
     
@@ -199,7 +262,6 @@ mod tests {
         let account = AccountState::new(Amount::from_relyo(1000).0, 0);
         state
             .set_account(sender.address().as_bytes(), &account)
-            .await
             .unwrap();
         
         let tx = RelyoTransaction::new(
@@ -215,6 +277,32 @@ mod tests {
         assert!(validator.validate(&tx, &state).await.is_ok());
     }
     
+    #[tokio::test]
+    async fn test_unaffordable_gapped_transaction_fails_on_balance() {
+        let validator = RelyoTransactionValidator::new(RelyoConfig::default());
+        let state = MemoryStateStore::new();
+        let sender = KeyPair::generate();
+        
+        let account = AccountState::new(Amount::from_relyo(1).0, 0);
+        state
+            .set_account(sender.address().as_bytes(), &account)
+            .unwrap();
+        
+        // A nonce gap must not get an unaffordable transaction held
+        let tx = RelyoTransaction::new(
+            sender.address(),
+            KeyPair::generate().address(),
+            Amount::from_relyo(10),
+            Amount::new(1_000_000_000_000_000),
+            rainsonet_core::Nonce::new(3),
+            &sender,
+        )
+        .unwrap();
+        
+        let result = validator.validate(&tx, &state).await;
+        assert!(matches!(result, Err(RainsonetError::InsufficientBalance { .. })));
+    }
+    
     #[tokio::test]
     async fn test_fee_too_low() {
         let config = RelyoConfig::default();
@@ -236,4 +324,144 @@ mod tests {
         let result = validator.validate_structure(&tx);
         assert!(matches!(result, Err(RainsonetError::FeeTooLow { .. })));
     }
+    
+    #[test]
+    fn test_zero_address_rejected_unless_allowed() {
+        let sender = KeyPair::generate();
+        let transfer = |to| {
+            RelyoTransaction::new(
+                sender.address(),
+                to,
+                Amount::from_relyo(10),
+                Amount::new(RelyoConfig::default().min_fee),
+                rainsonet_core::Nonce::new(0),
+                &sender,
+            )
+            .unwrap()
+        };
+        
+        let validator = RelyoTransactionValidator::new(RelyoConfig::default());
+        let result = validator.validate_structure(&transfer(Address::ZERO));
+        assert!(matches!(result, Err(RainsonetError::InvalidTransaction(_))));
+        assert!(validator.validate_structure(&transfer(KeyPair::generate().address())).is_ok());
+        
+        let burning = RelyoTransactionValidator::new(RelyoConfig {
+            allow_zero_recipient: true,
+            ..Default::default()
+        });
+        assert!(burning.validate_structure(&transfer(Address::ZERO)).is_ok());
+        
+        // Nothing is ever sent from the zero address
+        let mut forged = transfer(KeyPair::generate().address());
+        forged.from = Address::ZERO;
+        assert!(burning.validate_structure(&forged).is_err());
+    }
+    
+    #[test]
+    fn test_expiry_follows_time_source() {
+        let config = RelyoConfig::default();
+        let sender = KeyPair::generate();
+        let tx = RelyoTransaction::new(
+            sender.address(),
+            KeyPair::generate().address(),
+            Amount::from_relyo(10),
+            Amount::new(config.min_fee),
+            rainsonet_core::Nonce::new(0),
+            &sender,
+        )
+        .unwrap();
+        
+        let clock = std::sync::Arc::new(rainsonet_core::MockTimeSource::new(tx.timestamp));
+        let validator = RelyoTransactionValidator::new(config.clone()).with_time_source(clock.clone());
+        assert!(validator.validate_structure(&tx).is_ok());
+        
+        clock.advance(std::time::Duration::from_secs(config.tx_expiry_seconds + 1));
+        let result = validator.validate_structure(&tx);
+        assert!(matches!(result, Err(RainsonetError::TransactionExpired)));
+    }
+    
+    #[tokio::test]
+    async fn test_concurrent_batch_matches_sequential() {
+        let config = RelyoConfig::default();
+        let state = MemoryStateStore::new();
+        let recipient = KeyPair::generate().address();
+        
+        // Funded senders with the right nonce, interleaved with ones that
+        // have no balance or reuse an old nonce
+        let mut transactions = Vec::new();
+        for i in 0..40u64 {
+            let sender = KeyPair::generate();
+            if i % 3 != 0 {
+                let account = AccountState::new(Amount::from_relyo(1000).0, 1);
+                state.set_account(sender.address().as_bytes(), &account).unwrap();
+            }
+            let nonce = if i % 5 == 0 { 0 } else { 1 };
+            transactions.push(
+                RelyoTransaction::new(
+                    sender.address(),
+                    recipient,
+                    Amount::from_relyo(10),
+                    Amount::new(config.min_fee),
+                    rainsonet_core::Nonce::new(nonce),
+                    &sender,
+                )
+                .unwrap(),
+            );
+        }
+        
+        let sequential = BatchValidator::new(config.clone()).with_concurrency(1);
+        let concurrent = BatchValidator::new(config).with_concurrency(8);
+        
+        let expected: Vec<String> = sequential
+            .validate_batch(&transactions, &state)
+            .await
+            .iter()
+            .map(|r| format!("{:?}", r))
+            .collect();
+        let results: Vec<String> = concurrent
+            .validate_batch(&transactions, &state)
+            .await
+            .iter()
+            .map(|r| format!("{:?}", r))
+            .collect();
+        assert_eq!(results, expected);
+        assert!(expected.iter().any(|r| r == "Ok(())"));
+        assert!(expected.iter().any(|r| r != "Ok(())"));
+        
+        let valid = concurrent.filter_valid(transactions.clone(), &state).await;
+        let expected_valid: Vec<_> = transactions
+            .iter()
+            .zip(&expected)
+            .filter(|(_, r)| *r == "Ok(())")
+            .map(|(tx, _)| tx.from)
+            .collect();
+        assert_eq!(valid.iter().map(|tx| tx.from).collect::<Vec<_>>(), expected_valid);
+    }
+    
+    #[test]
+    fn test_verify_signatures_names_bad_transaction() {
+        let recipient = KeyPair::generate().address();
+        let mut transactions: Vec<RelyoTransaction> = (0..20)
+            .map(|_| {
+                let sender = KeyPair::generate();
+                RelyoTransaction::new(
+                    sender.address(),
+                    recipient,
+                    Amount::from_relyo(10),
+                    Amount::new(1),
+                    rainsonet_core::Nonce::new(0),
+                    &sender,
+                )
+                .unwrap()
+            })
+            .collect();
+        let validator = BatchValidator::new(RelyoConfig::default());
+        assert!(validator.verify_signatures(&transactions).is_ok());
+        
+        transactions[9].amount = Amount::from_relyo(11);
+        assert!(matches!(
+            validator.verify_signatures(&transactions),
+            Err(RainsonetError::InvalidBatchSignature { index: 9 })
+        ));
+    }
 }
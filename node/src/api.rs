@@ -1,20 +1,40 @@
 //! HTTP API for RAINSONET node
 
+use async_trait::async_trait;
 use axum::{
-    extract::{Path, State},
-    http::StatusCode,
-    response::IntoResponse,
+    body::Body,
+    error_handling::HandleErrorLayer,
+    extract::{DefaultBodyLimit, Path, Query, State},
+    http::{header, HeaderMap, StatusCode},
+    response::{
+        sse::{Event, KeepAlive, Sse},
+        IntoResponse, Response,
+    },
     routing::{get, post},
-    Json, Router,
+    Extension, Json, Router,
 };
-use rainsonet_core::{Address, Amount, Hash, Nonce};
-use rainsonet_relyo::{RelyoTransaction, VerifiedTransaction};
+use futures::stream::{self, Stream, StreamExt};
+use rainsonet_consensus::{ConsensusEvent, FinalityCertificate, ValidatorInfo};
+use rainsonet_core::{
+    amount_as_string, timestamp_as_rfc3339, Address, AddressConfig, Amount, Hash, NodeId, Nonce,
+    RainsonetError, Timestamp,
+};
+use rainsonet_crypto::hashing::{HashAlgorithm, MerkleProof};
+use rainsonet_p2p::{Multiaddr, NetworkCommand};
+use rainsonet_relyo::{FeeTier, RelyoTransaction, VerifiedTransaction};
+use rainsonet_state::AccountState;
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::convert::Infallible;
 use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::{broadcast, mpsc};
+use tower::timeout::{error::Elapsed, TimeoutLayer};
+use tower::{BoxError, ServiceBuilder};
 use tower_http::cors::{Any, CorsLayer};
-use tracing::{error, info};
+use tracing::{info, warn};
 
-use crate::runtime::NodeRuntime;
+use crate::runtime::{NodeRuntime, StateView};
 
 /// API state containing node runtime
 pub type ApiState = Arc<NodeRuntime>;
@@ -25,6 +45,9 @@ pub struct ApiResponse<T: Serialize> {
     pub success: bool,
     pub data: Option<T>,
     pub error: Option<String>,
+    /// Per-field validation failures of the request, if any
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub field_errors: Vec<FieldError>,
 }
 
 impl<T: Serialize> ApiResponse<T> {
@@ -33,6 +56,7 @@ impl<T: Serialize> ApiResponse<T> {
             success: true,
             data: Some(data),
             error: None,
+            field_errors: Vec::new(),
         }
     }
     
@@ -41,6 +65,18 @@ impl<T: Serialize> ApiResponse<T> {
             success: false,
             data: None,
             error: Some(error.to_string()),
+            field_errors: Vec::new(),
+        }
+    }
+    
+    /// Rejection of a request with invalid fields, summarized in `error`
+    pub fn invalid_fields(field_errors: Vec<FieldError>) -> Self {
+        let summary: Vec<String> = field_errors.iter().map(|e| e.to_string()).collect();
+        Self {
+            success: false,
+            data: None,
+            error: Some(format!("Invalid request: {}", summary.join("; "))),
+            field_errors,
         }
     }
 }
@@ -49,7 +85,8 @@ impl<T: Serialize> ApiResponse<T> {
 #[derive(Serialize)]
 pub struct BalanceResponse {
     pub address: String,
-    pub balance: String,
+    #[serde(with = "amount_as_string")]
+    pub balance: Amount,
     pub balance_relyo: String,
 }
 
@@ -57,11 +94,89 @@ pub struct BalanceResponse {
 #[derive(Serialize)]
 pub struct AccountResponse {
     pub address: String,
-    pub balance: String,
+    #[serde(with = "amount_as_string")]
+    pub balance: Amount,
     pub nonce: u64,
 }
 
-/// Transaction request
+/// Finalized account with its certificate and Merkle proof, as returned by
+/// `/account/:address/verified` for offline verification
+#[derive(Serialize)]
+pub struct VerifiedAccountResponse {
+    pub address: String,
+    pub account: AccountState,
+    pub certificate: FinalityCertificate,
+    pub proof: MerkleProof,
+    pub validators: Vec<ValidatorInfo>,
+}
+
+/// Pending transaction as listed by `/account/:address/pending`
+#[derive(Serialize)]
+pub struct PendingTransactionResponse {
+    pub tx_id: String,
+    pub from: String,
+    pub to: String,
+    #[serde(with = "amount_as_string")]
+    pub amount: Amount,
+    #[serde(with = "amount_as_string")]
+    pub fee: Amount,
+    pub nonce: u64,
+    pub received_at: u64,
+}
+
+/// Pending transactions for an account
+#[derive(Serialize)]
+pub struct PendingTransactionsResponse {
+    pub address: String,
+    pub next_nonce: u64,
+    pub transactions: Vec<PendingTransactionResponse>,
+}
+
+/// Validation failure of a single request field
+#[derive(Debug, Clone, Serialize, PartialEq, Eq)]
+pub struct FieldError {
+    pub field: String,
+    /// Value as provided. Requests carry no secrets, so it is echoed as is.
+    pub value: String,
+    pub reason: String,
+}
+
+impl FieldError {
+    fn new(field: &str, value: &str, reason: impl ToString) -> Self {
+        Self {
+            field: field.to_string(),
+            value: value.to_string(),
+            reason: reason.to_string(),
+        }
+    }
+}
+
+impl std::fmt::Display for FieldError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}", self.field, self.reason)
+    }
+}
+
+/// Transaction kind assumed when a request names none
+pub const DEFAULT_TRANSACTION_TYPE: &str = "transfer";
+
+fn default_transaction_type() -> String {
+    DEFAULT_TRANSACTION_TYPE.to_string()
+}
+
+/// Body of `/transaction`. The fields besides `transaction_type` are
+/// decoded by the parser registered for that type in
+/// [`TransactionTypeRegistry`].
+#[derive(Deserialize)]
+pub struct TypedTransactionRequest {
+    #[serde(default = "default_transaction_type")]
+    pub transaction_type: String,
+    #[serde(flatten)]
+    pub fields: serde_json::Map<String, serde_json::Value>,
+}
+
+/// Transaction request. Amounts are decimal strings, parsed along with the
+/// other fields so that every invalid field is reported at once.
 #[derive(Deserialize)]
 pub struct TransactionRequest {
     pub from: String,
@@ -71,6 +186,117 @@ pub struct TransactionRequest {
     pub nonce: u64,
     pub public_key: String,
     pub signature: String,
+    /// Signed timestamp in milliseconds, as returned by `/transaction/prepare`
+    #[serde(default)]
+    pub timestamp: Option<u64>,
+}
+
+/// Raw transaction request
+#[derive(Deserialize)]
+pub struct RawTransactionRequest {
+    /// Hex-encoded bincode bytes of a signed `RelyoTransaction`
+    pub tx: String,
+}
+
+/// Transaction prepare request
+#[derive(Deserialize)]
+pub struct PrepareTransactionRequest {
+    pub from: String,
+    pub to: String,
+    #[serde(with = "amount_as_string")]
+    pub amount: Amount,
+}
+
+/// Transaction prepare response
+#[derive(Serialize)]
+pub struct PrepareTransactionResponse {
+    pub from: String,
+    pub to: String,
+    #[serde(with = "amount_as_string")]
+    pub amount: Amount,
+    #[serde(with = "amount_as_string")]
+    pub fee: Amount,
+    pub next_nonce: u64,
+    pub timestamp: u64,
+    /// Hex-encoded bytes to sign with the sender's key
+    pub signing_bytes: String,
+}
+
+/// Fee estimate response
+#[derive(Serialize)]
+pub struct FeeEstimateResponse {
+    #[serde(with = "amount_as_string")]
+    pub low: Amount,
+    #[serde(with = "amount_as_string")]
+    pub medium: Amount,
+    #[serde(with = "amount_as_string")]
+    pub high: Amount,
+}
+
+/// Transaction policy response
+#[derive(Serialize)]
+pub struct PolicyResponse {
+    #[serde(with = "amount_as_string")]
+    pub min_fee: Amount,
+    #[serde(with = "amount_as_string")]
+    pub max_tx_amount: Amount,
+    pub fee_burn_percent: u8,
+    pub tx_expiry_seconds: u64,
+}
+
+/// Mempool statistics response
+#[derive(Serialize)]
+pub struct MempoolStatsResponse {
+    pub size: usize,
+    pub senders: usize,
+    #[serde(with = "amount_as_string")]
+    pub min_fee: Amount,
+    #[serde(with = "amount_as_string")]
+    pub median_fee: Amount,
+    #[serde(with = "amount_as_string")]
+    pub max_fee: Amount,
+    pub oldest_age_ms: u64,
+    pub total_bytes: u64,
+}
+
+/// Supply audit response
+#[derive(Serialize)]
+pub struct SupplyAuditResponse {
+    #[serde(with = "amount_as_string")]
+    pub balances: Amount,
+    #[serde(with = "amount_as_string")]
+    pub burned: Amount,
+    #[serde(with = "amount_as_string")]
+    pub collected_fees: Amount,
+    #[serde(with = "amount_as_string")]
+    pub total_supply: Amount,
+    pub reconciles: bool,
+}
+
+/// Query selecting the state view for account reads, e.g. `?view=optimistic`
+#[derive(Debug, Default, Deserialize)]
+pub struct ViewQuery {
+    #[serde(default)]
+    pub view: StateView,
+}
+
+/// Dial peer request
+#[derive(Deserialize)]
+pub struct DialPeerRequest {
+    pub multiaddr: String,
+}
+
+/// Disconnect peer request
+#[derive(Deserialize)]
+pub struct DisconnectPeerRequest {
+    pub node_id: String,
+}
+
+/// Peer command queued for the network service
+#[derive(Serialize)]
+pub struct PeerCommandResponse {
+    pub command: String,
+    pub target: String,
 }
 
 /// Transaction response
@@ -78,40 +304,241 @@ pub struct TransactionRequest {
 pub struct TransactionResponse {
     pub tx_id: String,
     pub status: String,
+    /// State version that included the transaction
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub version: Option<u64>,
+    /// Finalized versions on top of `version`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub confirmations: Option<u64>,
+    /// Signed transaction timestamp, as RFC 3339
+    #[serde(
+        with = "timestamp_as_rfc3339::option",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub timestamp: Option<Timestamp>,
+    /// Fee advice, only on submission
+    #[serde(flatten)]
+    pub advisory: Option<FeeAdvisory>,
+}
+
+/// Whether an accepted transaction's fee is likely to get it selected soon.
+///
+/// Purely informational: a fee at or above `min_fee` is accepted either way,
+/// but during congestion one below `suggested_fee` may wait a long time.
+#[derive(Serialize)]
+pub struct FeeAdvisory {
+    pub accepted: bool,
+    pub fee_adequate: bool,
+    #[serde(with = "amount_as_string")]
+    pub suggested_fee: Amount,
+    /// Whether the transaction can reach a validator, see
+    /// [`NodeRuntime::validators_reachable`]
+    pub will_forward: bool,
+    /// Why an accepted transaction may never finalize
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub warning: Option<String>,
 }
 
 /// Node status response
 #[derive(Serialize)]
 pub struct NodeStatusResponse {
     pub node_id: String,
+    /// Latest locally applied version, may still be reverted
+    pub optimistic_version: u64,
+    /// Latest certificate-backed version
+    pub finalized_version: u64,
+    /// Deprecated alias of `optimistic_version`, kept for existing clients
     pub state_version: u64,
+    /// Root of the optimistic state
     pub state_root: String,
+    /// Algorithm state roots and proofs are hashed with
+    pub hash_algorithm: HashAlgorithm,
     pub peer_count: usize,
     pub is_validator: bool,
+    /// Validator is holding back proposals until enough validator peers connect
+    pub waiting_for_peers: bool,
     pub mempool_size: usize,
+    /// Consensus stalls detected since the node started
+    pub consensus_stalls: u64,
+    /// Active validators that have not voted within the liveness window
+    pub inactive_validators: usize,
+}
+
+/// Active validator and its voting participation
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ValidatorResponse {
+    pub node_id: String,
+    #[serde(with = "amount_as_string")]
+    pub stake: Amount,
+    /// Highest proposal version the validator voted on
+    pub last_voted_version: Option<u64>,
+    /// No vote within the liveness window
+    pub inactive: bool,
+}
+
+/// Readiness response for `/health/ready`
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ReadinessResponse {
+    /// `ready` or `degraded`
+    pub status: String,
+    /// Consensus has not finalized a version within the stall threshold
+    /// while transactions are pending
+    pub consensus_stalled: bool,
+    /// Seconds since the finalized version last advanced
+    pub secs_since_finalized: u64,
+    pub consensus_stalls: u64,
+}
+
+/// Consensus event as streamed on `/consensus/events`
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ConsensusEventMessage {
+    #[serde(rename = "type")]
+    pub kind: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub proposal_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub voter: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub approve: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub version: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub state_root: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub node_id: Option<String>,
+}
+
+impl ConsensusEventMessage {
+    fn proposal(kind: &str, proposal_id: &Hash) -> Self {
+        Self {
+            kind: kind.to_string(),
+            proposal_id: Some(proposal_id.to_hex()),
+            voter: None,
+            approve: None,
+            version: None,
+            state_root: None,
+            node_id: None,
+        }
+    }
+    
+    fn validator(kind: &str, node_id: &NodeId) -> Self {
+        Self {
+            kind: kind.to_string(),
+            proposal_id: None,
+            voter: None,
+            approve: None,
+            version: None,
+            state_root: None,
+            node_id: Some(node_id.to_hex()),
+        }
+    }
 }
 
-/// Create API router
+impl From<&ConsensusEvent> for ConsensusEventMessage {
+    fn from(event: &ConsensusEvent) -> Self {
+        match event {
+            ConsensusEvent::ProposalCreated(id) => Self::proposal("proposal_created", id),
+            ConsensusEvent::ProposalReceived(id) => Self::proposal("proposal_received", id),
+            ConsensusEvent::VoteCast(id, voter, approve) => Self {
+                voter: Some(voter.to_hex()),
+                approve: Some(*approve),
+                ..Self::proposal("vote_cast", id)
+            },
+            ConsensusEvent::StateFinalized(version, root, certificate) => Self {
+                version: Some(version.0),
+                state_root: Some(root.to_hex()),
+                ..Self::proposal("state_finalized", &certificate.proposal_id)
+            },
+            ConsensusEvent::ProposalRejected(id) => Self::proposal("proposal_rejected", id),
+            ConsensusEvent::ProposalExpired(id) => Self::proposal("proposal_expired", id),
+            ConsensusEvent::LocalValidatorDeactivated(id) => {
+                Self::validator("validator_deactivated", id)
+            }
+            ConsensusEvent::LocalValidatorReactivated(id) => {
+                Self::validator("validator_reactivated", id)
+            }
+        }
+    }
+}
+
+/// Create API router accepting the built-in transaction types
 pub fn create_router(state: ApiState) -> Router {
+    create_router_with_transaction_types(state, TransactionTypeRegistry::default())
+}
+
+/// Create API router whose `/transaction` endpoint accepts the types in
+/// `transaction_types`
+pub fn create_router_with_transaction_types(
+    state: ApiState,
+    transaction_types: TransactionTypeRegistry,
+) -> Router {
     let cors = CorsLayer::new()
         .allow_origin(Any)
         .allow_methods(Any)
         .allow_headers(Any);
     
-    Router::new()
+    // Oversized bodies are rejected with 413 before they are buffered
+    let body_limit = DefaultBodyLimit::max(state.config().api.max_body_bytes);
+    let timeout = Duration::from_millis(state.config().api.request_timeout_ms);
+    
+    let router = Router::new()
         // Health
         .route("/health", get(health))
+        .route("/health/ready", get(ready))
         .route("/status", get(status))
+        .route("/validators", get(get_validators))
         // Accounts
         .route("/account/:address", get(get_account))
         .route("/balance/:address", get(get_balance))
+        .route("/account/:address/pending", get(get_pending_transactions))
+        .route("/account/:address/verified", get(get_verified_account))
         // Transactions
         .route("/transaction", post(submit_transaction))
+        .route("/transaction/raw", post(submit_raw_transaction))
+        .route("/transaction/prepare", post(prepare_transaction))
         .route("/transaction/:tx_id", get(get_transaction))
+        .route("/fee_estimate", get(fee_estimate))
+        .route("/policy", get(get_policy))
         // Mempool
         .route("/mempool", get(get_mempool))
+        .route("/mempool/stats", get(get_mempool_stats))
+        // Audits
+        .route("/audit/supply", get(audit_supply))
+        // Consensus
+        .route("/consensus/events", get(consensus_events))
+        // Admin
+        .route("/admin/peers/dial", post(dial_peer))
+        .route("/admin/peers/disconnect", post(disconnect_peer))
+        .route("/admin/export/accounts", get(export_accounts))
         .with_state(state)
-        .layer(cors)
+        .layer(Extension(Arc::new(transaction_types)))
+        .layer(body_limit);
+    
+    with_request_timeout(router, timeout).layer(cors)
+}
+
+/// Answer requests still running after `timeout` with 503, so one slow
+/// handler cannot hold a connection indefinitely
+fn with_request_timeout(router: Router, timeout: Duration) -> Router {
+    router.layer(
+        ServiceBuilder::new()
+            .layer(HandleErrorLayer::new(request_timed_out))
+            .layer(TimeoutLayer::new(timeout)),
+    )
+}
+
+async fn request_timed_out(error: BoxError) -> (StatusCode, Json<ApiResponse<()>>) {
+    if error.is::<Elapsed>() {
+        (
+            StatusCode::SERVICE_UNAVAILABLE,
+            Json(ApiResponse::err("Request timed out")),
+        )
+    } else {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ApiResponse::err(error)),
+        )
+    }
 }
 
 /// Health check
@@ -119,31 +546,80 @@ async fn health() -> impl IntoResponse {
     Json(serde_json::json!({"status": "ok"}))
 }
 
-/// Node status
+/// Readiness check, 503 while consensus is stalled
+async fn ready(State(runtime): State<ApiState>) -> impl IntoResponse {
+    let liveness = runtime.liveness();
+    let stalled = liveness.is_stalled();
+    let response = ReadinessResponse {
+        status: if stalled { "degraded" } else { "ready" }.to_string(),
+        consensus_stalled: stalled,
+        secs_since_finalized: liveness.since_last_finalized().as_secs(),
+        consensus_stalls: liveness.stall_count(),
+    };
+    let code = if stalled {
+        StatusCode::SERVICE_UNAVAILABLE
+    } else {
+        StatusCode::OK
+    };
+    
+    (code, Json(response))
+}
+
+/// Node status.
+///
+/// Only reads cached values: the state root is the one recorded when the
+/// state last changed, never recomputed per request.
 async fn status(State(runtime): State<ApiState>) -> impl IntoResponse {
     let status = NodeStatusResponse {
         node_id: runtime.node_id().map(|id| id.to_hex()).unwrap_or_default(),
+        optimistic_version: runtime.state_version().0,
+        finalized_version: runtime.finalized_version().0,
         state_version: runtime.state_version().0,
         state_root: runtime.state_root().to_hex(),
+        hash_algorithm: runtime.hash_algorithm(),
         peer_count: runtime.peer_count(),
         is_validator: runtime.is_validator(),
+        waiting_for_peers: runtime.is_waiting_for_peers(),
         mempool_size: runtime.mempool_size(),
+        consensus_stalls: runtime.liveness().stall_count(),
+        inactive_validators: runtime
+            .validator_liveness()
+            .iter()
+            .filter(|v| v.inactive)
+            .count(),
     };
     
     Json(ApiResponse::ok(status))
 }
 
+/// Active validators with their voting participation
+async fn get_validators(State(runtime): State<ApiState>) -> impl IntoResponse {
+    let validators: Vec<ValidatorResponse> = runtime
+        .validator_liveness()
+        .into_iter()
+        .map(|v| ValidatorResponse {
+            node_id: v.node_id.to_hex(),
+            stake: Amount::new(v.stake),
+            last_voted_version: v.last_voted_version.map(|version| version.0),
+            inactive: v.inactive,
+        })
+        .collect();
+    
+    Json(ApiResponse::ok(validators))
+}
+
 /// Get account
 async fn get_account(
     State(runtime): State<ApiState>,
     Path(address): Path<String>,
+    Query(query): Query<ViewQuery>,
 ) -> impl IntoResponse {
-    match Address::from_hex(&address) {
-        Ok(addr) => match runtime.get_account(&addr).await {
+    match runtime.config().address.parse(&address) {
+        Ok(addr) => match runtime.get_account_in(&addr, query.view).await {
             Ok(account) => {
                 let response = AccountResponse {
-                    address: addr.to_hex(),
-                    balance: account.balance.0.to_string(),
+                    address: runtime.config().address.render(&addr),
+                    balance: account.balance,
                     nonce: account.nonce.0,
                 };
                 (StatusCode::OK, Json(ApiResponse::ok(response)))
@@ -153,9 +629,9 @@ async fn get_account(
                 Json(ApiResponse::<AccountResponse>::err(e)),
             ),
         },
-        Err(_) => (
+        Err(e) => (
             StatusCode::BAD_REQUEST,
-            Json(ApiResponse::<AccountResponse>::err("Invalid address")),
+            Json(ApiResponse::<AccountResponse>::err(format!("Invalid address: {}", e))),
         ),
     }
 }
@@ -164,9 +640,10 @@ async fn get_account(
 async fn get_balance(
     State(runtime): State<ApiState>,
     Path(address): Path<String>,
+    Query(query): Query<ViewQuery>,
 ) -> impl IntoResponse {
-    match Address::from_hex(&address) {
-        Ok(addr) => match runtime.get_balance(&addr).await {
+    match runtime.config().address.parse(&address) {
+        Ok(addr) => match runtime.get_balance_in(&addr, query.view).await {
             Ok(balance) => {
                 let balance_relyo = format!(
                     "{}.{}",
@@ -174,8 +651,8 @@ async fn get_balance(
                     balance.0 % Amount::ONE_RELYO
                 );
                 let response = BalanceResponse {
-                    address: addr.to_hex(),
-                    balance: balance.0.to_string(),
+                    address: runtime.config().address.render(&addr),
+                    balance,
                     balance_relyo,
                 };
                 (StatusCode::OK, Json(ApiResponse::ok(response)))
@@ -185,9 +662,104 @@ async fn get_balance(
                 Json(ApiResponse::<BalanceResponse>::err(e)),
             ),
         },
-        Err(_) => (
+        Err(e) => (
             StatusCode::BAD_REQUEST,
-            Json(ApiResponse::<BalanceResponse>::err("Invalid address")),
+            Json(ApiResponse::<BalanceResponse>::err(format!("Invalid address: {}", e))),
+        ),
+    }
+}
+
+/// Get a finalized account with the certificate and proof to verify it
+async fn get_verified_account(
+    State(runtime): State<ApiState>,
+    Path(address): Path<String>,
+) -> impl IntoResponse {
+    let addr = match runtime.config().address.parse(&address) {
+        Ok(addr) => addr,
+        Err(e) => {
+            return (
+                StatusCode::BAD_REQUEST,
+                Json(ApiResponse::<VerifiedAccountResponse>::err(format!(
+                    "Invalid address: {}",
+                    e
+                ))),
+            )
+        }
+    };
+    
+    // Proving hashes the whole finalized state, so keep it off the async workers
+    let addresses = runtime.config().address.clone();
+    let proof = tokio::task::spawn_blocking(move || runtime.account_proof(&addr))
+        .await
+        .unwrap_or_else(|e| Err(RainsonetError::Internal(e.to_string())));
+    match proof {
+        Ok(bundle) => {
+            let response = VerifiedAccountResponse {
+                address: addresses.render(&bundle.address),
+                account: bundle.account,
+                certificate: bundle.certificate,
+                proof: bundle.proof,
+                validators: bundle.validators,
+            };
+            (StatusCode::OK, Json(ApiResponse::ok(response)))
+        }
+        Err(RainsonetError::StateNotFound) => (
+            StatusCode::NOT_FOUND,
+            Json(ApiResponse::<VerifiedAccountResponse>::err(
+                "No finalized state for account",
+            )),
+        ),
+        Err(e) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ApiResponse::<VerifiedAccountResponse>::err(e)),
+        ),
+    }
+}
+
+/// Get pending transactions sent from an account, in nonce order
+async fn get_pending_transactions(
+    State(runtime): State<ApiState>,
+    Path(address): Path<String>,
+) -> impl IntoResponse {
+    let addr = match runtime.config().address.parse(&address) {
+        Ok(addr) => addr,
+        Err(e) => {
+            return (
+                StatusCode::BAD_REQUEST,
+                Json(ApiResponse::<PendingTransactionsResponse>::err(format!(
+                    "Invalid address: {}",
+                    e
+                ))),
+            )
+        }
+    };
+    
+    match runtime.next_nonce(&addr).await {
+        Ok(next_nonce) => {
+            let addresses = &runtime.config().address;
+            let transactions = runtime
+                .pending_for(&addr)
+                .into_iter()
+                .map(|entry| PendingTransactionResponse {
+                    tx_id: entry.tx.tx_id.to_hex(),
+                    from: addresses.render(&entry.tx.tx.from),
+                    to: addresses.render(&entry.tx.tx.to),
+                    amount: entry.tx.tx.amount,
+                    fee: entry.tx.tx.fee,
+                    nonce: entry.tx.tx.nonce.0,
+                    received_at: entry.received_at.as_millis(),
+                })
+                .collect();
+            let response = PendingTransactionsResponse {
+                address: addresses.render(&addr),
+                next_nonce: next_nonce.0,
+                transactions,
+            };
+            (StatusCode::OK, Json(ApiResponse::ok(response)))
+        }
+        Err(e) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ApiResponse::<PendingTransactionsResponse>::err(e)),
         ),
     }
 }
@@ -195,28 +767,77 @@ async fn get_balance(
 /// Submit transaction
 async fn submit_transaction(
     State(runtime): State<ApiState>,
-    Json(req): Json<TransactionRequest>,
+    Extension(transaction_types): Extension<Arc<TransactionTypeRegistry>>,
+    Json(req): Json<TypedTransactionRequest>,
 ) -> impl IntoResponse {
-    // Parse transaction
-    let tx = match parse_transaction_request(&req) {
+    // Parse transaction with the parser for its type
+    let submission = match transaction_types.parse(&runtime.config().address, req) {
+        Ok(submission) => submission,
+        Err(errors) => {
+            return (
+                StatusCode::BAD_REQUEST,
+                Json(ApiResponse::<TransactionResponse>::invalid_fields(errors)),
+            )
+        }
+    };
+    
+    submission.submit(&runtime).await
+}
+
+/// Submit a transaction serialized with `RelyoTransaction::to_bytes`
+async fn submit_raw_transaction(
+    State(runtime): State<ApiState>,
+    Json(req): Json<RawTransactionRequest>,
+) -> impl IntoResponse {
+    let tx = match hex::decode(req.tx.trim())
+        .map_err(|e| e.to_string())
+        .and_then(|bytes| RelyoTransaction::from_bytes(&bytes).map_err(|e| e.to_string()))
+    {
         Ok(tx) => tx,
         Err(e) => {
             return (
                 StatusCode::BAD_REQUEST,
-                Json(ApiResponse::<TransactionResponse>::err(e)),
+                Json(ApiResponse::<TransactionResponse>::err(format!(
+                    "Invalid raw transaction: {}",
+                    e
+                ))),
             )
         }
     };
     
-    // Verify and submit
+    verify_and_submit(&runtime, tx).await
+}
+
+/// Verify a parsed transaction and hand it to the runtime
+async fn verify_and_submit(
+    runtime: &NodeRuntime,
+    tx: RelyoTransaction,
+) -> (StatusCode, Json<ApiResponse<TransactionResponse>>) {
     match VerifiedTransaction::new(tx) {
         Ok(verified) => {
             let tx_id = verified.tx_id.to_hex();
+            let fee = verified.tx.fee;
+            let timestamp = verified.tx.timestamp;
+            // Estimate before submitting, so the fee does not skew its own advice
+            let suggested_fee = runtime.suggested_fee(FeeTier::Medium);
             match runtime.submit_transaction(verified).await {
                 Ok(_) => {
+                    // A non-validator without validator peers keeps the
+                    // transaction pending until one connects
+                    let will_forward = runtime.validators_reachable();
                     let response = TransactionResponse {
                         tx_id,
                         status: "pending".to_string(),
+                        version: None,
+                        confirmations: None,
+                        timestamp: Some(timestamp),
+                        advisory: Some(FeeAdvisory {
+                            accepted: true,
+                            fee_adequate: fee >= suggested_fee,
+                            suggested_fee,
+                            will_forward,
+                            warning: (!will_forward).then(|| "no validators reachable".to_string()),
+                        }),
                     };
                     (StatusCode::ACCEPTED, Json(ApiResponse::ok(response)))
                 }
@@ -233,6 +854,67 @@ async fn submit_transaction(
     }
 }
 
+/// Prepare a transaction for client-side signing
+async fn prepare_transaction(
+    State(runtime): State<ApiState>,
+    Json(req): Json<PrepareTransactionRequest>,
+) -> impl IntoResponse {
+    let (from, to, amount) = match parse_prepare_request(&runtime.config().address, &req) {
+        Ok(parsed) => parsed,
+        Err(e) => {
+            return (
+                StatusCode::BAD_REQUEST,
+                Json(ApiResponse::<PrepareTransactionResponse>::err(e)),
+            )
+        }
+    };
+    
+    match runtime.prepare_transaction(from, to, amount).await {
+        Ok(prepared) => {
+            let addresses = &runtime.config().address;
+            let response = PrepareTransactionResponse {
+                from: addresses.render(&prepared.from),
+                to: addresses.render(&prepared.to),
+                amount: prepared.amount,
+                fee: prepared.fee,
+                next_nonce: prepared.nonce.0,
+                timestamp: prepared.timestamp.as_millis(),
+                signing_bytes: hex::encode(&prepared.signing_bytes),
+            };
+            (StatusCode::OK, Json(ApiResponse::ok(response)))
+        }
+        Err(e) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ApiResponse::<PrepareTransactionResponse>::err(e)),
+        ),
+    }
+}
+
+/// Get fee estimates
+async fn fee_estimate(State(runtime): State<ApiState>) -> impl IntoResponse {
+    let response = FeeEstimateResponse {
+        low: runtime.suggested_fee(FeeTier::Low),
+        medium: runtime.suggested_fee(FeeTier::Medium),
+        high: runtime.suggested_fee(FeeTier::High),
+    };
+    
+    Json(ApiResponse::ok(response))
+}
+
+/// Get the limits transactions are validated against
+async fn get_policy(State(runtime): State<ApiState>) -> impl IntoResponse {
+    // The ledger config comes from genesis, so every node reports the same
+    let config = runtime.ledger().config();
+    let response = PolicyResponse {
+        min_fee: Amount::new(config.min_fee),
+        max_tx_amount: Amount::new(config.max_tx_amount),
+        fee_burn_percent: config.fee_burn_percent,
+        tx_expiry_seconds: config.tx_expiry_seconds,
+    };
+    
+    Json(ApiResponse::ok(response))
+}
+
 /// Get transaction status
 async fn get_transaction(
     State(runtime): State<ApiState>,
@@ -240,21 +922,96 @@ async fn get_transaction(
 ) -> impl IntoResponse {
     match Hash::from_hex(&tx_id) {
         Ok(id) => {
-            let status = if runtime.is_transaction_pending(&id) {
-                "pending"
-            } else {
-                "unknown"
+            let receipt = runtime.transaction_receipt(&id);
+            let status = match receipt {
+                Some(r) if r.confirmations.is_some() => "finalized",
+                Some(_) => "included",
+                None if runtime.is_transaction_pending(&id) => "pending",
+                None => "unknown",
             };
             
             let response = TransactionResponse {
                 tx_id,
                 status: status.to_string(),
+                version: receipt.map(|r| r.version.0),
+                confirmations: receipt.and_then(|r| r.confirmations),
+                timestamp: receipt.and_then(|r| r.timestamp).or_else(|| {
+                    runtime.pending_transaction(&id).map(|tx| tx.tx.timestamp)
+                }),
+                advisory: None,
             };
             (StatusCode::OK, Json(ApiResponse::ok(response)))
         }
-        Err(_) => (
+        Err(e) => (
             StatusCode::BAD_REQUEST,
-            Json(ApiResponse::<TransactionResponse>::err("Invalid transaction ID")),
+            Json(ApiResponse::<TransactionResponse>::err(format!("Invalid transaction ID: {}", e))),
+        ),
+    }
+}
+
+/// Ask the network service to dial a peer
+async fn dial_peer(
+    State(runtime): State<ApiState>,
+    headers: HeaderMap,
+    Json(req): Json<DialPeerRequest>,
+) -> Response {
+    if let Err(rejection) = require_admin_token(&runtime, &headers) {
+        return rejection.into_response();
+    }
+    
+    match req.multiaddr.parse::<Multiaddr>() {
+        Ok(addr) => peer_command(&runtime, NetworkCommand::Dial(addr), "dial", req.multiaddr),
+        Err(e) => (
+            StatusCode::BAD_REQUEST,
+            Json(ApiResponse::<PeerCommandResponse>::err(format!("Invalid multiaddr: {}", e))),
+        ),
+    }
+    .into_response()
+}
+
+/// Ask the network service to disconnect a peer
+async fn disconnect_peer(
+    State(runtime): State<ApiState>,
+    headers: HeaderMap,
+    Json(req): Json<DisconnectPeerRequest>,
+) -> Response {
+    if let Err(rejection) = require_admin_token(&runtime, &headers) {
+        return rejection.into_response();
+    }
+    
+    match NodeId::from_hex(&req.node_id) {
+        Ok(node_id) => peer_command(
+            &runtime,
+            NetworkCommand::Disconnect(node_id),
+            "disconnect",
+            req.node_id,
+        ),
+        Err(e) => (
+            StatusCode::BAD_REQUEST,
+            Json(ApiResponse::<PeerCommandResponse>::err(format!("Invalid node ID: {}", e))),
+        ),
+    }
+    .into_response()
+}
+
+/// Queue a peer command, answering 202 once the network service has it
+fn peer_command(
+    runtime: &NodeRuntime,
+    command: NetworkCommand,
+    name: &str,
+    target: String,
+) -> (StatusCode, Json<ApiResponse<PeerCommandResponse>>) {
+    match runtime.send_network_command(command) {
+        Ok(()) => {
+            let response = PeerCommandResponse {
+                command: name.to_string(),
+                target,
+            };
+            (StatusCode::ACCEPTED, Json(ApiResponse::ok(response)))
+        }
+        Err(e) => (
+            StatusCode::SERVICE_UNAVAILABLE,
+            Json(ApiResponse::<PeerCommandResponse>::err(e)),
         ),
     }
 }
@@ -270,32 +1027,310 @@ async fn get_mempool(State(runtime): State<ApiState>) -> impl IntoResponse {
     Json(ApiResponse::ok(tx_ids))
 }
 
-fn parse_transaction_request(req: &TransactionRequest) -> Result<RelyoTransaction, String> {
-    let from = Address::from_hex(&req.from).map_err(|_| "Invalid from address")?;
-    let to = Address::from_hex(&req.to).map_err(|_| "Invalid to address")?;
-    let amount = Amount::new(
-        req.amount
-            .parse::<u128>()
-            .map_err(|_| "Invalid amount")?,
-    );
-    let fee = Amount::new(req.fee.parse::<u128>().map_err(|_| "Invalid fee")?);
-    let nonce = Nonce::new(req.nonce);
+/// Get aggregate mempool statistics
+async fn get_mempool_stats(State(runtime): State<ApiState>) -> impl IntoResponse {
+    let stats = runtime.mempool_stats();
+    
+    Json(ApiResponse::ok(MempoolStatsResponse {
+        size: stats.size,
+        senders: stats.senders,
+        min_fee: stats.min_fee,
+        median_fee: stats.median_fee,
+        max_fee: stats.max_fee,
+        oldest_age_ms: stats.oldest_age_ms,
+        total_bytes: stats.total_bytes,
+    }))
+}
+
+/// Audit that the total supply is fully accounted for
+async fn audit_supply(State(runtime): State<ApiState>) -> impl IntoResponse {
+    match runtime.audit_supply().await {
+        Ok(audit) => (
+            StatusCode::OK,
+            Json(ApiResponse::ok(SupplyAuditResponse {
+                reconciles: audit.reconciles(),
+                balances: audit.balances,
+                burned: audit.burned,
+                collected_fees: audit.collected_fees,
+                total_supply: audit.total_supply,
+            })),
+        ),
+        Err(e) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ApiResponse::<SupplyAuditResponse>::err(e.to_string())),
+        ),
+    }
+}
+
+/// Stream consensus events as server-sent events
+async fn consensus_events(
+    State(runtime): State<ApiState>,
+) -> Sse<impl Stream<Item = Result<Event, Infallible>>> {
+    let rx = runtime.subscribe_consensus_events();
+    
+    let events = stream::unfold(rx, |mut rx| async move {
+        loop {
+            match rx.recv().await {
+                Ok(event) => {
+                    let message = ConsensusEventMessage::from(&event);
+                    let event = Event::default()
+                        .event(message.kind.clone())
+                        .json_data(&message)
+                        .unwrap_or_else(|_| Event::default().comment("unencodable event"));
+                    return Some((Ok(event), rx));
+                }
+                // A slow client misses events rather than stalling the fan-out
+                Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                    warn!("Consensus event subscriber lagged, skipped {} events", skipped);
+                }
+                Err(broadcast::error::RecvError::Closed) => return None,
+            }
+        }
+    });
+    
+    Sse::new(events).keep_alive(KeepAlive::default())
+}
+
+/// Buffered export chunks before the exporting thread waits for the client
+const EXPORT_CHUNKS: usize = 16;
+
+/// Forwards an export to the response body, one chunk per write
+struct ChunkWriter(mpsc::Sender<std::io::Result<Vec<u8>>>);
+
+impl std::io::Write for ChunkWriter {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.blocking_send(Ok(buf.to_vec())).map_err(|_| {
+            std::io::Error::new(std::io::ErrorKind::BrokenPipe, "export client went away")
+        })?;
+        Ok(buf.len())
+    }
+    
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Compare tokens without an early exit, so response times do not reveal
+/// how much of a guess was right
+fn tokens_match(given: &str, expected: &str) -> bool {
+    given.len() == expected.len()
+        && given
+            .bytes()
+            .zip(expected.bytes())
+            .fold(0, |diff, (a, b)| diff | (a ^ b))
+            == 0
+}
+
+/// Check the `Authorization: Bearer` header against `api.admin_token`
+fn require_admin_token(
+    runtime: &NodeRuntime,
+    headers: &HeaderMap,
+) -> Result<(), (StatusCode, Json<ApiResponse<()>>)> {
+    let Some(expected) = &runtime.config().api.admin_token else {
+        return Err((
+            StatusCode::FORBIDDEN,
+            Json(ApiResponse::err("Admin token not configured")),
+        ));
+    };
+    let given = headers
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "));
+    match given {
+        Some(token) if tokens_match(token, expected) => Ok(()),
+        _ => Err((
+            StatusCode::UNAUTHORIZED,
+            Json(ApiResponse::err("Missing or invalid admin token")),
+        )),
+    }
+}
+
+/// Stream every account at the finalized version as JSON.
+///
+/// A failure before the first chunk is reported as a 500. Once streaming has
+/// started the status is sent, so a later failure aborts the body instead of
+/// ending it as if the export were complete.
+async fn export_accounts(State(runtime): State<ApiState>, headers: HeaderMap) -> Response {
+    if let Err(rejection) = require_admin_token(&runtime, &headers) {
+        return rejection.into_response();
+    }
     
-    let public_key_bytes = hex::decode(&req.public_key).map_err(|_| "Invalid public key")?;
-    if public_key_bytes.len() != 32 {
-        return Err("Public key must be 32 bytes".into());
+    let (tx, mut rx) = mpsc::channel(EXPORT_CHUNKS);
+    let failed = tx.clone();
+    tokio::task::spawn_blocking(move || match runtime.export_state_json(ChunkWriter(tx)) {
+        Ok(export) => info!(
+            "Exported {} accounts at version {}",
+            export.accounts, export.version
+        ),
+        Err(e) => {
+            warn!("Account export failed: {}", e);
+            let _ = failed.blocking_send(Err(std::io::Error::other(e.to_string())));
+        }
+    });
+    
+    let first = match rx.recv().await {
+        Some(Ok(chunk)) => chunk,
+        failed => {
+            let reason = failed.and_then(Result::err).map(|e| e.to_string()).unwrap_or_default();
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ApiResponse::<()>::err(format!("Account export failed: {}", reason))),
+            )
+                .into_response();
+        }
+    };
+    let rest = stream::unfold(rx, |mut rx| async move { rx.recv().await.map(|chunk| (chunk, rx)) });
+    let chunks = stream::once(async move { Ok(first) }).chain(rest);
+    ([(header::CONTENT_TYPE, "application/json")], Body::from_stream(chunks)).into_response()
+}
+
+fn parse_prepare_request(
+    addresses: &AddressConfig,
+    req: &PrepareTransactionRequest,
+) -> Result<(Address, Address, Amount), String> {
+    let from = addresses
+        .parse(&req.from)
+        .map_err(|e| format!("Invalid from address: {}", e))?;
+    let to = addresses
+        .parse(&req.to)
+        .map_err(|e| format!("Invalid to address: {}", e))?;
+    Ok((from, to, req.amount))
+}
+
+/// A parsed transaction of some registered type, which knows how to hand
+/// itself to the runtime. Modules implement it for their transaction kinds.
+#[async_trait]
+pub trait TransactionSubmission: Send {
+    /// Id the transaction will be submitted under
+    fn tx_id(&self) -> Hash;
+    
+    /// Verify the transaction and submit it to `runtime`
+    async fn submit(
+        self: Box<Self>,
+        runtime: &NodeRuntime,
+    ) -> (StatusCode, Json<ApiResponse<TransactionResponse>>);
+}
+
+#[async_trait]
+impl TransactionSubmission for RelyoTransaction {
+    fn tx_id(&self) -> Hash {
+        rainsonet_core::Hashable::hash(self)
+    }
+    
+    async fn submit(
+        self: Box<Self>,
+        runtime: &NodeRuntime,
+    ) -> (StatusCode, Json<ApiResponse<TransactionResponse>>) {
+        verify_and_submit(runtime, *self).await
+    }
+}
+
+/// Decodes and validates the fields of one transaction type, reading
+/// addresses in either format the node's [`AddressConfig`] accepts
+pub type TransactionParser = fn(
+    &AddressConfig,
+    serde_json::Map<String, serde_json::Value>,
+) -> Result<Box<dyn TransactionSubmission>, Vec<FieldError>>;
+
+/// Parsers for the transaction types `/transaction` accepts, keyed by
+/// `transaction_type`, so each kind brings its own decoding and checks
+#[derive(Clone)]
+pub struct TransactionTypeRegistry {
+    parsers: BTreeMap<String, TransactionParser>,
+}
+
+impl TransactionTypeRegistry {
+    /// Registry accepting no transaction types
+    pub fn empty() -> Self {
+        Self {
+            parsers: BTreeMap::new(),
+        }
+    }
+    
+    /// Accept `transaction_type`, replacing any parser registered for it
+    pub fn register(&mut self, transaction_type: &str, parser: TransactionParser) {
+        self.parsers.insert(transaction_type.to_string(), parser);
+    }
+    
+    /// Builder form of [`Self::register`]
+    pub fn with_type(mut self, transaction_type: &str, parser: TransactionParser) -> Self {
+        self.register(transaction_type, parser);
+        self
     }
-    let mut pk_arr = [0u8; 32];
-    pk_arr.copy_from_slice(&public_key_bytes);
-    let public_key = rainsonet_core::PublicKey::from_bytes(pk_arr);
     
-    let sig_bytes = hex::decode(&req.signature).map_err(|_| "Invalid signature")?;
-    if sig_bytes.len() != 64 {
-        return Err("Signature must be 64 bytes".into());
+    /// Registered transaction types, sorted
+    pub fn types(&self) -> impl Iterator<Item = &str> {
+        self.parsers.keys().map(String::as_str)
+    }
+    
+    /// Parse a request with the parser registered for its type
+    pub fn parse(
+        &self,
+        addresses: &AddressConfig,
+        req: TypedTransactionRequest,
+    ) -> Result<Box<dyn TransactionSubmission>, Vec<FieldError>> {
+        match self.parsers.get(&req.transaction_type) {
+            Some(parser) => parser(addresses, req.fields),
+            None => {
+                let expected = self.types().collect::<Vec<_>>().join(", ");
+                Err(vec![FieldError::new(
+                    "transaction_type",
+                    &req.transaction_type,
+                    format!("unknown transaction type, expected one of: {}", expected),
+                )])
+            }
+        }
+    }
+}
+
+/// Accepts transfers only
+impl Default for TransactionTypeRegistry {
+    fn default() -> Self {
+        Self::empty().with_type(DEFAULT_TRANSACTION_TYPE, parse_transfer)
     }
-    let mut sig_arr = [0u8; 64];
-    sig_arr.copy_from_slice(&sig_bytes);
-    let signature = rainsonet_core::Signature::from_bytes(sig_arr);
+}
+
+/// [`TransactionParser`] for transfers
+fn parse_transfer(
+    addresses: &AddressConfig,
+    fields: serde_json::Map<String, serde_json::Value>,
+) -> Result<Box<dyn TransactionSubmission>, Vec<FieldError>> {
+    let fields = serde_json::Value::Object(fields);
+    let req: TransactionRequest = serde_json::from_value(fields.clone())
+        .map_err(|e| vec![FieldError::new("request", &fields.to_string(), e)])?;
+    Ok(Box::new(parse_transaction_request(addresses, &req)?))
+}
+
+/// Parse a transaction request, collecting an error for every invalid field
+fn parse_transaction_request(
+    addresses: &AddressConfig,
+    req: &TransactionRequest,
+) -> Result<RelyoTransaction, Vec<FieldError>> {
+    let mut errors = Vec::new();
+    
+    let from = check_field(&mut errors, "from", &req.from, |s| addresses.parse(s));
+    let to = check_field(&mut errors, "to", &req.to, |s| addresses.parse(s));
+    let amount = check_field(&mut errors, "amount", &req.amount, parse_amount);
+    let fee = check_field(&mut errors, "fee", &req.fee, parse_amount);
+    let public_key = check_field(
+        &mut errors,
+        "public_key",
+        &req.public_key,
+        rainsonet_core::PublicKey::from_hex,
+    );
+    let signature = check_field(
+        &mut errors,
+        "signature",
+        &req.signature,
+        rainsonet_core::Signature::from_hex,
+    );
+    
+    let (Some(from), Some(to), Some(amount), Some(fee), Some(public_key), Some(signature)) =
+        (from, to, amount, fee, public_key, signature)
+    else {
+        return Err(errors);
+    };
+    let nonce = Nonce::new(req.nonce);
     
     Ok(RelyoTransaction {
         from,
@@ -303,15 +1338,46 @@ fn parse_transaction_request(req: &TransactionRequest) -> Result<RelyoTransactio
         amount,
         fee,
         nonce,
-        timestamp: rainsonet_core::Timestamp::now(),
         public_key,
         signature,
+        timestamp: req
+            .timestamp
+            .map(rainsonet_core::Timestamp::from_millis)
+            .unwrap_or_else(rainsonet_core::Timestamp::now),
     })
 }
 
-/// Start API server
-pub async fn start_api_server(runtime: Arc<NodeRuntime>, listen_addr: &str) -> anyhow::Result<()> {
-    let router = create_router(runtime);
+/// Parse one request field, recording a [`FieldError`] if it is invalid
+fn check_field<T, E: ToString>(
+    errors: &mut Vec<FieldError>,
+    field: &str,
+    value: &str,
+    parse: impl FnOnce(&str) -> Result<T, E>,
+) -> Option<T> {
+    parse(value)
+        .map_err(|reason| errors.push(FieldError::new(field, value, reason)))
+        .ok()
+}
+
+/// Parse a decimal amount, naming the reason it is invalid
+fn parse_amount(s: &str) -> Result<Amount, String> {
+    s.parse().map_err(|e: std::num::ParseIntError| {
+        match e.kind() {
+            std::num::IntErrorKind::PosOverflow => "overflow",
+            std::num::IntErrorKind::Empty => "empty",
+            _ => "not a non-negative integer",
+        }
+        .to_string()
+    })
+}
+
+/// Start API server accepting the types in `transaction_types`
+pub async fn start_api_server(
+    runtime: Arc<NodeRuntime>,
+    listen_addr: &str,
+    transaction_types: TransactionTypeRegistry,
+) -> anyhow::Result<()> {
+    let router = create_router_with_transaction_types(runtime, transaction_types);
     
     let listener = tokio::net::TcpListener::bind(listen_addr).await?;
     info!("API server listening on {}", listen_addr);
@@ -320,3 +1386,839 @@ pub async fn start_api_server(runtime: Arc<NodeRuntime>, listen_addr: &str) -> a
     
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::Request;
+    use rainsonet_core::{Hashable, NodeConfig};
+    use rainsonet_crypto::keys::KeyPair;
+    use rainsonet_relyo::GenesisConfig;
+    use tower::ServiceExt;
+    
+    fn create_test_runtime() -> Arc<NodeRuntime> {
+        create_test_runtime_with(NodeConfig::devnet())
+    }
+    
+    fn create_test_runtime_with(config: NodeConfig) -> Arc<NodeRuntime> {
+        Arc::new(NodeRuntime::new(config, KeyPair::generate(), GenesisConfig::devnet()))
+    }
+    
+    fn post_transaction(body: Vec<u8>) -> Request<Body> {
+        Request::builder()
+            .method("POST")
+            .uri("/transaction")
+            .header("content-type", "application/json")
+            .body(Body::from(body))
+            .unwrap()
+    }
+    
+    #[tokio::test]
+    async fn test_oversized_body_rejected() {
+        let runtime = create_test_runtime();
+        let limit = runtime.config().api.max_body_bytes;
+        
+        let mut body = b"{\"from\":\"".to_vec();
+        body.resize(limit + 1024, b'a');
+        
+        let response = create_router(runtime)
+            .oneshot(post_transaction(body))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::PAYLOAD_TOO_LARGE);
+    }
+    
+    #[tokio::test]
+    async fn test_normal_transaction_within_limit() {
+        let runtime = create_test_runtime();
+        let sender = KeyPair::generate();
+        let recipient = KeyPair::generate();
+        runtime
+            .initialize_genesis(GenesisConfig::devnet().add_allocation(&sender.address().to_hex(), 1000))
+            .await
+            .unwrap();
+        
+        let tx = RelyoTransaction::new(
+            sender.address(),
+            recipient.address(),
+            Amount::from_relyo(1),
+            Amount::ZERO,
+            Nonce::new(0),
+            &sender,
+        )
+        .unwrap();
+        let body = serde_json::json!({
+            "from": tx.from.to_hex(),
+            "to": tx.to.to_hex(),
+            "amount": tx.amount.0.to_string(),
+            "fee": tx.fee.0.to_string(),
+            "nonce": tx.nonce.0,
+            "public_key": tx.public_key.to_hex(),
+            "signature": tx.signature.to_hex(),
+            "timestamp": tx.timestamp.as_millis(),
+        });
+        
+        let response = create_router(runtime)
+            .oneshot(post_transaction(serde_json::to_vec(&body).unwrap()))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::ACCEPTED);
+    }
+    
+    fn post_raw_transaction(tx: &str) -> Request<Body> {
+        Request::builder()
+            .method("POST")
+            .uri("/transaction/raw")
+            .header("content-type", "application/json")
+            .body(Body::from(serde_json::to_vec(&serde_json::json!({ "tx": tx })).unwrap()))
+            .unwrap()
+    }
+    
+    #[tokio::test]
+    async fn test_raw_transaction_accepted() {
+        let runtime = create_test_runtime();
+        let sender = KeyPair::generate();
+        let recipient = KeyPair::generate();
+        runtime
+            .initialize_genesis(GenesisConfig::devnet().add_allocation(&sender.address().to_hex(), 1000))
+            .await
+            .unwrap();
+        
+        let tx = RelyoTransaction::new(
+            sender.address(),
+            recipient.address(),
+            Amount::from_relyo(1),
+            Amount::ZERO,
+            Nonce::new(0),
+            &sender,
+        )
+        .unwrap();
+        
+        let response = create_router(runtime)
+            .oneshot(post_raw_transaction(&hex::encode(tx.to_bytes())))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::ACCEPTED);
+        
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(json["data"]["tx_id"], tx.hash().to_hex());
+        assert_eq!(json["data"]["timestamp"], tx.timestamp.to_rfc3339());
+    }
+    
+    #[tokio::test]
+    async fn test_low_fee_accepted_with_advisory_during_congestion() {
+        // A non-validator keeps everything pending, like a congested pool
+        let runtime = create_test_runtime();
+        let senders: Vec<KeyPair> = (0..4).map(|_| KeyPair::generate()).collect();
+        let genesis = senders.iter().fold(GenesisConfig::devnet(), |genesis, sender| {
+            genesis.add_allocation(&sender.address().to_hex(), 1000)
+        });
+        runtime.initialize_genesis(genesis).await.unwrap();
+        
+        let submit = |sender: &KeyPair, fee: u128| {
+            let tx = RelyoTransaction::new(
+                sender.address(),
+                KeyPair::generate().address(),
+                Amount::from_relyo(1),
+                Amount::new(fee),
+                Nonce::new(0),
+                sender,
+            )
+            .unwrap();
+            let request = post_raw_transaction(&hex::encode(tx.to_bytes()));
+            let router = create_router(runtime.clone());
+            async move {
+                let response = router.oneshot(request).await.unwrap();
+                assert_eq!(response.status(), StatusCode::ACCEPTED);
+                let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+                serde_json::from_slice::<serde_json::Value>(&body).unwrap()["data"].clone()
+            }
+        };
+        
+        // Nothing pending yet: the minimum fee is enough
+        let first = submit(&senders[0], 5_000).await;
+        assert_eq!(first["accepted"], true);
+        assert_eq!(first["fee_adequate"], true);
+        submit(&senders[1], 5_000).await;
+        submit(&senders[2], 5_000).await;
+        
+        // The minimum fee is still valid, but below what the pool favours
+        let cheap = submit(&senders[3], 0).await;
+        assert_eq!(cheap["status"], "pending");
+        assert_eq!(cheap["accepted"], true);
+        assert_eq!(cheap["fee_adequate"], false);
+        assert_eq!(cheap["suggested_fee"], "5000");
+        assert_eq!(runtime.mempool_size(), 4);
+    }
+    
+    #[tokio::test]
+    async fn test_isolated_non_validator_warns_on_submit() {
+        let runtime = create_test_runtime();
+        let sender = KeyPair::generate();
+        runtime
+            .initialize_genesis(GenesisConfig::devnet().add_allocation(&sender.address().to_hex(), 1000))
+            .await
+            .unwrap();
+        
+        let submit = |nonce: u64| {
+            let tx = RelyoTransaction::new(
+                sender.address(),
+                KeyPair::generate().address(),
+                Amount::from_relyo(1),
+                Amount::ZERO,
+                Nonce::new(nonce),
+                &sender,
+            )
+            .unwrap();
+            let request = post_raw_transaction(&hex::encode(tx.to_bytes()));
+            let router = create_router(runtime.clone());
+            async move {
+                let response = router.oneshot(request).await.unwrap();
+                assert_eq!(response.status(), StatusCode::ACCEPTED);
+                let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+                serde_json::from_slice::<serde_json::Value>(&body).unwrap()["data"].clone()
+            }
+        };
+        
+        let isolated = submit(0).await;
+        assert_eq!(isolated["accepted"], true);
+        assert_eq!(isolated["will_forward"], false);
+        assert_eq!(isolated["warning"], "no validators reachable");
+        
+        // A connected non-validator peer does not help
+        let peers = runtime.peer_manager();
+        peers.add_peer(rainsonet_p2p::PeerInfo::new(KeyPair::generate().node_id(), false));
+        assert_eq!(submit(1).await["will_forward"], false);
+        
+        peers.add_peer(rainsonet_p2p::PeerInfo::new(KeyPair::generate().node_id(), true));
+        let connected = submit(2).await;
+        assert_eq!(connected["will_forward"], true);
+        assert!(connected.get("warning").is_none());
+    }
+    
+    #[tokio::test]
+    async fn test_undecodable_raw_transaction_rejected() {
+        let runtime = create_test_runtime();
+        
+        for tx in ["deadbeef", "not hex"] {
+            let response = create_router(runtime.clone())
+                .oneshot(post_raw_transaction(tx))
+                .await
+                .unwrap();
+            assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+            
+            let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+            let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+            assert!(json["error"].as_str().unwrap().starts_with("Invalid raw transaction"));
+        }
+    }
+    
+    #[tokio::test]
+    async fn test_slow_handler_cut_off_at_timeout() {
+        async fn slow() -> &'static str {
+            tokio::time::sleep(Duration::from_secs(60)).await;
+            "done"
+        }
+        let router = with_request_timeout(
+            Router::new().route("/slow", get(slow)),
+            Duration::from_millis(50),
+        );
+        
+        let request = Request::builder().uri("/slow").body(Body::empty()).unwrap();
+        let response = tokio::time::timeout(Duration::from_secs(5), router.oneshot(request))
+            .await
+            .expect("slow handler was not cut off")
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+    }
+    
+    #[tokio::test]
+    async fn test_status_serves_cached_root() {
+        let runtime = create_test_runtime();
+        runtime.initialize_genesis(GenesisConfig::devnet()).await.unwrap();
+        let cached = runtime.state_root();
+        
+        // Written behind the runtime's back, so a recomputed root would differ
+        runtime
+            .ledger()
+            .set_balance(&KeyPair::generate().address(), Amount::from_relyo(5))
+            .await
+            .unwrap();
+        
+        for _ in 0..2 {
+            let request = Request::builder().uri("/status").body(Body::empty()).unwrap();
+            let response = create_router(runtime.clone()).oneshot(request).await.unwrap();
+            let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+            let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+            assert_eq!(json["data"]["state_root"], cached.to_hex());
+            assert_eq!(json["data"]["state_version"], json["data"]["optimistic_version"]);
+        }
+    }
+    
+    #[tokio::test]
+    async fn test_balance_path_accepts_hex_and_bech32() {
+        let mut config = NodeConfig::devnet();
+        config.address.format = rainsonet_core::AddressFormat::Bech32;
+        let runtime = create_test_runtime_with(config);
+        let holder = KeyPair::generate().address();
+        runtime
+            .initialize_genesis(GenesisConfig::devnet().add_allocation(&holder.to_hex(), 1000))
+            .await
+            .unwrap();
+        
+        let bech32 = holder.to_bech32("rlyo");
+        for path in [holder.to_hex(), bech32.clone(), bech32.to_uppercase()] {
+            let request = Request::builder()
+                .uri(format!("/balance/{}", path))
+                .body(Body::empty())
+                .unwrap();
+            let response = create_router(runtime.clone()).oneshot(request).await.unwrap();
+            assert_eq!(response.status(), StatusCode::OK);
+            
+            let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+            let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+            assert_eq!(json["data"]["address"], bech32);
+            assert_eq!(json["data"]["balance"], Amount::from_relyo(1000).0.to_string());
+        }
+        
+        // A typo fails the checksum rather than reading another account
+        let mut typo = bech32.clone();
+        typo.replace_range(10..11, if &bech32[10..11] == "q" { "p" } else { "q" });
+        let request = Request::builder()
+            .uri(format!("/balance/{}", typo))
+            .body(Body::empty())
+            .unwrap();
+        let response = create_router(runtime).oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    }
+    
+    #[tokio::test]
+    async fn test_policy_reports_genesis_limits() {
+        let mut genesis = GenesisConfig::devnet();
+        genesis.relyo_config.max_tx_amount = Amount::from_relyo(500).0;
+        genesis.relyo_config.min_fee = 7;
+        let runtime = Arc::new(NodeRuntime::new(NodeConfig::devnet(), KeyPair::generate(), genesis));
+        
+        let request = Request::builder().uri("/policy").body(Body::empty()).unwrap();
+        let response = create_router(runtime).oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(json["data"]["max_tx_amount"], Amount::from_relyo(500).0.to_string());
+        assert_eq!(json["data"]["min_fee"], "7");
+    }
+    
+    #[tokio::test]
+    async fn test_validators_report_last_vote() {
+        let config = NodeConfig {
+            consensus: rainsonet_core::ConsensusConfig {
+                is_validator: true,
+                ..rainsonet_core::ConsensusConfig::devnet()
+            },
+            ..Default::default()
+        };
+        let keypair = KeyPair::generate();
+        let runtime = Arc::new(NodeRuntime::new(config, keypair.clone(), GenesisConfig::devnet()));
+        let sender = KeyPair::generate();
+        runtime
+            .initialize_genesis(GenesisConfig::devnet().add_allocation(&sender.address().to_hex(), 1000))
+            .await
+            .unwrap();
+        let tx = RelyoTransaction::new(
+            sender.address(),
+            KeyPair::generate().address(),
+            Amount::from_relyo(1),
+            Amount::ZERO,
+            Nonce::new(0),
+            &sender,
+        )
+        .unwrap();
+        runtime.submit_transaction(VerifiedTransaction::new(tx).unwrap()).await.unwrap();
+        
+        let request = Request::builder().uri("/validators").body(Body::empty()).unwrap();
+        let response = create_router(runtime).oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        let validators = json["data"].as_array().unwrap();
+        assert_eq!(validators.len(), 1);
+        assert_eq!(validators[0]["node_id"], keypair.node_id().to_hex());
+        assert_eq!(validators[0]["last_voted_version"], 1);
+        assert_eq!(validators[0]["inactive"], false);
+    }
+    
+    #[tokio::test]
+    async fn test_finalization_streams_state_finalized_event() {
+        use futures::StreamExt;
+        
+        let config = NodeConfig {
+            consensus: rainsonet_core::ConsensusConfig {
+                is_validator: true,
+                ..rainsonet_core::ConsensusConfig::devnet()
+            },
+            ..Default::default()
+        };
+        let runtime = create_test_runtime_with(config);
+        let sender = KeyPair::generate();
+        runtime
+            .initialize_genesis(GenesisConfig::devnet().add_allocation(&sender.address().to_hex(), 1000))
+            .await
+            .unwrap();
+        runtime.start_event_fanout();
+        
+        let request = Request::builder()
+            .uri("/consensus/events")
+            .body(Body::empty())
+            .unwrap();
+        let response = create_router(runtime.clone()).oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let mut body = response.into_body().into_data_stream();
+        
+        let tx = RelyoTransaction::new(
+            sender.address(),
+            KeyPair::generate().address(),
+            Amount::from_relyo(1),
+            Amount::ZERO,
+            Nonce::new(0),
+            &sender,
+        )
+        .unwrap();
+        runtime
+            .submit_transaction(VerifiedTransaction::new(tx).unwrap())
+            .await
+            .unwrap();
+        
+        let finalized = tokio::time::timeout(std::time::Duration::from_secs(5), async {
+            let mut buffer = String::new();
+            while let Some(chunk) = body.next().await {
+                buffer.push_str(&String::from_utf8_lossy(&chunk.unwrap()));
+                for frame in buffer.split("\n\n") {
+                    if !frame.contains("event: state_finalized") {
+                        continue;
+                    }
+                    let data = frame
+                        .lines()
+                        .find_map(|line| line.strip_prefix("data: "))
+                        .unwrap();
+                    return serde_json::from_str::<ConsensusEventMessage>(data).unwrap();
+                }
+            }
+            panic!("event stream ended before finalization");
+        })
+        .await
+        .expect("no state_finalized event received");
+        
+        assert_eq!(finalized.version, Some(1));
+        assert_eq!(finalized.state_root, Some(runtime.state_root().to_hex()));
+    }
+    
+    async fn pending_for(runtime: ApiState, address: &Address) -> serde_json::Value {
+        let request = Request::builder()
+            .uri(format!("/account/{}/pending", address.to_hex()))
+            .body(Body::empty())
+            .unwrap();
+        let response = create_router(runtime).oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let value: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        value["data"].clone()
+    }
+    
+    #[tokio::test]
+    async fn test_pending_endpoint_lists_until_finalized() {
+        let sender = KeyPair::generate();
+        let genesis = GenesisConfig::devnet().add_allocation(&sender.address().to_hex(), 1000);
+        let transfer = || {
+            let tx = RelyoTransaction::new(
+                sender.address(),
+                KeyPair::generate().address(),
+                Amount::from_relyo(1),
+                Amount::ZERO,
+                Nonce::new(0),
+                &sender,
+            )
+            .unwrap();
+            VerifiedTransaction::new(tx).unwrap()
+        };
+        
+        // A non-validator keeps the transaction in its mempool
+        let mut config = NodeConfig::devnet();
+        config.address.format = rainsonet_core::AddressFormat::Bech32;
+        let observer = create_test_runtime_with(config);
+        observer.initialize_genesis(genesis.clone()).await.unwrap();
+        let tx = transfer();
+        let tx_id = tx.tx_id;
+        observer.submit_transaction(tx).await.unwrap();
+        
+        let pending = pending_for(observer, &sender.address()).await;
+        let bech32 = sender.address().to_bech32("rlyo");
+        assert_eq!(pending["address"], bech32);
+        assert_eq!(pending["next_nonce"], 1);
+        assert_eq!(pending["transactions"][0]["tx_id"], tx_id.to_hex());
+        assert_eq!(pending["transactions"][0]["from"], bech32);
+        assert_eq!(pending["transactions"][0]["nonce"], 0);
+        assert_eq!(pending["transactions"][0]["amount"], Amount::from_relyo(1).0.to_string());
+        
+        // A single validator finalizes immediately, leaving nothing pending
+        let config = NodeConfig {
+            consensus: rainsonet_core::ConsensusConfig {
+                is_validator: true,
+                ..rainsonet_core::ConsensusConfig::devnet()
+            },
+            ..Default::default()
+        };
+        let validator = create_test_runtime_with(config);
+        validator.initialize_genesis(genesis).await.unwrap();
+        validator.submit_transaction(transfer()).await.unwrap();
+        
+        let pending = pending_for(validator, &sender.address()).await;
+        assert_eq!(pending["next_nonce"], 1);
+        assert_eq!(pending["transactions"], serde_json::json!([]));
+    }
+    
+    #[tokio::test]
+    async fn test_export_accounts_requires_admin_token() {
+        let mut config = NodeConfig::devnet();
+        config.api.admin_token = Some("secret".to_string());
+        let runtime = create_test_runtime_with(config);
+        let holder = KeyPair::generate().address();
+        runtime
+            .initialize_genesis(GenesisConfig::devnet().add_allocation(&holder.to_hex(), 1000))
+            .await
+            .unwrap();
+        
+        let export = |token: Option<&str>| {
+            let mut request = Request::builder().uri("/admin/export/accounts");
+            if let Some(token) = token {
+                request = request.header("authorization", format!("Bearer {}", token));
+            }
+            create_router(runtime.clone()).oneshot(request.body(Body::empty()).unwrap())
+        };
+        assert_eq!(export(None).await.unwrap().status(), StatusCode::UNAUTHORIZED);
+        assert_eq!(export(Some("secre")).await.unwrap().status(), StatusCode::UNAUTHORIZED);
+        
+        let response = export(Some("secret")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(json["state_root"], runtime.finalized_root().unwrap().to_hex());
+        let accounts = json["accounts"].as_array().unwrap();
+        let account = accounts.iter().find(|a| a["address"] == holder.to_hex()).unwrap();
+        assert_eq!(account["balance"], Amount::from_relyo(1000).0.to_string());
+        
+        // Without a configured token the endpoint stays closed
+        let config = NodeConfig::devnet();
+        let runtime = create_test_runtime_with(config);
+        let request = Request::builder()
+            .uri("/admin/export/accounts")
+            .header("authorization", "Bearer ")
+            .body(Body::empty())
+            .unwrap();
+        let response = create_router(runtime).oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    }
+    
+    #[tokio::test]
+    async fn test_peer_commands_require_admin_token() {
+        let mut config = NodeConfig::devnet();
+        config.api.admin_token = Some("secret".to_string());
+        let (commands, mut queued) = mpsc::channel(4);
+        let runtime = Arc::new(
+            NodeRuntime::new(config, KeyPair::generate(), GenesisConfig::devnet())
+                .with_network_commands(commands),
+        );
+        
+        let post = |uri: &str, body: serde_json::Value, token: Option<&str>| {
+            let mut request = Request::builder()
+                .method("POST")
+                .uri(uri)
+                .header("content-type", "application/json");
+            if let Some(token) = token {
+                request = request.header("authorization", format!("Bearer {}", token));
+            }
+            create_router(runtime.clone())
+                .oneshot(request.body(Body::from(body.to_string())).unwrap())
+        };
+        let dial = serde_json::json!({ "multiaddr": "/ip4/10.0.0.2/tcp/30333" });
+        let disconnect = serde_json::json!({ "node_id": KeyPair::generate().node_id().to_hex() });
+        
+        let endpoints = [("/admin/peers/dial", dial), ("/admin/peers/disconnect", disconnect)];
+        
+        for (uri, body) in &endpoints {
+            let response = post(uri, body.clone(), None).await.unwrap();
+            assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+            let response = post(uri, body.clone(), Some("wrong")).await.unwrap();
+            assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+        }
+        assert!(queued.try_recv().is_err());
+        
+        for (uri, body) in &endpoints {
+            let response = post(uri, body.clone(), Some("secret")).await.unwrap();
+            assert_eq!(response.status(), StatusCode::ACCEPTED);
+        }
+        assert!(matches!(queued.try_recv(), Ok(NetworkCommand::Dial(_))));
+        assert!(matches!(queued.try_recv(), Ok(NetworkCommand::Disconnect(_))));
+    }
+    
+    #[test]
+    fn test_parse_transaction_request_keeps_signed_timestamp() {
+        let sender = KeyPair::generate();
+        let recipient = KeyPair::generate();
+        let timestamp = rainsonet_core::Timestamp::from_millis(1_700_000_000_000);
+        let signing_bytes = RelyoTransaction::compute_signing_bytes(
+            &sender.address(),
+            &recipient.address(),
+            Amount::new(10),
+            Amount::new(1),
+            Nonce::new(0),
+            timestamp,
+        );
+        let signature = rainsonet_crypto::signing::sign(&sender, &signing_bytes);
+        
+        let req = TransactionRequest {
+            from: sender.address().to_hex(),
+            to: recipient.address().to_hex(),
+            amount: "10".to_string(),
+            fee: "1".to_string(),
+            nonce: 0,
+            public_key: sender.public_key().to_hex(),
+            signature: signature.to_hex(),
+            timestamp: Some(timestamp.as_millis()),
+        };
+        
+        let tx = parse_transaction_request(&AddressConfig::default(), &req).unwrap();
+        assert_eq!(tx.timestamp, timestamp);
+        assert!(tx.verify_signature().is_ok());
+    }
+    
+    #[test]
+    fn test_parse_transaction_request_accepts_prefixed_hex() {
+        let sender = KeyPair::generate();
+        let tx = RelyoTransaction::new(
+            sender.address(),
+            KeyPair::generate().address(),
+            Amount::new(10),
+            Amount::new(1),
+            Nonce::new(0),
+            &sender,
+        )
+        .unwrap();
+        
+        let mut req = TransactionRequest {
+            from: format!("0x{}", tx.from.to_hex()),
+            to: format!("0X{}", tx.to.to_hex()),
+            amount: tx.amount.0.to_string(),
+            fee: tx.fee.0.to_string(),
+            nonce: 0,
+            public_key: format!("0x{}", tx.public_key.to_hex()),
+            signature: format!("0x{}", tx.signature.to_hex()),
+            timestamp: Some(tx.timestamp.as_millis()),
+        };
+        let addresses = AddressConfig::default();
+        assert!(parse_transaction_request(&addresses, &req).unwrap().verify_signature().is_ok());
+        
+        // Addresses may also be given in bech32 under the configured prefix
+        req.from = tx.from.to_bech32(&addresses.hrp);
+        req.to = tx.to.to_bech32(&addresses.hrp).to_uppercase();
+        assert_eq!(parse_transaction_request(&addresses, &req).unwrap().hash(), tx.hash());
+        
+        req.signature.truncate(66);
+        let errors = parse_transaction_request(&addresses, &req).unwrap_err();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].field, "signature");
+        assert_eq!(errors[0].reason, "expected 128 hex characters, got 64");
+    }
+    
+    fn transfer_json(tx: &RelyoTransaction) -> serde_json::Value {
+        serde_json::json!({
+            "from": tx.from.to_hex(),
+            "to": tx.to.to_hex(),
+            "amount": tx.amount.0.to_string(),
+            "fee": tx.fee.0.to_string(),
+            "nonce": tx.nonce.0,
+            "public_key": tx.public_key.to_hex(),
+            "signature": tx.signature.to_hex(),
+            "timestamp": tx.timestamp.as_millis(),
+        })
+    }
+    
+    #[test]
+    fn test_transfer_parses_through_registry() {
+        let sender = KeyPair::generate();
+        let tx = RelyoTransaction::new(
+            sender.address(),
+            KeyPair::generate().address(),
+            Amount::new(10),
+            Amount::new(1),
+            Nonce::new(0),
+            &sender,
+        )
+        .unwrap();
+        let registry = TransactionTypeRegistry::default();
+        let addresses = AddressConfig::default();
+        assert_eq!(registry.types().collect::<Vec<_>>(), vec!["transfer"]);
+        
+        // Requests without a type are transfers
+        let mut json = transfer_json(&tx);
+        let req: TypedTransactionRequest = serde_json::from_value(json.clone()).unwrap();
+        assert_eq!(req.transaction_type, DEFAULT_TRANSACTION_TYPE);
+        assert_eq!(registry.parse(&addresses, req).unwrap().tx_id(), tx.hash());
+        
+        json["transaction_type"] = serde_json::json!("transfer");
+        let req = serde_json::from_value(json.clone()).unwrap();
+        assert_eq!(registry.parse(&addresses, req).unwrap().tx_id(), tx.hash());
+        
+        // A malformed request is echoed back in the error
+        json["nonce"] = serde_json::json!("seven");
+        let errors = registry
+            .parse(&addresses, serde_json::from_value(json).unwrap())
+            .err()
+            .expect("malformed request parsed");
+        assert_eq!(errors[0].field, "request");
+        assert!(errors[0].value.contains("\"nonce\":\"seven\""));
+        assert!(errors[0].reason.contains("invalid type"));
+    }
+    
+    /// Submission of a kind the node itself does not know
+    struct StakeSubmission(Hash);
+    
+    #[async_trait]
+    impl TransactionSubmission for StakeSubmission {
+        fn tx_id(&self) -> Hash {
+            self.0
+        }
+        
+        async fn submit(
+            self: Box<Self>,
+            _runtime: &NodeRuntime,
+        ) -> (StatusCode, Json<ApiResponse<TransactionResponse>>) {
+            let response = TransactionResponse {
+                tx_id: self.0.to_hex(),
+                status: "staked".to_string(),
+                version: None,
+                confirmations: None,
+                timestamp: None,
+                advisory: None,
+            };
+            (StatusCode::ACCEPTED, Json(ApiResponse::ok(response)))
+        }
+    }
+    
+    fn parse_stake(
+        _addresses: &AddressConfig,
+        fields: serde_json::Map<String, serde_json::Value>,
+    ) -> Result<Box<dyn TransactionSubmission>, Vec<FieldError>> {
+        let validator = fields.get("validator").and_then(|v| v.as_str()).unwrap_or_default();
+        let id = Hash::from_hex(validator)
+            .map_err(|e| vec![FieldError::new("validator", validator, e)])?;
+        Ok(Box::new(StakeSubmission(id)))
+    }
+    
+    #[tokio::test]
+    async fn test_registered_type_is_submitted_by_its_module() {
+        let runtime = create_test_runtime();
+        let registry = TransactionTypeRegistry::default().with_type("stake", parse_stake);
+        assert_eq!(registry.types().collect::<Vec<_>>(), vec!["stake", "transfer"]);
+        
+        let id = Hash::from_bytes([7u8; 32]);
+        let body = serde_json::json!({ "transaction_type": "stake", "validator": id.to_hex() });
+        let response = create_router_with_transaction_types(runtime, registry)
+            .oneshot(post_transaction(serde_json::to_vec(&body).unwrap()))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::ACCEPTED);
+        
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&bytes).unwrap();
+        assert_eq!(json["data"]["status"], "staked");
+        assert_eq!(json["data"]["tx_id"], id.to_hex());
+    }
+    
+    #[tokio::test]
+    async fn test_unknown_transaction_type_rejected() {
+        let runtime = create_test_runtime();
+        let sender = KeyPair::generate();
+        let tx = RelyoTransaction::new(
+            sender.address(),
+            KeyPair::generate().address(),
+            Amount::new(10),
+            Amount::ZERO,
+            Nonce::new(0),
+            &sender,
+        )
+        .unwrap();
+        let mut body = transfer_json(&tx);
+        body["transaction_type"] = serde_json::json!("stake");
+        
+        let response = create_router(runtime)
+            .oneshot(post_transaction(serde_json::to_vec(&body).unwrap()))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&bytes).unwrap();
+        let field_errors = json["field_errors"].as_array().unwrap();
+        assert_eq!(field_errors.len(), 1);
+        assert_eq!(field_errors[0]["field"], "transaction_type");
+        assert_eq!(field_errors[0]["value"], "stake");
+        assert_eq!(
+            field_errors[0]["reason"],
+            "unknown transaction type, expected one of: transfer"
+        );
+    }
+    
+    #[tokio::test]
+    async fn test_invalid_fields_are_all_reported() {
+        let runtime = create_test_runtime();
+        let sender = KeyPair::generate();
+        let overflow = format!("{}0", u128::MAX);
+        let body = serde_json::json!({
+            "from": "not-an-address",
+            "to": sender.address().to_hex(),
+            "amount": overflow,
+            "fee": "0",
+            "nonce": 0,
+            "public_key": sender.public_key().to_hex(),
+            "signature": "00".repeat(64),
+        });
+        
+        let response = create_router(runtime)
+            .oneshot(post_transaction(serde_json::to_vec(&body).unwrap()))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&bytes).unwrap();
+        let field_errors = json["field_errors"].as_array().unwrap();
+        assert_eq!(field_errors.len(), 2);
+        assert_eq!(field_errors[0]["field"], "from");
+        assert_eq!(field_errors[0]["value"], "not-an-address");
+        assert_eq!(field_errors[1]["field"], "amount");
+        assert_eq!(field_errors[1]["value"], serde_json::json!(overflow));
+        assert_eq!(field_errors[1]["reason"], "overflow");
+    }
+    
+    #[test]
+    fn test_amounts_are_json_strings() {
+        let json = serde_json::json!({
+            "from": "00",
+            "to": "00",
+            "amount": u128::MAX.to_string(),
+        });
+        let req: PrepareTransactionRequest = serde_json::from_value(json).unwrap();
+        assert_eq!(req.amount, Amount::MAX);
+        
+        let response = FeeEstimateResponse {
+            low: Amount::new(1),
+            medium: Amount::new(2),
+            high: Amount::MAX,
+        };
+        let value = serde_json::to_value(&response).unwrap();
+        assert_eq!(value["high"], serde_json::json!(u128::MAX.to_string()));
+    }
+}
@@ -0,0 +1,61 @@
+//! Tokio runtime construction from node configuration
+
+use rainsonet_core::RuntimeConfig;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use tokio::runtime::{Builder, Runtime};
+
+/// Build the multi-threaded tokio runtime the node runs on. Threads are
+/// named from `config.thread_name` followed by a sequence number.
+pub fn build_runtime(config: &RuntimeConfig) -> std::io::Result<Runtime> {
+    let mut builder = Builder::new_multi_thread();
+    let prefix = config.thread_name.clone();
+    let next_id = AtomicUsize::new(0);
+    
+    builder
+        .enable_all()
+        .thread_name_fn(move || format!("{}-{}", prefix, next_id.fetch_add(1, Ordering::Relaxed)))
+        .max_blocking_threads(config.max_blocking_threads.max(1));
+    
+    if config.worker_threads > 0 {
+        builder.worker_threads(config.worker_threads);
+    }
+    
+    builder.build()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_runtime_worker_threads() {
+        let config = RuntimeConfig {
+            worker_threads: 3,
+            ..Default::default()
+        };
+        
+        let runtime = build_runtime(&config).unwrap();
+        assert_eq!(runtime.metrics().num_workers(), 3);
+    }
+    
+    #[test]
+    fn test_runtime_thread_name() {
+        let config = RuntimeConfig {
+            worker_threads: 1,
+            thread_name: "rainsonet-test".to_string(),
+            ..Default::default()
+        };
+        
+        let runtime = build_runtime(&config).unwrap();
+        let name = runtime
+            .block_on(async {
+                tokio::task::spawn_blocking(|| std::thread::current().name().map(String::from))
+                    .await
+            })
+            .unwrap();
+        
+        let name = name.unwrap();
+        let suffix = name.strip_prefix("rainsonet-test-").unwrap();
+        assert!(suffix.parse::<usize>().is_ok());
+    }
+}
@@ -7,9 +7,19 @@
 //! - HTTP API
 
 mod api;
+mod executor;
+mod logging;
 mod node;
 mod runtime;
+mod shutdown;
+mod sync;
+mod watchdog;
 
 pub use api::*;
+pub use executor::*;
+pub use logging::*;
 pub use node::*;
 pub use runtime::*;
+pub use shutdown::*;
+pub use sync::*;
+pub use watchdog::*;
@@ -1,13 +1,14 @@
 //! RAINSONET Node Binary
 
 use clap::{Parser, Subcommand};
-use rainsonet_core::NodeConfig;
+use rainsonet_core::{Address, NodeConfig};
+use rainsonet_crypto::hashing::{set_default_hash_algorithm, HashAlgorithm};
 use rainsonet_crypto::keys::KeyPair;
-use rainsonet_node::{NodeBuilder, RainsonetNode};
-use rainsonet_relyo::GenesisConfig;
+use rainsonet_node::{build_runtime, build_subscriber, export_accounts_json, NodeBuilder};
+use rainsonet_relyo::{GenesisConfig, DEFAULT_VALIDATOR_STAKE};
+use rainsonet_state::{MemoryStateStore, SnapshotManager};
 use std::path::PathBuf;
-use tracing::{info, Level};
-use tracing_subscriber::FmtSubscriber;
+use tracing::info;
 
 #[derive(Parser)]
 #[command(name = "rainsonet-node")]
@@ -45,6 +46,10 @@ enum Commands {
         /// Data directory
         #[arg(long, default_value = "./data")]
         data_dir: PathBuf,
+        
+        /// Write a snapshot of the finalized state here on shutdown
+        #[arg(long)]
+        snapshot_out: Option<PathBuf>,
     },
     
     /// Generate a new keypair
@@ -67,28 +72,92 @@ enum Commands {
         /// Chain ID
         #[arg(long, default_value = "3")]
         chain_id: u64,
+        
+        /// Minimum transaction fee in smallest units, free by default
+        #[arg(long, default_value = "0")]
+        min_fee: u128,
+        
+        /// Percentage of fees burned
+        #[arg(long, default_value = "50")]
+        fee_burn_percent: u8,
+        
+        /// CSV of `address,balance_relyo` rows to allocate
+        #[arg(long)]
+        allocations: Option<PathBuf>,
+        
+        /// Validator public key in hex, with an optional stake; repeatable
+        #[arg(long = "validator", value_name = "PUBKEY[:STAKE]")]
+        validators: Vec<String>,
+        
+        /// Allocation of whole RELYO to an address; repeatable
+        #[arg(long = "allocation", value_name = "ADDRESS:RELYO")]
+        allocation: Vec<String>,
+        
+        /// Hash algorithm for state roots, blake3 or sha256; fixed for the chain's life
+        #[arg(long, default_value = "blake3")]
+        hash_algorithm: HashAlgorithm,
+    },
+    
+    /// Export the accounts in a snapshot written by `run --snapshot-out` as
+    /// JSON. A running node serves its live state at
+    /// `GET /admin/export/accounts`.
+    ExportAccounts {
+        /// Configuration file path, for the address format
+        #[arg(short, long)]
+        config: Option<PathBuf>,
+        
+        /// Snapshot file to read the accounts from
+        #[arg(short, long)]
+        snapshot: PathBuf,
+        
+        /// Hash algorithm of the chain's state roots, as set in its genesis
+        #[arg(long, default_value = "blake3")]
+        hash_algorithm: HashAlgorithm,
+        
+        /// Output file path, stdout if omitted
+        #[arg(short, long)]
+        output: Option<PathBuf>,
     },
 }
 
-#[tokio::main]
-async fn main() -> anyhow::Result<()> {
-    // Initialize logging
-    let subscriber = FmtSubscriber::builder()
-        .with_max_level(Level::INFO)
-        .with_target(false)
-        .pretty()
-        .init();
-    
+fn main() -> anyhow::Result<()> {
     let cli = Cli::parse();
     
+    // Load the node configuration up front so logging and the runtime can be
+    // set up from it
+    let (config_path, devnet) = match &cli.command {
+        Commands::Run { config, genesis, .. } => (config.clone(), genesis.is_none()),
+        Commands::ExportAccounts { config, .. } => (config.clone(), false),
+        _ => (None, false),
+    };
+    // Only the devnet genesis runs on a single validator by default
+    let node_config = match &config_path {
+        Some(path) => load_node_config(path)?,
+        None if devnet => NodeConfig::devnet(),
+        None => NodeConfig::default(),
+    };
+    
+    // Initialize logging; the guard flushes file output on exit
+    let (subscriber, _log_guard) = build_subscriber(&node_config)?;
+    tracing::subscriber::set_global_default(subscriber)?;
+    if let Some(path) = &config_path {
+        info!("Loaded configuration from {}", path.display());
+    }
+    
+    let runtime = build_runtime(&node_config.runtime)?;
+    runtime.block_on(run(cli, node_config))
+}
+
+async fn run(cli: Cli, node_config: NodeConfig) -> anyhow::Result<()> {
     match cli.command {
         Commands::Run {
-            config,
+            config: _,
             genesis,
             validator,
             api_addr,
             p2p_addr,
             data_dir,
+            snapshot_out,
         } => {
             info!("🌧️ Starting RAINSONET Node...");
             
@@ -96,16 +165,14 @@ async fn main() -> anyhow::Result<()> {
             let keypair = load_or_create_keypair(&data_dir)?;
             
             // Load or create genesis
-            let genesis_config = match genesis {
-                Some(path) => {
-                    let content = std::fs::read_to_string(&path)?;
-                    GenesisConfig::from_json(&content)?
-                }
-                None => GenesisConfig::devnet(),
-            };
+            let genesis_config = load_genesis(genesis)?;
+            
+            // State roots must be hashed the way the chain was defined
+            set_default_hash_algorithm(genesis_config.hash_algorithm);
             
             // Build node
             let mut builder = NodeBuilder::new()
+                .config(node_config)
                 .keypair(keypair)
                 .genesis(genesis_config.clone())
                 .api_addr(&api_addr)
@@ -114,6 +181,9 @@ async fn main() -> anyhow::Result<()> {
             if validator {
                 builder = builder.validator();
             }
+            if let Some(path) = snapshot_out {
+                builder = builder.snapshot_out(path);
+            }
             
             let node = builder.build();
             
@@ -147,23 +217,101 @@ async fn main() -> anyhow::Result<()> {
             output,
             chain_name,
             chain_id,
+            min_fee,
+            fee_burn_percent,
+            allocations,
+            validators,
+            allocation,
+            hash_algorithm,
         } => {
-            let genesis = GenesisConfig {
+            let mut genesis = GenesisConfig {
                 chain_name,
                 chain_id,
+                hash_algorithm,
                 ..GenesisConfig::devnet()
             };
+            genesis.relyo_config.min_fee = min_fee;
+            genesis.relyo_config.fee_burn_percent = fee_burn_percent;
+            if let Some(path) = allocations {
+                genesis = genesis.add_allocations_from_csv(&path)?;
+                let count = genesis.allocations.len();
+                println!("Loaded {} allocations from {}", count, path.display());
+            }
+            for arg in &validators {
+                let (public_key, stake) = match arg.split_once(':') {
+                    Some((key, stake)) => (key, stake.parse()?),
+                    None => (arg.as_str(), DEFAULT_VALIDATOR_STAKE),
+                };
+                genesis = genesis.try_add_validator_key(public_key, stake)?;
+            }
+            for arg in &allocation {
+                let (address, relyo) = arg
+                    .split_once(':')
+                    .ok_or_else(|| anyhow::anyhow!("Allocation {} is not ADDRESS:RELYO", arg))?;
+                let parsed = Address::from_hex(address)
+                    .map_err(|e| anyhow::anyhow!("Invalid allocation address {}: {}", address, e))?;
+                if genesis.parse_allocations()?.contains_key(&parsed) {
+                    anyhow::bail!("Duplicate allocation to {}", address);
+                }
+                genesis = genesis.try_add_allocation(address, relyo.parse()?)?;
+            }
+            genesis.validate()?;
             
             let json = genesis.to_json()?;
             std::fs::write(&output, &json)?;
             
             println!("Genesis configuration saved to: {}", output.display());
         }
+        
+        Commands::ExportAccounts {
+            config: _,
+            snapshot,
+            hash_algorithm,
+            output,
+        } => {
+            // The snapshot's root is checked on import
+            set_default_hash_algorithm(hash_algorithm);
+            let state = MemoryStateStore::new();
+            SnapshotManager::import_from_reader(std::fs::File::open(&snapshot)?, &state)?;
+            
+            let address = &node_config.address;
+            let export = match &output {
+                Some(path) => export_accounts_json(&state, address, std::fs::File::create(path)?)?,
+                None => export_accounts_json(&state, address, std::io::stdout().lock())?,
+            };
+            
+            // Stdout may hold the export itself
+            eprintln!(
+                "Exported {} accounts at version {} with state root {}",
+                export.accounts,
+                export.version,
+                export.state_root.to_hex()
+            );
+            if let Some(path) = output {
+                eprintln!("Saved to: {}", path.display());
+            }
+        }
     }
     
     Ok(())
 }
 
+fn load_genesis(path: Option<PathBuf>) -> anyhow::Result<GenesisConfig> {
+    match path {
+        Some(path) => {
+            let content = std::fs::read_to_string(&path)?;
+            Ok(GenesisConfig::from_json(&content)?)
+        }
+        None => Ok(GenesisConfig::devnet()),
+    }
+}
+
+fn load_node_config(path: &PathBuf) -> anyhow::Result<NodeConfig> {
+    let content = std::fs::read_to_string(path)?;
+    let config: NodeConfig = serde_json::from_str(&content)?;
+    Ok(config)
+}
+
 fn load_or_create_keypair(data_dir: &PathBuf) -> anyhow::Result<KeyPair> {
     let key_path = data_dir.join("node_key.json");
     
@@ -1,39 +1,135 @@
 //! Full node implementation
 
-use crate::api::start_api_server;
+use crate::api::{start_api_server, TransactionTypeRegistry};
 use crate::runtime::NodeRuntime;
-use rainsonet_core::NodeConfig;
+use crate::shutdown::ShutdownReport;
+use rainsonet_core::{NodeConfig, StateVersion};
 use rainsonet_crypto::keys::KeyPair;
 use rainsonet_relyo::GenesisConfig;
+use rainsonet_state::PersistentStateStore;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 use tokio::signal;
-use tracing::{error, info};
+use tokio::sync::broadcast;
+use tracing::{error, info, warn};
+
+/// How often the liveness watchdog checks for stalled consensus
+const LIVENESS_CHECK_INTERVAL: Duration = Duration::from_secs(5);
+
+/// File in the data directory holding votes collected towards the next
+/// version, restored on startup
+pub const PENDING_VOTES_FILE: &str = "pending_votes.json";
+
+/// Directory in the data directory the finalized state is written to on
+/// shutdown, on the configured `storage_backend`
+pub const STATE_DIR: &str = "state";
 
 /// Full RAINSONET node
 pub struct RainsonetNode {
     runtime: Arc<NodeRuntime>,
+    /// Where to write a state snapshot on shutdown
+    snapshot_out: Option<PathBuf>,
+    /// Transaction types the API accepts
+    transaction_types: TransactionTypeRegistry,
 }
 
 impl RainsonetNode {
     /// Create a new node
     pub fn new(config: NodeConfig, keypair: KeyPair, genesis: GenesisConfig) -> Self {
         let runtime = Arc::new(NodeRuntime::new(config, keypair, genesis));
-        Self { runtime }
+        Self {
+            runtime,
+            snapshot_out: None,
+            transaction_types: TransactionTypeRegistry::default(),
+        }
+    }
+    
+    /// Write the finalized state to `path` on shutdown, for other nodes to
+    /// sync from
+    pub fn with_snapshot_out(mut self, path: impl Into<PathBuf>) -> Self {
+        self.snapshot_out = Some(path.into());
+        self
+    }
+    
+    /// Accept the transaction types in `transaction_types` on the API, for
+    /// modules that bring their own kinds
+    pub fn with_transaction_types(mut self, transaction_types: TransactionTypeRegistry) -> Self {
+        self.transaction_types = transaction_types;
+        self
     }
     
     /// Start the node
     pub async fn start(&self, genesis: GenesisConfig) -> anyhow::Result<()> {
         info!("Starting RAINSONET node...");
+        let started_at = Instant::now();
+        
+        self.runtime.ensure_validator_membership()?;
         
         // Initialize genesis
         self.runtime.initialize_genesis(genesis).await?;
         
+        let data_dir = self.runtime.config().data_dir.clone();
+        std::fs::create_dir_all(&data_dir)?;
+        let votes_path = data_dir.join(PENDING_VOTES_FILE);
+        
+        // Fail now rather than at shutdown if the backend is not built in
+        // or the state directory was written by another one
+        let backend = self.runtime.config().storage_backend;
+        PersistentStateStore::open_with(data_dir.join(STATE_DIR), backend)?;
+        
+        // Apply own blocks as consensus finalizes them
+        let mut consensus_events = self.runtime.subscribe_consensus_events();
+        let finality_runtime = self.runtime.clone();
+        let finality_handle = tokio::spawn(async move {
+            loop {
+                match consensus_events.recv().await {
+                    Ok(event) => {
+                        if let Err(e) = finality_runtime.handle_consensus_event(&event).await {
+                            warn!("Failed to handle consensus event: {}", e);
+                        }
+                    }
+                    Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                        warn!("Skipped {} consensus events", skipped);
+                    }
+                    Err(broadcast::error::RecvError::Closed) => break,
+                }
+            }
+        });
+        
+        // Pick up votes collected before the last restart. A restored quorum
+        // finalizes at once, so this runs after the finality subscriber above.
+        match self.runtime.restore_pending_votes(&votes_path) {
+            Ok(0) => {}
+            Ok(restored) => info!("Restored votes for {} pending proposals", restored),
+            Err(e) => warn!("Failed to restore pending votes: {}", e),
+        }
+        
+        // Fan consensus events out to API subscribers
+        self.runtime.start_event_fanout();
+        
+        // Watch for consensus that stops finalizing, saving the votes
+        // collected so far in case the node does not stop cleanly
+        let watchdog_runtime = self.runtime.clone();
+        let watchdog_votes_path = votes_path.clone();
+        let watchdog_handle = tokio::spawn(async move {
+            let mut interval = tokio::time::interval(LIVENESS_CHECK_INTERVAL);
+            loop {
+                interval.tick().await;
+                watchdog_runtime.check_liveness();
+                if let Err(e) = watchdog_runtime.save_pending_votes(&watchdog_votes_path) {
+                    warn!("Failed to save pending votes: {}", e);
+                }
+            }
+        });
+        
         // Start API server
         let api_runtime = self.runtime.clone();
         let api_addr = self.runtime.config().api.listen_addr.clone();
+        let transaction_types = self.transaction_types.clone();
         
         let api_handle = tokio::spawn(async move {
-            if let Err(e) = start_api_server(api_runtime, &api_addr).await {
+            if let Err(e) = start_api_server(api_runtime, &api_addr, transaction_types).await {
                 error!("API server error: {}", e);
             }
         });
@@ -54,12 +150,81 @@ impl RainsonetNode {
         
         // Cleanup
         api_handle.abort();
+        watchdog_handle.abort();
+        finality_handle.abort();
+        
+        if let Err(e) = self.runtime.save_pending_votes(&votes_path) {
+            warn!("Failed to save pending votes: {}", e);
+        }
+        
+        if let Some(path) = &self.snapshot_out {
+            match self.write_snapshot(path) {
+                Ok(()) => info!("Wrote state snapshot to {}", path.display()),
+                Err(e) => warn!("Failed to write state snapshot: {}", e),
+            }
+        }
         
-        info!("Node stopped");
+        let state_flushed = match self.persist_state(&data_dir.join(STATE_DIR)) {
+            Ok(version) => {
+                info!("Persisted finalized state at version {}", version);
+                true
+            }
+            Err(e) => {
+                warn!("Failed to persist state: {}", e);
+                false
+            }
+        };
         
+        match ShutdownReport::from_runtime(&self.runtime, started_at.elapsed(), state_flushed) {
+            Ok(report) => {
+                report.log();
+                if let Err(e) = report.write_to(&self.runtime.config().data_dir) {
+                    warn!("Failed to write shutdown report: {}", e);
+                }
+            }
+            Err(e) => {
+                warn!("Failed to build shutdown report: {}", e);
+                info!("Node stopped");
+            }
+        }
+        
+        Ok(())
+    }
+    
+    /// Stream the finalized state to `path`, replacing it only once the
+    /// snapshot is complete
+    fn write_snapshot(&self, path: &Path) -> anyhow::Result<()> {
+        let partial = path.with_extension("partial");
+        let info = self.runtime.export_snapshot(std::fs::File::create(&partial)?)?;
+        std::fs::rename(&partial, path)?;
+        info!(
+            "Snapshot holds {} entries at version {} with root {}",
+            info.entries, info.version, info.root
+        );
         Ok(())
     }
     
+    /// Replace the state in `path` with the finalized state, on the
+    /// configured backend. It is written to a fresh store beside `path`
+    /// that is moved into place once flushed.
+    fn persist_state(&self, path: &Path) -> anyhow::Result<StateVersion> {
+        let partial = path.with_extension("partial");
+        if partial.exists() {
+            std::fs::remove_dir_all(&partial)?;
+        }
+        
+        let backend = self.runtime.config().storage_backend;
+        let store = PersistentStateStore::open_with(&partial, backend)?;
+        let version = self.runtime.persist_finalized_state(&store)?;
+        drop(store);
+        
+        if path.exists() {
+            std::fs::remove_dir_all(path)?;
+        }
+        std::fs::rename(&partial, path)?;
+        Ok(version)
+    }
+    
     /// Get runtime reference
     pub fn runtime(&self) -> &Arc<NodeRuntime> {
         &self.runtime
@@ -71,6 +236,8 @@ pub struct NodeBuilder {
     config: NodeConfig,
     keypair: Option<KeyPair>,
     genesis: GenesisConfig,
+    snapshot_out: Option<PathBuf>,
+    transaction_types: TransactionTypeRegistry,
 }
 
 impl NodeBuilder {
@@ -79,6 +246,8 @@ impl NodeBuilder {
             config: NodeConfig::default(),
             keypair: None,
             genesis: GenesisConfig::devnet(),
+            snapshot_out: None,
+            transaction_types: TransactionTypeRegistry::default(),
         }
     }
     
@@ -112,9 +281,24 @@ impl NodeBuilder {
         self
     }
     
+    pub fn snapshot_out(mut self, path: impl Into<PathBuf>) -> Self {
+        self.snapshot_out = Some(path.into());
+        self
+    }
+    
+    pub fn transaction_types(mut self, transaction_types: TransactionTypeRegistry) -> Self {
+        self.transaction_types = transaction_types;
+        self
+    }
+    
     pub fn build(self) -> RainsonetNode {
         let keypair = self.keypair.unwrap_or_else(KeyPair::generate);
-        RainsonetNode::new(self.config, keypair, self.genesis)
+        let node = RainsonetNode::new(self.config, keypair, self.genesis)
+            .with_transaction_types(self.transaction_types);
+        match self.snapshot_out {
+            Some(path) => node.with_snapshot_out(path),
+            None => node,
+        }
     }
 }
 
@@ -1,28 +1,211 @@
 //! Node runtime combining all components
 
 use rainsonet_consensus::{
-    ConsensusEvent, LocalValidator, RainsonetConsensus, SharedValidatorSet, ValidatorInfo,
-    ValidatorSet,
+    create_consensus_channel, load_vote_collections, save_vote_collections, ConsensusEvent,
+    FinalityCertificate, Proposal, RainsonetConsensus, SharedValidatorSet, ValidatorInfo,
+    ValidatorLiveness, ValidatorSet,
 };
 use rainsonet_core::{
-    Address, Amount, Hash, NodeConfig, NodeId, Nonce, RainsonetResult, StateChange,
-    StateRoot, StateVersion,
+    amount_as_string, Address, AddressConfig, Amount, FinalizationMode, Hash, NodeConfig, NodeId,
+    Nonce, RainsonetResult, StateChange, StateMutator, StateRoot, StateVersion, Timestamp,
 };
+use rainsonet_crypto::hashing::{default_hash_algorithm, HashAlgorithm, MerkleProof};
 use rainsonet_crypto::keys::KeyPair;
-use rainsonet_p2p::{create_network_channel, NetworkEvent, NetworkService};
+use rainsonet_p2p::{
+    create_peer_manager, CertificateMessage, NetworkCommand, NetworkEvent, NetworkService,
+    ProposalMessage, SeenFilter, SharedPeerManager, StateAnnouncer, SyncRequestMessage,
+     SyncResponseMessage, SyncSource, TransactionMessage, TransactionSource,
+};
 use rainsonet_relyo::{
-    create_mempool, Account, GenesisConfig, GenesisInitializer, RelyoLedger, SharedMempool,
-    VerifiedTransaction,
+    compute_genesis_hash, is_canonical_order, Account, FeeTier, GenesisConfig,
+    GenesisInitializer, Mempool, MempoolEntry, MempoolStats, ProposalContext, RelyoLedger,
+    RelyoTransaction, SharedMempool, SupplyAudit, VerifiedTransaction, DEFAULT_VALIDATOR_STAKE,
+};
+use rainsonet_state::{
+    account_key, create_memory_store, parse_account_key, AccountState, MemoryStateStore,
+    PersistentStateStore, SharedMemoryStateStore, SnapshotInfo, SnapshotManager, StateDiff,
+    ACCOUNT_PREFIX, VALIDATOR_PREFIX,
 };
-use rainsonet_state::{create_memory_store, MemoryStateStore, SharedMemoryStateStore};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::io::{BufWriter, Write};
+use std::path::Path;
 use std::sync::Arc;
-use tokio::sync::mpsc;
-use tracing::{debug, error, info, warn};
+use tokio::sync::{broadcast, mpsc};
+use tracing::{debug, info, warn};
+
+use crate::sync::{build_sync_response, SYNC_WINDOW_SIZE};
+use crate::watchdog::LivenessWatchdog;
+
+/// Unsigned transaction prepared by the node for a client to sign
+#[derive(Debug, Clone)]
+pub struct PreparedTransaction {
+    pub from: Address,
+    pub to: Address,
+    pub amount: Amount,
+    pub fee: Amount,
+    pub nonce: Nonce,
+    pub timestamp: Timestamp,
+    pub signing_bytes: Vec<u8>,
+}
+
+/// Finalized account state with everything needed to check it offline:
+/// the certificate for its version and a Merkle proof against its root
+#[derive(Debug, Clone)]
+pub struct AccountProof {
+    pub address: Address,
+    pub account: AccountState,
+    pub certificate: FinalityCertificate,
+    pub proof: MerkleProof,
+    /// Active validators the certificate is checked against. They come from
+    /// the serving node, so a client that does not trust it has to obtain
+    /// the validator set elsewhere; see `verify_account` in the CLI.
+    pub validators: Vec<ValidatorInfo>,
+}
+
+/// Where a transaction landed and how deeply it is finalized
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TransactionReceipt {
+    /// State version that included the transaction
+    pub version: StateVersion,
+    /// Finalized versions on top of `version`, `None` until it is finalized
+    pub confirmations: Option<u64>,
+    /// Signed timestamp, when the transaction went through this node's mempool
+    pub timestamp: Option<Timestamp>,
+}
+
+/// Account as written by [`NodeRuntime::export_state_json`]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExportedAccount {
+    pub address: String,
+    #[serde(with = "amount_as_string")]
+    pub balance: Amount,
+    pub nonce: u64,
+}
+
+/// Version and root a state export was taken at, and how many accounts
+/// it holds
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StateExport {
+    pub version: StateVersion,
+    pub state_root: StateRoot,
+    pub accounts: usize,
+}
+
+/// Write every account in `state` to `writer` as
+/// `{"version", "state_root", "accounts": [{address, balance, nonce}]}`,
+/// rendering addresses with `address`.
+///
+/// Accounts are streamed one at a time, in no particular order.
+pub fn export_accounts_json<W: Write>(
+    state: &MemoryStateStore,
+    address: &AddressConfig,
+    writer: W,
+) -> RainsonetResult<StateExport> {
+    let version = state.version();
+    let state_root = state.compute_root()?;
+    
+    let mut writer = BufWriter::new(writer);
+    write!(
+        writer,
+        "{{\"version\":{},\"state_root\":\"{}\",\"accounts\":[",
+        version.0,
+        state_root.to_hex()
+    )?;
+    
+    let mut accounts = 0;
+    for entry in state.iter_prefix(ACCOUNT_PREFIX) {
+        let Some(account_address) = parse_account_key(&entry.key)
+            .and_then(|bytes| <[u8; 32]>::try_from(bytes).ok())
+            .map(Address::from_bytes)
+        else {
+            continue;
+        };
+        let account = AccountState::from_bytes(&entry.value)?;
+        if accounts > 0 {
+            writer.write_all(b",")?;
+        }
+        serde_json::to_writer(
+            &mut writer,
+            &ExportedAccount {
+                address: address.render(&account_address),
+                balance: Amount::new(account.balance),
+                nonce: account.nonce,
+            },
+        )?;
+        accounts += 1;
+    }
+    
+    writer.write_all(b"]}")?;
+    writer.flush()?;
+    
+    Ok(StateExport {
+        version,
+        state_root,
+        accounts,
+    })
+}
+
+/// Which state account reads are served from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum StateView {
+    /// Applied locally when proposing, may still be reverted
+    Optimistic,
+    /// Backed by a finality certificate, irreversible
+    #[default]
+    Finalized,
+}
+
+/// Block executed against the current state, not yet proposed
+struct ExecutedBlock {
+    /// Root the changes were executed on
+    previous_root: StateRoot,
+    new_root: StateRoot,
+    tx_ids: Vec<Hash>,
+    inactive_validators: Vec<NodeId>,
+    stake_updates: Vec<(NodeId, u128)>,
+    changes: Vec<StateChange>,
+    context: ProposalContext,
+}
+
+/// Block proposed by this node, applied once its proposal is finalized
+struct PendingBlock {
+    proposal_id: Hash,
+    state_version: StateVersion,
+    /// Root the changes were executed on
+    previous_root: StateRoot,
+    new_root: StateRoot,
+    tx_ids: Vec<Hash>,
+    /// Queued stake updates the block includes, dequeued once it is applied
+    stake_updates: Vec<(NodeId, u128)>,
+    changes: Vec<StateChange>,
+    /// Ledger changes from executing the block, committed when it is applied
+    context: ProposalContext,
+}
+
+/// Validator records among `changes`, kept to update the validator set
+/// once the changes are applied
+fn validator_records(changes: &[StateChange]) -> Vec<StateChange> {
+    changes
+        .iter()
+        .filter(|change| match change {
+            StateChange::Set { key, .. } | StateChange::Delete { key } => {
+                key.starts_with(VALIDATOR_PREFIX)
+            }
+        })
+        .cloned()
+        .collect()
+}
 
 /// Node runtime managing all components
 pub struct NodeRuntime {
     config: NodeConfig,
     keypair: KeyPair,
+    /// Hash of the genesis the node started from, sent in handshakes
+    genesis_hash: Hash,
+    /// Algorithm the chain hashes state roots with, from genesis
+    hash_algorithm: HashAlgorithm,
     state: SharedMemoryStateStore,
     ledger: Arc<RelyoLedger<MemoryStateStore>>,
     mempool: SharedMempool,
@@ -30,11 +213,81 @@ pub struct NodeRuntime {
     validator_set: SharedValidatorSet,
     state_version: parking_lot::RwLock<StateVersion>,
     state_root: parking_lot::RwLock<StateRoot>,
+    state_announcer: StateAnnouncer,
+    consensus_events_rx: parking_lot::Mutex<Option<mpsc::Receiver<ConsensusEvent>>>,
+    consensus_events: broadcast::Sender<ConsensusEvent>,
+    waiting_proposals: parking_lot::Mutex<Vec<Proposal>>,
+    peer_manager: SharedPeerManager,
+    seen_transactions: parking_lot::Mutex<SeenFilter>,
+    transaction_outbox: Option<mpsc::Sender<TransactionMessage>>,
+    /// Certificates of finalized versions already received or broadcast
+    seen_certificates: parking_lot::Mutex<SeenFilter>,
+    certificate_outbox: Option<mpsc::Sender<CertificateMessage>>,
+    network_commands: Option<mpsc::Sender<NetworkCommand>>,
+    /// Copy of the state at the latest finalized version, advanced lazily
+    /// from the optimistic store's history
+    finalized_state: parking_lot::Mutex<MemoryStateStore>,
+    /// State version each applied transaction was included in
+    receipts: parking_lot::RwLock<Receipts>,
+    liveness: LivenessWatchdog,
+    /// Own block awaiting votes in `Voted` finalization mode
+    pending_block: parking_lot::Mutex<Option<PendingBlock>>,
+    /// Validator stakes to set in this node's next proposal
+    pending_stake_updates: parking_lot::Mutex<Vec<(NodeId, u128)>>,
+    /// Held from checking a block's base root until its changes are applied
+    apply_lock: tokio::sync::Mutex<()>,
+}
+
+/// Receipts of applied transactions, indexed by version as well so the
+/// oldest can be pruned along with state history
+#[derive(Default)]
+struct Receipts {
+    by_tx: HashMap<Hash, (StateVersion, Option<Timestamp>)>,
+    by_version: VecDeque<(StateVersion, Vec<Hash>)>,
+}
+
+impl Receipts {
+    fn record(&mut self, version: StateVersion, receipts: Vec<(Hash, Option<Timestamp>)>) {
+        let mut tx_ids = Vec::with_capacity(receipts.len());
+        for (tx_id, timestamp) in receipts {
+            self.by_tx.insert(tx_id, (version, timestamp));
+            tx_ids.push(tx_id);
+        }
+        self.by_version.push_back((version, tx_ids));
+    }
+    
+    /// Drop receipts of versions before `before_version`
+    fn prune(&mut self, before_version: StateVersion) {
+        while let Some((version, _)) = self.by_version.front() {
+            if *version >= before_version {
+                break;
+            }
+            let (version, tx_ids) = self.by_version.pop_front().expect("front exists");
+            for tx_id in tx_ids {
+                // A later inclusion of the same ID keeps its receipt
+                if self.by_tx.get(&tx_id).is_some_and(|(v, _)| *v == version) {
+                    self.by_tx.remove(&tx_id);
+                }
+            }
+        }
+    }
 }
 
+/// Buffered consensus events per subscriber before it starts lagging
+const CONSENSUS_EVENT_BUFFER: usize = 256;
+
+/// Received proposals kept while their transactions are still in flight
+const MAX_WAITING_PROPOSALS: usize = 64;
+
+/// Blocks built per proposal round when the state keeps moving under them
+const MAX_PROPOSAL_ATTEMPTS: usize = 3;
+
 impl NodeRuntime {
     /// Create a new node runtime
     pub fn new(config: NodeConfig, keypair: KeyPair, genesis: GenesisConfig) -> Self {
+        let hash_algorithm = genesis.hash_algorithm;
+        let genesis_hash = compute_genesis_hash(&genesis).expect("genesis serializes to JSON");
+        
         // Initialize state store
         let state = create_memory_store();
         
@@ -42,17 +295,33 @@ impl NodeRuntime {
         let ledger = Arc::new(RelyoLedger::new(state.clone(), genesis.relyo_config.clone()));
         
         // Initialize mempool
-        let mempool = create_mempool(10000, 100);
+        let mempool = Arc::new(
+            Mempool::new(10000, 100)
+                .with_max_per_sender_per_block(config.consensus.max_block_transactions_per_sender)
+                .with_replace_by_fee(config.consensus.mempool_replace_by_fee),
+        );
         
         // Initialize validator set
-        let validator_set = Arc::new(ValidatorSet::new());
+        let validator_set = Arc::new(
+            ValidatorSet::new().with_min_stake(config.consensus.min_validator_stake),
+        );
+        
+        // Load the validators whose keys genesis lists
+        match genesis.parse_validators() {
+            Ok(validators) => validators.into_iter().for_each(|v| validator_set.add_validator(v)),
+            Err(e) => warn!("Ignoring genesis validator keys: {}", e),
+        }
         
-        // Add self as validator if configured
-        if config.consensus.is_validator {
+        // Add self as validator if configured and, when genesis lists its
+        // validators, listed there
+        if config.consensus.is_validator
+            && Self::genesis_lists(&genesis, &keypair.address())
+            && !validator_set.is_validator(&keypair.node_id())
+        {
             let validator_info = ValidatorInfo::new(
                 keypair.node_id(),
                 keypair.public_key(),
-                1000, // Default stake
+                DEFAULT_VALIDATOR_STAKE,
             );
             validator_set.add_validator(validator_info);
         }
@@ -64,15 +333,27 @@ impl NodeRuntime {
             None
         };
         
-        let consensus = Arc::new(RainsonetConsensus::new(
+        let mut consensus = RainsonetConsensus::new(
             config.consensus.clone(),
             validator_set.clone(),
             consensus_keypair,
-        ));
+        )
+        .expect("validator keypair is always provided");
+        let (consensus_tx, consensus_rx) = create_consensus_channel();
+        consensus.set_event_channel(consensus_tx);
+        let consensus = Arc::new(consensus);
+        let (consensus_events, _) = broadcast::channel(CONSENSUS_EVENT_BUFFER);
+        let peer_manager = create_peer_manager(config.network.max_peers);
+        let liveness = LivenessWatchdog::new(
+            std::time::Duration::from_millis(config.consensus.stall_threshold_ms),
+            rainsonet_core::system_time(),
+        );
         
         Self {
             config,
             keypair,
+            genesis_hash,
+            hash_algorithm,
             state,
             ledger,
             mempool,
@@ -80,23 +361,159 @@ impl NodeRuntime {
             validator_set,
             state_version: parking_lot::RwLock::new(StateVersion::new(0)),
             state_root: parking_lot::RwLock::new(Hash::ZERO),
+            state_announcer: StateAnnouncer::default(),
+            consensus_events_rx: parking_lot::Mutex::new(Some(consensus_rx)),
+            consensus_events,
+            waiting_proposals: parking_lot::Mutex::new(Vec::new()),
+            peer_manager,
+            seen_transactions: parking_lot::Mutex::new(SeenFilter::default()),
+            transaction_outbox: None,
+            seen_certificates: parking_lot::Mutex::new(SeenFilter::default()),
+            certificate_outbox: None,
+            network_commands: None,
+            finalized_state: parking_lot::Mutex::new(MemoryStateStore::new()),
+            receipts: parking_lot::RwLock::new(Receipts::default()),
+            liveness,
+            pending_block: parking_lot::Mutex::new(None),
+            pending_stake_updates: parking_lot::Mutex::new(Vec::new()),
+            apply_lock: tokio::sync::Mutex::new(()),
         }
     }
     
-    /// Initialize genesis state
+    /// Whether `address` may validate under `genesis`; an empty validator
+    /// list, as on devnets, admits any validator
+    fn genesis_lists(genesis: &GenesisConfig, address: &Address) -> bool {
+        genesis.validators.is_empty()
+            || genesis
+                .validators
+                .iter()
+                .any(|v| Address::from_hex(v).map(|a| a == *address).unwrap_or(false))
+    }
+    
+    /// Check that a validator node is an active member of its own
+    /// validator set. Proposals from a node outside the set would never
+    /// gather votes, so such a node refuses to start.
+    pub fn ensure_validator_membership(&self) -> RainsonetResult<()> {
+        if self.is_validator() && !self.validator_set.is_validator(&self.keypair.node_id()) {
+            return Err(rainsonet_core::RainsonetError::ConfigError(format!(
+                "Node {} is configured as a validator but is not in the validator set",
+                self.keypair.node_id()
+            )));
+        }
+        Ok(())
+    }
+    
+    /// Queue locally submitted transactions for the network service to
+    /// broadcast, see [`rainsonet_p2p::create_transaction_outbox`]
+    pub fn with_transaction_outbox(mut self, outbox: mpsc::Sender<TransactionMessage>) -> Self {
+        self.transaction_outbox = Some(outbox);
+        self
+    }
+    
+    /// Queue the certificate of every version finalized here for the
+    /// network service to broadcast, see
+    /// [`rainsonet_p2p::create_certificate_outbox`]
+    pub fn with_certificate_outbox(mut self, outbox: mpsc::Sender<CertificateMessage>) -> Self {
+        self.certificate_outbox = Some(outbox);
+        self
+    }
+    
+    /// Forward operator commands to the network service, see
+    /// [`rainsonet_p2p::create_network_commands`]
+    pub fn with_network_commands(mut self, commands: mpsc::Sender<NetworkCommand>) -> Self {
+        self.network_commands = Some(commands);
+        self
+    }
+    
+    /// Track peers with the given manager, e.g. the network service's
+    pub fn with_peer_manager(mut self, peer_manager: SharedPeerManager) -> Self {
+        self.peer_manager = peer_manager;
+        self
+    }
+    
+    /// Initialize genesis state, unless the store already holds accounts.
+    ///
+    /// Fails unless the process hashes with the genesis algorithm, see
+    /// [`rainsonet_crypto::hashing::set_default_hash_algorithm`].
     pub async fn initialize_genesis(&self, genesis: GenesisConfig) -> RainsonetResult<()> {
-        let initializer = GenesisInitializer::new(self.ledger.clone(), genesis);
-        initializer.initialize().await?;
+        // The algorithm is process-wide, set once by the binary at startup
+        if default_hash_algorithm() != genesis.hash_algorithm {
+            return Err(rainsonet_core::RainsonetError::ConfigError(format!(
+                "Genesis hashes state roots with {:?} but the process uses {:?}",
+                genesis.hash_algorithm,
+                default_hash_algorithm()
+            )));
+        }
+        
+        if self.ledger.has_accounts().await? {
+            info!("State already initialized, skipping genesis allocations");
+        } else {
+            let initializer = GenesisInitializer::new(self.ledger.clone(), genesis);
+            initializer.initialize().await?;
+        }
         
         // Compute initial state root
-        let root = self.state.compute_root().await?;
+        let root = self.state.compute_root()?;
         *self.state_root.write() = root;
+        self.consensus.set_genesis_root(root);
+        self.state_announcer.announce(self.state_version(), root);
+        *self.finalized_state.lock() = self.state.snapshot();
         
         info!("Genesis initialized, state root: {}", root);
         
         Ok(())
     }
     
+    /// Create the network service for this node. Its handshakes carry the
+    /// node's role and genesis hash, so peers on another genesis disconnect,
+    /// and announce the state this runtime finalizes. Peers claiming to be
+    /// validators are only treated as such if they are in the validator set.
+    pub async fn create_network_service(
+        &self,
+        event_tx: mpsc::Sender<NetworkEvent>,
+    ) -> RainsonetResult<NetworkService> {
+        let service = NetworkService::new(&self.keypair, &self.config.network, event_tx)
+            .await
+            .map_err(|e| rainsonet_core::RainsonetError::NetworkError(e.to_string()))?;
+        Ok(service
+            .with_validator_role(self.is_validator())
+            .with_validator_registry(self.validator_set.clone())
+            .with_genesis_hash(self.genesis_hash)
+            .with_state_announcer(self.state_announcer.clone()))
+    }
+    
+    /// Hash of the genesis this node started from
+    pub fn genesis_hash(&self) -> Hash {
+        self.genesis_hash
+    }
+    
+    /// Algorithm the chain hashes state roots with
+    pub fn hash_algorithm(&self) -> HashAlgorithm {
+        self.hash_algorithm
+    }
+    
+    /// Start forwarding consensus events to subscribers.
+    ///
+    /// Must be called from within a tokio runtime; later calls are no-ops.
+    pub fn start_event_fanout(&self) {
+        let Some(mut rx) = self.consensus_events_rx.lock().take() else {
+            return;
+        };
+        let events = self.consensus_events.clone();
+        
+        tokio::spawn(async move {
+            while let Some(event) = rx.recv().await {
+                // No subscribers is fine, the event is simply dropped
+                let _ = events.send(event);
+            }
+        });
+    }
+    
+    /// Subscribe to consensus events
+    pub fn subscribe_consensus_events(&self) -> broadcast::Receiver<ConsensusEvent> {
+        self.consensus_events.subscribe()
+    }
+    
     /// Get node ID
     pub fn node_id(&self) -> Option<NodeId> {
         Some(self.keypair.node_id())
@@ -107,19 +524,75 @@ impl NodeRuntime {
         self.config.consensus.is_validator
     }
     
-    /// Get current state version
+    /// Get current state version. This is the optimistic version, which
+    /// includes locally proposed changes that are not finalized yet.
     pub fn state_version(&self) -> StateVersion {
         *self.state_version.read()
     }
     
+    /// Get the latest version backed by a finality certificate
+    pub fn finalized_version(&self) -> StateVersion {
+        self.consensus.latest_finalized_version()
+    }
+    
     /// Get current state root
     pub fn state_root(&self) -> StateRoot {
         *self.state_root.read()
     }
     
-    /// Get peer count (placeholder)
+    /// Get peer count
     pub fn peer_count(&self) -> usize {
-        0 // TODO: Implement when network is connected
+        self.peer_manager.peer_count()
+    }
+    
+    /// Whether transactions submitted here can reach a validator: this node
+    /// is one, or a validator peer is connected
+    pub fn validators_reachable(&self) -> bool {
+        self.is_validator() || self.peer_manager.validator_count() > 0
+    }
+    
+    /// Get the peer manager
+    pub fn peer_manager(&self) -> &SharedPeerManager {
+        &self.peer_manager
+    }
+    
+    /// When proposed blocks are applied, from the config or else by the
+    /// number of active validators
+    pub fn finalization_mode(&self) -> FinalizationMode {
+        self.config.consensus.finalization_mode.unwrap_or_else(|| {
+            if self.validator_set.active_count() <= 1 {
+                FinalizationMode::Instant
+            } else {
+                FinalizationMode::Voted
+            }
+        })
+    }
+    
+    /// Whether this validator holds back proposals until it is connected to
+    /// `min_peers_to_propose` validator peers
+    pub fn is_waiting_for_peers(&self) -> bool {
+        self.is_validator()
+            && self.peer_manager.validator_count() < self.config.consensus.min_peers_to_propose
+    }
+    
+    /// Check whether consensus stalled with transactions pending, see
+    /// [`LivenessWatchdog::check`]
+    pub fn check_liveness(&self) -> bool {
+        self.liveness.check(self.finalized_version(), self.mempool_size())
+    }
+    
+    /// Get the consensus liveness watchdog
+    pub fn liveness(&self) -> &LivenessWatchdog {
+        &self.liveness
+    }
+    
+    /// Voting participation of active validators over the configured
+    /// liveness window, up to the latest finalized version
+    pub fn validator_liveness(&self) -> Vec<ValidatorLiveness> {
+        self.validator_set.liveness(
+            self.finalized_version(),
+            self.config.consensus.liveness_window_versions,
+        )
     }
     
     /// Get mempool size
@@ -127,6 +600,16 @@ impl NodeRuntime {
         self.mempool.size()
     }
     
+    /// Check that balances, burned fees and collected fees add up to the supply
+    pub async fn audit_supply(&self) -> RainsonetResult<SupplyAudit> {
+        self.ledger.audit_supply().await
+    }
+    
+    /// Get aggregate mempool statistics
+    pub fn mempool_stats(&self) -> MempoolStats {
+        self.mempool.stats()
+    }
+    
     /// Get mempool transaction IDs
     pub fn mempool_tx_ids(&self) -> Vec<Hash> {
         self.mempool.all_tx_ids()
@@ -137,6 +620,29 @@ impl NodeRuntime {
         self.mempool.contains(tx_id)
     }
     
+    /// Get a pending transaction from the mempool
+    pub fn pending_transaction(&self, tx_id: &Hash) -> Option<VerifiedTransaction> {
+        self.mempool.get(tx_id)
+    }
+    
+    /// Get the receipt of an applied transaction
+    pub fn transaction_receipt(&self, tx_id: &Hash) -> Option<TransactionReceipt> {
+        let (version, timestamp) = *self.receipts.read().by_tx.get(tx_id)?;
+        let finalized = self.finalized_version();
+        Some(TransactionReceipt {
+            version,
+            confirmations: (finalized >= version).then(|| finalized.0 - version.0),
+            timestamp,
+        })
+    }
+    
+    /// Get pending transactions sent from an address, in nonce order
+    pub fn pending_for(&self, address: &Address) -> Vec<MempoolEntry> {
+        let mut entries = self.mempool.get_entries_by_sender(address);
+        entries.sort_by_key(|e| e.tx.tx.nonce);
+        entries
+    }
+    
     /// Get account
     pub async fn get_account(&self, address: &Address) -> RainsonetResult<Account> {
         self.ledger.get_account(address).await
@@ -147,114 +653,811 @@ impl NodeRuntime {
         self.ledger.get_balance(address).await
     }
     
+    /// Get account state as seen in `view`
+    pub async fn get_account_in(
+        &self,
+        address: &Address,
+        view: StateView,
+    ) -> RainsonetResult<Account> {
+        match view {
+            StateView::Optimistic => self.get_account(address).await,
+            StateView::Finalized => {
+                let finalized = self.finalized_state()?;
+                Ok(match finalized.get_account(address.as_bytes())? {
+                    Some(state) => Account::from_state(*address, state),
+                    None => Account::new(*address, Amount::ZERO),
+                })
+            }
+        }
+    }
+    
+    /// Get balance as seen in `view`
+    pub async fn get_balance_in(
+        &self,
+        address: &Address,
+        view: StateView,
+    ) -> RainsonetResult<Amount> {
+        Ok(self.get_account_in(address, view).await?.balance)
+    }
+    
+    /// Prove an account's finalized state for light clients.
+    ///
+    /// Fails with `StateNotFound` until a version with the account has been
+    /// finalized; absent accounts cannot be proven.
+    pub fn account_proof(&self, address: &Address) -> RainsonetResult<AccountProof> {
+        let finalized = self.finalized_state()?;
+        let certificate = self
+            .consensus
+            .get_certificate(finalized.version())
+            .ok_or(rainsonet_core::RainsonetError::StateNotFound)?;
+        if finalized.compute_root()? != certificate.state_root {
+            return Err(rainsonet_core::RainsonetError::StateCorruption(format!(
+                "Finalized state does not match certified root at version {}",
+                certificate.state_version
+            )));
+        }
+        
+        let account = finalized
+            .get_account(address.as_bytes())?
+            .ok_or(rainsonet_core::RainsonetError::StateNotFound)?;
+        let proof = finalized
+            .prove(&account_key(address.as_bytes()))
+            .ok_or(rainsonet_core::RainsonetError::StateNotFound)?;
+        
+        Ok(AccountProof {
+            address: *address,
+            account,
+            certificate,
+            proof,
+            validators: self.validator_set.active_validators(),
+        })
+    }
+    
+    /// State root at the latest finalized version
+    pub fn finalized_root(&self) -> RainsonetResult<StateRoot> {
+        Ok(self.finalized_state()?.root())
+    }
+    
+    /// Write every account at the finalized version to `writer`, see
+    /// [`export_accounts_json`].
+    ///
+    /// The finalized state is copied first and the copy streamed, so the
+    /// export matches the reported root without blocking finalization while
+    /// a slow client reads it.
+    pub fn export_state_json<W: Write>(&self, writer: W) -> RainsonetResult<StateExport> {
+        let state = self.finalized_state()?.snapshot();
+        export_accounts_json(&state, &self.config.address, writer)
+    }
+    
+    /// Stream the finalized state to `writer` as a snapshot a syncing node
+    /// can load with [`SnapshotManager::import_from_reader`]
+    pub fn export_snapshot<W: Write>(&self, writer: W) -> RainsonetResult<SnapshotInfo> {
+        let state = self.finalized_state()?;
+        SnapshotManager::export_to_writer(&*state, state.version(), writer)
+    }
+    
+    /// Write the finalized state into an empty persistent `store` as one
+    /// batch, flushed to disk, returning the version written
+    pub fn persist_finalized_state(
+        &self,
+        store: &PersistentStateStore,
+    ) -> RainsonetResult<StateVersion> {
+        if store.version() != StateVersion::new(0) {
+            return Err(rainsonet_core::RainsonetError::StateVersionMismatch {
+                expected: 0,
+                got: store.version().0,
+            });
+        }
+        
+        let state = self.finalized_state()?.snapshot();
+        let version = state.version();
+        if version.0 > 0 {
+            let mut diff = StateDiff::new(StateVersion::new(0), version);
+            for entry in state.iter_entries() {
+                diff.add(entry.key, entry.value);
+            }
+            store.apply_diff(&diff)?;
+        }
+        Ok(version)
+    }
+    
+    /// Save the consensus votes collected towards the next version to `path`
+    pub fn save_pending_votes(&self, path: &Path) -> RainsonetResult<()> {
+        save_vote_collections(path, &self.consensus.pending_vote_collections())
+    }
+    
+    /// Restore votes saved by [`Self::save_pending_votes`] before a restart,
+    /// returning for how many proposals
+    pub fn restore_pending_votes(&self, path: &Path) -> RainsonetResult<usize> {
+        Ok(self.consensus.restore_vote_collections(load_vote_collections(path)?))
+    }
+    
+    /// Drop state history and receipts more than the configured retention
+    /// behind the finalized version
+    fn prune_history(&self) -> RainsonetResult<()> {
+        let retention = self.config.history_retention_versions;
+        let cutoff = self.finalized_version().0.saturating_sub(retention);
+        if retention == 0 || cutoff == 0 {
+            return Ok(());
+        }
+        let cutoff = StateVersion::new(cutoff);
+        
+        // The finalized copy catches up by replaying the optimistic history,
+        // so it has to be advanced before that history is pruned
+        self.finalized_state()?.prune_history(cutoff);
+        self.state.prune_history(cutoff);
+        self.receipts.write().prune(cutoff);
+        Ok(())
+    }
+    
+    /// State at the latest finalized version.
+    ///
+    /// Versions finalized since the last call are replayed from the
+    /// optimistic store's history; if that history is gone, e.g. after a
+    /// snapshot was installed, the optimistic store is copied once it is
+    /// itself fully finalized.
+    fn finalized_state(&self) -> RainsonetResult<parking_lot::MutexGuard<'_, MemoryStateStore>> {
+        let mut finalized = self.finalized_state.lock();
+        let target = self.finalized_version().min(self.state.version());
+        let from = finalized.version();
+        if target <= from {
+            return Ok(finalized);
+        }
+        
+        match self.state.history_range(from, target) {
+            Some(diffs) => {
+                for diff in diffs {
+                    finalized.apply_batch(diff.to_changes())?;
+                }
+            }
+            None if target == self.state.version() => *finalized = self.state.snapshot(),
+            None => warn!("History no longer reaches finalized version {}", from),
+        }
+        
+        Ok(finalized)
+    }
+    
     /// Get nonce
     pub async fn get_nonce(&self, address: &Address) -> RainsonetResult<Nonce> {
         self.ledger.get_nonce(address).await
     }
     
+    /// Get the next nonce for an address, skipping nonces held by transactions still in the mempool
+    pub async fn next_nonce(&self, address: &Address) -> RainsonetResult<Nonce> {
+        let current = self.get_nonce(address).await?;
+        Ok(self.mempool.get_pending_nonce(address, current))
+    }
+    
+    /// Suggest a fee for the given tier from current mempool contents
+    pub fn suggested_fee(&self, tier: FeeTier) -> Amount {
+        self.mempool
+            .suggested_fee(tier, Amount::new(self.ledger.config().min_fee))
+    }
+    
+    /// Prepare a transfer with the fee, nonce and timestamp filled in, returning
+    /// the exact bytes the sender must sign
+    pub async fn prepare_transaction(
+        &self,
+        from: Address,
+        to: Address,
+        amount: Amount,
+    ) -> RainsonetResult<PreparedTransaction> {
+        let fee = self.suggested_fee(FeeTier::Medium);
+        let nonce = self.next_nonce(&from).await?;
+        let timestamp = Timestamp::now();
+        let signing_bytes =
+            RelyoTransaction::compute_signing_bytes(&from, &to, amount, fee, nonce, timestamp);
+        
+        Ok(PreparedTransaction {
+            from,
+            to,
+            amount,
+            fee,
+            nonce,
+            timestamp,
+            signing_bytes,
+        })
+    }
+    
     /// Submit a transaction
     pub async fn submit_transaction(&self, tx: VerifiedTransaction) -> RainsonetResult<Hash> {
+        let data = tx.tx.to_bytes();
+        let tx_id = self.add_to_mempool(tx).await?;
+        self.gossip_transaction(tx_id, data);
+        
+        // If an active validator, try to propose block. A node removed from
+        // the validator set stops proposing until it is re-added.
+        if self.is_validator() && self.consensus.is_active_validator() {
+            self.try_propose_block().await?;
+        }
+        
+        Ok(tx_id)
+    }
+    
+    /// Validate a transaction against current state and add it to the mempool
+    async fn add_to_mempool(&self, tx: VerifiedTransaction) -> RainsonetResult<Hash> {
         let tx_id = tx.tx_id;
         
         // Validate against current state
         let validator = rainsonet_relyo::RelyoTransactionValidator::new(
             self.ledger.config().clone(),
         );
-        validator.validate(&tx.tx, &*self.state).await?;
+        let added = match validator.validate(&tx.tx, &*self.state).await {
+            Ok(()) => self.mempool.add(tx)?,
+            // Ahead of the account: held until the missing nonces arrive
+            Err(rainsonet_core::RainsonetError::InvalidNonce { expected, got })
+                if got > expected =>
+            {
+                self.mempool.add_future(tx, Nonce::new(expected))?
+            }
+            Err(e) => return Err(e),
+        };
         
-        // Add to mempool
-        if !self.mempool.add(tx)? {
+        if !added {
             return Err(rainsonet_core::RainsonetError::InvalidTransaction(
                 "Failed to add to mempool".into(),
             ));
         }
         
-        info!("Transaction {} added to mempool", tx_id);
-        
-        // If validator, try to propose block
-        if self.is_validator() {
-            self.try_propose_block().await?;
-        }
+        info!("Transaction {} added to mempool", tx_id.short());
         
         Ok(tx_id)
     }
     
-    /// Try to propose a block with pending transactions
-    async fn try_propose_block(&self) -> RainsonetResult<()> {
-        // Get executable transactions
-        let transactions = self.mempool.get_executable(100);
+    /// Queue a locally submitted transaction for broadcast.
+    ///
+    /// Transactions received from the network are only marked as seen:
+    /// gossipsub already forwards them, so publishing them again would only
+    /// be deduplicated by the mesh after the work of sending them. A local
+    /// submission was just admitted to the mempool, so it is new; it is
+    /// broadcast even if the seen filter reports a false positive for it.
+    fn gossip_transaction(&self, tx_id: Hash, data: Vec<u8>) {
+        self.seen_transactions.lock().insert(&tx_id);
         
-        if transactions.is_empty() {
-            return Ok(());
+        if let Some(outbox) = &self.transaction_outbox {
+            if outbox.try_send(TransactionMessage::new(tx_id, data)).is_err() {
+                warn!("Transaction outbox full, not broadcasting {}", tx_id.short());
+            }
+        }
+    }
+    
+    /// Queue a finalized version's certificate for broadcast, unless it was
+    /// received from peers, who already relay it
+    fn gossip_certificate(&self, certificate: &FinalityCertificate) {
+        if !self.seen_certificates.lock().insert(&certificate.hash()) {
+            return;
         }
         
-        info!("Proposing block with {} transactions", transactions.len());
-        
-        // Execute transactions and collect changes
-        let mut all_changes = Vec::new();
-        let mut tx_ids = Vec::new();
-        
-        for verified in transactions {
-            match self.ledger.execute_transaction(&verified).await {
-                Ok(changes) => {
-                    all_changes.extend(changes);
-                    tx_ids.push(verified.tx_id);
-                }
-                Err(e) => {
-                    warn!("Transaction {} failed: {}", verified.tx_id, e);
-                    self.mempool.remove(&verified.tx_id);
+        if let Some(outbox) = &self.certificate_outbox {
+            if outbox.try_send(CertificateMessage::from(certificate)).is_err() {
+                warn!(
+                    "Certificate outbox full, not broadcasting version {}",
+                    certificate.state_version
+                );
+            }
+        }
+    }
+    
+    /// Send a command to the network service
+    pub fn send_network_command(&self, command: NetworkCommand) -> RainsonetResult<()> {
+        let commands = self.network_commands.as_ref().ok_or_else(|| {
+            rainsonet_core::RainsonetError::NetworkError("Network service not running".into())
+        })?;
+        commands.try_send(command).map_err(|e| {
+            rainsonet_core::RainsonetError::NetworkError(format!("Network command not sent: {}", e))
+        })
+    }
+    
+    /// Handle an event from the network service
+    pub async fn handle_network_event(&self, event: NetworkEvent) -> RainsonetResult<()> {
+        match event {
+            NetworkEvent::TransactionReceived(tx_id, data) => {
+                let verified = VerifiedTransaction::new(RelyoTransaction::from_bytes(&data)?)?;
+                if verified.tx_id != tx_id {
+                    return Err(rainsonet_core::RainsonetError::InvalidTransaction(
+                        "Transaction ID does not match contents".into(),
+                    ));
                 }
+                self.seen_transactions.lock().insert(&tx_id);
+                self.add_to_mempool(verified).await?;
+                self.retry_waiting_proposals().await;
             }
+            NetworkEvent::ProposalReceived(data) => {
+                let message: ProposalMessage = bincode::deserialize(&data)?;
+                self.process_proposal(Proposal::try_from(message)?).await?;
+            }
+            NetworkEvent::CertificateReceived(data) => {
+                let message: CertificateMessage = bincode::deserialize(&data)?;
+                let certificate = FinalityCertificate::from(message);
+                self.seen_certificates.lock().insert(&certificate.hash());
+                self.receive_certificate(certificate).await?;
+            }
+            other => debug!("Ignoring network event {:?}", other),
         }
         
-        if all_changes.is_empty() {
+        Ok(())
+    }
+    
+    /// Finalize a version on a peer's certificate, applying the certified
+    /// proposal's changes before consensus adopts the certificate.
+    ///
+    /// The changes come from the proposal as received earlier; a
+    /// certificate for a proposal never seen here is left for sync.
+    async fn receive_certificate(&self, certificate: FinalityCertificate) -> RainsonetResult<()> {
+        let version = certificate.state_version;
+        if !self.consensus.verify_certificate(&certificate)? {
+            debug!("Ignoring certificate for finalized version {}", version);
             return Ok(());
         }
         
-        // Compute new state root
-        let previous_root = *self.state_root.read();
-        let new_root = rainsonet_crypto::hashing::hash(&bincode::serialize(&all_changes)?);
-        
-        // Create proposal
-        let proposal = self.consensus.create_proposal(
-            previous_root,
-            new_root,
-            tx_ids.clone(),
-            all_changes.clone(),
-        )?;
-        
-        // For single node or when consensus is reached immediately
-        // (In production, this would wait for votes from other validators)
-        
-        // Apply changes
-        let new_version = self.state.apply_batch(all_changes).await?;
-        self.ledger.commit().await?;
+        // Our own block carries the ledger context to commit with it
+        let own_block = {
+            let mut pending = self.pending_block.lock();
+            match pending.as_ref() {
+                Some(block) if block.proposal_id == certificate.proposal_id => pending.take(),
+                _ => None,
+            }
+        };
+        if let Some(block) = own_block {
+            {
+                let _apply = self.apply_lock.lock().await;
+                self.commit_block(block).await?;
+                self.consensus.receive_certificate(certificate)?;
+            }
+            if self.is_validator() && self.consensus.is_active_validator() {
+                self.try_propose_block().await?;
+            }
+            return Ok(());
+        }
         
-        // Update state
-        *self.state_version.write() = new_version;
-        *self.state_root.write() = new_root;
+        match self.consensus.proposal_changes(&certificate.proposal_id) {
+            Some(changes) => self.apply_finalized_certificate(certificate, changes).await,
+            None => {
+                debug!(
+                    "Certificate for version {} names unknown proposal {}, leaving it to sync",
+                    version,
+                    certificate.proposal_id.short()
+                );
+                Ok(())
+            }
+        }
+    }
+    
+    /// Rebuild a received proposal's state changes from its transactions and
+    /// hand it to consensus.
+    ///
+    /// Proposal messages carry only the changes hash, so every referenced
+    /// transaction must be in the local mempool. Proposals that reference
+    /// transactions we have not seen yet wait until they arrive by gossip.
+    async fn process_proposal(&self, proposal: Proposal) -> RainsonetResult<()> {
+        self.consensus.check_validator_updates(&proposal)?;
         
-        // Remove from mempool
-        for tx_id in tx_ids {
-            self.mempool.remove(&tx_id);
+        let mut transactions = Vec::with_capacity(proposal.tx_ids.len());
+        for tx_id in &proposal.tx_ids {
+            match self.mempool.get(tx_id) {
+                Some(tx) => transactions.push(tx),
+                None => {
+                    debug!(
+                        "Proposal {} waiting for transaction {}",
+                        proposal.id.short(),
+                        tx_id.short()
+                    );
+                    let mut waiting = self.waiting_proposals.lock();
+                    if waiting.len() >= MAX_WAITING_PROPOSALS {
+                        waiting.remove(0);
+                    }
+                    waiting.push(proposal);
+                    return Ok(());
+                }
+            }
         }
         
-        info!(
-            "Block finalized: version={}, root={}, tx_count={}",
-            new_version, new_root, proposal.tx_ids.len()
-        );
+        // Executing in any other order could reach different changes
+        if !is_canonical_order(&transactions) {
+            return Err(rainsonet_core::RainsonetError::ProposalRejected(format!(
+                "Proposal {} transactions are not in canonical order",
+                proposal.id.short()
+            )));
+        }
         
-        Ok(())
+        let mut changes = self.ledger.preview_changes(&transactions).await?;
+        changes.extend(self.validator_set.deactivation_changes(
+            &proposal.inactive_validators,
+            self.config.consensus.min_validators,
+        )?);
+        changes.extend(self.validator_set.stake_changes(&proposal.stake_updates)?);
+        self.consensus.receive_proposal(proposal, changes).await
     }
     
-    /// Get keypair reference
-    pub fn keypair(&self) -> &KeyPair {
-        &self.keypair
+    /// Retry proposals that were waiting for transactions
+    async fn retry_waiting_proposals(&self) {
+        let waiting = std::mem::take(&mut *self.waiting_proposals.lock());
+        for proposal in waiting {
+            let proposal_id = proposal.id;
+            if let Err(e) = self.process_proposal(proposal).await {
+                warn!("Proposal {} rejected: {}", proposal_id.short(), e);
+            }
+        }
     }
     
-    /// Get config reference
-    pub fn config(&self) -> &NodeConfig {
-        &self.config
+    /// Apply or drop this node's pending block once consensus decides on it,
+    /// then propose the transactions that queued up meanwhile. Certificates
+    /// of finalized versions are queued for broadcast.
+    pub async fn handle_consensus_event(&self, event: &ConsensusEvent) -> RainsonetResult<()> {
+        if let ConsensusEvent::StateFinalized(_, _, certificate) = event {
+            self.gossip_certificate(certificate);
+        }
+        
+        let block = {
+            let mut pending = self.pending_block.lock();
+            let decided = match (event, pending.as_ref()) {
+                (ConsensusEvent::StateFinalized(version, _, _), Some(block)) => {
+                    block.state_version <= *version
+                }
+                (ConsensusEvent::ProposalRejected(id), Some(block))
+                | (ConsensusEvent::ProposalExpired(id), Some(block)) => block.proposal_id == *id,
+                _ => false,
+            };
+            if !decided {
+                return Ok(());
+            }
+            pending.take().expect("pending block checked")
+        };
+        
+        match event {
+            ConsensusEvent::StateFinalized(_, _, certificate)
+                if certificate.proposal_id == block.proposal_id =>
+            {
+                // Finality already covers this block, so failing to apply
+                // it is an error rather than a retry
+                self.apply_block(block).await?;
+            }
+            _ => {
+                // Rejected, expired or superseded by another proposal; the
+                // transactions stay in the mempool for the next block
+                warn!("Proposed block {} was not finalized", block.proposal_id.short());
+                self.ledger.rollback(block.context);
+            }
+        }
+        
+        if self.is_validator() && self.consensus.is_active_validator() {
+            self.try_propose_block().await?;
+        }
+        Ok(())
+    }
+    
+    /// Try to propose a block with pending transactions
+    async fn try_propose_block(&self) -> RainsonetResult<()> {
+        let mode = self.finalization_mode();
+        if mode == FinalizationMode::Voted && self.pending_block.lock().is_some() {
+            debug!("Waiting for the pending block to be finalized before proposing");
+            return Ok(());
+        }
+        
+        if self.is_waiting_for_peers() {
+            debug!(
+                "Waiting for validator peers before proposing ({} of {})",
+                self.peer_manager.validator_count(),
+                self.config.consensus.min_peers_to_propose
+            );
+            return Ok(());
+        }
+        
+        // Release held transactions whose missing nonces were finalized
+        for tx_id in self.mempool.expire_future() {
+            debug!("Transaction {} expired waiting for missing nonces", tx_id.short());
+        }
+        for sender in self.mempool.future_senders() {
+            let next_nonce = self.ledger.get_nonce(&sender).await?;
+            self.mempool.promote(&sender, Some(next_nonce));
+        }
+        
+        for _ in 0..MAX_PROPOSAL_ATTEMPTS {
+            let built = match self.execute_block().await? {
+                Some(built) => built,
+                None => return Ok(()),
+            };
+            if self.propose_block(built, mode).await? {
+                return Ok(());
+            }
+            debug!("State moved while building the block, retrying on fresh state");
+        }
+        
+        warn!("State kept moving while proposing, transactions wait for the next block");
+        Ok(())
+    }
+    
+    /// Propose an executed block, applying it if it is already final.
+    ///
+    /// Returns `false` without proposing if the state root moved since the
+    /// block was executed, so no vote or certificate is ever issued for a
+    /// block on a stale base.
+    async fn propose_block(
+        &self,
+        built: ExecutedBlock,
+        mode: FinalizationMode,
+    ) -> RainsonetResult<bool> {
+        // Held until the block is applied or parked, so the root checked
+        // here is the one it is applied on
+        let _apply = self.apply_lock.lock().await;
+        if *self.state_root.read() != built.previous_root {
+            self.ledger.rollback(built.context);
+            return Ok(false);
+        }
+        
+        let proposal = self
+            .consensus
+            .create_proposal_updating_validators(
+                built.previous_root,
+                built.new_root,
+                built.tx_ids.clone(),
+                built.inactive_validators,
+                built.stake_updates.clone(),
+                built.changes.clone(),
+            )
+            .await?;
+        self.consensus.vote_on_proposal(&proposal.id, true).await?;
+        let block = PendingBlock {
+            proposal_id: proposal.id,
+            state_version: proposal.state_version,
+            previous_root: built.previous_root,
+            new_root: built.new_root,
+            tx_ids: built.tx_ids,
+            stake_updates: built.stake_updates,
+            changes: built.changes,
+            context: built.context,
+        };
+        
+        // The proposer's own vote finalizes when it is the only validator
+        let finalized = self
+            .consensus
+            .get_certificate(block.state_version)
+            .is_some_and(|c| c.proposal_id == block.proposal_id);
+        if mode == FinalizationMode::Instant || finalized {
+            self.commit_block(block).await?;
+            return Ok(true);
+        }
+        
+        info!(
+            "Block proposed: version={}, tx_count={}, awaiting votes",
+            block.state_version, block.tx_ids.len()
+        );
+        *self.pending_block.lock() = Some(block);
+        Ok(true)
+    }
+    
+    /// Execute the executable mempool transactions against the current
+    /// state, or `None` if there is nothing to propose
+    async fn execute_block(&self) -> RainsonetResult<Option<ExecutedBlock>> {
+        // Get executable transactions
+        let transactions = self.mempool.get_executable(100);
+        
+        if transactions.is_empty() {
+            return Ok(None);
+        }
+        
+        info!("Proposing block with {} transactions", transactions.len());
+        
+        // Read before executing, so a state change during execution shows
+        // up as a moved root
+        let previous_root = *self.state_root.read();
+        
+        // Execute transactions and collect changes
+        let mut all_changes = Vec::new();
+        let mut tx_ids = Vec::new();
+        let mut context = ProposalContext::new();
+        
+        for verified in transactions {
+            match self.ledger.execute_transaction(&mut context, &verified).await {
+                Ok(changes) => {
+                    all_changes.extend(changes);
+                    tx_ids.push(verified.tx_id);
+                }
+                Err(rainsonet_core::RainsonetError::InvalidNonce { expected, got })
+                    if got > expected =>
+                {
+                    // Valid once the nonces before it arrive
+                    debug!(
+                        "Transaction {} waits for nonce {} of its sender",
+                        verified.tx_id.short(),
+                        expected
+                    );
+                    self.mempool.park(&verified.tx_id);
+                }
+                Err(e) => {
+                    warn!("Transaction {} failed: {}", verified.tx_id.short(), e);
+                    self.mempool.remove(&verified.tx_id);
+                }
+            }
+        }
+        
+        let stake_updates = self.pending_stake_updates.lock().clone();
+        if all_changes.is_empty() && stake_updates.is_empty() {
+            return Ok(None);
+        }
+        
+        // Deactivations for inactivity ride along after the transactions
+        let inactive_validators = self.consensus.inactive_validators();
+        all_changes.extend(
+            self.validator_set
+                .deactivation_changes(&inactive_validators, self.config.consensus.min_validators)?,
+        );
+        
+        // Then the queued stakes, except of validators deactivated above,
+        // whose records would otherwise undo the deactivation
+        let stake_updates: Vec<_> = stake_updates
+            .into_iter()
+            .filter(|(node_id, _)| !inactive_validators.contains(node_id))
+            .collect();
+        all_changes.extend(self.validator_set.stake_changes(&stake_updates)?);
+        
+        // Merkle root after the changes, merged over the cached leaves rather
+        // than a copy of the state, so certificates commit to the root that
+        // account proofs verify against
+        let new_root = self.state.root_after(&all_changes);
+        
+        Ok(Some(ExecutedBlock {
+            previous_root,
+            new_root,
+            tx_ids,
+            inactive_validators,
+            stake_updates,
+            changes: all_changes,
+            context,
+        }))
+    }
+    
+    /// Apply a finalized block of this node's
+    async fn apply_block(&self, block: PendingBlock) -> RainsonetResult<()> {
+        let _apply = self.apply_lock.lock().await;
+        self.commit_block(block).await
+    }
+    
+    /// Apply a block's changes and commit the ledger, with `apply_lock` held.
+    ///
+    /// Fails, applying nothing, if the state root is no longer the one the
+    /// block was built on.
+    async fn commit_block(&self, block: PendingBlock) -> RainsonetResult<()> {
+        let current_root = *self.state_root.read();
+        if current_root != block.previous_root {
+            self.ledger.rollback(block.context);
+            return Err(rainsonet_core::RainsonetError::StateCorruption(format!(
+                "Block {} was built on root {}, state is at {}",
+                block.proposal_id.short(),
+                block.previous_root,
+                current_root
+            )));
+        }
+        
+        let records = validator_records(&block.changes);
+        let new_version = StateMutator::apply_batch(&*self.state, block.changes).await?;
+        self.ledger.commit(block.context).await?;
+        self.apply_validator_records(&records)?;
+        self.pending_stake_updates
+            .lock()
+            .retain(|update| !block.stake_updates.contains(update));
+        
+        // Update state
+        *self.state_version.write() = new_version;
+        *self.state_root.write() = block.new_root;
+        self.state_announcer.announce(new_version, block.new_root);
+        
+        // Remove from mempool and record receipts
+        let receipts = block
+            .tx_ids
+            .iter()
+            .map(|tx_id| (*tx_id, self.mempool.remove(tx_id).map(|entry| entry.tx.tx.timestamp)))
+            .collect();
+        self.receipts.write().record(new_version, receipts);
+        self.prune_history()?;
+        
+        info!(
+            "Block finalized: version={}, root={}, tx_count={}",
+            new_version, block.new_root, block.tx_ids.len()
+        );
+        
+        Ok(())
+    }
+    
+    /// Apply a block finalized elsewhere, as followers do to track the
+    /// chain without proposing.
+    ///
+    /// The certificate must verify against the validator set and finalize
+    /// the version after the local one, and `changes` must lead from the
+    /// local state to the certified root. Nothing is modified unless all of
+    /// that holds. The state is written before consensus records the
+    /// certificate, so finality never runs ahead of the applied state.
+    pub async fn apply_finalized_certificate(
+        &self,
+        certificate: FinalityCertificate,
+        changes: Vec<StateChange>,
+    ) -> RainsonetResult<()> {
+        certificate.verify_full(&self.validator_set)?;
+        
+        let _apply = self.apply_lock.lock().await;
+        let expected = self.finalized_version().next();
+        let local = self.state.version();
+        if certificate.state_version != expected || local.next() != expected {
+            return Err(rainsonet_core::RainsonetError::StateVersionMismatch {
+                expected: local.next().0,
+                got: certificate.state_version.0,
+            });
+        }
+        
+        if self.state.root_after(&changes) != certificate.state_root {
+            return Err(rainsonet_core::RainsonetError::ProposalRejected(format!(
+                "Changes for version {} do not reach the certified root",
+                certificate.state_version
+            )));
+        }
+        
+        // Everything consensus checks before adopting the certificate, e.g.
+        // that it extends the finalized root, is checked before writing
+        self.consensus.verify_certificate(&certificate)?;
+        
+        let root = certificate.state_root;
+        let records = validator_records(&changes);
+        let new_version = StateMutator::apply_batch(&*self.state, changes).await?;
+        self.apply_validator_records(&records)?;
+        
+        *self.state_version.write() = new_version;
+        *self.state_root.write() = root;
+        
+        // Held `apply_lock` keeps other certificates out since the check; a
+        // vote finalizing the same version meanwhile leaves nothing to record
+        if !self.consensus.receive_certificate(certificate)? {
+            debug!("Version {} finalized by votes while it was applied", new_version);
+        }
+        self.state_announcer.announce(new_version, root);
+        self.prune_history()?;
+        
+        info!("Applied finalized version {}, root={}", new_version, root);
+        Ok(())
+    }
+    
+    /// Set this node's own stake in its next proposal; peers reject stake
+    /// updates for anyone but a proposal's proposer. The validator set changes
+    /// once that proposal is finalized; a later update replaces one still
+    /// queued.
+    pub fn queue_stake_update(&self, node_id: NodeId, stake: u128) -> RainsonetResult<()> {
+        if node_id != self.keypair.node_id() {
+            return Err(rainsonet_core::RainsonetError::ValidatorSetError(format!(
+                "Only this node's own stake can be updated, not {}",
+                node_id.short()
+            )));
+        }
+        self.validator_set.stake_change(&node_id, stake)?;
+        let mut pending = self.pending_stake_updates.lock();
+        pending.retain(|(queued, _)| *queued != node_id);
+        pending.push((node_id, stake));
+        Ok(())
+    }
+    
+    /// Update the validator set from finalized validator records
+    fn apply_validator_records(&self, records: &[StateChange]) -> RainsonetResult<()> {
+        for node_id in self.validator_set.apply_records(records)? {
+            warn!(
+                "Validator {} deactivated for inactivity, quorum is now {}",
+                node_id.short(),
+                self.validator_set.required_votes()
+            );
+        }
+        Ok(())
+    }
+    
+    /// Get keypair reference
+    pub fn keypair(&self) -> &KeyPair {
+        &self.keypair
+    }
+    
+    /// Get config reference
+    pub fn config(&self) -> &NodeConfig {
+        &self.config
+    }
+    
+    /// Get the announcer that gossips finalized versions to peers
+    pub fn state_announcer(&self) -> &StateAnnouncer {
+        &self.state_announcer
     }
     
     /// Get ledger reference
@@ -263,16 +1466,57 @@ impl NodeRuntime {
     }
 }
 
+/// Offers pending mempool transactions to newly connected peers
+impl TransactionSource for NodeRuntime {
+    fn pending_tx_ids(&self, limit: usize) -> Vec<Hash> {
+        self.mempool
+            .get_highest_priority(limit)
+            .into_iter()
+            .map(|tx| tx.tx_id)
+            .collect()
+    }
+    
+    fn has_transaction(&self, tx_id: &Hash) -> bool {
+        self.mempool.contains(tx_id)
+    }
+    
+    fn transaction_data(&self, tx_id: &Hash) -> Option<Vec<u8>> {
+        self.mempool.get(tx_id).map(|tx| tx.tx.to_bytes())
+    }
+}
+
+/// Answers peers' sync requests from the finalized state, so a syncing node
+/// never receives versions that could still be rolled back
+impl SyncSource for NodeRuntime {
+    fn sync_response(&self, request: &SyncRequestMessage) -> Option<SyncResponseMessage> {
+        let finalized = match self.finalized_state() {
+            Ok(finalized) => finalized,
+            Err(e) => {
+                warn!("Cannot serve sync request: {}", e);
+                return None;
+            }
+        };
+        match build_sync_response(&finalized, request, SYNC_WINDOW_SIZE) {
+            Ok(response) => Some(response),
+            Err(e) => {
+                warn!("Cannot serve sync request from {}: {}", request.from_version, e);
+                None
+            }
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use rainsonet_core::Amount;
+    use rainsonet_p2p::PeerInfo;
     
     fn create_test_runtime() -> NodeRuntime {
         let config = NodeConfig {
             consensus: rainsonet_core::ConsensusConfig {
                 is_validator: true,
-                ..Default::default()
+                ..rainsonet_core::ConsensusConfig::devnet()
             },
             ..Default::default()
         };
@@ -291,6 +1535,77 @@ mod tests {
         assert_eq!(runtime.state_version().0, 0);
     }
     
+    #[tokio::test]
+    async fn test_validator_outside_own_set_fails_to_start() {
+        let config = NodeConfig {
+            consensus: rainsonet_core::ConsensusConfig {
+                is_validator: true,
+                ..rainsonet_core::ConsensusConfig::devnet()
+            },
+            ..Default::default()
+        };
+        let keypair = KeyPair::generate();
+        let other = KeyPair::generate();
+        let genesis = GenesisConfig::testnet().add_validator(&other.address().to_hex());
+        
+        let node = crate::node::RainsonetNode::new(config.clone(), keypair.clone(), genesis.clone());
+        assert!(node.runtime().ensure_validator_membership().is_err());
+        assert!(node.start(genesis.clone()).await.is_err());
+        
+        // Listed in genesis, the same node starts as a validator
+        let genesis = genesis.add_validator(&keypair.address().to_hex());
+        let runtime = NodeRuntime::new(config, keypair, genesis);
+        runtime.ensure_validator_membership().unwrap();
+    }
+    
+    #[tokio::test]
+    async fn test_genesis_validator_keys_form_the_set() {
+        let keypair = KeyPair::generate();
+        let other = KeyPair::generate();
+        let genesis = GenesisConfig::testnet()
+            .try_add_validator_key(&keypair.public_key().to_hex(), 3_000)
+            .unwrap()
+            .try_add_validator_key(&other.public_key().to_hex(), 2_000)
+            .unwrap();
+        
+        // Followers know the set too
+        let follower =
+            NodeRuntime::new(NodeConfig::devnet(), KeyPair::generate(), genesis.clone());
+        assert_eq!(follower.validator_set.active_count(), 2);
+        assert_eq!(follower.finalization_mode(), FinalizationMode::Voted);
+        
+        let mut config = NodeConfig::devnet();
+        config.consensus.is_validator = true;
+        let runtime = NodeRuntime::new(config, keypair.clone(), genesis);
+        runtime.ensure_validator_membership().unwrap();
+        assert_eq!(runtime.validator_set.active_count(), 2);
+        assert_eq!(runtime.validator_set.get_validator(&keypair.node_id()).unwrap().stake, 3_000);
+    }
+    
+    #[tokio::test]
+    async fn test_genesis_requires_matching_hash_algorithm() {
+        // Tests never change the process-wide algorithm from BLAKE3
+        let mut genesis = GenesisConfig::devnet();
+        genesis.hash_algorithm = HashAlgorithm::Sha256;
+        let runtime = NodeRuntime::new(NodeConfig::devnet(), KeyPair::generate(), genesis.clone());
+        assert_eq!(runtime.hash_algorithm(), HashAlgorithm::Sha256);
+        assert!(runtime.initialize_genesis(genesis).await.is_err());
+        assert_eq!(default_hash_algorithm(), HashAlgorithm::Blake3);
+    }
+    
+    #[test]
+    fn test_genesis_hash_follows_genesis() {
+        let genesis = GenesisConfig::devnet();
+        let devnet = NodeRuntime::new(NodeConfig::devnet(), KeyPair::generate(), genesis);
+        assert_eq!(devnet.genesis_hash(), compute_genesis_hash(&GenesisConfig::devnet()).unwrap());
+        assert_ne!(devnet.genesis_hash(), Hash::ZERO);
+        
+        let mut pricier = GenesisConfig::devnet();
+        pricier.relyo_config.min_fee = 1_000;
+        let other = NodeRuntime::new(NodeConfig::devnet(), KeyPair::generate(), pricier);
+        assert_ne!(other.genesis_hash(), devnet.genesis_hash());
+    }
+    
     #[tokio::test]
     async fn test_genesis_initialization() {
         let runtime = create_test_runtime();
@@ -305,6 +1620,136 @@ mod tests {
         assert_eq!(balance.0, Amount::from_relyo(1000).0);
     }
     
+    #[tokio::test]
+    async fn test_export_state_json() {
+        let runtime = create_test_runtime();
+        let funded: Vec<KeyPair> = (0..3).map(|_| KeyPair::generate()).collect();
+        let mut genesis = GenesisConfig::devnet();
+        for (kp, relyo) in funded.iter().zip([100, 200, 300]) {
+            genesis = genesis.add_allocation(&kp.address().to_hex(), relyo);
+        }
+        runtime.initialize_genesis(genesis).await.unwrap();
+        runtime.submit_transaction(signed_transfer(&funded[0])).await.unwrap();
+        
+        let mut out = Vec::new();
+        let export = runtime.export_state_json(&mut out).unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&out).unwrap();
+        assert_eq!(export.version, runtime.finalized_version());
+        assert_eq!(export.state_root, runtime.finalized_root().unwrap());
+        assert_eq!(json["version"], export.version.0);
+        assert_eq!(json["state_root"], export.state_root.to_hex());
+        
+        let accounts: Vec<ExportedAccount> =
+            serde_json::from_value(json["accounts"].clone()).unwrap();
+        assert_eq!(accounts.len(), export.accounts);
+        for kp in &funded {
+            let address = kp.address();
+            let account = accounts.iter().find(|a| a.address == address.to_hex()).unwrap();
+            let expected = runtime.get_account_in(&address, StateView::Finalized).await.unwrap();
+            assert_eq!(account.balance, expected.balance);
+            assert_eq!(account.nonce, expected.nonce.0);
+        }
+        
+        // The transfer was finalized before the export
+        let sender = funded[0].address().to_hex();
+        assert_eq!(accounts.iter().find(|a| a.address == sender).unwrap().nonce, 1);
+    }
+    
+    #[tokio::test]
+    async fn test_export_snapshot_round_trips_finalized_state() {
+        let runtime = create_test_runtime();
+        let sender = KeyPair::generate();
+        let genesis = GenesisConfig::devnet().add_allocation(&sender.address().to_hex(), 1000);
+        runtime.initialize_genesis(genesis).await.unwrap();
+        runtime.submit_transaction(signed_transfer(&sender)).await.unwrap();
+        
+        let mut out = Vec::new();
+        let info = runtime.export_snapshot(&mut out).unwrap();
+        assert_eq!(info.version, runtime.finalized_version());
+        assert_eq!(info.root, runtime.finalized_root().unwrap());
+        
+        let imported = MemoryStateStore::new();
+        assert_eq!(SnapshotManager::import_from_reader(out.as_slice(), &imported).unwrap(), info);
+        assert_eq!(imported.version(), info.version);
+        assert_eq!(imported.compute_root().unwrap(), info.root);
+        
+        // `export-accounts` reads the snapshot back into the same accounts
+        let mut from_snapshot = Vec::new();
+        export_accounts_json(&imported, &runtime.config().address, &mut from_snapshot).unwrap();
+        let mut from_runtime = Vec::new();
+        runtime.export_state_json(&mut from_runtime).unwrap();
+        let parse = |json: &[u8]| serde_json::from_slice::<serde_json::Value>(json).unwrap();
+        assert_eq!(parse(&from_snapshot)["state_root"], parse(&from_runtime)["state_root"]);
+        assert_eq!(
+            parse(&from_snapshot)["accounts"].as_array().unwrap().len(),
+            parse(&from_runtime)["accounts"].as_array().unwrap().len()
+        );
+    }
+    
+    #[tokio::test]
+    async fn test_persist_finalized_state() {
+        let runtime = create_test_runtime();
+        let sender = KeyPair::generate();
+        let genesis = GenesisConfig::devnet().add_allocation(&sender.address().to_hex(), 1000);
+        runtime.initialize_genesis(genesis).await.unwrap();
+        runtime.submit_transaction(signed_transfer(&sender)).await.unwrap();
+        
+        let path = std::env::temp_dir().join(format!("rainsonet-state-{}", std::process::id()));
+        let store = PersistentStateStore::open_with(&path, runtime.config().storage_backend)
+            .unwrap();
+        let version = runtime.persist_finalized_state(&store).unwrap();
+        assert_eq!(version, runtime.finalized_version());
+        drop(store);
+        
+        // Reopened from disk, the store holds the finalized state
+        let store = PersistentStateStore::open(&path).unwrap();
+        assert_eq!(store.version(), version);
+        assert_eq!(store.compute_root().unwrap(), runtime.finalized_root().unwrap());
+        
+        // Only an empty store is written to
+        assert!(runtime.persist_finalized_state(&store).is_err());
+        drop(store);
+        let _ = std::fs::remove_dir_all(&path);
+    }
+    
+    #[tokio::test]
+    async fn test_sync_request_served_from_finalized_state() {
+        let runtime = create_test_runtime();
+        let sender = KeyPair::generate();
+        let genesis = GenesisConfig::devnet().add_allocation(&sender.address().to_hex(), 1000);
+        runtime.initialize_genesis(genesis).await.unwrap();
+        runtime.submit_transaction(signed_transfer(&sender)).await.unwrap();
+        
+        let requester = KeyPair::generate().node_id();
+        let head = runtime.finalized_version();
+        let request = SyncRequestMessage::window(StateVersion::new(0), head, requester);
+        let response = runtime.sync_response(&request).unwrap();
+        assert_eq!(response.head_version, head);
+        assert_eq!(response.state_root, runtime.finalized_root().unwrap());
+        
+        // Replaying the response reproduces the finalized state
+        let store = MemoryStateStore::new();
+        let mut session = crate::SyncSession::new(requester, head, SYNC_WINDOW_SIZE);
+        session.apply_response(&store, &response).unwrap();
+        assert_eq!(store.compute_root().unwrap(), runtime.finalized_root().unwrap());
+    }
+    
+    #[test]
+    fn test_receipts_pruned_by_version() {
+        let mut receipts = Receipts::default();
+        let [old, kept, again] = [1u8, 2, 3].map(|b| Hash::from_bytes([b; 32]));
+        receipts.record(StateVersion::new(1), vec![(old, None), (again, None)]);
+        receipts.record(StateVersion::new(2), vec![(kept, None)]);
+        receipts.record(StateVersion::new(3), vec![(again, None)]);
+        
+        receipts.prune(StateVersion::new(2));
+        assert!(!receipts.by_tx.contains_key(&old));
+        assert_eq!(receipts.by_tx[&kept].0, StateVersion::new(2));
+        // Included again later, the transaction keeps its newer receipt
+        assert_eq!(receipts.by_tx[&again].0, StateVersion::new(3));
+        assert_eq!(receipts.by_version.len(), 2);
+    }
+    
     #[tokio::test]
     async fn test_transaction_submission() {
         let runtime = create_test_runtime();
@@ -334,4 +1779,716 @@ mod tests {
         // (Transaction might be immediately processed in single-validator mode)
         assert!(tx_id != Hash::ZERO);
     }
+    
+    #[tokio::test]
+    async fn test_optimistic_and_finalized_views_converge() {
+        // Instant finalization applies the block before the other validator votes
+        let config = NodeConfig {
+            consensus: rainsonet_core::ConsensusConfig {
+                is_validator: true,
+                finalization_mode: Some(FinalizationMode::Instant),
+                ..rainsonet_core::ConsensusConfig::devnet()
+            },
+            ..Default::default()
+        };
+        let runtime = NodeRuntime::new(config, KeyPair::generate(), GenesisConfig::devnet());
+        let other = KeyPair::generate();
+        runtime
+            .validator_set
+            .add_validator(ValidatorInfo::new(other.node_id(), other.public_key(), 1000));
+        let sender = KeyPair::generate();
+        runtime
+            .initialize_genesis(GenesisConfig::devnet().add_allocation(&sender.address().to_hex(), 1000))
+            .await
+            .unwrap();
+        runtime.start_event_fanout();
+        let mut events = runtime.subscribe_consensus_events();
+        
+        // With two validators the proposal needs the other's vote
+        runtime.submit_transaction(signed_transfer(&sender)).await.unwrap();
+        let proposal_id = loop {
+            let event = tokio::time::timeout(std::time::Duration::from_secs(5), events.recv())
+                .await
+                .unwrap()
+                .unwrap();
+            if let ConsensusEvent::ProposalCreated(id) = event {
+                break id;
+            }
+        };
+        
+        assert_eq!(runtime.state_version().0, 1);
+        assert_eq!(runtime.finalized_version().0, 0);
+        let address = sender.address();
+        let optimistic = runtime.get_balance_in(&address, StateView::Optimistic).await.unwrap();
+        let finalized = runtime.get_balance_in(&address, StateView::Finalized).await.unwrap();
+        assert_eq!(finalized, Amount::from_relyo(1000));
+        assert!(optimistic < finalized);
+        
+        let vote = rainsonet_consensus::Vote::new(
+            proposal_id,
+            other.node_id(),
+            true,
+            StateVersion::new(0),
+            Hash::ZERO,
+            |msg| rainsonet_crypto::signing::sign(&other, msg),
+        );
+        runtime.consensus.receive_vote(vote).await.unwrap();
+        
+        assert_eq!(runtime.finalized_version(), runtime.state_version());
+        assert_eq!(
+            runtime.get_balance_in(&address, StateView::Finalized).await.unwrap(),
+            optimistic
+        );
+    }
+    
+    #[tokio::test]
+    async fn test_confirmations_grow_with_finalized_versions() {
+        let runtime = create_test_runtime();
+        let sender = KeyPair::generate();
+        runtime
+            .initialize_genesis(GenesisConfig::devnet().add_allocation(&sender.address().to_hex(), 1000))
+            .await
+            .unwrap();
+        
+        let transfer = |nonce| {
+            let tx = RelyoTransaction::new(
+                sender.address(),
+                KeyPair::generate().address(),
+                Amount::from_relyo(5),
+                Amount::ZERO,
+                Nonce::new(nonce),
+                &sender,
+            )
+            .unwrap();
+            VerifiedTransaction::new(tx).unwrap()
+        };
+        
+        let first = runtime.submit_transaction(transfer(0)).await.unwrap();
+        let receipt = runtime.transaction_receipt(&first).unwrap();
+        assert_eq!(receipt.version, StateVersion::new(1));
+        assert_eq!(receipt.confirmations, Some(0));
+        
+        runtime.submit_transaction(transfer(1)).await.unwrap();
+        runtime.submit_transaction(transfer(2)).await.unwrap();
+        assert_eq!(runtime.transaction_receipt(&first).unwrap().confirmations, Some(2));
+        assert!(runtime.transaction_receipt(&Hash::ZERO).is_none());
+    }
+    
+    #[tokio::test]
+    async fn test_validator_waits_for_peers_before_proposing() {
+        let config = NodeConfig {
+            consensus: rainsonet_core::ConsensusConfig {
+                is_validator: true,
+                min_peers_to_propose: 2,
+                ..rainsonet_core::ConsensusConfig::devnet()
+            },
+            ..Default::default()
+        };
+        let runtime = NodeRuntime::new(config, KeyPair::generate(), GenesisConfig::devnet());
+        let sender = KeyPair::generate();
+        runtime
+            .initialize_genesis(GenesisConfig::devnet().add_allocation(&sender.address().to_hex(), 1000))
+            .await
+            .unwrap();
+        
+        runtime.submit_transaction(signed_transfer(&sender)).await.unwrap();
+        assert!(runtime.is_waiting_for_peers());
+        assert_eq!(runtime.state_version().0, 0);
+        
+        // Non-validator peers don't count towards the gate
+        runtime.peer_manager().add_peer(PeerInfo::new(KeyPair::generate().node_id(), false));
+        runtime.peer_manager().add_peer(PeerInfo::new(KeyPair::generate().node_id(), true));
+        runtime.try_propose_block().await.unwrap();
+        assert_eq!(runtime.state_version().0, 0);
+        
+        runtime.peer_manager().add_peer(PeerInfo::new(KeyPair::generate().node_id(), true));
+        assert!(!runtime.is_waiting_for_peers());
+        runtime.try_propose_block().await.unwrap();
+        assert_eq!(runtime.state_version().0, 1);
+    }
+    
+    #[tokio::test]
+    async fn test_prepare_transaction() {
+        // Non-validator so submitted transactions stay pending in the mempool
+        let runtime = NodeRuntime::new(NodeConfig::devnet(), KeyPair::generate(), GenesisConfig::devnet());
+        let sender = KeyPair::generate();
+        let recipient = KeyPair::generate();
+        
+        let genesis = GenesisConfig::devnet()
+            .add_allocation(&sender.address().to_hex(), 1000);
+        runtime.initialize_genesis(genesis).await.unwrap();
+        
+        let prepared = runtime
+            .prepare_transaction(sender.address(), recipient.address(), Amount::from_relyo(10))
+            .await
+            .unwrap();
+        assert_eq!(prepared.nonce, Nonce::new(0));
+        assert_eq!(prepared.fee, runtime.suggested_fee(FeeTier::Medium));
+        
+        // Signing the prepared bytes yields a transaction the node accepts
+        let signature = rainsonet_crypto::signing::sign(&sender, &prepared.signing_bytes);
+        let tx = RelyoTransaction {
+            from: prepared.from,
+            to: prepared.to,
+            amount: prepared.amount,
+            fee: prepared.fee,
+            nonce: prepared.nonce,
+            timestamp: prepared.timestamp,
+            public_key: sender.public_key(),
+            signature,
+        };
+        let verified = VerifiedTransaction::new(tx).unwrap();
+        let tx_id = runtime.submit_transaction(verified).await.unwrap();
+        assert!(runtime.is_transaction_pending(&tx_id));
+        
+        // The pending transaction is accounted for in the next nonce
+        let next = runtime
+            .prepare_transaction(sender.address(), recipient.address(), Amount::from_relyo(10))
+            .await
+            .unwrap();
+        assert_eq!(next.nonce, Nonce::new(1));
+    }
+    
+    #[tokio::test]
+    async fn test_pending_for_lists_sender_transactions_in_nonce_order() {
+        let runtime = NodeRuntime::new(NodeConfig::devnet(), KeyPair::generate(), GenesisConfig::devnet());
+        let sender = KeyPair::generate();
+        let recipient = KeyPair::generate();
+        
+        let genesis = GenesisConfig::devnet()
+            .add_allocation(&sender.address().to_hex(), 1000);
+        runtime.initialize_genesis(genesis).await.unwrap();
+        
+        let transfer = |nonce| {
+            let tx = RelyoTransaction::new(
+                sender.address(),
+                recipient.address(),
+                Amount::from_relyo(1),
+                Amount::ZERO,
+                Nonce::new(nonce),
+                &sender,
+            )
+            .unwrap();
+            VerifiedTransaction::new(tx).unwrap()
+        };
+        
+        // The follow-up queues behind the first, which is the only one valid against state
+        runtime.mempool.add(transfer(1)).unwrap();
+        runtime.submit_transaction(transfer(0)).await.unwrap();
+        
+        let pending = runtime.pending_for(&sender.address());
+        let nonces: Vec<_> = pending.iter().map(|e| e.tx.tx.nonce).collect();
+        assert_eq!(nonces, vec![Nonce::new(0), Nonce::new(1)]);
+        assert!(runtime.pending_for(&recipient.address()).is_empty());
+    }
+    
+    #[tokio::test]
+    async fn test_received_transaction_is_not_rebroadcast() {
+        let (outbox, mut broadcasts) = rainsonet_p2p::create_transaction_outbox();
+        let runtime = NodeRuntime::new(NodeConfig::devnet(), KeyPair::generate(), GenesisConfig::devnet())
+            .with_transaction_outbox(outbox);
+        let remote_sender = KeyPair::generate();
+        let local_sender = KeyPair::generate();
+        let genesis = GenesisConfig::devnet()
+            .add_allocation(&remote_sender.address().to_hex(), 1000)
+            .add_allocation(&local_sender.address().to_hex(), 1000);
+        runtime.initialize_genesis(genesis).await.unwrap();
+        
+        let remote = signed_transfer(&remote_sender);
+        runtime
+            .handle_network_event(NetworkEvent::TransactionReceived(remote.tx_id, remote.tx.to_bytes()))
+            .await
+            .unwrap();
+        assert!(runtime.is_transaction_pending(&remote.tx_id));
+        
+        let local = signed_transfer(&local_sender);
+        runtime.submit_transaction(local.clone()).await.unwrap();
+        
+        let mut broadcast_ids = Vec::new();
+        while let Ok(msg) = broadcasts.try_recv() {
+            broadcast_ids.push(msg.tx_id);
+        }
+        assert_eq!(broadcast_ids, vec![local.tx_id]);
+    }
+    
+    #[tokio::test]
+    async fn test_local_transaction_broadcast_despite_seen_filter() {
+        let (outbox, mut broadcasts) = rainsonet_p2p::create_transaction_outbox();
+        let runtime = NodeRuntime::new(NodeConfig::devnet(), KeyPair::generate(), GenesisConfig::devnet())
+            .with_transaction_outbox(outbox);
+        let sender = KeyPair::generate();
+        let genesis = GenesisConfig::devnet().add_allocation(&sender.address().to_hex(), 1000);
+        runtime.initialize_genesis(genesis).await.unwrap();
+        
+        // As if the bloom filter reported a false positive for it
+        let local = signed_transfer(&sender);
+        runtime.seen_transactions.lock().insert(&local.tx_id);
+        runtime.submit_transaction(local.clone()).await.unwrap();
+        
+        assert_eq!(broadcasts.try_recv().unwrap().tx_id, local.tx_id);
+    }
+    
+    /// Runtime that only follows `proposer`'s proposals
+    fn create_follower(proposer: &KeyPair) -> NodeRuntime {
+        let follower = NodeRuntime::new(NodeConfig::devnet(), KeyPair::generate(), GenesisConfig::devnet());
+        follower.validator_set.add_validator(ValidatorInfo::new(
+            proposer.node_id(),
+            proposer.public_key(),
+            1000,
+        ));
+        follower
+    }
+    
+    fn signed_transfer(sender: &KeyPair) -> VerifiedTransaction {
+        let tx = RelyoTransaction::new(
+            sender.address(),
+            KeyPair::generate().address(),
+            Amount::from_relyo(5),
+            Amount::ZERO,
+            Nonce::new(0),
+            sender,
+        )
+        .unwrap();
+        VerifiedTransaction::new(tx).unwrap()
+    }
+    
+    #[tokio::test]
+    async fn test_instant_mode_applies_on_proposal() {
+        let runtime = create_test_runtime();
+        let sender = KeyPair::generate();
+        runtime
+            .initialize_genesis(GenesisConfig::devnet().add_allocation(&sender.address().to_hex(), 1000))
+            .await
+            .unwrap();
+        assert_eq!(runtime.finalization_mode(), FinalizationMode::Instant);
+        
+        let tx_id = runtime.submit_transaction(signed_transfer(&sender)).await.unwrap();
+        
+        assert_eq!(runtime.state_version(), StateVersion::new(1));
+        assert_eq!(runtime.transaction_receipt(&tx_id).unwrap().version, StateVersion::new(1));
+        assert_eq!(runtime.mempool_size(), 0);
+    }
+    
+    #[tokio::test]
+    async fn test_queued_stake_applied_with_block() {
+        let runtime = create_test_runtime();
+        let sender = KeyPair::generate();
+        runtime
+            .initialize_genesis(GenesisConfig::devnet().add_allocation(&sender.address().to_hex(), 1000))
+            .await
+            .unwrap();
+        let validator = runtime.keypair.node_id();
+        assert!(runtime.queue_stake_update(KeyPair::generate().node_id(), 5_000).is_err());
+        runtime.queue_stake_update(validator, 4_000).unwrap();
+        runtime.queue_stake_update(validator, 5_000).unwrap();
+        
+        // Nothing changes until a block carrying the update is applied
+        assert_ne!(runtime.validator_set.get_validator(&validator).unwrap().stake, 5_000);
+        runtime.submit_transaction(signed_transfer(&sender)).await.unwrap();
+        
+        assert_eq!(runtime.validator_set.get_validator(&validator).unwrap().stake, 5_000);
+        assert!(runtime.pending_stake_updates.lock().is_empty());
+    }
+    
+    #[tokio::test]
+    async fn test_stale_block_not_proposed() {
+        let runtime = create_test_runtime();
+        let sender = KeyPair::generate();
+        runtime
+            .initialize_genesis(GenesisConfig::devnet().add_allocation(&sender.address().to_hex(), 1000))
+            .await
+            .unwrap();
+        let tx = signed_transfer(&sender);
+        let tx_id = tx.tx_id;
+        runtime.mempool.add(tx).unwrap();
+        let stale = runtime.execute_block().await.unwrap().unwrap();
+        
+        // Another block lands between executing this one and proposing it
+        let moved_root = move_state_root(&runtime).await;
+        
+        assert!(!runtime.propose_block(stale, FinalizationMode::Instant).await.unwrap());
+        assert!(runtime.consensus.get_certificate(StateVersion::new(1)).is_none());
+        assert_eq!(runtime.state_version(), StateVersion::new(1));
+        assert_eq!(runtime.state_root(), moved_root);
+        assert!(runtime.transaction_receipt(&tx_id).is_none());
+        assert!(runtime.is_transaction_pending(&tx_id));
+        
+        // Proposing again builds on the moved state
+        runtime.try_propose_block().await.unwrap();
+        assert_eq!(runtime.state_version(), StateVersion::new(2));
+        assert_eq!(runtime.transaction_receipt(&tx_id).unwrap().version, StateVersion::new(2));
+        assert!(!runtime.is_transaction_pending(&tx_id));
+    }
+    
+    #[tokio::test]
+    async fn test_finalized_block_on_moved_root_fails() {
+        let runtime = create_test_runtime();
+        let other = KeyPair::generate();
+        runtime
+            .validator_set
+            .add_validator(ValidatorInfo::new(other.node_id(), other.public_key(), 1000));
+        let sender = KeyPair::generate();
+        runtime
+            .initialize_genesis(GenesisConfig::devnet().add_allocation(&sender.address().to_hex(), 1000))
+            .await
+            .unwrap();
+        runtime.submit_transaction(signed_transfer(&sender)).await.unwrap();
+        let (proposal_id, new_root) = {
+            let pending = runtime.pending_block.lock();
+            let block = pending.as_ref().unwrap();
+            (block.proposal_id, block.new_root)
+        };
+        
+        let moved_root = move_state_root(&runtime).await;
+        let vote = rainsonet_consensus::Vote::new(
+            proposal_id,
+            other.node_id(),
+            true,
+            StateVersion::new(1),
+            new_root,
+            |msg| rainsonet_crypto::signing::sign(&other, msg),
+        );
+        runtime.consensus.receive_vote(vote).await.unwrap();
+        let certificate = runtime.consensus.get_certificate(StateVersion::new(1)).unwrap();
+        let event = ConsensusEvent::StateFinalized(
+            certificate.state_version,
+            certificate.state_root,
+            certificate,
+        );
+        
+        assert!(matches!(
+            runtime.handle_consensus_event(&event).await,
+            Err(rainsonet_core::RainsonetError::StateCorruption(_))
+        ));
+        assert_eq!(runtime.state_root(), moved_root);
+    }
+    
+    /// Apply an unrelated change, as a concurrent block would
+    async fn move_state_root(runtime: &NodeRuntime) -> StateRoot {
+        let change = StateChange::Set { key: b"other".to_vec(), value: vec![1] };
+        let version = StateMutator::apply_batch(&*runtime.state, vec![change]).await.unwrap();
+        let moved_root = runtime.state.compute_root().unwrap();
+        *runtime.state_version.write() = version;
+        *runtime.state_root.write() = moved_root;
+        moved_root
+    }
+    
+    #[tokio::test]
+    async fn test_gapped_transaction_included_after_predecessor() {
+        let runtime = create_test_runtime();
+        let sender = KeyPair::generate();
+        runtime
+            .initialize_genesis(GenesisConfig::devnet().add_allocation(&sender.address().to_hex(), 1000))
+            .await
+            .unwrap();
+        let transfer = |nonce| {
+            let tx = RelyoTransaction::new(
+                sender.address(),
+                KeyPair::generate().address(),
+                Amount::from_relyo(5),
+                Amount::ZERO,
+                Nonce::new(nonce),
+                &sender,
+            )
+            .unwrap();
+            VerifiedTransaction::new(tx).unwrap()
+        };
+        
+        // Arrives before nonce 0 and is kept out of blocks rather than dropped
+        let gapped = runtime.submit_transaction(transfer(1)).await.unwrap();
+        assert!(runtime.is_transaction_pending(&gapped));
+        assert_eq!(runtime.state_version(), StateVersion::new(0));
+        
+        let first = runtime.submit_transaction(transfer(0)).await.unwrap();
+        assert_eq!(runtime.state_version(), StateVersion::new(1));
+        assert!(runtime.transaction_receipt(&first).is_some());
+        assert!(runtime.transaction_receipt(&gapped).is_some());
+        assert_eq!(runtime.get_nonce(&sender.address()).await.unwrap(), Nonce::new(2));
+        assert_eq!(runtime.mempool_size(), 0);
+    }
+    
+    #[tokio::test]
+    async fn test_voted_mode_defers_until_certificate() {
+        let runtime = create_test_runtime();
+        let other = KeyPair::generate();
+        runtime
+            .validator_set
+            .add_validator(ValidatorInfo::new(other.node_id(), other.public_key(), 1000));
+        let sender = KeyPair::generate();
+        runtime
+            .initialize_genesis(GenesisConfig::devnet().add_allocation(&sender.address().to_hex(), 1000))
+            .await
+            .unwrap();
+        assert_eq!(runtime.finalization_mode(), FinalizationMode::Voted);
+        
+        let tx_id = runtime.submit_transaction(signed_transfer(&sender)).await.unwrap();
+        
+        // Proposed, but nothing applied without the other validator's vote
+        assert_eq!(runtime.state_version(), StateVersion::new(0));
+        assert!(runtime.transaction_receipt(&tx_id).is_none());
+        assert!(runtime.is_transaction_pending(&tx_id));
+        
+        let (proposal_id, new_root) = {
+            let pending = runtime.pending_block.lock();
+            let block = pending.as_ref().unwrap();
+            (block.proposal_id, block.new_root)
+        };
+        let vote = rainsonet_consensus::Vote::new(
+            proposal_id,
+            other.node_id(),
+            true,
+            StateVersion::new(1),
+            new_root,
+            |msg| rainsonet_crypto::signing::sign(&other, msg),
+        );
+        runtime.consensus.receive_vote(vote).await.unwrap();
+        let certificate = runtime.consensus.get_certificate(StateVersion::new(1)).unwrap();
+        
+        let event = ConsensusEvent::StateFinalized(
+            certificate.state_version,
+            certificate.state_root,
+            certificate,
+        );
+        runtime.handle_consensus_event(&event).await.unwrap();
+        
+        assert_eq!(runtime.state_version(), StateVersion::new(1));
+        assert_eq!(runtime.state_root(), new_root);
+        assert_eq!(runtime.transaction_receipt(&tx_id).unwrap().version, StateVersion::new(1));
+        assert!(!runtime.is_transaction_pending(&tx_id));
+    }
+    
+    #[tokio::test]
+    async fn test_same_mempool_contents_produce_same_block() {
+        let senders: Vec<_> = (0..3).map(|_| KeyPair::generate()).collect();
+        let recipient = KeyPair::generate().address();
+        let mut genesis = GenesisConfig::devnet();
+        for sender in &senders {
+            genesis = genesis.add_allocation(&sender.address().to_hex(), 1000);
+        }
+        let mut transactions = Vec::new();
+        for sender in &senders {
+            for nonce in 0..3 {
+                let tx = RelyoTransaction::new(
+                    sender.address(),
+                    recipient,
+                    Amount::from_relyo(5),
+                    Amount::ZERO,
+                    Nonce::new(nonce),
+                    sender,
+                )
+                .unwrap();
+                transactions.push(VerifiedTransaction::new(tx).unwrap());
+            }
+        }
+        
+        // Two validators receive the same transactions in opposite orders
+        let other = KeyPair::generate();
+        let mut blocks = Vec::new();
+        for reversed in [false, true] {
+            let runtime = create_test_runtime();
+            runtime
+                .validator_set
+                .add_validator(ValidatorInfo::new(other.node_id(), other.public_key(), 1000));
+            runtime.initialize_genesis(genesis.clone()).await.unwrap();
+            
+            let mut arrivals = transactions.clone();
+            if reversed {
+                arrivals.reverse();
+            }
+            for tx in arrivals {
+                runtime.mempool.add(tx).unwrap();
+            }
+            runtime.try_propose_block().await.unwrap();
+            
+            let block = runtime.pending_block.lock().take().unwrap();
+            blocks.push((block.tx_ids, Proposal::compute_changes_hash(&block.changes)));
+        }
+        
+        assert_eq!(blocks[0].0.len(), transactions.len());
+        assert_eq!(blocks[0], blocks[1]);
+    }
+    
+    fn proposal_event(proposer: &KeyPair, tx_ids: Vec<Hash>, changes: &[StateChange]) -> (Hash, NetworkEvent) {
+        let proposal = Proposal::new(
+            proposer.node_id(),
+            StateVersion::new(1),
+            Hash::ZERO,
+            Hash::ZERO,
+            Hash::from_bytes([1u8; 32]),
+            tx_ids,
+            changes,
+            |msg| rainsonet_crypto::signing::sign(proposer, msg),
+        );
+        let data = bincode::serialize(&ProposalMessage::from(&proposal)).unwrap();
+        (proposal.id, NetworkEvent::ProposalReceived(data))
+    }
+    
+    #[tokio::test]
+    async fn test_received_proposal_rebuilds_changes() {
+        let proposer = KeyPair::generate();
+        let sender = KeyPair::generate();
+        let genesis = GenesisConfig::devnet().add_allocation(&sender.address().to_hex(), 1000);
+        
+        let follower = create_follower(&proposer);
+        follower.initialize_genesis(genesis.clone()).await.unwrap();
+        follower.start_event_fanout();
+        let mut events = follower.subscribe_consensus_events();
+        
+        // The proposer's view of the changes, computed on its own copy of the ledger
+        let proposer_node = NodeRuntime::new(NodeConfig::devnet(), proposer.clone(), GenesisConfig::devnet());
+        proposer_node.initialize_genesis(genesis).await.unwrap();
+        let tx = signed_transfer(&sender);
+        let changes = proposer_node
+            .ledger()
+            .preview_changes(std::slice::from_ref(&tx))
+            .await
+            .unwrap();
+        let (proposal_id, event) = proposal_event(&proposer, vec![tx.tx_id], &changes);
+        
+        // The proposal arrives before its transaction and waits for it
+        follower.handle_network_event(event).await.unwrap();
+        assert!(events.try_recv().is_err());
+        
+        follower
+            .handle_network_event(NetworkEvent::TransactionReceived(tx.tx_id, tx.tx.to_bytes()))
+            .await
+            .unwrap();
+        
+        let received = tokio::time::timeout(std::time::Duration::from_secs(5), events.recv())
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(matches!(received, ConsensusEvent::ProposalReceived(id) if id == proposal_id));
+        
+        // Rebuilding the changes left no pending ledger state behind
+        assert_eq!(
+            follower.get_balance(&sender.address()).await.unwrap(),
+            Amount::from_relyo(1000)
+        );
+    }
+    
+    #[tokio::test]
+    async fn test_follower_applies_finalized_certificate() {
+        let proposer = KeyPair::generate();
+        let sender = KeyPair::generate();
+        let genesis = GenesisConfig::devnet().add_allocation(&sender.address().to_hex(), 1000);
+        
+        let config = NodeConfig {
+            consensus: rainsonet_core::ConsensusConfig {
+                is_validator: true,
+                ..rainsonet_core::ConsensusConfig::devnet()
+            },
+            ..Default::default()
+        };
+        let producer = NodeRuntime::new(config, proposer.clone(), GenesisConfig::devnet());
+        producer.initialize_genesis(genesis.clone()).await.unwrap();
+        let tx = signed_transfer(&sender);
+        producer.submit_transaction(tx.clone()).await.unwrap();
+        let certificate = producer.consensus.get_certificate(StateVersion::new(1)).unwrap();
+        
+        let follower = create_follower(&proposer);
+        follower.initialize_genesis(genesis).await.unwrap();
+        let genesis_root = follower.state_root();
+        let changes = follower.ledger().preview_changes(&[tx]).await.unwrap();
+        
+        // Changes that do not reach the certified root leave everything as is
+        let result = follower.apply_finalized_certificate(certificate.clone(), vec![]).await;
+        assert!(matches!(result, Err(rainsonet_core::RainsonetError::ProposalRejected(_))));
+        assert_eq!(follower.finalized_version().0, 0);
+        assert_eq!(follower.state_version().0, 0);
+        assert_eq!(follower.state_root(), genesis_root);
+        
+        follower.apply_finalized_certificate(certificate.clone(), changes.clone()).await.unwrap();
+        assert_eq!(follower.finalized_version().0, 1);
+        assert_eq!(follower.state_version().0, 1);
+        assert_eq!(follower.state_root(), producer.state_root());
+        assert_eq!(
+            follower.get_balance(&sender.address()).await.unwrap(),
+            producer.get_balance(&sender.address()).await.unwrap()
+        );
+        
+        // The same version cannot be applied twice
+        assert!(follower.apply_finalized_certificate(certificate, changes).await.is_err());
+    }
+    
+    #[tokio::test]
+    async fn test_gossiped_certificate_applied_before_finality() {
+        let (outbox, mut broadcasts) = rainsonet_p2p::create_certificate_outbox();
+        let producer = create_test_runtime().with_certificate_outbox(outbox);
+        let other = KeyPair::generate();
+        producer
+            .validator_set
+            .add_validator(ValidatorInfo::new(other.node_id(), other.public_key(), 1000));
+        let sender = KeyPair::generate();
+        let genesis = GenesisConfig::devnet().add_allocation(&sender.address().to_hex(), 1000);
+        producer.initialize_genesis(genesis.clone()).await.unwrap();
+        let genesis_root = producer.state_root();
+        producer.submit_transaction(signed_transfer(&sender)).await.unwrap();
+        
+        // The follower saw the proposal, but not the votes on it
+        let collection = producer.consensus.pending_vote_collections().remove(0);
+        let (follower_outbox, mut follower_broadcasts) = rainsonet_p2p::create_certificate_outbox();
+        let follower = create_follower(producer.keypair()).with_certificate_outbox(follower_outbox);
+        follower
+            .validator_set
+            .add_validator(ValidatorInfo::new(other.node_id(), other.public_key(), 1000));
+        follower.initialize_genesis(genesis).await.unwrap();
+        follower
+            .consensus
+            .receive_proposal(collection.proposal.clone(), collection.changes)
+            .await
+            .unwrap();
+        
+        let vote = rainsonet_consensus::Vote::new(
+            collection.proposal.id,
+            other.node_id(),
+            true,
+            StateVersion::new(0),
+            genesis_root,
+            |msg| rainsonet_crypto::signing::sign(&other, msg),
+        );
+        producer.consensus.receive_vote(vote).await.unwrap();
+        let certificate = producer.consensus.get_certificate(StateVersion::new(1)).unwrap();
+        let event = ConsensusEvent::StateFinalized(
+            certificate.state_version,
+            certificate.state_root,
+            certificate,
+        );
+        producer.handle_consensus_event(&event).await.unwrap();
+        let message = broadcasts.try_recv().unwrap();
+        
+        let data = bincode::serialize(&message).unwrap();
+        follower.handle_network_event(NetworkEvent::CertificateReceived(data)).await.unwrap();
+        assert_eq!(follower.finalized_version().0, 1);
+        assert_eq!(follower.state_version().0, 1);
+        assert_eq!(follower.state_root(), producer.state_root());
+        
+        // Certificates received from peers are not broadcast again
+        follower.handle_consensus_event(&event).await.unwrap();
+        assert!(follower_broadcasts.try_recv().is_err());
+    }
+    
+    #[tokio::test]
+    async fn test_received_proposal_with_mismatched_changes_rejected() {
+        let proposer = KeyPair::generate();
+        let sender = KeyPair::generate();
+        let genesis = GenesisConfig::devnet().add_allocation(&sender.address().to_hex(), 1000);
+        
+        let follower = create_follower(&proposer);
+        follower.initialize_genesis(genesis).await.unwrap();
+        
+        let tx = signed_transfer(&sender);
+        follower
+            .handle_network_event(NetworkEvent::TransactionReceived(tx.tx_id, tx.tx.to_bytes()))
+            .await
+            .unwrap();
+        
+        // Commits to no changes even though the transaction moves funds
+        let (_, event) = proposal_event(&proposer, vec![tx.tx_id], &[]);
+        let result = follower.handle_network_event(event).await;
+        assert!(matches!(
+            result,
+            Err(rainsonet_core::RainsonetError::ProposalRejected(_))
+        ));
+    }
 }
@@ -0,0 +1,144 @@
+//! Summary of node state at shutdown
+
+use crate::runtime::NodeRuntime;
+use rainsonet_core::RainsonetResult;
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tracing::info;
+
+/// File in the data directory the last shutdown report is written to
+pub const SHUTDOWN_REPORT_FILE: &str = "shutdown.json";
+
+/// What the node looked like when it stopped
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ShutdownReport {
+    pub finalized_version: u64,
+    /// Hex state root at the finalized version
+    pub finalized_root: String,
+    /// Pending transactions, which are dropped: the mempool is not persisted
+    pub mempool_size: usize,
+    pub peer_count: usize,
+    pub uptime_secs: u64,
+    /// Whether the finalized state was persisted to the data directory
+    pub state_flushed: bool,
+}
+
+impl ShutdownReport {
+    /// Snapshot `runtime` for the report
+    pub fn from_runtime(
+        runtime: &NodeRuntime,
+        uptime: Duration,
+        state_flushed: bool,
+    ) -> RainsonetResult<Self> {
+        Ok(Self {
+            finalized_version: runtime.finalized_version().0,
+            finalized_root: runtime.finalized_root()?.to_hex(),
+            mempool_size: runtime.mempool_size(),
+            peer_count: runtime.peer_count(),
+            uptime_secs: uptime.as_secs(),
+            state_flushed,
+        })
+    }
+    
+    /// Log the report as structured fields
+    pub fn log(&self) {
+        info!(
+            finalized_version = self.finalized_version,
+            finalized_root = %self.finalized_root,
+            mempool_size = self.mempool_size,
+            peer_count = self.peer_count,
+            uptime_secs = self.uptime_secs,
+            state_flushed = self.state_flushed,
+            "Node stopped"
+        );
+        if self.mempool_size > 0 {
+            info!("Dropped {} pending transactions", self.mempool_size);
+        }
+    }
+    
+    /// Write the report as JSON to [`SHUTDOWN_REPORT_FILE`] in `data_dir`
+    pub fn write_to(&self, data_dir: &Path) -> anyhow::Result<PathBuf> {
+        std::fs::create_dir_all(data_dir)?;
+        let path = data_dir.join(SHUTDOWN_REPORT_FILE);
+        std::fs::write(&path, serde_json::to_string_pretty(self)?)?;
+        Ok(path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rainsonet_core::{Amount, NodeConfig, Nonce};
+    use rainsonet_crypto::keys::KeyPair;
+    use rainsonet_relyo::{GenesisConfig, RelyoTransaction, VerifiedTransaction};
+    
+    #[tokio::test]
+    async fn test_report_from_runtime() {
+        let mut config = NodeConfig::devnet();
+        config.consensus.is_validator = true;
+        let runtime = NodeRuntime::new(config, KeyPair::generate(), GenesisConfig::devnet());
+        let sender = KeyPair::generate();
+        runtime
+            .initialize_genesis(GenesisConfig::devnet().add_allocation(&sender.address().to_hex(), 1000))
+            .await
+            .unwrap();
+        
+        // Gapped, so it stays pending
+        let tx = RelyoTransaction::new(
+            sender.address(),
+            KeyPair::generate().address(),
+            Amount::from_relyo(5),
+            Amount::ZERO,
+            Nonce::new(1),
+            &sender,
+        )
+        .unwrap();
+        runtime.submit_transaction(VerifiedTransaction::new(tx).unwrap()).await.unwrap();
+        
+        let report = ShutdownReport::from_runtime(&runtime, Duration::from_millis(90_500), true)
+            .unwrap();
+        
+        assert_eq!(
+            report,
+            ShutdownReport {
+                finalized_version: 0,
+                finalized_root: runtime.state_root().to_hex(),
+                mempool_size: 1,
+                peer_count: 0,
+                uptime_secs: 90,
+                state_flushed: true,
+            }
+        );
+        assert_ne!(report.finalized_root, rainsonet_core::Hash::ZERO.to_hex());
+        
+        let json: serde_json::Value = serde_json::to_value(&report).unwrap();
+        assert_eq!(json["mempool_size"], 1);
+        assert_eq!(json["state_flushed"], true);
+    }
+    
+    #[test]
+    fn test_report_written_to_data_dir() {
+        let report = ShutdownReport {
+            finalized_version: 7,
+            finalized_root: rainsonet_core::Hash::from_bytes([1u8; 32]).to_hex(),
+            mempool_size: 0,
+            peer_count: 2,
+            uptime_secs: 60,
+            state_flushed: false,
+        };
+        let data_dir = std::env::temp_dir()
+            .join(format!("rainsonet-shutdown-{}", std::process::id()))
+            .join("data");
+        
+        // The data directory is created if missing
+        let path = report.write_to(&data_dir).unwrap();
+        assert_eq!(path, data_dir.join(SHUTDOWN_REPORT_FILE));
+        let json: serde_json::Value =
+            serde_json::from_slice(&std::fs::read(&path).unwrap()).unwrap();
+        assert_eq!(json, serde_json::to_value(&report).unwrap());
+        assert_eq!(json["state_flushed"], false);
+        
+        let _ = std::fs::remove_dir_all(data_dir.parent().unwrap());
+    }
+}
@@ -0,0 +1,355 @@
+//! Windowed state sync over the sync protocol
+//!
+//! A node that is behind requests history in bounded windows instead of one
+//! response covering the whole gap. Each window carries one diff per version
+//! together with the root it produces, and the requester checks every root
+//! before asking for the next window. If the responder has pruned the
+//! history the requester needs, it answers with a full snapshot instead.
+
+use rainsonet_core::{
+    NodeId, RainsonetError, RainsonetResult, StateChange, StateVersion, Timestamp,
+};
+use rainsonet_p2p::{StateChangeData, SyncDiffData, SyncRequestMessage, SyncResponseMessage};
+use rainsonet_state::{compute_state_root, MemoryStateStore, StateChangeOp, StateDiff, StateEntry};
+use tracing::{debug, info};
+
+/// Default number of versions per sync window
+pub const SYNC_WINDOW_SIZE: u64 = 16;
+
+/// Build the response to a sync request from the local store.
+///
+/// Serves at most `max_window` versions, falling back to a full snapshot
+/// when history no longer reaches back to the requested version.
+pub fn build_sync_response(
+    store: &MemoryStateStore,
+    request: &SyncRequestMessage,
+    max_window: u64,
+) -> RainsonetResult<SyncResponseMessage> {
+    let head_version = store.version();
+    let from_version = request.from_version;
+    
+    let window = request
+        .to_version
+        .map(|to| to.0.saturating_sub(from_version.0))
+        .unwrap_or(max_window)
+        .clamp(1, max_window.max(1));
+    
+    let to_version = StateVersion::new(from_version.0.saturating_add(window));
+    match store.history_range(from_version, to_version) {
+        Some(history) => {
+            let diffs = history
+                .iter()
+                .map(diff_to_sync_data)
+                .collect::<RainsonetResult<Vec<_>>>()?;
+            
+            let (to_version, state_root) = match diffs.last() {
+                Some(last) => (last.version, last.root),
+                None => (from_version, store.compute_root()?),
+            };
+            
+            Ok(SyncResponseMessage {
+                from_version,
+                to_version,
+                state_root,
+                changes: Vec::new(),
+                diffs,
+                head_version,
+                is_snapshot: false,
+                timestamp: Timestamp::now(),
+            })
+        }
+        None => {
+            debug!(
+                "History before version {} pruned, answering with snapshot",
+                from_version
+            );
+            
+            let entries = store.all_entries()?;
+            let state_root = compute_state_root(&entries);
+            let changes = entries
+                .into_iter()
+                .map(|e| StateChangeData {
+                    key: e.key,
+                    value: Some(e.value),
+                })
+                .collect();
+            
+            Ok(SyncResponseMessage {
+                from_version,
+                to_version: head_version,
+                state_root,
+                changes,
+                diffs: Vec::new(),
+                head_version,
+                is_snapshot: true,
+                timestamp: Timestamp::now(),
+            })
+        }
+    }
+}
+
+fn diff_to_sync_data(diff: &StateDiff) -> RainsonetResult<SyncDiffData> {
+    let root = diff.root.ok_or_else(|| {
+        RainsonetError::StateCorruption(format!(
+            "History diff for version {} has no recorded root",
+            diff.to_version
+        ))
+    })?;
+    
+    let changes = diff
+        .to_changes()
+        .into_iter()
+        .map(|op| match op {
+            StateChangeOp::Set { key, value } => StateChangeData {
+                key,
+                value: Some(value),
+            },
+            StateChangeOp::Delete { key } => StateChangeData { key, value: None },
+        })
+        .collect();
+    
+    Ok(SyncDiffData {
+        version: diff.to_version,
+        root,
+        changes,
+    })
+}
+
+fn to_changes(changes: &[StateChangeData]) -> Vec<StateChange> {
+    changes
+        .iter()
+        .map(|c| match &c.value {
+            Some(value) => StateChange::Set {
+                key: c.key.clone(),
+                value: value.clone(),
+            },
+            None => StateChange::Delete { key: c.key.clone() },
+        })
+        .collect()
+}
+
+/// Requester side of a windowed sync
+pub struct SyncSession {
+    requester: NodeId,
+    head_version: StateVersion,
+    window: u64,
+}
+
+impl SyncSession {
+    /// Start syncing towards a peer's advertised `head_version`
+    pub fn new(requester: NodeId, head_version: StateVersion, window: u64) -> Self {
+        Self {
+            requester,
+            head_version,
+            window: window.max(1),
+        }
+    }
+    
+    /// Version the session is syncing towards
+    pub fn head_version(&self) -> StateVersion {
+        self.head_version
+    }
+    
+    /// Request for the next window, or `None` once the store has caught up
+    pub fn next_request(&self, store: &MemoryStateStore) -> Option<SyncRequestMessage> {
+        let local = store.version();
+        if local >= self.head_version {
+            return None;
+        }
+        
+        let to_version = StateVersion::new((local.0 + self.window).min(self.head_version.0));
+        Some(SyncRequestMessage::window(local, to_version, self.requester))
+    }
+    
+    /// Apply one response window to the store, checking the root each version
+    /// leads to before applying it. Returns the store's version afterwards.
+    ///
+    /// A root mismatch aborts the window with `StateCorruption`; versions
+    /// before the mismatching one remain applied, the mismatching one is not.
+    pub fn apply_response(
+        &mut self,
+        store: &MemoryStateStore,
+        response: &SyncResponseMessage,
+    ) -> RainsonetResult<StateVersion> {
+        if response.head_version > self.head_version {
+            self.head_version = response.head_version;
+        }
+        
+        if response.is_snapshot {
+            return self.install_snapshot(store, response);
+        }
+        
+        let local = store.version();
+        if response.from_version != local {
+            return Err(RainsonetError::StateVersionMismatch {
+                expected: local.0,
+                got: response.from_version.0,
+            });
+        }
+        
+        for diff in &response.diffs {
+            let expected = store.version().next();
+            if diff.version != expected {
+                return Err(RainsonetError::StateVersionMismatch {
+                    expected: expected.0,
+                    got: diff.version.0,
+                });
+            }
+            
+            let changes = to_changes(&diff.changes);
+            let root = store.root_after(&changes);
+            if root != diff.root {
+                return Err(RainsonetError::StateCorruption(format!(
+                    "Root mismatch at version {}: expected {}, computed {}",
+                    diff.version, diff.root, root
+                )));
+            }
+            
+            store.apply_batch(changes.into_iter().map(StateChangeOp::from).collect())?;
+        }
+        
+        debug!("Synced up to version {}", store.version());
+        
+        Ok(store.version())
+    }
+    
+    fn install_snapshot(
+        &self,
+        store: &MemoryStateStore,
+        response: &SyncResponseMessage,
+    ) -> RainsonetResult<StateVersion> {
+        let mut entries = Vec::with_capacity(response.changes.len());
+        for change in &response.changes {
+            let value = change.value.clone().ok_or_else(|| {
+                RainsonetError::StateCorruption("Snapshot contains a deletion".into())
+            })?;
+            entries.push(StateEntry {
+                key: change.key.clone(),
+                value,
+            });
+        }
+        
+        let root = compute_state_root(&entries);
+        if root != response.state_root {
+            return Err(RainsonetError::StateCorruption(format!(
+                "Snapshot root mismatch: expected {}, computed {}",
+                response.state_root, root
+            )));
+        }
+        
+        store.reset_to(
+            entries.into_iter().map(|e| (e.key, e.value)).collect(),
+            response.to_version,
+        );
+        
+        info!("Installed snapshot at version {}", response.to_version);
+        
+        Ok(response.to_version)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rainsonet_p2p::Message;
+    
+    fn advance(store: &MemoryStateStore, versions: u64) {
+        for i in 0..versions {
+            let v = store.version().0;
+            let mut changes = vec![StateChangeOp::Set {
+                key: format!("key{}", v % 7).into_bytes(),
+                value: format!("value{}", v).into_bytes(),
+            }];
+            if i % 5 == 4 {
+                changes.push(StateChangeOp::Delete {
+                    key: format!("key{}", (v + 3) % 7).into_bytes(),
+                });
+            }
+            store.apply_batch(changes).unwrap();
+        }
+    }
+    
+    /// Run a session against a responder, passing messages through the wire format
+    fn run_sync(responder: &MemoryStateStore, requester: &MemoryStateStore, window: u64) -> usize {
+        let mut session = SyncSession::new(NodeId::from_bytes([9u8; 32]), responder.version(), window);
+        let mut rounds = 0;
+        
+        while let Some(request) = session.next_request(requester) {
+            let request = match Message::from_bytes(&Message::SyncRequest(request).to_bytes()) {
+                Some(Message::SyncRequest(r)) => r,
+                _ => panic!("bad request encoding"),
+            };
+            let response = build_sync_response(responder, &request, window).unwrap();
+            let response = match Message::from_bytes(&Message::SyncResponse(response).to_bytes()) {
+                Some(Message::SyncResponse(r)) => r,
+                _ => panic!("bad response encoding"),
+            };
+            
+            session.apply_response(requester, &response).unwrap();
+            rounds += 1;
+            assert!(rounds <= 100, "sync did not converge");
+        }
+        
+        rounds
+    }
+    
+    #[test]
+    fn test_windowed_catch_up() {
+        let responder = MemoryStateStore::new();
+        advance(&responder, 50);
+        
+        let requester = MemoryStateStore::new();
+        let rounds = run_sync(&responder, &requester, 8);
+        
+        // 50 versions in windows of 8
+        assert_eq!(rounds, 7);
+        assert_eq!(requester.version(), StateVersion::new(50));
+        assert_eq!(requester.compute_root().unwrap(), responder.compute_root().unwrap());
+    }
+    
+    #[test]
+    fn test_snapshot_fallback_after_prune() {
+        let responder = MemoryStateStore::new();
+        advance(&responder, 50);
+        
+        let requester = MemoryStateStore::new();
+        let mut session = SyncSession::new(NodeId::from_bytes([9u8; 32]), responder.version(), 8);
+        
+        // First window streams normally
+        let request = session.next_request(&requester).unwrap();
+        let response = build_sync_response(&responder, &request, 8).unwrap();
+        assert!(!response.is_snapshot);
+        session.apply_response(&requester, &response).unwrap();
+        assert_eq!(requester.version(), StateVersion::new(8));
+        
+        // Responder prunes the history we still need
+        responder.prune_history(StateVersion::new(30));
+        
+        let request = session.next_request(&requester).unwrap();
+        let response = build_sync_response(&responder, &request, 8).unwrap();
+        assert!(response.is_snapshot);
+        session.apply_response(&requester, &response).unwrap();
+        
+        assert!(session.next_request(&requester).is_none());
+        assert_eq!(requester.version(), StateVersion::new(50));
+        assert_eq!(requester.compute_root().unwrap(), responder.compute_root().unwrap());
+    }
+    
+    #[test]
+    fn test_root_mismatch_rejected() {
+        let responder = MemoryStateStore::new();
+        advance(&responder, 4);
+        
+        let requester = MemoryStateStore::new();
+        let mut session = SyncSession::new(NodeId::from_bytes([9u8; 32]), responder.version(), 4);
+        
+        let request = session.next_request(&requester).unwrap();
+        let mut response = build_sync_response(&responder, &request, 4).unwrap();
+        response.diffs[2].root = rainsonet_core::Hash::from_bytes([0xAB; 32]);
+        
+        let result = session.apply_response(&requester, &response);
+        assert!(matches!(result, Err(RainsonetError::StateCorruption(_))));
+        assert_eq!(requester.version(), StateVersion::new(2));
+        assert_eq!(requester.compute_root().unwrap(), response.diffs[1].root);
+    }
+}
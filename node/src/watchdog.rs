@@ -0,0 +1,113 @@
+//! Consensus liveness watchdog
+
+use parking_lot::Mutex;
+use rainsonet_core::{SharedTimeSource, StateVersion, Timestamp};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::time::Duration;
+use tracing::{error, info};
+
+/// Detects consensus that stopped finalizing while transactions wait.
+///
+/// An idle network finalizes nothing either, so a stall is only reported
+/// while the mempool holds transactions.
+pub struct LivenessWatchdog {
+    clock: SharedTimeSource,
+    threshold: Duration,
+    /// Latest finalized version seen and when it was first seen
+    last_finalized: Mutex<(StateVersion, Timestamp)>,
+    stalled: AtomicBool,
+    /// Stalls detected since start, counting each stall once
+    stalls: AtomicU64,
+}
+
+impl LivenessWatchdog {
+    pub fn new(threshold: Duration, clock: SharedTimeSource) -> Self {
+        let now = clock.now();
+        Self {
+            clock,
+            threshold,
+            last_finalized: Mutex::new((StateVersion::new(0), now)),
+            stalled: AtomicBool::new(false),
+            stalls: AtomicU64::new(0),
+        }
+    }
+    
+    /// Record the current finalized version and mempool size, returning
+    /// whether consensus is stalled
+    pub fn check(&self, finalized: StateVersion, pending_transactions: usize) -> bool {
+        let now = self.clock.now();
+        let mut last = self.last_finalized.lock();
+        if finalized > last.0 {
+            *last = (finalized, now);
+        }
+        
+        let since = Duration::from_millis(now.as_millis().saturating_sub(last.1.as_millis()));
+        let stalled = pending_transactions > 0 && since > self.threshold;
+        let was_stalled = self.stalled.swap(stalled, Ordering::SeqCst);
+        
+        if stalled && !was_stalled {
+            let stalls = self.stalls.fetch_add(1, Ordering::SeqCst) + 1;
+            error!(
+                finalized_version = last.0 .0,
+                pending_transactions,
+                stalled_for_secs = since.as_secs(),
+                consensus_stalls = stalls,
+                "Consensus stalled: nothing finalized for {:?}",
+                since
+            );
+        } else if was_stalled && !stalled {
+            info!("Consensus recovered at finalized version {}", last.0);
+        }
+        
+        stalled
+    }
+    
+    /// Whether the last check found consensus stalled
+    pub fn is_stalled(&self) -> bool {
+        self.stalled.load(Ordering::SeqCst)
+    }
+    
+    /// Number of stalls detected so far
+    pub fn stall_count(&self) -> u64 {
+        self.stalls.load(Ordering::SeqCst)
+    }
+    
+    /// Time since the finalized version last advanced
+    pub fn since_last_finalized(&self) -> Duration {
+        let last = self.last_finalized.lock().1;
+        Duration::from_millis(self.clock.now().as_millis().saturating_sub(last.as_millis()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rainsonet_core::MockTimeSource;
+    use std::sync::Arc;
+    
+    #[test]
+    fn test_stall_flagged_with_pending_transactions() {
+        let clock = Arc::new(MockTimeSource::new(Timestamp::from_millis(1_000_000)));
+        let watchdog = LivenessWatchdog::new(Duration::from_secs(60), clock.clone());
+        
+        clock.advance(Duration::from_secs(30));
+        assert!(!watchdog.check(StateVersion::new(0), 5));
+        
+        // Nothing pending, nothing to finalize
+        clock.advance(Duration::from_secs(60));
+        assert!(!watchdog.check(StateVersion::new(0), 0));
+        
+        assert!(watchdog.check(StateVersion::new(0), 5));
+        assert!(watchdog.is_stalled());
+        assert!(watchdog.check(StateVersion::new(0), 5));
+        assert_eq!(watchdog.stall_count(), 1);
+        
+        // A new finalized version clears the stall
+        assert!(!watchdog.check(StateVersion::new(1), 5));
+        assert!(!watchdog.is_stalled());
+        
+        clock.advance(Duration::from_secs(61));
+        assert!(watchdog.check(StateVersion::new(1), 5));
+        assert_eq!(watchdog.stall_count(), 2);
+    }
+}
@@ -3,35 +3,82 @@
 use libp2p::{
     gossipsub::{self, IdentTopic, MessageAuthenticity, ValidationMode},
     mdns,
+    request_response::{self, ProtocolSupport},
     swarm::NetworkBehaviour,
     identity::Keypair,
 };
+use rainsonet_core::MessageIdStrategy;
 use std::time::Duration;
 
+use crate::message::Message;
+use crate::sync_protocol::{SyncCodec, SYNC_PROTOCOL};
+
 /// Topic names for gossipsub
 pub const TOPIC_TRANSACTIONS: &str = "rainsonet/transactions/1";
 pub const TOPIC_PROPOSALS: &str = "rainsonet/proposals/1";
 pub const TOPIC_VOTES: &str = "rainsonet/votes/1";
 pub const TOPIC_SYNC: &str = "rainsonet/sync/1";
+pub const TOPIC_CERTIFICATES: &str = "rainsonet/certificates/1";
+
+/// Message ID for `message` under `strategy`
+pub fn message_id(
+    strategy: MessageIdStrategy,
+    message: &gossipsub::Message,
+) -> gossipsub::MessageId {
+    let hash = match (strategy, Message::from_bytes(&message.data)) {
+        (MessageIdStrategy::ConsensusIdentity, Some(Message::Vote(vote))) => {
+            consensus_identity(message, b"vote", vote.voter.as_bytes(), &vote.proposal_id)
+        }
+        (MessageIdStrategy::ConsensusIdentity, Some(Message::Proposal(proposal))) => {
+            consensus_identity(
+                message,
+                b"proposal",
+                proposal.proposer.as_bytes(),
+                &proposal.proposal_id,
+            )
+        }
+        _ => rainsonet_crypto::hashing::hash(&message.data),
+    };
+    gossipsub::MessageId::from(hash.to_hex())
+}
+
+/// Hash of a consensus message's identity and the publication carrying it
+fn consensus_identity(
+    message: &gossipsub::Message,
+    kind: &[u8],
+    sender: &[u8],
+    proposal_id: &rainsonet_core::Hash,
+) -> rainsonet_core::Hash {
+    let publisher = message.source.map(|peer| peer.to_bytes()).unwrap_or_default();
+    let sequence_number = message.sequence_number.unwrap_or_default().to_le_bytes();
+    rainsonet_crypto::hashing::hash_multiple(&[
+        kind,
+        sender,
+        proposal_id.as_bytes(),
+        &publisher,
+        &sequence_number,
+    ])
+}
 
 /// Combined network behaviour
 #[derive(NetworkBehaviour)]
 pub struct RainsonetBehaviour {
     pub gossipsub: gossipsub::Behaviour,
     pub mdns: mdns::tokio::Behaviour,
+    pub sync: request_response::Behaviour<SyncCodec>,
 }
 
 impl RainsonetBehaviour {
-    pub fn new(keypair: &Keypair, enable_mdns: bool) -> Result<Self, Box<dyn std::error::Error>> {
+    pub fn new(
+        keypair: &Keypair,
+        enable_mdns: bool,
+        message_ids: MessageIdStrategy,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
         // Configure gossipsub
         let gossipsub_config = gossipsub::ConfigBuilder::default()
             .heartbeat_interval(Duration::from_secs(1))
             .validation_mode(ValidationMode::Strict)
-            .message_id_fn(|message| {
-                // Use hash of data as message ID for deduplication
-                let hash = rainsonet_crypto::hashing::hash(&message.data);
-                gossipsub::MessageId::from(hash.to_hex())
-            })
+            .message_id_fn(move |message| message_id(message_ids, message))
             .build()
             .map_err(|e| format!("Failed to build gossipsub config: {}", e))?;
         
@@ -57,7 +104,15 @@ impl RainsonetBehaviour {
             )?
         };
         
-        Ok(Self { gossipsub, mdns })
+        // State sync requests go to a single peer and are answered over the
+        // same connection
+        let sync = request_response::Behaviour::with_codec(
+            SyncCodec,
+            [(SYNC_PROTOCOL, ProtocolSupport::Full)],
+            request_response::Config::default(),
+        );
+        
+        Ok(Self { gossipsub, mdns, sync })
     }
     
     /// Subscribe to all RAINSONET topics
@@ -66,6 +121,7 @@ impl RainsonetBehaviour {
         self.gossipsub.subscribe(&IdentTopic::new(TOPIC_PROPOSALS))?;
         self.gossipsub.subscribe(&IdentTopic::new(TOPIC_VOTES))?;
         self.gossipsub.subscribe(&IdentTopic::new(TOPIC_SYNC))?;
+        self.gossipsub.subscribe(&IdentTopic::new(TOPIC_CERTIFICATES))?;
         Ok(())
     }
     
@@ -85,7 +141,11 @@ pub fn topic_for_message(message_type: &str) -> &'static str {
         "transaction" => TOPIC_TRANSACTIONS,
         "proposal" => TOPIC_PROPOSALS,
         "vote" => TOPIC_VOTES,
-        "sync_request" | "sync_response" => TOPIC_SYNC,
+        "certificate" => TOPIC_CERTIFICATES,
+        "state_announce" => TOPIC_SYNC,
+        "mempool_request" | "mempool_inventory" | "get_transactions" | "transactions" => {
+            TOPIC_SYNC
+        }
         _ => TOPIC_TRANSACTIONS,
     }
 }
@@ -99,5 +159,51 @@ mod tests {
         assert_eq!(topic_for_message("transaction"), TOPIC_TRANSACTIONS);
         assert_eq!(topic_for_message("proposal"), TOPIC_PROPOSALS);
         assert_eq!(topic_for_message("vote"), TOPIC_VOTES);
+        assert_eq!(topic_for_message("certificate"), TOPIC_CERTIFICATES);
+        assert_eq!(topic_for_message("state_announce"), TOPIC_SYNC);
+    }
+    
+    fn gossip_message(message: &Message, sequence_number: u64) -> gossipsub::Message {
+        gossipsub::Message {
+            source: Some(libp2p::PeerId::random()),
+            data: message.to_bytes(),
+            sequence_number: Some(sequence_number),
+            topic: IdentTopic::new(TOPIC_VOTES).hash(),
+        }
+    }
+    
+    #[test]
+    fn test_message_id_strategies() {
+        let vote = Message::Vote(crate::message::VoteMessage {
+            proposal_id: rainsonet_core::Hash::from_bytes([1u8; 32]),
+            voter: rainsonet_core::NodeId::from_bytes([2u8; 32]),
+            approve: true,
+            state_version: rainsonet_core::StateVersion::new(1),
+            state_root: rainsonet_core::Hash::ZERO,
+            signature: rainsonet_core::Signature::from_bytes([0u8; 64]),
+            timestamp: rainsonet_core::Timestamp::from_millis(1_000),
+        });
+        let original = gossip_message(&vote, 1);
+        let resend = gossipsub::Message {
+            sequence_number: Some(2),
+            ..original.clone()
+        };
+        
+        let content = MessageIdStrategy::ContentHash;
+        assert_eq!(message_id(content, &original), message_id(content, &resend));
+        
+        let identity = MessageIdStrategy::ConsensusIdentity;
+        assert_eq!(message_id(identity, &original), message_id(identity, &original.clone()));
+        assert_ne!(message_id(identity, &original), message_id(identity, &resend));
+        
+        // Transactions keep content-hash IDs
+        let tx = Message::Transaction(crate::message::TransactionMessage::new(
+            rainsonet_core::Hash::from_bytes([3u8; 32]),
+            vec![1, 2, 3],
+        ));
+        assert_eq!(
+            message_id(identity, &gossip_message(&tx, 1)),
+            message_id(identity, &gossip_message(&tx, 2))
+        );
     }
 }
@@ -4,13 +4,22 @@
 //! - Noise protocol for encryption
 //! - Gossipsub for message propagation
 //! - mDNS for local peer discovery
+//! - Request-response for state sync
 
 pub mod network;
 pub mod behaviour;
+pub mod mempool_sync;
 pub mod message;
 pub mod peer;
+pub mod seen;
+pub mod sync_protocol;
 
 pub use network::*;
 pub use behaviour::*;
+pub use mempool_sync::*;
 pub use message::*;
 pub use peer::*;
+pub use seen::*;
+pub use sync_protocol::*;
+
+pub use libp2p::Multiaddr;
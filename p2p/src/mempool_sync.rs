@@ -0,0 +1,227 @@
+//! Mempool sync with newly connected peers
+//!
+//! Gossipsub only forwards messages published after a peer joined, so a
+//! node that connects late never hears of transactions already pending
+//! elsewhere. On connect the node asks the peer for its pending transaction
+//! IDs and then fetches the ones it is missing by ID. Both steps are
+//! bounded, and only answers to our own requests are accepted.
+
+use crate::message::{
+    GetTransactionsMessage, MempoolInventoryMessage, MempoolRequestMessage, TransactionMessage,
+    TransactionsMessage,
+};
+use rainsonet_core::{Hash, NodeId, Timestamp};
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+
+/// Most transaction IDs exchanged in one mempool inventory
+pub const MAX_MEMPOOL_SYNC_IDS: usize = 1000;
+
+/// Most transactions requested, and returned, in one `GetTransactions` round
+pub const MAX_TRANSACTIONS_PER_REQUEST: usize = 64;
+
+/// Pending transactions offered to peers, typically the node's mempool
+pub trait TransactionSource: Send + Sync {
+    /// Up to `limit` pending transaction IDs, most valuable first
+    fn pending_tx_ids(&self, limit: usize) -> Vec<Hash>;
+    
+    /// Whether the transaction is already pending locally
+    fn has_transaction(&self, tx_id: &Hash) -> bool;
+    
+    /// Encoded transaction, as carried by a [`TransactionMessage`]
+    fn transaction_data(&self, tx_id: &Hash) -> Option<Vec<u8>>;
+}
+
+/// State of the mempool exchanges with connected peers
+pub struct MempoolSync {
+    local: NodeId,
+    source: Arc<dyn TransactionSource>,
+    /// Peers asked for their inventory and not yet answered
+    asked: HashSet<NodeId>,
+    /// Transactions requested and the peer asked for each
+    requested: HashMap<Hash, NodeId>,
+}
+
+impl MempoolSync {
+    pub fn new(local: NodeId, source: Arc<dyn TransactionSource>) -> Self {
+        Self {
+            local,
+            source,
+            asked: HashSet::new(),
+            requested: HashMap::new(),
+        }
+    }
+    
+    /// Inventory request for a newly connected peer, once per connection
+    pub fn request_for(&mut self, peer: NodeId) -> Option<MempoolRequestMessage> {
+        if !self.asked.insert(peer) {
+            return None;
+        }
+        Some(MempoolRequestMessage {
+            requester: self.local,
+            responder: peer,
+            timestamp: Timestamp::now(),
+        })
+    }
+    
+    /// Forget outstanding requests to a disconnected peer
+    pub fn peer_disconnected(&mut self, peer: &NodeId) {
+        self.asked.remove(peer);
+        self.requested.retain(|_, asked| asked != peer);
+    }
+    
+    /// Answer an inventory request addressed to us
+    pub fn handle_request(
+        &self,
+        request: &MempoolRequestMessage,
+    ) -> Option<MempoolInventoryMessage> {
+        if request.responder != self.local {
+            return None;
+        }
+        Some(MempoolInventoryMessage {
+            requester: request.requester,
+            tx_ids: self.source.pending_tx_ids(MAX_MEMPOOL_SYNC_IDS),
+            timestamp: Timestamp::now(),
+        })
+    }
+    
+    /// Requests for the transactions missing from an inventory `author`
+    /// sent in answer to our request
+    pub fn handle_inventory(
+        &mut self,
+        author: NodeId,
+        inventory: &MempoolInventoryMessage,
+    ) -> Vec<GetTransactionsMessage> {
+        if inventory.requester != self.local || !self.asked.remove(&author) {
+            return Vec::new();
+        }
+        
+        let room = MAX_MEMPOOL_SYNC_IDS.saturating_sub(self.requested.len());
+        let missing: Vec<Hash> = inventory
+            .tx_ids
+            .iter()
+            .take(MAX_MEMPOOL_SYNC_IDS)
+            .filter(|id| !self.source.has_transaction(id) && !self.requested.contains_key(id))
+            .take(room)
+            .copied()
+            .collect();
+        for tx_id in &missing {
+            self.requested.insert(*tx_id, author);
+        }
+        
+        missing
+            .chunks(MAX_TRANSACTIONS_PER_REQUEST)
+            .map(|tx_ids| GetTransactionsMessage {
+                requester: self.local,
+                responder: author,
+                tx_ids: tx_ids.to_vec(),
+                timestamp: Timestamp::now(),
+            })
+            .collect()
+    }
+    
+    /// Answer a transaction request addressed to us
+    pub fn handle_get(&self, request: &GetTransactionsMessage) -> Option<TransactionsMessage> {
+        if request.responder != self.local {
+            return None;
+        }
+        let transactions = request
+            .tx_ids
+            .iter()
+            .take(MAX_TRANSACTIONS_PER_REQUEST)
+            .filter_map(|id| {
+                let data = self.source.transaction_data(id)?;
+                Some(TransactionMessage::new(*id, data))
+            })
+            .collect();
+        Some(TransactionsMessage {
+            requester: request.requester,
+            transactions,
+            timestamp: Timestamp::now(),
+        })
+    }
+    
+    /// Transactions we requested from `author`; anything else is dropped
+    pub fn handle_transactions(
+        &mut self,
+        author: NodeId,
+        response: TransactionsMessage,
+    ) -> Vec<TransactionMessage> {
+        if response.requester != self.local {
+            return Vec::new();
+        }
+        response
+            .transactions
+            .into_iter()
+            .take(MAX_TRANSACTIONS_PER_REQUEST)
+            .filter(|tx| {
+                if self.requested.get(&tx.tx_id) == Some(&author) {
+                    self.requested.remove(&tx.tx_id);
+                    true
+                } else {
+                    false
+                }
+            })
+            .collect()
+    }
+    
+    /// Number of transactions requested and not yet received
+    pub fn outstanding(&self) -> usize {
+        self.requested.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    /// Source holding transactions `0..count`
+    struct Holding(u8);
+    
+    impl TransactionSource for Holding {
+        fn pending_tx_ids(&self, limit: usize) -> Vec<Hash> {
+            (0..self.0).take(limit).map(|i| Hash::from_bytes([i; 32])).collect()
+        }
+        
+        fn has_transaction(&self, tx_id: &Hash) -> bool {
+            tx_id.as_bytes()[0] < self.0
+        }
+        
+        fn transaction_data(&self, tx_id: &Hash) -> Option<Vec<u8>> {
+            self.has_transaction(tx_id).then(|| vec![tx_id.as_bytes()[0]])
+        }
+    }
+    
+    #[test]
+    fn test_only_requested_transactions_accepted() {
+        let local = NodeId::from_bytes([1u8; 32]);
+        let peer = NodeId::from_bytes([2u8; 32]);
+        let stranger = NodeId::from_bytes([3u8; 32]);
+        let mut sync = MempoolSync::new(local, Arc::new(Holding(2)));
+        let remote = MempoolSync::new(peer, Arc::new(Holding(200)));
+        
+        let request = sync.request_for(peer).unwrap();
+        assert!(sync.request_for(peer).is_none());
+        let inventory = remote.handle_request(&request).unwrap();
+        
+        // Unsolicited inventories are ignored
+        assert!(sync.handle_inventory(stranger, &inventory).is_empty());
+        
+        let gets = sync.handle_inventory(peer, &inventory);
+        assert_eq!(gets.len(), 198usize.div_ceil(MAX_TRANSACTIONS_PER_REQUEST));
+        assert_eq!(sync.outstanding(), 198);
+        assert!(sync.handle_inventory(peer, &inventory).is_empty());
+        
+        let response = remote.handle_get(&gets[0]).unwrap();
+        assert_eq!(response.transactions.len(), MAX_TRANSACTIONS_PER_REQUEST);
+        assert!(sync.handle_transactions(stranger, response.clone()).is_empty());
+        let received = sync.handle_transactions(peer, response.clone());
+        assert_eq!(received.len(), MAX_TRANSACTIONS_PER_REQUEST);
+        
+        // Replays are dropped
+        assert!(sync.handle_transactions(peer, response).is_empty());
+        
+        sync.peer_disconnected(&peer);
+        assert_eq!(sync.outstanding(), 0);
+    }
+}
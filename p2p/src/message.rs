@@ -21,17 +21,37 @@ pub enum Message {
     /// Vote on a proposal
     Vote(VoteMessage),
     
-    /// State sync request
+    /// State sync request. No longer gossiped; sync runs over
+    /// [`crate::SYNC_PROTOCOL`] and the variant only keeps the encoding of
+    /// later variants stable.
     SyncRequest(SyncRequestMessage),
     
-    /// State sync response
+    /// State sync response, see [`Message::SyncRequest`]
     SyncResponse(SyncResponseMessage),
     
+    /// Announcement of a newly finalized state version
+    StateAnnounce(StateAnnounceMessage),
+    
     /// Ping for keepalive
     Ping(PingMessage),
     
     /// Pong response
     Pong(PongMessage),
+    
+    /// Finality certificate for a finalized state version
+    Certificate(CertificateMessage),
+    
+    /// Request for a peer's pending transaction IDs
+    MempoolRequest(MempoolRequestMessage),
+    
+    /// Pending transaction IDs answering a mempool request
+    MempoolInventory(MempoolInventoryMessage),
+    
+    /// Request for transactions by ID
+    GetTransactions(GetTransactionsMessage),
+    
+    /// Transactions answering a `GetTransactions` request
+    Transactions(TransactionsMessage),
 }
 
 impl Message {
@@ -51,8 +71,14 @@ impl Message {
             Message::Vote(_) => "vote",
             Message::SyncRequest(_) => "sync_request",
             Message::SyncResponse(_) => "sync_response",
+            Message::StateAnnounce(_) => "state_announce",
             Message::Ping(_) => "ping",
             Message::Pong(_) => "pong",
+            Message::Certificate(_) => "certificate",
+            Message::MempoolRequest(_) => "mempool_request",
+            Message::MempoolInventory(_) => "mempool_inventory",
+            Message::GetTransactions(_) => "get_transactions",
+            Message::Transactions(_) => "transactions",
         }
     }
 }
@@ -65,9 +91,17 @@ pub struct HandshakeMessage {
     pub is_validator: bool,
     pub state_version: StateVersion,
     pub state_root: StateRoot,
+    /// Genesis the sender started from; peers on another genesis disconnect
+    #[serde(default = "unknown_genesis")]
+    pub genesis_hash: Hash,
     pub timestamp: Timestamp,
 }
 
+/// Genesis of a peer that predates genesis hashes in handshakes
+fn unknown_genesis() -> Hash {
+    Hash::ZERO
+}
+
 impl HandshakeMessage {
     pub fn new(
         node_id: NodeId,
@@ -81,9 +115,16 @@ impl HandshakeMessage {
             is_validator,
             state_version,
             state_root,
+            genesis_hash: Hash::ZERO,
             timestamp: Timestamp::now(),
         }
     }
+    
+    /// Announce the genesis this node started from
+    pub fn with_genesis_hash(mut self, genesis_hash: Hash) -> Self {
+        self.genesis_hash = genesis_hash;
+        self
+    }
 }
 
 /// Transaction broadcast message
@@ -104,6 +145,39 @@ impl TransactionMessage {
     }
 }
 
+/// Ask `responder` for its pending transaction IDs
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MempoolRequestMessage {
+    pub requester: NodeId,
+    pub responder: NodeId,
+    pub timestamp: Timestamp,
+}
+
+/// Pending transaction IDs of the responder, most valuable first
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MempoolInventoryMessage {
+    pub requester: NodeId,
+    pub tx_ids: Vec<Hash>,
+    pub timestamp: Timestamp,
+}
+
+/// Ask `responder` for the transactions with the given IDs
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GetTransactionsMessage {
+    pub requester: NodeId,
+    pub responder: NodeId,
+    pub tx_ids: Vec<Hash>,
+    pub timestamp: Timestamp,
+}
+
+/// Requested transactions the responder still had
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TransactionsMessage {
+    pub requester: NodeId,
+    pub transactions: Vec<TransactionMessage>,
+    pub timestamp: Timestamp,
+}
+
 /// State proposal from a validator
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ProposalMessage {
@@ -111,8 +185,12 @@ pub struct ProposalMessage {
     pub proposer: NodeId,
     pub state_version: StateVersion,
     pub previous_root: StateRoot,
+    pub previous_certificate_hash: Hash,
     pub new_root: StateRoot,
     pub tx_ids: Vec<Hash>,
+    pub inactive_validators: Vec<NodeId>,
+    #[serde(default)]
+    pub stake_updates: Vec<(NodeId, u128)>,
     pub changes_hash: Hash,
     pub signature: Signature,
     pub timestamp: Timestamp,
@@ -130,6 +208,16 @@ pub struct VoteMessage {
     pub timestamp: Timestamp,
 }
 
+/// Finality certificate broadcast after a version is finalized
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CertificateMessage {
+    pub proposal_id: Hash,
+    pub state_version: StateVersion,
+    pub state_root: StateRoot,
+    pub votes: Vec<VoteMessage>,
+    pub finalized_at: Timestamp,
+}
+
 /// Request state sync
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SyncRequestMessage {
@@ -148,18 +236,64 @@ impl SyncRequestMessage {
             timestamp: Timestamp::now(),
         }
     }
+    
+    /// Request the bounded window of versions `from_version..to_version`
+    pub fn window(from_version: StateVersion, to_version: StateVersion, requester: NodeId) -> Self {
+        Self {
+            to_version: Some(to_version),
+            ..Self::new(from_version, requester)
+        }
+    }
 }
 
 /// State sync response
+///
+/// Normally carries one entry in `diffs` per version so the requester can
+/// check the root after each step. If the responder no longer has history
+/// back to `from_version`, it sets `is_snapshot` and sends its full state in
+/// `changes` instead.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SyncResponseMessage {
     pub from_version: StateVersion,
     pub to_version: StateVersion,
     pub state_root: StateRoot,
     pub changes: Vec<StateChangeData>,
+    #[serde(default)]
+    pub diffs: Vec<SyncDiffData>,
+    /// Latest version the responder has finalized
+    #[serde(default)]
+    pub head_version: StateVersion,
+    #[serde(default)]
+    pub is_snapshot: bool,
+    pub timestamp: Timestamp,
+}
+
+/// Changes for a single version, with the root they produce
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SyncDiffData {
+    pub version: StateVersion,
+    pub root: StateRoot,
+    pub changes: Vec<StateChangeData>,
+}
+
+/// Lightweight announcement of the sender's latest finalized state
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StateAnnounceMessage {
+    pub version: StateVersion,
+    pub root: StateRoot,
     pub timestamp: Timestamp,
 }
 
+impl StateAnnounceMessage {
+    pub fn new(version: StateVersion, root: StateRoot) -> Self {
+        Self {
+            version,
+            root,
+            timestamp: Timestamp::now(),
+        }
+    }
+}
+
 /// State change data for sync
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct StateChangeData {
@@ -232,4 +366,20 @@ mod tests {
         assert_eq!(msg.version, PROTOCOL_VERSION);
         assert!(msg.is_validator);
     }
+    
+    #[test]
+    fn test_state_announce_roundtrip() {
+        let msg = Message::StateAnnounce(StateAnnounceMessage::new(
+            StateVersion::new(7),
+            Hash::from_bytes([3u8; 32]),
+        ));
+        
+        match Message::from_bytes(&msg.to_bytes()).unwrap() {
+            Message::StateAnnounce(announce) => {
+                assert_eq!(announce.version, StateVersion::new(7));
+                assert_eq!(announce.root, Hash::from_bytes([3u8; 32]));
+            }
+            other => panic!("unexpected message: {}", other.message_type()),
+        }
+    }
 }
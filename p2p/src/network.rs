@@ -1,23 +1,39 @@
 //! Main network implementation
 
-use crate::behaviour::{RainsonetBehaviour, RainsonetBehaviourEvent, TOPIC_PROPOSALS, TOPIC_TRANSACTIONS, TOPIC_VOTES};
-use crate::message::{Message, TransactionMessage};
-use crate::peer::{create_peer_manager, PeerInfo, SharedPeerManager};
+use crate::behaviour::{
+    RainsonetBehaviour, RainsonetBehaviourEvent, TOPIC_CERTIFICATES, TOPIC_PROPOSALS, TOPIC_SYNC,
+    TOPIC_TRANSACTIONS, TOPIC_VOTES,
+};
+use crate::mempool_sync::{MempoolSync, TransactionSource};
+use crate::message::{
+    CertificateMessage, HandshakeMessage, Message, ProposalMessage, StateAnnounceMessage,
+    SyncRequestMessage, SyncResponseMessage, TransactionMessage, VoteMessage,
+};
+use crate::peer::{
+    create_peer_manager, PeerInfo, PeerManager, SharedPeerManager, ValidatorRegistry,
+};
+use crate::sync_protocol::SyncSource;
 use anyhow::Result;
 use futures::StreamExt;
 use libp2p::{
-    gossipsub::{self, IdentTopic},
+    gossipsub,
     identity::Keypair,
     mdns,
-    multiaddr::Protocol,
+    request_response,
     swarm::{SwarmEvent},
     Multiaddr, PeerId, Swarm,
 };
-use rainsonet_core::{Hash, NetworkConfig, NodeId, RainsonetResult, StateRoot, StateVersion};
-use rainsonet_crypto::keys::KeyPair as RainsonetKeyPair;
+use parking_lot::Mutex;
+use rainsonet_core::{
+    Hash, LogLimiter, NetworkConfig, NodeId, StateRoot, StateVersion, Timestamp,
+};
+use rainsonet_core::PublicKey;
+use rainsonet_crypto::keys::{address_from_public_key, KeyPair as RainsonetKeyPair};
+use std::collections::{HashMap, VecDeque};
 use std::sync::Arc;
+use std::time::Duration;
 use tokio::sync::mpsc;
-use tracing::{debug, error, info, warn};
+use tracing::{debug, info, warn};
 
 /// Network event for consumers
 #[derive(Debug, Clone)]
@@ -32,8 +48,150 @@ pub enum NetworkEvent {
     ProposalReceived(Vec<u8>),
     /// Vote received
     VoteReceived(Vec<u8>),
-    /// Sync request received
-    SyncRequestReceived(Vec<u8>),
+    /// Finality certificate received
+    CertificateReceived(Vec<u8>),
+    /// Answer from the given peer to a sync request we sent it
+    SyncResponseReceived(NodeId, Vec<u8>),
+}
+
+/// Command for the network task, which owns the swarm
+#[derive(Debug, Clone)]
+pub enum NetworkCommand {
+    /// Dial a peer address
+    Dial(Multiaddr),
+    /// Close all connections to a peer
+    Disconnect(NodeId),
+}
+
+/// Minimum interval between two state announcements from the same node
+pub const STATE_ANNOUNCE_INTERVAL_MS: u64 = 1000;
+
+/// Idle time after which a non-validator connection is closed
+pub const IDLE_CONNECTION_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Swarm-wide idle timeout. Validator connections are only subject to this
+/// one, while non-validators are closed after [`IDLE_CONNECTION_TIMEOUT`]
+/// by the network loop.
+pub const VALIDATOR_IDLE_CONNECTION_TIMEOUT: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// Messages kept for re-broadcast while no peer is subscribed to their topic
+pub const MAX_UNSENT_MESSAGES: usize = 1024;
+
+/// How often idle non-validator connections are swept
+const IDLE_SWEEP_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Shared handle for announcing the local finalized state to peers.
+///
+/// Callers record every finalized version with [`StateAnnouncer::announce`];
+/// the network loop publishes at most one announcement per interval and only
+/// ever the most recent version, so bursts of finalizations collapse into a
+/// single message.
+#[derive(Clone)]
+pub struct StateAnnouncer {
+    inner: Arc<Mutex<AnnouncerState>>,
+}
+
+struct AnnouncerState {
+    min_interval_ms: u64,
+    latest_version: Option<StateVersion>,
+    latest_root: StateRoot,
+    pending: Option<StateAnnounceMessage>,
+    last_sent: Option<Timestamp>,
+}
+
+impl StateAnnouncer {
+    pub fn new(min_interval_ms: u64) -> Self {
+        Self {
+            inner: Arc::new(Mutex::new(AnnouncerState {
+                min_interval_ms,
+                latest_version: None,
+                latest_root: Hash::ZERO,
+                pending: None,
+                last_sent: None,
+            })),
+        }
+    }
+    
+    /// Record a newly finalized state. Versions not newer than the last
+    /// recorded one are ignored.
+    pub fn announce(&self, version: StateVersion, root: StateRoot) {
+        let mut inner = self.inner.lock();
+        if inner.latest_version.is_some_and(|latest| version <= latest) {
+            return;
+        }
+        inner.latest_version = Some(version);
+        inner.latest_root = root;
+        inner.pending = Some(StateAnnounceMessage::new(version, root));
+    }
+    
+    /// Take the pending announcement if the throttle interval has elapsed
+    pub fn take_due(&self, now: Timestamp) -> Option<StateAnnounceMessage> {
+        let mut inner = self.inner.lock();
+        inner.pending.as_ref()?;
+        
+        if let Some(last) = inner.last_sent {
+            if now.as_millis().saturating_sub(last.as_millis()) < inner.min_interval_ms {
+                return None;
+            }
+        }
+        
+        inner.last_sent = Some(now);
+        inner.pending.take()
+    }
+    
+    /// Latest recorded state, or genesis if none was recorded yet
+    pub fn latest(&self) -> (StateVersion, StateRoot) {
+        let inner = self.inner.lock();
+        (inner.latest_version.unwrap_or_default(), inner.latest_root)
+    }
+    
+    /// Interval at which the network loop should poll for due announcements
+    pub fn interval(&self) -> Duration {
+        Duration::from_millis(self.inner.lock().min_interval_ms.max(1))
+    }
+}
+
+impl Default for StateAnnouncer {
+    fn default() -> Self {
+        Self::new(STATE_ANNOUNCE_INTERVAL_MS)
+    }
+}
+
+/// Apply a peer's state announcement to the peer manager.
+///
+/// Announcements may arrive out of order through gossip, so one that is
+/// older than what we already know for the peer is dropped. Returns whether
+/// the peer's state was updated.
+pub fn apply_state_announce(
+    peer_manager: &PeerManager,
+    from: &NodeId,
+    announce: &StateAnnounceMessage,
+) -> bool {
+    match peer_manager.get_peer(from) {
+        Some(peer) if peer.state_version <= announce.version => {
+            peer_manager.update_peer_state(from, announce.version, announce.root);
+            true
+        }
+        _ => false,
+    }
+}
+
+/// Derive the node ID used by the peer manager from a libp2p peer ID
+fn node_id_from_peer_id(peer_id: &PeerId) -> NodeId {
+    let peer_bytes = peer_id.to_bytes();
+    let mut node_id_bytes = [0u8; 32];
+    let len = peer_bytes.len().min(32);
+    node_id_bytes[..len].copy_from_slice(&peer_bytes[..len]);
+    NodeId::from_bytes(node_id_bytes)
+}
+
+/// Node ID derived from the Ed25519 key a peer ID embeds, the ID a
+/// validator is registered under; `None` if the peer uses another key type
+fn signer_node_id(peer_id: &PeerId) -> Option<NodeId> {
+    let key = libp2p::identity::PublicKey::try_decode_protobuf(peer_id.as_ref().digest()).ok()?;
+    let key = key.try_into_ed25519().ok()?;
+    let address = address_from_public_key(&PublicKey::from_bytes(key.to_bytes()));
+    Some(NodeId::from_bytes(*address.as_bytes()))
 }
 
 /// Network service for RAINSONET
@@ -42,6 +200,29 @@ pub struct NetworkService {
     peer_manager: SharedPeerManager,
     node_id: NodeId,
     event_tx: mpsc::Sender<NetworkEvent>,
+    state_announcer: StateAnnouncer,
+    /// Role announced in our handshake
+    is_validator: bool,
+    /// Genesis announced in our handshake and required of peers
+    genesis_hash: Hash,
+    /// Connected libp2p peers, to close connections by node ID
+    peer_ids: HashMap<NodeId, PeerId>,
+    /// Transactions queued for broadcast by the node runtime
+    transaction_outbox: Option<mpsc::Receiver<TransactionMessage>>,
+    /// Certificates of newly finalized versions queued by the node runtime
+    certificate_outbox: Option<mpsc::Receiver<CertificateMessage>>,
+    /// Operator commands, e.g. from the admin API
+    commands: Option<mpsc::Receiver<NetworkCommand>>,
+    /// Messages that found no peers, re-published when a peer subscribes
+    unsent: VecDeque<(&'static str, Vec<u8>)>,
+    /// Keeps warnings caused by misbehaving peers from flooding the logs
+    log_limiter: LogLimiter,
+    /// Pulls pending transactions from newly connected peers
+    mempool_sync: Option<MempoolSync>,
+    /// Answers sync requests from peers
+    sync_source: Option<Arc<dyn SyncSource>>,
+    /// Confirms the validator role peers claim in handshakes
+    validator_registry: Option<Arc<dyn ValidatorRegistry>>,
 }
 
 impl NetworkService {
@@ -55,7 +236,9 @@ impl NetworkService {
         let libp2p_keypair = Keypair::ed25519_from_bytes(keypair.secret_bytes().to_vec())?;
         
         // Create swarm
-        let behaviour = RainsonetBehaviour::new(&libp2p_keypair, config.enable_mdns)?;
+        let behaviour =
+            RainsonetBehaviour::new(&libp2p_keypair, config.enable_mdns, config.message_ids)
+                .map_err(|e| anyhow::anyhow!("{}", e))?;
         
         let mut swarm = libp2p::SwarmBuilder::with_existing_identity(libp2p_keypair)
             .with_tokio()
@@ -66,7 +249,7 @@ impl NetworkService {
             )?
             .with_behaviour(|_| behaviour)?
             .with_swarm_config(|cfg| {
-                cfg.with_idle_connection_timeout(std::time::Duration::from_secs(60))
+                cfg.with_idle_connection_timeout(VALIDATOR_IDLE_CONNECTION_TIMEOUT)
             })
             .build();
         
@@ -80,16 +263,94 @@ impl NetworkService {
         let node_id = keypair.node_id();
         let peer_manager = create_peer_manager(config.max_peers);
         
-        info!("Network service created for node {}", node_id);
+        info!("Network service created for node {}", node_id.short());
         
         Ok(Self {
             swarm,
             peer_manager,
             node_id,
             event_tx,
+            state_announcer: StateAnnouncer::default(),
+            is_validator: false,
+            genesis_hash: Hash::ZERO,
+            peer_ids: HashMap::new(),
+            transaction_outbox: None,
+            certificate_outbox: None,
+            commands: None,
+            unsent: VecDeque::new(),
+            log_limiter: LogLimiter::default(),
+            mempool_sync: None,
+            sync_source: None,
+            validator_registry: None,
         })
     }
     
+    /// Announce this node as a validator in handshakes
+    pub fn with_validator_role(mut self, is_validator: bool) -> Self {
+        self.is_validator = is_validator;
+        self
+    }
+    
+    /// Only peer with nodes started from the same genesis: a handshake
+    /// carrying another genesis hash disconnects its author
+    pub fn with_genesis_hash(mut self, genesis_hash: Hash) -> Self {
+        self.genesis_hash = genesis_hash;
+        self
+    }
+    
+    /// Use a shared state announcer, typically owned by the node runtime
+    pub fn with_state_announcer(mut self, announcer: StateAnnouncer) -> Self {
+        self.state_announcer = announcer;
+        self
+    }
+    
+    /// Broadcast transactions queued by the node runtime, see
+    /// [`create_transaction_outbox`]
+    pub fn with_transaction_outbox(mut self, outbox: mpsc::Receiver<TransactionMessage>) -> Self {
+        self.transaction_outbox = Some(outbox);
+        self
+    }
+    
+    /// Broadcast certificates queued by the node runtime as it finalizes
+    /// versions, see [`create_certificate_outbox`]
+    pub fn with_certificate_outbox(mut self, outbox: mpsc::Receiver<CertificateMessage>) -> Self {
+        self.certificate_outbox = Some(outbox);
+        self
+    }
+    
+    /// Exchange pending transactions with newly connected peers: offer
+    /// those in `source` and fetch the ones it lacks
+    pub fn with_transaction_source(mut self, source: Arc<dyn TransactionSource>) -> Self {
+        let local = node_id_from_peer_id(self.swarm.local_peer_id());
+        self.mempool_sync = Some(MempoolSync::new(local, source));
+        self
+    }
+    
+    /// Answer peers' sync requests from `source`. Without one, requests
+    /// are left unanswered and the requester tries another peer.
+    pub fn with_sync_source(mut self, source: Arc<dyn SyncSource>) -> Self {
+        self.sync_source = Some(source);
+        self
+    }
+    
+    /// Treat a peer as a validator only if `registry` knows its key as one.
+    /// Without a registry no peer is, whatever its handshake claims.
+    pub fn with_validator_registry(mut self, registry: Arc<dyn ValidatorRegistry>) -> Self {
+        self.validator_registry = Some(registry);
+        self
+    }
+    
+    /// Execute commands sent through [`create_network_commands`]
+    pub fn with_commands(mut self, commands: mpsc::Receiver<NetworkCommand>) -> Self {
+        self.commands = Some(commands);
+        self
+    }
+    
+    /// Get the state announcer
+    pub fn state_announcer(&self) -> StateAnnouncer {
+        self.state_announcer.clone()
+    }
+    
     /// Get the node ID
     pub fn node_id(&self) -> NodeId {
         self.node_id
@@ -107,7 +368,9 @@ impl NetworkService {
                 Ok(addr) => {
                     info!("Connecting to bootstrap node: {}", addr);
                     if let Err(e) = self.swarm.dial(addr.clone()) {
-                        warn!("Failed to dial {}: {}", addr, e);
+                        self.log_limiter.warn("dial_failed", || {
+                            format!("Failed to dial {}: {}", addr, e)
+                        });
                     }
                 }
                 Err(e) => {
@@ -123,53 +386,241 @@ impl NetworkService {
         let msg = Message::Transaction(TransactionMessage::new(tx_id, tx_data));
         let data = msg.to_bytes();
         
-        self.swarm
-            .behaviour_mut()
-            .publish(TOPIC_TRANSACTIONS, data)?;
+        self.publish_or_queue(TOPIC_TRANSACTIONS, data)?;
         
-        debug!("Broadcast transaction {}", tx_id);
+        debug!("Broadcast transaction {}", tx_id.short());
         Ok(())
     }
     
     /// Broadcast a proposal
-    pub fn broadcast_proposal(&mut self, proposal_data: Vec<u8>) -> Result<()> {
-        let msg = Message::Proposal(bincode::deserialize(&proposal_data)?);
-        let data = msg.to_bytes();
+    pub fn broadcast_proposal(&mut self, proposal: impl Into<ProposalMessage>) -> Result<()> {
+        let proposal = proposal.into();
+        let proposal_id = proposal.proposal_id;
+        let data = Message::Proposal(proposal).to_bytes();
         
-        self.swarm.behaviour_mut().publish(TOPIC_PROPOSALS, data)?;
+        self.publish_or_queue(TOPIC_PROPOSALS, data)?;
         
-        debug!("Broadcast proposal");
+        debug!("Broadcast proposal {}", proposal_id.short());
         Ok(())
     }
     
     /// Broadcast a vote
-    pub fn broadcast_vote(&mut self, vote_data: Vec<u8>) -> Result<()> {
-        let msg = Message::Vote(bincode::deserialize(&vote_data)?);
-        let data = msg.to_bytes();
+    pub fn broadcast_vote(&mut self, vote: impl Into<VoteMessage>) -> Result<()> {
+        let vote = vote.into();
+        let proposal_id = vote.proposal_id;
+        let data = Message::Vote(vote).to_bytes();
         
-        self.swarm.behaviour_mut().publish(TOPIC_VOTES, data)?;
+        self.publish_or_queue(TOPIC_VOTES, data)?;
         
-        debug!("Broadcast vote");
+        debug!("Broadcast vote on proposal {}", proposal_id.short());
         Ok(())
     }
     
+    /// Broadcast a finality certificate
+    pub fn broadcast_certificate(&mut self, certificate: impl Into<CertificateMessage>) -> Result<()> {
+        let certificate = certificate.into();
+        let version = certificate.state_version;
+        let data = Message::Certificate(certificate).to_bytes();
+        
+        self.publish_or_queue(TOPIC_CERTIFICATES, data)?;
+        
+        debug!("Broadcast certificate for version {}", version);
+        Ok(())
+    }
+    
+    /// Send a sync request to a connected peer. Its answer arrives as
+    /// [`NetworkEvent::SyncResponseReceived`].
+    pub fn send_sync_request(&mut self, peer: NodeId, request: SyncRequestMessage) -> Result<()> {
+        let peer_id = *self
+            .peer_ids
+            .get(&peer)
+            .ok_or_else(|| anyhow::anyhow!("Peer {} is not connected", peer.short()))?;
+        self.swarm.behaviour_mut().sync.send_request(&peer_id, request);
+        
+        debug!("Sent sync request to {}", peer_id);
+        Ok(())
+    }
+    
+    /// Answer a sync request over the connection it arrived on. The
+    /// response is built for the connected peer, whatever requester the
+    /// message names.
+    fn handle_sync_request(
+        &mut self,
+        peer_id: PeerId,
+        mut request: SyncRequestMessage,
+        channel: request_response::ResponseChannel<SyncResponseMessage>,
+    ) {
+        request.requester = node_id_from_peer_id(&peer_id);
+        let Some(response) = self.sync_source.as_ref().and_then(|s| s.sync_response(&request))
+        else {
+            debug!("No sync response for {}", peer_id);
+            return;
+        };
+        
+        let to_version = response.to_version;
+        if self.swarm.behaviour_mut().sync.send_response(channel, response).is_err() {
+            debug!("Sync request from {} closed before the response", peer_id);
+            return;
+        }
+        debug!("Sent sync response up to version {} to {}", to_version, peer_id);
+    }
+    
+    /// Publish `data`, or queue it for re-broadcast if no peer is subscribed
+    /// to `topic` yet. Local processing does not depend on the broadcast, so
+    /// a lack of peers is not an error.
+    fn publish_or_queue(&mut self, topic: &'static str, data: Vec<u8>) -> Result<()> {
+        match self.swarm.behaviour_mut().publish(topic, data.clone()) {
+            Ok(_) => Ok(()),
+            Err(gossipsub::PublishError::InsufficientPeers) => {
+                warn!("No peers on {}, queueing message for re-broadcast", topic);
+                if self.unsent.len() >= MAX_UNSENT_MESSAGES {
+                    self.unsent.pop_front();
+                }
+                self.unsent.push_back((topic, data));
+                Ok(())
+            }
+            Err(e) => Err(e.into()),
+        }
+    }
+    
+    /// Re-publish queued messages for `topic` now that a peer subscribed
+    fn flush_unsent(&mut self, topic: &str) {
+        let (ready, rest): (VecDeque<_>, VecDeque<_>) =
+            self.unsent.drain(..).partition(|(t, _)| *t == topic);
+        self.unsent = rest;
+        
+        for (topic, data) in ready {
+            if let Err(e) = self.publish_or_queue(topic, data) {
+                debug!("Failed to re-broadcast on {}: {}", topic, e);
+            }
+        }
+    }
+    
+    /// Number of messages waiting for a peer
+    pub fn unsent_count(&self) -> usize {
+        self.unsent.len()
+    }
+    
+    /// Publish the pending state announcement if it is due
+    fn publish_state_announce(&mut self) {
+        let Some(announce) = self.state_announcer.take_due(Timestamp::now()) else {
+            return;
+        };
+        
+        let version = announce.version;
+        let data = Message::StateAnnounce(announce).to_bytes();
+        
+        match self.swarm.behaviour_mut().publish(TOPIC_SYNC, data) {
+            Ok(_) => debug!("Announced state version {}", version),
+            Err(e) => debug!("Failed to announce state version {}: {}", version, e),
+        }
+    }
+    
+    /// Publish our handshake so peers learn our role and state
+    fn publish_handshake(&mut self) {
+        let (version, root) = self.state_announcer.latest();
+        let handshake = HandshakeMessage::new(self.node_id, self.is_validator, version, root)
+            .with_genesis_hash(self.genesis_hash);
+        let data = Message::Handshake(handshake).to_bytes();
+        
+        if let Err(e) = self.swarm.behaviour_mut().publish(TOPIC_SYNC, data) {
+            debug!("Failed to publish handshake: {}", e);
+        }
+    }
+    
+    /// Publish a message on the sync topic, where requests and answers
+    /// between two peers travel
+    fn publish_sync(&mut self, message: Message) {
+        let message_type = message.message_type();
+        if let Err(e) = self.swarm.behaviour_mut().publish(TOPIC_SYNC, message.to_bytes()) {
+            debug!("Failed to publish {}: {}", message_type, e);
+        }
+    }
+    
+    /// Ask a newly connected peer for its pending transactions
+    fn request_mempool(&mut self, peer_id: PeerId) {
+        let peer = node_id_from_peer_id(&peer_id);
+        if let Some(request) = self.mempool_sync.as_mut().and_then(|sync| sync.request_for(peer)) {
+            debug!("Requesting pending transactions from {}", peer_id);
+            self.publish_sync(Message::MempoolRequest(request));
+        }
+    }
+    
+    /// Close connections to non-validators that have been idle too long
+    fn disconnect_idle_peers(&mut self) {
+        let idle = self
+            .peer_manager
+            .idle_non_validators(Timestamp::now(), IDLE_CONNECTION_TIMEOUT.as_millis() as u64);
+        
+        for node_id in idle {
+            if let Some(peer_id) = self.peer_ids.get(&node_id) {
+                debug!("Closing idle connection to {}", peer_id);
+                let _ = self.swarm.disconnect_peer_id(*peer_id);
+            }
+        }
+    }
+    
+    /// Execute an operator command
+    fn handle_command(&mut self, command: NetworkCommand) {
+        match command {
+            NetworkCommand::Dial(addr) => {
+                info!("Dialing {}", addr);
+                if let Err(e) = self.swarm.dial(addr.clone()) {
+                    self.log_limiter
+                        .warn("dial_failed", || format!("Failed to dial {}: {}", addr, e));
+                }
+            }
+            NetworkCommand::Disconnect(node_id) => match self.peer_ids.get(&node_id) {
+                Some(peer_id) => {
+                    info!("Disconnecting {}", peer_id);
+                    let _ = self.swarm.disconnect_peer_id(*peer_id);
+                }
+                None => warn!("Cannot disconnect {}: not connected", node_id.short()),
+            },
+        }
+    }
+    
     /// Run the network event loop
     pub async fn run(&mut self) {
+        let mut announce_interval = tokio::time::interval(self.state_announcer.interval());
+        let mut idle_sweep = tokio::time::interval(IDLE_SWEEP_INTERVAL);
+        
         loop {
-            match self.swarm.select_next_some().await {
-                SwarmEvent::Behaviour(event) => {
-                    self.handle_behaviour_event(event).await;
+            tokio::select! {
+                event = self.swarm.select_next_some() => match event {
+                    SwarmEvent::Behaviour(event) => {
+                        self.handle_behaviour_event(event).await;
+                    }
+                    SwarmEvent::NewListenAddr { address, .. } => {
+                        info!("Listening on {}", address);
+                    }
+                    SwarmEvent::ConnectionEstablished { peer_id, .. } => {
+                        self.handle_peer_connected(peer_id).await;
+                    }
+                    SwarmEvent::ConnectionClosed { peer_id, .. } => {
+                        self.handle_peer_disconnected(peer_id).await;
+                    }
+                    _ => {}
+                },
+                _ = announce_interval.tick() => {
+                    self.publish_state_announce();
                 }
-                SwarmEvent::NewListenAddr { address, .. } => {
-                    info!("Listening on {}", address);
+                _ = idle_sweep.tick() => {
+                    self.disconnect_idle_peers();
                 }
-                SwarmEvent::ConnectionEstablished { peer_id, .. } => {
-                    self.handle_peer_connected(peer_id).await;
+                Some(tx) = next_outbound(&mut self.transaction_outbox) => {
+                    if let Err(e) = self.broadcast_transaction(tx.tx_id, tx.tx_data) {
+                        debug!("Failed to broadcast transaction: {}", e);
+                    }
                 }
-                SwarmEvent::ConnectionClosed { peer_id, .. } => {
-                    self.handle_peer_disconnected(peer_id).await;
+                Some(certificate) = next_outbound(&mut self.certificate_outbox) => {
+                    if let Err(e) = self.broadcast_certificate(certificate) {
+                        debug!("Failed to broadcast certificate: {}", e);
+                    }
+                }
+                Some(command) = next_outbound(&mut self.commands) => {
+                    self.handle_command(command);
                 }
-                _ => {}
             }
         }
     }
@@ -183,11 +634,46 @@ impl NetworkService {
             }) => {
                 self.handle_gossip_message(message, propagation_source).await;
             }
+            RainsonetBehaviourEvent::Gossipsub(gossipsub::Event::Subscribed { peer_id, topic }) => {
+                debug!("Peer {} subscribed to {}", peer_id, topic);
+                if topic.as_str() == TOPIC_SYNC {
+                    self.publish_handshake();
+                    self.request_mempool(peer_id);
+                }
+                self.flush_unsent(topic.as_str());
+            }
+            RainsonetBehaviourEvent::Sync(request_response::Event::Message { peer, message }) => {
+                self.peer_manager.update_last_seen(&node_id_from_peer_id(&peer));
+                match message {
+                    request_response::Message::Request { request, channel, .. } => {
+                        self.handle_sync_request(peer, request, channel);
+                    }
+                    request_response::Message::Response { response, .. } => {
+                        let data = bincode::serialize(&response).unwrap_or_default();
+                        let _ = self
+                            .event_tx
+                            .send(NetworkEvent::SyncResponseReceived(
+                                node_id_from_peer_id(&peer),
+                                data,
+                            ))
+                            .await;
+                    }
+                }
+            }
+            RainsonetBehaviourEvent::Sync(request_response::Event::OutboundFailure {
+                peer,
+                error,
+                ..
+            }) => {
+                debug!("Sync request to {} failed: {}", peer, error);
+            }
             RainsonetBehaviourEvent::Mdns(mdns::Event::Discovered(peers)) => {
                 for (peer_id, addr) in peers {
                     info!("mDNS discovered peer: {} at {}", peer_id, addr);
                     if let Err(e) = self.swarm.dial(addr) {
-                        warn!("Failed to dial discovered peer: {}", e);
+                        self.log_limiter.warn("dial_failed", || {
+                            format!("Failed to dial discovered peer {}: {}", peer_id, e)
+                        });
                     }
                 }
             }
@@ -200,11 +686,38 @@ impl NetworkService {
         }
     }
     
-    async fn handle_gossip_message(&self, message: gossipsub::Message, source: PeerId) {
+    async fn handle_gossip_message(&mut self, message: gossipsub::Message, source: PeerId) {
         let topic = message.topic.as_str();
+        self.peer_manager.update_last_seen(&node_id_from_peer_id(&source));
         
         if let Some(msg) = Message::from_bytes(&message.data) {
             match msg {
+                Message::Handshake(handshake) => {
+                    // Trust the role of the signed author, not the relaying peer
+                    let author = message.source.unwrap_or(source);
+                    if handshake.genesis_hash != self.genesis_hash {
+                        self.log_limiter.warn("genesis_mismatch", || {
+                            format!(
+                                "Peer {} started from genesis {}, not {}; disconnecting",
+                                author,
+                                handshake.genesis_hash.short(),
+                                self.genesis_hash.short()
+                            )
+                        });
+                        let _ = self.swarm.disconnect_peer_id(author);
+                        return;
+                    }
+                    let node_id = node_id_from_peer_id(&author);
+                    // The handshake only claims the role; the registry has to
+                    // know the key that signed it as a validator
+                    let is_validator = handshake.is_validator
+                        && signer_node_id(&author).is_some_and(|id| {
+                            self.validator_registry.as_ref().is_some_and(|r| r.is_validator(&id))
+                        });
+                    self.peer_manager.set_validator(&node_id, is_validator);
+                    self.peer_manager
+                        .update_peer_state(&node_id, handshake.state_version, handshake.state_root);
+                }
                 Message::Transaction(tx_msg) => {
                     let _ = self.event_tx.send(NetworkEvent::TransactionReceived(
                         tx_msg.tx_id,
@@ -219,41 +732,87 @@ impl NetworkService {
                     let data = bincode::serialize(&vote_msg).unwrap_or_default();
                     let _ = self.event_tx.send(NetworkEvent::VoteReceived(data)).await;
                 }
-                Message::SyncRequest(sync_msg) => {
-                    let data = bincode::serialize(&sync_msg).unwrap_or_default();
-                    let _ = self.event_tx.send(NetworkEvent::SyncRequestReceived(data)).await;
+                Message::Certificate(certificate_msg) => {
+                    let data = bincode::serialize(&certificate_msg).unwrap_or_default();
+                    let _ = self.event_tx.send(NetworkEvent::CertificateReceived(data)).await;
+                }
+                Message::StateAnnounce(announce) => {
+                    // Attribute the announcement to its signed author, not the relaying peer
+                    let author = message.source.unwrap_or(source);
+                    let node_id = node_id_from_peer_id(&author);
+                    if apply_state_announce(&self.peer_manager, &node_id, &announce) {
+                        debug!("Peer {} announced state version {}", author, announce.version);
+                    }
+                }
+                Message::MempoolRequest(request) => {
+                    let inventory =
+                        self.mempool_sync.as_ref().and_then(|s| s.handle_request(&request));
+                    if let Some(inventory) = inventory {
+                        self.publish_sync(Message::MempoolInventory(inventory));
+                    }
+                }
+                Message::MempoolInventory(inventory) => {
+                    let author = node_id_from_peer_id(&message.source.unwrap_or(source));
+                    let requests = match self.mempool_sync.as_mut() {
+                        Some(sync) => sync.handle_inventory(author, &inventory),
+                        None => Vec::new(),
+                    };
+                    for request in requests {
+                        self.publish_sync(Message::GetTransactions(request));
+                    }
+                }
+                Message::GetTransactions(request) => {
+                    let response = self.mempool_sync.as_ref().and_then(|s| s.handle_get(&request));
+                    if let Some(response) = response {
+                        self.publish_sync(Message::Transactions(response));
+                    }
+                }
+                Message::Transactions(response) => {
+                    let author = node_id_from_peer_id(&message.source.unwrap_or(source));
+                    let transactions = match self.mempool_sync.as_mut() {
+                        Some(sync) => sync.handle_transactions(author, response),
+                        None => Vec::new(),
+                    };
+                    for tx in transactions {
+                        let _ = self
+                            .event_tx
+                            .send(NetworkEvent::TransactionReceived(tx.tx_id, tx.tx_data))
+                            .await;
+                    }
                 }
                 _ => {}
             }
+        } else {
+            self.log_limiter.warn("invalid_message", || {
+                format!("Undecodable message on {} relayed by {}", topic, source)
+            });
         }
     }
     
-    async fn handle_peer_connected(&self, peer_id: PeerId) {
-        // Convert PeerId to NodeId
-        let peer_bytes = peer_id.to_bytes();
-        let mut node_id_bytes = [0u8; 32];
-        let len = peer_bytes.len().min(32);
-        node_id_bytes[..len].copy_from_slice(&peer_bytes[..len]);
-        let node_id = NodeId::from_bytes(node_id_bytes);
+    async fn handle_peer_connected(&mut self, peer_id: PeerId) {
+        let node_id = node_id_from_peer_id(&peer_id);
         
         info!("Peer connected: {}", peer_id);
         
+        // Treated as a non-validator until its handshake says otherwise
         let peer_info = PeerInfo::new(node_id, false);
         self.peer_manager.add_peer(peer_info);
+        self.peer_ids.insert(node_id, peer_id);
+        self.publish_handshake();
         
         let _ = self.event_tx.send(NetworkEvent::PeerConnected(node_id)).await;
     }
     
-    async fn handle_peer_disconnected(&self, peer_id: PeerId) {
-        let peer_bytes = peer_id.to_bytes();
-        let mut node_id_bytes = [0u8; 32];
-        let len = peer_bytes.len().min(32);
-        node_id_bytes[..len].copy_from_slice(&peer_bytes[..len]);
-        let node_id = NodeId::from_bytes(node_id_bytes);
+    async fn handle_peer_disconnected(&mut self, peer_id: PeerId) {
+        let node_id = node_id_from_peer_id(&peer_id);
         
         info!("Peer disconnected: {}", peer_id);
         
         self.peer_manager.remove_peer(&node_id);
+        self.peer_ids.remove(&node_id);
+        if let Some(sync) = self.mempool_sync.as_mut() {
+            sync.peer_disconnected(&node_id);
+        }
         
         let _ = self.event_tx.send(NetworkEvent::PeerDisconnected(node_id)).await;
     }
@@ -264,15 +823,302 @@ pub fn create_network_channel() -> (mpsc::Sender<NetworkEvent>, mpsc::Receiver<N
     mpsc::channel(1000)
 }
 
+/// Create the channel through which the runtime queues transactions for
+/// the network service to broadcast
+pub fn create_transaction_outbox() -> (
+    mpsc::Sender<TransactionMessage>,
+    mpsc::Receiver<TransactionMessage>,
+) {
+    mpsc::channel(1000)
+}
+
+/// Create the channel through which the runtime queues the certificates of
+/// versions it finalized for the network service to broadcast
+pub fn create_certificate_outbox() -> (
+    mpsc::Sender<CertificateMessage>,
+    mpsc::Receiver<CertificateMessage>,
+) {
+    mpsc::channel(256)
+}
+
+/// Create the channel through which operators send commands to the
+/// network service
+pub fn create_network_commands() -> (mpsc::Sender<NetworkCommand>, mpsc::Receiver<NetworkCommand>) {
+    mpsc::channel(16)
+}
+
+/// Next queued item, or never if there is no queue
+async fn next_outbound<T>(outbox: &mut Option<mpsc::Receiver<T>>) -> Option<T> {
+    match outbox {
+        Some(rx) => rx.recv().await,
+        None => std::future::pending().await,
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     
     #[test]
     fn test_network_event_channel() {
-        let (tx, mut rx) = create_network_channel();
+        let (tx, _rx) = create_network_channel();
         
         // Channel should be created
-        assert!(tx.is_closed() == false);
+        assert!(!tx.is_closed());
+    }
+    
+    #[test]
+    fn test_signer_node_id_matches_validator_id() {
+        let keypair = RainsonetKeyPair::generate();
+        let libp2p_keypair = Keypair::ed25519_from_bytes(keypair.secret_bytes().to_vec()).unwrap();
+        let peer_id = libp2p_keypair.public().to_peer_id();
+        assert_eq!(signer_node_id(&peer_id), Some(keypair.node_id()));
+        assert_ne!(node_id_from_peer_id(&peer_id), keypair.node_id());
+    }
+    
+    /// Create a network service on a free local port, returning its address
+    async fn create_service() -> (Multiaddr, NetworkService, mpsc::Receiver<NetworkEvent>) {
+        let port = std::net::TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .port();
+        let listen_addr = format!("/ip4/127.0.0.1/tcp/{}", port);
+        let config = NetworkConfig {
+            listen_addr: listen_addr.clone(),
+            enable_mdns: false,
+            ..Default::default()
+        };
+        let (event_tx, event_rx) = create_network_channel();
+        let service = NetworkService::new(&RainsonetKeyPair::generate(), &config, event_tx)
+            .await
+            .unwrap();
+        
+        (listen_addr.parse().unwrap(), service, event_rx)
+    }
+    
+    /// Start a network service on a free local port, returning its address
+    async fn spawn_service(
+        commands: Option<mpsc::Receiver<NetworkCommand>>,
+    ) -> (Multiaddr, mpsc::Receiver<NetworkEvent>) {
+        let (addr, mut service, event_rx) = create_service().await;
+        if let Some(commands) = commands {
+            service = service.with_commands(commands);
+        }
+        tokio::spawn(async move { service.run().await });
+        
+        (addr, event_rx)
+    }
+    
+    /// Run a service's event loop for `duration`
+    async fn drive(service: &mut NetworkService, duration: Duration) {
+        let _ = tokio::time::timeout(duration, service.run()).await;
+    }
+    
+    /// Wait for a vote to arrive, skipping other events
+    async fn vote_received(events: &mut mpsc::Receiver<NetworkEvent>) -> bool {
+        tokio::time::timeout(Duration::from_secs(5), async {
+            while let Some(event) = events.recv().await {
+                if matches!(event, NetworkEvent::VoteReceived(_)) {
+                    return true;
+                }
+            }
+            false
+        })
+        .await
+        .unwrap_or(false)
+    }
+    
+    #[tokio::test]
+    async fn test_resent_vote_reaches_late_peer() {
+        let (_, mut publisher, _publisher_events) = create_service().await;
+        let (early_addr, mut early_events) = spawn_service(None).await;
+        
+        publisher.connect_bootstrap(&[early_addr.to_string()]).await.unwrap();
+        drive(&mut publisher, Duration::from_secs(2)).await;
+        
+        let vote = VoteMessage {
+            proposal_id: Hash::from_bytes([1u8; 32]),
+            voter: publisher.node_id(),
+            approve: true,
+            state_version: StateVersion::new(1),
+            state_root: Hash::ZERO,
+            signature: rainsonet_core::Signature::from_bytes([0u8; 64]),
+            timestamp: Timestamp::now(),
+        };
+        publisher.broadcast_vote(vote.clone()).unwrap();
+        drive(&mut publisher, Duration::from_millis(500)).await;
+        assert!(vote_received(&mut early_events).await);
+        
+        // A peer joining after the original send gets the identical vote re-sent
+        let (late_addr, mut late_events) = spawn_service(None).await;
+        publisher.connect_bootstrap(&[late_addr.to_string()]).await.unwrap();
+        drive(&mut publisher, Duration::from_secs(2)).await;
+        
+        publisher.broadcast_vote(vote).unwrap();
+        drive(&mut publisher, Duration::from_millis(500)).await;
+        assert!(vote_received(&mut late_events).await);
+    }
+    
+    #[tokio::test]
+    async fn test_transaction_without_peers_rebroadcast_on_join() {
+        let (_, mut publisher, _publisher_events) = create_service().await;
+        let tx_id = Hash::from_bytes([2u8; 32]);
+        
+        // No peers yet: the broadcast succeeds and the transaction waits
+        publisher.broadcast_transaction(tx_id, vec![1, 2, 3]).unwrap();
+        assert_eq!(publisher.unsent_count(), 1);
+        
+        let (peer_addr, mut peer_events) = spawn_service(None).await;
+        publisher.connect_bootstrap(&[peer_addr.to_string()]).await.unwrap();
+        drive(&mut publisher, Duration::from_secs(2)).await;
+        assert_eq!(publisher.unsent_count(), 0);
+        
+        let received = tokio::time::timeout(Duration::from_secs(5), async {
+            while let Some(event) = peer_events.recv().await {
+                if let NetworkEvent::TransactionReceived(id, _) = event {
+                    return Some(id);
+                }
+            }
+            None
+        })
+        .await
+        .unwrap_or(None);
+        assert_eq!(received, Some(tx_id));
+    }
+    
+    /// Pending transactions held in memory, standing in for a mempool
+    #[derive(Default)]
+    struct PendingTransactions(Mutex<HashMap<Hash, Vec<u8>>>);
+    
+    impl TransactionSource for PendingTransactions {
+        fn pending_tx_ids(&self, limit: usize) -> Vec<Hash> {
+            self.0.lock().keys().take(limit).copied().collect()
+        }
+        
+        fn has_transaction(&self, tx_id: &Hash) -> bool {
+            self.0.lock().contains_key(tx_id)
+        }
+        
+        fn transaction_data(&self, tx_id: &Hash) -> Option<Vec<u8>> {
+            self.0.lock().get(tx_id).cloned()
+        }
+    }
+    
+    #[tokio::test]
+    async fn test_late_peer_pulls_pending_transactions() {
+        let pending: HashMap<Hash, Vec<u8>> =
+            (1..=3u8).map(|i| (Hash::from_bytes([i; 32]), vec![i; 8])).collect();
+        let (holder_addr, holder, _holder_events) = create_service().await;
+        let mut holder = holder
+            .with_transaction_source(Arc::new(PendingTransactions(Mutex::new(pending.clone()))));
+        tokio::spawn(async move { holder.run().await });
+        
+        // The transactions were gossiped before this node connected
+        let (_, node, mut events) = create_service().await;
+        let mut node = node.with_transaction_source(Arc::new(PendingTransactions::default()));
+        node.connect_bootstrap(&[holder_addr.to_string()]).await.unwrap();
+        drive(&mut node, Duration::from_secs(3)).await;
+        
+        let mut received = HashMap::new();
+        while let Ok(event) = events.try_recv() {
+            if let NetworkEvent::TransactionReceived(tx_id, data) = event {
+                received.insert(tx_id, data);
+            }
+        }
+        assert_eq!(received, pending);
+    }
+    
+    #[tokio::test]
+    async fn test_different_genesis_peers_disconnect() {
+        let (_, service, _events) = create_service().await;
+        let mut node = service.with_genesis_hash(Hash::from_bytes([1u8; 32]));
+        let (other_addr, other, mut other_events) = create_service().await;
+        let mut other = other.with_genesis_hash(Hash::from_bytes([2u8; 32]));
+        tokio::spawn(async move { other.run().await });
+        
+        node.connect_bootstrap(&[other_addr.to_string()]).await.unwrap();
+        drive(&mut node, Duration::from_secs(3)).await;
+        
+        let disconnected = tokio::time::timeout(Duration::from_secs(5), async {
+            while let Some(event) = other_events.recv().await {
+                if matches!(event, NetworkEvent::PeerDisconnected(_)) {
+                    return true;
+                }
+            }
+            false
+        })
+        .await
+        .unwrap_or(false);
+        assert!(disconnected);
+    }
+    
+    #[tokio::test]
+    async fn test_dial_command_connects() {
+        let (target_addr, _target_events) = spawn_service(None).await;
+        let (command_tx, command_rx) = create_network_commands();
+        let (_, mut events) = spawn_service(Some(command_rx)).await;
+        
+        command_tx.send(NetworkCommand::Dial(target_addr)).await.unwrap();
+        
+        let event = tokio::time::timeout(Duration::from_secs(10), events.recv())
+            .await
+            .expect("dial command was not executed")
+            .unwrap();
+        assert!(matches!(event, NetworkEvent::PeerConnected(_)));
+    }
+    
+    #[test]
+    fn test_state_announcer_throttle() {
+        let announcer = StateAnnouncer::new(1000);
+        let start = Timestamp::from_millis(10_000);
+        
+        assert!(announcer.take_due(start).is_none());
+        
+        announcer.announce(StateVersion::new(1), Hash::from_bytes([1u8; 32]));
+        assert_eq!(announcer.take_due(start).unwrap().version, StateVersion::new(1));
+        
+        // Bursts within the interval collapse into the latest version
+        announcer.announce(StateVersion::new(2), Hash::from_bytes([2u8; 32]));
+        announcer.announce(StateVersion::new(3), Hash::from_bytes([3u8; 32]));
+        assert!(announcer.take_due(Timestamp::from_millis(10_500)).is_none());
+        
+        let announce = announcer.take_due(Timestamp::from_millis(11_000)).unwrap();
+        assert_eq!(announce.version, StateVersion::new(3));
+        assert_eq!(announce.root, Hash::from_bytes([3u8; 32]));
+        
+        // Stale versions are not re-announced
+        announcer.announce(StateVersion::new(2), Hash::from_bytes([2u8; 32]));
+        assert!(announcer.take_due(Timestamp::from_millis(20_000)).is_none());
+    }
+    
+    #[test]
+    fn test_state_announce_updates_peer() {
+        // Node A finalizes a new version and announces it
+        let peer_a = PeerId::random();
+        let node_a = node_id_from_peer_id(&peer_a);
+        let announcer_a = StateAnnouncer::default();
+        announcer_a.announce(StateVersion::new(5), Hash::from_bytes([5u8; 32]));
+        
+        let data = Message::StateAnnounce(announcer_a.take_due(Timestamp::now()).unwrap()).to_bytes();
+        
+        // Node B knows A only from the handshake-time version
+        let manager_b = PeerManager::new(10);
+        manager_b.add_peer(PeerInfo::new(node_a, true));
+        
+        let Some(Message::StateAnnounce(announce)) = Message::from_bytes(&data) else {
+            panic!("expected state announcement");
+        };
+        assert!(apply_state_announce(&manager_b, &node_a, &announce));
+        
+        let info = manager_b.get_peer(&node_a).unwrap();
+        assert_eq!(info.state_version, StateVersion::new(5));
+        assert_eq!(info.state_root, Hash::from_bytes([5u8; 32]));
+        assert_eq!(manager_b.peers_at_version(StateVersion::new(5)).len(), 1);
+        
+        // An older announcement delivered late does not roll the peer back
+        let late = StateAnnounceMessage::new(StateVersion::new(4), Hash::from_bytes([4u8; 32]));
+        assert!(!apply_state_announce(&manager_b, &node_a, &late));
+        assert_eq!(manager_b.get_peer(&node_a).unwrap().state_version, StateVersion::new(5));
     }
 }
@@ -48,6 +48,11 @@ impl PeerInfo {
         let now = Timestamp::now();
         now.as_millis() - self.last_seen.as_millis() > timeout_ms
     }
+    
+    /// Whether nothing has been heard from the peer for `timeout_ms` at `now`
+    pub fn is_idle_at(&self, now: Timestamp, timeout_ms: u64) -> bool {
+        now.as_millis().saturating_sub(self.last_seen.as_millis()) > timeout_ms
+    }
 }
 
 /// Peer manager for tracking connected peers
@@ -93,6 +98,15 @@ impl PeerManager {
         }
     }
     
+    /// Record whether a peer is a validator, as checked against a
+    /// [`ValidatorRegistry`]
+    pub fn set_validator(&self, node_id: &NodeId, is_validator: bool) {
+        if let Some(peer) = self.peers.write().get_mut(node_id) {
+            peer.is_validator = is_validator;
+            peer.update_last_seen();
+        }
+    }
+    
     /// Update peer's state
     pub fn update_peer_state(&self, node_id: &NodeId, version: StateVersion, root: StateRoot) {
         if let Some(peer) = self.peers.write().get_mut(node_id) {
@@ -141,6 +155,19 @@ impl PeerManager {
         stale
     }
     
+    /// Non-validator peers idle for longer than `timeout_ms` at `now`.
+    ///
+    /// Validators are never reported: they must stay connected for
+    /// consensus even through quiet periods.
+    pub fn idle_non_validators(&self, now: Timestamp, timeout_ms: u64) -> Vec<NodeId> {
+        self.peers
+            .read()
+            .values()
+            .filter(|p| !p.is_validator && p.is_idle_at(now, timeout_ms))
+            .map(|p| p.node_id)
+            .collect()
+    }
+    
     /// Number of connected peers
     pub fn peer_count(&self) -> usize {
         self.peers.read().len()
@@ -152,6 +179,13 @@ impl PeerManager {
     }
 }
 
+/// Decides which peers are validators, typically the node's validator set.
+/// The role a peer claims in its handshake is not trusted on its own.
+pub trait ValidatorRegistry: Send + Sync {
+    /// Whether `node_id` is an active validator
+    fn is_validator(&self, node_id: &NodeId) -> bool;
+}
+
 /// Shared peer manager
 pub type SharedPeerManager = Arc<PeerManager>;
 
@@ -181,6 +215,24 @@ mod tests {
         assert_eq!(manager.peer_count(), 1);
     }
     
+    #[test]
+    fn test_idle_non_validators() {
+        let manager = PeerManager::new(10);
+        let validator = NodeId::from_bytes([1u8; 32]);
+        let light_client = NodeId::from_bytes([2u8; 32]);
+        manager.add_peer(PeerInfo::new(validator, false));
+        manager.add_peer(PeerInfo::new(light_client, false));
+        
+        // The handshake identifies the first peer as a validator
+        manager.set_validator(&validator, true);
+        
+        let connected_at = manager.get_peer(&light_client).unwrap().last_seen;
+        let later = Timestamp::from_millis(connected_at.as_millis() + 61_000);
+        
+        assert!(manager.idle_non_validators(connected_at, 60_000).is_empty());
+        assert_eq!(manager.idle_non_validators(later, 60_000), vec![light_client]);
+    }
+    
     #[test]
     fn test_max_peers() {
         let manager = PeerManager::new(2);
@@ -0,0 +1,159 @@
+//! Bloom filter of recently seen transactions
+
+use rainsonet_core::Hash;
+
+/// Transactions per filter generation: a minute of traffic at ~1000 tx/s
+pub const SEEN_TRANSACTIONS_CAPACITY: usize = 65_536;
+
+/// Target false-positive rate of a full generation
+pub const SEEN_FALSE_POSITIVE_RATE: f64 = 0.001;
+
+/// Fixed-size bloom filter over hashes
+struct Bloom {
+    bits: Vec<u64>,
+    num_bits: u64,
+    num_hashes: u32,
+    len: usize,
+}
+
+impl Bloom {
+    fn new(num_bits: u64, num_hashes: u32) -> Self {
+        Self {
+            bits: vec![0; num_bits.div_ceil(64) as usize],
+            num_bits,
+            num_hashes,
+            len: 0,
+        }
+    }
+    
+    /// Bit positions for `id` by double hashing. Transaction IDs are already
+    /// uniform hashes, so two of their words serve as the base hashes.
+    fn positions(&self, id: &Hash) -> impl Iterator<Item = u64> {
+        let bytes = id.as_bytes();
+        let h1 = u64::from_le_bytes(bytes[..8].try_into().expect("8 bytes"));
+        let h2 = u64::from_le_bytes(bytes[8..16].try_into().expect("8 bytes")) | 1;
+        let num_bits = self.num_bits;
+        (0..self.num_hashes as u64).map(move |i| h1.wrapping_add(i.wrapping_mul(h2)) % num_bits)
+    }
+    
+    fn insert(&mut self, id: &Hash) {
+        for bit in self.positions(id) {
+            self.bits[(bit / 64) as usize] |= 1 << (bit % 64);
+        }
+        self.len += 1;
+    }
+    
+    fn contains(&self, id: &Hash) -> bool {
+        self.positions(id)
+            .all(|bit| self.bits[(bit / 64) as usize] & (1 << (bit % 64)) != 0)
+    }
+    
+    fn clear(&mut self) {
+        self.bits.iter_mut().for_each(|word| *word = 0);
+        self.len = 0;
+    }
+}
+
+/// Recently seen transaction IDs.
+///
+/// Two bloom filter generations rotate once the current one holds
+/// `capacity` IDs, so memory stays bounded and an ID is remembered for
+/// between one and two generations. Lookups may return false positives at
+/// roughly the configured rate, never false negatives.
+pub struct SeenFilter {
+    current: Bloom,
+    previous: Bloom,
+    capacity: usize,
+}
+
+impl SeenFilter {
+    /// Size each generation for `capacity` IDs at `false_positive_rate`
+    pub fn new(capacity: usize, false_positive_rate: f64) -> Self {
+        let capacity = capacity.max(1);
+        let ln2 = std::f64::consts::LN_2;
+        let num_bits = (-(capacity as f64) * false_positive_rate.ln() / (ln2 * ln2))
+            .ceil()
+            .max(64.0) as u64;
+        let num_hashes = ((num_bits as f64 / capacity as f64) * ln2).round().max(1.0) as u32;
+        
+        Self {
+            current: Bloom::new(num_bits, num_hashes),
+            previous: Bloom::new(num_bits, num_hashes),
+            capacity,
+        }
+    }
+    
+    /// Check if `id` was (probably) seen recently
+    pub fn contains(&self, id: &Hash) -> bool {
+        self.current.contains(id) || self.previous.contains(id)
+    }
+    
+    /// Record `id`, returning false if it was (probably) already seen
+    pub fn insert(&mut self, id: &Hash) -> bool {
+        if self.contains(id) {
+            return false;
+        }
+        
+        if self.current.len >= self.capacity {
+            std::mem::swap(&mut self.current, &mut self.previous);
+            self.current.clear();
+        }
+        self.current.insert(id);
+        true
+    }
+}
+
+impl Default for SeenFilter {
+    fn default() -> Self {
+        Self::new(SEEN_TRANSACTIONS_CAPACITY, SEEN_FALSE_POSITIVE_RATE)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rainsonet_crypto::hashing::hash;
+    
+    fn id(n: u64) -> Hash {
+        hash(&n.to_le_bytes())
+    }
+    
+    #[test]
+    fn test_insert_and_contains() {
+        let mut seen = SeenFilter::default();
+        
+        assert!(!seen.contains(&id(1)));
+        assert!(seen.insert(&id(1)));
+        assert!(seen.contains(&id(1)));
+        assert!(!seen.insert(&id(1)));
+    }
+    
+    #[test]
+    fn test_old_generations_are_forgotten() {
+        let mut seen = SeenFilter::new(100, 0.001);
+        seen.insert(&id(0));
+        
+        // Still remembered through the first rotation
+        for n in 1..150 {
+            seen.insert(&id(n));
+        }
+        assert!(seen.contains(&id(0)));
+        
+        for n in 150..400 {
+            seen.insert(&id(n));
+        }
+        assert!(!seen.contains(&id(0)));
+    }
+    
+    #[test]
+    fn test_false_positive_rate() {
+        let mut seen = SeenFilter::new(10_000, 0.001);
+        for n in 0..10_000 {
+            seen.insert(&id(n));
+        }
+        
+        let false_positives = (10_000..60_000).filter(|n| seen.contains(&id(*n))).count();
+        // 50 expected at the target rate
+        assert!(false_positives < 150, "{} false positives", false_positives);
+    }
+}
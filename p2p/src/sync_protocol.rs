@@ -0,0 +1,137 @@
+//! State sync over a request-response protocol
+//!
+//! Sync requests and responses used to be gossiped on the sync topic, where
+//! every subscriber received them and any peer could answer a request in
+//! the requester's name. They now travel on a dedicated libp2p
+//! request-response protocol: a request goes to one peer over its own
+//! connection and only that peer's answer is delivered back.
+
+use crate::message::{SyncRequestMessage, SyncResponseMessage};
+use async_trait::async_trait;
+use futures::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use libp2p::StreamProtocol;
+use serde::{de::DeserializeOwned, Serialize};
+use std::io;
+
+/// Protocol name of state sync
+pub const SYNC_PROTOCOL: StreamProtocol = StreamProtocol::new("/rainsonet/sync/1");
+
+/// Largest encoded sync request accepted
+pub const MAX_SYNC_REQUEST_SIZE: u64 = 1024;
+
+/// Largest encoded sync response accepted, which bounds snapshot answers
+pub const MAX_SYNC_RESPONSE_SIZE: u64 = 64 * 1024 * 1024;
+
+/// Answers sync requests from the local state, typically the node runtime
+pub trait SyncSource: Send + Sync {
+    /// Response to `request`, or `None` if it cannot be served
+    fn sync_response(&self, request: &SyncRequestMessage) -> Option<SyncResponseMessage>;
+}
+
+/// Bincode codec for [`SYNC_PROTOCOL`]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SyncCodec;
+
+#[async_trait]
+impl libp2p::request_response::Codec for SyncCodec {
+    type Protocol = StreamProtocol;
+    type Request = SyncRequestMessage;
+    type Response = SyncResponseMessage;
+    
+    async fn read_request<T>(&mut self, _: &StreamProtocol, io: &mut T) -> io::Result<Self::Request>
+    where
+        T: AsyncRead + Unpin + Send,
+    {
+        read_message(io, MAX_SYNC_REQUEST_SIZE).await
+    }
+    
+    async fn read_response<T>(
+        &mut self,
+        _: &StreamProtocol,
+        io: &mut T,
+    ) -> io::Result<Self::Response>
+    where
+        T: AsyncRead + Unpin + Send,
+    {
+        read_message(io, MAX_SYNC_RESPONSE_SIZE).await
+    }
+    
+    async fn write_request<T>(
+        &mut self,
+        _: &StreamProtocol,
+        io: &mut T,
+        request: Self::Request,
+    ) -> io::Result<()>
+    where
+        T: AsyncWrite + Unpin + Send,
+    {
+        write_message(io, &request).await
+    }
+    
+    async fn write_response<T>(
+        &mut self,
+        _: &StreamProtocol,
+        io: &mut T,
+        response: Self::Response,
+    ) -> io::Result<()>
+    where
+        T: AsyncWrite + Unpin + Send,
+    {
+        write_message(io, &response).await
+    }
+}
+
+/// Read one message, which the sender ends by closing its side
+async fn read_message<M, T>(io: &mut T, limit: u64) -> io::Result<M>
+where
+    M: DeserializeOwned,
+    T: AsyncRead + Unpin + Send,
+{
+    let mut bytes = Vec::new();
+    io.take(limit + 1).read_to_end(&mut bytes).await?;
+    if bytes.len() as u64 > limit {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, "Sync message too large"));
+    }
+    bincode::deserialize(&bytes).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
+
+async fn write_message<M, T>(io: &mut T, message: &M) -> io::Result<()>
+where
+    M: Serialize,
+    T: AsyncWrite + Unpin + Send,
+{
+    let bytes = bincode::serialize(message)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+    io.write_all(&bytes).await?;
+    io.close().await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use libp2p::request_response::Codec;
+    use rainsonet_core::{NodeId, StateVersion};
+    
+    #[test]
+    fn test_codec_round_trip_and_limit() {
+        futures::executor::block_on(async {
+            let request = SyncRequestMessage::window(
+                StateVersion::new(3),
+                StateVersion::new(7),
+                NodeId::from_bytes([1u8; 32]),
+            );
+            let mut codec = SyncCodec;
+            let mut buffer = futures::io::Cursor::new(Vec::new());
+            codec.write_request(&SYNC_PROTOCOL, &mut buffer, request).await.unwrap();
+            
+            let mut reader = futures::io::Cursor::new(buffer.into_inner());
+            let decoded = codec.read_request(&SYNC_PROTOCOL, &mut reader).await.unwrap();
+            assert_eq!(decoded.from_version, StateVersion::new(3));
+            assert_eq!(decoded.to_version, Some(StateVersion::new(7)));
+            
+            let oversized = vec![0u8; MAX_SYNC_REQUEST_SIZE as usize + 1];
+            let mut reader = futures::io::Cursor::new(oversized);
+            assert!(codec.read_request(&SYNC_PROTOCOL, &mut reader).await.is_err());
+        });
+    }
+}
@@ -125,8 +125,11 @@ export class RelyoClient {
   async getStatus(): Promise<NodeStatus> {
     const data = await this.request<{
       node_id: string;
+      optimistic_version: number;
+      finalized_version: number;
       state_version: number;
       state_root: string;
+      hash_algorithm: 'blake3' | 'sha256';
       peer_count: number;
       is_validator: boolean;
       mempool_size: number;
@@ -134,8 +137,11 @@ export class RelyoClient {
     
     return {
       nodeId: data.node_id,
+      optimisticVersion: data.optimistic_version,
+      finalizedVersion: data.finalized_version,
       stateVersion: data.state_version,
       stateRoot: data.state_root,
+      hashAlgorithm: data.hash_algorithm,
       peerCount: data.peer_count,
       isValidator: data.is_validator,
       mempoolSize: data.mempool_size,
@@ -191,6 +197,10 @@ export class RelyoClient {
     const data = await this.request<{
       tx_id: string;
       status: string;
+      fee_adequate?: boolean;
+      suggested_fee?: string;
+      will_forward?: boolean;
+      warning?: string;
     }>('POST', '/transaction', {
       from: tx.from,
       to: tx.to,
@@ -204,6 +214,10 @@ export class RelyoClient {
     return {
       txId: data.tx_id,
       status: data.status as TransactionResponse['status'],
+      feeAdequate: data.fee_adequate,
+      suggestedFee: data.suggested_fee,
+      willForward: data.will_forward,
+      warning: data.warning,
     };
   }
   
@@ -214,11 +228,17 @@ export class RelyoClient {
     const data = await this.request<{
       tx_id: string;
       status: string;
+      version?: number;
+      confirmations?: number;
+      timestamp?: string;
     }>('GET', `/transaction/${txId}`);
     
     return {
       txId: data.tx_id,
       status: data.status as TransactionResponse['status'],
+      version: data.version,
+      confirmations: data.confirmations,
+      timestamp: data.timestamp,
     };
   }
   
@@ -249,19 +269,21 @@ export class RelyoClient {
   }
   
   /**
-   * Wait for transaction to be confirmed
+   * Wait for transaction to be finalized with at least `minConfirmations`
+   * finalized versions on top
    */
   async waitForTransaction(
     txId: Hash,
     timeoutMs: number = 60000,
-    pollIntervalMs: number = 1000
+    pollIntervalMs: number = 1000,
+    minConfirmations: number = 0
   ): Promise<TransactionResponse> {
     const startTime = Date.now();
     
     while (Date.now() - startTime < timeoutMs) {
       const tx = await this.getTransaction(txId);
       
-      if (tx.status === 'confirmed' || tx.status === 'failed') {
+      if (tx.status === 'finalized' && (tx.confirmations ?? 0) >= minConfirmations) {
         return tx;
       }
       
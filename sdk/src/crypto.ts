@@ -94,6 +94,9 @@ export function hashMultiple(...parts: Uint8Array[]): Uint8Array {
 
 /**
  * Create transaction hash (unique identifier)
+ *
+ * @deprecated Does not match the node's transaction IDs, use
+ * `getTransactionId`, which hashes the canonical encoding.
  */
 export function createTransactionHash(
   from: string,
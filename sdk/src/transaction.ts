@@ -2,8 +2,8 @@
  * Transaction utilities for RAINSONET/RELYO SDK
  */
 
-import { verify, createTransactionHash, createSigningMessage } from './crypto';
-import { hexToBytes, isValidAddress, isValidAmount, bytesToHex } from './utils';
+import { verify, hashBlake3, createSigningMessage } from './crypto';
+import { addressToBytes, addressToHex, hexToBytes, isValidAddress, isValidAmount, bytesToHex } from './utils';
 import type { SignedTransaction, Hash, Address, AmountWei, Nonce } from './types';
 import { Amount } from './types';
 
@@ -39,8 +39,8 @@ export async function validateTransaction(tx: SignedTransaction): Promise<{
   
   // Validate signature
   const message = createSigningMessage(
-    tx.from,
-    tx.to,
+    addressToHex(tx.from),
+    addressToHex(tx.to),
     tx.amount,
     tx.fee,
     tx.nonce,
@@ -60,17 +60,51 @@ export async function validateTransaction(tx: SignedTransaction): Promise<{
 }
 
 /**
- * Calculate transaction ID
+ * Encode an unsigned integer as `width` little-endian bytes
+ */
+function uintLE(value: bigint, width: number): Uint8Array {
+  const bytes = new Uint8Array(width);
+  for (let i = 0; i < width; i++) {
+    bytes[i] = Number(value & 0xffn);
+    value >>= 8n;
+  }
+  return bytes;
+}
+
+/**
+ * Canonical transaction encoding, as hashed by nodes for the transaction ID.
+ *
+ * Fixed width, integers little-endian: `"RELYO_TX:"`, from (32), to (32),
+ * amount (u128), fee (u128), nonce (u64), timestamp in ms (u64),
+ * public key (32), signature (64).
+ */
+export function encodeCanonicalTransaction(tx: SignedTransaction): Uint8Array {
+  const parts = [
+    new TextEncoder().encode('RELYO_TX:'),
+    addressToBytes(tx.from),
+    addressToBytes(tx.to),
+    uintLE(BigInt(tx.amount), 16),
+    uintLE(BigInt(tx.fee), 16),
+    uintLE(BigInt(tx.nonce), 8),
+    uintLE(BigInt(tx.timestamp), 8),
+    hexToBytes(tx.publicKey),
+    hexToBytes(tx.signature),
+  ];
+  
+  const encoded = new Uint8Array(parts.reduce((sum, p) => sum + p.length, 0));
+  let offset = 0;
+  for (const part of parts) {
+    encoded.set(part, offset);
+    offset += part.length;
+  }
+  return encoded;
+}
+
+/**
+ * Calculate transaction ID, matching the node's
  */
 export function getTransactionId(tx: SignedTransaction): Hash {
-  return createTransactionHash(
-    tx.from,
-    tx.to,
-    tx.amount,
-    tx.fee,
-    tx.nonce,
-    tx.timestamp
-  );
+  return bytesToHex(hashBlake3(encodeCanonicalTransaction(tx)));
 }
 
 /**
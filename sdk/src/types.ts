@@ -2,7 +2,7 @@
  * Core types for RAINSONET/RELYO SDK
  */
 
-/** 32-byte address (hex string) */
+/** 32-byte address, hex or bech32 (`rlyo1...`) as the node is configured to show */
 export type Address = string;
 
 /** 32-byte hash (hex string) */
@@ -69,7 +69,21 @@ export interface SignedTransaction extends TransactionData {
  */
 export interface TransactionResponse {
   txId: Hash;
-  status: 'pending' | 'confirmed' | 'failed' | 'unknown';
+  status: 'pending' | 'included' | 'finalized' | 'unknown';
+  /** State version that included the transaction */
+  version?: number;
+  /** Finalized versions on top of `version` */
+  confirmations?: number;
+  /** Signed transaction timestamp, as RFC 3339 */
+  timestamp?: string;
+  /** On submission: false when the fee is valid but below `suggestedFee` */
+  feeAdequate?: boolean;
+  /** On submission: fee currently favoured by the node's mempool */
+  suggestedFee?: string;
+  /** On submission: false when no validator is reachable from the node */
+  willForward?: boolean;
+  /** On submission: why the transaction may never finalize */
+  warning?: string;
 }
 
 /**
@@ -77,8 +91,15 @@ export interface TransactionResponse {
  */
 export interface NodeStatus {
   nodeId: string;
+  /** Latest locally applied version, may still be reverted */
+  optimisticVersion: number;
+  /** Latest certificate-backed version */
+  finalizedVersion: number;
+  /** @deprecated Use `optimisticVersion` */
   stateVersion: number;
   stateRoot: Hash;
+  /** Algorithm state roots and proofs are hashed with, fixed by genesis */
+  hashAlgorithm: 'blake3' | 'sha256';
   peerCount: number;
   isValidator: boolean;
   mempoolSize: number;
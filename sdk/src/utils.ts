@@ -2,6 +2,8 @@
  * Utility functions for RAINSONET/RELYO SDK
  */
 
+import type { Address } from './types';
+
 /**
  * Convert bytes to hex string
  */
@@ -38,12 +40,127 @@ export function isValidHex(value: string): boolean {
   return /^[0-9a-fA-F]*$/.test(hex) && hex.length % 2 === 0;
 }
 
+/** Human-readable prefix of bech32 addresses, as nodes use by default */
+export const DEFAULT_ADDRESS_HRP = 'rlyo';
+
+const BECH32_CHARSET = 'qpzry9x8gf2tvdw0s3jn54khce6mua7l';
+const BECH32_GENERATOR = [0x3b6a57b2, 0x26508e6d, 0x1ea119fa, 0x3d4233dd, 0x2a1462b3];
+const BECH32_MAX_LENGTH = 90;
+
+function bech32Polymod(values: number[]): number {
+  let chk = 1;
+  for (const value of values) {
+    const top = chk >>> 25;
+    chk = ((chk & 0x1ffffff) << 5) ^ value;
+    for (let i = 0; i < 5; i++) {
+      if ((top >>> i) & 1) {
+        chk ^= BECH32_GENERATOR[i];
+      }
+    }
+  }
+  return chk >>> 0;
+}
+
+function bech32HrpExpand(hrp: string): number[] {
+  const codes = Array.from(hrp, (c) => c.charCodeAt(0));
+  return [...codes.map((c) => c >> 5), 0, ...codes.map((c) => c & 31)];
+}
+
+function convertBits(data: number[], from: number, to: number, pad: boolean): number[] {
+  const maxValue = (1 << to) - 1;
+  let acc = 0;
+  let bits = 0;
+  const out: number[] = [];
+  
+  for (const value of data) {
+    acc = ((acc << from) | value) & 0xfff;
+    bits += from;
+    while (bits >= to) {
+      bits -= to;
+      out.push((acc >> bits) & maxValue);
+    }
+  }
+  
+  if (pad) {
+    if (bits > 0) {
+      out.push((acc << (to - bits)) & maxValue);
+    }
+  } else if (bits >= from || ((acc << (to - bits)) & maxValue) !== 0) {
+    throw new Error('Invalid bech32 padding');
+  }
+  return out;
+}
+
 /**
- * Check if string is valid address (64 hex chars)
+ * Bech32 form of an address, e.g. `rlyo1...`
  */
-export function isValidAddress(address: string): boolean {
-  const hex = address.startsWith('0x') ? address.slice(2) : address;
-  return isValidHex(address) && hex.length === 64;
+export function toBech32Address(address: Address, hrp: string = DEFAULT_ADDRESS_HRP): string {
+  const values = convertBits(Array.from(addressToBytes(address, hrp)), 8, 5, true);
+  const polymod = bech32Polymod([...bech32HrpExpand(hrp), ...values, 0, 0, 0, 0, 0, 0]) ^ 1;
+  const checksum = Array.from({ length: 6 }, (_, i) => (polymod >>> (5 * (5 - i))) & 31);
+  return `${hrp}1${[...values, ...checksum].map((v) => BECH32_CHARSET[v]).join('')}`;
+}
+
+/**
+ * Decode an address given in hex, with or without `0x`, or in bech32 under
+ * the prefix `hrp`; throws if it is neither
+ */
+export function addressToBytes(address: Address, hrp: string = DEFAULT_ADDRESS_HRP): Uint8Array {
+  if (address.toLowerCase().startsWith(`${hrp}1`)) {
+    return decodeBech32Address(address, hrp);
+  }
+  if (!isValidHex(address)) {
+    throw new Error('Invalid hex address');
+  }
+  const bytes = hexToBytes(address);
+  if (bytes.length !== 32) {
+    throw new Error(`Expected 32 address bytes, got ${bytes.length}`);
+  }
+  return bytes;
+}
+
+/**
+ * Hex form of an address given in either format, as transactions sign it
+ */
+export function addressToHex(address: Address, hrp: string = DEFAULT_ADDRESS_HRP): string {
+  return bytesToHex(addressToBytes(address, hrp));
+}
+
+function decodeBech32Address(address: string, hrp: string): Uint8Array {
+  if (address.length > BECH32_MAX_LENGTH) {
+    throw new Error(`Bech32 address is longer than ${BECH32_MAX_LENGTH} characters`);
+  }
+  if (address !== address.toLowerCase() && address !== address.toUpperCase()) {
+    throw new Error('Bech32 address mixes upper and lower case');
+  }
+  const data = address.toLowerCase().slice(hrp.length + 1);
+  const values = Array.from(data, (c) => {
+    const value = BECH32_CHARSET.indexOf(c);
+    if (value < 0) {
+      throw new Error(`Invalid bech32 character ${c}`);
+    }
+    return value;
+  });
+  if (values.length < 6 || bech32Polymod([...bech32HrpExpand(hrp), ...values]) !== 1) {
+    throw new Error('Bech32 checksum mismatch');
+  }
+  const bytes = convertBits(values.slice(0, -6), 5, 8, false);
+  if (bytes.length !== 32) {
+    throw new Error(`Expected 32 address bytes, got ${bytes.length}`);
+  }
+  return new Uint8Array(bytes);
+}
+
+/**
+ * Check if string is a valid address, 64 hex chars or bech32 under `hrp`
+ */
+export function isValidAddress(address: string, hrp: string = DEFAULT_ADDRESS_HRP): boolean {
+  try {
+    addressToBytes(address, hrp);
+    return true;
+  } catch {
+    return false;
+  }
 }
 
 /**
@@ -9,7 +9,7 @@ import {
   sign,
   createSigningMessage,
 } from './crypto';
-import { bytesToHex, hexToBytes, isValidAddress } from './utils';
+import { addressToHex, bytesToHex, hexToBytes, isValidAddress } from './utils';
 import type {
   Address,
   PublicKey,
@@ -157,10 +157,13 @@ export class Wallet {
     
     const timestamp = params.timestamp ?? Date.now();
     
+    // Nodes sign over hex addresses, whichever format they display
+    const to = addressToHex(params.to);
+    
     // Create signing message
     const message = createSigningMessage(
       this.address,
-      params.to,
+      to,
       amount,
       fee,
       params.nonce,
@@ -173,7 +176,7 @@ export class Wallet {
     
     return {
       from: this.address,
-      to: params.to,
+      to,
       amount,
       fee,
       nonce: params.nonce,
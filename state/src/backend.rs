@@ -0,0 +1,295 @@
+//! Key-value backends for the persistent state store
+//!
+//! [`PersistentStateStore`](crate::PersistentStateStore) keeps versioning,
+//! history and root computation to itself and reads and writes raw bytes
+//! through [`KvBackend`]. sled is always available; RocksDB is compiled in
+//! with the `rocksdb` feature.
+
+use rainsonet_core::{RainsonetError, RainsonetResult, StorageBackend};
+use sled::transaction::ConflictableTransactionError;
+use sled::{Db, Transactional, Tree};
+use std::path::Path;
+
+#[cfg(feature = "rocksdb")]
+pub use crate::rocks::RocksDbBackend;
+
+/// Keyspace within a backend
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Column {
+    /// Current state entries
+    State,
+    /// Store metadata such as the state version
+    Meta,
+    /// Diffs between consecutive versions
+    History,
+}
+
+impl Column {
+    pub const ALL: [Column; 3] = [Column::State, Column::Meta, Column::History];
+    
+    /// Name of the sled tree or RocksDB column family
+    pub fn name(self) -> &'static str {
+        match self {
+            Column::State => "state",
+            Column::Meta => "meta",
+            Column::History => "history",
+        }
+    }
+}
+
+/// Single write in a [`KvBatch`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum KvOp {
+    Set { column: Column, key: Vec<u8>, value: Vec<u8> },
+    Delete { column: Column, key: Vec<u8> },
+}
+
+impl KvOp {
+    pub fn column(&self) -> Column {
+        match self {
+            KvOp::Set { column, .. } | KvOp::Delete { column, .. } => *column,
+        }
+    }
+}
+
+/// Writes applied together by [`KvBackend::write_batch`]
+#[derive(Debug, Clone, Default)]
+pub struct KvBatch {
+    ops: Vec<KvOp>,
+}
+
+impl KvBatch {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    
+    pub fn set(&mut self, column: Column, key: &[u8], value: &[u8]) {
+        self.ops.push(KvOp::Set {
+            column,
+            key: key.to_vec(),
+            value: value.to_vec(),
+        });
+    }
+    
+    pub fn delete(&mut self, column: Column, key: &[u8]) {
+        self.ops.push(KvOp::Delete {
+            column,
+            key: key.to_vec(),
+        });
+    }
+    
+    pub fn ops(&self) -> &[KvOp] {
+        &self.ops
+    }
+    
+    pub fn is_empty(&self) -> bool {
+        self.ops.is_empty()
+    }
+}
+
+/// Entries yielded by [`KvBackend::scan_prefix`]
+pub type KvIter<'a> = Box<dyn Iterator<Item = RainsonetResult<(Vec<u8>, Vec<u8>)>> + 'a>;
+
+/// Ordered key-value database with separate columns
+pub trait KvBackend: Send + Sync {
+    fn get(&self, column: Column, key: &[u8]) -> RainsonetResult<Option<Vec<u8>>>;
+    
+    fn set(&self, column: Column, key: &[u8], value: &[u8]) -> RainsonetResult<()>;
+    
+    fn delete(&self, column: Column, key: &[u8]) -> RainsonetResult<()>;
+    
+    /// Apply every write in `batch` atomically, across columns
+    fn write_batch(&self, batch: KvBatch) -> RainsonetResult<()>;
+    
+    /// Lazily iterate over entries of `column` whose key starts with
+    /// `prefix`, in key order. An empty prefix scans the whole column.
+    fn scan_prefix(&self, column: Column, prefix: &[u8]) -> KvIter<'_>;
+    
+    /// Persist all writes made so far
+    fn flush(&self) -> RainsonetResult<()>;
+    
+    /// Reclaim space held by deleted or overwritten entries of `column`
+    fn compact(&self, _column: Column) -> RainsonetResult<()> {
+        Ok(())
+    }
+}
+
+/// Open the backend selected by `backend` at `path`
+pub fn open_backend(
+    path: impl AsRef<Path>,
+    backend: StorageBackend,
+) -> RainsonetResult<Box<dyn KvBackend>> {
+    match backend {
+        StorageBackend::Sled => Ok(Box::new(SledBackend::open(path)?)),
+        #[cfg(feature = "rocksdb")]
+        StorageBackend::RocksDb => Ok(Box::new(RocksDbBackend::open(path)?)),
+        #[cfg(not(feature = "rocksdb"))]
+        StorageBackend::RocksDb => Err(RainsonetError::ConfigError(
+            "RocksDB storage requires building with the `rocksdb` feature".into(),
+        )),
+    }
+}
+
+/// Backends compiled into this build
+#[cfg(test)]
+pub(crate) fn compiled_backends() -> Vec<StorageBackend> {
+    #[allow(unused_mut)]
+    let mut backends = vec![StorageBackend::Sled];
+    #[cfg(feature = "rocksdb")]
+    backends.push(StorageBackend::RocksDb);
+    backends
+}
+
+pub(crate) fn internal(e: impl std::fmt::Display) -> RainsonetError {
+    RainsonetError::Internal(e.to_string())
+}
+
+/// sled backend, one tree per column
+pub struct SledBackend {
+    db: Db,
+    state: Tree,
+    meta: Tree,
+    history: Tree,
+}
+
+impl SledBackend {
+    pub fn open(path: impl AsRef<Path>) -> RainsonetResult<Self> {
+        let db = sled::open(path).map_err(internal)?;
+        let state = db.open_tree(Column::State.name()).map_err(internal)?;
+        let meta = db.open_tree(Column::Meta.name()).map_err(internal)?;
+        let history = db.open_tree(Column::History.name()).map_err(internal)?;
+        
+        Ok(Self {
+            db,
+            state,
+            meta,
+            history,
+        })
+    }
+    
+    fn tree(&self, column: Column) -> &Tree {
+        match column {
+            Column::State => &self.state,
+            Column::Meta => &self.meta,
+            Column::History => &self.history,
+        }
+    }
+}
+
+impl KvBackend for SledBackend {
+    fn get(&self, column: Column, key: &[u8]) -> RainsonetResult<Option<Vec<u8>>> {
+        self.tree(column)
+            .get(key)
+            .map(|opt| opt.map(|v| v.to_vec()))
+            .map_err(internal)
+    }
+    
+    fn set(&self, column: Column, key: &[u8], value: &[u8]) -> RainsonetResult<()> {
+        self.tree(column).insert(key, value).map_err(internal)?;
+        Ok(())
+    }
+    
+    fn delete(&self, column: Column, key: &[u8]) -> RainsonetResult<()> {
+        self.tree(column).remove(key).map_err(internal)?;
+        Ok(())
+    }
+    
+    fn write_batch(&self, batch: KvBatch) -> RainsonetResult<()> {
+        // sled batches are per tree, so span the trees with a transaction
+        (&self.state, &self.meta, &self.history)
+            .transaction(|(state, meta, history)| {
+                for op in batch.ops() {
+                    let tree = match op.column() {
+                        Column::State => state,
+                        Column::Meta => meta,
+                        Column::History => history,
+                    };
+                    match op {
+                        KvOp::Set { key, value, .. } => {
+                            tree.insert(key.as_slice(), value.as_slice())?;
+                        }
+                        KvOp::Delete { key, .. } => {
+                            tree.remove(key.as_slice())?;
+                        }
+                    }
+                }
+                Ok::<_, ConflictableTransactionError>(())
+            })
+            .map_err(internal)
+    }
+    
+    fn scan_prefix(&self, column: Column, prefix: &[u8]) -> KvIter<'_> {
+        Box::new(self.tree(column).scan_prefix(prefix).map(|result| {
+            result
+                .map(|(key, value)| (key.to_vec(), value.to_vec()))
+                .map_err(internal)
+        }))
+    }
+    
+    fn flush(&self) -> RainsonetResult<()> {
+        self.db.flush().map_err(internal)?;
+        Ok(())
+    }
+    
+    fn compact(&self, column: Column) -> RainsonetResult<()> {
+        // sled has no explicit compaction. Rewriting the live entries moves
+        // them out of fragmented segments, which its cleaner then frees.
+        let tree = self.tree(column);
+        let mut batch = sled::Batch::default();
+        for result in tree.iter() {
+            let (key, value) = result.map_err(internal)?;
+            batch.insert(key, value);
+        }
+        tree.apply_batch(batch).map_err(internal)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+    
+    #[test]
+    fn test_backend_columns_batches_and_scans() {
+        for kind in compiled_backends() {
+            let tmp = TempDir::new().unwrap();
+            let backend = open_backend(tmp.path(), kind).unwrap();
+            
+            backend.set(Column::State, b"a:2", b"two").unwrap();
+            backend.set(Column::Meta, b"a:1", b"meta").unwrap();
+            
+            let mut batch = KvBatch::new();
+            batch.set(Column::State, b"a:1", b"one");
+            batch.set(Column::State, b"b:1", b"other");
+            batch.delete(Column::State, b"a:2");
+            batch.set(Column::History, b"h", b"diff");
+            backend.write_batch(batch).unwrap();
+            backend.flush().unwrap();
+            
+            let scanned: Vec<_> = backend
+                .scan_prefix(Column::State, b"a:")
+                .collect::<RainsonetResult<_>>()
+                .unwrap();
+            assert_eq!(scanned, vec![(b"a:1".to_vec(), b"one".to_vec())], "{:?}", kind);
+            
+            // Columns do not see each other's keys
+            assert_eq!(backend.get(Column::Meta, b"a:1").unwrap(), Some(b"meta".to_vec()));
+            assert_eq!(backend.get(Column::History, b"h").unwrap(), Some(b"diff".to_vec()));
+            assert_eq!(backend.get(Column::State, b"h").unwrap(), None);
+            assert_eq!(backend.scan_prefix(Column::State, b"").count(), 2);
+            
+            backend.delete(Column::State, b"b:1").unwrap();
+            assert_eq!(backend.get(Column::State, b"b:1").unwrap(), None);
+        }
+    }
+    
+    #[cfg(not(feature = "rocksdb"))]
+    #[test]
+    fn test_rocksdb_requires_feature() {
+        let tmp = TempDir::new().unwrap();
+        assert!(matches!(
+            open_backend(tmp.path(), StorageBackend::RocksDb),
+            Err(RainsonetError::ConfigError(_))
+        ));
+    }
+}
@@ -5,20 +5,32 @@
 //!
 //! # Stores
 //! - `MemoryStateStore`: In-memory store for testing and light nodes
-//! - `PersistentStateStore`: Sled-backed persistent storage
+//! - `PersistentStateStore`: Persistent storage over a `KvBackend`, sled
+//!   by default or RocksDB with the `rocksdb` feature
 //!
 //! # Snapshots
 //! - `StateSnapshot`: Point-in-time state snapshots for sync
 
+pub mod backend;
 pub mod memory;
 pub mod persistent;
+#[cfg(feature = "rocksdb")]
+mod rocks;
 pub mod snapshot;
 pub mod store;
 
+pub use backend::{open_backend, Column, KvBackend, KvBatch, KvOp, SledBackend};
+#[cfg(feature = "rocksdb")]
+pub use backend::RocksDbBackend;
 pub use memory::{create_memory_store, MemoryStateStore, SharedMemoryStateStore};
 pub use persistent::{create_persistent_store, PersistentStateStore, SharedPersistentStateStore};
-pub use snapshot::{SnapshotManager, StateSnapshot};
+pub use snapshot::{
+    SnapshotCodec, SnapshotInfo, SnapshotManager, StateSnapshot, DEFAULT_COMPRESSION_LEVEL,
+    MAX_SNAPSHOT_SIZE, SNAPSHOT_FORMAT_VERSION, SNAPSHOT_MAGIC,
+};
 pub use store::{
-    account_key, compute_state_root, parse_account_key, AccountState, StateBatch,
-    StateChangeOp, StateDiff, StateEntry,
+    account_key, compute_sorted_state_root, compute_state_proof, compute_state_root,
+    parse_account_key, validator_key,
+    AccountState, StateBatch, StateChangeOp, StateDiff, StateEntry, StateStore, ACCOUNT_PREFIX,
+    VALIDATOR_PREFIX,
 };
@@ -1,12 +1,19 @@
 //! In-memory state store for testing and light nodes
 
+use async_trait::async_trait;
 use dashmap::DashMap;
 use parking_lot::RwLock;
-use rainsonet_core::{Hash, RainsonetResult, StateRoot, StateVersion};
+use rainsonet_core::{
+    Hash, RainsonetError, RainsonetResult, StateChange, StateMutator, StateProvider, StateRoot,
+    StateVersion,
+};
+use rainsonet_crypto::hashing::{MerkleBuilder, MerkleProof};
+use std::collections::BTreeMap;
 use std::sync::Arc;
 
 use crate::store::{
-    account_key, compute_state_root, AccountState, StateChangeOp, StateDiff, StateEntry,
+    account_key, compute_state_proof, AccountState, StateChangeOp, StateDiff,
+    StateEntry, StateStore,
 };
 
 /// In-memory state store
@@ -14,6 +21,16 @@ pub struct MemoryStateStore {
     data: DashMap<Vec<u8>, Vec<u8>>,
     version: RwLock<StateVersion>,
     history: RwLock<Vec<StateDiff>>,
+    /// Held for writing while `data` changes, so it always matches it
+    merkle: RwLock<MerkleCache>,
+}
+
+/// Leaf hashes in key order, updated on every write so a root only has to
+/// hash the inner nodes again, and the root itself until the next write
+#[derive(Clone, Default)]
+struct MerkleCache {
+    leaves: BTreeMap<Vec<u8>, Hash>,
+    root: Option<StateRoot>,
 }
 
 impl MemoryStateStore {
@@ -22,13 +39,14 @@ impl MemoryStateStore {
             data: DashMap::new(),
             version: RwLock::new(StateVersion::new(0)),
             history: RwLock::new(Vec::new()),
+            merkle: RwLock::new(MerkleCache::default()),
         }
     }
     
     pub fn with_data(data: Vec<(Vec<u8>, Vec<u8>)>) -> Self {
         let store = Self::new();
         for (key, value) in data {
-            store.data.insert(key, value);
+            store.insert_entry(key, value);
         }
         store
     }
@@ -50,73 +68,264 @@ impl MemoryStateStore {
     }
     
     pub fn set(&self, key: &[u8], value: &[u8]) -> RainsonetResult<()> {
-        self.data.insert(key.to_vec(), value.to_vec());
+        self.insert_entry(key.to_vec(), value.to_vec());
         Ok(())
     }
     
     pub fn delete(&self, key: &[u8]) -> RainsonetResult<()> {
-        self.data.remove(key);
+        self.remove_entry(key);
         Ok(())
     }
     
     pub fn apply_batch(&self, changes: Vec<StateChangeOp>) -> RainsonetResult<StateVersion> {
         let old_version = *self.version.read();
-        let mut diff = StateDiff::new(old_version, old_version.next());
+        self.apply_changes(old_version, old_version.next(), changes)
+    }
+    
+    /// Apply a diff received from a peer, moving straight to its
+    /// `to_version`. Fails with `StateVersionMismatch`, changing nothing,
+    /// unless the store is at the diff's `from_version`, and with
+    /// `StateCorruption` if the changes do not produce the diff's root.
+    pub fn apply_diff(&self, diff: &StateDiff) -> RainsonetResult<StateVersion> {
+        diff.check_applies_to(self.version())?;
+        diff.check_root_after(self.iter_entries().map(Ok))?;
+        self.apply_changes(diff.from_version, diff.to_version, diff.to_changes())
+    }
+    
+    /// Apply `changes` and record them as one history diff
+    fn apply_changes(
+        &self,
+        old_version: StateVersion,
+        new_version: StateVersion,
+        changes: Vec<StateChangeOp>,
+    ) -> RainsonetResult<StateVersion> {
+        let mut diff = StateDiff::new(old_version, new_version);
         
         for change in changes {
             match change {
                 StateChangeOp::Set { key, value } => {
                     diff.add(key.clone(), value.clone());
-                    self.data.insert(key, value);
+                    self.insert_entry(key, value);
                 }
                 StateChangeOp::Delete { key } => {
                     diff.remove(key.clone());
-                    self.data.remove(&key);
+                    self.remove_entry(&key);
                 }
             }
         }
         
-        let new_version = old_version.next();
+        diff.root = Some(self.compute_root()?);
         *self.version.write() = new_version;
         self.history.write().push(diff);
         
         Ok(new_version)
     }
     
+    fn insert_entry(&self, key: Vec<u8>, value: Vec<u8>) {
+        let mut merkle = self.merkle.write();
+        let entry = StateEntry { key, value };
+        merkle.leaves.insert(entry.key.clone(), entry.hash());
+        merkle.root = None;
+        self.data.insert(entry.key, entry.value);
+    }
+    
+    fn remove_entry(&self, key: &[u8]) {
+        let mut merkle = self.merkle.write();
+        if merkle.leaves.remove(key).is_some() {
+            merkle.root = None;
+        }
+        self.data.remove(key);
+    }
+    
+    /// Set the version of a store being loaded, without recording history
+    pub(crate) fn set_version(&self, version: StateVersion) {
+        *self.version.write() = version;
+    }
+    
+    /// Replace the entire state with `data` at `version`, discarding history.
+    ///
+    /// Used to install a snapshot received from a peer.
+    pub fn reset_to(&self, data: Vec<(Vec<u8>, Vec<u8>)>, version: StateVersion) {
+        {
+            let mut merkle = self.merkle.write();
+            *merkle = MerkleCache::default();
+            self.data.clear();
+        }
+        for (key, value) in data {
+            self.insert_entry(key, value);
+        }
+        *self.version.write() = version;
+        self.history.write().clear();
+    }
+    
+    /// Get the consecutive history diffs from `from_version` up to at most
+    /// `to_version`.
+    ///
+    /// A diff applied from a peer may span several versions; the range ends
+    /// before one that would pass `to_version`. Returns `None` if history no
+    /// longer reaches back to `from_version`, in which case the caller has
+    /// to fall back to a full snapshot.
+    pub fn history_range(
+        &self,
+        from_version: StateVersion,
+        to_version: StateVersion,
+    ) -> Option<Vec<StateDiff>> {
+        let history = self.history.read();
+        let current_version = *self.version.read();
+        
+        if from_version == current_version {
+            return Some(Vec::new());
+        }
+        
+        let start = history.iter().position(|d| d.from_version == from_version)?;
+        Some(
+            history[start..]
+                .iter()
+                .take_while(|d| d.to_version <= to_version)
+                .cloned()
+                .collect(),
+        )
+    }
+    
+    /// Drop history diffs that start before `before_version`
+    pub fn prune_history(&self, before_version: StateVersion) {
+        self.history
+            .write()
+            .retain(|d| d.from_version.0 >= before_version.0);
+    }
+    
+    /// Lazily iterate over all entries, in no particular order
+    pub fn iter_entries(&self) -> impl Iterator<Item = StateEntry> + '_ {
+        self.data.iter().map(|entry| StateEntry {
+            key: entry.key().clone(),
+            value: entry.value().clone(),
+        })
+    }
+    
     pub fn all_entries(&self) -> RainsonetResult<Vec<StateEntry>> {
-        let entries: Vec<StateEntry> = self
-            .data
+        Ok(self.iter_entries().collect())
+    }
+    
+    /// Iterate over all entries in key order. Only the keys are collected
+    /// up front; each value is copied as its entry is reached.
+    pub fn iter_sorted(&self) -> impl Iterator<Item = StateEntry> + '_ {
+        let mut keys: Vec<Vec<u8>> = self.data.iter().map(|entry| entry.key().clone()).collect();
+        keys.sort();
+        keys.into_iter().filter_map(|key| {
+            let value = self.data.get(&key)?.value().clone();
+            Some(StateEntry { key, value })
+        })
+    }
+    
+    /// Lazily iterate over entries whose key starts with `prefix`, in no
+    /// particular order
+    pub fn iter_prefix<'a>(&'a self, prefix: &'a [u8]) -> impl Iterator<Item = StateEntry> + 'a {
+        self.data
             .iter()
+            .filter(move |entry| entry.key().starts_with(prefix))
             .map(|entry| StateEntry {
                 key: entry.key().clone(),
                 value: entry.value().clone(),
             })
-            .collect();
-        Ok(entries)
     }
     
+    /// All entries whose key starts with `prefix`, in no particular order
+    pub fn scan_prefix(&self, prefix: &[u8]) -> RainsonetResult<Vec<StateEntry>> {
+        Ok(self.iter_prefix(prefix).collect())
+    }
+    
+    /// Root over the cached leaf hashes, equal to
+    /// [`compute_state_root`](crate::compute_state_root) over all entries.
+    /// Kept until the next write.
     pub fn compute_root(&self) -> RainsonetResult<StateRoot> {
-        let entries = self.all_entries()?;
-        Ok(compute_state_root(&entries))
+        let mut merkle = self.merkle.write();
+        if let Some(root) = merkle.root {
+            return Ok(root);
+        }
+        
+        let mut builder = MerkleBuilder::new();
+        for leaf in merkle.leaves.values() {
+            builder.push(*leaf);
+        }
+        let root = builder.finish();
+        merkle.root = Some(root);
+        Ok(root)
+    }
+    
+    /// Root the store would have after `changes`, without applying them or
+    /// copying the state: the changed leaves are merged into the cached ones
+    pub fn root_after(&self, changes: &[StateChange]) -> StateRoot {
+        let mut overlay = BTreeMap::new();
+        for change in changes {
+            match change {
+                StateChange::Set { key, value } => {
+                    let entry = StateEntry { key: key.clone(), value: value.clone() };
+                    overlay.insert(key.as_slice(), Some(entry.hash()));
+                }
+                StateChange::Delete { key } => {
+                    overlay.insert(key.as_slice(), None);
+                }
+            }
+        }
+        
+        // Both sides are in key order; a changed leaf replaces the cached one
+        // under the same key, and a deletion leaves no leaf at all
+        let merkle = self.merkle.read();
+        let mut builder = MerkleBuilder::new();
+        let mut overlay = overlay.into_iter().peekable();
+        for (key, leaf) in &merkle.leaves {
+            while let Some((_, changed)) = overlay.next_if(|(k, _)| *k < key.as_slice()) {
+                changed.into_iter().for_each(|leaf| builder.push(leaf));
+            }
+            match overlay.next_if(|(k, _)| *k == key.as_slice()) {
+                Some((_, changed)) => changed.into_iter().for_each(|leaf| builder.push(leaf)),
+                None => builder.push(*leaf),
+            }
+        }
+        overlay.filter_map(|(_, changed)| changed).for_each(|leaf| builder.push(leaf));
+        builder.finish()
+    }
+    
+    /// Inclusion proof for the entry under `key` against [`Self::compute_root`]
+    pub fn prove(&self, key: &[u8]) -> Option<MerkleProof> {
+        compute_state_proof(self.iter_entries(), key)
     }
     
+    /// Copy of the current data and version without history.
+    ///
+    /// The copy can only [`Self::diff`] from its own version onwards; use
+    /// `clone()` to keep the history as well.
     pub fn snapshot(&self) -> Self {
         let new_store = Self::new();
+        let merkle = self.merkle.read();
         for entry in self.data.iter() {
             new_store.data.insert(entry.key().clone(), entry.value().clone());
         }
+        *new_store.merkle.write() = merkle.clone();
         *new_store.version.write() = *self.version.read();
         new_store
     }
     
+    /// Combined changes from `from_version` to the current version.
+    ///
+    /// Fails with `StateNotFound` if history no longer reaches back to
+    /// `from_version`, e.g. on a [`Self::snapshot`] or after pruning, rather
+    /// than returning a partial diff.
     pub fn diff(&self, from_version: StateVersion) -> RainsonetResult<StateDiff> {
         let history = self.history.read();
         let current_version = *self.version.read();
         
+        if from_version != current_version
+            && !history.iter().any(|d| d.from_version == from_version)
+        {
+            return Err(RainsonetError::StateNotFound);
+        }
+        
         let mut combined = StateDiff::new(from_version, current_version);
         
         for d in history.iter() {
             if d.from_version.0 >= from_version.0 {
+                combined.root = d.root;
                 for (key, value) in &d.added {
                     combined.add(key.clone(), value.clone());
                 }
@@ -153,15 +362,92 @@ impl MemoryStateStore {
     }
 }
 
+#[async_trait]
+impl StateProvider for MemoryStateStore {
+    async fn version(&self) -> StateVersion {
+        MemoryStateStore::version(self)
+    }
+    
+    async fn root(&self) -> RainsonetResult<StateRoot> {
+        MemoryStateStore::compute_root(self)
+    }
+    
+    async fn get(&self, key: &[u8]) -> RainsonetResult<Option<Vec<u8>>> {
+        MemoryStateStore::get(self, key)
+    }
+    
+    async fn exists(&self, key: &[u8]) -> RainsonetResult<bool> {
+        MemoryStateStore::exists(self, key)
+    }
+}
+
+#[async_trait]
+impl StateMutator for MemoryStateStore {
+    async fn set(&self, key: &[u8], value: &[u8]) -> RainsonetResult<()> {
+        MemoryStateStore::set(self, key, value)
+    }
+    
+    async fn delete(&self, key: &[u8]) -> RainsonetResult<()> {
+        MemoryStateStore::delete(self, key)
+    }
+    
+    async fn apply_batch(&self, changes: Vec<StateChange>) -> RainsonetResult<StateVersion> {
+        MemoryStateStore::apply_batch(self, changes.into_iter().map(StateChangeOp::from).collect())
+    }
+}
+
+#[async_trait]
+impl StateStore for MemoryStateStore {
+    async fn scan_prefix(&self, prefix: &[u8]) -> RainsonetResult<Vec<StateEntry>> {
+        MemoryStateStore::scan_prefix(self, prefix)
+    }
+    
+    async fn compute_root(&self) -> RainsonetResult<StateRoot> {
+        MemoryStateStore::compute_root(self)
+    }
+    
+    async fn all_entries(&self) -> RainsonetResult<Vec<StateEntry>> {
+        MemoryStateStore::all_entries(self)
+    }
+    
+    async fn snapshot(&self) -> RainsonetResult<Box<dyn StateStore>> {
+        Ok(Box::new(MemoryStateStore::snapshot(self)))
+    }
+    
+    async fn diff(&self, from_version: StateVersion) -> RainsonetResult<StateDiff> {
+        MemoryStateStore::diff(self, from_version)
+    }
+    
+    async fn apply_diff(&self, diff: &StateDiff) -> RainsonetResult<StateVersion> {
+        MemoryStateStore::apply_diff(self, diff)
+    }
+    
+    fn ordered_entries(&self) -> Box<dyn Iterator<Item = RainsonetResult<StateEntry>> + '_> {
+        Box::new(self.iter_sorted().map(Ok))
+    }
+    
+    fn load_entries(&self, entries: Vec<StateEntry>, version: StateVersion) -> RainsonetResult<()> {
+        for entry in entries {
+            self.insert_entry(entry.key, entry.value);
+        }
+        self.set_version(version);
+        Ok(())
+    }
+}
+
 impl Default for MemoryStateStore {
     fn default() -> Self {
         Self::new()
     }
 }
 
+/// Deep copy including history, so the clone answers [`MemoryStateStore::diff`]
+/// exactly like the original
 impl Clone for MemoryStateStore {
     fn clone(&self) -> Self {
-        self.snapshot()
+        let new_store = self.snapshot();
+        *new_store.history.write() = self.history.read().clone();
+        new_store
     }
 }
 
@@ -212,6 +498,198 @@ mod tests {
         assert!(store.exists(b"k2").unwrap());
     }
     
+    #[test]
+    fn test_cached_root_follows_writes() {
+        let store = MemoryStateStore::with_data(vec![(b"a".to_vec(), b"1".to_vec())]);
+        let full_root = |s: &MemoryStateStore| crate::compute_state_root(s.iter_entries());
+        assert_eq!(store.compute_root().unwrap(), full_root(&store));
+        
+        store
+            .apply_batch(vec![
+                StateChangeOp::Set { key: b"c".to_vec(), value: b"3".to_vec() },
+                StateChangeOp::Set { key: b"b".to_vec(), value: b"2".to_vec() },
+            ])
+            .unwrap();
+        assert_eq!(store.compute_root().unwrap(), full_root(&store));
+        
+        store.delete(b"a").unwrap();
+        store.set(b"c", b"4").unwrap();
+        assert_eq!(store.compute_root().unwrap(), full_root(&store));
+        
+        let copy = store.snapshot();
+        copy.set(b"d", b"5").unwrap();
+        assert_eq!(store.compute_root().unwrap(), full_root(&store));
+        assert_eq!(copy.compute_root().unwrap(), full_root(&copy));
+        
+        store.reset_to(vec![(b"e".to_vec(), b"6".to_vec())], StateVersion::new(9));
+        assert_eq!(store.compute_root().unwrap(), full_root(&store));
+    }
+    
+    #[test]
+    fn test_root_after_matches_applied_changes() {
+        let store = MemoryStateStore::with_data(vec![
+            (b"b".to_vec(), b"1".to_vec()),
+            (b"d".to_vec(), b"2".to_vec()),
+        ]);
+        let changes = vec![
+            StateChange::Set { key: b"a".to_vec(), value: b"3".to_vec() },
+            StateChange::Delete { key: b"b".to_vec() },
+            StateChange::Set { key: b"d".to_vec(), value: b"4".to_vec() },
+            StateChange::Set { key: b"e".to_vec(), value: b"5".to_vec() },
+            StateChange::Delete { key: b"f".to_vec() },
+        ];
+        
+        let before = store.compute_root().unwrap();
+        let predicted = store.root_after(&changes);
+        assert_eq!(store.compute_root().unwrap(), before);
+        
+        store.apply_batch(changes.into_iter().map(StateChangeOp::from).collect()).unwrap();
+        assert_eq!(store.compute_root().unwrap(), predicted);
+    }
+    
+    #[test]
+    fn test_prove_entry() {
+        let store = MemoryStateStore::new();
+        for i in 0..5u8 {
+            store.set(&[b'k', i], &[i]).unwrap();
+        }
+        let root = store.compute_root().unwrap();
+        
+        let entry = StateEntry { key: vec![b'k', 3], value: vec![3] };
+        let proof = store.prove(&entry.key).unwrap();
+        assert!(proof.verify(entry.hash(), &root));
+        
+        let forged = StateEntry { key: vec![b'k', 3], value: vec![4] };
+        assert!(!proof.verify(forged.hash(), &root));
+        assert!(store.prove(b"missing").is_none());
+    }
+    
+    #[test]
+    fn test_history_range_and_prune() {
+        let store = MemoryStateStore::new();
+        
+        for i in 0..5u8 {
+            store
+                .apply_batch(vec![StateChangeOp::Set {
+                    key: vec![i],
+                    value: vec![i],
+                }])
+                .unwrap();
+        }
+        
+        let version = StateVersion::new;
+        let diffs = store.history_range(version(1), version(3)).unwrap();
+        assert_eq!(diffs.len(), 2);
+        assert_eq!(diffs[0].from_version, version(1));
+        assert_eq!(diffs[1].to_version, version(3));
+        
+        let last = store.history_range(version(4), version(10)).unwrap();
+        assert_eq!(last[0].root, Some(store.compute_root().unwrap()));
+        assert!(store.history_range(version(5), version(10)).unwrap().is_empty());
+        
+        store.prune_history(version(3));
+        assert!(store.history_range(version(1), version(3)).is_none());
+        assert_eq!(store.history_range(version(3), version(10)).unwrap().len(), 2);
+    }
+    
+    #[test]
+    fn test_history_range_stops_at_spanning_diff() {
+        let set = |key: u8| StateChangeOp::Set { key: vec![key], value: vec![key] };
+        let source = MemoryStateStore::new();
+        source.apply_batch(vec![set(0)]).unwrap();
+        let replica = source.clone();
+        for key in 1..4 {
+            source.apply_batch(vec![set(key)]).unwrap();
+        }
+        
+        // One diff now covers versions 1 to 4 on the replica
+        replica.apply_diff(&source.diff(StateVersion::new(1)).unwrap()).unwrap();
+        replica.apply_batch(vec![set(9)]).unwrap();
+        
+        let version = StateVersion::new;
+        assert!(replica.history_range(version(1), version(2)).unwrap().is_empty());
+        assert_eq!(replica.history_range(version(1), version(4)).unwrap().len(), 1);
+        let through = replica.history_range(version(0), version(5)).unwrap();
+        assert_eq!(through.last().unwrap().to_version, version(5));
+        assert_eq!(through.len(), 3);
+    }
+    
+    #[test]
+    fn test_diff_on_clone_and_snapshot() {
+        let store = MemoryStateStore::new();
+        for i in 0..3u8 {
+            store
+                .apply_batch(vec![StateChangeOp::Set {
+                    key: vec![i],
+                    value: vec![i],
+                }])
+                .unwrap();
+        }
+        
+        let clone = store.clone();
+        let diff = clone.diff(StateVersion::new(0)).unwrap();
+        assert_eq!(diff.added.len(), 3);
+        assert_eq!(diff.to_version, StateVersion::new(3));
+        
+        // A snapshot has no history before its own version
+        let snapshot = store.snapshot();
+        assert_eq!(snapshot.version(), StateVersion::new(3));
+        assert!(matches!(
+            snapshot.diff(StateVersion::new(0)),
+            Err(RainsonetError::StateNotFound)
+        ));
+        assert!(snapshot.diff(StateVersion::new(3)).unwrap().added.is_empty());
+        
+        snapshot
+            .apply_batch(vec![StateChangeOp::Set {
+                key: vec![9],
+                value: vec![9],
+            }])
+            .unwrap();
+        assert_eq!(snapshot.diff(StateVersion::new(3)).unwrap().added.len(), 1);
+    }
+    
+    #[test]
+    fn test_apply_diff_catches_up() {
+        let set = |key: u8, value: u8| StateChangeOp::Set { key: vec![key], value: vec![value] };
+        let source = MemoryStateStore::new();
+        source.apply_batch(vec![set(1, 1), set(2, 2)]).unwrap();
+        let replica = source.clone();
+        
+        // The source moves on by three versions
+        source.apply_batch(vec![set(3, 3)]).unwrap();
+        source.apply_batch(vec![StateChangeOp::Delete { key: vec![1] }]).unwrap();
+        source.apply_batch(vec![set(2, 9)]).unwrap();
+        assert_ne!(replica.root(), source.root());
+        
+        let diff = source.diff(replica.version()).unwrap();
+        assert_eq!(replica.apply_diff(&diff).unwrap(), StateVersion::new(4));
+        assert_eq!(replica.version(), source.version());
+        assert_eq!(replica.root(), source.root());
+        
+        // The replica can serve the same diff onwards
+        assert_eq!(replica.diff(StateVersion::new(1)).unwrap().removed, vec![vec![1]]);
+        
+        // A diff from another base is rejected without changes
+        let root = replica.root();
+        assert!(matches!(
+            replica.apply_diff(&diff),
+            Err(RainsonetError::StateVersionMismatch { expected: 4, got: 1 })
+        ));
+        assert_eq!(replica.root(), root);
+        
+        // So is one whose recorded root the changes do not produce
+        let mut forged = StateDiff::new(replica.version(), StateVersion::new(5));
+        forged.add(vec![7], vec![7]);
+        forged.root = Some(source.root());
+        assert!(matches!(
+            replica.apply_diff(&forged),
+            Err(RainsonetError::StateCorruption(_))
+        ));
+        assert_eq!(replica.root(), root);
+        assert_eq!(replica.version(), StateVersion::new(4));
+    }
+    
     #[test]
     fn test_account_state() {
         let store = MemoryStateStore::new();
@@ -1,183 +1,319 @@
-//! Persistent state store using sled database
+//! Persistent state store over a pluggable key-value backend
 
+use async_trait::async_trait;
 use parking_lot::RwLock;
-use rainsonet_core::{Hash, RainsonetError, RainsonetResult, StateRoot, StateVersion};
-use sled::{Db, Tree};
+use rainsonet_core::{
+    RainsonetError, RainsonetResult, StateChange, StateMutator, StateProvider, StateRoot,
+    StateVersion, StorageBackend,
+};
+use rainsonet_crypto::hashing::MerkleBuilder;
 use std::path::Path;
 use std::sync::Arc;
 
+use crate::backend::{internal, open_backend, Column, KvBackend, KvBatch};
 use crate::store::{
-    account_key, compute_state_root, AccountState, StateChangeOp, StateDiff, StateEntry,
+    account_key, AccountState, StateChangeOp, StateDiff, StateEntry, StateStore,
 };
 use crate::memory::MemoryStateStore;
 
-const STATE_TREE: &str = "state";
-const META_TREE: &str = "meta";
-const HISTORY_TREE: &str = "history";
 const VERSION_KEY: &[u8] = b"version";
 
-/// Persistent state store backed by sled database
+/// Oldest version whose diff is still in history
+const HISTORY_START_KEY: &[u8] = b"history_start";
+
+/// History key of the diff from `version` to the next
+fn history_key(version: u64) -> [u8; 8] {
+    version.to_le_bytes()
+}
+
+/// Leading byte of history diffs stored as bincode. Older stores wrote
+/// JSON, which always starts with `{`, so both can be told apart.
+const HISTORY_FORMAT_BINCODE: u8 = 1;
+
+/// Encode a history diff: the format tag, then the bincode diff
+fn encode_history_diff(diff: &StateDiff) -> RainsonetResult<Vec<u8>> {
+    let mut bytes = vec![HISTORY_FORMAT_BINCODE];
+    bincode::serialize_into(&mut bytes, diff).map_err(internal)?;
+    Ok(bytes)
+}
+
+/// Decode a history diff written by [`encode_history_diff`] or as JSON by
+/// older stores
+fn decode_history_diff(bytes: &[u8]) -> RainsonetResult<StateDiff> {
+    match bytes.first() {
+        Some(&HISTORY_FORMAT_BINCODE) => bincode::deserialize(&bytes[1..]).map_err(internal),
+        Some(b'{') => serde_json::from_slice(bytes).map_err(internal),
+        _ => Err(RainsonetError::StateCorruption(
+            "Unknown history diff format".into(),
+        )),
+    }
+}
+
+/// Persistent state store. Versioning, history and root computation live
+/// here; the [`KvBackend`] only stores bytes.
 pub struct PersistentStateStore {
-    db: Db,
-    state: Tree,
-    meta: Tree,
-    history: Tree,
-    version: RwLock<StateVersion>,
+    backend: Arc<dyn KvBackend>,
+    version: Arc<RwLock<StateVersion>>,
+    /// Versions of history kept by [`Self::apply_batch`], all if unset
+    history_retention: Option<u64>,
 }
 
 impl PersistentStateStore {
+    /// Open a sled-backed store at `path`
     pub fn open<P: AsRef<Path>>(path: P) -> RainsonetResult<Self> {
-        let db = sled::open(path).map_err(|e| RainsonetError::Internal(e.to_string()))?;
-        
-        let state = db
-            .open_tree(STATE_TREE)
-            .map_err(|e| RainsonetError::Internal(e.to_string()))?;
-        let meta = db
-            .open_tree(META_TREE)
-            .map_err(|e| RainsonetError::Internal(e.to_string()))?;
-        let history = db
-            .open_tree(HISTORY_TREE)
-            .map_err(|e| RainsonetError::Internal(e.to_string()))?;
-        
+        Self::open_with(path, StorageBackend::Sled)
+    }
+    
+    /// Open a store at `path` on the configured backend
+    pub fn open_with<P: AsRef<Path>>(path: P, backend: StorageBackend) -> RainsonetResult<Self> {
+        Self::with_backend(open_backend(path, backend)?)
+    }
+    
+    /// Use an already opened backend
+    pub fn with_backend(backend: Box<dyn KvBackend>) -> RainsonetResult<Self> {
         // Load version from disk or start at 0
-        let version = match meta.get(VERSION_KEY).map_err(|e| RainsonetError::Internal(e.to_string()))? {
+        let version = match backend.get(Column::Meta, VERSION_KEY)? {
             Some(bytes) => {
-                let v = u64::from_le_bytes(bytes.as_ref().try_into().unwrap_or([0; 8]));
+                let v = u64::from_le_bytes(bytes.as_slice().try_into().unwrap_or([0; 8]));
                 StateVersion::new(v)
             }
             None => StateVersion::new(0),
         };
         
         Ok(Self {
-            db,
-            state,
-            meta,
-            history,
-            version: RwLock::new(version),
+            backend: Arc::from(backend),
+            version: Arc::new(RwLock::new(version)),
+            history_retention: None,
         })
     }
     
-    pub fn version(&self) -> StateVersion {
-        *self.version.read()
+    /// Keep the diffs of only the last `versions` versions, pruning older
+    /// ones as batches are applied. Without it history grows forever.
+    pub fn with_history_retention(mut self, versions: u64) -> Self {
+        self.history_retention = Some(versions);
+        self
     }
     
-    pub fn root(&self) -> StateRoot {
-        self.compute_root().unwrap_or(Hash::ZERO)
+    /// Second handle onto the same backend and version, for moving into
+    /// blocking tasks
+    fn handle(&self) -> Self {
+        Self {
+            backend: self.backend.clone(),
+            version: self.version.clone(),
+            history_retention: self.history_retention,
+        }
+    }
+    
+    pub fn version(&self) -> StateVersion {
+        *self.version.read()
     }
     
     pub fn get(&self, key: &[u8]) -> RainsonetResult<Option<Vec<u8>>> {
-        self.state
-            .get(key)
-            .map(|opt| opt.map(|v| v.to_vec()))
-            .map_err(|e| RainsonetError::Internal(e.to_string()))
+        self.backend.get(Column::State, key)
     }
     
     pub fn exists(&self, key: &[u8]) -> RainsonetResult<bool> {
-        self.state
-            .contains_key(key)
-            .map_err(|e| RainsonetError::Internal(e.to_string()))
+        Ok(self.get(key)?.is_some())
     }
     
     pub fn set(&self, key: &[u8], value: &[u8]) -> RainsonetResult<()> {
-        self.state
-            .insert(key, value)
-            .map_err(|e| RainsonetError::Internal(e.to_string()))?;
-        Ok(())
+        self.backend.set(Column::State, key, value)
     }
     
     pub fn delete(&self, key: &[u8]) -> RainsonetResult<()> {
-        self.state
-            .remove(key)
-            .map_err(|e| RainsonetError::Internal(e.to_string()))?;
-        Ok(())
+        self.backend.delete(Column::State, key)
     }
     
     pub fn apply_batch(&self, changes: Vec<StateChangeOp>) -> RainsonetResult<StateVersion> {
         let old_version = *self.version.read();
-        let new_version = old_version.next();
+        self.apply_changes(old_version, old_version.next(), changes)
+    }
+    
+    /// Apply a diff received from a peer in one batch, moving straight to
+    /// its `to_version`. Fails with `StateVersionMismatch`, changing
+    /// nothing, unless the store is at the diff's `from_version`, and with
+    /// `StateCorruption` if the changes do not produce the diff's root.
+    pub fn apply_diff(&self, diff: &StateDiff) -> RainsonetResult<StateVersion> {
+        diff.check_applies_to(self.version())?;
+        diff.check_root_after(self.iter_entries())?;
+        self.apply_changes(diff.from_version, diff.to_version, diff.to_changes())
+    }
+    
+    /// Write `entries` and move to `version` as one batch, without history.
+    ///
+    /// Used to load a snapshot in pieces; the caller checks its root.
+    pub fn load_entries(
+        &self,
+        entries: Vec<StateEntry>,
+        version: StateVersion,
+    ) -> RainsonetResult<()> {
+        let mut batch = KvBatch::new();
+        for entry in entries {
+            batch.set(Column::State, &entry.key, &entry.value);
+        }
+        batch.set(Column::Meta, VERSION_KEY, &version.0.to_le_bytes());
+        self.backend.write_batch(batch)?;
+        self.backend.flush()?;
         
-        // Create a batch for atomic writes
-        let mut batch = sled::Batch::default();
+        *self.version.write() = version;
+        Ok(())
+    }
+    
+    /// Write `changes` with the new version and one history diff covering
+    /// `old_version` to `new_version`
+    fn apply_changes(
+        &self,
+        old_version: StateVersion,
+        new_version: StateVersion,
+        changes: Vec<StateChangeOp>,
+    ) -> RainsonetResult<StateVersion> {
+        // State changes, version and history are written as one batch
+        let mut batch = KvBatch::new();
         let mut diff = StateDiff::new(old_version, new_version);
         
         for change in changes {
             match change {
                 StateChangeOp::Set { key, value } => {
-                    diff.add(key.clone(), value.clone());
-                    batch.insert(key.as_slice(), value.as_slice());
+                    batch.set(Column::State, &key, &value);
+                    diff.add(key, value);
                 }
                 StateChangeOp::Delete { key } => {
-                    diff.remove(key.clone());
-                    batch.remove(key.as_slice());
+                    batch.delete(Column::State, &key);
+                    diff.remove(key);
                 }
             }
         }
         
-        // Apply state changes atomically
-        self.state
-            .apply_batch(batch)
-            .map_err(|e| RainsonetError::Internal(e.to_string()))?;
-        
         // Save new version
-        self.meta
-            .insert(VERSION_KEY, &new_version.0.to_le_bytes())
-            .map_err(|e| RainsonetError::Internal(e.to_string()))?;
+        batch.set(Column::Meta, VERSION_KEY, &new_version.0.to_le_bytes());
         
         // Save diff to history
-        let diff_key = old_version.0.to_le_bytes();
-        let diff_bytes = serde_json::to_vec(&diff)
-            .map_err(|e| RainsonetError::Internal(e.to_string()))?;
-        self.history
-            .insert(&diff_key, diff_bytes)
-            .map_err(|e| RainsonetError::Internal(e.to_string()))?;
+        let diff_key = history_key(old_version.0);
+        batch.set(Column::History, &diff_key, &encode_history_diff(&diff)?);
+        
+        self.backend.write_batch(batch)?;
         
         // Flush to disk
-        self.db.flush().map_err(|e| RainsonetError::Internal(e.to_string()))?;
+        self.backend.flush()?;
         
         // Update in-memory version
         *self.version.write() = new_version;
         
+        if let Some(retention) = self.history_retention {
+            let keep_from = StateVersion::new(new_version.0.saturating_sub(retention));
+            if keep_from.0 > self.history_start()?.0 {
+                self.prune_history(keep_from)?;
+            }
+        }
+        
         Ok(new_version)
     }
     
-    pub fn all_entries(&self) -> RainsonetResult<Vec<StateEntry>> {
-        let entries: Result<Vec<StateEntry>, _> = self
-            .state
-            .iter()
-            .map(|result| {
-                result.map(|(key, value)| StateEntry {
-                    key: key.to_vec(),
-                    value: value.to_vec(),
-                })
-            })
-            .collect();
+    /// Oldest version [`Self::diff`] can start from
+    pub fn history_start(&self) -> RainsonetResult<StateVersion> {
+        Ok(match self.backend.get(Column::Meta, HISTORY_START_KEY)? {
+            Some(bytes) => {
+                StateVersion::new(u64::from_le_bytes(bytes.as_slice().try_into().unwrap_or([0; 8])))
+            }
+            None => StateVersion::new(0),
+        })
+    }
+    
+    /// Number of diffs held in history
+    pub fn history_len(&self) -> RainsonetResult<usize> {
+        let mut len = 0;
+        for result in self.backend.scan_prefix(Column::History, &[]) {
+            result?;
+            len += 1;
+        }
+        Ok(len)
+    }
+    
+    /// Drop history diffs that start before `before_version`. Diffs from
+    /// earlier versions fail with `StateNotFound` afterwards.
+    pub fn prune_history(&self, before_version: StateVersion) -> RainsonetResult<()> {
+        let start = self.history_start()?.0;
+        let end = before_version.0.min(self.version().0);
+        if end <= start {
+            return Ok(());
+        }
         
-        entries.map_err(|e| RainsonetError::Internal(e.to_string()))
+        let mut batch = KvBatch::new();
+        for version in start..end {
+            batch.delete(Column::History, &history_key(version));
+        }
+        batch.set(Column::Meta, HISTORY_START_KEY, &end.to_le_bytes());
+        self.backend.write_batch(batch)
+    }
+    
+    /// Lazily iterate over all entries in key order
+    pub fn iter_entries(&self) -> impl Iterator<Item = RainsonetResult<StateEntry>> + '_ {
+        self.backend
+            .scan_prefix(Column::State, &[])
+            .map(|result| result.map(|(key, value)| StateEntry { key, value }))
+    }
+    
+    pub fn all_entries(&self) -> RainsonetResult<Vec<StateEntry>> {
+        self.iter_entries().collect()
+    }
+    
+    /// All entries whose key starts with `prefix`, in key order
+    pub fn scan_prefix(&self, prefix: &[u8]) -> RainsonetResult<Vec<StateEntry>> {
+        self.backend
+            .scan_prefix(Column::State, prefix)
+            .map(|result| result.map(|(key, value)| StateEntry { key, value }))
+            .collect()
     }
     
+    /// Compute the state root, streaming entries instead of loading them all
     pub fn compute_root(&self) -> RainsonetResult<StateRoot> {
-        let entries = self.all_entries()?;
-        Ok(compute_state_root(&entries))
+        // Backends iterate in key order, so leaves can be hashed as they arrive
+        let mut builder = MerkleBuilder::new();
+        for entry in self.iter_entries() {
+            builder.push(entry?.hash());
+        }
+        Ok(builder.finish())
     }
     
-    pub fn snapshot(&self) -> MemoryStateStore {
-        let entries = self.all_entries().unwrap_or_default();
+    /// In-memory copy of the current data and version, without history
+    pub fn snapshot(&self) -> RainsonetResult<MemoryStateStore> {
+        let entries = self.all_entries()?;
         let data: Vec<(Vec<u8>, Vec<u8>)> = entries
             .into_iter()
             .map(|e| (e.key, e.value))
             .collect();
-        MemoryStateStore::with_data(data)
+        let snapshot = MemoryStateStore::new();
+        snapshot.reset_to(data, self.version());
+        Ok(snapshot)
     }
     
+    /// Combined changes from `from_version` to the current version.
+    ///
+    /// Fails with `StateNotFound` if history was pruned past `from_version`.
     pub fn diff(&self, from_version: StateVersion) -> RainsonetResult<StateDiff> {
         let current_version = *self.version.read();
+        if from_version.0 < self.history_start()?.0 {
+            return Err(RainsonetError::StateNotFound);
+        }
         let mut combined = StateDiff::new(from_version, current_version);
         
-        // Read all diffs from history
-        for result in self.history.range(from_version.0.to_le_bytes()..) {
-            let (_, diff_bytes) = result.map_err(|e| RainsonetError::Internal(e.to_string()))?;
-            let d: StateDiff = serde_json::from_slice(&diff_bytes)
-                .map_err(|e| RainsonetError::Internal(e.to_string()))?;
+        // Keys are little-endian, so look diffs up by version rather than
+        // relying on key order. A diff applied from a peer may span several
+        // versions, so continue from where each one ends.
+        let mut version = from_version;
+        while version < current_version {
+            let diff_bytes = self
+                .backend
+                .get(Column::History, &history_key(version.0))?
+                .ok_or(RainsonetError::StateNotFound)?;
+            let d = decode_history_diff(&diff_bytes)?;
+            if d.to_version <= version {
+                return Err(RainsonetError::StateCorruption(format!(
+                    "History diff at version {} does not advance",
+                    version
+                )));
+            }
+            version = d.to_version;
             
             for (key, value) in d.added {
                 combined.add(key, value);
@@ -213,15 +349,115 @@ impl PersistentStateStore {
         Ok(self.get_account(address)?.map(|a| a.nonce).unwrap_or(0))
     }
     
-    /// Compact the database
+    /// Compact the database, reclaiming space left by deleted entries and
+    /// pruned history where the backend allows
     pub fn compact(&self) -> RainsonetResult<()> {
-        // Sled doesn't have explicit compaction, but we can flush
-        self.db.flush().map_err(|e| RainsonetError::Internal(e.to_string()))
+        self.backend.compact(Column::State)?;
+        self.backend.compact(Column::History)?;
+        self.backend.flush()
     }
     
     /// Get database size estimate
     pub fn size_estimate(&self) -> RainsonetResult<u64> {
-        Ok(self.state.len() as u64)
+        Ok(self.backend.scan_prefix(Column::State, &[]).count() as u64)
+    }
+}
+
+impl PersistentStateStore {
+    /// Run a blocking store operation on the tokio blocking pool.
+    ///
+    /// Backend reads, writes and flushes block the calling thread, so the
+    /// async trait methods go through this instead of calling the store
+    /// directly.
+    async fn blocking<T, F>(&self, f: F) -> RainsonetResult<T>
+    where
+        F: FnOnce(&PersistentStateStore) -> RainsonetResult<T> + Send + 'static,
+        T: Send + 'static,
+    {
+        let store = self.handle();
+        tokio::task::spawn_blocking(move || f(&store))
+            .await
+            .map_err(|e| RainsonetError::Internal(format!("Blocking task failed: {}", e)))?
+    }
+}
+
+// The trait methods run every backend call on the blocking pool, so generic
+// async code never stalls a runtime worker on disk I/O.
+
+#[async_trait]
+impl StateProvider for PersistentStateStore {
+    async fn version(&self) -> StateVersion {
+        PersistentStateStore::version(self)
+    }
+    
+    async fn root(&self) -> RainsonetResult<StateRoot> {
+        self.blocking(|store| store.compute_root()).await
+    }
+    
+    async fn get(&self, key: &[u8]) -> RainsonetResult<Option<Vec<u8>>> {
+        let key = key.to_vec();
+        self.blocking(move |store| store.get(&key)).await
+    }
+    
+    async fn exists(&self, key: &[u8]) -> RainsonetResult<bool> {
+        let key = key.to_vec();
+        self.blocking(move |store| store.exists(&key)).await
+    }
+}
+
+#[async_trait]
+impl StateMutator for PersistentStateStore {
+    async fn set(&self, key: &[u8], value: &[u8]) -> RainsonetResult<()> {
+        let (key, value) = (key.to_vec(), value.to_vec());
+        self.blocking(move |store| store.set(&key, &value)).await
+    }
+    
+    async fn delete(&self, key: &[u8]) -> RainsonetResult<()> {
+        let key = key.to_vec();
+        self.blocking(move |store| store.delete(&key)).await
+    }
+    
+    async fn apply_batch(&self, changes: Vec<StateChange>) -> RainsonetResult<StateVersion> {
+        let changes = changes.into_iter().map(StateChangeOp::from).collect();
+        self.blocking(move |store| store.apply_batch(changes)).await
+    }
+}
+
+#[async_trait]
+impl StateStore for PersistentStateStore {
+    async fn scan_prefix(&self, prefix: &[u8]) -> RainsonetResult<Vec<StateEntry>> {
+        let prefix = prefix.to_vec();
+        self.blocking(move |store| store.scan_prefix(&prefix)).await
+    }
+    
+    async fn compute_root(&self) -> RainsonetResult<StateRoot> {
+        self.blocking(|store| store.compute_root()).await
+    }
+    
+    async fn all_entries(&self) -> RainsonetResult<Vec<StateEntry>> {
+        self.blocking(|store| store.all_entries()).await
+    }
+    
+    async fn snapshot(&self) -> RainsonetResult<Box<dyn StateStore>> {
+        let snapshot = self.blocking(|store| store.snapshot()).await?;
+        Ok(Box::new(snapshot))
+    }
+    
+    async fn diff(&self, from_version: StateVersion) -> RainsonetResult<StateDiff> {
+        self.blocking(move |store| store.diff(from_version)).await
+    }
+    
+    async fn apply_diff(&self, diff: &StateDiff) -> RainsonetResult<StateVersion> {
+        let diff = diff.clone();
+        self.blocking(move |store| store.apply_diff(&diff)).await
+    }
+    
+    fn ordered_entries(&self) -> Box<dyn Iterator<Item = RainsonetResult<StateEntry>> + '_> {
+        Box::new(self.iter_entries())
+    }
+    
+    fn load_entries(&self, entries: Vec<StateEntry>, version: StateVersion) -> RainsonetResult<()> {
+        PersistentStateStore::load_entries(self, entries, version)
     }
 }
 
@@ -236,43 +472,223 @@ pub fn create_persistent_store<P: AsRef<Path>>(path: P) -> RainsonetResult<Share
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::backend::compiled_backends;
+    use crate::store::compute_state_root;
     use tempfile::TempDir;
     
     #[test]
     fn test_persistent_store_basic() {
-        let tmp = TempDir::new().unwrap();
-        let store = PersistentStateStore::open(tmp.path()).unwrap();
-        
-        store.set(b"key1", b"value1").unwrap();
-        let value = store.get(b"key1").unwrap();
-        assert_eq!(value, Some(b"value1".to_vec()));
-        
-        store.delete(b"key1").unwrap();
-        let value = store.get(b"key1").unwrap();
-        assert_eq!(value, None);
+        for backend in compiled_backends() {
+            let tmp = TempDir::new().unwrap();
+            let store = PersistentStateStore::open_with(tmp.path(), backend).unwrap();
+            
+            store.set(b"key1", b"value1").unwrap();
+            let value = store.get(b"key1").unwrap();
+            assert_eq!(value, Some(b"value1".to_vec()));
+            assert!(store.exists(b"key1").unwrap());
+            
+            store.delete(b"key1").unwrap();
+            let value = store.get(b"key1").unwrap();
+            assert_eq!(value, None);
+        }
     }
     
     #[test]
     fn test_persistent_store_reopen() {
-        let tmp = TempDir::new().unwrap();
-        
-        // Write data
-        {
-            let store = PersistentStateStore::open(tmp.path()).unwrap();
-            store.set(b"key1", b"value1").unwrap();
-            let changes = vec![StateChangeOp::Set {
-                key: b"k2".to_vec(),
-                value: b"v2".to_vec(),
-            }];
+        for backend in compiled_backends() {
+            let tmp = TempDir::new().unwrap();
+            
+            // Write data
+            {
+                let store = PersistentStateStore::open_with(tmp.path(), backend).unwrap();
+                store.set(b"key1", b"value1").unwrap();
+                let changes = vec![StateChangeOp::Set {
+                    key: b"k2".to_vec(),
+                    value: b"v2".to_vec(),
+                }];
+                store.apply_batch(changes).unwrap();
+            }
+            
+            // Reopen and verify
+            {
+                let store = PersistentStateStore::open_with(tmp.path(), backend).unwrap();
+                assert_eq!(store.get(b"key1").unwrap(), Some(b"value1".to_vec()));
+                assert_eq!(store.get(b"k2").unwrap(), Some(b"v2".to_vec()));
+                assert_eq!(store.version().0, 1);
+                assert_eq!(store.diff(StateVersion::new(0)).unwrap().added.len(), 1);
+            }
+        }
+    }
+    
+    #[tokio::test]
+    async fn test_trait_methods_run_on_blocking_pool() {
+        for backend in compiled_backends() {
+            let tmp = TempDir::new().unwrap();
+            let store = PersistentStateStore::open_with(tmp.path(), backend).unwrap();
+            let dyn_store: &dyn StateStore = &store;
+            
+            let version = StateMutator::apply_batch(
+                dyn_store,
+                vec![StateChange::Set {
+                    key: b"k1".to_vec(),
+                    value: b"v1".to_vec(),
+                }],
+            )
+            .await
+            .unwrap();
+            assert_eq!(version.0, 1);
+            assert_eq!(
+                StateStore::compute_root(dyn_store).await.unwrap(),
+                store.compute_root().unwrap()
+            );
+            assert_eq!(StateProvider::get(dyn_store, b"k1").await.unwrap(), Some(b"v1".to_vec()));
+        }
+    }
+    
+    #[test]
+    fn test_history_reads_legacy_json_diffs() {
+        for backend in compiled_backends() {
+            let tmp = TempDir::new().unwrap();
+            let store = PersistentStateStore::open_with(tmp.path(), backend).unwrap();
+            store.set(b"gone", b"v").unwrap();
+            store
+                .apply_batch(vec![StateChangeOp::Delete { key: b"gone".to_vec() }])
+                .unwrap();
+            
+            // Rewrite the diff the way older stores did
+            let mut legacy = StateDiff::new(StateVersion::new(0), StateVersion::new(1));
+            legacy.remove(b"gone".to_vec());
+            let mut batch = KvBatch::new();
+            batch.set(Column::History, &history_key(0), &serde_json::to_vec(&legacy).unwrap());
+            store.backend.write_batch(batch).unwrap();
+            
+            let diff = store.diff(StateVersion::new(0)).unwrap();
+            assert_eq!(diff.removed, vec![b"gone".to_vec()]);
+            
+            // New diffs are tagged, and untagged garbage is refused
+            store.apply_batch(vec![StateChangeOp::Delete { key: b"k".to_vec() }]).unwrap();
+            let bytes = store.backend.get(Column::History, &history_key(1)).unwrap().unwrap();
+            assert_eq!(bytes[0], HISTORY_FORMAT_BINCODE);
+            assert!(matches!(
+                decode_history_diff(&[0xff, 0, 0]),
+                Err(RainsonetError::StateCorruption(_))
+            ));
+        }
+    }
+    
+    #[test]
+    fn test_streaming_root_matches_materialized_root() {
+        for backend in compiled_backends() {
+            let tmp = TempDir::new().unwrap();
+            let store = PersistentStateStore::open_with(tmp.path(), backend).unwrap();
+            assert_eq!(store.compute_root().unwrap(), rainsonet_core::Hash::ZERO);
+            
+            let changes = (0..37u32)
+                .rev()
+                .map(|i| StateChangeOp::Set {
+                    key: i.to_be_bytes().to_vec(),
+                    value: vec![i as u8; 8],
+                })
+                .collect();
             store.apply_batch(changes).unwrap();
+            
+            let entries = store.all_entries().unwrap();
+            assert_eq!(store.compute_root().unwrap(), compute_state_root(&entries));
+            let snapshot = store.snapshot().unwrap();
+            assert_eq!(store.compute_root().unwrap(), snapshot.compute_root().unwrap());
         }
-        
-        // Reopen and verify
-        {
-            let store = PersistentStateStore::open(tmp.path()).unwrap();
-            assert_eq!(store.get(b"key1").unwrap(), Some(b"value1".to_vec()));
-            assert_eq!(store.get(b"k2").unwrap(), Some(b"v2".to_vec()));
-            assert_eq!(store.version().0, 1);
+    }
+    
+    /// One batch per version, overwriting a small key space
+    fn apply_versions(store: &PersistentStateStore, count: u32) {
+        for i in 0..count {
+            store
+                .apply_batch(vec![StateChangeOp::Set {
+                    key: (i % 50).to_be_bytes().to_vec(),
+                    value: i.to_be_bytes().to_vec(),
+                }])
+                .unwrap();
+        }
+    }
+    
+    #[test]
+    fn test_prune_history_keeps_state() {
+        for backend in compiled_backends() {
+            let tmp = TempDir::new().unwrap();
+            let store = PersistentStateStore::open_with(tmp.path(), backend).unwrap();
+            apply_versions(&store, 1000);
+            assert_eq!(store.history_len().unwrap(), 1000);
+            let root = store.compute_root().unwrap();
+            let entries = store.all_entries().unwrap();
+            
+            store.prune_history(StateVersion::new(900)).unwrap();
+            store.compact().unwrap();
+            assert_eq!(store.history_len().unwrap(), 100);
+            assert_eq!(store.history_start().unwrap(), StateVersion::new(900));
+            assert_eq!(store.version(), StateVersion::new(1000));
+            assert_eq!(store.compute_root().unwrap(), root);
+            assert_eq!(store.all_entries().unwrap().len(), entries.len());
+            
+            // Diffs within the retained history still combine in order
+            let diff = store.diff(StateVersion::new(950)).unwrap();
+            assert_eq!(diff.added.len(), 50);
+            let last = diff.added.get(49u32.to_be_bytes().as_slice());
+            assert_eq!(last, Some(&999u32.to_be_bytes().to_vec()));
+            assert!(matches!(
+                store.diff(StateVersion::new(10)),
+                Err(RainsonetError::StateNotFound)
+            ));
+        }
+    }
+    
+    #[test]
+    fn test_history_retention_prunes_on_apply() {
+        for backend in compiled_backends() {
+            let tmp = TempDir::new().unwrap();
+            let store = PersistentStateStore::open_with(tmp.path(), backend)
+                .unwrap()
+                .with_history_retention(10);
+            apply_versions(&store, 100);
+            
+            assert_eq!(store.history_len().unwrap(), 10);
+            assert_eq!(store.history_start().unwrap(), StateVersion::new(90));
+            assert_eq!(store.diff(StateVersion::new(90)).unwrap().added.len(), 10);
+            assert_eq!(store.all_entries().unwrap().len(), 50);
+        }
+    }
+    
+    #[test]
+    fn test_apply_diff_spanning_versions() {
+        for backend in compiled_backends() {
+            let tmp = TempDir::new().unwrap();
+            let store = PersistentStateStore::open_with(tmp.path(), backend).unwrap();
+            apply_versions(&store, 2);
+            
+            let source = store.snapshot().unwrap();
+            for i in 2..10u32 {
+                source
+                    .apply_batch(vec![StateChangeOp::Set {
+                        key: i.to_be_bytes().to_vec(),
+                        value: i.to_be_bytes().to_vec(),
+                    }])
+                    .unwrap();
+            }
+            let diff = source.diff(StateVersion::new(2)).unwrap();
+            
+            assert_eq!(store.apply_diff(&diff).unwrap(), StateVersion::new(10));
+            assert_eq!(store.compute_root().unwrap(), source.compute_root().unwrap());
+            
+            // History still combines across the single diff, but not from
+            // versions inside it
+            assert_eq!(store.diff(StateVersion::new(0)).unwrap().added.len(), 10);
+            assert!(matches!(
+                store.diff(StateVersion::new(5)),
+                Err(RainsonetError::StateNotFound)
+            ));
+            assert!(matches!(
+                store.apply_diff(&diff),
+                Err(RainsonetError::StateVersionMismatch { expected: 10, got: 2 })
+            ));
         }
     }
 }
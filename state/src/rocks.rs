@@ -0,0 +1,88 @@
+//! RocksDB key-value backend
+
+use crate::backend::{internal, Column, KvBackend, KvBatch, KvIter, KvOp};
+use rainsonet_core::{RainsonetError, RainsonetResult};
+use rocksdb::{
+    ColumnFamily, ColumnFamilyDescriptor, Direction, IteratorMode, Options, WriteBatch, DB,
+};
+use std::path::Path;
+
+/// RocksDB backend, one column family per column
+pub struct RocksDbBackend {
+    db: DB,
+}
+
+impl RocksDbBackend {
+    pub fn open(path: impl AsRef<Path>) -> RainsonetResult<Self> {
+        let mut options = Options::default();
+        options.create_if_missing(true);
+        options.create_missing_column_families(true);
+        
+        let columns = Column::ALL
+            .iter()
+            .map(|column| ColumnFamilyDescriptor::new(column.name(), Options::default()));
+        let db = DB::open_cf_descriptors(&options, path, columns).map_err(internal)?;
+        
+        Ok(Self { db })
+    }
+    
+    fn cf(&self, column: Column) -> RainsonetResult<&ColumnFamily> {
+        self.db.cf_handle(column.name()).ok_or_else(|| {
+            RainsonetError::Internal(format!("Missing column family {}", column.name()))
+        })
+    }
+}
+
+impl KvBackend for RocksDbBackend {
+    fn get(&self, column: Column, key: &[u8]) -> RainsonetResult<Option<Vec<u8>>> {
+        self.db.get_cf(self.cf(column)?, key).map_err(internal)
+    }
+    
+    fn set(&self, column: Column, key: &[u8], value: &[u8]) -> RainsonetResult<()> {
+        self.db.put_cf(self.cf(column)?, key, value).map_err(internal)
+    }
+    
+    fn delete(&self, column: Column, key: &[u8]) -> RainsonetResult<()> {
+        self.db.delete_cf(self.cf(column)?, key).map_err(internal)
+    }
+    
+    fn write_batch(&self, batch: KvBatch) -> RainsonetResult<()> {
+        let mut write = WriteBatch::default();
+        for op in batch.ops() {
+            let cf = self.cf(op.column())?;
+            match op {
+                KvOp::Set { key, value, .. } => write.put_cf(cf, key, value),
+                KvOp::Delete { key, .. } => write.delete_cf(cf, key),
+            }
+        }
+        self.db.write(write).map_err(internal)
+    }
+    
+    fn scan_prefix(&self, column: Column, prefix: &[u8]) -> KvIter<'_> {
+        let cf = match self.cf(column) {
+            Ok(cf) => cf,
+            Err(e) => return Box::new(std::iter::once(Err(e))),
+        };
+        let prefix = prefix.to_vec();
+        let mode = IteratorMode::From(&prefix, Direction::Forward);
+        let entries = self.db.iterator_cf(cf, mode).map(|result| {
+            result
+                .map(|(key, value)| (key.into_vec(), value.into_vec()))
+                .map_err(internal)
+        });
+        Box::new(entries.take_while(move |result| match result {
+            Ok((key, _)) => key.starts_with(&prefix),
+            Err(_) => true,
+        }))
+    }
+    
+    fn flush(&self) -> RainsonetResult<()> {
+        // Writes go to the write-ahead log first, so syncing it persists them
+        self.db.flush_wal(true).map_err(internal)
+    }
+    
+    fn compact(&self, column: Column) -> RainsonetResult<()> {
+        self.db.compact_range_cf(self.cf(column)?, None::<&[u8]>, None::<&[u8]>);
+        Ok(())
+    }
+}
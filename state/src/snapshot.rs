@@ -1,10 +1,69 @@
 //! State snapshot functionality
 
-use rainsonet_core::{Hash, RainsonetResult, StateRoot, StateVersion};
+use bincode::Options;
+use rainsonet_core::{Hash, RainsonetError, RainsonetResult, StateRoot, StateVersion};
+use rainsonet_crypto::hashing::MerkleBuilder;
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::io::{BufReader, BufWriter, Read, Write};
 
 use crate::memory::MemoryStateStore;
-use crate::store::{compute_state_root, StateEntry};
+use crate::store::{compute_state_root, StateDiff, StateEntry, StateStore};
+
+/// First bytes of a streamed snapshot
+pub const SNAPSHOT_MAGIC: &[u8; 4] = b"RSNP";
+
+/// Streamed snapshot layout written by this build
+pub const SNAPSHOT_FORMAT_VERSION: u8 = 1;
+
+/// Key length marking the end of the records
+const END_OF_RECORDS: u32 = u32::MAX;
+
+/// Longest key or value accepted on import, so a corrupt length cannot
+/// trigger a huge allocation
+const MAX_RECORD_LEN: u32 = 64 * 1024 * 1024;
+
+/// Entries written to the store per batch on import
+const IMPORT_BATCH_SIZE: usize = 4096;
+
+/// Zstd level used by [`StateSnapshot::to_compressed_bytes`] callers that
+/// have no preference
+pub const DEFAULT_COMPRESSION_LEVEL: i32 = 3;
+
+/// Largest decoded snapshot [`StateSnapshot::from_bytes`] accepts, so a
+/// small compressed input cannot expand without bound
+pub const MAX_SNAPSHOT_SIZE: u64 = 1024 * 1024 * 1024;
+
+/// Encoding of a serialized snapshot, stored in its first byte
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum SnapshotCodec {
+    /// Plain bincode
+    Raw = 0,
+    /// Zstd-compressed bincode
+    Zstd = 1,
+}
+
+impl SnapshotCodec {
+    fn from_tag(tag: u8) -> RainsonetResult<Self> {
+        match tag {
+            0 => Ok(Self::Raw),
+            1 => Ok(Self::Zstd),
+            other => Err(RainsonetError::DeserializationError(format!(
+                "Unknown snapshot codec {}",
+                other
+            ))),
+        }
+    }
+}
+
+/// Bincode options matching `bincode::serialize`, with a decode limit
+fn limited_bincode(limit: u64) -> impl Options {
+    bincode::DefaultOptions::new()
+        .with_fixint_encoding()
+        .allow_trailing_bytes()
+        .with_limit(limit)
+}
 
 /// A complete state snapshot
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -57,16 +116,128 @@ impl StateSnapshot {
         MemoryStateStore::with_data(data)
     }
     
-    /// Serialize snapshot to bytes
+    /// Serialize snapshot to uncompressed bytes
     pub fn to_bytes(&self) -> RainsonetResult<Vec<u8>> {
-        bincode::serialize(self)
-            .map_err(|e| rainsonet_core::RainsonetError::Serialization(e.to_string()))
+        let mut bytes = vec![SnapshotCodec::Raw as u8];
+        bincode::serialize_into(&mut bytes, self)
+            .map_err(|e| RainsonetError::SerializationError(e.to_string()))?;
+        Ok(bytes)
     }
     
-    /// Deserialize snapshot from bytes
+    /// Serialize snapshot to zstd-compressed bytes at `level`
+    pub fn to_compressed_bytes(&self, level: i32) -> RainsonetResult<Vec<u8>> {
+        let mut encoder = zstd::Encoder::new(vec![SnapshotCodec::Zstd as u8], level)
+            .map_err(|e| RainsonetError::SerializationError(e.to_string()))?;
+        bincode::serialize_into(&mut encoder, self)
+            .map_err(|e| RainsonetError::SerializationError(e.to_string()))?;
+        encoder
+            .finish()
+            .map_err(|e| RainsonetError::SerializationError(e.to_string()))
+    }
+    
+    /// Deserialize snapshot from bytes, compressed or not, decoding at most
+    /// [`MAX_SNAPSHOT_SIZE`] bytes
     pub fn from_bytes(bytes: &[u8]) -> RainsonetResult<Self> {
-        bincode::deserialize(bytes)
-            .map_err(|e| rainsonet_core::RainsonetError::Serialization(e.to_string()))
+        Self::from_bytes_with_limit(bytes, MAX_SNAPSHOT_SIZE)
+    }
+    
+    /// [`StateSnapshot::from_bytes`] decoding at most `limit` bytes
+    pub fn from_bytes_with_limit(bytes: &[u8], limit: u64) -> RainsonetResult<Self> {
+        let (&tag, body) = bytes
+            .split_first()
+            .ok_or_else(|| RainsonetError::DeserializationError("Empty snapshot".to_string()))?;
+        let decoded = match SnapshotCodec::from_tag(tag)? {
+            // Read through `Read` too, since bincode lifts the limit for slices
+            SnapshotCodec::Raw => limited_bincode(limit).deserialize_from(body),
+            SnapshotCodec::Zstd => {
+                let decoder = zstd::Decoder::new(body)
+                    .map_err(|e| RainsonetError::DeserializationError(e.to_string()))?;
+                limited_bincode(limit).deserialize_from(decoder)
+            }
+        };
+        decoded.map_err(|e| RainsonetError::DeserializationError(e.to_string()))
+    }
+    
+    /// Deserialize snapshot from bytes written by
+    /// [`StateSnapshot::to_compressed_bytes`]
+    pub fn from_compressed_bytes(bytes: &[u8]) -> RainsonetResult<Self> {
+        if bytes.first() != Some(&(SnapshotCodec::Zstd as u8)) {
+            return Err(RainsonetError::DeserializationError(
+                "Snapshot is not zstd-compressed".to_string(),
+            ));
+        }
+        Self::from_bytes(bytes)
+    }
+    
+    /// Keys added, changed and removed going from `base` to this snapshot.
+    ///
+    /// The diff carries this snapshot's root, so a peer holding `base` can
+    /// rebuild and check it with [`StateSnapshot::apply_diff`].
+    pub fn diff_against(&self, base: &StateSnapshot) -> StateDiff {
+        let base_entries = base.entry_map();
+        let entries = self.entry_map();
+        let mut diff = StateDiff::new(base.version, self.version);
+        
+        for (key, value) in &entries {
+            if base_entries.get(key) != Some(value) {
+                diff.add(key.to_vec(), value.to_vec());
+            }
+        }
+        for key in base_entries.keys() {
+            if !entries.contains_key(key) {
+                diff.remove(key.to_vec());
+            }
+        }
+        
+        diff.root = Some(self.root);
+        diff
+    }
+    
+    /// Rebuild the snapshot `diff` leads to from `base`, checking the result
+    /// against the root the diff was made with
+    pub fn apply_diff(base: &StateSnapshot, diff: &StateDiff) -> RainsonetResult<StateSnapshot> {
+        if diff.from_version != base.version {
+            return Err(RainsonetError::StateCorruption(format!(
+                "Diff from version {} does not apply to snapshot at version {}",
+                diff.from_version, base.version
+            )));
+        }
+        
+        let mut entries = base.entry_map();
+        for key in &diff.removed {
+            entries.remove(key.as_slice());
+        }
+        for (key, value) in &diff.added {
+            entries.insert(key.as_slice(), value.as_slice());
+        }
+        
+        let entries = entries
+            .into_iter()
+            .map(|(key, value)| StateEntry {
+                key: key.to_vec(),
+                value: value.to_vec(),
+            })
+            .collect();
+        let snapshot = Self::new(diff.to_version, entries);
+        
+        if let Some(root) = diff.root {
+            if snapshot.root != root {
+                return Err(RainsonetError::StateCorruption(format!(
+                    "Snapshot rebuilt from diff has root {}, expected {}",
+                    snapshot.root, root
+                )));
+            }
+        }
+        
+        Ok(snapshot)
+    }
+    
+    /// Entries keyed and ordered by key
+    fn entry_map(&self) -> BTreeMap<&[u8], &[u8]> {
+        self.entries
+            .iter()
+            .map(|e| (e.key.as_slice(), e.value.as_slice()))
+            .collect()
     }
     
     /// Get the number of entries
@@ -80,6 +251,14 @@ impl StateSnapshot {
     }
 }
 
+/// Version, root and size of a streamed snapshot
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SnapshotInfo {
+    pub version: StateVersion,
+    pub root: StateRoot,
+    pub entries: u64,
+}
+
 /// Snapshot manager for incremental snapshots
 pub struct SnapshotManager {
     snapshots: Vec<StateSnapshot>,
@@ -131,6 +310,175 @@ impl SnapshotManager {
     pub fn clear(&mut self) {
         self.snapshots.clear();
     }
+    
+    /// Stream `store`, which is at `version`, to `w` without holding its
+    /// values.
+    ///
+    /// The stream is [`SNAPSHOT_MAGIC`], [`SNAPSHOT_FORMAT_VERSION`] and the
+    /// little-endian state version, then one `key length, key, value length,
+    /// value` record per entry in key order with `u32` little-endian
+    /// lengths. A `u32::MAX` key length ends the records, followed by the
+    /// entry count and the state root folded over the records.
+    pub fn export_to_writer<W: Write>(
+        store: &dyn StateStore,
+        version: StateVersion,
+        w: W,
+    ) -> RainsonetResult<SnapshotInfo> {
+        let mut w = BufWriter::new(w);
+        w.write_all(SNAPSHOT_MAGIC)?;
+        w.write_all(&[SNAPSHOT_FORMAT_VERSION])?;
+        w.write_all(&version.0.to_le_bytes())?;
+        
+        let mut builder = MerkleBuilder::new();
+        let mut previous: Option<Vec<u8>> = None;
+        let mut entries = 0u64;
+        for entry in store.ordered_entries() {
+            let entry = entry?;
+            if previous.as_ref().is_some_and(|prev| *prev >= entry.key) {
+                return Err(RainsonetError::StateCorruption(
+                    "Snapshot entries are not in key order".into(),
+                ));
+            }
+            write_record(&mut w, &entry.key)?;
+            write_record(&mut w, &entry.value)?;
+            builder.push(entry.hash());
+            entries += 1;
+            previous = Some(entry.key);
+        }
+        
+        let root = builder.finish();
+        w.write_all(&END_OF_RECORDS.to_le_bytes())?;
+        w.write_all(&entries.to_le_bytes())?;
+        w.write_all(root.as_bytes())?;
+        w.flush()?;
+        
+        Ok(SnapshotInfo {
+            version,
+            root,
+            entries,
+        })
+    }
+    
+    /// Load a snapshot streamed by [`Self::export_to_writer`] into the empty
+    /// `store`, [`IMPORT_BATCH_SIZE`] entries at a time. The root is folded
+    /// as records arrive and the store only moves to the snapshot's version
+    /// if it matches the embedded one; on error the store holds part of the
+    /// snapshot and should be discarded.
+    pub fn import_from_reader<R: Read>(
+        r: R,
+        store: &dyn StateStore,
+    ) -> RainsonetResult<SnapshotInfo> {
+        let mut r = BufReader::new(r);
+        let malformed = |reason: &str| RainsonetError::DeserializationError(reason.to_string());
+        
+        if store.ordered_entries().next().is_some() {
+            return Err(RainsonetError::StateCorruption(
+                "Snapshots are only imported into an empty store".into(),
+            ));
+        }
+        
+        let mut magic = [0u8; 4];
+        r.read_exact(&mut magic)?;
+        if &magic != SNAPSHOT_MAGIC {
+            return Err(malformed("Not a RAINSONET snapshot"));
+        }
+        let mut format_version = [0u8; 1];
+        r.read_exact(&mut format_version)?;
+        if format_version[0] != SNAPSHOT_FORMAT_VERSION {
+            return Err(malformed(&format!(
+                "Unsupported snapshot format version {}",
+                format_version[0]
+            )));
+        }
+        let version = StateVersion::new(read_u64(&mut r)?);
+        
+        let mut builder = MerkleBuilder::new();
+        let mut previous: Option<Vec<u8>> = None;
+        let mut batch = Vec::with_capacity(IMPORT_BATCH_SIZE);
+        let mut entries = 0u64;
+        loop {
+            let key = match read_record(&mut r)? {
+                Some(key) => key,
+                None => break,
+            };
+            let value = read_record(&mut r)?
+                .ok_or_else(|| malformed("Snapshot record has no value"))?;
+            if previous.as_ref().is_some_and(|prev| *prev >= key) {
+                return Err(malformed("Snapshot entries are not in key order"));
+            }
+            
+            let entry = StateEntry { key, value };
+            builder.push(entry.hash());
+            entries += 1;
+            previous = Some(entry.key.clone());
+            batch.push(entry);
+            if batch.len() == IMPORT_BATCH_SIZE {
+                store.load_entries(std::mem::take(&mut batch), StateVersion::new(0))?;
+            }
+        }
+        store.load_entries(batch, StateVersion::new(0))?;
+        
+        let expected_entries = read_u64(&mut r)?;
+        let mut root = [0u8; 32];
+        r.read_exact(&mut root)?;
+        let root = Hash::from_bytes(root);
+        
+        let computed = builder.finish();
+        if entries != expected_entries || computed != root {
+            return Err(RainsonetError::StateCorruption(format!(
+                "Snapshot of {} entries has root {}, but records {} entries with root {}",
+                entries, computed, expected_entries, root
+            )));
+        }
+        
+        store.load_entries(Vec::new(), version)?;
+        Ok(SnapshotInfo {
+            version,
+            root,
+            entries,
+        })
+    }
+}
+
+/// Write a length-prefixed key or value
+fn write_record<W: Write>(w: &mut W, bytes: &[u8]) -> RainsonetResult<()> {
+    let len = u32::try_from(bytes.len())
+        .ok()
+        .filter(|len| *len <= MAX_RECORD_LEN)
+        .ok_or_else(|| {
+            RainsonetError::SerializationError(format!(
+                "Snapshot record of {} bytes is too large",
+                bytes.len()
+            ))
+        })?;
+    w.write_all(&len.to_le_bytes())?;
+    w.write_all(bytes)?;
+    Ok(())
+}
+
+/// Read a length-prefixed key or value, `None` at the end of the records
+fn read_record<R: Read>(r: &mut R) -> RainsonetResult<Option<Vec<u8>>> {
+    let mut len = [0u8; 4];
+    r.read_exact(&mut len)?;
+    let len = u32::from_le_bytes(len);
+    if len == END_OF_RECORDS {
+        return Ok(None);
+    }
+    if len > MAX_RECORD_LEN {
+        return Err(RainsonetError::DeserializationError(format!(
+            "Snapshot record of {} bytes is too large",
+            len
+        )));
+    }
+    let mut bytes = vec![0u8; len as usize];
+    r.read_exact(&mut bytes)?;
+    Ok(Some(bytes))
+}
+
+fn read_u64<R: Read>(r: &mut R) -> RainsonetResult<u64> {
+    let mut bytes = [0u8; 8];
+    r.read_exact(&mut bytes)?;
+    Ok(u64::from_le_bytes(bytes))
 }
 
 impl Default for SnapshotManager {
@@ -142,6 +490,8 @@ impl Default for SnapshotManager {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::persistent::PersistentStateStore;
+    use crate::store::AccountState;
     
     #[test]
     fn test_snapshot_roundtrip() {
@@ -167,6 +517,176 @@ mod tests {
         assert!(restored.verify());
     }
     
+    #[test]
+    fn test_compressed_snapshot_roundtrip() {
+        let entries = (0..10_000u32)
+            .map(|i| StateEntry {
+                key: format!("account:{:08}", i).into_bytes(),
+                value: AccountState::new(u128::from(i) * 1_000, u64::from(i % 7)).to_bytes(),
+            })
+            .collect();
+        let snapshot = StateSnapshot::new(StateVersion::new(9), entries);
+        
+        let raw = snapshot.to_bytes().unwrap();
+        let compressed = snapshot.to_compressed_bytes(DEFAULT_COMPRESSION_LEVEL).unwrap();
+        assert!(compressed.len() * 3 < raw.len(), "{} vs {}", compressed.len(), raw.len());
+        
+        for bytes in [&raw, &compressed] {
+            let restored = StateSnapshot::from_bytes(bytes).unwrap();
+            assert_eq!(restored.version, snapshot.version);
+            assert_eq!(restored.root, snapshot.root);
+            assert_eq!(restored.entries.len(), 10_000);
+            assert!(restored.verify());
+        }
+        assert!(StateSnapshot::from_compressed_bytes(&compressed).is_ok());
+        assert!(StateSnapshot::from_compressed_bytes(&raw).is_err());
+        
+        let mut unknown = raw.clone();
+        unknown[0] = 0xff;
+        assert!(StateSnapshot::from_bytes(&unknown).is_err());
+        assert!(StateSnapshot::from_bytes(&raw[..raw.len() - 1]).is_err());
+        assert!(StateSnapshot::from_bytes(&[]).is_err());
+    }
+    
+    #[test]
+    fn test_decode_limit_bounds_decompression() {
+        let entries = (0..10_000u32)
+            .map(|i| StateEntry {
+                key: format!("account:{:08}", i).into_bytes(),
+                value: vec![0u8; 256],
+            })
+            .collect();
+        let snapshot = StateSnapshot::new(StateVersion::new(3), entries);
+        let raw = snapshot.to_bytes().unwrap();
+        let compressed = snapshot.to_compressed_bytes(DEFAULT_COMPRESSION_LEVEL).unwrap();
+        let limit = raw.len() as u64 / 2;
+        assert!((compressed.len() as u64) < limit);
+        
+        for bytes in [&raw, &compressed] {
+            assert!(StateSnapshot::from_bytes_with_limit(bytes, limit).is_err());
+            assert!(StateSnapshot::from_bytes_with_limit(bytes, raw.len() as u64).is_ok());
+        }
+    }
+    
+    fn entry(key: &str, value: &str) -> StateEntry {
+        StateEntry {
+            key: key.as_bytes().to_vec(),
+            value: value.as_bytes().to_vec(),
+        }
+    }
+    
+    #[test]
+    fn test_apply_diff_reconstructs_target() {
+        let base = StateSnapshot::new(
+            StateVersion::new(1),
+            vec![entry("a", "1"), entry("b", "2"), entry("c", "3")],
+        );
+        let target = StateSnapshot::new(
+            StateVersion::new(5),
+            vec![entry("d", "4"), entry("a", "1"), entry("b", "20")],
+        );
+        
+        let diff = target.diff_against(&base);
+        assert_eq!(diff.added.len(), 2);
+        assert_eq!(diff.removed, vec![b"c".to_vec()]);
+        
+        let rebuilt = StateSnapshot::apply_diff(&base, &diff).unwrap();
+        assert_eq!(rebuilt.version, target.version);
+        assert_eq!(rebuilt.root, target.root);
+        assert!(rebuilt.verify());
+        
+        let mut expected = target.entries.clone();
+        expected.sort_by(|a, b| a.key.cmp(&b.key));
+        assert_eq!(rebuilt.entries, expected);
+        
+        // Against itself the diff is empty
+        assert!(base.diff_against(&base).is_empty());
+    }
+    
+    #[test]
+    fn test_apply_diff_rejects_mismatch() {
+        let base = StateSnapshot::new(StateVersion::new(1), vec![entry("a", "1")]);
+        let target = StateSnapshot::new(StateVersion::new(2), vec![entry("a", "2")]);
+        
+        let mut diff = target.diff_against(&base);
+        diff.added.insert(b"x".to_vec(), b"9".to_vec());
+        assert!(StateSnapshot::apply_diff(&base, &diff).is_err());
+        
+        let other_base = StateSnapshot::new(StateVersion::new(0), vec![entry("a", "1")]);
+        assert!(StateSnapshot::apply_diff(&other_base, &target.diff_against(&base)).is_err());
+    }
+    
+    #[test]
+    fn test_streamed_snapshot_round_trip() {
+        let memory = MemoryStateStore::new();
+        let count = IMPORT_BATCH_SIZE as u32 * 2 + 500;
+        let changes = (0..count)
+            .rev()
+            .map(|i| crate::StateChangeOp::Set {
+                key: i.to_be_bytes().to_vec(),
+                value: vec![i as u8; (i % 7) as usize],
+            })
+            .collect();
+        let version = memory.apply_batch(changes).unwrap();
+        
+        let mut out = Vec::new();
+        let info = SnapshotManager::export_to_writer(&memory, version, &mut out).unwrap();
+        assert_eq!(info.entries, u64::from(count));
+        assert_eq!(info.root, memory.compute_root().unwrap());
+        assert_eq!(&out[..4], SNAPSHOT_MAGIC);
+        
+        let imported = MemoryStateStore::new();
+        assert_eq!(SnapshotManager::import_from_reader(out.as_slice(), &imported).unwrap(), info);
+        assert_eq!(imported.version(), version);
+        assert_eq!(imported.compute_root().unwrap(), info.root);
+        assert_eq!(imported.get(&8u32.to_be_bytes()).unwrap(), Some(vec![8]));
+        
+        // A persistent store loads in batches and streams the same bytes back
+        let tmp = tempfile::TempDir::new().unwrap();
+        {
+            let persistent = PersistentStateStore::open(tmp.path()).unwrap();
+            SnapshotManager::import_from_reader(out.as_slice(), &persistent).unwrap();
+        }
+        let persistent = PersistentStateStore::open(tmp.path()).unwrap();
+        assert_eq!(persistent.version(), version);
+        let mut from_disk = Vec::new();
+        SnapshotManager::export_to_writer(&persistent, version, &mut from_disk).unwrap();
+        assert_eq!(from_disk, out);
+        
+        // Loading over existing state would mix two snapshots
+        let occupied = SnapshotManager::import_from_reader(out.as_slice(), &persistent);
+        assert!(matches!(occupied, Err(RainsonetError::StateCorruption(_))));
+    }
+    
+    #[test]
+    fn test_streamed_snapshot_rejects_corruption() {
+        let memory = MemoryStateStore::with_data(vec![
+            (b"a".to_vec(), b"1".to_vec()),
+            (b"b".to_vec(), b"2".to_vec()),
+        ]);
+        let mut out = Vec::new();
+        SnapshotManager::export_to_writer(&memory, memory.version(), &mut out).unwrap();
+        let import = |bytes: &[u8]| {
+            SnapshotManager::import_from_reader(bytes, &MemoryStateStore::new())
+        };
+        
+        // A flipped value no longer matches the embedded root
+        let mut tampered = out.clone();
+        let value_at = 4 + 1 + 8 + 4 + 1 + 4;
+        tampered[value_at] ^= 1;
+        assert!(matches!(import(&tampered), Err(RainsonetError::StateCorruption(_))));
+        
+        let mut bad_magic = out.clone();
+        bad_magic[0] = b'X';
+        assert!(import(&bad_magic).is_err());
+        
+        let mut newer = out.clone();
+        newer[4] = SNAPSHOT_FORMAT_VERSION + 1;
+        assert!(import(&newer).is_err());
+        
+        assert!(import(&out[..out.len() - 1]).is_err());
+    }
+    
     #[test]
     fn test_snapshot_manager() {
         let mut manager = SnapshotManager::new(3);
@@ -1,8 +1,14 @@
 //! Core state store traits and types
 
-use rainsonet_core::{Hash, RainsonetError, RainsonetResult, StateRoot, StateVersion};
-use rainsonet_crypto::hashing::{hash, merkle_root};
+use async_trait::async_trait;
+use rainsonet_core::{
+    Hash, RainsonetError, RainsonetResult, StateChange, StateMutator, StateRoot, StateVersion,
+};
+use rainsonet_crypto::hashing::{
+    default_hash_algorithm, hash_with, merkle_proof, MerkleBuilder, MerkleProof,
+};
 use serde::{Deserialize, Serialize};
+use std::borrow::Borrow;
 use std::collections::BTreeMap;
 
 /// Account state for RELYO
@@ -27,7 +33,7 @@ impl AccountState {
 }
 
 /// State entry for merkle tree computation
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct StateEntry {
     pub key: Vec<u8>,
     pub value: Vec<u8>,
@@ -38,22 +44,69 @@ impl StateEntry {
         let mut data = Vec::new();
         data.extend_from_slice(&self.key);
         data.extend_from_slice(&self.value);
-        hash(&data)
+        hash_with(default_hash_algorithm(), &data)
     }
 }
 
-/// Compute state root from entries
-pub fn compute_state_root(entries: &[StateEntry]) -> StateRoot {
-    if entries.is_empty() {
-        return Hash::ZERO;
-    }
+/// Compute state root from entries in any order.
+///
+/// Each entry is hashed as it is consumed, so only keys and leaf hashes are
+/// held for sorting, never the values. Use [`compute_sorted_state_root`]
+/// when the entries already arrive in key order.
+pub fn compute_state_root<I>(entries: I) -> StateRoot
+where
+    I: IntoIterator,
+    I::Item: Borrow<StateEntry>,
+{
+    let mut leaves: Vec<(Vec<u8>, Hash)> = entries
+        .into_iter()
+        .map(|e| {
+            let e = e.borrow();
+            (e.key.clone(), e.hash())
+        })
+        .collect();
+    leaves.sort_by(|a, b| a.0.cmp(&b.0));
     
-    let mut sorted: Vec<_> = entries.iter().collect();
-    sorted.sort_by(|a, b| a.key.cmp(&b.key));
-    
-    let leaves: Vec<Hash> = sorted.iter().map(|e| e.hash()).collect();
+    let mut builder = MerkleBuilder::new();
+    for (_, leaf) in leaves {
+        builder.push(leaf);
+    }
+    builder.finish()
+}
+
+/// Compute state root from entries already sorted by key, streaming them
+/// without buffering
+pub fn compute_sorted_state_root<I>(entries: I) -> StateRoot
+where
+    I: IntoIterator,
+    I::Item: Borrow<StateEntry>,
+{
+    let mut builder = MerkleBuilder::new();
+    for entry in entries {
+        builder.push(entry.borrow().hash());
+    }
+    builder.finish()
+}
+
+/// Inclusion proof for the entry under `key` against the root that
+/// [`compute_state_root`] computes over the same entries
+pub fn compute_state_proof<I>(entries: I, key: &[u8]) -> Option<MerkleProof>
+where
+    I: IntoIterator,
+    I::Item: Borrow<StateEntry>,
+{
+    let mut leaves: Vec<(Vec<u8>, Hash)> = entries
+        .into_iter()
+        .map(|e| {
+            let e = e.borrow();
+            (e.key.clone(), e.hash())
+        })
+        .collect();
+    leaves.sort_by(|a, b| a.0.cmp(&b.0));
     
-    merkle_root(&leaves)
+    let index = leaves.iter().position(|(k, _)| k.as_slice() == key)?;
+    let hashes: Vec<Hash> = leaves.into_iter().map(|(_, leaf)| leaf).collect();
+    merkle_proof(&hashes, index)
 }
 
 /// Batch of state changes with metadata
@@ -72,6 +125,66 @@ pub enum StateChangeOp {
     Delete { key: Vec<u8> },
 }
 
+impl From<StateChange> for StateChangeOp {
+    fn from(change: StateChange) -> Self {
+        match change {
+            StateChange::Set { key, value } => StateChangeOp::Set { key, value },
+            StateChange::Delete { key } => StateChangeOp::Delete { key },
+        }
+    }
+}
+
+/// State store used by modules through the core state traits.
+///
+/// Implemented by both [`MemoryStateStore`](crate::MemoryStateStore) and
+/// [`PersistentStateStore`](crate::PersistentStateStore), so code generic
+/// over `S: StateStore` runs against either.
+#[async_trait]
+pub trait StateStore: StateMutator {
+    /// All entries whose key starts with `prefix`
+    async fn scan_prefix(&self, prefix: &[u8]) -> RainsonetResult<Vec<StateEntry>>;
+    
+    /// Compute the Merkle root over all entries
+    async fn compute_root(&self) -> RainsonetResult<StateRoot>;
+    
+    /// Every entry in the store
+    async fn all_entries(&self) -> RainsonetResult<Vec<StateEntry>>;
+    
+    /// In-memory copy of the current data and version, without history
+    async fn snapshot(&self) -> RainsonetResult<Box<dyn StateStore>>;
+    
+    /// Combined changes from `from_version` to the current version
+    async fn diff(&self, from_version: StateVersion) -> RainsonetResult<StateDiff>;
+    
+    /// Apply a diff produced by [`StateStore::diff`] on another store, all
+    /// or nothing, moving to its `to_version`. Rejected unless this store is
+    /// at the diff's `from_version` and, if the diff records a root, the
+    /// changes produce it.
+    async fn apply_diff(&self, diff: &StateDiff) -> RainsonetResult<StateVersion>;
+    
+    /// Every entry in key order, read lazily on the calling thread, for
+    /// streaming a snapshot
+    fn ordered_entries(&self) -> Box<dyn Iterator<Item = RainsonetResult<StateEntry>> + '_>;
+    
+    /// Write `entries` and move to `version` on the calling thread, without
+    /// recording history or checking a root. Used to load a snapshot whose
+    /// root the caller verifies.
+    fn load_entries(&self, entries: Vec<StateEntry>, version: StateVersion) -> RainsonetResult<()>;
+    
+    /// Get an account, if it exists
+    async fn get_account(&self, address: &[u8]) -> RainsonetResult<Option<AccountState>> {
+        match self.get(&account_key(address)).await? {
+            Some(bytes) => Ok(Some(AccountState::from_bytes(&bytes)?)),
+            None => Ok(None),
+        }
+    }
+    
+    /// Write an account
+    async fn set_account(&self, address: &[u8], state: &AccountState) -> RainsonetResult<()> {
+        self.set(&account_key(address), &state.to_bytes()).await
+    }
+}
+
 /// State diff for synchronization
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct StateDiff {
@@ -79,6 +192,9 @@ pub struct StateDiff {
     pub to_version: StateVersion,
     pub added: BTreeMap<Vec<u8>, Vec<u8>>,
     pub removed: Vec<Vec<u8>>,
+    /// State root after applying this diff, when the store recorded it
+    #[serde(default)]
+    pub root: Option<StateRoot>,
 }
 
 impl StateDiff {
@@ -88,20 +204,94 @@ impl StateDiff {
             to_version,
             added: BTreeMap::new(),
             removed: Vec::new(),
+            root: None,
         }
     }
     
+    // A key is either added or removed, never both, so a diff can be
+    // replayed without knowing the order its operations happened in.
+    
     pub fn add(&mut self, key: Vec<u8>, value: Vec<u8>) {
+        self.removed.retain(|k| k != &key);
         self.added.insert(key, value);
     }
     
     pub fn remove(&mut self, key: Vec<u8>) {
-        self.removed.push(key);
+        self.added.remove(&key);
+        if !self.removed.contains(&key) {
+            self.removed.push(key);
+        }
+    }
+    
+    /// Convert to change operations, removals first
+    pub fn to_changes(&self) -> Vec<StateChangeOp> {
+        self.removed
+            .iter()
+            .map(|key| StateChangeOp::Delete { key: key.clone() })
+            .chain(self.added.iter().map(|(key, value)| StateChangeOp::Set {
+                key: key.clone(),
+                value: value.clone(),
+            }))
+            .collect()
     }
     
     pub fn is_empty(&self) -> bool {
         self.added.is_empty() && self.removed.is_empty()
     }
+    
+    /// Check that applying this diff over `entries`, the store's current
+    /// entries in any order, yields the recorded root. Passes without
+    /// reading `entries` when no root was recorded.
+    pub fn check_root_after<I>(&self, entries: I) -> RainsonetResult<()>
+    where
+        I: IntoIterator<Item = RainsonetResult<StateEntry>>,
+    {
+        let expected = match self.root {
+            Some(root) => root,
+            None => return Ok(()),
+        };
+        
+        let removed: std::collections::BTreeSet<&[u8]> =
+            self.removed.iter().map(Vec::as_slice).collect();
+        let mut error = None;
+        let kept = entries
+            .into_iter()
+            .filter_map(|entry| entry.map_err(|e| error.get_or_insert(e)).ok())
+            .filter(|e| !self.added.contains_key(&e.key) && !removed.contains(e.key.as_slice()));
+        let added = self.added.iter().map(|(key, value)| StateEntry {
+            key: key.clone(),
+            value: value.clone(),
+        });
+        let root = compute_state_root(kept.chain(added));
+        if let Some(e) = error {
+            return Err(e);
+        }
+        
+        if root != expected {
+            return Err(RainsonetError::StateCorruption(format!(
+                "Diff to version {} yields root {}, but records {}",
+                self.to_version, root, expected
+            )));
+        }
+        Ok(())
+    }
+    
+    /// Check this diff can be applied to a store at `version`
+    pub fn check_applies_to(&self, version: StateVersion) -> RainsonetResult<()> {
+        if self.from_version != version {
+            return Err(RainsonetError::StateVersionMismatch {
+                expected: version.0,
+                got: self.from_version.0,
+            });
+        }
+        if self.to_version <= self.from_version {
+            return Err(RainsonetError::StateCorruption(format!(
+                "Diff to version {} does not advance from {}",
+                self.to_version, self.from_version
+            )));
+        }
+        Ok(())
+    }
 }
 
 /// Key prefix for accounts
@@ -122,3 +312,144 @@ pub fn parse_account_key(key: &[u8]) -> Option<Vec<u8>> {
         None
     }
 }
+
+/// Key prefix for validator records
+pub const VALIDATOR_PREFIX: &[u8] = b"validator:";
+
+/// Build validator record key
+pub fn validator_key(node_id: &[u8]) -> Vec<u8> {
+    let mut key = VALIDATOR_PREFIX.to_vec();
+    key.extend_from_slice(node_id);
+    key
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_diff_keeps_last_operation_per_key() {
+        let mut diff = StateDiff::new(StateVersion::new(0), StateVersion::new(1));
+        
+        diff.remove(b"a".to_vec());
+        diff.add(b"a".to_vec(), b"1".to_vec());
+        diff.add(b"b".to_vec(), b"2".to_vec());
+        diff.remove(b"b".to_vec());
+        diff.remove(b"b".to_vec());
+        
+        assert_eq!(diff.added.get(b"a".as_slice()), Some(&b"1".to_vec()));
+        assert!(!diff.removed.contains(&b"a".to_vec()));
+        assert!(!diff.added.contains_key(b"b".as_slice()));
+        assert_eq!(diff.removed, vec![b"b".to_vec()]);
+        assert_eq!(diff.to_changes().len(), 2);
+    }
+    
+    /// Drive a store purely through a `&dyn StateStore`
+    async fn exercise_dyn(store: &dyn StateStore) {
+        let start = store.version().await;
+        store.set_account(b"alice", &AccountState::new(10, 1)).await.unwrap();
+        let changes = vec![
+            StateChange::Set { key: b"k1".to_vec(), value: b"v1".to_vec() },
+            StateChange::Set { key: b"k2".to_vec(), value: b"v2".to_vec() },
+        ];
+        let version = store.apply_batch(changes).await.unwrap();
+        store.apply_batch(vec![StateChange::Delete { key: b"k2".to_vec() }]).await.unwrap();
+        
+        assert_eq!(store.get_account(b"alice").await.unwrap().unwrap().balance, 10);
+        assert!(store.exists(b"k1").await.unwrap());
+        assert!(!store.exists(b"k2").await.unwrap());
+        assert_eq!(store.all_entries().await.unwrap().len(), 2);
+        assert_eq!(store.scan_prefix(b"k").await.unwrap().len(), 1);
+        assert_eq!(store.root().await.unwrap(), store.compute_root().await.unwrap());
+        
+        let diff = store.diff(version).await.unwrap();
+        assert_eq!(diff.removed, vec![b"k2".to_vec()]);
+        assert!(store.diff(start).await.unwrap().added.contains_key(b"k1".as_slice()));
+        
+        let snapshot = store.snapshot().await.unwrap();
+        assert_eq!(snapshot.version().await, store.version().await);
+        assert_eq!(snapshot.root().await.unwrap(), store.root().await.unwrap());
+        snapshot.delete(b"k1").await.unwrap();
+        assert!(store.exists(b"k1").await.unwrap());
+    }
+    
+    #[tokio::test]
+    async fn test_stores_as_trait_objects() {
+        use crate::{MemoryStateStore, PersistentStateStore};
+        
+        exercise_dyn(&MemoryStateStore::new()).await;
+        
+        let tmp = tempfile::TempDir::new().unwrap();
+        let persistent: Box<dyn StateStore> =
+            Box::new(PersistentStateStore::open(tmp.path()).unwrap());
+        exercise_dyn(persistent.as_ref()).await;
+    }
+    
+    mod roots {
+        use super::*;
+        use crate::{MemoryStateStore, PersistentStateStore};
+        use proptest::prelude::*;
+        
+        /// Operation on a small key space, so sets overwrite, deletes hit
+        /// existing keys and the tree often empties or holds one entry.
+        /// Keys of different lengths share prefixes to exercise ordering.
+        fn change() -> impl Strategy<Value = StateChangeOp> {
+            let key = prop::collection::vec(0..3u8, 0..3);
+            prop_oneof![
+                (key.clone(), prop::collection::vec(any::<u8>(), 0..4))
+                    .prop_map(|(key, value)| StateChangeOp::Set { key, value }),
+                key.prop_map(|key| StateChangeOp::Delete { key }),
+            ]
+        }
+        
+        proptest! {
+            #![proptest_config(ProptestConfig::with_cases(64))]
+            
+            /// Every way a store arrives at its root agrees with computing it
+            /// from scratch after each batch: directly, as recorded in the
+            /// history, replayed diff by diff onto a copy (as the finalized
+            /// state is advanced) and streamed from disk. Failures shrink to a
+            /// minimal batch sequence.
+            #[test]
+            fn test_roots_match_from_scratch(
+                batches in prop::collection::vec(prop::collection::vec(change(), 0..6), 1..12)
+            ) {
+                let store = MemoryStateStore::new();
+                let replica = MemoryStateStore::new();
+                let tmp = tempfile::TempDir::new().unwrap();
+                let persistent = PersistentStateStore::open(tmp.path()).unwrap();
+                let mut model = BTreeMap::new();
+                
+                for changes in batches {
+                    for change in &changes {
+                        match change.clone() {
+                            StateChangeOp::Set { key, value } => model.insert(key, value),
+                            StateChangeOp::Delete { key } => model.remove(&key),
+                        };
+                    }
+                    let version = store.apply_batch(changes.clone()).unwrap();
+                    persistent.apply_batch(changes).unwrap();
+                    for diff in store.history_range(replica.version(), version).unwrap() {
+                        replica.apply_batch(diff.to_changes()).unwrap();
+                    }
+                    
+                    let entries: Vec<StateEntry> = model
+                        .iter()
+                        .map(|(key, value)| StateEntry { key: key.clone(), value: value.clone() })
+                        .collect();
+                    let expected = compute_state_root(entries.iter().rev());
+                    let recorded = store
+                        .history_range(StateVersion::new(version.0 - 1), version)
+                        .unwrap()[0]
+                        .root;
+                    
+                    prop_assert_eq!(compute_sorted_state_root(&entries), expected);
+                    prop_assert_eq!(store.compute_root().unwrap(), expected);
+                    prop_assert_eq!(recorded, Some(expected));
+                    prop_assert_eq!(replica.compute_root().unwrap(), expected);
+                    prop_assert_eq!(persistent.compute_root().unwrap(), expected);
+                }
+            }
+        }
+    }
+}